@@ -1,9 +1,40 @@
 use anyhow::Context;
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use futures::StreamExt;
-use mp_client::{Client, ClientError, StdioAuthMode, StdioClient};
-use mp_kernel::{ErrorCode, ProjectListEntry, WorkspaceListEntry};
-use mp_protocol::{CommandRejection, ErrorResponse, SubmitCommandResponse};
+use mp_client::{ArtifactRange, Client, ClientError, StdioAuthMode, StdioClient};
+use mp_daemon::{
+    cases_from_commands, cases_from_events, load_hook_chain, run_hook_tests, HookTestOptions,
+};
+use mp_kernel::{
+    Actor, Approver, ApproverKind, ArtifactPutPayload, Backoff, BlueprintEntry, BlueprintRef,
+    BlueprintSyncPayload, BoardCommentAddPayload, BoardGroupCreatePayload, BoardNodeMovePayload,
+    BoardPosition, BoardSnapshot, CapsuleEntry, CapsuleKind, CapsuleWritePayload, CatalogKind,
+    ErrorCode, FailureClass, ForkMode, GateDecisionPayload, GateDefinePayload, GateEntry, GateKind,
+    GateScope, JitterMode, LeaseEntry, LeaseHolder, LeaseMintPayload, LeaseRevokePayload,
+    LeaseScope, LeaseStatus, MessageScope, MessageSendPayload, PipelineBindPayload,
+    PipelineStageView, PipelineTemplateDefinePayload, PipelineTemplateEntry, PolicyEffect,
+    PolicyExplainRequest, PolicyExplanation, ProcessExitedPayload, ProjectLifecyclePayload,
+    ProjectListEntry, ProjectRenamePayload, RetryPolicy, SecretContext, SecretDeletePayload,
+    SecretEntry, SecretExecPayload, SecretInjectVia, SecretInjection, SecretOwner,
+    SecretRotatePayload, SecretScope, SecretSetPayload, SessionForkPayload, SessionListEntry,
+    SessionSpawnPayload, SideEffect, SkillApprovePayload, SkillEntry, SkillSignatureStatus,
+    SkillSyncPayload, SkillTrust, SkillVerifyReport, StageDefinition, StageTransitionPayload,
+    Subject, TaskCreatePayload, TaskListEntry, TaskState, TaskTransitionPayload, ToolCallEntry,
+    ToolCallStatus, ToolCompletePayload, ToolError, ToolRequestPayload, ToolSearchResponse,
+    VaultInitPayload, VaultLockPayload, VaultStatus, VaultUnlockPayload, WorkspaceLifecyclePayload,
+    WorkspaceListEntry, WorkspaceRenamePayload, WorktreeEntry, WorktreeRegisterPayload,
+    WorktreeSessionPayload, COMMAND_GATE_APPROVE, COMMAND_GATE_REJECT, COMMAND_PROJECT_ARCHIVE,
+    COMMAND_PROJECT_RESTORE, COMMAND_TASK_AWAIT_INPUT, COMMAND_TASK_CANCEL, COMMAND_TASK_FAIL,
+    COMMAND_TASK_PAUSE, COMMAND_TASK_RESUME, COMMAND_TASK_START, COMMAND_TASK_SUCCEED,
+    COMMAND_WORKSPACE_ARCHIVE, COMMAND_WORKSPACE_RESTORE, COMMAND_WORKTREE_ATTACH,
+    COMMAND_WORKTREE_DETACH, COMMAND_WORKTREE_LOCK_ACQUIRE, COMMAND_WORKTREE_LOCK_RELEASE,
+    EVENT_PROCESS_EXITED,
+};
+use mp_protocol::{
+    CommandEnvelope, CommandRejection, ErrorResponse, EventEnvelope, EventFilter,
+    SubmitCommandResponse,
+};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 use tokio::time::{sleep, Duration};
@@ -29,10 +60,78 @@ enum Commands {
         #[command(subcommand)]
         command: ProjectCommands,
     },
+    Session {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+    Task {
+        #[command(subcommand)]
+        command: TaskCommands,
+    },
+    Pipeline {
+        #[command(subcommand)]
+        command: PipelineCommands,
+    },
+    Gate {
+        #[command(subcommand)]
+        command: GateCommands,
+    },
+    Worktree {
+        #[command(subcommand)]
+        command: WorktreeCommands,
+    },
+    Board {
+        #[command(subcommand)]
+        command: BoardCommands,
+    },
+    Capsule {
+        #[command(subcommand)]
+        command: CapsuleCommands,
+    },
+    Blueprint {
+        #[command(subcommand)]
+        command: BlueprintCommands,
+    },
+    Skill {
+        #[command(subcommand)]
+        command: SkillCommands,
+    },
+    Policy {
+        #[command(subcommand)]
+        command: PolicyCommands,
+    },
+    Lease {
+        #[command(subcommand)]
+        command: LeaseCommands,
+    },
+    Vault {
+        #[command(subcommand)]
+        command: VaultCommands,
+    },
+    Secret {
+        #[command(subcommand)]
+        command: SecretCommands,
+    },
+    Message {
+        #[command(subcommand)]
+        command: MessageCommands,
+    },
+    Artifact {
+        #[command(subcommand)]
+        command: ArtifactCommands,
+    },
+    Tool {
+        #[command(subcommand)]
+        command: ToolCommands,
+    },
     Events {
         #[command(subcommand)]
         command: EventCommands,
     },
+    Hooks {
+        #[command(subcommand)]
+        command: HookCommands,
+    },
 }
 
 #[derive(Subcommand)]
@@ -51,12 +150,32 @@ enum WorkspaceCommands {
         #[arg(long)]
         name: Option<String>,
     },
+    Rename {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        name: String,
+    },
+    /// Hides the workspace from lists; its history is kept.
+    Archive(WorkspaceLifecycleArgs),
+    Restore(WorkspaceLifecycleArgs),
     List {
+        /// Include archived workspaces.
+        #[arg(long, default_value_t = false)]
+        all: bool,
         #[arg(long, default_value_t = false)]
         json: bool,
     },
 }
 
+#[derive(Args)]
+struct WorkspaceLifecycleArgs {
+    #[arg(long)]
+    workspace: String,
+    #[arg(long)]
+    reason: Option<String>,
+}
+
 #[derive(Subcommand)]
 enum ProjectCommands {
     Create {
@@ -65,131 +184,1312 @@ enum ProjectCommands {
         #[arg(long)]
         name: String,
     },
+    Rename {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        project: String,
+        #[arg(long)]
+        name: String,
+    },
+    /// Hides the project from lists; its history is kept.
+    Archive(ProjectLifecycleArgs),
+    Restore(ProjectLifecycleArgs),
     List {
         #[arg(long)]
         workspace: String,
+        /// Include archived projects.
+        #[arg(long, default_value_t = false)]
+        all: bool,
         #[arg(long, default_value_t = false)]
         json: bool,
     },
 }
 
+#[derive(Args)]
+struct ProjectLifecycleArgs {
+    #[arg(long)]
+    workspace: String,
+    #[arg(long)]
+    project: String,
+    #[arg(long)]
+    reason: Option<String>,
+}
+
 #[derive(Subcommand)]
-enum EventCommands {
-    Watch {
+enum SessionCommands {
+    Spawn {
         #[arg(long)]
         workspace: String,
-        #[arg(long, default_value_t = 0)]
-        from: i64,
-        #[arg(long, value_enum, default_value_t = EventTransport::Sse)]
-        transport: EventTransport,
         #[arg(long)]
-        mpd_path: Option<PathBuf>,
+        project: Option<String>,
         #[arg(long)]
-        db: Option<PathBuf>,
+        parent: Option<String>,
+        #[arg(long)]
+        label: Option<String>,
+        /// Blueprint id to start from; its pins must match the daemon's loaded components.
+        #[arg(long)]
+        blueprint: Option<String>,
+        /// Exact blueprint version; defaults to the latest.
+        #[arg(long, requires = "blueprint")]
+        blueprint_version: Option<u32>,
+    },
+    Fork {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        session: String,
+        #[arg(long, value_enum, default_value_t = ForkModeArg::Default)]
+        mode: ForkModeArg,
+        #[arg(long)]
+        label: Option<String>,
+    },
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        root: Option<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
     },
 }
 
 #[derive(Clone, Copy, Debug, ValueEnum)]
-enum EventTransport {
-    Sse,
-    Ndjson,
-    Stdio,
-}
-
-#[derive(Debug, Clone)]
-struct CliError {
-    error: ErrorResponse,
+enum ForkModeArg {
+    Default,
+    SameBranch,
+    PlanningOnly,
 }
 
-impl CliError {
-    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
-        Self {
-            error: ErrorResponse {
-                code,
-                message: message.into(),
-                details: None,
-                trace_id: None,
-            },
-        }
-    }
-
-    fn from_error_response(error: ErrorResponse) -> Self {
-        Self { error }
-    }
-
-    fn from_rejection(rejection: CommandRejection, trace_id: String) -> Self {
-        Self {
-            error: ErrorResponse {
-                code: rejection.code,
-                message: rejection.message,
-                details: None,
-                trace_id: Some(trace_id),
-            },
+impl From<ForkModeArg> for ForkMode {
+    fn from(mode: ForkModeArg) -> Self {
+        match mode {
+            ForkModeArg::Default => ForkMode::Default,
+            ForkModeArg::SameBranch => ForkMode::SameBranch,
+            ForkModeArg::PlanningOnly => ForkMode::PlanningOnly,
         }
     }
 }
 
-impl std::fmt::Display for CliError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", self.error.code, self.error.message)
-    }
+#[derive(Subcommand)]
+enum TaskCommands {
+    Create {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        title: String,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        session: Option<String>,
+        /// RFC 3339 timestamp after which the task is considered overdue.
+        #[arg(long)]
+        deadline: Option<String>,
+        /// Per-attempt timeout enforced by the daemon scheduler.
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        #[command(flatten)]
+        retry: TaskRetryArgs,
+    },
+    Start(TaskTransitionArgs),
+    Succeed(TaskTransitionArgs),
+    Fail {
+        #[command(flatten)]
+        args: TaskTransitionArgs,
+        #[arg(long, value_enum)]
+        class: Option<FailureClassArg>,
+    },
+    Cancel(TaskTransitionArgs),
+    Pause(TaskTransitionArgs),
+    Resume(TaskTransitionArgs),
+    /// Halts a running task until a user answers; `task resume` continues it.
+    AwaitInput(TaskTransitionArgs),
+    /// Moves a task to a stage of its project's pipeline.
+    Stage {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        task: String,
+        #[arg(long)]
+        to: String,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long, value_enum)]
+        state: Option<TaskStateArg>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
-impl std::error::Error for CliError {}
-
-impl From<anyhow::Error> for CliError {
-    fn from(err: anyhow::Error) -> Self {
-        if let Some(client_error) = err.downcast_ref::<ClientError>() {
-            return CliError::from_error_response(client_error.error.clone());
-        }
-        CliError::new(ErrorCode::Unknown, err.to_string())
-    }
+#[derive(Subcommand)]
+enum PipelineCommands {
+    /// Defines a template; reusing a name publishes its next version.
+    Define {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        name: String,
+        /// Stage names in order; repeat for each stage.
+        #[arg(long = "stage", required = true)]
+        stages: Vec<String>,
+    },
+    Bind {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        project: String,
+        /// Template id or name.
+        #[arg(long)]
+        template: String,
+        /// Defaults to the latest version.
+        #[arg(long)]
+        version: Option<u32>,
+    },
+    Templates {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    Stages {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        project: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
-type CliResult<T> = Result<T, CliError>;
+#[derive(Subcommand)]
+enum GateCommands {
+    /// Guards one command type on one subject until the gate is satisfied.
+    Define {
+        #[arg(long)]
+        workspace: String,
+        /// Guarded command type, e.g. stage.transition.
+        #[arg(long)]
+        command: String,
+        #[arg(long, value_enum, default_value_t = GateSubjectArg::Task)]
+        subject_kind: GateSubjectArg,
+        #[arg(long)]
+        subject: String,
+        #[arg(long, value_enum, default_value_t = GateKindArg::ManualApproval)]
+        kind: GateKindArg,
+        #[arg(long)]
+        approvals: Option<u32>,
+        #[arg(long)]
+        delay_ms: Option<u64>,
+        #[arg(long)]
+        description: Option<String>,
+    },
+    Approve(GateDecisionArgs),
+    Reject(GateDecisionArgs),
+    List {
+        #[arg(long)]
+        workspace: String,
+        /// Only gates guarding this command type.
+        #[arg(long)]
+        command: Option<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
 
-fn exit_code_for_error_code(code: ErrorCode) -> i32 {
-    match code {
-        ErrorCode::InvalidSchema
-        | ErrorCode::ValidationFailed
-        | ErrorCode::IdempotencyKeyRequired
-        | ErrorCode::UnknownCommand => 2,
-        ErrorCode::ExpectedVersionMismatch => 3,
-        ErrorCode::Unauthorized => 4,
-        ErrorCode::NotFound => 5,
-        ErrorCode::PolicyDenied => 6,
-        ErrorCode::Unknown | ErrorCode::Internal => 1,
-    }
+#[derive(Subcommand)]
+enum WorktreeCommands {
+    Register {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        path: PathBuf,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        branch: Option<String>,
+        /// Repository identity, e.g. the origin remote URL.
+        #[arg(long)]
+        repo: Option<String>,
+    },
+    Attach(WorktreeSessionArgs),
+    Detach(WorktreeSessionArgs),
+    /// Takes the single-writer lock; the session must be attached.
+    Lock(WorktreeSessionArgs),
+    Unlock(WorktreeSessionArgs),
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
 }
 
-fn wants_json(cli: &Cli) -> bool {
-    match &cli.command {
-        Commands::Daemon {
-            command: DaemonCommands::Status { json },
-        } => *json,
-        Commands::Workspace {
-            command: WorkspaceCommands::List { json },
-        } => *json,
-        Commands::Project {
-            command: ProjectCommands::List { json, .. },
-        } => *json,
-        _ => false,
-    }
+#[derive(Subcommand)]
+enum BoardCommands {
+    /// Prints every node and edge of the workspace board.
+    Show {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Creates a group (frame) node.
+    Group {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        label: String,
+        #[command(flatten)]
+        position: BoardPositionArgs,
+    },
+    Comment {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        body: String,
+        /// Node the comment annotates, e.g. `task:<id>`.
+        #[arg(long)]
+        target: Option<String>,
+        #[command(flatten)]
+        position: BoardPositionArgs,
+    },
+    /// Persists a node's layout position, optionally placing it in a group.
+    Move {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        node: String,
+        #[arg(long, allow_hyphen_values = true)]
+        x: f64,
+        #[arg(long, allow_hyphen_values = true)]
+        y: f64,
+        #[arg(long)]
+        group: Option<String>,
+    },
 }
 
-fn render_error(err: &CliError, json: bool) {
-    if json {
-        match serde_json::to_string_pretty(&err.error) {
-            Ok(payload) => println!("{payload}"),
-            Err(_) => {
-                let fallback = serde_json::json!({
-                    "code": err.error.code,
-                    "message": err.error.message,
-                    "details": err.error.details,
-                    "trace_id": err.error.trace_id,
-                });
-                match serde_json::to_string(&fallback) {
-                    Ok(payload) => println!("{payload}"),
+#[derive(Subcommand)]
+enum BlueprintCommands {
+    /// Rescans `agents/blueprints/` and records a version for each new or changed file.
+    Sync {
+        #[arg(long)]
+        workspace: String,
+    },
+    /// Lists the latest version of each blueprint.
+    List {
+        #[arg(long)]
+        workspace: String,
+        /// Include superseded versions.
+        #[arg(long, default_value_t = false)]
+        all_versions: bool,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Prints one blueprint version as JSON.
+    Show {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+        /// Defaults to the latest version.
+        #[arg(long)]
+        version: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum SkillCommands {
+    /// Rescans workspace and global skill directories and records new, changed or removed skills.
+    Sync {
+        #[arg(long)]
+        workspace: String,
+    },
+    /// Lists registered skills with their trust tier.
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, value_enum)]
+        trust: Option<SkillTrustArg>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Prints one registered skill as JSON.
+    Inspect {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+    },
+    /// Checks a skill's directory against what was registered; fails on drift or a bad signature.
+    Verify {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Approves a proposed skill so it runs sandboxed.
+    Approve {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+        /// Content hash that was reviewed; defaults to the registered hash.
+        #[arg(long)]
+        hash: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum CapsuleCommands {
+    /// Writes the next version of a capsule; rejected when over the kind's budget.
+    Write {
+        #[arg(long)]
+        workspace: String,
+        /// Subject kind, e.g. `session` or `task`.
+        #[arg(long)]
+        subject_kind: String,
+        #[arg(long)]
+        subject: String,
+        #[arg(long, value_enum)]
+        kind: CapsuleKindArg,
+        #[arg(long)]
+        content: String,
+    },
+    /// Lists the latest capsule per subject and kind.
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, requires = "subject")]
+        subject_kind: Option<String>,
+        #[arg(long, requires = "subject_kind")]
+        subject: Option<String>,
+        #[arg(long, value_enum)]
+        kind: Option<CapsuleKindArg>,
+        /// Include superseded versions.
+        #[arg(long, default_value_t = false)]
+        all_versions: bool,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ArtifactCommands {
+    /// Streams a file into the artifact store; with `--workspace`, also records `artifact.stored`.
+    Put {
+        file: PathBuf,
+        #[arg(long)]
+        mime_type: Option<String>,
+        #[arg(long)]
+        workspace: Option<String>,
+        /// Display name; defaults to the file name.
+        #[arg(long, requires = "workspace")]
+        name: Option<String>,
+        /// Subject kind of the producer, e.g. `task`.
+        #[arg(long, requires_all = ["producer", "workspace"])]
+        producer_kind: Option<String>,
+        #[arg(long, requires = "producer_kind")]
+        producer: Option<String>,
+    },
+    /// Streams an artifact to a file, or to stdout.
+    Get {
+        /// `blake3:<hex>` content hash.
+        hash: String,
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Inclusive byte range, `START-END` or `START-`.
+        #[arg(long)]
+        range: Option<ArtifactRange>,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookCommands {
+    /// Replays captured hook inputs through a chain, checks fixtures and writes a report.
+    Test(HookTestArgs),
+}
+
+#[derive(Args)]
+struct HookTestArgs {
+    /// Hook chain document to test.
+    #[arg(long)]
+    chain: PathBuf,
+    /// Replays `hook.input_recorded` events from this workspace on the running daemon.
+    #[arg(long)]
+    workspace: Option<String>,
+    /// NDJSON event dump, e.g. from `mpctl events watch --transport ndjson`.
+    #[arg(long)]
+    events: Option<PathBuf>,
+    /// NDJSON file of command envelopes.
+    #[arg(long)]
+    commands: Option<PathBuf>,
+    /// Directory of expected outcomes, one `<case>.json` per case.
+    #[arg(long)]
+    fixtures: Option<PathBuf>,
+    /// Overwrite the fixtures with this run's outcomes.
+    #[arg(long, default_value_t = false, requires = "fixtures")]
+    update_fixtures: bool,
+    /// Malformed inputs fed to each hook per case.
+    #[arg(long, default_value_t = 0)]
+    fuzz: u32,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Database capsule hostcalls read from; defaults to an empty one.
+    #[arg(long)]
+    db: Option<PathBuf>,
+    /// Writes the report here instead of printing it.
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum MessageCommands {
+    /// Sends a message to the sessions a lineage scope resolves to.
+    Send {
+        #[arg(long)]
+        workspace: String,
+        /// Sending session.
+        #[arg(long)]
+        from: String,
+        #[arg(long, value_enum)]
+        scope: MessageScopeArg,
+        /// A core message type or an `x.<org>.<name>` extension.
+        #[arg(long = "type")]
+        message_type: String,
+        /// JSON message body.
+        #[arg(long, value_parser = parse_json_value)]
+        body: serde_json::Value,
+    },
+}
+
+#[derive(Subcommand)]
+enum PolicyCommands {
+    /// Shows which rule would allow or deny a command for an actor, without submitting it.
+    Explain {
+        /// Command type, e.g. `task.create`.
+        command_type: String,
+        /// Actor as `<kind>:<id>`, e.g. `user:alice` or `session:s_1`.
+        #[arg(long, value_parser = parse_actor)]
+        actor: Actor,
+        /// Workspace the command targets; fills `workspace_id` in the payload.
+        #[arg(long)]
+        workspace: Option<String>,
+        /// JSON command payload.
+        #[arg(long, value_parser = parse_json_value)]
+        payload: Option<serde_json::Value>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum LeaseCommands {
+    /// Grants an actor a time-boxed exception to policy, e.g. one hour of network tool use.
+    Mint {
+        #[arg(long)]
+        workspace: String,
+        /// Holder as `<kind>:<id>`, e.g. `session:s_1`.
+        #[arg(long, value_parser = parse_actor)]
+        holder: Actor,
+        /// Command type the lease covers: exact, `*`, or `family.*`. Repeatable.
+        #[arg(long = "action", required = true)]
+        actions: Vec<String>,
+        /// Policy attribute the command must match, as `<attribute>=<value>`. Repeatable; values
+        /// given for the same attribute are alternatives.
+        #[arg(long = "attribute", value_parser = parse_attribute)]
+        attributes: Vec<(String, String)>,
+        /// Lifetime; the daemon records expiry once it passes.
+        #[arg(long)]
+        ttl_ms: u64,
+        #[arg(long)]
+        reason: String,
+    },
+    /// Ends an active lease before it expires.
+    Revoke {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Lists leases by expiry.
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, value_enum)]
+        status: Option<LeaseStatusArg>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum VaultCommands {
+    /// Creates the workspace vault, locked. Reads the password from stdin.
+    Init {
+        #[arg(long)]
+        workspace: String,
+    },
+    /// Derives the vault key from the password on stdin and holds it until lock or idle timeout.
+    Unlock {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long)]
+        idle_timeout_ms: Option<u64>,
+    },
+    /// Drops the vault key from daemon memory.
+    Lock {
+        #[arg(long)]
+        workspace: String,
+    },
+    Status {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SecretCommands {
+    /// Stores a secret. The value is read from stdin so it stays out of shell history and
+    /// process listings.
+    Set {
+        #[arg(long)]
+        workspace: String,
+        name: String,
+        #[arg(long, value_enum, default_value_t = SecretScopeArg::Global)]
+        scope: SecretScopeArg,
+        /// User, project or session id the secret belongs to; required unless global.
+        #[arg(long)]
+        owner: Option<String>,
+    },
+    /// Replaces a secret's value with a new version read from stdin.
+    Rotate {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+    },
+    Delete {
+        #[arg(long)]
+        workspace: String,
+        id: String,
+    },
+    /// Lists secret metadata; values are never shown.
+    List {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Runs a program with secrets on stdin or in files removed when it exits, never in its
+    /// environment. Exits with the program's exit code.
+    Exec {
+        #[arg(long)]
+        workspace: String,
+        /// Secret written to the program's stdin.
+        #[arg(long)]
+        stdin: Option<String>,
+        /// Secret written to a private file; `{secret:<name>}` in the arguments becomes its
+        /// path. Repeatable.
+        #[arg(long = "file")]
+        files: Vec<String>,
+        #[arg(long)]
+        user: Option<String>,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        session: Option<String>,
+        /// Who the program runs on behalf of, as `<kind>:<id>`.
+        #[arg(long, value_parser = parse_actor)]
+        actor: Option<Actor>,
+        #[arg(long)]
+        timeout_ms: Option<u64>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+        #[arg(required = true, last = true)]
+        command: Vec<String>,
+    },
+    /// Shows which secret a name resolves to for the given identities.
+    Resolve {
+        #[arg(long)]
+        workspace: String,
+        name: String,
+        #[arg(long)]
+        user: Option<String>,
+        #[arg(long)]
+        project: Option<String>,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ToolCommands {
+    /// Asks the kernel to run a registered tool; the input must match its schema.
+    Request {
+        #[arg(long)]
+        workspace: String,
+        /// Namespaced tool id, e.g. `acme.search`.
+        #[arg(long)]
+        tool: String,
+        /// JSON tool input.
+        #[arg(long, value_parser = parse_json_value)]
+        input: serde_json::Value,
+        /// Session making the request.
+        #[arg(long)]
+        session: Option<String>,
+    },
+    /// Reports the result of a pending call to a tool the kernel does not run itself.
+    Complete {
+        #[arg(long)]
+        workspace: String,
+        /// Request id from `tool.requested`.
+        #[arg(long)]
+        request: String,
+        /// JSON output; must match the tool's output schema.
+        #[arg(long, value_parser = parse_json_value, required_unless_present = "error_code")]
+        output: Option<serde_json::Value>,
+        #[arg(long, conflicts_with = "output", requires = "error_message")]
+        error_code: Option<String>,
+        #[arg(long, requires = "error_code")]
+        error_message: Option<String>,
+    },
+    /// Finds operations by title, description or side effect, best match first.
+    Search {
+        query: String,
+        #[arg(long)]
+        limit: Option<usize>,
+        #[arg(long, value_enum)]
+        side_effect: Option<SideEffectArg>,
+        #[arg(long, value_enum)]
+        kind: Option<CatalogKindArg>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// Prints an operation's schemas, examples and safety notes.
+    Load { id: String },
+    /// Runs a tool or kernel command from the catalog.
+    Run {
+        id: String,
+        /// JSON input; for commands, the command payload.
+        #[arg(long, value_parser = parse_json_value)]
+        input: serde_json::Value,
+        /// Workspace to run a registry tool in; commands take it from their input.
+        #[arg(long)]
+        workspace: Option<String>,
+        #[arg(long, requires = "workspace")]
+        session: Option<String>,
+    },
+    /// Lists tool calls in request order.
+    Calls {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, value_enum)]
+        status: Option<ToolCallStatusArg>,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Args)]
+struct BoardPositionArgs {
+    #[arg(long, requires = "y", allow_hyphen_values = true)]
+    x: Option<f64>,
+    #[arg(long, requires = "x", allow_hyphen_values = true)]
+    y: Option<f64>,
+}
+
+impl BoardPositionArgs {
+    fn position(&self) -> Option<BoardPosition> {
+        Some(BoardPosition {
+            x: self.x?,
+            y: self.y?,
+        })
+    }
+}
+
+#[derive(Args)]
+struct WorktreeSessionArgs {
+    #[arg(long)]
+    workspace: String,
+    #[arg(long)]
+    worktree: String,
+    #[arg(long)]
+    session: String,
+}
+
+#[derive(Args)]
+struct GateDecisionArgs {
+    #[arg(long)]
+    workspace: String,
+    #[arg(long)]
+    gate: String,
+    #[arg(long)]
+    approver: String,
+    #[arg(long, value_enum, default_value_t = ApproverKindArg::Human)]
+    approver_kind: ApproverKindArg,
+    #[arg(long)]
+    approver_label: Option<String>,
+    #[arg(long)]
+    reason: Option<String>,
+    /// Artifact reference backing the decision; repeatable.
+    #[arg(long)]
+    evidence: Vec<String>,
+}
+
+#[derive(Args)]
+struct TaskTransitionArgs {
+    #[arg(long)]
+    workspace: String,
+    #[arg(long)]
+    task: String,
+    #[arg(long)]
+    reason: Option<String>,
+}
+
+#[derive(Args)]
+struct TaskRetryArgs {
+    /// Enables retries; counts the first attempt.
+    #[arg(long)]
+    max_attempts: Option<u32>,
+    /// Fixed delay, or the initial delay when --backoff-max-ms is set.
+    #[arg(long, default_value_t = 1000)]
+    backoff_ms: u64,
+    /// Switches to exponential backoff capped at this delay.
+    #[arg(long)]
+    backoff_max_ms: Option<u64>,
+    #[arg(long, value_enum, default_value_t = JitterArg::None)]
+    jitter: JitterArg,
+    #[arg(long, value_enum, value_delimiter = ',')]
+    retry_on: Vec<FailureClassArg>,
+}
+
+impl TaskRetryArgs {
+    fn policy(&self) -> Option<RetryPolicy> {
+        let max_attempts = self.max_attempts?;
+        let backoff = match self.backoff_max_ms {
+            Some(max_ms) => Backoff::Exponential {
+                initial_ms: self.backoff_ms,
+                max_ms,
+                multiplier: 2,
+            },
+            None => Backoff::Fixed {
+                delay_ms: self.backoff_ms,
+            },
+        };
+        let retry_on = if self.retry_on.is_empty() {
+            vec![
+                FailureClass::Transient,
+                FailureClass::Timeout,
+                FailureClass::Crash,
+            ]
+        } else {
+            self.retry_on.iter().copied().map(Into::into).collect()
+        };
+        Some(RetryPolicy {
+            max_attempts,
+            backoff,
+            jitter: self.jitter.into(),
+            retry_on,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum JitterArg {
+    None,
+    Full,
+    Decorrelated,
+}
+
+impl From<JitterArg> for JitterMode {
+    fn from(jitter: JitterArg) -> Self {
+        match jitter {
+            JitterArg::None => JitterMode::None,
+            JitterArg::Full => JitterMode::Full,
+            JitterArg::Decorrelated => JitterMode::Decorrelated,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FailureClassArg {
+    Transient,
+    Timeout,
+    Crash,
+    Permanent,
+}
+
+impl From<FailureClassArg> for FailureClass {
+    fn from(class: FailureClassArg) -> Self {
+        match class {
+            FailureClassArg::Transient => FailureClass::Transient,
+            FailureClassArg::Timeout => FailureClass::Timeout,
+            FailureClassArg::Crash => FailureClass::Crash,
+            FailureClassArg::Permanent => FailureClass::Permanent,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TaskStateArg {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    Paused,
+    AwaitingUserInput,
+    BlockedByPolicy,
+    BlockedByApproval,
+}
+
+impl From<TaskStateArg> for TaskState {
+    fn from(state: TaskStateArg) -> Self {
+        match state {
+            TaskStateArg::Queued => TaskState::Queued,
+            TaskStateArg::Running => TaskState::Running,
+            TaskStateArg::Succeeded => TaskState::Succeeded,
+            TaskStateArg::Failed => TaskState::Failed,
+            TaskStateArg::Cancelled => TaskState::Cancelled,
+            TaskStateArg::Paused => TaskState::Paused,
+            TaskStateArg::AwaitingUserInput => TaskState::AwaitingUserInput,
+            TaskStateArg::BlockedByPolicy => TaskState::BlockedByPolicy,
+            TaskStateArg::BlockedByApproval => TaskState::BlockedByApproval,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum EventCommands {
+    Watch {
+        #[arg(long)]
+        workspace: String,
+        #[arg(long, default_value_t = 0)]
+        from: i64,
+        #[arg(long, value_enum, default_value_t = EventTransport::Sse)]
+        transport: EventTransport,
+        #[arg(long)]
+        mpd_path: Option<PathBuf>,
+        #[arg(long)]
+        db: Option<PathBuf>,
+        #[command(flatten)]
+        filter: EventFilterArgs,
+    },
+}
+
+/// Narrows `events watch`; only the SSE and NDJSON transports support filters.
+#[derive(Args)]
+struct EventFilterArgs {
+    #[arg(long)]
+    subject_kind: Option<String>,
+    #[arg(long)]
+    subject: Option<String>,
+    /// Exact event type, or a `prefix.*` pattern such as `message.*`.
+    #[arg(long)]
+    event_type: Option<String>,
+}
+
+impl EventFilterArgs {
+    fn filter(&self) -> EventFilter {
+        EventFilter {
+            subject_kind: self.subject_kind.clone(),
+            subject_id: self.subject.clone(),
+            event_type: self.event_type.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum GateSubjectArg {
+    Workspace,
+    Project,
+    Session,
+    Task,
+}
+
+impl GateSubjectArg {
+    fn as_str(self) -> &'static str {
+        match self {
+            GateSubjectArg::Workspace => "workspace",
+            GateSubjectArg::Project => "project",
+            GateSubjectArg::Session => "session",
+            GateSubjectArg::Task => "task",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum GateKindArg {
+    ManualApproval,
+    PolicyEvaluation,
+    Delay,
+}
+
+impl From<GateKindArg> for GateKind {
+    fn from(kind: GateKindArg) -> Self {
+        match kind {
+            GateKindArg::ManualApproval => GateKind::ManualApproval,
+            GateKindArg::PolicyEvaluation => GateKind::PolicyEvaluation,
+            GateKindArg::Delay => GateKind::Delay,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ApproverKindArg {
+    Human,
+    PolicyBot,
+    AiScorer,
+}
+
+impl From<ApproverKindArg> for ApproverKind {
+    fn from(kind: ApproverKindArg) -> Self {
+        match kind {
+            ApproverKindArg::Human => ApproverKind::Human,
+            ApproverKindArg::PolicyBot => ApproverKind::PolicyBot,
+            ApproverKindArg::AiScorer => ApproverKind::AiScorer,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CapsuleKindArg {
+    Status,
+    Plan,
+    Decision,
+    ArtifactManifest,
+    Risk,
+}
+
+impl From<CapsuleKindArg> for CapsuleKind {
+    fn from(kind: CapsuleKindArg) -> Self {
+        match kind {
+            CapsuleKindArg::Status => CapsuleKind::Status,
+            CapsuleKindArg::Plan => CapsuleKind::Plan,
+            CapsuleKindArg::Decision => CapsuleKind::Decision,
+            CapsuleKindArg::ArtifactManifest => CapsuleKind::ArtifactManifest,
+            CapsuleKindArg::Risk => CapsuleKind::Risk,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SideEffectArg {
+    Pure,
+    Workspace,
+    Filesystem,
+    Network,
+    Process,
+    Git,
+    Container,
+}
+
+impl From<SideEffectArg> for SideEffect {
+    fn from(side_effect: SideEffectArg) -> Self {
+        match side_effect {
+            SideEffectArg::Pure => SideEffect::Pure,
+            SideEffectArg::Workspace => SideEffect::Workspace,
+            SideEffectArg::Filesystem => SideEffect::Filesystem,
+            SideEffectArg::Network => SideEffect::Network,
+            SideEffectArg::Process => SideEffect::Process,
+            SideEffectArg::Git => SideEffect::Git,
+            SideEffectArg::Container => SideEffect::Container,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SkillTrustArg {
+    Trusted,
+    Sandboxed,
+    Proposed,
+}
+
+impl From<SkillTrustArg> for SkillTrust {
+    fn from(trust: SkillTrustArg) -> Self {
+        match trust {
+            SkillTrustArg::Trusted => SkillTrust::Trusted,
+            SkillTrustArg::Sandboxed => SkillTrust::Sandboxed,
+            SkillTrustArg::Proposed => SkillTrust::Proposed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum LeaseStatusArg {
+    Active,
+    Revoked,
+    Expired,
+}
+
+impl From<LeaseStatusArg> for LeaseStatus {
+    fn from(status: LeaseStatusArg) -> Self {
+        match status {
+            LeaseStatusArg::Active => LeaseStatus::Active,
+            LeaseStatusArg::Revoked => LeaseStatus::Revoked,
+            LeaseStatusArg::Expired => LeaseStatus::Expired,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SecretScopeArg {
+    Global,
+    User,
+    Project,
+    Session,
+}
+
+impl From<SecretScopeArg> for SecretScope {
+    fn from(scope: SecretScopeArg) -> Self {
+        match scope {
+            SecretScopeArg::Global => SecretScope::Global,
+            SecretScopeArg::User => SecretScope::User,
+            SecretScopeArg::Project => SecretScope::Project,
+            SecretScopeArg::Session => SecretScope::Session,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CatalogKindArg {
+    Tool,
+    Command,
+}
+
+impl From<CatalogKindArg> for CatalogKind {
+    fn from(kind: CatalogKindArg) -> Self {
+        match kind {
+            CatalogKindArg::Tool => CatalogKind::Tool,
+            CatalogKindArg::Command => CatalogKind::Command,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum ToolCallStatusArg {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl From<ToolCallStatusArg> for ToolCallStatus {
+    fn from(status: ToolCallStatusArg) -> Self {
+        match status {
+            ToolCallStatusArg::Pending => ToolCallStatus::Pending,
+            ToolCallStatusArg::Succeeded => ToolCallStatus::Succeeded,
+            ToolCallStatusArg::Failed => ToolCallStatus::Failed,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum MessageScopeArg {
+    Parent,
+    Children,
+    Siblings,
+    Descendants,
+    Project,
+    Workspace,
+}
+
+impl From<MessageScopeArg> for MessageScope {
+    fn from(scope: MessageScopeArg) -> Self {
+        match scope {
+            MessageScopeArg::Parent => MessageScope::Parent,
+            MessageScopeArg::Children => MessageScope::Children,
+            MessageScopeArg::Siblings => MessageScope::Siblings,
+            MessageScopeArg::Descendants => MessageScope::Descendants,
+            MessageScopeArg::Project => MessageScope::Project,
+            MessageScopeArg::Workspace => MessageScope::Workspace,
+        }
+    }
+}
+
+fn parse_json_value(value: &str) -> Result<serde_json::Value, String> {
+    serde_json::from_str(value).map_err(|err| format!("invalid JSON: {err}"))
+}
+
+fn parse_actor(value: &str) -> Result<Actor, String> {
+    match value.split_once(':') {
+        Some((kind, id)) if !kind.is_empty() && !id.is_empty() => Ok(Actor {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            label: None,
+            claimed: false,
+        }),
+        _ => Err("expected <kind>:<id>".to_string()),
+    }
+}
+
+fn parse_attribute(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((attribute, value)) if !attribute.is_empty() => {
+            Ok((attribute.to_string(), value.to_string()))
+        }
+        _ => Err("expected <attribute>=<value>".to_string()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum EventTransport {
+    Sse,
+    Ndjson,
+    Stdio,
+}
+
+#[derive(Debug, Clone)]
+struct CliError {
+    error: ErrorResponse,
+}
+
+impl CliError {
+    fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            error: ErrorResponse {
+                code,
+                message: message.into(),
+                details: None,
+                trace_id: None,
+            },
+        }
+    }
+
+    fn from_error_response(error: ErrorResponse) -> Self {
+        Self { error }
+    }
+
+    fn from_rejection(rejection: CommandRejection, trace_id: String) -> Self {
+        Self {
+            error: ErrorResponse {
+                code: rejection.code,
+                message: rejection.message,
+                details: None,
+                trace_id: Some(trace_id),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.error.code, self.error.message)
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<anyhow::Error> for CliError {
+    fn from(err: anyhow::Error) -> Self {
+        if let Some(client_error) = err.downcast_ref::<ClientError>() {
+            return CliError::from_error_response(client_error.error.clone());
+        }
+        CliError::new(ErrorCode::Unknown, err.to_string())
+    }
+}
+
+type CliResult<T> = Result<T, CliError>;
+
+fn exit_code_for_error_code(code: ErrorCode) -> i32 {
+    match code {
+        ErrorCode::InvalidSchema
+        | ErrorCode::ValidationFailed
+        | ErrorCode::IdempotencyKeyRequired
+        | ErrorCode::UnknownCommand => 2,
+        ErrorCode::ExpectedVersionMismatch => 3,
+        ErrorCode::Unauthorized => 4,
+        ErrorCode::NotFound => 5,
+        ErrorCode::PolicyDenied => 6,
+        ErrorCode::LockConflict => 7,
+        ErrorCode::BudgetExceeded => 8,
+        ErrorCode::Unknown | ErrorCode::Internal => 1,
+    }
+}
+
+fn wants_json(cli: &Cli) -> bool {
+    match &cli.command {
+        Commands::Daemon {
+            command: DaemonCommands::Status { json },
+        } => *json,
+        Commands::Workspace {
+            command: WorkspaceCommands::List { json, .. },
+        } => *json,
+        Commands::Project {
+            command: ProjectCommands::List { json, .. },
+        } => *json,
+        Commands::Session {
+            command: SessionCommands::List { json, .. },
+        } => *json,
+        Commands::Task {
+            command: TaskCommands::List { json, .. },
+        } => *json,
+        Commands::Pipeline {
+            command:
+                PipelineCommands::Templates { json, .. } | PipelineCommands::Stages { json, .. },
+        } => *json,
+        Commands::Gate {
+            command: GateCommands::List { json, .. },
+        } => *json,
+        Commands::Worktree {
+            command: WorktreeCommands::List { json, .. },
+        } => *json,
+        Commands::Board {
+            command: BoardCommands::Show { json, .. },
+        } => *json,
+        Commands::Capsule {
+            command: CapsuleCommands::List { json, .. },
+        } => *json,
+        Commands::Blueprint {
+            command: BlueprintCommands::List { json, .. },
+        } => *json,
+        Commands::Skill {
+            command: SkillCommands::List { json, .. } | SkillCommands::Verify { json, .. },
+        } => *json,
+        Commands::Policy {
+            command: PolicyCommands::Explain { json, .. },
+        } => *json,
+        Commands::Lease {
+            command: LeaseCommands::List { json, .. },
+        } => *json,
+        Commands::Vault {
+            command: VaultCommands::Status { json, .. },
+        } => *json,
+        Commands::Secret {
+            command:
+                SecretCommands::List { json, .. }
+                | SecretCommands::Exec { json, .. }
+                | SecretCommands::Resolve { json, .. },
+        } => *json,
+        Commands::Tool {
+            command: ToolCommands::Calls { json, .. } | ToolCommands::Search { json, .. },
+        } => *json,
+        _ => false,
+    }
+}
+
+fn render_error(err: &CliError, json: bool) {
+    if json {
+        match serde_json::to_string_pretty(&err.error) {
+            Ok(payload) => println!("{payload}"),
+            Err(_) => {
+                let fallback = serde_json::json!({
+                    "code": err.error.code,
+                    "message": err.error.message,
+                    "details": err.error.details,
+                    "trace_id": err.error.trace_id,
+                });
+                match serde_json::to_string(&fallback) {
+                    Ok(payload) => println!("{payload}"),
                     Err(_) => {
                         println!("{{\"code\":\"internal\",\"message\":\"serialization error\"}}")
                     }
@@ -199,394 +1499,3043 @@ fn render_error(err: &CliError, json: bool) {
         return;
     }
 
-    eprintln!("{}: {}", err.error.code, err.error.message);
-    if let Some(trace_id) = &err.error.trace_id {
-        eprintln!("trace_id: {trace_id}");
+    eprintln!("{}: {}", err.error.code, err.error.message);
+    if let Some(trace_id) = &err.error.trace_id {
+        eprintln!("trace_id: {trace_id}");
+    }
+}
+
+fn ensure_command_accepted(response: SubmitCommandResponse) -> CliResult<SubmitCommandResponse> {
+    if response.accepted {
+        return Ok(response);
+    }
+    if let Some(rejection) = response.rejection {
+        return Err(CliError::from_rejection(rejection, response.trace_id));
+    }
+    Err(CliError::new(
+        ErrorCode::Unknown,
+        "command rejected without details",
+    ))
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    let json = wants_json(&cli);
+    if let Err(err) = run(cli).await {
+        render_error(&err, json);
+        std::process::exit(exit_code_for_error_code(err.error.code));
+    }
+}
+
+async fn run(cli: Cli) -> CliResult<()> {
+    match cli.command {
+        Commands::Daemon { command } => match command {
+            DaemonCommands::Start => {
+                start_daemon()?;
+            }
+            DaemonCommands::Status { json } => {
+                daemon_status(json).await?;
+            }
+        },
+        Commands::Workspace { command } => match command {
+            WorkspaceCommands::Init { path, name } => {
+                let client = ensure_client().await?;
+                let resolved_name = name.unwrap_or_else(|| {
+                    path.file_name()
+                        .map(|s| s.to_string_lossy().to_string())
+                        .unwrap_or_else(|| "workspace".to_string())
+                });
+                let response = client
+                    .workspace_create(resolved_name, Some(path.display().to_string()), None, None)
+                    .await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            WorkspaceCommands::Rename { workspace, name } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = WorkspaceRenamePayload { workspace_id, name };
+                let response = client.workspace_rename(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            WorkspaceCommands::Archive(args) => {
+                workspace_lifecycle(COMMAND_WORKSPACE_ARCHIVE, args).await?
+            }
+            WorkspaceCommands::Restore(args) => {
+                workspace_lifecycle(COMMAND_WORKSPACE_RESTORE, args).await?
+            }
+            WorkspaceCommands::List { all, json } => {
+                let client = ensure_client().await?;
+                let workspaces = client.workspace_list(all).await?;
+                if json {
+                    print_json(&workspaces)?;
+                } else {
+                    print_workspaces(&workspaces);
+                }
+            }
+        },
+        Commands::Project { command } => match command {
+            ProjectCommands::Create { workspace, name } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let response = client
+                    .project_create(workspace_id, name, None, None)
+                    .await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            ProjectCommands::Rename {
+                workspace,
+                project,
+                name,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = resolve_project_id(&client, &workspace_id, &project).await?;
+                let payload = ProjectRenamePayload {
+                    workspace_id,
+                    project_id,
+                    name,
+                };
+                let response = client.project_rename(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            ProjectCommands::Archive(args) => {
+                project_lifecycle(COMMAND_PROJECT_ARCHIVE, args).await?
+            }
+            ProjectCommands::Restore(args) => {
+                project_lifecycle(COMMAND_PROJECT_RESTORE, args).await?
+            }
+            ProjectCommands::List {
+                workspace,
+                all,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let projects = client.project_list(&workspace_id, all).await?;
+                if json {
+                    print_json(&projects)?;
+                } else {
+                    print_projects(&projects);
+                }
+            }
+        },
+        Commands::Session { command } => match command {
+            SessionCommands::Spawn {
+                workspace,
+                project,
+                parent,
+                label,
+                blueprint,
+                blueprint_version,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = match project {
+                    Some(project) => {
+                        Some(resolve_project_id(&client, &workspace_id, &project).await?)
+                    }
+                    None => None,
+                };
+                let payload = SessionSpawnPayload {
+                    workspace_id,
+                    project_id,
+                    parent_session_id: parent,
+                    label,
+                    blueprint: blueprint.map(|id| BlueprintRef {
+                        id,
+                        version: blueprint_version,
+                    }),
+                };
+                let response = client.session_spawn(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SessionCommands::Fork {
+                workspace,
+                session,
+                mode,
+                label,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = SessionForkPayload {
+                    workspace_id,
+                    session_id: session,
+                    mode: Some(mode.into()),
+                    label,
+                };
+                let response = client.session_fork(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SessionCommands::List {
+                workspace,
+                root,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let sessions = client.session_list(&workspace_id, root.as_deref()).await?;
+                if json {
+                    print_json(&sessions)?;
+                } else {
+                    print_sessions(&sessions);
+                }
+            }
+        },
+        Commands::Task { command } => match command {
+            TaskCommands::Create {
+                workspace,
+                title,
+                project,
+                session,
+                deadline,
+                timeout_ms,
+                retry,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = match project {
+                    Some(project) => {
+                        Some(resolve_project_id(&client, &workspace_id, &project).await?)
+                    }
+                    None => None,
+                };
+                let payload = TaskCreatePayload {
+                    workspace_id,
+                    title,
+                    project_id,
+                    session_id: session,
+                    deadline_at: deadline,
+                    retry: retry.policy(),
+                    timeout_ms,
+                };
+                let response = client.task_create(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            TaskCommands::Start(args) => task_transition(COMMAND_TASK_START, args, None).await?,
+            TaskCommands::Succeed(args) => {
+                task_transition(COMMAND_TASK_SUCCEED, args, None).await?
+            }
+            TaskCommands::Fail { args, class } => {
+                task_transition(COMMAND_TASK_FAIL, args, class.map(Into::into)).await?
+            }
+            TaskCommands::Cancel(args) => task_transition(COMMAND_TASK_CANCEL, args, None).await?,
+            TaskCommands::Pause(args) => task_transition(COMMAND_TASK_PAUSE, args, None).await?,
+            TaskCommands::Resume(args) => task_transition(COMMAND_TASK_RESUME, args, None).await?,
+            TaskCommands::AwaitInput(args) => {
+                task_transition(COMMAND_TASK_AWAIT_INPUT, args, None).await?
+            }
+            TaskCommands::Stage {
+                workspace,
+                task,
+                to,
+                reason,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = StageTransitionPayload {
+                    workspace_id,
+                    task_id: task,
+                    to_stage: to,
+                    reason,
+                };
+                let response = client.stage_transition(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            TaskCommands::List {
+                workspace,
+                project,
+                state,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = match project {
+                    Some(project) => {
+                        Some(resolve_project_id(&client, &workspace_id, &project).await?)
+                    }
+                    None => None,
+                };
+                let tasks = client
+                    .task_list(&workspace_id, project_id.as_deref(), state.map(Into::into))
+                    .await?;
+                if json {
+                    print_json(&tasks)?;
+                } else {
+                    print_tasks(&tasks);
+                }
+            }
+        },
+        Commands::Pipeline { command } => match command {
+            PipelineCommands::Define {
+                workspace,
+                name,
+                stages,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = PipelineTemplateDefinePayload {
+                    workspace_id,
+                    name,
+                    stages: stages
+                        .into_iter()
+                        .map(|name| StageDefinition {
+                            name,
+                            description: None,
+                        })
+                        .collect(),
+                };
+                let response = client.pipeline_template_define(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            PipelineCommands::Bind {
+                workspace,
+                project,
+                template,
+                version,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = resolve_project_id(&client, &workspace_id, &project).await?;
+                let template_id = resolve_template_id(&client, &workspace_id, &template).await?;
+                let payload = PipelineBindPayload {
+                    workspace_id,
+                    project_id,
+                    template_id,
+                    template_version: version,
+                };
+                let response = client.pipeline_bind(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            PipelineCommands::Templates { workspace, json } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let templates = client.pipeline_template_list(&workspace_id).await?;
+                if json {
+                    print_json(&templates)?;
+                } else {
+                    print_pipeline_templates(&templates);
+                }
+            }
+            PipelineCommands::Stages {
+                workspace,
+                project,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = resolve_project_id(&client, &workspace_id, &project).await?;
+                let view = client.pipeline_stages(&workspace_id, &project_id).await?;
+                if json {
+                    print_json(&view)?;
+                } else {
+                    print_pipeline_stages(&view);
+                }
+            }
+        },
+        Commands::Gate { command } => match command {
+            GateCommands::Define {
+                workspace,
+                command,
+                subject_kind,
+                subject,
+                kind,
+                approvals,
+                delay_ms,
+                description,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = GateDefinePayload {
+                    workspace_id,
+                    scope: GateScope {
+                        command_type: command,
+                        subject: Subject {
+                            kind: subject_kind.as_str().to_string(),
+                            id: subject,
+                        },
+                    },
+                    kind: kind.into(),
+                    required_approvals: approvals,
+                    delay_ms,
+                    description,
+                };
+                let response = client.gate_define(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            GateCommands::Approve(args) => gate_decide(COMMAND_GATE_APPROVE, args).await?,
+            GateCommands::Reject(args) => gate_decide(COMMAND_GATE_REJECT, args).await?,
+            GateCommands::List {
+                workspace,
+                command,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let gates = client.gate_list(&workspace_id, command.as_deref()).await?;
+                if json {
+                    print_json(&gates)?;
+                } else {
+                    print_gates(&gates);
+                }
+            }
+        },
+        Commands::Worktree { command } => match command {
+            WorktreeCommands::Register {
+                workspace,
+                path,
+                project,
+                branch,
+                repo,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let project_id = match project {
+                    Some(project) => {
+                        Some(resolve_project_id(&client, &workspace_id, &project).await?)
+                    }
+                    None => None,
+                };
+                let payload = WorktreeRegisterPayload {
+                    workspace_id,
+                    project_id,
+                    path: path.to_string_lossy().to_string(),
+                    branch,
+                    repo,
+                };
+                let response = client.worktree_register(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            WorktreeCommands::Attach(args) => {
+                worktree_command(COMMAND_WORKTREE_ATTACH, args).await?
+            }
+            WorktreeCommands::Detach(args) => {
+                worktree_command(COMMAND_WORKTREE_DETACH, args).await?
+            }
+            WorktreeCommands::Lock(args) => {
+                worktree_command(COMMAND_WORKTREE_LOCK_ACQUIRE, args).await?
+            }
+            WorktreeCommands::Unlock(args) => {
+                worktree_command(COMMAND_WORKTREE_LOCK_RELEASE, args).await?
+            }
+            WorktreeCommands::List { workspace, json } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let worktrees = client.worktree_list(&workspace_id).await?;
+                if json {
+                    print_json(&worktrees)?;
+                } else {
+                    print_worktrees(&worktrees);
+                }
+            }
+        },
+        Commands::Board { command } => match command {
+            BoardCommands::Show { workspace, json } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let board = client.board(&workspace_id).await?;
+                if json {
+                    print_json(&board)?;
+                } else {
+                    print_board(&board);
+                }
+            }
+            BoardCommands::Group {
+                workspace,
+                label,
+                position,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = BoardGroupCreatePayload {
+                    workspace_id,
+                    label,
+                    position: position.position(),
+                };
+                let response = client.board_group_create(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            BoardCommands::Comment {
+                workspace,
+                body,
+                target,
+                position,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = BoardCommentAddPayload {
+                    workspace_id,
+                    body,
+                    target_node_id: target,
+                    position: position.position(),
+                };
+                let response = client.board_comment_add(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            BoardCommands::Move {
+                workspace,
+                node,
+                x,
+                y,
+                group,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = BoardNodeMovePayload {
+                    workspace_id,
+                    node_id: node,
+                    position: BoardPosition { x, y },
+                    group_node_id: group,
+                };
+                let response = client.board_node_move(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+        },
+        Commands::Capsule { command } => match command {
+            CapsuleCommands::Write {
+                workspace,
+                subject_kind,
+                subject,
+                kind,
+                content,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = CapsuleWritePayload {
+                    workspace_id,
+                    subject: Subject {
+                        kind: subject_kind,
+                        id: subject,
+                    },
+                    kind: kind.into(),
+                    content,
+                };
+                let response = client.capsule_write(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            CapsuleCommands::List {
+                workspace,
+                subject_kind,
+                subject,
+                kind,
+                all_versions,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let capsules = client
+                    .capsule_list(
+                        &workspace_id,
+                        subject_kind.as_deref().zip(subject.as_deref()),
+                        kind.map(CapsuleKind::from),
+                        all_versions,
+                    )
+                    .await?;
+                if json {
+                    print_json(&capsules)?;
+                } else {
+                    print_capsules(&capsules);
+                }
+            }
+        },
+        Commands::Blueprint { command } => match command {
+            BlueprintCommands::Sync { workspace } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let response = client
+                    .blueprint_sync(BlueprintSyncPayload { workspace_id }, None, None)
+                    .await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            BlueprintCommands::List {
+                workspace,
+                all_versions,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let blueprints = client.blueprint_list(&workspace_id, all_versions).await?;
+                if json {
+                    print_json(&blueprints)?;
+                } else {
+                    print_blueprints(&blueprints);
+                }
+            }
+            BlueprintCommands::Show {
+                workspace,
+                id,
+                version,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let blueprint = client.blueprint_get(&workspace_id, &id, version).await?;
+                print_json(&blueprint)?;
+            }
+        },
+        Commands::Skill { command } => match command {
+            SkillCommands::Sync { workspace } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let response = client
+                    .skill_sync(SkillSyncPayload { workspace_id }, None, None)
+                    .await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SkillCommands::List {
+                workspace,
+                trust,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let skills = client
+                    .skill_list(&workspace_id, trust.map(Into::into))
+                    .await?;
+                if json {
+                    print_json(&skills)?;
+                } else {
+                    print_skills(&skills);
+                }
+            }
+            SkillCommands::Inspect { workspace, id } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let skill = client.skill_get(&workspace_id, &id).await?;
+                print_json(&skill)?;
+            }
+            SkillCommands::Verify {
+                workspace,
+                id,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let report = client.skill_verify(&workspace_id, &id).await?;
+                if json {
+                    print_json(&report)?;
+                } else {
+                    print_skill_verify(&report);
+                }
+                if report.drift || report.signature.status == SkillSignatureStatus::Invalid {
+                    return Err(CliError::new(
+                        ErrorCode::ValidationFailed,
+                        format!("skill {id} does not match what was registered or signed"),
+                    ));
+                }
+            }
+            SkillCommands::Approve {
+                workspace,
+                id,
+                hash,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let content_hash = match hash {
+                    Some(hash) => hash,
+                    None => client.skill_get(&workspace_id, &id).await?.content_hash,
+                };
+                let payload = SkillApprovePayload {
+                    workspace_id,
+                    skill_id: id,
+                    content_hash,
+                };
+                let response = client.skill_approve(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+        },
+        Commands::Policy { command } => match command {
+            PolicyCommands::Explain {
+                command_type,
+                actor,
+                workspace,
+                payload,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let mut payload = payload.unwrap_or_else(|| serde_json::json!({}));
+                if let Some(workspace) = workspace {
+                    let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                    let Some(fields) = payload.as_object_mut() else {
+                        return Err(CliError::new(
+                            ErrorCode::ValidationFailed,
+                            "--payload must be a JSON object",
+                        ));
+                    };
+                    fields.insert("workspace_id".to_string(), workspace_id.into());
+                }
+                let explanation = client
+                    .policy_explain(PolicyExplainRequest {
+                        actor,
+                        command_type,
+                        payload,
+                    })
+                    .await?;
+                if json {
+                    print_json(&explanation)?;
+                } else {
+                    print_policy_explanation(&explanation);
+                }
+                if let Some(decision) = explanation
+                    .decision
+                    .filter(|decision| decision.effect == PolicyEffect::Deny)
+                {
+                    return Err(CliError::new(
+                        ErrorCode::PolicyDenied,
+                        format!(
+                            "{} would be denied: {}",
+                            explanation.command_type, decision.rationale
+                        ),
+                    ));
+                }
+            }
+        },
+        Commands::Lease { command } => match command {
+            LeaseCommands::Mint {
+                workspace,
+                holder,
+                actions,
+                attributes,
+                ttl_ms,
+                reason,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let mut scoped = BTreeMap::<String, Vec<String>>::new();
+                for (attribute, value) in attributes {
+                    scoped.entry(attribute).or_default().push(value);
+                }
+                let payload = LeaseMintPayload {
+                    workspace_id,
+                    holder: LeaseHolder {
+                        kind: holder.kind,
+                        id: holder.id,
+                    },
+                    scope: LeaseScope {
+                        actions,
+                        attributes: scoped,
+                    },
+                    ttl_ms,
+                    reason,
+                };
+                let response = client.lease_mint(payload, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            LeaseCommands::Revoke {
+                workspace,
+                id,
+                reason,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = LeaseRevokePayload {
+                    workspace_id,
+                    lease_id: id,
+                    reason,
+                };
+                let response = client.lease_revoke(payload, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            LeaseCommands::List {
+                workspace,
+                status,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let leases = client
+                    .lease_list(&workspace_id, status.map(Into::into))
+                    .await?;
+                if json {
+                    print_json(&leases)?;
+                } else {
+                    print_leases(&leases);
+                }
+            }
+        },
+        Commands::Vault { command } => match command {
+            VaultCommands::Init { workspace } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let password = read_stdin_secret("vault password")?;
+                let payload = VaultInitPayload {
+                    workspace_id: workspace_id.clone(),
+                    password,
+                };
+                ensure_command_accepted(client.vault_init(payload, None).await?)?;
+                print_json(&client.vault_status(&workspace_id).await?)?;
+            }
+            VaultCommands::Unlock {
+                workspace,
+                idle_timeout_ms,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let password = read_stdin_secret("vault password")?;
+                let payload = VaultUnlockPayload {
+                    workspace_id: workspace_id.clone(),
+                    password,
+                    idle_timeout_ms,
+                };
+                ensure_command_accepted(client.vault_unlock(payload, None).await?)?;
+                print_json(&client.vault_status(&workspace_id).await?)?;
+            }
+            VaultCommands::Lock { workspace } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = VaultLockPayload {
+                    workspace_id: workspace_id.clone(),
+                };
+                ensure_command_accepted(client.vault_lock(payload, None).await?)?;
+                print_json(&client.vault_status(&workspace_id).await?)?;
+            }
+            VaultCommands::Status { workspace, json } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let status = client.vault_status(&workspace_id).await?;
+                if json {
+                    print_json(&status)?;
+                } else {
+                    print_vault_status(&status);
+                }
+            }
+        },
+        Commands::Secret { command } => match command {
+            SecretCommands::Set {
+                workspace,
+                name,
+                scope,
+                owner,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let value = read_stdin_secret("secret value")?;
+                let payload = SecretSetPayload {
+                    workspace_id,
+                    name,
+                    owner: SecretOwner {
+                        scope: scope.into(),
+                        id: owner,
+                    },
+                    value,
+                };
+                let response = client.secret_set(payload, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SecretCommands::Rotate { workspace, id } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let value = read_stdin_secret("secret value")?;
+                let payload = SecretRotatePayload {
+                    workspace_id,
+                    secret_id: id,
+                    value,
+                };
+                let response = client.secret_rotate(payload, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SecretCommands::Delete { workspace, id } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = SecretDeletePayload {
+                    workspace_id,
+                    secret_id: id,
+                };
+                let response = client.secret_delete(payload, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            SecretCommands::List { workspace, json } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let secrets = client.secret_list(&workspace_id).await?;
+                if json {
+                    print_json(&secrets)?;
+                } else {
+                    print_secrets(&secrets);
+                }
+            }
+            SecretCommands::Exec {
+                workspace,
+                stdin,
+                files,
+                user,
+                project,
+                session,
+                actor,
+                timeout_ms,
+                json,
+                command,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let client = match actor {
+                    Some(actor) => client.with_actor(actor),
+                    None => client,
+                };
+                let mut secrets: Vec<_> = files
+                    .into_iter()
+                    .map(|name| SecretInjection {
+                        name,
+                        via: SecretInjectVia::File,
+                    })
+                    .collect();
+                if let Some(name) = stdin {
+                    secrets.push(SecretInjection {
+                        name,
+                        via: SecretInjectVia::Stdin,
+                    });
+                }
+                let mut command = command.into_iter();
+                let payload = SecretExecPayload {
+                    workspace_id,
+                    program: command.next().unwrap_or_default(),
+                    args: command.collect(),
+                    cwd: std::env::current_dir()
+                        .ok()
+                        .map(|dir| dir.display().to_string()),
+                    context: SecretContext {
+                        user_id: user,
+                        project_id: project,
+                        session_id: session,
+                    },
+                    secrets,
+                    timeout_ms,
+                };
+                let response = ensure_command_accepted(client.secret_exec(payload, None).await?)?;
+                let exited = response
+                    .events
+                    .iter()
+                    .find(|event| event.event_type == EVENT_PROCESS_EXITED)
+                    .ok_or_else(|| {
+                        CliError::new(ErrorCode::Unknown, "the daemon did not report an exit")
+                    })?;
+                let exited: ProcessExitedPayload =
+                    serde_json::from_value(exited.payload.clone()).map_err(anyhow::Error::from)?;
+                if json {
+                    print_json(&exited)?;
+                    return Ok(());
+                }
+                print!("{}", exited.stdout);
+                eprint!("{}", exited.stderr);
+                if exited.truncated {
+                    eprintln!("output truncated");
+                }
+                if let Some(error) = &exited.error {
+                    eprintln!("{error}");
+                }
+                // Mirrors timeout(1): 124 when the program was killed for running too long.
+                match (exited.exit_code, exited.timed_out) {
+                    (_, true) => std::process::exit(124),
+                    (Some(0), false) => {}
+                    (Some(code), false) => std::process::exit(code),
+                    (None, false) => std::process::exit(1),
+                }
+            }
+            SecretCommands::Resolve {
+                workspace,
+                name,
+                user,
+                project,
+                session,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let context = SecretContext {
+                    user_id: user,
+                    project_id: project,
+                    session_id: session,
+                };
+                let secret = client
+                    .secret_resolve(&workspace_id, &name, &context)
+                    .await?;
+                if json {
+                    print_json(&secret)?;
+                } else {
+                    print_secrets(std::slice::from_ref(&secret));
+                }
+            }
+        },
+        Commands::Message { command } => match command {
+            MessageCommands::Send {
+                workspace,
+                from,
+                scope,
+                message_type,
+                body,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = MessageSendPayload {
+                    workspace_id,
+                    from_session_id: from,
+                    scope: scope.into(),
+                    message_type,
+                    body,
+                };
+                let response = client.message_send(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+        },
+        Commands::Tool { command } => match command {
+            ToolCommands::Request {
+                workspace,
+                tool,
+                input,
+                session,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = ToolRequestPayload {
+                    workspace_id,
+                    tool_id: tool,
+                    input,
+                    session_id: session,
+                };
+                let response = client.tool_request(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            ToolCommands::Complete {
+                workspace,
+                request,
+                output,
+                error_code,
+                error_message,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let payload = ToolCompletePayload {
+                    workspace_id,
+                    request_id: request,
+                    output,
+                    error: error_code
+                        .zip(error_message)
+                        .map(|(code, message)| ToolError { code, message }),
+                };
+                let response = client.tool_complete(payload, None, None).await?;
+                let response = ensure_command_accepted(response)?;
+                print_json(&response)?;
+            }
+            ToolCommands::Search {
+                query,
+                limit,
+                side_effect,
+                kind,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let results = client
+                    .tool_search(
+                        &query,
+                        limit,
+                        side_effect.map(SideEffect::from),
+                        kind.map(CatalogKind::from),
+                    )
+                    .await?;
+                if json {
+                    print_json(&results)?;
+                } else {
+                    print_tool_search(&results);
+                }
+            }
+            ToolCommands::Load { id } => {
+                let client = ensure_client().await?;
+                let tool = client.tool_load(&id).await?;
+                print_json(&tool)?;
+            }
+            ToolCommands::Run {
+                id,
+                input,
+                workspace,
+                session,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = match workspace {
+                    Some(workspace) => Some(resolve_workspace_id(&client, &workspace).await?),
+                    None => None,
+                };
+                let response = client
+                    .tool_run(&id, input, workspace_id, session, None, None)
+                    .await?;
+                if !response.accepted {
+                    return Err(match response.rejection {
+                        Some(rejection) => CliError::from_rejection(rejection, response.trace_id),
+                        None => {
+                            CliError::new(ErrorCode::Unknown, "command rejected without details")
+                        }
+                    });
+                }
+                print_json(&response)?;
+            }
+            ToolCommands::Calls {
+                workspace,
+                status,
+                session,
+                json,
+            } => {
+                let client = ensure_client().await?;
+                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                let calls = client
+                    .tool_call_list(
+                        &workspace_id,
+                        status.map(ToolCallStatus::from),
+                        session.as_deref(),
+                    )
+                    .await?;
+                if json {
+                    print_json(&calls)?;
+                } else {
+                    print_tool_calls(&calls);
+                }
+            }
+        },
+        Commands::Artifact { command } => match command {
+            ArtifactCommands::Put {
+                file,
+                mime_type,
+                workspace,
+                name,
+                producer_kind,
+                producer,
+            } => {
+                let client = ensure_client().await?;
+                let uploaded = client.artifact_put(&file, mime_type.as_deref()).await?;
+                match workspace {
+                    Some(workspace) => {
+                        let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                        let name = name.or_else(|| {
+                            file.file_name()
+                                .map(|name| name.to_string_lossy().into_owned())
+                        });
+                        let payload = ArtifactPutPayload {
+                            workspace_id,
+                            content_base64: None,
+                            hash: Some(uploaded.artifact.hash),
+                            name,
+                            mime_type,
+                            metadata: None,
+                            producer: producer_kind
+                                .zip(producer)
+                                .map(|(kind, id)| Subject { kind, id }),
+                        };
+                        let response = client.artifact_record(payload, None, None).await?;
+                        let response = ensure_command_accepted(response)?;
+                        print_json(&response)?;
+                    }
+                    None => print_json(&uploaded)?,
+                }
+            }
+            ArtifactCommands::Get {
+                hash,
+                output,
+                range,
+            } => {
+                let client = ensure_client().await?;
+                match output {
+                    Some(path) => {
+                        let mut file = tokio::fs::File::create(&path)
+                            .await
+                            .with_context(|| format!("failed to create {}", path.display()))?;
+                        let written = client.artifact_get(&hash, range, &mut file).await?;
+                        println!("wrote {written} bytes to {}", path.display());
+                    }
+                    None => {
+                        let mut stdout = tokio::io::stdout();
+                        client.artifact_get(&hash, range, &mut stdout).await?;
+                    }
+                }
+            }
+        },
+        Commands::Hooks { command } => match command {
+            HookCommands::Test(args) => hooks_test(args).await?,
+        },
+        Commands::Events { command } => match command {
+            EventCommands::Watch {
+                workspace,
+                from,
+                transport,
+                mpd_path,
+                db,
+                filter,
+            } => match transport {
+                EventTransport::Sse => {
+                    let client = ensure_client().await?;
+                    let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                    watch_events_sse(&client, &workspace_id, from, &filter.filter()).await?;
+                }
+                EventTransport::Ndjson => {
+                    let client = ensure_client().await?;
+                    let workspace_id = resolve_workspace_id(&client, &workspace).await?;
+                    watch_events_ndjson(&client, &workspace_id, from, &filter.filter()).await?;
+                }
+                EventTransport::Stdio => {
+                    if filter.filter() != EventFilter::default() {
+                        return Err(CliError::new(
+                            ErrorCode::ValidationFailed,
+                            "event filters require the sse or ndjson transport",
+                        ));
+                    }
+                    watch_events_stdio(&workspace, from, mpd_path, db).await?;
+                }
+            },
+        },
+    }
+
+    Ok(())
+}
+
+async fn hooks_test(args: HookTestArgs) -> CliResult<()> {
+    let chain = load_hook_chain(&args.chain)?;
+    let mut cases = Vec::new();
+    if let Some(path) = &args.commands {
+        let commands: Vec<CommandEnvelope> = read_ndjson(path)?;
+        cases.extend(cases_from_commands(&commands));
+    }
+    if let Some(path) = &args.events {
+        let events: Vec<EventEnvelope> = read_ndjson(path)?;
+        cases.extend(cases_from_events(&events)?);
+    }
+    if let Some(workspace) = &args.workspace {
+        let client = ensure_client().await?;
+        let workspace_id = resolve_workspace_id(&client, workspace).await?;
+        let events = client.events_read_from(&workspace_id, 0).await?;
+        cases.extend(cases_from_events(&events)?);
+    }
+    if args.commands.is_none() && args.events.is_none() && args.workspace.is_none() {
+        return Err(CliError::new(
+            ErrorCode::ValidationFailed,
+            "give --commands, --events or --workspace to capture inputs from",
+        ));
+    }
+    // The same input can arrive as a command and as its recorded event; replay it once.
+    let mut seen = std::collections::HashSet::new();
+    cases.retain(|case| seen.insert(case.name.clone()));
+
+    let options = HookTestOptions {
+        fixtures: args.fixtures,
+        update_fixtures: args.update_fixtures,
+        fuzz_budget: args.fuzz,
+        seed: args.seed,
+        db: args.db,
+    };
+    let report = run_hook_tests(&chain, &cases, &options).await?;
+    let json = report.to_json()?;
+    match &args.report {
+        Some(path) => {
+            std::fs::write(path, &json)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            print_json(&serde_json::json!({
+                "report": path.display().to_string(),
+                "hash": format!("blake3:{}", blake3::hash(json.as_bytes()).to_hex()),
+                "summary": report.summary,
+            }))?;
+        }
+        None => print!("{json}"),
+    }
+    if !report.passed() {
+        return Err(CliError::new(
+            ErrorCode::ValidationFailed,
+            format!(
+                "hook tests failed: {} fixture mismatches, {} runs hit module limits",
+                report.summary.failed, report.summary.limits_hit
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn read_ndjson<T: serde::de::DeserializeOwned>(path: &std::path::Path) -> CliResult<Vec<T>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| {
+                CliError::new(
+                    ErrorCode::ValidationFailed,
+                    format!("{}:{}: {err}", path.display(), index + 1),
+                )
+            })
+        })
+        .collect()
+}
+
+async fn workspace_lifecycle(command_type: &str, args: WorkspaceLifecycleArgs) -> CliResult<()> {
+    let client = ensure_client().await?;
+    let workspace_id = resolve_workspace_id(&client, &args.workspace).await?;
+    let payload = WorkspaceLifecyclePayload {
+        workspace_id,
+        reason: args.reason,
+    };
+    let response = client
+        .workspace_lifecycle(command_type, payload, None, None)
+        .await?;
+    let response = ensure_command_accepted(response)?;
+    print_json(&response)
+}
+
+async fn project_lifecycle(command_type: &str, args: ProjectLifecycleArgs) -> CliResult<()> {
+    let client = ensure_client().await?;
+    let workspace_id = resolve_workspace_id(&client, &args.workspace).await?;
+    let project_id = resolve_project_id(&client, &workspace_id, &args.project).await?;
+    let payload = ProjectLifecyclePayload {
+        workspace_id,
+        project_id,
+        reason: args.reason,
+    };
+    let response = client
+        .project_lifecycle(command_type, payload, None, None)
+        .await?;
+    let response = ensure_command_accepted(response)?;
+    print_json(&response)
+}
+
+async fn task_transition(
+    command_type: &str,
+    args: TaskTransitionArgs,
+    failure_class: Option<FailureClass>,
+) -> CliResult<()> {
+    let client = ensure_client().await?;
+    let workspace_id = resolve_workspace_id(&client, &args.workspace).await?;
+    let payload = TaskTransitionPayload {
+        workspace_id,
+        task_id: args.task,
+        reason: args.reason,
+        failure_class,
+    };
+    let response = client
+        .task_transition(command_type, payload, None, None)
+        .await?;
+    let response = ensure_command_accepted(response)?;
+    print_json(&response)
+}
+
+async fn gate_decide(command_type: &str, args: GateDecisionArgs) -> CliResult<()> {
+    let client = ensure_client().await?;
+    let workspace_id = resolve_workspace_id(&client, &args.workspace).await?;
+    let payload = GateDecisionPayload {
+        workspace_id,
+        gate_id: args.gate,
+        approver: Approver {
+            kind: args.approver_kind.into(),
+            id: args.approver,
+            label: args.approver_label,
+        },
+        reason: args.reason,
+        evidence: args.evidence,
+    };
+    let response = client
+        .gate_decide(command_type, payload, None, None)
+        .await?;
+    let response = ensure_command_accepted(response)?;
+    print_json(&response)
+}
+
+async fn worktree_command(command_type: &str, args: WorktreeSessionArgs) -> CliResult<()> {
+    let client = ensure_client().await?;
+    let workspace_id = resolve_workspace_id(&client, &args.workspace).await?;
+    let payload = WorktreeSessionPayload {
+        workspace_id,
+        worktree_id: args.worktree,
+        session_id: args.session,
+    };
+    let response = client
+        .worktree_command(command_type, payload, None, None)
+        .await?;
+    let response = ensure_command_accepted(response)?;
+    print_json(&response)
+}
+
+fn start_daemon() -> CliResult<()> {
+    let child = Command::new("mpd")
+        .arg("start")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("failed to start mpd")?;
+    let _ = child.id();
+    Ok(())
+}
+
+async fn daemon_status(json: bool) -> CliResult<()> {
+    match try_client().await {
+        Ok(client) => {
+            let resp = client.ping().await?;
+            if json {
+                print_json(&resp)?;
+            } else {
+                println!(
+                    "daemon: {} (version {} @ {})",
+                    resp.status, resp.version, resp.timestamp
+                );
+            }
+        }
+        Err(err) => {
+            if json {
+                print_json(&serde_json::json!({"status": "down", "error": err.to_string()}))?;
+            } else {
+                println!("daemon: down ({err})");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn ensure_client() -> CliResult<Client> {
+    if let Ok(client) = try_client().await {
+        return Ok(client);
+    }
+
+    start_daemon()?;
+    for _ in 0..20 {
+        sleep(Duration::from_millis(150)).await;
+        if let Ok(client) = try_client().await {
+            return Ok(client);
+        }
+    }
+    Err(CliError::new(ErrorCode::Unknown, "daemon failed to start"))
+}
+
+async fn try_client() -> CliResult<Client> {
+    let runtime_dir = Client::default_runtime_dir();
+    let client = Client::from_runtime_dir(&runtime_dir)?;
+    client.ping().await?;
+    Ok(client)
+}
+
+async fn resolve_workspace_id(client: &Client, selector: &str) -> CliResult<String> {
+    // Archived workspaces still resolve so they can be restored by name.
+    let workspaces = client.workspace_list(true).await?;
+    if workspaces.iter().any(|ws| ws.workspace_id == selector) {
+        return Ok(selector.to_string());
+    }
+    let mut matches = workspaces
+        .iter()
+        .filter(|ws| ws.name == selector || ws.root_path == selector)
+        .collect::<Vec<_>>();
+    if matches.len() > 1 {
+        matches.retain(|ws| ws.archived_at.is_none());
+    }
+    if matches.len() == 1 {
+        return Ok(matches.pop().unwrap().workspace_id.clone());
+    }
+    Err(CliError::new(
+        ErrorCode::NotFound,
+        format!("workspace not found or ambiguous: {selector}"),
+    ))
+}
+
+async fn resolve_project_id(
+    client: &Client,
+    workspace_id: &str,
+    selector: &str,
+) -> CliResult<String> {
+    let projects = client.project_list(workspace_id, true).await?;
+    if projects
+        .iter()
+        .any(|project| project.project_id == selector)
+    {
+        return Ok(selector.to_string());
+    }
+    let mut matches = projects
+        .iter()
+        .filter(|project| project.name == selector)
+        .collect::<Vec<_>>();
+    if matches.len() > 1 {
+        matches.retain(|project| project.archived_at.is_none());
+    }
+    if matches.len() == 1 {
+        return Ok(matches.pop().unwrap().project_id.clone());
+    }
+    Err(CliError::new(
+        ErrorCode::NotFound,
+        format!("project not found or ambiguous: {selector}"),
+    ))
+}
+
+async fn resolve_template_id(
+    client: &Client,
+    workspace_id: &str,
+    selector: &str,
+) -> CliResult<String> {
+    let templates = client.pipeline_template_list(workspace_id).await?;
+    if templates
+        .iter()
+        .any(|template| template.template_id == selector)
+    {
+        return Ok(selector.to_string());
+    }
+    // Every version of a name shares one template id.
+    match templates.iter().find(|template| template.name == selector) {
+        Some(template) => Ok(template.template_id.clone()),
+        None => Err(CliError::new(
+            ErrorCode::NotFound,
+            format!("pipeline template not found: {selector}"),
+        )),
+    }
+}
+
+async fn watch_events_sse(
+    client: &Client,
+    workspace_id: &str,
+    from: i64,
+    filter: &EventFilter,
+) -> CliResult<()> {
+    let resp = client
+        .events_stream_filtered(workspace_id, from, filter)
+        .await?;
+    let mut stream = resp.bytes_stream();
+    let mut buffer = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
+        let text = String::from_utf8_lossy(&chunk);
+        buffer.push_str(&text);
+        while let Some(pos) = buffer.find('\n') {
+            let line = buffer[..pos].trim_end().to_string();
+            buffer = buffer[pos + 1..].to_string();
+            if line.starts_with("data:") {
+                let data = line.trim_start_matches("data:").trim();
+                if !data.is_empty() {
+                    println!("{data}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn watch_events_ndjson(
+    client: &Client,
+    workspace_id: &str,
+    from: i64,
+    filter: &EventFilter,
+) -> CliResult<()> {
+    let resp = client
+        .events_stream_ndjson_filtered(workspace_id, from, filter)
+        .await?;
+    let mut stream = resp.bytes_stream();
+    let mut buffer = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
+        let text = String::from_utf8_lossy(&chunk);
+        buffer.push_str(&text);
+        while let Some(pos) = buffer.find('\n') {
+            let line = buffer[..pos].trim_end().to_string();
+            buffer = buffer[pos + 1..].to_string();
+            if !line.is_empty() {
+                println!("{line}");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn watch_events_stdio(
+    workspace: &str,
+    from: i64,
+    mpd_path: Option<PathBuf>,
+    db: Option<PathBuf>,
+) -> CliResult<()> {
+    let mpd_path = mpd_path.unwrap_or_else(|| PathBuf::from("mpd"));
+    let mut client = StdioClient::spawn(&mpd_path, db, StdioAuthMode::None).await?;
+    let result = async {
+        let workspace_id = resolve_workspace_id_stdio(&mut client, workspace).await?;
+        client.subscribe_events(&workspace_id, from).await?;
+
+        loop {
+            tokio::select! {
+                event = client.next_event() => {
+                    let event = event?;
+                    let json = serde_json::to_string(&event)
+                        .map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
+                    println!("{json}");
+                }
+                _ = tokio::signal::ctrl_c() => {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    let shutdown_result = client.shutdown().await;
+    if result.is_err() {
+        let _ = shutdown_result;
+        return result;
+    }
+    shutdown_result?;
+    result
+}
+
+async fn resolve_workspace_id_stdio(client: &mut StdioClient, selector: &str) -> CliResult<String> {
+    let workspaces = client.list_workspaces(true).await?;
+    if workspaces.iter().any(|ws| ws.workspace_id == selector) {
+        return Ok(selector.to_string());
+    }
+    let mut matches = workspaces
+        .iter()
+        .filter(|ws| ws.name == selector || ws.root_path == selector)
+        .collect::<Vec<_>>();
+    if matches.len() > 1 {
+        matches.retain(|ws| ws.archived_at.is_none());
+    }
+    if matches.len() == 1 {
+        return Ok(matches.pop().unwrap().workspace_id.clone());
+    }
+    Err(CliError::new(
+        ErrorCode::NotFound,
+        format!("workspace not found or ambiguous: {selector}"),
+    ))
+}
+
+fn print_workspaces(workspaces: &[WorkspaceListEntry]) {
+    if workspaces.is_empty() {
+        println!("no workspaces");
+        return;
+    }
+    for workspace in workspaces {
+        let archived = if workspace.archived_at.is_some() {
+            "\tarchived"
+        } else {
+            ""
+        };
+        println!(
+            "{}\t{}\t{}{archived}",
+            workspace.workspace_id, workspace.name, workspace.root_path
+        );
+    }
+}
+
+fn print_projects(projects: &[ProjectListEntry]) {
+    if projects.is_empty() {
+        println!("no projects");
+        return;
+    }
+    for project in projects {
+        let archived = if project.archived_at.is_some() {
+            "\tarchived"
+        } else {
+            ""
+        };
+        println!("{}\t{}{archived}", project.project_id, project.name);
+    }
+}
+
+fn print_sessions(sessions: &[SessionListEntry]) {
+    if sessions.is_empty() {
+        println!("no sessions");
+        return;
+    }
+    for session in sessions {
+        let indent = "  ".repeat(session.depth.max(0) as usize);
+        let mut line = format!("{indent}{}", session.session_id);
+        if let Some(label) = &session.label {
+            line.push_str(&format!("\t{label}"));
+        }
+        if let Some(mode) = session.fork_mode {
+            line.push_str(&format!("\t(fork: {mode})"));
+        }
+        println!("{line}");
+    }
+}
+
+fn print_tasks(tasks: &[TaskListEntry]) {
+    if tasks.is_empty() {
+        println!("no tasks");
+        return;
+    }
+    for task in tasks {
+        println!("{}\t{}\t{}", task.task_id, task.state, task.title);
+    }
+}
+
+fn print_gates(gates: &[GateEntry]) {
+    if gates.is_empty() {
+        println!("no gates");
+        return;
+    }
+    for gate in gates {
+        println!(
+            "{}\t{}\t{}\t{} {}:{}\t{}/{}",
+            gate.gate_id,
+            gate.status,
+            gate.kind,
+            gate.scope.command_type,
+            gate.scope.subject.kind,
+            gate.scope.subject.id,
+            gate.approval_count(),
+            gate.required_approvals
+        );
+    }
+}
+
+fn print_worktrees(worktrees: &[WorktreeEntry]) {
+    if worktrees.is_empty() {
+        println!("no worktrees");
+        return;
+    }
+    for worktree in worktrees {
+        println!(
+            "{}\t{}\t{}\tsessions={}\tlock={}",
+            worktree.worktree_id,
+            worktree.path,
+            worktree.branch.as_deref().unwrap_or("-"),
+            worktree.sessions.len(),
+            worktree.lock_holder().unwrap_or("-")
+        );
+    }
+}
+
+fn print_board(board: &BoardSnapshot) {
+    if board.nodes.is_empty() {
+        println!("empty board");
+        return;
+    }
+    for node in &board.nodes {
+        let position = node
+            .position
+            .map(|position| format!("{},{}", position.x, position.y))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "{}\t{}\tat={}\tgroup={}",
+            node.node_id,
+            node.label
+                .as_deref()
+                .or(node.body.as_deref())
+                .unwrap_or("-"),
+            position,
+            node.group_node_id.as_deref().unwrap_or("-")
+        );
+    }
+    for edge in &board.edges {
+        println!(
+            "{}\t{} -> {}",
+            edge.kind, edge.from_node_id, edge.to_node_id
+        );
+    }
+}
+
+fn print_capsules(capsules: &[CapsuleEntry]) {
+    if capsules.is_empty() {
+        println!("no capsules");
+        return;
+    }
+    for capsule in capsules {
+        println!(
+            "{}:{}\t{}\tv{}\t{} chars",
+            capsule.subject.kind, capsule.subject.id, capsule.kind, capsule.version, capsule.chars
+        );
+        println!("{}", capsule.content);
+    }
+}
+
+fn print_tool_calls(calls: &[ToolCallEntry]) {
+    if calls.is_empty() {
+        println!("no tool calls");
+        return;
+    }
+    for call in calls {
+        println!(
+            "{}\t{}@{}\t{}\t{}",
+            call.request_id, call.tool_id, call.tool_version, call.side_effect, call.status
+        );
+        if let Some(error) = &call.error {
+            println!("  {}: {}", error.code, error.message);
+        }
+    }
+}
+
+fn print_tool_search(results: &ToolSearchResponse) {
+    if results.hits.is_empty() {
+        println!("no matching tools");
+        return;
+    }
+    for hit in &results.hits {
+        println!(
+            "{}\t{}\t{}\t{}",
+            hit.id, hit.kind, hit.side_effect, hit.title
+        );
+        println!("  {}", hit.summary);
+    }
+    if results.hits.len() < results.total {
+        println!(
+            "showing {} of {} matches",
+            results.hits.len(),
+            results.total
+        );
+    }
+}
+
+fn print_blueprints(blueprints: &[BlueprintEntry]) {
+    if blueprints.is_empty() {
+        println!("no blueprints");
+        return;
+    }
+    for blueprint in blueprints {
+        println!(
+            "{}\tv{}\t{}\t{}\t{}",
+            blueprint.blueprint_id,
+            blueprint.version,
+            blueprint.name,
+            blueprint.path,
+            blueprint.content_hash
+        );
     }
 }
 
-fn ensure_command_accepted(response: SubmitCommandResponse) -> CliResult<SubmitCommandResponse> {
-    if response.accepted {
-        return Ok(response);
+fn print_skills(skills: &[SkillEntry]) {
+    if skills.is_empty() {
+        println!("no skills");
+        return;
     }
-    if let Some(rejection) = response.rejection {
-        return Err(CliError::from_rejection(rejection, response.trace_id));
+    for skill in skills {
+        println!(
+            "{}\tr{}\t{}\t{}\t{}\t{}",
+            skill.skill_id,
+            skill.revision,
+            skill.trust,
+            skill.scope,
+            skill.path,
+            skill.content_hash
+        );
     }
-    Err(CliError::new(
-        ErrorCode::Unknown,
-        "command rejected without details",
-    ))
 }
 
-#[tokio::main]
-async fn main() {
-    let cli = Cli::parse();
-    let json = wants_json(&cli);
-    if let Err(err) = run(cli).await {
-        render_error(&err, json);
-        std::process::exit(exit_code_for_error_code(err.error.code));
+fn print_skill_verify(report: &SkillVerifyReport) {
+    println!("skill: {}", report.skill_id);
+    println!("registered: {}", report.registered_hash);
+    println!(
+        "on disk: {}",
+        report.disk_hash.as_deref().unwrap_or("(unreadable)")
+    );
+    println!("drift: {}", report.drift);
+    match &report.signature.key_id {
+        Some(key_id) => println!("signature: {} ({key_id})", report.signature.status),
+        None => println!("signature: {}", report.signature.status),
+    }
+    println!("trust if synced: {}", report.trust);
+    if let Some(error) = &report.error {
+        println!("error: {error}");
     }
 }
 
-async fn run(cli: Cli) -> CliResult<()> {
-    match cli.command {
-        Commands::Daemon { command } => match command {
-            DaemonCommands::Start => {
-                start_daemon()?;
-            }
-            DaemonCommands::Status { json } => {
-                daemon_status(json).await?;
-            }
-        },
-        Commands::Workspace { command } => match command {
-            WorkspaceCommands::Init { path, name } => {
-                let client = ensure_client().await?;
-                let resolved_name = name.unwrap_or_else(|| {
-                    path.file_name()
-                        .map(|s| s.to_string_lossy().to_string())
-                        .unwrap_or_else(|| "workspace".to_string())
-                });
-                let response = client
-                    .workspace_create(resolved_name, Some(path.display().to_string()), None, None)
-                    .await?;
-                let response = ensure_command_accepted(response)?;
-                print_json(&response)?;
-            }
-            WorkspaceCommands::List { json } => {
-                let client = ensure_client().await?;
-                let workspaces = client.workspace_list().await?;
-                if json {
-                    print_json(&workspaces)?;
-                } else {
-                    print_workspaces(&workspaces);
+fn print_policy_explanation(explanation: &PolicyExplanation) {
+    println!("command: {}", explanation.command_type);
+    println!("actor: {}:{}", explanation.actor.kind, explanation.actor.id);
+    let Some(decision) = &explanation.decision else {
+        println!("decision: allow (no policy loaded)");
+        return;
+    };
+    let deciding = match (&decision.bundle_id, &decision.rule_id) {
+        (Some(bundle_id), Some(rule_id)) => format!("{bundle_id}/{rule_id}"),
+        (Some(bundle_id), None) => format!("{bundle_id} default"),
+        _ => "default".to_string(),
+    };
+    println!("decision: {} ({deciding})", decision.effect);
+    println!("rationale: {}", decision.rationale);
+    println!("policy: {}", decision.policy_hash);
+    if explanation.matched.is_empty() {
+        println!("matched: none");
+    } else {
+        println!("matched:");
+        for rule in &explanation.matched {
+            println!("  {}\t{}/{}", rule.effect, rule.bundle_id, rule.rule_id);
+        }
+    }
+}
+
+fn print_leases(leases: &[LeaseEntry]) {
+    if leases.is_empty() {
+        println!("no leases");
+        return;
+    }
+    for lease in leases {
+        println!(
+            "{}\t{}\t{}:{}\t{}\t{}\tused {}\t{}",
+            lease.lease_id,
+            lease.status,
+            lease.holder.kind,
+            lease.holder.id,
+            lease.scope.actions.join(","),
+            lease.expires_at,
+            lease.use_count,
+            lease.reason
+        );
+    }
+}
+
+fn print_vault_status(status: &VaultStatus) {
+    let state = match (status.initialized, status.unlocked) {
+        (false, _) => "not initialized",
+        (true, false) => "locked",
+        (true, true) => "unlocked",
+    };
+    println!("vault: {state}");
+    if let Some(kdf) = &status.kdf {
+        println!(
+            "kdf: argon2id m={}KiB t={} p={}",
+            kdf.m_cost_kib, kdf.t_cost, kdf.p_cost
+        );
+    }
+    if let Some(locks_at) = &status.locks_at {
+        println!("locks_at: {locks_at}");
+    }
+}
+
+fn print_secrets(secrets: &[SecretEntry]) {
+    if secrets.is_empty() {
+        println!("no secrets");
+        return;
+    }
+    for secret in secrets {
+        let owner = match &secret.owner.id {
+            Some(id) => format!("{}:{id}", secret.owner.scope),
+            None => secret.owner.scope.to_string(),
+        };
+        println!(
+            "{}\t{}\t{}\tv{}\t{}\t{}",
+            secret.secret_id,
+            secret.name,
+            owner,
+            secret.version,
+            secret.redacted,
+            secret.updated_at
+        );
+    }
+}
+
+fn print_pipeline_templates(templates: &[PipelineTemplateEntry]) {
+    if templates.is_empty() {
+        println!("no pipeline templates");
+        return;
+    }
+    for template in templates {
+        let stages: Vec<_> = template.stages.iter().map(|s| s.name.as_str()).collect();
+        println!(
+            "{}\t{}\tv{}\t{}",
+            template.template_id,
+            template.name,
+            template.version,
+            stages.join(" -> ")
+        );
+    }
+}
+
+fn print_pipeline_stages(view: &PipelineStageView) {
+    for lane in &view.lanes {
+        println!("{} ({})", lane.stage, lane.tasks.len());
+        for task in &lane.tasks {
+            println!("  {}\t{}\t{}", task.task_id, task.state, task.title);
+        }
+    }
+    if !view.unstaged.is_empty() {
+        println!("unstaged ({})", view.unstaged.len());
+        for task in &view.unstaged {
+            println!("  {}\t{}\t{}", task.task_id, task.state, task.title);
+        }
+    }
+}
+
+/// Reads a password or secret value from stdin, up to the first newline. Never taken as an
+/// argument, where it would leak through shell history and process listings.
+fn read_stdin_secret(what: &str) -> CliResult<String> {
+    let mut line = String::new();
+    std::io::stdin()
+        .read_line(&mut line)
+        .map_err(|err| CliError::new(ErrorCode::Unknown, format!("reading {what}: {err}")))?;
+    let value = line.strip_suffix('\n').unwrap_or(&line);
+    let value = value.strip_suffix('\r').unwrap_or(value);
+    if value.is_empty() {
+        return Err(CliError::new(
+            ErrorCode::InvalidSchema,
+            format!("{what} must be given on stdin"),
+        ));
+    }
+    Ok(value.to_string())
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> CliResult<()> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
+    println!("{json}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mp_kernel::ErrorCode;
+
+    #[test]
+    fn parse_events_watch_defaults() {
+        let cli =
+            Cli::try_parse_from(["mpctl", "events", "watch", "--workspace", "w1"]).expect("parse");
+        match cli.command {
+            Commands::Events { command } => match command {
+                EventCommands::Watch {
+                    workspace,
+                    from,
+                    transport,
+                    mpd_path,
+                    db,
+                    filter,
+                } => {
+                    assert_eq!(workspace, "w1");
+                    assert_eq!(from, 0);
+                    assert!(matches!(transport, EventTransport::Sse));
+                    assert!(mpd_path.is_none());
+                    assert!(db.is_none());
+                    assert_eq!(filter.filter(), EventFilter::default());
+                }
+            },
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn parse_events_watch_ndjson() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "events",
+            "watch",
+            "--workspace",
+            "w1",
+            "--transport",
+            "ndjson",
+            "--from",
+            "5",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Events { command } => match command {
+                EventCommands::Watch {
+                    transport, from, ..
+                } => {
+                    assert!(matches!(transport, EventTransport::Ndjson));
+                    assert_eq!(from, 5);
+                }
+            },
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn parse_events_watch_stdio() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "events",
+            "watch",
+            "--workspace",
+            "w1",
+            "--transport",
+            "stdio",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Events { command } => match command {
+                EventCommands::Watch { transport, .. } => {
+                    assert!(matches!(transport, EventTransport::Stdio));
                 }
+            },
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn parse_session_fork_mode() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "session",
+            "fork",
+            "--workspace",
+            "w1",
+            "--session",
+            "s1",
+            "--mode",
+            "planning-only",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Session {
+                command: SessionCommands::Fork { session, mode, .. },
+            } => {
+                assert_eq!(session, "s1");
+                assert_eq!(ForkMode::from(mode), ForkMode::PlanningOnly);
             }
-        },
-        Commands::Project { command } => match command {
-            ProjectCommands::Create { workspace, name } => {
-                let client = ensure_client().await?;
-                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
-                let response = client
-                    .project_create(workspace_id, name, None, None)
-                    .await?;
-                let response = ensure_command_accepted(response)?;
-                print_json(&response)?;
+            _ => panic!("unexpected command"),
+        }
+    }
+
+    #[test]
+    fn parse_session_spawn_with_blueprint_version() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "session",
+            "spawn",
+            "--workspace",
+            "w1",
+            "--blueprint",
+            "reviewer",
+            "--blueprint-version",
+            "3",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Session {
+                command:
+                    SessionCommands::Spawn {
+                        blueprint,
+                        blueprint_version,
+                        ..
+                    },
+            } => {
+                assert_eq!(blueprint.as_deref(), Some("reviewer"));
+                assert_eq!(blueprint_version, Some(3));
             }
-            ProjectCommands::List { workspace, json } => {
-                let client = ensure_client().await?;
-                let workspace_id = resolve_workspace_id(&client, &workspace).await?;
-                let projects = client.project_list(&workspace_id).await?;
-                if json {
-                    print_json(&projects)?;
-                } else {
-                    print_projects(&projects);
-                }
+            _ => panic!("unexpected command"),
+        }
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "session",
+            "spawn",
+            "--workspace",
+            "w1",
+            "--blueprint-version",
+            "3",
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn parse_hooks_test() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "hooks",
+            "test",
+            "--chain",
+            "hooks.yaml",
+            "--events",
+            "events.ndjson",
+            "--fixtures",
+            "fixtures",
+            "--fuzz",
+            "25",
+            "--report",
+            "report.json",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Hooks {
+                command: HookCommands::Test(args),
+            } => {
+                assert_eq!(args.chain, PathBuf::from("hooks.yaml"));
+                assert_eq!(args.events, Some(PathBuf::from("events.ndjson")));
+                assert_eq!(args.fuzz, 25);
+                assert_eq!(args.seed, 0);
+                assert!(!args.update_fixtures);
+                assert!(args.workspace.is_none());
             }
-        },
-        Commands::Events { command } => match command {
-            EventCommands::Watch {
-                workspace,
-                from,
-                transport,
-                mpd_path,
-                db,
-            } => match transport {
-                EventTransport::Sse => {
-                    let client = ensure_client().await?;
-                    let workspace_id = resolve_workspace_id(&client, &workspace).await?;
-                    watch_events_sse(&client, &workspace_id, from).await?;
-                }
-                EventTransport::Ndjson => {
-                    let client = ensure_client().await?;
-                    let workspace_id = resolve_workspace_id(&client, &workspace).await?;
-                    watch_events_ndjson(&client, &workspace_id, from).await?;
-                }
-                EventTransport::Stdio => {
-                    watch_events_stdio(&workspace, from, mpd_path, db).await?;
-                }
-            },
-        },
+            _ => panic!("unexpected command"),
+        }
+        // Updating needs somewhere to write the fixtures.
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "hooks",
+            "test",
+            "--chain",
+            "hooks.yaml",
+            "--update-fixtures",
+        ])
+        .is_err());
     }
 
-    Ok(())
-}
+    #[test]
+    fn parse_task_transition_and_list_filters() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "task",
+            "fail",
+            "--workspace",
+            "w1",
+            "--task",
+            "t1",
+            "--reason",
+            "flaky",
+            "--class",
+            "timeout",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Task {
+                command: TaskCommands::Fail { args, class },
+            } => {
+                assert_eq!(args.task, "t1");
+                assert_eq!(args.reason.as_deref(), Some("flaky"));
+                assert_eq!(class.map(FailureClass::from), Some(FailureClass::Timeout));
+            }
+            _ => panic!("unexpected command"),
+        }
 
-fn start_daemon() -> CliResult<()> {
-    let child = Command::new("mpd")
-        .arg("start")
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .context("failed to start mpd")?;
-    let _ = child.id();
-    Ok(())
-}
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "task",
+            "list",
+            "--workspace",
+            "w1",
+            "--state",
+            "blocked-by-approval",
+            "--json",
+        ])
+        .expect("parse");
+        assert!(wants_json(&cli));
+        match cli.command {
+            Commands::Task {
+                command: TaskCommands::List { state, .. },
+            } => {
+                assert_eq!(
+                    state.map(TaskState::from),
+                    Some(TaskState::BlockedByApproval)
+                );
+            }
+            _ => panic!("unexpected command"),
+        }
+    }
 
-async fn daemon_status(json: bool) -> CliResult<()> {
-    match try_client().await {
-        Ok(client) => {
-            let resp = client.ping().await?;
-            if json {
-                print_json(&resp)?;
-            } else {
-                println!(
-                    "daemon: {} (version {} @ {})",
-                    resp.status, resp.version, resp.timestamp
+    #[test]
+    fn parse_task_create_retry_policy() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "task",
+            "create",
+            "--workspace",
+            "w1",
+            "--title",
+            "deploy",
+            "--timeout-ms",
+            "60000",
+            "--max-attempts",
+            "4",
+            "--backoff-ms",
+            "500",
+            "--backoff-max-ms",
+            "8000",
+            "--jitter",
+            "full",
+            "--retry-on",
+            "transient,crash",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Task {
+                command:
+                    TaskCommands::Create {
+                        timeout_ms, retry, ..
+                    },
+            } => {
+                assert_eq!(timeout_ms, Some(60000));
+                let policy = retry.policy().expect("policy");
+                assert_eq!(policy.max_attempts, 4);
+                assert_eq!(
+                    policy.backoff,
+                    Backoff::Exponential {
+                        initial_ms: 500,
+                        max_ms: 8000,
+                        multiplier: 2
+                    }
+                );
+                assert_eq!(policy.jitter, JitterMode::Full);
+                assert_eq!(
+                    policy.retry_on,
+                    vec![FailureClass::Transient, FailureClass::Crash]
                 );
             }
+            _ => panic!("unexpected command"),
         }
-        Err(err) => {
-            if json {
-                print_json(&serde_json::json!({"status": "down", "error": err.to_string()}))?;
-            } else {
-                println!("daemon: down ({err})");
+    }
+
+    #[test]
+    fn parse_pipeline_define_and_task_stage() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "pipeline",
+            "define",
+            "--workspace",
+            "w1",
+            "--name",
+            "default",
+            "--stage",
+            "plan",
+            "--stage",
+            "build",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Pipeline {
+                command: PipelineCommands::Define { name, stages, .. },
+            } => {
+                assert_eq!(name, "default");
+                assert_eq!(stages, vec!["plan", "build"]);
+            }
+            _ => panic!("unexpected command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "pipeline",
+            "stages",
+            "--workspace",
+            "w1",
+            "--project",
+            "p1",
+            "--json",
+        ])
+        .expect("parse");
+        assert!(wants_json(&cli));
+
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "task",
+            "stage",
+            "--workspace",
+            "w1",
+            "--task",
+            "t1",
+            "--to",
+            "build",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Task {
+                command: TaskCommands::Stage { task, to, .. },
+            } => {
+                assert_eq!(task, "t1");
+                assert_eq!(to, "build");
             }
+            _ => panic!("unexpected command"),
         }
     }
-    Ok(())
-}
 
-async fn ensure_client() -> CliResult<Client> {
-    if let Ok(client) = try_client().await {
-        return Ok(client);
+    #[test]
+    fn parse_gate_define_and_approve() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "gate",
+            "define",
+            "--workspace",
+            "w1",
+            "--command",
+            "stage.transition",
+            "--subject",
+            "t1",
+            "--approvals",
+            "2",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Gate {
+                command:
+                    GateCommands::Define {
+                        subject_kind,
+                        kind,
+                        approvals,
+                        ..
+                    },
+            } => {
+                assert_eq!(subject_kind.as_str(), "task");
+                assert_eq!(GateKind::from(kind), GateKind::ManualApproval);
+                assert_eq!(approvals, Some(2));
+            }
+            _ => panic!("unexpected command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "gate",
+            "approve",
+            "--workspace",
+            "w1",
+            "--gate",
+            "g1",
+            "--approver",
+            "release-bot",
+            "--approver-kind",
+            "policy-bot",
+            "--evidence",
+            "blake3:abc",
+            "--evidence",
+            "blake3:def",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Gate {
+                command: GateCommands::Approve(args),
+            } => {
+                assert_eq!(
+                    ApproverKind::from(args.approver_kind),
+                    ApproverKind::PolicyBot
+                );
+                assert_eq!(args.evidence.len(), 2);
+            }
+            _ => panic!("unexpected command"),
+        }
     }
 
-    start_daemon()?;
-    for _ in 0..20 {
-        sleep(Duration::from_millis(150)).await;
-        if let Ok(client) = try_client().await {
-            return Ok(client);
+    #[test]
+    fn parse_project_archive_and_list_all() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "project",
+            "archive",
+            "--workspace",
+            "w1",
+            "--project",
+            "core",
+            "--reason",
+            "typo",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Project {
+                command: ProjectCommands::Archive(args),
+            } => {
+                assert_eq!(args.project, "core");
+                assert_eq!(args.reason.as_deref(), Some("typo"));
+            }
+            _ => panic!("unexpected command"),
+        }
+
+        let cli = Cli::try_parse_from(["mpctl", "workspace", "list", "--all"]).expect("parse");
+        match cli.command {
+            Commands::Workspace {
+                command: WorkspaceCommands::List { all, json },
+            } => {
+                assert!(all);
+                assert!(!json);
+            }
+            _ => panic!("unexpected command"),
         }
     }
-    Err(CliError::new(ErrorCode::Unknown, "daemon failed to start"))
-}
 
-async fn try_client() -> CliResult<Client> {
-    let runtime_dir = Client::default_runtime_dir();
-    let client = Client::from_runtime_dir(&runtime_dir)?;
-    client.ping().await?;
-    Ok(client)
-}
+    #[test]
+    fn parse_worktree_lock() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "worktree",
+            "lock",
+            "--workspace",
+            "w1",
+            "--worktree",
+            "wt1",
+            "--session",
+            "s1",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Worktree {
+                command: WorktreeCommands::Lock(args),
+            } => {
+                assert_eq!(args.worktree, "wt1");
+                assert_eq!(args.session, "s1");
+            }
+            _ => panic!("unexpected command"),
+        }
+    }
 
-async fn resolve_workspace_id(client: &Client, selector: &str) -> CliResult<String> {
-    let workspaces = client.workspace_list().await?;
-    if workspaces.iter().any(|ws| ws.workspace_id == selector) {
-        return Ok(selector.to_string());
+    #[test]
+    fn parse_board_move_with_negative_position() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "board",
+            "move",
+            "--workspace",
+            "w1",
+            "--node",
+            "task:t1",
+            "--x",
+            "-40",
+            "--y",
+            "12.5",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Board {
+                command:
+                    BoardCommands::Move {
+                        node, x, y, group, ..
+                    },
+            } => {
+                assert_eq!(node, "task:t1");
+                assert_eq!((x, y), (-40.0, 12.5));
+                assert!(group.is_none());
+            }
+            _ => panic!("unexpected command"),
+        }
+        let partial = Cli::try_parse_from([
+            "mpctl",
+            "board",
+            "group",
+            "--workspace",
+            "w1",
+            "--label",
+            "Review",
+            "--x",
+            "10",
+        ]);
+        assert!(partial.is_err());
     }
-    let mut matches = workspaces
-        .iter()
-        .filter(|ws| ws.name == selector || ws.root_path == selector)
-        .collect::<Vec<_>>();
-    if matches.len() == 1 {
-        return Ok(matches.pop().unwrap().workspace_id.clone());
+
+    #[test]
+    fn parse_capsule_write_and_list() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "capsule",
+            "write",
+            "--workspace",
+            "w1",
+            "--subject-kind",
+            "session",
+            "--subject",
+            "s1",
+            "--kind",
+            "artifact-manifest",
+            "--content",
+            "built target/release/mpd",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Capsule {
+                command:
+                    CapsuleCommands::Write {
+                        subject_kind, kind, ..
+                    },
+            } => {
+                assert_eq!(subject_kind, "session");
+                assert_eq!(CapsuleKind::from(kind), CapsuleKind::ArtifactManifest);
+            }
+            _ => panic!("unexpected command"),
+        }
+        let partial = Cli::try_parse_from([
+            "mpctl",
+            "capsule",
+            "list",
+            "--workspace",
+            "w1",
+            "--subject",
+            "s1",
+        ]);
+        assert!(partial.is_err());
     }
-    Err(CliError::new(
-        ErrorCode::NotFound,
-        format!("workspace not found or ambiguous: {selector}"),
-    ))
-}
 
-async fn watch_events_sse(client: &Client, workspace_id: &str, from: i64) -> CliResult<()> {
-    let resp = client.events_stream(workspace_id, from).await?;
-    let mut stream = resp.bytes_stream();
-    let mut buffer = String::new();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
-        while let Some(pos) = buffer.find('\n') {
-            let line = buffer[..pos].trim_end().to_string();
-            buffer = buffer[pos + 1..].to_string();
-            if line.starts_with("data:") {
-                let data = line.trim_start_matches("data:").trim();
-                if !data.is_empty() {
-                    println!("{data}");
-                }
+    #[test]
+    fn parse_blueprint_show_with_version() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "blueprint",
+            "show",
+            "--workspace",
+            "w1",
+            "reviewer",
+            "--version",
+            "2",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Blueprint {
+                command: BlueprintCommands::Show { id, version, .. },
+            } => {
+                assert_eq!(id, "reviewer");
+                assert_eq!(version, Some(2));
             }
+            _ => panic!("unexpected command"),
         }
     }
-    Ok(())
-}
 
-async fn watch_events_ndjson(client: &Client, workspace_id: &str, from: i64) -> CliResult<()> {
-    let resp = client.events_stream_ndjson(workspace_id, from).await?;
-    let mut stream = resp.bytes_stream();
-    let mut buffer = String::new();
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
-        let text = String::from_utf8_lossy(&chunk);
-        buffer.push_str(&text);
-        while let Some(pos) = buffer.find('\n') {
-            let line = buffer[..pos].trim_end().to_string();
-            buffer = buffer[pos + 1..].to_string();
-            if !line.is_empty() {
-                println!("{line}");
+    #[test]
+    fn parse_skill_list_and_approve() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "skill",
+            "list",
+            "--workspace",
+            "w1",
+            "--trust",
+            "proposed",
+            "--json",
+        ])
+        .expect("parse");
+        assert!(wants_json(&cli));
+        match cli.command {
+            Commands::Skill {
+                command: SkillCommands::List { trust, .. },
+            } => assert_eq!(trust.map(SkillTrust::from), Some(SkillTrust::Proposed)),
+            _ => panic!("unexpected command"),
+        }
+
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "skill",
+            "approve",
+            "--workspace",
+            "w1",
+            "pdf-tools",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Skill {
+                command: SkillCommands::Approve { id, hash, .. },
+            } => {
+                assert_eq!(id, "pdf-tools");
+                assert!(hash.is_none());
             }
+            _ => panic!("unexpected command"),
         }
     }
-    Ok(())
-}
-
-async fn watch_events_stdio(
-    workspace: &str,
-    from: i64,
-    mpd_path: Option<PathBuf>,
-    db: Option<PathBuf>,
-) -> CliResult<()> {
-    let mpd_path = mpd_path.unwrap_or_else(|| PathBuf::from("mpd"));
-    let mut client = StdioClient::spawn(&mpd_path, db, StdioAuthMode::None).await?;
-    let result = async {
-        let workspace_id = resolve_workspace_id_stdio(&mut client, workspace).await?;
-        client.subscribe_events(&workspace_id, from).await?;
 
-        loop {
-            tokio::select! {
-                event = client.next_event() => {
-                    let event = event?;
-                    let json = serde_json::to_string(&event)
-                        .map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
-                    println!("{json}");
-                }
-                _ = tokio::signal::ctrl_c() => {
-                    break;
-                }
+    #[test]
+    fn parse_policy_explain_actor() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "policy",
+            "explain",
+            "task.create",
+            "--actor",
+            "session:s_1",
+            "--payload",
+            r#"{"project_id":"p1"}"#,
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Policy {
+                command:
+                    PolicyCommands::Explain {
+                        command_type,
+                        actor,
+                        payload,
+                        ..
+                    },
+            } => {
+                assert_eq!(command_type, "task.create");
+                assert_eq!((actor.kind.as_str(), actor.id.as_str()), ("session", "s_1"));
+                assert_eq!(payload.expect("payload")["project_id"], "p1");
             }
+            _ => panic!("unexpected command"),
         }
-        Ok(())
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "policy",
+            "explain",
+            "task.create",
+            "--actor",
+            "alice",
+        ])
+        .is_err());
     }
-    .await;
 
-    let shutdown_result = client.shutdown().await;
-    if result.is_err() {
-        let _ = shutdown_result;
-        return result;
+    #[test]
+    fn parse_lease_mint_scope() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "lease",
+            "mint",
+            "--workspace",
+            "w1",
+            "--holder",
+            "session:s_1",
+            "--action",
+            "tool.request",
+            "--attribute",
+            "payload.tool_id=net.fetch",
+            "--attribute",
+            "payload.tool_id=net.post",
+            "--ttl-ms",
+            "3600000",
+            "--reason",
+            "network exception",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Lease {
+                command:
+                    LeaseCommands::Mint {
+                        holder,
+                        actions,
+                        attributes,
+                        ttl_ms,
+                        ..
+                    },
+            } => {
+                assert_eq!(holder.kind, "session");
+                assert_eq!(actions, ["tool.request"]);
+                assert_eq!(attributes.len(), 2);
+                assert_eq!(attributes[1].1, "net.post");
+                assert_eq!(ttl_ms, 3_600_000);
+            }
+            _ => panic!("unexpected command"),
+        }
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "lease",
+            "mint",
+            "--workspace",
+            "w1",
+            "--holder",
+            "session:s_1",
+            "--ttl-ms",
+            "1000",
+            "--reason",
+            "no actions",
+        ])
+        .is_err());
     }
-    shutdown_result?;
-    result
-}
 
-async fn resolve_workspace_id_stdio(client: &mut StdioClient, selector: &str) -> CliResult<String> {
-    let workspaces = client.list_workspaces().await?;
-    if workspaces.iter().any(|ws| ws.workspace_id == selector) {
-        return Ok(selector.to_string());
-    }
-    let mut matches = workspaces
-        .iter()
-        .filter(|ws| ws.name == selector || ws.root_path == selector)
-        .collect::<Vec<_>>();
-    if matches.len() == 1 {
-        return Ok(matches.pop().unwrap().workspace_id.clone());
+    #[test]
+    fn parse_secret_set_takes_no_value_argument() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "secret",
+            "set",
+            "--workspace",
+            "w1",
+            "OPENAI_API_KEY",
+            "--scope",
+            "project",
+            "--owner",
+            "p_1",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Secret {
+                command:
+                    SecretCommands::Set {
+                        name, scope, owner, ..
+                    },
+            } => {
+                assert_eq!(name, "OPENAI_API_KEY");
+                assert_eq!(SecretScope::from(scope), SecretScope::Project);
+                assert_eq!(owner.as_deref(), Some("p_1"));
+            }
+            _ => panic!("unexpected command"),
+        }
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "secret",
+            "set",
+            "--workspace",
+            "w1",
+            "OPENAI_API_KEY",
+            "sk-live-123",
+        ])
+        .is_err());
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "vault",
+            "unlock",
+            "--workspace",
+            "w1",
+            "--idle-timeout-ms",
+            "60000",
+        ])
+        .is_ok());
     }
-    Err(CliError::new(
-        ErrorCode::NotFound,
-        format!("workspace not found or ambiguous: {selector}"),
-    ))
-}
 
-fn print_workspaces(workspaces: &[WorkspaceListEntry]) {
-    if workspaces.is_empty() {
-        println!("no workspaces");
-        return;
-    }
-    for workspace in workspaces {
-        println!(
-            "{}\t{}\t{}",
-            workspace.workspace_id, workspace.name, workspace.root_path
-        );
+    #[test]
+    fn parse_secret_exec_command_after_separator() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "secret",
+            "exec",
+            "--workspace",
+            "w1",
+            "--stdin",
+            "API_KEY",
+            "--file",
+            "KUBECONFIG",
+            "--",
+            "deploy",
+            "--kubeconfig",
+            "{secret:KUBECONFIG}",
+        ])
+        .expect("parse exec");
+        match cli.command {
+            Commands::Secret {
+                command:
+                    SecretCommands::Exec {
+                        stdin,
+                        files,
+                        command,
+                        ..
+                    },
+            } => {
+                assert_eq!(stdin.as_deref(), Some("API_KEY"));
+                assert_eq!(files, ["KUBECONFIG"]);
+                assert_eq!(command, ["deploy", "--kubeconfig", "{secret:KUBECONFIG}"]);
+            }
+            _ => panic!("unexpected command"),
+        }
+        assert!(Cli::try_parse_from([
+            "mpctl",
+            "secret",
+            "exec",
+            "--workspace",
+            "w1",
+            "--stdin",
+            "API_KEY",
+        ])
+        .is_err());
     }
-}
 
-fn print_projects(projects: &[ProjectListEntry]) {
-    if projects.is_empty() {
-        println!("no projects");
-        return;
-    }
-    for project in projects {
-        println!("{}\t{}", project.project_id, project.name);
+    #[test]
+    fn parse_tool_request_and_complete() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "tool",
+            "request",
+            "--workspace",
+            "w1",
+            "--tool",
+            "acme.add",
+            "--input",
+            r#"{"a": 1, "b": 2}"#,
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Tool {
+                command: ToolCommands::Request { tool, input, .. },
+            } => {
+                assert_eq!(tool, "acme.add");
+                assert_eq!(input, serde_json::json!({"a": 1, "b": 2}));
+            }
+            _ => panic!("unexpected command"),
+        }
+        let complete = |extra: &[&str]| {
+            let mut args = vec![
+                "mpctl",
+                "tool",
+                "complete",
+                "--workspace",
+                "w1",
+                "--request",
+                "r1",
+            ];
+            args.extend_from_slice(extra);
+            Cli::try_parse_from(args)
+        };
+        assert!(complete(&[]).is_err());
+        assert!(complete(&[
+            "--output",
+            "{}",
+            "--error-code",
+            "x",
+            "--error-message",
+            "y"
+        ])
+        .is_err());
+        assert!(complete(&["--error-code", "timeout"]).is_err());
+        assert!(complete(&["--error-code", "timeout", "--error-message", "slow"]).is_ok());
     }
-}
-
-fn print_json<T: serde::Serialize>(value: &T) -> CliResult<()> {
-    let json = serde_json::to_string_pretty(value)
-        .map_err(|err| CliError::new(ErrorCode::Unknown, err.to_string()))?;
-    println!("{json}");
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use mp_kernel::ErrorCode;
 
     #[test]
-    fn parse_events_watch_defaults() {
-        let cli =
-            Cli::try_parse_from(["mpctl", "events", "watch", "--workspace", "w1"]).expect("parse");
+    fn parse_tool_search_and_run() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "tool",
+            "search",
+            "web search",
+            "--side-effect",
+            "network",
+            "--kind",
+            "tool",
+            "--json",
+        ])
+        .expect("parse");
         match cli.command {
-            Commands::Events { command } => match command {
-                EventCommands::Watch {
-                    workspace,
-                    from,
-                    transport,
-                    mpd_path,
-                    db,
-                } => {
-                    assert_eq!(workspace, "w1");
-                    assert_eq!(from, 0);
-                    assert!(matches!(transport, EventTransport::Sse));
-                    assert!(mpd_path.is_none());
-                    assert!(db.is_none());
-                }
-            },
+            Commands::Tool {
+                command:
+                    ToolCommands::Search {
+                        query,
+                        side_effect,
+                        kind,
+                        json,
+                        ..
+                    },
+            } => {
+                assert_eq!(query, "web search");
+                assert_eq!(side_effect.map(SideEffect::from), Some(SideEffect::Network));
+                assert_eq!(kind.map(CatalogKind::from), Some(CatalogKind::Tool));
+                assert!(json);
+            }
             _ => panic!("unexpected command"),
         }
+        let run = |extra: &[&str]| {
+            let mut args = vec!["mpctl", "tool", "run", "task.create", "--input", "{}"];
+            args.extend_from_slice(extra);
+            Cli::try_parse_from(args)
+        };
+        assert!(run(&[]).is_ok());
+        assert!(run(&["--session", "s1"]).is_err());
+        assert!(run(&["--workspace", "w1", "--session", "s1"]).is_ok());
     }
 
     #[test]
-    fn parse_events_watch_ndjson() {
+    fn parse_message_send_and_filtered_watch() {
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "message",
+            "send",
+            "--workspace",
+            "w1",
+            "--from",
+            "s2",
+            "--scope",
+            "parent",
+            "--type",
+            "status_capsule",
+            "--body",
+            r#"{"text":"tests green"}"#,
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Message {
+                command:
+                    MessageCommands::Send {
+                        scope,
+                        message_type,
+                        body,
+                        ..
+                    },
+            } => {
+                assert_eq!(MessageScope::from(scope), MessageScope::Parent);
+                assert_eq!(message_type, "status_capsule");
+                assert_eq!(body["text"], "tests green");
+            }
+            _ => panic!("unexpected command"),
+        }
+        let invalid = Cli::try_parse_from([
+            "mpctl",
+            "message",
+            "send",
+            "--workspace",
+            "w1",
+            "--from",
+            "s2",
+            "--scope",
+            "parent",
+            "--type",
+            "status_capsule",
+            "--body",
+            "not json",
+        ]);
+        assert!(invalid.is_err());
+
         let cli = Cli::try_parse_from([
             "mpctl",
             "events",
             "watch",
             "--workspace",
             "w1",
-            "--transport",
-            "ndjson",
-            "--from",
-            "5",
+            "--subject-kind",
+            "session",
+            "--subject",
+            "s1",
+            "--event-type",
+            "message.*",
         ])
         .expect("parse");
         match cli.command {
-            Commands::Events { command } => match command {
-                EventCommands::Watch {
-                    transport, from, ..
-                } => {
-                    assert!(matches!(transport, EventTransport::Ndjson));
-                    assert_eq!(from, 5);
-                }
-            },
+            Commands::Events {
+                command: EventCommands::Watch { filter, .. },
+            } => {
+                let filter = filter.filter();
+                assert_eq!(filter.subject_id.as_deref(), Some("s1"));
+                assert_eq!(filter.event_type.as_deref(), Some("message.*"));
+            }
             _ => panic!("unexpected command"),
         }
     }
 
     #[test]
-    fn parse_events_watch_stdio() {
+    fn parse_artifact_put_and_get() {
         let cli = Cli::try_parse_from([
             "mpctl",
-            "events",
-            "watch",
+            "artifact",
+            "put",
+            "target/build.log",
             "--workspace",
             "w1",
-            "--transport",
-            "stdio",
+            "--producer-kind",
+            "task",
+            "--producer",
+            "t1",
         ])
         .expect("parse");
         match cli.command {
-            Commands::Events { command } => match command {
-                EventCommands::Watch { transport, .. } => {
-                    assert!(matches!(transport, EventTransport::Stdio));
-                }
-            },
+            Commands::Artifact {
+                command:
+                    ArtifactCommands::Put {
+                        file,
+                        producer_kind,
+                        ..
+                    },
+            } => {
+                assert_eq!(file, PathBuf::from("target/build.log"));
+                assert_eq!(producer_kind.as_deref(), Some("task"));
+            }
+            _ => panic!("unexpected command"),
+        }
+        let unrecorded = Cli::try_parse_from(["mpctl", "artifact", "put", "a.log", "--name", "a"]);
+        assert!(unrecorded.is_err());
+
+        let cli = Cli::try_parse_from([
+            "mpctl",
+            "artifact",
+            "get",
+            "blake3:00",
+            "--range",
+            "100-",
+            "-o",
+            "out.log",
+        ])
+        .expect("parse");
+        match cli.command {
+            Commands::Artifact {
+                command: ArtifactCommands::Get { range, output, .. },
+            } => {
+                assert_eq!(
+                    range,
+                    Some(ArtifactRange {
+                        start: 100,
+                        end: None
+                    })
+                );
+                assert_eq!(output, Some(PathBuf::from("out.log")));
+            }
             _ => panic!("unexpected command"),
         }
     }
@@ -607,6 +4556,8 @@ mod tests {
         assert_eq!(exit_code_for_error_code(ErrorCode::Unauthorized), 4);
         assert_eq!(exit_code_for_error_code(ErrorCode::NotFound), 5);
         assert_eq!(exit_code_for_error_code(ErrorCode::PolicyDenied), 6);
+        assert_eq!(exit_code_for_error_code(ErrorCode::LockConflict), 7);
+        assert_eq!(exit_code_for_error_code(ErrorCode::BudgetExceeded), 8);
         assert_eq!(exit_code_for_error_code(ErrorCode::Unknown), 1);
         assert_eq!(exit_code_for_error_code(ErrorCode::Internal), 1);
     }
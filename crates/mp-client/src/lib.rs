@@ -1,14 +1,29 @@
 use anyhow::Context;
 use mp_dirs::runtime_dir as default_runtime_dir_impl;
 use mp_kernel::{
-    DaemonPingResponse, ErrorCode, ProjectCreatePayload, ProjectListEntry, RuntimeInfo,
-    WorkspaceCreatePayload, WorkspaceListEntry,
+    Actor, ArtifactPutPayload, ArtifactUploadResponse, BlueprintEntry, BlueprintSyncPayload,
+    BoardCommentAddPayload, BoardGroupCreatePayload, BoardNodeMovePayload, BoardSnapshot,
+    CapsuleEntry, CapsuleKind, CapsuleWritePayload, CatalogKind, DaemonPingResponse, ErrorCode,
+    GateDecisionPayload, GateDefinePayload, GateEntry, LeaseEntry, LeaseMintPayload,
+    LeaseRevokePayload, LeaseStatus, MessageSendPayload, PipelineBindPayload, PipelineStageView,
+    PipelineTemplateDefinePayload, PipelineTemplateEntry, PolicyExplainRequest, PolicyExplanation,
+    ProjectCreatePayload, ProjectLifecyclePayload, ProjectListEntry, ProjectRenamePayload,
+    RuntimeInfo, SecretContext, SecretDeletePayload, SecretEntry, SecretExecPayload,
+    SecretRotatePayload, SecretSetPayload, SessionForkPayload, SessionListEntry,
+    SessionSpawnPayload, SideEffect, SkillApprovePayload, SkillEntry, SkillSyncPayload, SkillTrust,
+    SkillVerifyReport, StageTransitionPayload, TaskCreatePayload, TaskListEntry, TaskState,
+    TaskTransitionPayload, ToolCallEntry, ToolCallStatus, ToolCompletePayload, ToolLoadResponse,
+    ToolRequestPayload, ToolRunRequest, ToolSearchResponse, VaultInitPayload, VaultLockPayload,
+    VaultStatus, VaultUnlockPayload, WorkspaceCreatePayload, WorkspaceLifecyclePayload,
+    WorkspaceListEntry, WorkspaceRenamePayload, WorktreeEntry, WorktreeRegisterPayload,
+    WorktreeSessionPayload,
 };
 use mp_protocol::{
-    CommandEnvelope, ErrorResponse, StdioAuthPayload, StdioErrorPayload, StdioEventsSubscribe,
-    StdioFrame, StdioProjectsQuery, SubmitCommandResponse,
+    CommandEnvelope, ErrorResponse, EventFilter, StdioAuthPayload, StdioBoardQuery,
+    StdioErrorPayload, StdioEventsSubscribe, StdioFrame, StdioProjectsQuery, StdioSessionsQuery,
+    StdioTasksQuery, StdioWorkspacesQuery, SubmitCommandResponse, ToolRunResponse,
 };
-use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, RANGE};
 use reqwest::{Client as HttpClient, Response, StatusCode};
 use serde::de::DeserializeOwned;
 use serde_json::Value;
@@ -16,50 +31,1037 @@ use std::collections::VecDeque;
 use std::fmt;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
 use tokio::process::{Child, Command as TokioCommand};
 use url::Url;
 use uuid::Uuid;
 
+/// Inclusive byte range for [`Client::artifact_get`]; without `end` it reads to the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArtifactRange {
+    pub start: u64,
+    pub end: Option<u64>,
+}
+
+impl ArtifactRange {
+    fn header_value(&self) -> String {
+        match self.end {
+            Some(end) => format!("bytes={}-{end}", self.start),
+            None => format!("bytes={}-", self.start),
+        }
+    }
+}
+
+impl std::str::FromStr for ArtifactRange {
+    type Err = String;
+
+    /// Parses `START-END` or `START-`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid range {value:?}; expected START-END or START-");
+        let (start, end) = value.split_once('-').ok_or_else(invalid)?;
+        let start = start.parse().map_err(|_| invalid())?;
+        let end = match end {
+            "" => None,
+            end => Some(end.parse().map_err(|_| invalid())?),
+        };
+        if end.is_some_and(|end| end < start) {
+            return Err(invalid());
+        }
+        Ok(Self { start, end })
+    }
+}
+
 pub struct Client {
     base_url: Url,
     token: String,
     http: HttpClient,
+    actor: Option<Actor>,
+    lease_id: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientError {
+    pub error: ErrorResponse,
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.error.code, self.error.message)
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct ClientError {
-    pub error: ErrorResponse,
-}
+impl std::error::Error for ClientError {}
+
+impl Client {
+    pub fn from_runtime_dir(runtime_dir: &Path) -> anyhow::Result<Self> {
+        let path = runtime_dir.join("daemon.json");
+        let data = std::fs::read(&path)
+            .with_context(|| format!("failed to read runtime file at {}", path.display()))?;
+        let info: RuntimeInfo = serde_json::from_slice(&data)?;
+        let base_url = Url::parse(&info.addr)?;
+        Ok(Self {
+            base_url,
+            token: info.token,
+            http: HttpClient::new(),
+            actor: None,
+            lease_id: None,
+        })
+    }
+
+    /// Submits commands as `actor` instead of the daemon's `system` actor.
+    pub fn with_actor(mut self, actor: Actor) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    /// Presents capability lease `lease_id` with every command this client submits.
+    pub fn with_lease(mut self, lease_id: String) -> Self {
+        self.lease_id = Some(lease_id);
+        self
+    }
+
+    pub fn default_runtime_dir() -> PathBuf {
+        default_runtime_dir_impl()
+    }
+
+    pub async fn ping(&self) -> anyhow::Result<DaemonPingResponse> {
+        let url = self.base_url.join("/v1/daemon/ping")?;
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn workspace_create(
+        &self,
+        name: String,
+        path: Option<String>,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        let payload = WorkspaceCreatePayload { name, path };
+        self.submit_command(
+            "workspace.create",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn project_create(
+        &self,
+        workspace_id: String,
+        name: String,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        let payload = ProjectCreatePayload { workspace_id, name };
+        self.submit_command(
+            "project.create",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn workspace_rename(
+        &self,
+        payload: WorkspaceRenamePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "workspace.rename",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits `workspace.archive` or `workspace.restore`.
+    pub async fn workspace_lifecycle(
+        &self,
+        command_type: &str,
+        payload: WorkspaceLifecyclePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            command_type,
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn project_rename(
+        &self,
+        payload: ProjectRenamePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "project.rename",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits `project.archive` or `project.restore`.
+    pub async fn project_lifecycle(
+        &self,
+        command_type: &str,
+        payload: ProjectLifecyclePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            command_type,
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn session_spawn(
+        &self,
+        payload: SessionSpawnPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "session.spawn",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn session_fork(
+        &self,
+        payload: SessionForkPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "session.fork",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn task_create(
+        &self,
+        payload: TaskCreatePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "task.create",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits a lifecycle command such as `task.start` or `task.cancel`.
+    pub async fn task_transition(
+        &self,
+        command_type: &str,
+        payload: TaskTransitionPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            command_type,
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn pipeline_template_define(
+        &self,
+        payload: PipelineTemplateDefinePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "pipeline.template.define",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn pipeline_bind(
+        &self,
+        payload: PipelineBindPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "pipeline.bind",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn stage_transition(
+        &self,
+        payload: StageTransitionPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "stage.transition",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn gate_define(
+        &self,
+        payload: GateDefinePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "gate.define",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits `gate.approve` or `gate.reject`.
+    pub async fn gate_decide(
+        &self,
+        command_type: &str,
+        payload: GateDecisionPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            command_type,
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn worktree_register(
+        &self,
+        payload: WorktreeRegisterPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "worktree.register",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits `worktree.attach`, `worktree.detach`, `worktree.lock.acquire` or `worktree.lock.release`.
+    pub async fn worktree_command(
+        &self,
+        command_type: &str,
+        payload: WorktreeSessionPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            command_type,
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn board_group_create(
+        &self,
+        payload: BoardGroupCreatePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "board.group.create",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn board_comment_add(
+        &self,
+        payload: BoardCommentAddPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "board.comment.add",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn board_node_move(
+        &self,
+        payload: BoardNodeMovePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "board.node.move",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn capsule_write(
+        &self,
+        payload: CapsuleWritePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "capsule.write",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn blueprint_sync(
+        &self,
+        payload: BlueprintSyncPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "blueprint.sync",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    /// Submits `artifact.put`, recording inline content or a blob uploaded by [`Self::artifact_put`].
+    pub async fn artifact_record(
+        &self,
+        payload: ArtifactPutPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "artifact.put",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn message_send(
+        &self,
+        payload: MessageSendPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "message.send",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn skill_sync(
+        &self,
+        payload: SkillSyncPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "skill.sync",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn skill_approve(
+        &self,
+        payload: SkillApprovePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "skill.approve",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn lease_mint(
+        &self,
+        payload: LeaseMintPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "lease.mint",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn lease_revoke(
+        &self,
+        payload: LeaseRevokePayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "lease.revoke",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    /// Runs a process on the daemon's host with the named secrets injected; blocks until it
+    /// exits or times out, and answers with `process.exited` among the events.
+    pub async fn secret_exec(
+        &self,
+        payload: SecretExecPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "secret.exec",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn vault_init(
+        &self,
+        payload: VaultInitPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "vault.init",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn vault_unlock(
+        &self,
+        payload: VaultUnlockPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "vault.unlock",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn vault_lock(
+        &self,
+        payload: VaultLockPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "vault.lock",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn secret_set(
+        &self,
+        payload: SecretSetPayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "secret.set",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn secret_rotate(
+        &self,
+        payload: SecretRotatePayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "secret.rotate",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn secret_delete(
+        &self,
+        payload: SecretDeletePayload,
+        idempotency_key: Option<String>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "secret.delete",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            None,
+        )
+        .await
+    }
+
+    pub async fn tool_request(
+        &self,
+        payload: ToolRequestPayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "tool.request",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn tool_complete(
+        &self,
+        payload: ToolCompletePayload,
+        idempotency_key: Option<String>,
+        expected_version: Option<i64>,
+    ) -> anyhow::Result<SubmitCommandResponse> {
+        self.submit_command(
+            "tool.complete",
+            1,
+            serde_json::to_value(payload)?,
+            idempotency_key,
+            expected_version,
+        )
+        .await
+    }
+
+    pub async fn workspace_list(
+        &self,
+        include_archived: bool,
+    ) -> anyhow::Result<Vec<WorkspaceListEntry>> {
+        let mut url = self.base_url.join("/v1/workspaces")?;
+        if include_archived {
+            url.query_pairs_mut()
+                .append_pair("include_archived", "true");
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn project_list(
+        &self,
+        workspace_id: &str,
+        include_archived: bool,
+    ) -> anyhow::Result<Vec<ProjectListEntry>> {
+        let mut url = self
+            .base_url
+            .join(&format!("/v1/projects?workspace_id={workspace_id}"))?;
+        if include_archived {
+            url.query_pairs_mut()
+                .append_pair("include_archived", "true");
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Lists sessions in lineage order, optionally restricted to the tree under `root_session_id`.
+    pub async fn session_list(
+        &self,
+        workspace_id: &str,
+        root_session_id: Option<&str>,
+    ) -> anyhow::Result<Vec<SessionListEntry>> {
+        let mut url = self.base_url.join("/v1/sessions")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(root_session_id) = root_session_id {
+            url.query_pairs_mut()
+                .append_pair("root_session_id", root_session_id);
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn task_list(
+        &self,
+        workspace_id: &str,
+        project_id: Option<&str>,
+        state: Option<TaskState>,
+    ) -> anyhow::Result<Vec<TaskListEntry>> {
+        let mut url = self.base_url.join("/v1/tasks")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(project_id) = project_id {
+            url.query_pairs_mut().append_pair("project_id", project_id);
+        }
+        if let Some(state) = state {
+            url.query_pairs_mut().append_pair("state", state.as_str());
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn pipeline_template_list(
+        &self,
+        workspace_id: &str,
+    ) -> anyhow::Result<Vec<PipelineTemplateEntry>> {
+        let mut url = self.base_url.join("/v1/pipelines/templates")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn gate_list(
+        &self,
+        workspace_id: &str,
+        command_type: Option<&str>,
+    ) -> anyhow::Result<Vec<GateEntry>> {
+        let mut url = self.base_url.join("/v1/gates")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(command_type) = command_type {
+            url.query_pairs_mut()
+                .append_pair("command_type", command_type);
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn worktree_list(&self, workspace_id: &str) -> anyhow::Result<Vec<WorktreeEntry>> {
+        let mut url = self.base_url.join("/v1/worktrees")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn board(&self, workspace_id: &str) -> anyhow::Result<BoardSnapshot> {
+        let mut url = self.base_url.join("/v1/boards")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Latest capsule per subject and kind unless `all_versions` is set.
+    pub async fn capsule_list(
+        &self,
+        workspace_id: &str,
+        subject: Option<(&str, &str)>,
+        kind: Option<CapsuleKind>,
+        all_versions: bool,
+    ) -> anyhow::Result<Vec<CapsuleEntry>> {
+        let mut url = self.base_url.join("/v1/capsules")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some((subject_kind, subject_id)) = subject {
+            url.query_pairs_mut()
+                .append_pair("subject_kind", subject_kind)
+                .append_pair("subject_id", subject_id);
+        }
+        if let Some(kind) = kind {
+            url.query_pairs_mut().append_pair("kind", kind.as_str());
+        }
+        if all_versions {
+            url.query_pairs_mut().append_pair("all_versions", "true");
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Latest version per blueprint unless `all_versions` is set.
+    pub async fn blueprint_list(
+        &self,
+        workspace_id: &str,
+        all_versions: bool,
+    ) -> anyhow::Result<Vec<BlueprintEntry>> {
+        let mut url = self.base_url.join("/v1/blueprints")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if all_versions {
+            url.query_pairs_mut().append_pair("all_versions", "true");
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Resolves one blueprint version; `version` of `None` is the latest.
+    pub async fn blueprint_get(
+        &self,
+        workspace_id: &str,
+        blueprint_id: &str,
+        version: Option<u32>,
+    ) -> anyhow::Result<BlueprintEntry> {
+        let mut url = self.base_url.join("/v1/blueprints/")?.join(blueprint_id)?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(version) = version {
+            url.query_pairs_mut()
+                .append_pair("version", &version.to_string());
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn skill_list(
+        &self,
+        workspace_id: &str,
+        trust: Option<SkillTrust>,
+    ) -> anyhow::Result<Vec<SkillEntry>> {
+        let mut url = self.base_url.join("/v1/skills")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(trust) = trust {
+            url.query_pairs_mut().append_pair("trust", trust.as_str());
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
 
-impl fmt::Display for ClientError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}: {}", self.error.code, self.error.message)
+    pub async fn skill_get(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+    ) -> anyhow::Result<SkillEntry> {
+        let mut url = self.base_url.join("/v1/skills/")?.join(skill_id)?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
     }
-}
 
-impl std::error::Error for ClientError {}
+    /// Leases by expiry, optionally narrowed to one status.
+    pub async fn lease_list(
+        &self,
+        workspace_id: &str,
+        status: Option<LeaseStatus>,
+    ) -> anyhow::Result<Vec<LeaseEntry>> {
+        let mut url = self.base_url.join("/v1/leases")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(status) = status {
+            url.query_pairs_mut().append_pair("status", status.as_str());
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
 
-impl Client {
-    pub fn from_runtime_dir(runtime_dir: &Path) -> anyhow::Result<Self> {
-        let path = runtime_dir.join("daemon.json");
-        let data = std::fs::read(&path)
-            .with_context(|| format!("failed to read runtime file at {}", path.display()))?;
-        let info: RuntimeInfo = serde_json::from_slice(&data)?;
-        let base_url = Url::parse(&info.addr)?;
-        Ok(Self {
-            base_url,
-            token: info.token,
-            http: HttpClient::new(),
-        })
+    pub async fn secret_list(&self, workspace_id: &str) -> anyhow::Result<Vec<SecretEntry>> {
+        let mut url = self.base_url.join("/v1/secrets")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
     }
 
-    pub fn default_runtime_dir() -> PathBuf {
-        default_runtime_dir_impl()
+    /// Metadata of the secret `name` resolves to for `context`, narrowest scope first.
+    pub async fn secret_resolve(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        context: &SecretContext,
+    ) -> anyhow::Result<SecretEntry> {
+        let mut url = self.base_url.join("/v1/secrets/resolve")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id)
+            .append_pair("name", name);
+        for (key, value) in [
+            ("user_id", &context.user_id),
+            ("project_id", &context.project_id),
+            ("session_id", &context.session_id),
+        ] {
+            if let Some(value) = value {
+                url.query_pairs_mut().append_pair(key, value);
+            }
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
     }
 
-    pub async fn ping(&self) -> anyhow::Result<DaemonPingResponse> {
-        let url = self.base_url.join("/v1/daemon/ping")?;
+    pub async fn vault_status(&self, workspace_id: &str) -> anyhow::Result<VaultStatus> {
+        let mut url = self.base_url.join("/v1/vault/status")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
         let resp = self
             .http
             .get(url)
@@ -69,44 +1071,147 @@ impl Client {
         parse_response(resp).await
     }
 
-    pub async fn workspace_create(
+    /// Compares a registered skill with its directory on disk; records nothing.
+    pub async fn skill_verify(
         &self,
-        name: String,
-        path: Option<String>,
-        idempotency_key: Option<String>,
-        expected_version: Option<i64>,
-    ) -> anyhow::Result<SubmitCommandResponse> {
-        let payload = WorkspaceCreatePayload { name, path };
-        self.submit_command(
-            "workspace.create",
-            1,
-            serde_json::to_value(payload)?,
-            idempotency_key,
-            expected_version,
-        )
-        .await
+        workspace_id: &str,
+        skill_id: &str,
+    ) -> anyhow::Result<SkillVerifyReport> {
+        let mut url = self
+            .base_url
+            .join("/v1/skills/")?
+            .join(&format!("{skill_id}/verify"))?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
     }
 
-    pub async fn project_create(
+    pub async fn tool_call_list(
         &self,
-        workspace_id: String,
-        name: String,
+        workspace_id: &str,
+        status: Option<ToolCallStatus>,
+        session_id: Option<&str>,
+    ) -> anyhow::Result<Vec<ToolCallEntry>> {
+        let mut url = self.base_url.join("/v1/tool_calls")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        if let Some(status) = status {
+            url.query_pairs_mut().append_pair("status", status.as_str());
+        }
+        if let Some(session_id) = session_id {
+            url.query_pairs_mut().append_pair("session_id", session_id);
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn tool_call_get(
+        &self,
+        workspace_id: &str,
+        request_id: &str,
+    ) -> anyhow::Result<ToolCallEntry> {
+        let mut url = self.base_url.join("/v1/tool_calls/")?.join(request_id)?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id);
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn tool_search(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        side_effect: Option<SideEffect>,
+        kind: Option<CatalogKind>,
+    ) -> anyhow::Result<ToolSearchResponse> {
+        let mut url = self.base_url.join("/v1/tools/search")?;
+        url.query_pairs_mut().append_pair("query", query);
+        if let Some(limit) = limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+        if let Some(side_effect) = side_effect {
+            url.query_pairs_mut()
+                .append_pair("side_effect", side_effect.as_str());
+        }
+        if let Some(kind) = kind {
+            url.query_pairs_mut().append_pair("kind", kind.as_str());
+        }
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    pub async fn tool_load(&self, tool_id: &str) -> anyhow::Result<ToolLoadResponse> {
+        let url = self.base_url.join("/v1/tools/")?.join(tool_id)?;
+        let resp = self
+            .http
+            .get(url)
+            .headers(self.auth_headers())
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Runs a catalog operation; `workspace_id` and `session_id` apply to registry tools only.
+    pub async fn tool_run(
+        &self,
+        tool_id: &str,
+        input: Value,
+        workspace_id: Option<String>,
+        session_id: Option<String>,
         idempotency_key: Option<String>,
         expected_version: Option<i64>,
-    ) -> anyhow::Result<SubmitCommandResponse> {
-        let payload = ProjectCreatePayload { workspace_id, name };
-        self.submit_command(
-            "project.create",
-            1,
-            serde_json::to_value(payload)?,
-            idempotency_key,
+    ) -> anyhow::Result<ToolRunResponse> {
+        let url = self.base_url.join("/v1/tools/run")?;
+        let request = ToolRunRequest {
+            id: tool_id.to_string(),
+            input,
+            workspace_id,
+            session_id,
+            idempotency_key: idempotency_key.unwrap_or_else(new_idempotency_key),
             expected_version,
-        )
-        .await
+            trace_id: Some(new_trace_id()),
+        };
+        let resp = self
+            .http
+            .post(url)
+            .headers(self.auth_headers())
+            .json(&request)
+            .send()
+            .await?;
+        parse_response(resp).await
     }
 
-    pub async fn workspace_list(&self) -> anyhow::Result<Vec<WorkspaceListEntry>> {
-        let url = self.base_url.join("/v1/workspaces")?;
+    pub async fn pipeline_stages(
+        &self,
+        workspace_id: &str,
+        project_id: &str,
+    ) -> anyhow::Result<PipelineStageView> {
+        let mut url = self.base_url.join("/v1/pipelines/stages")?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id)
+            .append_pair("project_id", project_id);
         let resp = self
             .http
             .get(url)
@@ -116,19 +1221,73 @@ impl Client {
         parse_response(resp).await
     }
 
-    pub async fn project_list(&self, workspace_id: &str) -> anyhow::Result<Vec<ProjectListEntry>> {
-        let url = self
-            .base_url
-            .join(&format!("/v1/projects?workspace_id={workspace_id}"))?;
+    /// Asks how the daemon's loaded policy would decide a command, without submitting it.
+    pub async fn policy_explain(
+        &self,
+        request: PolicyExplainRequest,
+    ) -> anyhow::Result<PolicyExplanation> {
+        let url = self.base_url.join("/v1/policy/explain")?;
         let resp = self
             .http
-            .get(url)
+            .post(url)
+            .headers(self.auth_headers())
+            .json(&request)
+            .send()
+            .await?;
+        parse_response(resp).await
+    }
+
+    /// Streams the file at `path` to `PUT /v1/artifacts`. The file is hashed first so the
+    /// daemon can reject a body that changed or was truncated in transit.
+    pub async fn artifact_put(
+        &self,
+        path: &Path,
+        mime_type: Option<&str>,
+    ) -> anyhow::Result<ArtifactUploadResponse> {
+        let hash = hash_file(path).await?;
+        let mut url = self.base_url.join("/v1/artifacts")?;
+        url.query_pairs_mut().append_pair("hash", &hash);
+        if let Some(mime_type) = mime_type {
+            url.query_pairs_mut().append_pair("mime_type", mime_type);
+        }
+        let file = tokio::fs::File::open(path)
+            .await
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        let resp = self
+            .http
+            .put(url)
             .headers(self.auth_headers())
+            .body(file)
             .send()
             .await?;
         parse_response(resp).await
     }
 
+    /// Streams an artifact (or one byte range of it) into `out`; returns the bytes written.
+    pub async fn artifact_get(
+        &self,
+        hash: &str,
+        range: Option<ArtifactRange>,
+        out: &mut (dyn AsyncWrite + Unpin + Send),
+    ) -> anyhow::Result<u64> {
+        let url = self.base_url.join(&format!("/v1/artifacts/{hash}"))?;
+        let mut request = self.http.get(url).headers(self.auth_headers());
+        if let Some(range) = range {
+            request = request.header(RANGE, range.header_value());
+        }
+        let mut resp = request.send().await?;
+        if !resp.status().is_success() {
+            return Err(error_from_response(resp).await);
+        }
+        let mut written = 0u64;
+        while let Some(chunk) = resp.chunk().await? {
+            out.write_all(&chunk).await?;
+            written += chunk.len() as u64;
+        }
+        out.flush().await?;
+        Ok(written)
+    }
+
     pub async fn events_read_from(
         &self,
         workspace_id: &str,
@@ -147,20 +1306,18 @@ impl Client {
     }
 
     pub async fn events_stream(&self, workspace_id: &str, from: i64) -> anyhow::Result<Response> {
-        let url = self.base_url.join(&format!(
-            "/v1/events/stream?workspace_id={workspace_id}&from={from}"
-        ))?;
-        let resp = self
-            .http
-            .get(url)
-            .headers(self.auth_headers())
-            .send()
-            .await?;
-        if resp.status().is_success() {
-            Ok(resp)
-        } else {
-            Err(error_from_response(resp).await)
-        }
+        self.events_stream_filtered(workspace_id, from, &EventFilter::default())
+            .await
+    }
+
+    pub async fn events_stream_filtered(
+        &self,
+        workspace_id: &str,
+        from: i64,
+        filter: &EventFilter,
+    ) -> anyhow::Result<Response> {
+        self.open_event_stream("/v1/events/stream", workspace_id, from, filter)
+            .await
     }
 
     pub async fn events_stream_ndjson(
@@ -168,9 +1325,41 @@ impl Client {
         workspace_id: &str,
         from: i64,
     ) -> anyhow::Result<Response> {
-        let url = self.base_url.join(&format!(
-            "/v1/events/stream-ndjson?workspace_id={workspace_id}&from={from}"
-        ))?;
+        self.events_stream_ndjson_filtered(workspace_id, from, &EventFilter::default())
+            .await
+    }
+
+    pub async fn events_stream_ndjson_filtered(
+        &self,
+        workspace_id: &str,
+        from: i64,
+        filter: &EventFilter,
+    ) -> anyhow::Result<Response> {
+        self.open_event_stream("/v1/events/stream-ndjson", workspace_id, from, filter)
+            .await
+    }
+
+    async fn open_event_stream(
+        &self,
+        path: &str,
+        workspace_id: &str,
+        from: i64,
+        filter: &EventFilter,
+    ) -> anyhow::Result<Response> {
+        let mut url = self.base_url.join(path)?;
+        url.query_pairs_mut()
+            .append_pair("workspace_id", workspace_id)
+            .append_pair("from", &from.to_string());
+        if let Some(subject_kind) = &filter.subject_kind {
+            url.query_pairs_mut()
+                .append_pair("subject_kind", subject_kind);
+        }
+        if let Some(subject_id) = &filter.subject_id {
+            url.query_pairs_mut().append_pair("subject_id", subject_id);
+        }
+        if let Some(event_type) = &filter.event_type {
+            url.query_pairs_mut().append_pair("event_type", event_type);
+        }
         let resp = self
             .http
             .get(url)
@@ -207,6 +1396,8 @@ impl Client {
             idempotency_key: Some(idempotency_key.unwrap_or_else(new_idempotency_key)),
             expected_version,
             trace_id: new_trace_id(),
+            actor: self.actor.clone(),
+            lease_id: self.lease_id.clone(),
         };
         let resp = self
             .http
@@ -295,25 +1486,64 @@ impl StdioClient {
         Ok(serde_json::from_value(response)?)
     }
 
-    pub async fn list_workspaces(&mut self) -> anyhow::Result<Vec<WorkspaceListEntry>> {
-        let response = self
-            .request("query.workspaces", serde_json::json!({}))
-            .await?
-            .payload;
+    pub async fn list_workspaces(
+        &mut self,
+        include_archived: bool,
+    ) -> anyhow::Result<Vec<WorkspaceListEntry>> {
+        let payload = serde_json::to_value(StdioWorkspacesQuery { include_archived })?;
+        let response = self.request("query.workspaces", payload).await?.payload;
         Ok(serde_json::from_value(response)?)
     }
 
     pub async fn list_projects(
         &mut self,
         workspace_id: &str,
+        include_archived: bool,
     ) -> anyhow::Result<Vec<ProjectListEntry>> {
         let payload = serde_json::to_value(StdioProjectsQuery {
             workspace_id: workspace_id.to_string(),
+            include_archived,
         })?;
         let response = self.request("query.projects", payload).await?.payload;
         Ok(serde_json::from_value(response)?)
     }
 
+    pub async fn list_sessions(
+        &mut self,
+        workspace_id: &str,
+        root_session_id: Option<&str>,
+    ) -> anyhow::Result<Vec<SessionListEntry>> {
+        let payload = serde_json::to_value(StdioSessionsQuery {
+            workspace_id: workspace_id.to_string(),
+            root_session_id: root_session_id.map(|id| id.to_string()),
+        })?;
+        let response = self.request("query.sessions", payload).await?.payload;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    pub async fn list_tasks(
+        &mut self,
+        workspace_id: &str,
+        project_id: Option<&str>,
+        state: Option<TaskState>,
+    ) -> anyhow::Result<Vec<TaskListEntry>> {
+        let payload = serde_json::to_value(StdioTasksQuery {
+            workspace_id: workspace_id.to_string(),
+            project_id: project_id.map(|id| id.to_string()),
+            state,
+        })?;
+        let response = self.request("query.tasks", payload).await?.payload;
+        Ok(serde_json::from_value(response)?)
+    }
+
+    pub async fn board(&mut self, workspace_id: &str) -> anyhow::Result<BoardSnapshot> {
+        let payload = serde_json::to_value(StdioBoardQuery {
+            workspace_id: workspace_id.to_string(),
+        })?;
+        let response = self.request("query.board", payload).await?.payload;
+        Ok(serde_json::from_value(response)?)
+    }
+
     pub async fn subscribe_events(&mut self, workspace_id: &str, from: i64) -> anyhow::Result<()> {
         let payload = serde_json::to_value(StdioEventsSubscribe {
             workspace_id: workspace_id.to_string(),
@@ -398,6 +1628,26 @@ fn new_request_id() -> String {
     format!("rq_{}", Uuid::now_v7())
 }
 
+async fn hash_file(path: &Path) -> anyhow::Result<String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .with_context(|| format!("failed to open {}", path.display()))?;
+    let mut hasher = blake3::Hasher::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!(
+        "{}{}",
+        mp_kernel::ARTIFACT_HASH_PREFIX,
+        hasher.finalize().to_hex()
+    ))
+}
+
 async fn parse_response<T: DeserializeOwned>(resp: Response) -> anyhow::Result<T> {
     if resp.status().is_success() {
         Ok(resp.json().await?)
@@ -452,12 +1702,31 @@ mod tests {
         assert!(Uuid::parse_str(&request[3..]).is_ok());
     }
 
+    #[test]
+    fn artifact_range_parses_open_and_closed_ranges() {
+        let closed: ArtifactRange = "0-99".parse().expect("closed");
+        assert_eq!(closed.header_value(), "bytes=0-99");
+        let open: ArtifactRange = "100-".parse().expect("open");
+        assert_eq!(
+            open,
+            ArtifactRange {
+                start: 100,
+                end: None
+            }
+        );
+        assert_eq!(open.header_value(), "bytes=100-");
+        assert!("9-0".parse::<ArtifactRange>().is_err());
+        assert!("-10".parse::<ArtifactRange>().is_err());
+    }
+
     #[test]
     fn auth_headers_include_bearer_token() {
         let client = Client {
             base_url: Url::parse("http://localhost:8080").expect("url"),
             token: "secret".to_string(),
             http: HttpClient::new(),
+            actor: None,
+            lease_id: None,
         };
         let headers = client.auth_headers();
         let token = headers
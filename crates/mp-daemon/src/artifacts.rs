@@ -0,0 +1,434 @@
+use crate::{
+    authorize, decode_payload, ensure_expected_version, internal_error, reject_command, ApiError,
+    AppState, CommandOutcome,
+};
+use axum::{
+    body::Body,
+    extract::rejection::QueryRejection,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::Engine;
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use mp_kernel::{
+    artifact_hash_hex, board_node_id, Actor, ArtifactPutPayload, ArtifactStoredPayload,
+    ArtifactUploadResponse, ErrorCode, Subject, ARTIFACT_HASH_PREFIX, EVENT_ARTIFACT_STORED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{ArtifactStore, NewEvent, ProjectionReader};
+use serde::Deserialize;
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Inline content is written before the event is appended; storage is content-addressed, so a
+/// failed append only leaves an unreferenced blob behind. A `hash` put records a blob that was
+/// streamed in through `PUT /v1/artifacts`.
+pub(crate) async fn plan_put(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: ArtifactPutPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let content = match payload
+        .content_base64
+        .as_deref()
+        .map(|content| base64::engine::general_purpose::STANDARD.decode(content))
+        .transpose()
+    {
+        Ok(content) => content,
+        Err(err) => {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!("content_base64 is not valid base64: {err}"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+
+    let store = state.store.lock().await;
+    let project_id = match &payload.producer {
+        Some(producer) => {
+            let node_id = board_node_id(&producer.kind, &producer.id);
+            let node = store
+                .get_board_node(&payload.workspace_id, &node_id)
+                .map_err(|err| {
+                    tracing::error!("get_board_node failed: {err}");
+                    internal_error(Some(command.trace_id.clone()))
+                })?;
+            let Some(node) = node else {
+                drop(store);
+                return reject_command(
+                    state,
+                    command,
+                    ErrorCode::NotFound,
+                    &format!("producer {}:{} not found", producer.kind, producer.id),
+                )
+                .await
+                .map(CommandOutcome::Rejected);
+            };
+            node.project_id
+        }
+        None => None,
+    };
+    let metadata = payload
+        .metadata
+        .clone()
+        .unwrap_or_else(|| serde_json::json!({}));
+    let (entry, deduplicated) = match (content, &payload.hash) {
+        (Some(content), _) => {
+            let stored = store
+                .put_artifact(
+                    &mut content.as_slice(),
+                    payload.mime_type.as_deref(),
+                    &metadata,
+                )
+                .map_err(|err| {
+                    tracing::error!("put_artifact failed: {err}");
+                    internal_error(Some(command.trace_id.clone()))
+                })?;
+            (stored.entry, stored.deduplicated)
+        }
+        (None, Some(hash)) => {
+            let entry = store.get_artifact(hash).map_err(|err| {
+                tracing::error!("get_artifact failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+            let Some(entry) = entry else {
+                drop(store);
+                return reject_command(
+                    state,
+                    command,
+                    ErrorCode::NotFound,
+                    &format!("artifact {hash} has not been uploaded"),
+                )
+                .await
+                .map(CommandOutcome::Rejected);
+            };
+            (entry, true)
+        }
+        (None, None) => {
+            drop(store);
+            return reject_command(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                "artifact.put requires content_base64 or hash",
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+    drop(store);
+
+    let hash = entry.hash.clone();
+    let event_payload = serde_json::to_value(ArtifactStoredPayload {
+        hash: hash.clone(),
+        size_bytes: entry.size_bytes,
+        name: payload.name,
+        mime_type: payload.mime_type,
+        metadata: payload.metadata,
+        producer: payload.producer,
+        deduplicated,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize artifact.stored payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_ARTIFACT_STORED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id,
+        subject: Subject {
+            kind: "artifact".to_string(),
+            id: hash,
+        },
+        payload: event_payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ArtifactUploadQuery {
+    /// Expected `blake3:<hex>` of the body; the upload is discarded when it does not match.
+    hash: String,
+    #[serde(default)]
+    mime_type: Option<String>,
+}
+
+/// `PUT /v1/artifacts`: streams the body to the staging area, hashing as it goes, and only
+/// holds the store lock to move the verified blob into place.
+pub(crate) async fn handle_artifact_put(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<ArtifactUploadQuery>, QueryRejection>,
+    body: Body,
+) -> Result<Response, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    if artifact_hash_hex(&query.hash).is_none() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            format!("malformed artifact hash {}", query.hash),
+            None,
+            None,
+        ));
+    }
+
+    let staged_path = {
+        let store = state.store.lock().await;
+        store.artifact_staging_path().map_err(|err| {
+            tracing::error!("artifact_staging_path failed: {err}");
+            internal_error(None)
+        })?
+    };
+    let actual = match receive_upload(body, &staged_path).await {
+        Ok(hex) => format!("{ARTIFACT_HASH_PREFIX}{hex}"),
+        Err(err) => {
+            let _ = tokio::fs::remove_file(&staged_path).await;
+            return Err(err);
+        }
+    };
+    if actual != query.hash {
+        let _ = tokio::fs::remove_file(&staged_path).await;
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::ValidationFailed,
+            "uploaded content does not match hash",
+            Some(serde_json::json!({ "expected": query.hash, "actual": actual })),
+            None,
+        ));
+    }
+
+    let stored = {
+        let store = state.store.lock().await;
+        store
+            .commit_artifact(
+                &staged_path,
+                &actual,
+                query.mime_type.as_deref(),
+                &serde_json::json!({}),
+            )
+            .map_err(|err| {
+                tracing::error!("commit_artifact failed: {err}");
+                internal_error(None)
+            })?
+    };
+    let status = if stored.deduplicated {
+        StatusCode::OK
+    } else {
+        StatusCode::CREATED
+    };
+    Ok((
+        status,
+        Json(ArtifactUploadResponse {
+            artifact: stored.entry,
+            deduplicated: stored.deduplicated,
+        }),
+    )
+        .into_response())
+}
+
+async fn receive_upload(body: Body, staged_path: &std::path::Path) -> Result<String, ApiError> {
+    let write_failed = |err: std::io::Error| {
+        tracing::error!("write staged artifact failed: {err}");
+        internal_error(None)
+    };
+    let mut file = tokio::fs::File::create(staged_path)
+        .await
+        .map_err(write_failed)?;
+    let mut hasher = blake3::Hasher::new();
+    let mut chunks = body.into_data_stream();
+    while let Some(chunk) = chunks.next().await {
+        let chunk = chunk.map_err(|err| {
+            ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::ValidationFailed,
+                format!("failed to read upload body: {err}"),
+                None,
+                None,
+            )
+        })?;
+        hasher.update(&chunk);
+        file.write_all(&chunk).await.map_err(write_failed)?;
+    }
+    file.sync_all().await.map_err(write_failed)?;
+    Ok(hasher.finalize().to_hex().to_string())
+}
+
+fn read_chunks(
+    mut reader: impl AsyncRead + Unpin + Send + 'static,
+) -> impl Stream<Item = std::io::Result<Bytes>> + Send {
+    async_stream::try_stream! {
+        let mut buffer = vec![0u8; CHUNK_BYTES];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+            yield Bytes::copy_from_slice(&buffer[..read]);
+        }
+    }
+}
+
+/// What a `Range` header asks for, resolved against the blob size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+    Full,
+    /// Inclusive byte offsets.
+    Partial {
+        start: u64,
+        end: u64,
+    },
+    Unsatisfiable,
+}
+
+/// Single `bytes=` ranges only; anything else is ignored and served in full, as RFC 9110 allows.
+pub(crate) fn parse_byte_range(header: Option<&str>, size: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|value| value.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let Some((first, last)) = spec.trim().split_once('-') else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        let Ok(suffix) = last.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        if suffix == 0 || size == 0 {
+            return ByteRange::Unsatisfiable;
+        }
+        return ByteRange::Partial {
+            start: size.saturating_sub(suffix),
+            end: size - 1,
+        };
+    }
+    let Ok(start) = first.parse::<u64>() else {
+        return ByteRange::Full;
+    };
+    let end = if last.is_empty() {
+        size.saturating_sub(1)
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => end.min(size.saturating_sub(1)),
+            Ok(_) => return ByteRange::Unsatisfiable,
+            Err(_) => return ByteRange::Full,
+        }
+    };
+    if start >= size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial { start, end }
+}
+
+/// `GET /v1/artifacts/{hash}`: streams the blob from disk, honouring a single byte range.
+pub(crate) async fn handle_artifact_get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(hash): Path<String>,
+) -> Result<Response, ApiError> {
+    authorize(&state, &headers)?;
+    if artifact_hash_hex(&hash).is_none() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            format!("malformed artifact hash {hash}"),
+            None,
+            None,
+        ));
+    }
+    let (entry, path) = {
+        let store = state.store.lock().await;
+        let lookup = store
+            .get_artifact(&hash)
+            .and_then(|entry| Ok((entry, store.artifact_path(&hash)?)));
+        lookup.map_err(|err| {
+            tracing::error!("artifact lookup failed: {err}");
+            internal_error(None)
+        })?
+    };
+    let (Some(entry), Some(path)) = (entry, path) else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("artifact {hash} not found"),
+            None,
+            None,
+        ));
+    };
+
+    let size = entry.size_bytes;
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok());
+    let (status, start, len) = match parse_byte_range(range, size) {
+        ByteRange::Full => (StatusCode::OK, 0, size),
+        ByteRange::Partial { start, end } => (StatusCode::PARTIAL_CONTENT, start, end - start + 1),
+        ByteRange::Unsatisfiable => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{size}"))],
+            )
+                .into_response());
+        }
+    };
+
+    let read_failed = |err: std::io::Error| {
+        tracing::error!("read artifact {hash} failed: {err}");
+        internal_error(None)
+    };
+    let mut file = tokio::fs::File::open(&path).await.map_err(read_failed)?;
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(read_failed)?;
+    let body = Body::from_stream(read_chunks(file.take(len)));
+
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    let response_headers = response.headers_mut();
+    let content_type = entry
+        .mime_type
+        .as_deref()
+        .and_then(|mime| HeaderValue::from_str(mime).ok())
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    response_headers.insert(header::CONTENT_TYPE, content_type);
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(len));
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{hash}\"")) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    if status == StatusCode::PARTIAL_CONTENT {
+        let content_range = format!("bytes {start}-{}/{size}", start + len - 1);
+        if let Ok(value) = HeaderValue::from_str(&content_range) {
+            response_headers.insert(header::CONTENT_RANGE, value);
+        }
+    }
+    Ok(response)
+}
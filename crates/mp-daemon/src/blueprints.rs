@@ -0,0 +1,265 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, reject_command,
+    reject_command_with_details, workspaces::load_workspace, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    resolve_pins, Actor, AgentBlueprint, BlueprintRef, BlueprintSyncPayload,
+    BlueprintVersionPayload, ErrorCode, SessionBlueprint, Subject, ARTIFACT_HASH_PREFIX,
+    BLUEPRINT_API_VERSION, BLUEPRINT_DIR, EVENT_BLUEPRINT_REGISTERED,
+    EVENT_BLUEPRINT_VERSION_CREATED,
+};
+use mp_protocol::{CommandEnvelope, SchemaRegistry, SubmitCommandResponse};
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Serialize;
+use std::path::Path;
+
+/// One blueprint file that parsed and validated.
+struct LoadedBlueprint {
+    /// Relative to the workspace root.
+    path: String,
+    content_hash: String,
+    blueprint: AgentBlueprint,
+}
+
+#[derive(Debug, Serialize)]
+struct FileError {
+    path: String,
+    message: String,
+}
+
+/// Files are the source of truth: every file is read, and a version is recorded only for ids
+/// that are new or whose bytes changed. One invalid file rejects the whole sync, so the index
+/// never mixes an old and a new view of the directory.
+pub(crate) async fn plan_sync(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: BlueprintSyncPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(workspace) = load_workspace(state, command, &payload.workspace_id).await? else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+
+    let root = Path::new(&workspace.root_path);
+    let loaded = match load_blueprints(&state.schema_registry, root) {
+        Ok(loaded) => loaded,
+        Err(errors) => {
+            return reject_command_with_details(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!("{} blueprint file(s) failed validation", errors.len()),
+                Some(serde_json::json!({ "errors": errors })),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+
+    let mut events = Vec::new();
+    let store = state.store.lock().await;
+    for file in loaded {
+        let latest = store
+            .get_blueprint(&payload.workspace_id, &file.blueprint.id, None)
+            .map_err(|err| {
+                tracing::error!("get_blueprint failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        let (event_type, version) = match latest {
+            None => (EVENT_BLUEPRINT_REGISTERED, 1),
+            Some(latest) if latest.content_hash == file.content_hash => continue,
+            Some(latest) => (EVENT_BLUEPRINT_VERSION_CREATED, latest.version + 1),
+        };
+        let blueprint_id = file.blueprint.id.clone();
+        let event_payload = serde_json::to_value(BlueprintVersionPayload {
+            blueprint_id: blueprint_id.clone(),
+            version,
+            name: file.blueprint.name.clone(),
+            path: file.path,
+            content_hash: file.content_hash,
+            blueprint: file.blueprint,
+        })
+        .map_err(|err| {
+            tracing::error!("serialize {event_type} payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+        events.push(NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: actor.clone(),
+            workspace_id: payload.workspace_id.clone(),
+            project_id: None,
+            subject: Subject {
+                kind: "blueprint".to_string(),
+                id: blueprint_id,
+            },
+            payload: event_payload,
+            trace_id: Some(command.trace_id.clone()),
+            stream_id: None,
+        });
+    }
+    Ok(CommandOutcome::Append(events))
+}
+
+/// Resolves the exact blueprint version a session starts from and checks its pins against the
+/// daemon's manifest. The inner `Err` is the recorded rejection, with every mismatch in
+/// `details.mismatches`.
+pub(crate) async fn resolve_for_session(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    reference: &BlueprintRef,
+) -> Result<Result<SessionBlueprint, SubmitCommandResponse>, ApiError> {
+    let entry = {
+        let store = state.store.lock().await;
+        store
+            .get_blueprint(workspace_id, &reference.id, reference.version)
+            .map_err(|err| {
+                tracing::error!("get_blueprint failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let Some(entry) = entry else {
+        let message = match reference.version {
+            Some(version) => format!("blueprint {} version {version} not found", reference.id),
+            None => format!("blueprint {} not found", reference.id),
+        };
+        return reject_command(state, command, ErrorCode::NotFound, &message)
+            .await
+            .map(Err);
+    };
+
+    match resolve_pins(
+        &entry.blueprint,
+        &state.pin_manifest,
+        state.allow_floating_pins,
+    ) {
+        Ok(pins) => Ok(Ok(SessionBlueprint {
+            blueprint_id: entry.blueprint_id,
+            version: entry.version,
+            content_hash: entry.content_hash,
+            pins,
+        })),
+        Err(mismatches) => reject_command_with_details(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            &format!(
+                "blueprint {} v{} pins do not match loaded components ({} mismatch(es))",
+                entry.blueprint_id,
+                entry.version,
+                mismatches.len()
+            ),
+            Some(serde_json::json!({
+                "blueprint_id": entry.blueprint_id,
+                "version": entry.version,
+                "allow_floating_pins": state.allow_floating_pins,
+                "mismatches": mismatches,
+            })),
+        )
+        .await
+        .map(Err),
+    }
+}
+
+/// Reads `<root>/agents/blueprints/*.yaml` in file-name order. A missing directory holds no
+/// blueprints. Each file's `id` must match its file stem so one id maps to one file.
+fn load_blueprints(
+    registry: &SchemaRegistry,
+    root: &Path,
+) -> Result<Vec<LoadedBlueprint>, Vec<FileError>> {
+    let dir = root.join(BLUEPRINT_DIR);
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(vec![FileError {
+                path: BLUEPRINT_DIR.to_string(),
+                message: err.to_string(),
+            }])
+        }
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("yaml" | "yml")
+                )
+        })
+        .collect();
+    files.sort();
+
+    let mut loaded: Vec<LoadedBlueprint> = Vec::new();
+    let mut errors = Vec::new();
+    for file in files {
+        let file_name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let path = format!("{BLUEPRINT_DIR}/{file_name}");
+        let stem = file
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        match load_blueprint(registry, &file, &stem) {
+            Ok((content_hash, blueprint)) => {
+                if let Some(existing) = loaded.iter().find(|b| b.blueprint.id == blueprint.id) {
+                    errors.push(FileError {
+                        message: format!(
+                            "blueprint id {} is already defined by {}",
+                            blueprint.id, existing.path
+                        ),
+                        path,
+                    });
+                    continue;
+                }
+                loaded.push(LoadedBlueprint {
+                    path,
+                    content_hash,
+                    blueprint,
+                });
+            }
+            Err(message) => errors.push(FileError { path, message }),
+        }
+    }
+    if errors.is_empty() {
+        Ok(loaded)
+    } else {
+        Err(errors)
+    }
+}
+
+fn load_blueprint(
+    registry: &SchemaRegistry,
+    file: &Path,
+    stem: &str,
+) -> Result<(String, AgentBlueprint), String> {
+    let bytes = std::fs::read(file).map_err(|err| err.to_string())?;
+    let document: serde_json::Value =
+        serde_yaml::from_slice(&bytes).map_err(|err| format!("invalid YAML: {err}"))?;
+    registry
+        .validate_document("agent_blueprint", BLUEPRINT_API_VERSION as i32, &document)
+        .map_err(|err| err.message)?;
+    let blueprint: AgentBlueprint =
+        serde_json::from_value(document).map_err(|err| err.to_string())?;
+    if blueprint.id != stem {
+        return Err(format!(
+            "blueprint id {} does not match file name {stem}",
+            blueprint.id
+        ));
+    }
+    let content_hash = format!("{ARTIFACT_HASH_PREFIX}{}", blake3::hash(&bytes).to_hex());
+    Ok((content_hash, blueprint))
+}
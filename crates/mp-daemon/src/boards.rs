@@ -0,0 +1,183 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, reject_command, ApiError, AppState,
+    CommandOutcome,
+};
+use mp_kernel::{
+    parse_board_node_id, Actor, BoardCommentAddPayload, BoardCommentAddedPayload,
+    BoardGroupCreatePayload, BoardGroupCreatedPayload, BoardNode, BoardNodeMovePayload,
+    BoardNodeMovedPayload, ErrorCode, Subject, EVENT_BOARD_COMMENT_ADDED,
+    EVENT_BOARD_GROUP_CREATED, EVENT_BOARD_NODE_MOVED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Serialize;
+
+pub(crate) async fn plan_group_create(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: BoardGroupCreatePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let event = board_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        None,
+        Subject {
+            kind: "group".to_string(),
+            id: mp_kernel::new_uuid(),
+        },
+        EVENT_BOARD_GROUP_CREATED,
+        BoardGroupCreatedPayload {
+            label: payload.label,
+            position: payload.position,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+pub(crate) async fn plan_comment_add(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: BoardCommentAddPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let mut project_id = None;
+    if let Some(target_node_id) = &payload.target_node_id {
+        match load_node(state, command, &payload.workspace_id, target_node_id).await? {
+            Some(target) => project_id = target.project_id,
+            None => return reject_missing(state, command, target_node_id).await,
+        }
+    }
+
+    let event = board_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        project_id,
+        Subject {
+            kind: "comment".to_string(),
+            id: mp_kernel::new_uuid(),
+        },
+        EVENT_BOARD_COMMENT_ADDED,
+        BoardCommentAddedPayload {
+            body: payload.body,
+            target_node_id: payload.target_node_id,
+            position: payload.position,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+/// The move is recorded against the node's own subject so it shows up in that subject's stream.
+pub(crate) async fn plan_node_move(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: BoardNodeMovePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(node) = load_node(state, command, &payload.workspace_id, &payload.node_id).await?
+    else {
+        return reject_missing(state, command, &payload.node_id).await;
+    };
+    if let Some(group_node_id) = &payload.group_node_id {
+        let Some(group) = load_node(state, command, &payload.workspace_id, group_node_id).await?
+        else {
+            return reject_missing(state, command, group_node_id).await;
+        };
+        let message = if group.kind != "group" {
+            Some(format!("board node {group_node_id} is not a group"))
+        } else if group.node_id == node.node_id {
+            Some(format!("board node {group_node_id} cannot contain itself"))
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            return reject_command(state, command, ErrorCode::ValidationFailed, &message)
+                .await
+                .map(CommandOutcome::Rejected);
+        }
+    }
+    let Some(subject) = parse_board_node_id(&node.node_id) else {
+        tracing::error!("board node {} has a malformed id", node.node_id);
+        return Err(internal_error(Some(command.trace_id.clone())));
+    };
+
+    let event = board_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        node.project_id,
+        subject,
+        EVENT_BOARD_NODE_MOVED,
+        BoardNodeMovedPayload {
+            position: payload.position,
+            group_node_id: payload.group_node_id,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+async fn load_node(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    node_id: &str,
+) -> Result<Option<BoardNode>, ApiError> {
+    let store = state.store.lock().await;
+    store.get_board_node(workspace_id, node_id).map_err(|err| {
+        tracing::error!("get_board_node failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
+
+async fn reject_missing(
+    state: &AppState,
+    command: &CommandEnvelope,
+    node_id: &str,
+) -> Result<CommandOutcome, ApiError> {
+    reject_command(
+        state,
+        command,
+        ErrorCode::NotFound,
+        &format!("board node {node_id} not found"),
+    )
+    .await
+    .map(CommandOutcome::Rejected)
+}
+
+fn board_event<P: Serialize>(
+    command: &CommandEnvelope,
+    actor: Actor,
+    workspace_id: &str,
+    project_id: Option<String>,
+    subject: Subject,
+    event_type: &str,
+    payload: P,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id,
+        subject,
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
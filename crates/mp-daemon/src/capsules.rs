@@ -0,0 +1,108 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, reject_command,
+    reject_command_with_details, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    board_node_id, capsule_chars, Actor, CapsuleWritePayload, CapsuleWrittenPayload, ErrorCode,
+    EVENT_CAPSULE_WRITTEN,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+
+/// Budgets are checked here rather than in the schema so the rejection can carry the numbers.
+/// The event is recorded on the capsule's subject, so followers of that subject see it.
+pub(crate) async fn plan_write(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: CapsuleWritePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let chars = capsule_chars(&payload.content);
+    let budget = payload.kind.budget_chars();
+    if chars > budget {
+        return reject_command_with_details(
+            state,
+            command,
+            ErrorCode::BudgetExceeded,
+            &format!(
+                "{} capsule is {chars} characters; budget is {budget}",
+                payload.kind
+            ),
+            Some(serde_json::json!({
+                "kind": payload.kind,
+                "budget_chars": budget,
+                "actual_chars": chars,
+            })),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let (node, latest) = {
+        let store = state.store.lock().await;
+        let node_id = board_node_id(&payload.subject.kind, &payload.subject.id);
+        let node = store
+            .get_board_node(&payload.workspace_id, &node_id)
+            .map_err(|err| {
+                tracing::error!("get_board_node failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        let latest = store
+            .list_capsules(
+                &payload.workspace_id,
+                Some(&payload.subject),
+                Some(payload.kind),
+                false,
+            )
+            .map_err(|err| {
+                tracing::error!("list_capsules failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        (node, latest)
+    };
+    let Some(node) = node else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!(
+                "subject {}:{} not found",
+                payload.subject.kind, payload.subject.id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    let version = latest
+        .iter()
+        .map(|capsule| capsule.version)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let event_payload = serde_json::to_value(CapsuleWrittenPayload {
+        kind: payload.kind,
+        version,
+        content: payload.content,
+        chars: chars as u32,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize capsule.written payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_CAPSULE_WRITTEN.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: node.project_id,
+        subject: payload.subject,
+        payload: event_payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
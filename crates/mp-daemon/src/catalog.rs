@@ -0,0 +1,822 @@
+use crate::{authorize, submit_command_inner, ApiError, AppState};
+use axum::{
+    extract::rejection::{JsonRejection, QueryRejection},
+    extract::{Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use mp_kernel::{
+    CatalogKind, ErrorCode, SideEffect, ToolExample, ToolLoadResponse, ToolRequestPayload,
+    ToolResultPayload, ToolRunRequest, ToolSearchHit, ToolSearchResponse, COMMAND_TOOL_COMPLETE,
+    COMMAND_TOOL_REQUEST, EVENT_TOOL_REQUESTED, EVENT_TOOL_RESULT, TOOL_LOAD_BUDGET_BYTES,
+    TOOL_RUN_BUDGET_BYTES, TOOL_SEARCH_BUDGET_BYTES, TOOL_SEARCH_DEFAULT_LIMIT,
+    TOOL_SEARCH_MAX_LIMIT,
+};
+use mp_protocol::{CommandEnvelope, SchemaRegistry, SubmitCommandResponse, ToolRunResponse};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Index weight of a term by the field it came from.
+const WEIGHT_TITLE: f64 = 3.0;
+const WEIGHT_TAXONOMY: f64 = 2.0;
+const WEIGHT_DESCRIPTION: f64 = 1.0;
+/// Query terms at least this long also match longer tokens they prefix, at half weight.
+const PREFIX_MIN_CHARS: usize = 3;
+const SUMMARY_MAX_CHARS: usize = 160;
+
+/// Title and description of each kernel command offered through the catalog. `tool.request`
+/// and `tool.complete` are left out: registry tools are listed under their own ids.
+const COMMAND_DOCS: &[(&str, &str, &str)] = &[
+    (
+        "artifact.put",
+        "Store artifact",
+        "Records a content-addressed artifact uploaded to the daemon and attaches it to a subject.",
+    ),
+    (
+        "blueprint.sync",
+        "Sync blueprint",
+        "Reads a workspace blueprint file, validates it and records a new version when it changed.",
+    ),
+    (
+        "board.comment.add",
+        "Comment on board node",
+        "Adds a comment to a node on the workspace board.",
+    ),
+    (
+        "board.group.create",
+        "Create board group",
+        "Creates a group on the workspace board that other nodes can be moved into.",
+    ),
+    (
+        "board.node.move",
+        "Move board node",
+        "Moves a board node into a group or back to the top level.",
+    ),
+    (
+        "capsule.write",
+        "Write context capsule",
+        "Writes a size-budgeted summary capsule for a subject, superseding the previous one of its kind.",
+    ),
+    (
+        "gate.approve",
+        "Approve gate",
+        "Approves a pending gate so the guarded stage transition or command can proceed.",
+    ),
+    (
+        "gate.define",
+        "Define gate",
+        "Defines an approval gate that holds matching commands until it is approved or rejected.",
+    ),
+    (
+        "gate.reject",
+        "Reject gate",
+        "Rejects a pending gate, refusing the command it holds.",
+    ),
+    (
+        "lease.mint",
+        "Mint lease",
+        "Grants an actor a time-boxed exception to policy for the listed commands.",
+    ),
+    (
+        "lease.revoke",
+        "Revoke lease",
+        "Ends an active capability lease before its TTL runs out.",
+    ),
+    (
+        "message.send",
+        "Send message",
+        "Sends a message between sessions in a workspace.",
+    ),
+    (
+        "pipeline.bind",
+        "Bind pipeline",
+        "Binds a project to a pipeline template and places it in the template's first stage.",
+    ),
+    (
+        "pipeline.template.define",
+        "Define pipeline template",
+        "Defines or revises a pipeline template: its ordered stages and allowed transitions.",
+    ),
+    (
+        "project.archive",
+        "Archive project",
+        "Archives a project, hiding it from default listings.",
+    ),
+    (
+        "project.create",
+        "Create project",
+        "Creates a project inside a workspace.",
+    ),
+    (
+        "project.rename",
+        "Rename project",
+        "Renames a project.",
+    ),
+    (
+        "project.restore",
+        "Restore project",
+        "Restores an archived project.",
+    ),
+    (
+        "secret.delete",
+        "Delete secret",
+        "Deletes a secret and every stored version of its value.",
+    ),
+    (
+        "secret.exec",
+        "Run with secrets",
+        "Launches a program on the daemon host with secrets on stdin or in files, never in its environment; denied unless a policy rule allows it.",
+    ),
+    (
+        "secret.rotate",
+        "Rotate secret",
+        "Seals a new value for an existing secret; requires the workspace vault to be unlocked.",
+    ),
+    (
+        "secret.set",
+        "Set secret",
+        "Stores a new secret in the workspace vault under a name and scope; events record only redacted metadata.",
+    ),
+    (
+        "session.fork",
+        "Fork session",
+        "Starts a new session from an existing one, inheriting its context.",
+    ),
+    (
+        "session.spawn",
+        "Spawn session",
+        "Starts an agent session in a workspace, optionally from a blueprint.",
+    ),
+    (
+        "skill.approve",
+        "Approve skill",
+        "Approves a proposed skill at the content hash that was reviewed, so it runs sandboxed.",
+    ),
+    (
+        "skill.sync",
+        "Sync skills",
+        "Rescans workspace and global skill directories, checks signatures and records skills that changed.",
+    ),
+    (
+        "stage.transition",
+        "Transition stage",
+        "Moves a project to another stage of its pipeline, subject to gates.",
+    ),
+    (
+        "task.await_input",
+        "Await user input",
+        "Halts a running task until a user answers and it is resumed.",
+    ),
+    (
+        "task.cancel",
+        "Cancel task",
+        "Cancels a task that has not finished.",
+    ),
+    (
+        "task.create",
+        "Create task",
+        "Creates a task with optional deadline, timeout and retry policy.",
+    ),
+    (
+        "task.fail",
+        "Fail task",
+        "Marks a running task as failed, retrying it when its policy allows.",
+    ),
+    (
+        "task.pause",
+        "Pause task",
+        "Pauses a task until it is resumed.",
+    ),
+    (
+        "task.resume",
+        "Resume task",
+        "Resumes a paused task.",
+    ),
+    (
+        "task.start",
+        "Start task",
+        "Marks a task as running.",
+    ),
+    (
+        "task.succeed",
+        "Complete task",
+        "Marks a running task as succeeded.",
+    ),
+    (
+        "vault.init",
+        "Create vault",
+        "Creates a locked vault for a workspace's secrets, keyed by a password that is never recorded.",
+    ),
+    (
+        "vault.lock",
+        "Lock vault",
+        "Drops the workspace vault key from memory so secrets cannot be read until it is unlocked again.",
+    ),
+    (
+        "vault.unlock",
+        "Unlock vault",
+        "Derives the workspace vault key from its password and holds it until the vault is locked or sits idle.",
+    ),
+    (
+        "workspace.archive",
+        "Archive workspace",
+        "Archives a workspace, hiding it from default listings.",
+    ),
+    (
+        "workspace.create",
+        "Create workspace",
+        "Creates a workspace rooted at a path.",
+    ),
+    (
+        "workspace.rename",
+        "Rename workspace",
+        "Renames a workspace.",
+    ),
+    (
+        "workspace.restore",
+        "Restore workspace",
+        "Restores an archived workspace.",
+    ),
+    (
+        "worktree.attach",
+        "Attach worktree",
+        "Attaches a registered git worktree to a session.",
+    ),
+    (
+        "worktree.detach",
+        "Detach worktree",
+        "Detaches a worktree from its session.",
+    ),
+    (
+        "worktree.lock.acquire",
+        "Lock worktree",
+        "Takes the exclusive lock on a worktree for a session.",
+    ),
+    (
+        "worktree.lock.release",
+        "Unlock worktree",
+        "Releases a session's lock on a worktree.",
+    ),
+    (
+        "worktree.register",
+        "Register worktree",
+        "Registers a git worktree path and branch with a project.",
+    ),
+];
+
+#[derive(Debug, Clone)]
+struct CatalogEntry {
+    id: String,
+    kind: CatalogKind,
+    title: String,
+    description: String,
+    side_effect: SideEffect,
+    /// Command schema version to submit with; unused for tools.
+    schema_version: i32,
+    input_schema: Value,
+    output_schema: Option<Value>,
+    examples: Vec<ToolExample>,
+    safety_notes: Option<String>,
+}
+
+/// Every operation agents can discover, with a local inverted index over titles, descriptions
+/// and taxonomy. Built once at startup from the schema registry.
+pub(crate) struct Catalog {
+    /// Ordered by id.
+    entries: Vec<CatalogEntry>,
+    /// Token → (entry index, weight of the heaviest field it appears in).
+    index: BTreeMap<String, Vec<(usize, f64)>>,
+}
+
+impl Catalog {
+    pub(crate) fn build(registry: &SchemaRegistry) -> Self {
+        let docs: HashMap<&str, (&str, &str)> = COMMAND_DOCS
+            .iter()
+            .map(|(id, title, description)| (*id, (*title, *description)))
+            .collect();
+        let mut entries = Vec::new();
+        for (command_type, version, schema) in registry.command_schemas() {
+            if command_type == COMMAND_TOOL_REQUEST || command_type == COMMAND_TOOL_COMPLETE {
+                continue;
+            }
+            let (title, description) = docs
+                .get(command_type)
+                .copied()
+                .unwrap_or((command_type, "Kernel command."));
+            entries.push(CatalogEntry {
+                id: command_type.to_string(),
+                kind: CatalogKind::Command,
+                title: title.to_string(),
+                description: description.to_string(),
+                side_effect: SideEffect::Workspace,
+                schema_version: version,
+                input_schema: schema.clone(),
+                output_schema: None,
+                examples: Vec::new(),
+                safety_notes: None,
+            });
+        }
+        for tool in registry.tools() {
+            entries.push(CatalogEntry {
+                id: tool.id.clone(),
+                kind: CatalogKind::Tool,
+                title: tool.title.clone(),
+                description: tool.description.clone(),
+                side_effect: tool.side_effect,
+                schema_version: 1,
+                input_schema: tool.input_schema.clone(),
+                output_schema: Some(tool.output_schema.clone()),
+                examples: tool.examples.clone(),
+                safety_notes: tool.safety_notes.clone(),
+            });
+        }
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut index: BTreeMap<String, Vec<(usize, f64)>> = BTreeMap::new();
+        for (position, entry) in entries.iter().enumerate() {
+            let mut weights: HashMap<String, f64> = HashMap::new();
+            let taxonomy = format!("{} {}", entry.kind, entry.side_effect);
+            for (text, weight) in [
+                (entry.id.as_str(), WEIGHT_TITLE),
+                (entry.title.as_str(), WEIGHT_TITLE),
+                (taxonomy.as_str(), WEIGHT_TAXONOMY),
+                (entry.description.as_str(), WEIGHT_DESCRIPTION),
+            ] {
+                for token in tokenize(text) {
+                    let best = weights.entry(token).or_insert(weight);
+                    *best = best.max(weight);
+                }
+            }
+            for (token, weight) in weights {
+                index.entry(token).or_default().push((position, weight));
+            }
+        }
+        Self { entries, index }
+    }
+
+    fn get(&self, id: &str) -> Option<&CatalogEntry> {
+        self.entries
+            .binary_search_by(|entry| entry.id.as_str().cmp(id))
+            .ok()
+            .map(|position| &self.entries[position])
+    }
+
+    /// Matching entries, best first. Rarer terms count for more, and ties go to the lower id.
+    fn search(
+        &self,
+        query: &str,
+        side_effect: Option<SideEffect>,
+        kind: Option<CatalogKind>,
+    ) -> Vec<(&CatalogEntry, f64)> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        let total = self.entries.len() as f64;
+        let mut scores: HashMap<usize, f64> = HashMap::new();
+        for term in &terms {
+            let mut best: HashMap<usize, f64> = HashMap::new();
+            let matches = self
+                .index
+                .range(term.clone()..)
+                .take_while(|(token, _)| token.starts_with(term.as_str()));
+            for (token, postings) in matches {
+                let factor = if token == term {
+                    1.0
+                } else if term.chars().count() >= PREFIX_MIN_CHARS {
+                    0.5
+                } else {
+                    continue;
+                };
+                let idf = (1.0 + total / postings.len() as f64).ln();
+                for (position, weight) in postings {
+                    let score = weight * factor * idf;
+                    let entry = best.entry(*position).or_insert(score);
+                    *entry = entry.max(score);
+                }
+            }
+            for (position, score) in best {
+                *scores.entry(position).or_insert(0.0) += score;
+            }
+        }
+        let mut hits = scores
+            .into_iter()
+            .map(|(position, score)| (&self.entries[position], score))
+            .filter(|(entry, _)| side_effect.is_none_or(|wanted| entry.side_effect == wanted))
+            .filter(|(entry, _)| kind.is_none_or(|wanted| entry.kind == wanted))
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        hits
+    }
+}
+
+/// Lowercased alphanumeric runs of two or more characters; ids split on their dots.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|token| token.chars().count() >= 2)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// The first sentence of `description`, cut at a character limit.
+fn summarize(description: &str) -> String {
+    let sentence = match description.find(". ") {
+        Some(end) => &description[..=end],
+        None => description,
+    };
+    if sentence.chars().count() <= SUMMARY_MAX_CHARS {
+        return sentence.to_string();
+    }
+    let mut summary = sentence
+        .chars()
+        .take(SUMMARY_MAX_CHARS - 1)
+        .collect::<String>();
+    summary.push('…');
+    summary
+}
+
+fn json_len<T: serde::Serialize>(value: &T) -> usize {
+    serde_json::to_vec(value).map_or(usize::MAX, |bytes| bytes.len())
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ToolSearchQuery {
+    query: String,
+    #[serde(default)]
+    limit: Option<usize>,
+    #[serde(default)]
+    side_effect: Option<SideEffect>,
+    #[serde(default)]
+    kind: Option<CatalogKind>,
+}
+
+/// `tool.search`: ranked hits, cut to the limit and then to the response budget.
+pub(crate) async fn handle_tool_search(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<ToolSearchQuery>, QueryRejection>,
+) -> Result<Json<ToolSearchResponse>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    if query.query.trim().is_empty() {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            "query must not be empty",
+            None,
+            None,
+        ));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(TOOL_SEARCH_DEFAULT_LIMIT)
+        .clamp(1, TOOL_SEARCH_MAX_LIMIT);
+    let matches = state
+        .catalog
+        .search(&query.query, query.side_effect, query.kind);
+    let mut response = ToolSearchResponse {
+        hits: Vec::new(),
+        total: matches.len(),
+        truncated: false,
+    };
+    for (entry, score) in matches.into_iter().take(limit) {
+        response.hits.push(ToolSearchHit {
+            id: entry.id.clone(),
+            kind: entry.kind,
+            title: entry.title.clone(),
+            summary: summarize(&entry.description),
+            side_effect: entry.side_effect,
+            score: (score * 1000.0).round() / 1000.0,
+        });
+        if json_len(&response) > TOOL_SEARCH_BUDGET_BYTES {
+            response.hits.pop();
+            response.truncated = true;
+            break;
+        }
+    }
+    Ok(Json(response))
+}
+
+/// `tool.load`: one operation's full contract. Examples are dropped from the end to fit the
+/// budget; an operation whose schemas alone exceed it is refused.
+pub(crate) async fn handle_tool_load(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(tool_id): Path<String>,
+) -> Result<Json<ToolLoadResponse>, ApiError> {
+    authorize(&state, &headers)?;
+    let Some(entry) = state.catalog.get(&tool_id) else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("tool {tool_id} not found"),
+            None,
+            None,
+        ));
+    };
+    let mut response = ToolLoadResponse {
+        id: entry.id.clone(),
+        kind: entry.kind,
+        title: entry.title.clone(),
+        description: entry.description.clone(),
+        side_effect: entry.side_effect,
+        input_schema: entry.input_schema.clone(),
+        output_schema: entry.output_schema.clone(),
+        examples: entry.examples.clone(),
+        safety_notes: entry.safety_notes.clone(),
+        omitted_examples: 0,
+    };
+    while json_len(&response) > TOOL_LOAD_BUDGET_BYTES && response.examples.pop().is_some() {
+        response.omitted_examples += 1;
+    }
+    let size = json_len(&response);
+    if size > TOOL_LOAD_BUDGET_BYTES {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorCode::BudgetExceeded,
+            format!("tool {tool_id} is {size} bytes; budget is {TOOL_LOAD_BUDGET_BYTES}"),
+            Some(serde_json::json!({
+                "tool_id": tool_id,
+                "budget_bytes": TOOL_LOAD_BUDGET_BYTES,
+                "actual_bytes": size,
+            })),
+            None,
+        ));
+    }
+    Ok(Json(response))
+}
+
+/// `tool.run`: submits the operation as a command, so hooks, gates and idempotency apply
+/// exactly as they do to `commands/submit`. Registry tools go through `tool.request`.
+pub(crate) async fn handle_tool_run(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Result<Json<ToolRunRequest>, JsonRejection>,
+) -> Result<Json<ToolRunResponse>, ApiError> {
+    authorize(&state, &headers)?;
+    let Json(request) = payload.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let Some(entry) = state.catalog.get(&request.id) else {
+        return Err(ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("tool {} not found", request.id),
+            None,
+            request.trace_id,
+        ));
+    };
+    let trace_id = request.trace_id.unwrap_or_else(mp_kernel::new_uuid);
+    let (command_type, schema_version, payload) = match entry.kind {
+        CatalogKind::Tool => {
+            let Some(workspace_id) = request.workspace_id else {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::InvalidSchema,
+                    format!("workspace_id is required to run tool {}", entry.id),
+                    None,
+                    Some(trace_id),
+                ));
+            };
+            let payload = serde_json::to_value(ToolRequestPayload {
+                workspace_id,
+                tool_id: entry.id.clone(),
+                input: request.input,
+                session_id: request.session_id,
+            })
+            .map_err(|err| {
+                tracing::error!("serialize tool.request payload failed: {err}");
+                crate::internal_error(Some(trace_id.clone()))
+            })?;
+            (COMMAND_TOOL_REQUEST.to_string(), 1, payload)
+        }
+        CatalogKind::Command => {
+            if request.workspace_id.is_some() || request.session_id.is_some() {
+                return Err(ApiError::new(
+                    StatusCode::BAD_REQUEST,
+                    ErrorCode::InvalidSchema,
+                    format!(
+                        "{} is a command; pass workspace_id and session_id in its input",
+                        entry.id
+                    ),
+                    None,
+                    Some(trace_id),
+                ));
+            }
+            (entry.id.clone(), entry.schema_version, request.input)
+        }
+    };
+    let command = CommandEnvelope {
+        command_type,
+        schema_version,
+        payload,
+        idempotency_key: Some(request.idempotency_key),
+        expected_version: request.expected_version,
+        trace_id,
+        actor: None,
+        lease_id: None,
+    };
+    let response = submit_command_inner(&state, command).await?;
+    Ok(Json(run_summary(response)))
+}
+
+/// Condenses a command response: the acted-on subject, and for tool calls their outcome.
+/// Output that would break the budget is left out; it stays readable through `tool_calls`.
+fn run_summary(response: SubmitCommandResponse) -> ToolRunResponse {
+    let mut summary = ToolRunResponse {
+        accepted: response.accepted,
+        trace_id: response.trace_id,
+        rejection: response.rejection,
+        subject: None,
+        status: None,
+        output: None,
+        error: None,
+        last_seq: response.events.last().map(|event| event.seq_global),
+        truncated: false,
+    };
+    if !summary.accepted {
+        return summary;
+    }
+    // Events recorded by fail-open hooks come first; the command's own follow.
+    summary.subject = response
+        .events
+        .iter()
+        .find(|event| !event.event_type.starts_with("hook."))
+        .map(|event| event.subject.clone());
+    for event in &response.events {
+        if event.event_type == EVENT_TOOL_REQUESTED {
+            summary.status = Some(mp_kernel::ToolCallStatus::Pending);
+        } else if event.event_type == EVENT_TOOL_RESULT {
+            if let Ok(result) = serde_json::from_value::<ToolResultPayload>(event.payload.clone()) {
+                summary.status = Some(result.status);
+                summary.output = result.output;
+                summary.error = result.error;
+            }
+        }
+    }
+    if json_len(&summary) > TOOL_RUN_BUDGET_BYTES {
+        summary.output = None;
+        summary.truncated = true;
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mp_kernel::{ToolDefinition, ToolRegistry};
+    use serde_json::json;
+
+    fn catalog() -> Catalog {
+        let mut registry = SchemaRegistry::new().expect("registry");
+        let tool = |id: &str, title: &str, description: &str, side_effect| ToolDefinition {
+            id: id.to_string(),
+            version: "1.0.0".to_string(),
+            title: title.to_string(),
+            description: description.to_string(),
+            side_effect,
+            input_schema: json!({"type": "object", "additionalProperties": false}),
+            output_schema: json!({"type": "object"}),
+            examples: Vec::new(),
+            safety_notes: None,
+            runtime: None,
+        };
+        registry
+            .register_tools(&ToolRegistry {
+                api_version: 1,
+                version: 1,
+                tools: vec![
+                    tool(
+                        "acme.search.web",
+                        "Web search",
+                        "Searches the public web and returns ranked links.",
+                        SideEffect::Network,
+                    ),
+                    tool(
+                        "acme.git.diff",
+                        "Git diff",
+                        "Shows the diff between two revisions of a worktree.",
+                        SideEffect::Git,
+                    ),
+                ],
+            })
+            .expect("tools");
+        Catalog::build(&registry)
+    }
+
+    fn ids(hits: &[(&CatalogEntry, f64)]) -> Vec<String> {
+        hits.iter().map(|(entry, _)| entry.id.clone()).collect()
+    }
+
+    #[test]
+    fn every_offered_command_is_described() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let described = COMMAND_DOCS.iter().map(|doc| doc.0).collect::<Vec<_>>();
+        for (command_type, _, _) in registry.command_schemas() {
+            if command_type == COMMAND_TOOL_REQUEST || command_type == COMMAND_TOOL_COMPLETE {
+                continue;
+            }
+            assert!(
+                described.contains(&command_type),
+                "{command_type} has no catalog description"
+            );
+        }
+        assert_eq!(described.len(), Catalog::build(&registry).entries.len());
+    }
+
+    #[test]
+    fn search_ranks_titles_over_descriptions_and_matches_prefixes() {
+        let catalog = catalog();
+        let hits = catalog.search("worktree", None, None);
+        // Titles and ids outrank the git tool, which only mentions worktrees in its description.
+        assert_eq!(
+            hits.last().map(|hit| hit.0.id.as_str()),
+            Some("acme.git.diff")
+        );
+        assert!(ids(&hits).contains(&"worktree.register".to_string()));
+
+        let hits = catalog.search("sear", None, None);
+        assert_eq!(ids(&hits), vec!["acme.search.web"]);
+        assert!(catalog.search("se", None, None).is_empty());
+
+        let hits = catalog.search("network", None, None);
+        assert_eq!(ids(&hits), vec!["acme.search.web"]);
+
+        let hits = catalog.search("worktree", Some(SideEffect::Git), None);
+        assert_eq!(ids(&hits), vec!["acme.git.diff"]);
+        let hits = catalog.search("task", None, Some(CatalogKind::Tool));
+        assert!(hits.is_empty());
+    }
+
+    #[test]
+    fn run_summaries_drop_output_over_budget() {
+        let event = |event_type: &str, seq_global, payload| mp_protocol::EventEnvelope {
+            event_id: format!("e{seq_global}"),
+            event_type: event_type.to_string(),
+            timestamp: "2026-01-01T00:00:00Z".to_string(),
+            actor: mp_kernel::Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: mp_kernel::Subject {
+                kind: "tool_call".to_string(),
+                id: "c1".to_string(),
+            },
+            payload,
+            schema_version: 1,
+            seq_global,
+            seq_stream: seq_global,
+            trace_id: None,
+        };
+        let response = |output: Value| SubmitCommandResponse {
+            accepted: true,
+            events: vec![
+                event(EVENT_TOOL_REQUESTED, 1, json!({})),
+                event(
+                    EVENT_TOOL_RESULT,
+                    2,
+                    json!({"tool_id": "acme.big", "status": "succeeded", "output": output}),
+                ),
+            ],
+            rejection: None,
+            trace_id: "t1".to_string(),
+        };
+
+        let small = run_summary(response(json!({"ok": true})));
+        assert_eq!(small.output, Some(json!({"ok": true})));
+        assert_eq!(small.last_seq, Some(2));
+        assert!(!small.truncated);
+
+        let big = run_summary(response(json!({"blob": "x".repeat(TOOL_RUN_BUDGET_BYTES)})));
+        assert_eq!(big.status, Some(mp_kernel::ToolCallStatus::Succeeded));
+        assert!(big.output.is_none());
+        assert!(big.truncated);
+        assert_eq!(
+            big.subject.map(|subject| subject.id),
+            Some("c1".to_string())
+        );
+    }
+
+    #[test]
+    fn summaries_stop_at_the_first_sentence_or_limit() {
+        assert_eq!(summarize("Does one thing. Then more."), "Does one thing.");
+        let long = "word ".repeat(60);
+        let summary = summarize(&long);
+        assert_eq!(summary.chars().count(), SUMMARY_MAX_CHARS);
+        assert!(summary.ends_with('…'));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, project_exists, reject_command,
+    reject_command_after, sessions::load_session, tasks, tasks::load_task, ApiError, AppState,
+    CommandOutcome,
+};
+use mp_kernel::{
+    command_kind, Actor, CommandKind, ErrorCode, GateDecidedPayload, GateDecisionPayload,
+    GateDefinePayload, GateDefinedPayload, GateEntry, GateKind, GateStatus, Subject, TaskAction,
+    EVENT_GATE_APPROVED, EVENT_GATE_DEFINED, EVENT_GATE_REJECTED,
+};
+use mp_protocol::{CommandEnvelope, SubmitCommandResponse};
+use mp_storage::{NewEvent, ProjectionReader};
+use serde_json::json;
+
+pub(crate) async fn plan_define(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: GateDefinePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let guarded = payload.scope.command_type.as_str();
+    // Gating gate.* commands could leave a gate that nothing is allowed to approve.
+    if command_kind(guarded) != Some(CommandKind::StateChanging) || guarded.starts_with("gate.") {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("{guarded} cannot be guarded by a gate"),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    let delay_ok = match payload.kind {
+        GateKind::Delay => payload.delay_ms.is_some() && payload.required_approvals.is_none(),
+        _ => payload.delay_ms.is_none(),
+    };
+    if !delay_ok {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            "delay_ms is required for delay gates and approvals are not",
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if !subject_exists(
+        state,
+        command,
+        &payload.workspace_id,
+        &payload.scope.subject,
+    )
+    .await?
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!(
+                "{} {} not found",
+                payload.scope.subject.kind, payload.scope.subject.id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let required_approvals = match payload.kind {
+        GateKind::Delay => 0,
+        _ => payload.required_approvals.unwrap_or(1),
+    };
+    let payload_json = serde_json::to_value(GateDefinedPayload {
+        scope: payload.scope,
+        kind: payload.kind,
+        required_approvals,
+        delay_ms: payload.delay_ms,
+        description: payload.description,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize gate.defined payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_GATE_DEFINED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: None,
+        subject: Subject {
+            kind: "gate".to_string(),
+            id: mp_kernel::new_uuid(),
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+/// Plans `gate.approve` (`approve == true`) or `gate.reject`.
+pub(crate) async fn plan_decision(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    approve: bool,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: GateDecisionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let gate = {
+        let store = state.store.lock().await;
+        store
+            .get_gate(&payload.workspace_id, &payload.gate_id)
+            .map_err(|err| {
+                tracing::error!("get_gate failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let Some(gate) = gate else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("gate {} not found", payload.gate_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if gate.status != GateStatus::Open {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("gate {} is already {}", gate.gate_id, gate.status),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if gate.has_decided(&payload.approver) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "{} {} already decided gate {}",
+                payload.approver.kind, payload.approver.id, gate.gate_id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if approve && !gate.kind.accepts(payload.approver.kind) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "{} gates do not accept approvals from {}",
+                gate.kind, payload.approver.kind
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    // The approver is new and accepted by this gate kind, so it adds exactly one approval.
+    let status = if !approve {
+        GateStatus::Rejected
+    } else if gate.approval_count() + 1 >= gate.required_approvals {
+        GateStatus::Approved
+    } else {
+        GateStatus::Open
+    };
+    let payload_json = serde_json::to_value(GateDecidedPayload {
+        approver: payload.approver,
+        scope: gate.scope,
+        reason: payload.reason,
+        evidence: payload.evidence,
+        status,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize gate decision payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: if approve {
+            EVENT_GATE_APPROVED
+        } else {
+            EVENT_GATE_REJECTED
+        }
+        .to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: None,
+        subject: Subject {
+            kind: "gate".to_string(),
+            id: gate.gate_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+/// Rejects `command` with `PolicyDenied` while any gate guarding it is unsatisfied. A running
+/// task whose lifecycle command is refused is held in `blocked_by_approval`.
+pub(crate) async fn enforce(
+    state: &AppState,
+    command: &CommandEnvelope,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    let Some(workspace_id) = command
+        .payload
+        .get("workspace_id")
+        .and_then(|value| value.as_str())
+    else {
+        return Ok(None);
+    };
+    let gates = {
+        let store = state.store.lock().await;
+        store
+            .list_gates(workspace_id, Some(&command.command_type))
+            .map_err(|err| {
+                tracing::error!("list_gates failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let now = state.clock.now();
+    let blocking: Vec<&GateEntry> = gates
+        .iter()
+        .filter(|gate| gate.scope.targets(&command.payload) && !gate.is_satisfied(now))
+        .collect();
+    if blocking.is_empty() {
+        return Ok(None);
+    }
+
+    let ids: Vec<&str> = blocking.iter().map(|gate| gate.gate_id.as_str()).collect();
+    let details = json!({
+        "gates": blocking
+            .iter()
+            .map(|gate| json!({
+                "gate_id": gate.gate_id,
+                "kind": gate.kind,
+                "status": gate.status,
+                "required_approvals": gate.required_approvals,
+                "approvals": gate.approval_count(),
+            }))
+            .collect::<Vec<_>>()
+    });
+    let message = format!(
+        "{} blocked by gate {}",
+        command.command_type,
+        ids.join(", ")
+    );
+    let held = tasks::hold_event(state, command, TaskAction::BlockOnApproval, &message).await?;
+    reject_command_after(
+        state,
+        command,
+        ErrorCode::PolicyDenied,
+        &message,
+        Some(details),
+        held.into_iter().collect(),
+    )
+    .await
+    .map(Some)
+}
+
+/// Whether an approval gate covering `command` has been approved.
+pub(crate) async fn approved(
+    state: &AppState,
+    command: &CommandEnvelope,
+) -> Result<bool, ApiError> {
+    let Some(workspace_id) = command
+        .payload
+        .get("workspace_id")
+        .and_then(|value| value.as_str())
+    else {
+        return Ok(false);
+    };
+    let gates = {
+        let store = state.store.lock().await;
+        store
+            .list_gates(workspace_id, Some(&command.command_type))
+            .map_err(|err| {
+                tracing::error!("list_gates failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let now = state.clock.now();
+    Ok(gates.iter().any(|gate| {
+        gate.kind != GateKind::Delay
+            && gate.scope.targets(&command.payload)
+            && gate.is_satisfied(now)
+    }))
+}
+
+async fn subject_exists(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    subject: &Subject,
+) -> Result<bool, ApiError> {
+    match subject.kind.as_str() {
+        "workspace" => Ok(subject.id == workspace_id),
+        "project" => project_exists(state, command, workspace_id, &subject.id).await,
+        "session" => Ok(load_session(state, command, workspace_id, &subject.id)
+            .await?
+            .is_some()),
+        "task" => Ok(load_task(state, command, workspace_id, &subject.id)
+            .await?
+            .is_some()),
+        _ => Ok(false),
+    }
+}
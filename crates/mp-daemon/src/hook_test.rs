@@ -0,0 +1,666 @@
+//! Offline replay of hook chains for hook authors.
+//!
+//! Cases are the values hooks saw on a daemon: rebuilt from submitted `CommandEnvelope`s, or
+//! read back from `hook.input_recorded` events (`mpd start --record-hook-inputs`). Each case is
+//! replayed through a chain the way the daemon runs it, compared with an expected fixture, and
+//! optionally fuzzed: every hook is fed malformed copies of the input to show it stays within
+//! its budgets. Reports are deterministic for a given chain, cases, seed and module behaviour.
+
+use crate::hooks::{command_phases, evaluate_hook, HookHost};
+use crate::sandbox::{is_limit_error, Sandbox};
+use crate::{Clock, ManualClock};
+use anyhow::Context;
+use mp_kernel::{
+    HookAction, HookChain, HookDecision, HookInputRecordedPayload, HookPhase, HookSpec,
+    SandboxHostcallPayload, EVENT_HOOK_INPUT_RECORDED, EVENT_SANDBOX_HOSTCALL,
+};
+use mp_protocol::{CommandEnvelope, EventEnvelope, SchemaRegistry};
+use mp_storage_sqlite::SqliteStore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use time::OffsetDateTime;
+use tokio::sync::Mutex;
+
+/// Workspace and trace ids sandbox events are recorded against during a replay.
+const REPLAY_SCOPE: (&str, &str) = ("hook-test", "hook-test");
+
+/// Fuzz failures kept per hook; the counters still cover every run.
+const MAX_FUZZ_FINDINGS: usize = 16;
+
+/// Length of the strings `oversize` mutations substitute.
+const OVERSIZE_CHARS: usize = 64 * 1024;
+
+/// Depth of the arrays `nest` mutations substitute.
+const NEST_DEPTH: usize = 64;
+
+/// One value a phase's hooks received, replayable offline.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookTestCase {
+    /// Stable name; expected fixtures are stored as `<name>.json`.
+    pub name: String,
+    pub phase: HookPhase,
+    pub command_type: String,
+    pub input: Value,
+}
+
+impl HookTestCase {
+    fn new(trace_id: &str, phase: HookPhase, command_type: &str, input: Value) -> Self {
+        let name = format!("{command_type}.{phase}.{trace_id}")
+            .chars()
+            .map(|ch| {
+                if ch.is_ascii_alphanumeric() || matches!(ch, '.' | '_' | '-') {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        Self {
+            name,
+            phase,
+            command_type: command_type.to_string(),
+            input,
+        }
+    }
+}
+
+/// The phases the daemon runs for each submitted command, with the payload as input.
+pub fn cases_from_commands(commands: &[CommandEnvelope]) -> Vec<HookTestCase> {
+    let mut cases = Vec::new();
+    for command in commands {
+        for &phase in command_phases(&command.command_type) {
+            cases.push(HookTestCase::new(
+                &command.trace_id,
+                phase,
+                &command.command_type,
+                command.payload.clone(),
+            ));
+        }
+    }
+    cases
+}
+
+/// Cases for every `hook.input_recorded` event; other events are skipped.
+pub fn cases_from_events(events: &[EventEnvelope]) -> anyhow::Result<Vec<HookTestCase>> {
+    events
+        .iter()
+        .filter(|event| event.event_type == EVENT_HOOK_INPUT_RECORDED)
+        .map(|event| {
+            let recorded: HookInputRecordedPayload = serde_json::from_value(event.payload.clone())
+                .with_context(|| format!("invalid hook input in event {}", event.event_id))?;
+            let trace_id = event.trace_id.as_deref().unwrap_or(&event.event_id);
+            Ok(HookTestCase::new(
+                trace_id,
+                recorded.phase,
+                &recorded.command_type,
+                recorded.input,
+            ))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HookTestOptions {
+    /// Directory of expected outcomes, one `<case name>.json` per case.
+    pub fixtures: Option<PathBuf>,
+    /// Writes the actual outcomes as the new fixtures instead of comparing.
+    pub update_fixtures: bool,
+    /// Malformed inputs fed to each hook per case; zero disables fuzzing.
+    pub fuzz_budget: u32,
+    pub seed: u64,
+    /// Database capsule hostcalls read from; defaults to an empty in-memory store.
+    pub db: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayDecision {
+    Continue,
+    Block,
+    RequireApproval,
+    Failed,
+}
+
+/// How a case ended; this is what fixtures record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookTestOutcome {
+    pub decision: ReplayDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// The value after every hook that continued.
+    pub output: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FixtureStatus {
+    Pass,
+    Fail,
+    Missing,
+    Updated,
+    Unchecked,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FixtureDiff {
+    /// JSON pointer into the outcome.
+    pub pointer: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookStepReport {
+    pub hook_id: String,
+    pub hook_version: String,
+    pub decision: ReplayDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub fuel_used: u64,
+    /// Hostcalls the hook's module made, in order, as `name` or `name: error`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hostcalls: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookCaseReport {
+    pub name: String,
+    pub phase: HookPhase,
+    pub command_type: String,
+    pub steps: Vec<HookStepReport>,
+    pub outcome: HookTestOutcome,
+    pub status: FixtureStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diff: Vec<FixtureDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FuzzFinding {
+    pub case: String,
+    /// The mutation applied, e.g. `oversize /body/text`.
+    pub mutation: String,
+    pub error: String,
+}
+
+/// Malformed-input runs of one hook, across every case it applies to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookFuzzReport {
+    pub hook_id: String,
+    pub hook_version: String,
+    pub runs: u64,
+    pub continued: u64,
+    pub blocked: u64,
+    pub approvals: u64,
+    pub failed: u64,
+    /// Runs that hit the module's fuel, memory or wall-clock limit.
+    pub limits_hit: u64,
+    pub peak_fuel: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fuel_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<FuzzFinding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookTestSummary {
+    pub cases: u64,
+    pub passed: u64,
+    pub failed: u64,
+    pub missing: u64,
+    pub fuzz_runs: u64,
+    pub limits_hit: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookTestReport {
+    /// `id@version` of every hook, with the module hash for `wasm` hooks.
+    pub chain: Vec<String>,
+    pub seed: u64,
+    pub fuzz_budget: u32,
+    pub cases: Vec<HookCaseReport>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fuzz: Vec<HookFuzzReport>,
+    pub summary: HookTestSummary,
+}
+
+impl HookTestReport {
+    /// No fixture mismatched and no fuzzed hook ran out of budget.
+    pub fn passed(&self) -> bool {
+        self.summary.failed == 0 && self.summary.limits_hit == 0
+    }
+
+    /// Pretty JSON with a trailing newline, byte-identical for identical reports.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)? + "\n")
+    }
+}
+
+/// Replays `cases` through `chain`, compares fixtures and fuzzes each applicable hook.
+///
+/// `wasm` modules run with their declared capabilities and limits against a fixed clock, so
+/// `clock_now` always returns the Unix epoch.
+pub async fn run_hook_tests(
+    chain: &HookChain,
+    cases: &[HookTestCase],
+    options: &HookTestOptions,
+) -> anyhow::Result<HookTestReport> {
+    let sandbox = Sandbox::for_hook_chain(chain)?;
+    let store = match &options.db {
+        Some(path) => SqliteStore::open(path)?,
+        None => SqliteStore::open(Path::new(":memory:"))?,
+    };
+    let store = Arc::new(Mutex::new(store));
+    let clock: Arc<dyn Clock> = Arc::new(ManualClock::new(OffsetDateTime::UNIX_EPOCH));
+    let host = HookHost {
+        sandbox: &sandbox,
+        store: &store,
+        clock: &clock,
+    };
+    let registry = SchemaRegistry::new()?;
+
+    let mut reports = Vec::new();
+    let mut fuzz: BTreeMap<usize, HookFuzzReport> = BTreeMap::new();
+    for case in cases {
+        let (steps, outcome) = replay(&host, &registry, chain, case).await;
+        let (status, diff) = check_fixture(options, case, &outcome)?;
+        reports.push(HookCaseReport {
+            name: case.name.clone(),
+            phase: case.phase,
+            command_type: case.command_type.clone(),
+            steps,
+            outcome,
+            status,
+            diff,
+        });
+
+        let mut rng = SplitMix64::new(options.seed ^ name_seed(&case.name));
+        for _ in 0..options.fuzz_budget {
+            let (mutation, input) = mutate(&case.input, &mut rng);
+            for (index, hook) in chain.hooks.iter().enumerate() {
+                if !hook.applies_to(case.phase, &case.command_type) {
+                    continue;
+                }
+                let report = fuzz
+                    .entry(index)
+                    .or_insert_with(|| fuzz_report(hook, &sandbox));
+                fuzz_hook(&host, hook, case, &mutation, input.clone(), report).await;
+            }
+        }
+    }
+
+    let fuzz: Vec<HookFuzzReport> = fuzz.into_values().collect();
+    let count = |status: FixtureStatus| {
+        reports
+            .iter()
+            .filter(|report| report.status == status)
+            .count() as u64
+    };
+    let summary = HookTestSummary {
+        cases: reports.len() as u64,
+        passed: count(FixtureStatus::Pass),
+        failed: count(FixtureStatus::Fail),
+        missing: count(FixtureStatus::Missing),
+        fuzz_runs: fuzz.iter().map(|report| report.runs).sum(),
+        limits_hit: fuzz.iter().map(|report| report.limits_hit).sum(),
+    };
+    Ok(HookTestReport {
+        chain: chain
+            .hooks
+            .iter()
+            .map(|hook| match module_hash(hook, &sandbox) {
+                Some(hash) => format!("{}@{} {hash}", hook.id, hook.version),
+                None => format!("{}@{}", hook.id, hook.version),
+            })
+            .collect(),
+        seed: options.seed,
+        fuzz_budget: options.fuzz_budget,
+        cases: reports,
+        fuzz,
+        summary,
+    })
+}
+
+/// Runs the chain the way the daemon does, stopping at the first hook that does not continue.
+/// Approval gates cannot be consulted offline, so `require_approval` always stops the chain.
+async fn replay(
+    host: &HookHost<'_>,
+    registry: &SchemaRegistry,
+    chain: &HookChain,
+    case: &HookTestCase,
+) -> (Vec<HookStepReport>, HookTestOutcome) {
+    let mut target = case.input.clone();
+    let mut steps = Vec::new();
+    for hook in chain.for_phase(case.phase, &case.command_type) {
+        let mut candidate = target.clone();
+        let outcome = evaluate_hook(
+            host,
+            hook,
+            case.phase,
+            &case.command_type,
+            REPLAY_SCOPE,
+            &mut candidate,
+        )
+        .await;
+        let result = outcome.result.and_then(|decision| {
+            // Every phase but post_tool sees the command payload.
+            let command_phase = case.phase != HookPhase::PostTool;
+            if command_phase && candidate != target {
+                registry
+                    .validate_command_payload(&case.command_type, 1, &candidate)
+                    .map_err(|err| format!("rewritten payload is invalid: {}", err.message))?;
+            }
+            Ok(decision)
+        });
+        let (decision, reason) = match result {
+            Ok(HookDecision::Continue) => (ReplayDecision::Continue, None),
+            Ok(HookDecision::Block { reason }) => (ReplayDecision::Block, Some(reason)),
+            Ok(HookDecision::RequireApproval { reason }) => {
+                (ReplayDecision::RequireApproval, Some(reason))
+            }
+            Err(error) => (ReplayDecision::Failed, Some(error)),
+        };
+        steps.push(HookStepReport {
+            hook_id: hook.id.clone(),
+            hook_version: hook.version.clone(),
+            decision,
+            reason: reason.clone(),
+            fuel_used: outcome.fuel_used,
+            hostcalls: outcome
+                .events
+                .iter()
+                .filter(|event| event.event_type == EVENT_SANDBOX_HOSTCALL)
+                .filter_map(|event| {
+                    serde_json::from_value::<SandboxHostcallPayload>(event.payload.clone()).ok()
+                })
+                .map(|call| match call.error {
+                    Some(error) => format!("{}: {error}", call.hostcall),
+                    None => call.hostcall.to_string(),
+                })
+                .collect(),
+        });
+        if decision != ReplayDecision::Continue {
+            return (
+                steps,
+                HookTestOutcome {
+                    decision,
+                    hook_id: Some(hook.id.clone()),
+                    reason,
+                    output: target,
+                },
+            );
+        }
+        target = candidate;
+    }
+    (
+        steps,
+        HookTestOutcome {
+            decision: ReplayDecision::Continue,
+            hook_id: None,
+            reason: None,
+            output: target,
+        },
+    )
+}
+
+fn check_fixture(
+    options: &HookTestOptions,
+    case: &HookTestCase,
+    outcome: &HookTestOutcome,
+) -> anyhow::Result<(FixtureStatus, Vec<FixtureDiff>)> {
+    let Some(dir) = &options.fixtures else {
+        return Ok((FixtureStatus::Unchecked, Vec::new()));
+    };
+    let path = dir.join(format!("{}.json", case.name));
+    if options.update_fixtures {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("failed to create {}", dir.display()))?;
+        std::fs::write(&path, serde_json::to_string_pretty(outcome)? + "\n")
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        return Ok((FixtureStatus::Updated, Vec::new()));
+    }
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((FixtureStatus::Missing, Vec::new()));
+        }
+        Err(err) => return Err(err).with_context(|| format!("failed to read {}", path.display())),
+    };
+    let expected: Value = serde_json::from_slice(&bytes)
+        .with_context(|| format!("invalid fixture {}", path.display()))?;
+    let mut diff = Vec::new();
+    diff_values(
+        "",
+        Some(&expected),
+        Some(&serde_json::to_value(outcome)?),
+        &mut diff,
+    );
+    let status = if diff.is_empty() {
+        FixtureStatus::Pass
+    } else {
+        FixtureStatus::Fail
+    };
+    Ok((status, diff))
+}
+
+/// Every leaf where `expected` and `actual` differ, in key order.
+pub(crate) fn diff_values(
+    pointer: &str,
+    expected: Option<&Value>,
+    actual: Option<&Value>,
+    out: &mut Vec<FixtureDiff>,
+) {
+    match (expected, actual) {
+        (Some(Value::Object(expected)), Some(Value::Object(actual))) => {
+            let mut keys: Vec<&String> = expected.keys().chain(actual.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let child = format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                diff_values(&child, expected.get(key), actual.get(key), out);
+            }
+        }
+        (Some(Value::Array(expected)), Some(Value::Array(actual))) => {
+            for index in 0..expected.len().max(actual.len()) {
+                let child = format!("{pointer}/{index}");
+                diff_values(&child, expected.get(index), actual.get(index), out);
+            }
+        }
+        (expected, actual) if expected != actual => out.push(FixtureDiff {
+            pointer: pointer.to_string(),
+            expected: expected.cloned(),
+            actual: actual.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+fn fuzz_report(hook: &HookSpec, sandbox: &Sandbox) -> HookFuzzReport {
+    let fuel_limit = match &hook.action {
+        HookAction::Wasm { limits, .. } if module_hash(hook, sandbox).is_some() => {
+            Some(limits.fuel)
+        }
+        _ => None,
+    };
+    HookFuzzReport {
+        hook_id: hook.id.clone(),
+        hook_version: hook.version.clone(),
+        fuel_limit,
+        ..HookFuzzReport::default()
+    }
+}
+
+async fn fuzz_hook(
+    host: &HookHost<'_>,
+    hook: &HookSpec,
+    case: &HookTestCase,
+    mutation: &str,
+    mut input: Value,
+    report: &mut HookFuzzReport,
+) {
+    let outcome = evaluate_hook(
+        host,
+        hook,
+        case.phase,
+        &case.command_type,
+        REPLAY_SCOPE,
+        &mut input,
+    )
+    .await;
+    report.runs += 1;
+    report.peak_fuel = report.peak_fuel.max(outcome.fuel_used);
+    match outcome.result {
+        Ok(HookDecision::Continue) => report.continued += 1,
+        Ok(HookDecision::Block { .. }) => report.blocked += 1,
+        Ok(HookDecision::RequireApproval { .. }) => report.approvals += 1,
+        Err(error) => {
+            report.failed += 1;
+            if is_limit_error(&error) {
+                report.limits_hit += 1;
+            }
+            if report.findings.len() < MAX_FUZZ_FINDINGS {
+                report.findings.push(FuzzFinding {
+                    case: case.name.clone(),
+                    mutation: mutation.to_string(),
+                    error,
+                });
+            }
+        }
+    }
+}
+
+fn module_hash(hook: &HookSpec, sandbox: &Sandbox) -> Option<String> {
+    match &hook.action {
+        HookAction::Wasm { module, .. } => sandbox.module(&module.path).map(|loaded| loaded.hash),
+        _ => None,
+    }
+}
+
+fn name_seed(name: &str) -> u64 {
+    let hash = blake3::hash(name.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&hash.as_bytes()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Small fixed PRNG so fuzz inputs stay identical across platforms and dependency upgrades.
+pub(crate) struct SplitMix64(u64);
+
+impl SplitMix64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+}
+
+/// A malformed copy of `input`: one value, picked at random, removed or replaced with a wrong
+/// type, an oversized string or deep nesting.
+pub(crate) fn mutate(input: &Value, rng: &mut SplitMix64) -> (String, Value) {
+    let mut pointers = Vec::new();
+    collect_pointers(input, String::new(), &mut pointers);
+    let pointer = pointers[rng.below(pointers.len())].clone();
+    let mut output = input.clone();
+    let kinds = ["remove", "null", "retype", "oversize", "nest", "truncate"];
+    let kind = kinds[rng.below(kinds.len())];
+    let label = if pointer.is_empty() {
+        "<root>"
+    } else {
+        &pointer
+    };
+    if kind == "remove" {
+        if let Some((parent, key)) = pointer.rsplit_once('/') {
+            let key = key.replace("~1", "/").replace("~0", "~");
+            match output.pointer_mut(parent) {
+                Some(Value::Object(map)) => {
+                    map.remove(&key);
+                }
+                Some(Value::Array(items)) => {
+                    if let Ok(index) = key.parse::<usize>() {
+                        items.remove(index);
+                    }
+                }
+                _ => {}
+            }
+            return (format!("{kind} {label}"), output);
+        }
+    }
+    let slot = output.pointer_mut(&pointer).expect("collected pointer");
+    match kind {
+        // Removing the root leaves nothing to send; treat it as null.
+        "remove" | "null" => *slot = Value::Null,
+        "retype" => {
+            *slot = match slot {
+                Value::String(_) => json!(0),
+                Value::Number(_) => json!("0"),
+                Value::Bool(_) => json!("true"),
+                Value::Array(_) => json!({}),
+                Value::Object(_) => json!([]),
+                Value::Null => json!({}),
+            }
+        }
+        "oversize" => *slot = Value::String("x".repeat(OVERSIZE_CHARS)),
+        "nest" => {
+            let mut nested = Value::Null;
+            for _ in 0..NEST_DEPTH {
+                nested = Value::Array(vec![nested]);
+            }
+            *slot = nested;
+        }
+        _ => {
+            *slot = match slot {
+                Value::String(text) => {
+                    Value::String(text.chars().take(text.chars().count() / 2).collect())
+                }
+                Value::Array(items) => Value::Array(items[..items.len() / 2].to_vec()),
+                Value::Object(_) => json!({}),
+                _ => json!(""),
+            }
+        }
+    }
+    (format!("{kind} {label}"), output)
+}
+
+fn collect_pointers(value: &Value, pointer: String, out: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                let child_pointer =
+                    format!("{pointer}/{}", key.replace('~', "~0").replace('/', "~1"));
+                collect_pointers(child, child_pointer, out);
+            }
+        }
+        Value::Array(items) => {
+            for (index, child) in items.iter().enumerate() {
+                collect_pointers(child, format!("{pointer}/{index}"), out);
+            }
+        }
+        _ => {}
+    }
+    out.push(pointer);
+}
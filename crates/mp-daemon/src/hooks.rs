@@ -0,0 +1,348 @@
+use crate::sandbox::{Invocation, Sandbox};
+use crate::{
+    command_workspace_id, gates, internal_error, reject_command_after, ApiError, AppState, Clock,
+};
+use mp_kernel::{
+    Actor, ErrorCode, HookAction, HookDecision, HookFailedPayload, HookInputRecordedPayload,
+    HookPhase, HookSpec, Subject, WasmHookDecision, WasmHookOutput, COMMAND_MESSAGE_SEND,
+    COMMAND_TOOL_REQUEST, EVENT_HOOK_FAILED, EVENT_HOOK_INPUT_RECORDED,
+};
+use mp_protocol::{CommandEnvelope, SubmitCommandResponse};
+use mp_storage::NewEvent;
+use mp_storage_sqlite::SqliteStore;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// What `wasm` hooks run against: the daemon's store and clock, or a replay's scratch copies.
+pub(crate) struct HookHost<'a> {
+    pub(crate) sandbox: &'a Sandbox,
+    pub(crate) store: &'a Arc<Mutex<SqliteStore>>,
+    pub(crate) clock: &'a Arc<dyn Clock>,
+}
+
+/// One hook's verdict, with the events its module recorded and the fuel it burned.
+pub(crate) struct HookOutcome {
+    pub(crate) result: Result<HookDecision, String>,
+    pub(crate) events: Vec<NewEvent>,
+    pub(crate) fuel_used: u64,
+}
+
+/// The phases that run over a command's payload before it is planned, in order.
+///
+/// `message.send` is where agent output enters the kernel, so after `pre_send` has shaped it
+/// `post_receive` decides whether it is accepted. `tool.request` runs `pre_tool` before the tool
+/// is validated or run; `post_tool` runs on results, see [`crate::tools`].
+pub(crate) fn command_phases(command_type: &str) -> &'static [HookPhase] {
+    match command_type {
+        COMMAND_MESSAGE_SEND => &[
+            HookPhase::PreCommand,
+            HookPhase::PreSend,
+            HookPhase::PostReceive,
+        ],
+        COMMAND_TOOL_REQUEST => &[HookPhase::PreCommand, HookPhase::PreTool],
+        _ => &[HookPhase::PreCommand],
+    }
+}
+
+/// Runs the command-level phases over `command.payload`, keeping any rewrites.
+///
+/// Returns the events the chain recorded (recorded inputs, sandbox hostcalls, and `hook.failed`
+/// tolerated in fail-open mode), to be appended with the command's own events, or the recorded rejection.
+pub(crate) async fn intercept_command(
+    state: &AppState,
+    command: &mut CommandEnvelope,
+) -> Result<Result<Vec<NewEvent>, SubmitCommandResponse>, ApiError> {
+    let phases = command_phases(&command.command_type);
+    let registry = state.schema_registry.clone();
+    let (command_type, schema_version) = (command.command_type.clone(), command.schema_version);
+    // A rewrite must leave a payload the command schema still accepts.
+    let check = |payload: &Value| {
+        registry
+            .validate_command_payload(&command_type, schema_version, payload)
+            .map_err(|err| format!("rewritten payload is invalid: {}", err.message))
+    };
+
+    let mut recorded = Vec::new();
+    for &phase in phases {
+        let mut payload = command.payload.clone();
+        if let Some(rejected) =
+            run_phase(state, command, phase, &mut payload, &check, &mut recorded).await?
+        {
+            return Ok(Err(rejected));
+        }
+        command.payload = payload;
+    }
+    Ok(Ok(recorded))
+}
+
+/// Why a phase stopped an action: the rejection message and its `details`.
+pub(crate) struct Refusal {
+    pub(crate) message: String,
+    pub(crate) details: Value,
+}
+
+/// Runs every hook registered for `phase` over `target`, in chain order.
+///
+/// Events hooks produce, including `hook.failed` for a hook that errors, are pushed to
+/// `recorded`. Unless the daemon runs fail-open, an error also rejects the command; blocks and
+/// unapproved approval requests always do. A rejection carries `recorded` with it.
+pub(crate) async fn run_phase(
+    state: &AppState,
+    command: &CommandEnvelope,
+    phase: HookPhase,
+    target: &mut Value,
+    check: &(dyn Fn(&Value) -> Result<(), String> + Sync),
+    recorded: &mut Vec<NewEvent>,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    let Some(refusal) = check_phase(state, command, phase, target, check, recorded).await? else {
+        return Ok(None);
+    };
+    reject_command_after(
+        state,
+        command,
+        ErrorCode::PolicyDenied,
+        &refusal.message,
+        Some(refusal.details),
+        std::mem::take(recorded),
+    )
+    .await
+    .map(Some)
+}
+
+/// Like [`run_phase`], but hands a refusal back instead of recording `command.rejected`, for
+/// actions whose command is already on record.
+pub(crate) async fn check_phase(
+    state: &AppState,
+    command: &CommandEnvelope,
+    phase: HookPhase,
+    target: &mut Value,
+    check: &(dyn Fn(&Value) -> Result<(), String> + Sync),
+    recorded: &mut Vec<NewEvent>,
+) -> Result<Option<Refusal>, ApiError> {
+    let host = HookHost {
+        sandbox: &state.sandbox,
+        store: &state.store,
+        clock: &state.clock,
+    };
+    let workspace_id = command_workspace_id(command);
+    let mut hooks = state
+        .hook_chain
+        .for_phase(phase, &command.command_type)
+        .peekable();
+    if state.record_hook_inputs && hooks.peek().is_some() {
+        recorded.push(input_event(command, phase, target)?);
+    }
+    for hook in hooks {
+        // Hooks work on a copy so a failing hook never leaves a half-applied rewrite.
+        let mut candidate = target.clone();
+        let outcome = evaluate_hook(
+            &host,
+            hook,
+            phase,
+            &command.command_type,
+            (&workspace_id, &command.trace_id),
+            &mut candidate,
+        )
+        .await;
+        recorded.extend(outcome.events);
+        let result = outcome.result.and_then(|decision| {
+            if candidate != *target {
+                check(&candidate)?;
+            }
+            Ok(decision)
+        });
+        let (decision, reason, message) = match result {
+            Ok(HookDecision::Continue) => {
+                *target = candidate;
+                continue;
+            }
+            Ok(HookDecision::Block { reason }) => {
+                let message = format!(
+                    "{} blocked by hook {}: {reason}",
+                    command.command_type, hook.id
+                );
+                ("block", reason, message)
+            }
+            Ok(HookDecision::RequireApproval { reason }) => {
+                if gates::approved(state, command).await? {
+                    continue;
+                }
+                let message = format!(
+                    "{} requires approval from hook {}: {reason}",
+                    command.command_type, hook.id
+                );
+                ("require_approval", reason, message)
+            }
+            Err(error) => {
+                recorded.push(failed_event(state, command, hook, phase, &error)?);
+                if state.hooks_fail_open {
+                    tracing::warn!("hook {} failed open during {phase}: {error}", hook.id);
+                    continue;
+                }
+                let message = format!("hook {} failed during {phase}: {error}", hook.id);
+                ("failed", error, message)
+            }
+        };
+
+        let details = json!({
+            "hook_id": hook.id,
+            "hook_version": hook.version,
+            "phase": phase,
+            "decision": decision,
+            "reason": reason,
+        });
+        return Ok(Some(Refusal { message, details }));
+    }
+    Ok(None)
+}
+
+/// Applies `hook` to `target` in place. `wasm` hooks run in the sandbox on a blocking thread,
+/// see `{phase, command_type, target}` and answer with a [`WasmHookOutput`]; their events are
+/// recorded against `scope` (workspace id, trace id).
+pub(crate) async fn evaluate_hook(
+    host: &HookHost<'_>,
+    hook: &HookSpec,
+    phase: HookPhase,
+    command_type: &str,
+    scope: (&str, &str),
+    target: &mut Value,
+) -> HookOutcome {
+    let HookAction::Wasm {
+        module,
+        capabilities,
+        limits,
+    } = &hook.action
+    else {
+        return HookOutcome {
+            result: hook.action.apply(target),
+            events: Vec::new(),
+            fuel_used: 0,
+        };
+    };
+    let failed = |error: String| HookOutcome {
+        result: Err(error),
+        events: Vec::new(),
+        fuel_used: 0,
+    };
+    let Some(loaded) = host.sandbox.module(&module.path) else {
+        return failed(format!("module {} is not loaded", module.path));
+    };
+    let (workspace_id, trace_id) = scope;
+    let invocation = Invocation {
+        module_id: hook.id.clone(),
+        subject: Subject {
+            kind: "hook".to_string(),
+            id: hook.id.clone(),
+        },
+        workspace_id: workspace_id.to_string(),
+        trace_id: trace_id.to_string(),
+        capabilities: capabilities.clone(),
+        limits: *limits,
+        store: host.store.clone(),
+        clock: host.clock.clone(),
+    };
+    let input = json!({
+        "phase": phase,
+        "command_type": command_type,
+        "target": target.clone(),
+    });
+    let invoked = match tokio::task::spawn_blocking(move || loaded.invoke(invocation, &input)).await
+    {
+        Ok(invoked) => invoked,
+        Err(err) => {
+            tracing::error!("sandbox task for hook {} failed: {err}", hook.id);
+            return failed("sandbox task failed".to_string());
+        }
+    };
+
+    let result = invoked.output.and_then(|output| {
+        let output = serde_json::from_value::<WasmHookOutput>(output)
+            .map_err(|err| format!("invalid hook output: {err}"))?;
+        let reason = output
+            .reason
+            .unwrap_or_else(|| "no reason given".to_string());
+        Ok(match output.decision {
+            WasmHookDecision::Continue => {
+                if let Some(rewritten) = output.target {
+                    *target = rewritten;
+                }
+                HookDecision::Continue
+            }
+            WasmHookDecision::Block => HookDecision::Block { reason },
+            WasmHookDecision::RequireApproval => HookDecision::RequireApproval { reason },
+        })
+    });
+    HookOutcome {
+        result,
+        events: invoked.events,
+        fuel_used: invoked.fuel_used,
+    }
+}
+
+/// Records what a phase's hooks see. Like every hook event it names the command's workspace,
+/// which the pipeline swaps for the new one when a `workspace.create` is accepted.
+fn input_event(
+    command: &CommandEnvelope,
+    phase: HookPhase,
+    target: &Value,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(HookInputRecordedPayload {
+        phase,
+        command_type: command.command_type.clone(),
+        input: target.clone(),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize hook.input_recorded payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: EVENT_HOOK_INPUT_RECORDED.to_string(),
+        schema_version: 1,
+        actor: Actor::system(),
+        workspace_id: command_workspace_id(command),
+        project_id: None,
+        subject: Subject {
+            kind: "command".to_string(),
+            id: command.trace_id.clone(),
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
+
+fn failed_event(
+    state: &AppState,
+    command: &CommandEnvelope,
+    hook: &HookSpec,
+    phase: HookPhase,
+    error: &str,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(HookFailedPayload {
+        hook_id: hook.id.clone(),
+        hook_version: hook.version.clone(),
+        phase,
+        command_type: command.command_type.clone(),
+        error: error.to_string(),
+        fail_open: state.hooks_fail_open,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize hook.failed payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: EVENT_HOOK_FAILED.to_string(),
+        schema_version: 1,
+        actor: Actor::system(),
+        workspace_id: command_workspace_id(command),
+        project_id: None,
+        subject: Subject {
+            kind: "hook".to_string(),
+            id: hook.id.clone(),
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
@@ -0,0 +1,577 @@
+//! Launching processes with secrets, without putting them in environment variables.
+//!
+//! `secret.exec` is a command like any other, so hooks, gates, leases and idempotency apply,
+//! and policy denies it unless a rule allows it. Each named secret is then resolved for the
+//! command's context, checked against policy as `secret.inject`, and decrypted with the
+//! unlocked vault key. The launch is recorded as `process.launched`, and every secret handed
+//! over as `secret.injected`, before the process starts with a cleared environment. Secrets
+//! reach it on stdin or in an owner-only tmpfs file that is removed once it exits, and its
+//! exit is recorded as `process.exited` with every value redacted from its output.
+
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, record_events, reject_command,
+    reject_command_with_details,
+    secrets::{secret_event, secret_location, unlocked_key},
+    ApiError, AppState,
+};
+use mp_kernel::{
+    resolve_secret, Actor, ErrorCode, PolicyEffect, ProcessExitedPayload, ProcessLaunchedPayload,
+    SecretExecPayload, SecretInjectVia, SecretInjectedPayload, Subject, EVENT_PROCESS_EXITED,
+    EVENT_PROCESS_LAUNCHED, EVENT_SECRET_INJECTED, SECRET_EXEC_DEFAULT_TIMEOUT_MS,
+    SECRET_EXEC_MAX_TIMEOUT_MS, SECRET_EXEC_OUTPUT_MAX_BYTES, SECRET_MAX_BYTES, SECRET_REDACTED,
+};
+use mp_policy::PolicyRequest;
+use mp_protocol::{CommandEnvelope, EventEnvelope, SubmitCommandResponse};
+use mp_storage::{NewEvent, ProjectionReader, SecretStore};
+use mp_vault::SealedSecret;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Policy action checked for every secret handed to a process.
+const ACTION_SECRET_INJECT: &str = "secret.inject";
+
+/// Variables passed on from the daemon's environment; the process sees no others.
+const INHERITED_ENV: &[&str] = &["PATH", "HOME", "LANG", "LC_ALL", "TZ"];
+
+/// Where file-injected secrets go when it exists, so they never reach a disk.
+const TMPFS_DIR: &str = "/dev/shm";
+
+/// How long output is still read once the process has exited, in case a process it started
+/// holds the pipes open.
+const OUTPUT_GRACE: Duration = Duration::from_secs(1);
+
+/// A decrypted secret on its way to the process.
+struct Injection {
+    name: String,
+    via: SecretInjectVia,
+    value: Vec<u8>,
+}
+
+/// A planned launch, started once its events are on record.
+pub(crate) struct Launch {
+    program: String,
+    /// With file placeholders replaced by paths.
+    args: Vec<String>,
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+    timeout: Duration,
+    injections: Vec<Injection>,
+    /// Holds the file-injected secrets; dropping it, on any path, removes them.
+    dir: Option<SecretDir>,
+}
+
+/// A planned `secret.exec`: its events and the launch they record, or its rejection.
+pub(crate) type ExecPlan = Result<(Vec<NewEvent>, Launch), SubmitCommandResponse>;
+
+/// Resolves, checks and decrypts the requested secrets and writes the file-injected ones.
+/// Nothing runs until [`run_launched`] is handed the appended events.
+pub(crate) async fn plan_exec(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<ExecPlan, ApiError> {
+    let payload: SecretExecPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    let timeout_ms = match check_payload(&payload) {
+        Ok(timeout_ms) => timeout_ms,
+        Err(message) => {
+            return reject_command(state, command, ErrorCode::ValidationFailed, &message)
+                .await
+                .map(Err)
+        }
+    };
+    let workspace_id = payload.workspace_id.as_str();
+    let key = match unlocked_key(state, command, workspace_id).await? {
+        Ok(key) => key,
+        Err(rejected) => return Ok(Err(rejected)),
+    };
+    let store = state.store.lock().await;
+    let secrets = store.list_secrets(workspace_id).map_err(|err| {
+        tracing::error!("list_secrets failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    let mut injections = Vec::with_capacity(payload.secrets.len());
+    let mut injected = Vec::with_capacity(payload.secrets.len());
+    for wanted in &payload.secrets {
+        let Some(secret) = resolve_secret(&secrets, &wanted.name, &payload.context) else {
+            drop(store);
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("no secret {} is visible here", wanted.name),
+            )
+            .await
+            .map(Err);
+        };
+        let policy_request = PolicyRequest::for_command(
+            &actor,
+            ACTION_SECRET_INJECT,
+            &json!({
+                "workspace_id": workspace_id,
+                "secret_id": secret.secret_id,
+                "name": secret.name,
+                "scope": secret.owner.scope.as_str(),
+                "via": wanted.via.as_str(),
+                "program": payload.program,
+            }),
+        );
+        // Like the launch itself, handing over a secret needs a policy that allows it.
+        let refusal = match state.policy.evaluate(&policy_request) {
+            Some(decision) if decision.effect == PolicyEffect::Allow => None,
+            Some(decision) => Some((
+                format!(
+                    "injecting {} denied by policy: {}",
+                    secret.name, decision.rationale
+                ),
+                json!({ "name": secret.name, "policy": decision }),
+            )),
+            None => Some((
+                format!(
+                    "injecting {} needs a policy that allows secret.inject",
+                    secret.name
+                ),
+                json!({ "name": secret.name }),
+            )),
+        };
+        if let Some((message, details)) = refusal {
+            drop(store);
+            return reject_command_with_details(
+                state,
+                command,
+                ErrorCode::PolicyDenied,
+                &message,
+                Some(details),
+            )
+            .await
+            .map(Err);
+        }
+
+        let location = secret_location(workspace_id, &secret.secret_id, secret.version);
+        let sealed = store
+            .get_secret_version(workspace_id, &secret.secret_id, secret.version)
+            .map_err(|err| {
+                tracing::error!("get_secret_version failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+            .ok_or_else(|| {
+                tracing::error!("no ciphertext stored for {location}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        let value = key
+            .open_secret(
+                &location,
+                &SealedSecret {
+                    wrapped_key: sealed.wrapped_key,
+                    ciphertext: sealed.ciphertext,
+                },
+            )
+            .map_err(|err| {
+                tracing::error!("open secret {location} failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        let event_payload = to_payload(
+            command,
+            EVENT_SECRET_INJECTED,
+            SecretInjectedPayload {
+                name: secret.name.clone(),
+                owner: secret.owner.clone(),
+                version: secret.version,
+                via: wanted.via,
+                program: payload.program.clone(),
+            },
+        )?;
+        injected.push(secret_event(
+            actor.clone(),
+            workspace_id,
+            EVENT_SECRET_INJECTED,
+            secret.secret_id.clone(),
+            event_payload,
+            &command.trace_id,
+        ));
+        injections.push(Injection {
+            name: wanted.name.clone(),
+            via: wanted.via,
+            value,
+        });
+    }
+    drop(store);
+
+    // Files are written before anything is recorded, so a failed write records nothing.
+    let mut args = payload.args.clone();
+    let mut dir = None;
+    for injection in injections
+        .iter()
+        .filter(|injection| injection.via == SecretInjectVia::File)
+    {
+        let dir = match &mut dir {
+            Some(dir) => dir,
+            None => dir.insert(SecretDir::create().map_err(|err| {
+                tracing::error!("create secret dir failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?),
+        };
+        let path = dir
+            .write(&injection.name, &injection.value)
+            .map_err(|err| {
+                tracing::error!("write secret file failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        let placeholder = file_placeholder(&injection.name);
+        for arg in &mut args {
+            *arg = arg.replace(&placeholder, &path.to_string_lossy());
+        }
+    }
+
+    let env: Vec<(String, String)> = INHERITED_ENV
+        .iter()
+        .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+        .collect();
+    let launched = to_payload(
+        command,
+        EVENT_PROCESS_LAUNCHED,
+        ProcessLaunchedPayload {
+            program: payload.program.clone(),
+            args: payload.args.clone(),
+            cwd: payload.cwd.clone(),
+            env: env.iter().map(|(name, _)| name.clone()).collect(),
+            timeout_ms,
+        },
+    )?;
+    let mut events = vec![NewEvent {
+        event_type: EVENT_PROCESS_LAUNCHED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id: None,
+        subject: Subject {
+            kind: "process".to_string(),
+            id: mp_kernel::new_uuid(),
+        },
+        payload: launched,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }];
+    events.extend(injected);
+    Ok(Ok((
+        events,
+        Launch {
+            program: payload.program,
+            args,
+            cwd: payload.cwd,
+            env,
+            timeout: Duration::from_millis(timeout_ms),
+            injections,
+            dir,
+        },
+    )))
+}
+
+/// Starts a process whose `process.launched` was just appended, waits for it, and records
+/// `process.exited`. Running only after the launch is on record means a retried command is
+/// answered from the log rather than by launching again. Returns the events appended.
+pub(crate) async fn run_launched(
+    state: &AppState,
+    command: &CommandEnvelope,
+    appended: &[EventEnvelope],
+    launch: Launch,
+) -> Result<Vec<EventEnvelope>, ApiError> {
+    let Some(launched) = appended
+        .iter()
+        .find(|event| event.event_type == EVENT_PROCESS_LAUNCHED)
+    else {
+        return Ok(Vec::new());
+    };
+    let exited = run(launch).await;
+    let event = NewEvent {
+        event_type: EVENT_PROCESS_EXITED.to_string(),
+        schema_version: 1,
+        actor: launched.actor.clone(),
+        workspace_id: launched.workspace_id.clone(),
+        project_id: None,
+        subject: launched.subject.clone(),
+        payload: to_payload(command, EVENT_PROCESS_EXITED, exited)?,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    };
+    let mut store = state.store.lock().await;
+    record_events(state, &mut store, &command.command_type, vec![event])
+}
+
+async fn run(launch: Launch) -> ProcessExitedPayload {
+    let stdin_value = launch
+        .injections
+        .iter()
+        .find(|injection| injection.via == SecretInjectVia::Stdin)
+        .map(|injection| injection.value.clone());
+    let mut command = Command::new(&launch.program);
+    command
+        .args(&launch.args)
+        .env_clear()
+        .envs(launch.env.iter().map(|(name, value)| (name, value)))
+        .stdin(if stdin_value.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if let Some(cwd) = &launch.cwd {
+        command.current_dir(cwd);
+    }
+    let failed = |error: String| ProcessExitedPayload {
+        exit_code: None,
+        timed_out: false,
+        stdout: String::new(),
+        stderr: String::new(),
+        truncated: false,
+        error: Some(error),
+    };
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => return failed(format!("failed to launch {}: {err}", launch.program)),
+    };
+    if let (Some(mut pipe), Some(value)) = (child.stdin.take(), stdin_value) {
+        // Dropping the pipe afterwards closes stdin, so the process sees end of input.
+        tokio::spawn(async move {
+            let _ = pipe.write_all(&value).await;
+        });
+    }
+    let stdout = child.stdout.take().map(capture);
+    let stderr = child.stderr.take().map(capture);
+
+    let (exit_code, timed_out) = match tokio::time::timeout(launch.timeout, child.wait()).await {
+        Ok(Ok(status)) => (status.code(), false),
+        Ok(Err(err)) => return failed(format!("waiting on {} failed: {err}", launch.program)),
+        Err(_) => {
+            let _ = child.kill().await;
+            (None, true)
+        }
+    };
+    drop(launch.dir);
+
+    let values: Vec<_> = launch
+        .injections
+        .iter()
+        .map(|injection| String::from_utf8_lossy(&injection.value).into_owned())
+        .collect();
+    let deadline = Instant::now() + OUTPUT_GRACE;
+    let (stdout, stdout_truncated) = finish_output(stdout, &values, deadline).await;
+    let (stderr, stderr_truncated) = finish_output(stderr, &values, deadline).await;
+    ProcessExitedPayload {
+        exit_code,
+        timed_out,
+        stdout,
+        stderr,
+        truncated: stdout_truncated || stderr_truncated,
+        error: None,
+    }
+}
+
+fn to_payload(
+    command: &CommandEnvelope,
+    event_type: &str,
+    payload: impl serde::Serialize,
+) -> Result<Value, ApiError> {
+    serde_json::to_value(payload).map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
+
+/// Checks what the schema cannot; returns the timeout to apply.
+fn check_payload(payload: &SecretExecPayload) -> Result<u64, String> {
+    if payload.program.is_empty() {
+        return Err("program must not be empty".to_string());
+    }
+    if payload.secrets.is_empty() {
+        return Err("name at least one secret to inject".to_string());
+    }
+    let mut names = HashSet::new();
+    for injection in &payload.secrets {
+        if !names.insert(injection.name.as_str()) {
+            return Err(format!(
+                "secret {} is requested more than once",
+                injection.name
+            ));
+        }
+        if injection.via == SecretInjectVia::File {
+            let placeholder = file_placeholder(&injection.name);
+            if !payload.args.iter().any(|arg| arg.contains(&placeholder)) {
+                return Err(format!(
+                    "secret {} is injected as a file but no argument contains {placeholder}",
+                    injection.name
+                ));
+            }
+        }
+    }
+    let on_stdin = payload
+        .secrets
+        .iter()
+        .filter(|injection| injection.via == SecretInjectVia::Stdin)
+        .count();
+    if on_stdin > 1 {
+        return Err("at most one secret can be injected on stdin".to_string());
+    }
+    let timeout_ms = payload.timeout_ms.unwrap_or(SECRET_EXEC_DEFAULT_TIMEOUT_MS);
+    if timeout_ms == 0 || timeout_ms > SECRET_EXEC_MAX_TIMEOUT_MS {
+        return Err(format!(
+            "timeout must be between 1 and {SECRET_EXEC_MAX_TIMEOUT_MS} ms"
+        ));
+    }
+    Ok(timeout_ms)
+}
+
+/// Stands in for a file-injected secret's path in the arguments.
+fn file_placeholder(name: &str) -> String {
+    format!("{{secret:{name}}}")
+}
+
+/// Reads a pipe to its end, keeping enough past the output budget that a value straddling the
+/// cut is still redacted. Returns what was kept and whether anything was dropped.
+fn capture(mut pipe: impl AsyncRead + Unpin + Send + 'static) -> JoinHandle<(Vec<u8>, bool)> {
+    tokio::spawn(async move {
+        let limit = SECRET_EXEC_OUTPUT_MAX_BYTES + SECRET_MAX_BYTES;
+        let mut kept = Vec::new();
+        let mut dropped = false;
+        let mut chunk = [0u8; 8192];
+        loop {
+            match pipe.read(&mut chunk).await {
+                Ok(0) | Err(_) => break,
+                Ok(read) => {
+                    let room = limit.saturating_sub(kept.len());
+                    kept.extend_from_slice(&chunk[..read.min(room)]);
+                    dropped |= read > room;
+                }
+            }
+        }
+        (kept, dropped)
+    })
+}
+
+async fn finish_output(
+    capture: Option<JoinHandle<(Vec<u8>, bool)>>,
+    values: &[String],
+    deadline: Instant,
+) -> (String, bool) {
+    let Some(mut capture) = capture else {
+        return (String::new(), false);
+    };
+    let (bytes, mut truncated) = match tokio::time::timeout_at(deadline, &mut capture).await {
+        Ok(Ok(captured)) => captured,
+        _ => {
+            capture.abort();
+            (Vec::new(), true)
+        }
+    };
+    let mut text = String::from_utf8_lossy(&bytes).into_owned();
+    for value in values.iter().filter(|value| !value.is_empty()) {
+        text = text.replace(value.as_str(), SECRET_REDACTED);
+    }
+    if text.len() > SECRET_EXEC_OUTPUT_MAX_BYTES {
+        let mut cut = SECRET_EXEC_OUTPUT_MAX_BYTES;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        text.truncate(cut);
+        truncated = true;
+    }
+    (text, truncated)
+}
+
+/// Owner-only directory holding one launch's file-injected secrets. Removed with its contents
+/// on drop, so timeouts, errors and abandoned requests clean up too.
+struct SecretDir {
+    path: PathBuf,
+}
+
+impl SecretDir {
+    fn create() -> std::io::Result<Self> {
+        let base = Path::new(TMPFS_DIR);
+        let base = if base.is_dir() {
+            base.to_path_buf()
+        } else {
+            std::env::temp_dir()
+        };
+        let path = base.join(format!("mp-secrets-{}", mp_kernel::new_uuid()));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::DirBuilderExt;
+            builder.mode(0o700);
+        }
+        builder.create(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Secret names cannot contain path separators, so each lands directly in the directory.
+    fn write(&self, name: &str, value: &[u8]) -> std::io::Result<PathBuf> {
+        let path = self.path.join(name);
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        options.open(&path)?.write_all(value)?;
+        Ok(path)
+    }
+}
+
+impl Drop for SecretDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_dirs_are_private_and_removed_on_drop() {
+        let dir = SecretDir::create().expect("create");
+        let path = dir.write("API_KEY", b"sk-live-123").expect("write");
+        assert_eq!(std::fs::read(&path).expect("read"), b"sk-live-123");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| std::fs::metadata(path).expect("meta").permissions().mode();
+            assert_eq!(mode(&dir.path) & 0o777, 0o700);
+            assert_eq!(mode(&path) & 0o777, 0o600);
+        }
+        let root = dir.path.clone();
+        drop(dir);
+        assert!(!root.exists());
+    }
+
+    #[tokio::test]
+    async fn output_is_redacted_before_it_is_cut() {
+        let mut output = "x".repeat(SECRET_EXEC_OUTPUT_MAX_BYTES - 4).into_bytes();
+        output.extend_from_slice(b"sk-live-123 and more");
+        let capture = tokio::spawn(async move { (output, false) });
+        let (text, truncated) = finish_output(
+            Some(capture),
+            &["sk-live-123".to_string()],
+            Instant::now() + OUTPUT_GRACE,
+        )
+        .await;
+        assert!(truncated);
+        assert!(!text.contains("sk-live"));
+        assert!(text.ends_with("****"));
+        assert_eq!(text.len(), SECRET_EXEC_OUTPUT_MAX_BYTES);
+    }
+}
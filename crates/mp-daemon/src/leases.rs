@@ -0,0 +1,407 @@
+//! Capability leases: time-boxed exceptions to policy's default deny, minted by trusted actors
+//! and presented by their holder on individual commands. A lease never overrides a deny rule.
+//!
+//! Minting, every use, revocation and expiry are events, so the lease table is a projection
+//! like any other; the scheduler records expiry once a lease outlives its TTL.
+
+use crate::{
+    authorize, command_workspace_id, decode_payload, ensure_expected_version, internal_error,
+    policy::Admission, reject_command, reject_command_after, workspaces::load_workspace, ApiError,
+    AppState, CommandOutcome,
+};
+use axum::{
+    extract::rejection::QueryRejection,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use mp_kernel::{
+    format_rfc3339, parse_rfc3339, Actor, ErrorCode, LeaseEntry, LeaseExpiredPayload,
+    LeaseMintPayload, LeaseMintedPayload, LeaseRevokePayload, LeaseRevokedPayload, LeaseStatus,
+    LeaseUsedPayload, PolicyDecision, PolicyEffect, Subject, COMMAND_LEASE_MINT,
+    COMMAND_LEASE_REVOKE, EVENT_LEASE_EXPIRED, EVENT_LEASE_MINTED, EVENT_LEASE_REVOKED,
+    EVENT_LEASE_USED, LEASE_MAX_TTL_MS, LEASE_MINTER_KINDS,
+};
+use mp_policy::{action_matches, PolicyRequest};
+use mp_protocol::{CommandEnvelope, SubmitCommandResponse};
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Deserialize;
+use serde_json::json;
+use time::Duration;
+
+/// Only minters established by their credentials grant leases, and only under a policy that
+/// allows `lease.mint`; a session may hold one but never grant one.
+pub(crate) async fn plan_mint(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: LeaseMintPayload = decode_payload(command)?;
+    if !LEASE_MINTER_KINDS.contains(&actor.kind.as_str()) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            &format!("{} actors cannot mint leases", actor.kind),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if actor.claimed {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            &format!(
+                "{}:{} is only claimed and cannot mint leases",
+                actor.kind, actor.id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    // Running without policy admits commands, but never lets anyone hand out exceptions.
+    let request = PolicyRequest::for_command(&actor, &command.command_type, &command.payload);
+    let allowed = state
+        .policy
+        .evaluate(&request)
+        .is_some_and(|decision| decision.effect == PolicyEffect::Allow);
+    if !allowed {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            "minting a lease needs a policy that allows lease.mint",
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    if load_workspace(state, command, &payload.workspace_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if payload.ttl_ms == 0 || payload.ttl_ms > LEASE_MAX_TTL_MS {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("lease ttl must be between 1 and {LEASE_MAX_TTL_MS} ms"),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    // A lease that could mint or revoke leases would let its holder extend itself.
+    let grants_leases = payload.scope.actions.iter().any(|pattern| {
+        [COMMAND_LEASE_MINT, COMMAND_LEASE_REVOKE]
+            .iter()
+            .any(|action| action_matches(pattern, action))
+    });
+    if grants_leases {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            "a lease cannot cover lease commands",
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let expires_at = state.clock.now() + Duration::milliseconds(payload.ttl_ms as i64);
+    let event_payload = serde_json::to_value(LeaseMintedPayload {
+        holder: payload.holder,
+        scope: payload.scope,
+        ttl_ms: payload.ttl_ms,
+        expires_at: format_rfc3339(expires_at),
+        reason: payload.reason,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_LEASE_MINTED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![lease_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_LEASE_MINTED,
+        mp_kernel::new_uuid(),
+        event_payload,
+        &command.trace_id,
+    )]))
+}
+
+/// Ends an active lease early. Minters may revoke any lease; a holder may give up its own.
+pub(crate) async fn plan_revoke(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: LeaseRevokePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(lease) = load_lease(state, command, &payload.workspace_id, &payload.lease_id).await?
+    else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("lease {} not found", payload.lease_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    let is_minter = LEASE_MINTER_KINDS.contains(&actor.kind.as_str()) && !actor.claimed;
+    if !is_minter && !lease.holder.holds(&actor) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            &format!(
+                "{}:{} cannot revoke lease {}",
+                actor.kind, actor.id, lease.lease_id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if lease.status != LeaseStatus::Active {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("lease {} is already {}", lease.lease_id, lease.status),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let event_payload = serde_json::to_value(LeaseRevokedPayload {
+        reason: payload.reason,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_LEASE_REVOKED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![lease_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_LEASE_REVOKED,
+        lease.lease_id,
+        event_payload,
+        &command.trace_id,
+    )]))
+}
+
+/// Admits a command that presents `lease_id`. The lease must be active, unexpired, held by the
+/// submitting actor and cover the request; it then overrides a bundle's default deny, but never
+/// a deny rule. A lease that fails any check rejects the command even where policy alone would
+/// have allowed it.
+pub(crate) async fn admit(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: &Actor,
+    request: &PolicyRequest,
+    decision: Option<PolicyDecision>,
+    lease_id: &str,
+    hook_events: Vec<NewEvent>,
+) -> Result<Result<Admission, SubmitCommandResponse>, ApiError> {
+    let workspace_id = command_workspace_id(command);
+    let lease = load_lease(state, command, &workspace_id, lease_id).await?;
+    let refusal = match &lease {
+        None => Some((ErrorCode::NotFound, format!("lease {lease_id} not found"))),
+        Some(lease) => refusal(state, lease, actor, request),
+    };
+    if let Some((code, message)) = refusal {
+        return reject_command_after(
+            state,
+            command,
+            code,
+            &message,
+            Some(json!({ "lease_id": lease_id })),
+            hook_events,
+        )
+        .await
+        .map(Err);
+    }
+
+    let (decision, overrode) = match decision {
+        Some(decision) if decision.effect == PolicyEffect::Deny && decision.rule_id.is_some() => {
+            let message = format!("denied by policy: {}", decision.rationale);
+            return reject_command_after(
+                state,
+                command,
+                ErrorCode::PolicyDenied,
+                &message,
+                Some(json!({ "policy": decision, "lease_id": lease_id })),
+                hook_events,
+            )
+            .await
+            .map(Err);
+        }
+        Some(decision) if decision.effect == PolicyEffect::Deny => (None, Some(decision)),
+        decision => (decision, None),
+    };
+    let payload = serde_json::to_value(LeaseUsedPayload {
+        command_type: command.command_type.clone(),
+        overrode,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_LEASE_USED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(Ok(Admission {
+        hook_events,
+        decision,
+        lease_used: Some(lease_event(
+            actor.clone(),
+            &workspace_id,
+            EVENT_LEASE_USED,
+            lease_id.to_string(),
+            payload,
+            &command.trace_id,
+        )),
+    }))
+}
+
+fn refusal(
+    state: &AppState,
+    lease: &LeaseEntry,
+    actor: &Actor,
+    request: &PolicyRequest,
+) -> Option<(ErrorCode, String)> {
+    let lease_id = &lease.lease_id;
+    if !lease.holder.holds(actor) {
+        return Some((
+            ErrorCode::PolicyDenied,
+            format!(
+                "lease {lease_id} is held by {}:{}",
+                lease.holder.kind, lease.holder.id
+            ),
+        ));
+    }
+    if lease.status != LeaseStatus::Active {
+        return Some((
+            ErrorCode::PolicyDenied,
+            format!("lease {lease_id} is {}", lease.status),
+        ));
+    }
+    // The scheduler records expiry on its next tick; until then the timestamp still decides.
+    if is_expired(lease, state.clock.now()) {
+        return Some((
+            ErrorCode::PolicyDenied,
+            format!("lease {lease_id} expired at {}", lease.expires_at),
+        ));
+    }
+    if !request.is_covered_by(&lease.scope) {
+        return Some((
+            ErrorCode::PolicyDenied,
+            format!("lease {lease_id} does not cover {}", request.action),
+        ));
+    }
+    None
+}
+
+pub(crate) fn is_expired(lease: &LeaseEntry, now: time::OffsetDateTime) -> bool {
+    parse_rfc3339(&lease.expires_at).is_none_or(|expires_at| now >= expires_at)
+}
+
+/// `lease.expired` for a lease the scheduler found past its expiry.
+pub(crate) fn expired_event(lease: &LeaseEntry, trace_id: &str) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(LeaseExpiredPayload {
+        expires_at: lease.expires_at.clone(),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_LEASE_EXPIRED} payload failed: {err}");
+        internal_error(Some(trace_id.to_string()))
+    })?;
+    Ok(lease_event(
+        Actor::system(),
+        &lease.workspace_id,
+        EVENT_LEASE_EXPIRED,
+        lease.lease_id.clone(),
+        payload,
+        trace_id,
+    ))
+}
+
+fn lease_event(
+    actor: Actor,
+    workspace_id: &str,
+    event_type: &str,
+    lease_id: String,
+    payload: serde_json::Value,
+    trace_id: &str,
+) -> NewEvent {
+    NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id: None,
+        subject: Subject {
+            kind: "lease".to_string(),
+            id: lease_id,
+        },
+        payload,
+        trace_id: Some(trace_id.to_string()),
+        stream_id: None,
+    }
+}
+
+async fn load_lease(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    lease_id: &str,
+) -> Result<Option<LeaseEntry>, ApiError> {
+    let store = state.store.lock().await;
+    store.get_lease(workspace_id, lease_id).map_err(|err| {
+        tracing::error!("get_lease failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct LeasesQuery {
+    workspace_id: String,
+    #[serde(default)]
+    status: Option<LeaseStatus>,
+}
+
+pub(crate) async fn handle_list_leases(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<LeasesQuery>, QueryRejection>,
+) -> Result<Json<Vec<LeaseEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let leases = store
+        .list_leases(&query.workspace_id, query.status)
+        .map_err(|err| {
+            tracing::error!("list_leases failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(leases))
+}
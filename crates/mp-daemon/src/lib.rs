@@ -9,20 +9,25 @@ use axum::{
 use base64::Engine;
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
-use mp_dirs::{default_db_path as default_db_path_impl, runtime_dir as runtime_dir_impl};
+use mp_dirs::{
+    data_dir as data_dir_impl, default_db_path as default_db_path_impl,
+    runtime_dir as runtime_dir_impl,
+};
 use mp_kernel::{
     command_kind, now_rfc3339, Actor, CommandKind, CommandRejectedPayload, DaemonPingResponse,
-    ErrorCode, ProjectCreatePayload, RuntimeInfo, WorkspaceCreatePayload, EVENT_COMMAND_REJECTED,
-    EVENT_PROJECT_CREATED, EVENT_WORKSPACE_CREATED,
+    ErrorCode, HookChain, PinManifest, ProjectCreatePayload, RuntimeInfo, TaskAction, ToolRegistry,
+    WorkspaceCreatePayload, CLAIMABLE_ACTOR_KINDS, EVENT_COMMAND_REJECTED, EVENT_PROJECT_CREATED,
+    EVENT_WORKSPACE_CREATED,
 };
 use mp_protocol::{
-    CommandEnvelope, CommandRejection, ErrorResponse, SchemaRegistry, StdioAuthPayload,
-    StdioEventsSubscribe, StdioFrame, StdioProjectsQuery, SubmitCommandResponse,
+    CommandEnvelope, CommandRejection, ErrorResponse, EventFilter, SchemaRegistry,
+    StdioAuthPayload, StdioBoardQuery, StdioEventsSubscribe, StdioFrame, StdioProjectsQuery,
+    StdioSessionsQuery, StdioTasksQuery, StdioWorkspacesQuery, SubmitCommandResponse,
 };
 use mp_storage::{CommandMeta, EventStore, NewEvent, ProjectionReader};
 use mp_storage_sqlite::SqliteStore;
 use rand::RngCore;
-use serde::Deserialize;
+use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::Value;
 use std::{
     convert::Infallible,
@@ -34,12 +39,70 @@ use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader
 use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio_stream::wrappers::BroadcastStream;
 
+mod artifacts;
+mod blueprints;
+mod boards;
+mod capsules;
+mod catalog;
+mod gates;
+mod hook_test;
+mod hooks;
+mod injection;
+mod leases;
+mod messages;
+mod pipelines;
+mod policy;
+mod sandbox;
+mod scheduler;
+mod secrets;
+mod sessions;
+mod skills;
+mod tasks;
+mod tools;
+mod workspaces;
+mod worktrees;
+
+use sandbox::Sandbox;
+
+pub use hook_test::{
+    cases_from_commands, cases_from_events, run_hook_tests, FixtureDiff, FixtureStatus,
+    FuzzFinding, HookCaseReport, HookFuzzReport, HookStepReport, HookTestCase, HookTestOptions,
+    HookTestOutcome, HookTestReport, HookTestSummary, ReplayDecision,
+};
+pub use mp_policy::PolicySet;
+pub use policy::load_policy_dir;
+pub use scheduler::{Clock, ManualClock, SystemClock};
+pub use skills::{load_skill_trust_key, SkillTrustKey};
+
 #[derive(Clone)]
 pub struct DaemonConfig {
     pub db_path: PathBuf,
     pub addr: SocketAddr,
     pub runtime_dir: PathBuf,
+    /// Root for on-disk state beside the database, such as artifact blobs.
+    pub data_dir: PathBuf,
     pub safe_mode: bool,
+    /// Components this daemon has loaded; blueprint pins are checked against it.
+    pub pin_manifest: PinManifest,
+    /// Lets sessions start from blueprints that leave components unpinned.
+    pub allow_floating_pins: bool,
+    /// Hooks run at each interception phase, in order.
+    pub hook_chain: HookChain,
+    /// Lets actions proceed when a hook errors; only allowed on loopback addresses.
+    pub hooks_fail_open: bool,
+    /// Appends `hook.input_recorded` with the unredacted value each hooked phase receives.
+    pub record_hook_inputs: bool,
+    /// Tools agents may request through `tool.request`.
+    pub tool_registry: ToolRegistry,
+    /// Skills shared by every workspace, synced alongside each workspace's `agents/skills`.
+    pub skill_dir: Option<PathBuf>,
+    /// Keys whose signatures make a skill trusted.
+    pub skill_trust_keys: Vec<SkillTrustKey>,
+    /// Bundles every submitted command is evaluated against.
+    pub policy: PolicySet,
+    /// Admits commands while no policy is loaded instead of rejecting them; only allowed on
+    /// loopback addresses.
+    pub allow_without_policy: bool,
 }
 
 #[derive(Clone)]
@@ -49,6 +112,22 @@ struct AppState {
     broadcaster: broadcast::Sender<mp_protocol::EventEnvelope>,
     token: String,
     safe_mode: bool,
+    clock: Arc<dyn Clock>,
+    pin_manifest: Arc<PinManifest>,
+    allow_floating_pins: bool,
+    hook_chain: Arc<HookChain>,
+    hooks_fail_open: bool,
+    record_hook_inputs: bool,
+    sandbox: Arc<Sandbox>,
+    /// Operations offered through `tool.search`, `tool.load` and `tool.run`.
+    catalog: Arc<catalog::Catalog>,
+    skill_sources: Arc<skills::SkillSources>,
+    policy: Arc<PolicySet>,
+    allow_without_policy: bool,
+    /// Vault keys held in memory while their vault is unlocked.
+    vaults: Arc<secrets::Vaults>,
+    /// Held from a planner's precondition checks until its events are appended.
+    planning: Arc<Mutex<()>>,
 }
 
 #[derive(Clone, Debug)]
@@ -68,17 +147,131 @@ struct EventsQuery {
     workspace_id: String,
     #[serde(default)]
     from: Option<i64>,
+    #[serde(default)]
+    subject_kind: Option<String>,
+    #[serde(default)]
+    subject_id: Option<String>,
+    #[serde(default)]
+    event_type: Option<String>,
+}
+
+impl EventsQuery {
+    fn filter(&self) -> EventFilter {
+        EventFilter {
+            subject_kind: self.subject_kind.clone(),
+            subject_id: self.subject_id.clone(),
+            event_type: self.event_type.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspacesQuery {
+    #[serde(default)]
+    include_archived: bool,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
 struct ProjectsQuery {
     workspace_id: String,
+    #[serde(default)]
+    include_archived: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionsQuery {
+    workspace_id: String,
+    #[serde(default)]
+    root_session_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TasksQuery {
+    workspace_id: String,
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    state: Option<mp_kernel::TaskState>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct WorkspaceQuery {
+    workspace_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GatesQuery {
+    workspace_id: String,
+    #[serde(default)]
+    command_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CapsulesQuery {
+    workspace_id: String,
+    /// Narrows to one subject; requires `subject_id`.
+    #[serde(default)]
+    subject_kind: Option<String>,
+    #[serde(default)]
+    subject_id: Option<String>,
+    #[serde(default)]
+    kind: Option<mp_kernel::CapsuleKind>,
+    #[serde(default)]
+    all_versions: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BlueprintsQuery {
+    workspace_id: String,
+    #[serde(default)]
+    all_versions: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolCallsQuery {
+    workspace_id: String,
+    #[serde(default)]
+    status: Option<mp_kernel::ToolCallStatus>,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolCallQuery {
+    workspace_id: String,
 }
 
 #[derive(Debug, Deserialize)]
 #[serde(deny_unknown_fields)]
-struct EmptyPayload {}
+struct BlueprintQuery {
+    workspace_id: String,
+    /// Exact version to resolve; the latest when omitted.
+    #[serde(default)]
+    version: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PipelineStagesQuery {
+    workspace_id: String,
+    project_id: String,
+}
+
+/// Result of planning a state-changing command: events to append, or an already recorded rejection.
+enum CommandOutcome {
+    Append(Vec<NewEvent>),
+    Rejected(SubmitCommandResponse),
+}
 
 #[derive(Debug)]
 struct ApiError {
@@ -123,16 +316,33 @@ fn internal_error(trace_id: Option<String>) -> ApiError {
 }
 
 pub async fn run_daemon(config: DaemonConfig) -> anyhow::Result<()> {
+    run_daemon_with_clock(config, Arc::new(SystemClock)).await
+}
+
+/// Like `run_daemon`, but task scheduling reads time from `clock`.
+pub async fn run_daemon_with_clock(
+    config: DaemonConfig,
+    clock: Arc<dyn Clock>,
+) -> anyhow::Result<()> {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .try_init();
 
-    let store = SqliteStore::open(&config.db_path)?;
+    if config.hooks_fail_open && !config.addr.ip().is_loopback() {
+        anyhow::bail!("hook fail-open mode is only allowed on a loopback address");
+    }
+    if config.allow_without_policy && !config.addr.ip().is_loopback() {
+        anyhow::bail!("running without policy is only allowed on a loopback address");
+    }
+
+    let store =
+        SqliteStore::open(&config.db_path)?.with_artifact_dir(config.data_dir.join("artifacts"));
     if !config.safe_mode {
         store.rebuild_projections()?;
     }
 
-    let registry = SchemaRegistry::new()?;
+    let registry = schema_registry_for(&config)?;
+    let catalog = catalog::Catalog::build(&registry);
     let (tx, _) = broadcast::channel(1024);
 
     let token = generate_token()?;
@@ -142,6 +352,22 @@ pub async fn run_daemon(config: DaemonConfig) -> anyhow::Result<()> {
         broadcaster: tx,
         token: token.clone(),
         safe_mode: config.safe_mode,
+        pin_manifest: Arc::new(pin_manifest_for(&config)?),
+        allow_floating_pins: config.allow_floating_pins,
+        hook_chain: Arc::new(config.hook_chain.clone()),
+        hooks_fail_open: config.hooks_fail_open,
+        record_hook_inputs: config.record_hook_inputs,
+        sandbox: Arc::new(sandbox_for(&config)?),
+        catalog: Arc::new(catalog),
+        skill_sources: Arc::new(skills::SkillSources {
+            global_dir: config.skill_dir.clone(),
+            trust_keys: config.skill_trust_keys.clone(),
+        }),
+        policy: Arc::new(config.policy.clone()),
+        allow_without_policy: config.allow_without_policy,
+        vaults: Arc::new(secrets::Vaults::default()),
+        planning: Arc::new(Mutex::new(())),
+        clock,
     };
 
     let listener = tokio::net::TcpListener::bind(config.addr).await?;
@@ -153,6 +379,76 @@ pub async fn run_daemon(config: DaemonConfig) -> anyhow::Result<()> {
         .route("/v1/commands/submit", axum::routing::post(handle_submit))
         .route("/v1/workspaces", axum::routing::get(handle_list_workspaces))
         .route("/v1/projects", axum::routing::get(handle_list_projects))
+        .route("/v1/sessions", axum::routing::get(handle_list_sessions))
+        .route("/v1/tasks", axum::routing::get(handle_list_tasks))
+        .route(
+            "/v1/pipelines/templates",
+            axum::routing::get(handle_list_pipeline_templates),
+        )
+        .route(
+            "/v1/pipelines/stages",
+            axum::routing::get(handle_pipeline_stages),
+        )
+        .route("/v1/gates", axum::routing::get(handle_list_gates))
+        .route("/v1/worktrees", axum::routing::get(handle_list_worktrees))
+        .route("/v1/boards", axum::routing::get(handle_get_board))
+        .route("/v1/capsules", axum::routing::get(handle_list_capsules))
+        .route("/v1/blueprints", axum::routing::get(handle_list_blueprints))
+        .route(
+            "/v1/blueprints/:blueprint_id",
+            axum::routing::get(handle_get_blueprint),
+        )
+        .route("/v1/tool_calls", axum::routing::get(handle_list_tool_calls))
+        .route(
+            "/v1/tool_calls/:request_id",
+            axum::routing::get(handle_get_tool_call),
+        )
+        .route("/v1/skills", axum::routing::get(skills::handle_list_skills))
+        .route("/v1/leases", axum::routing::get(leases::handle_list_leases))
+        .route(
+            "/v1/secrets",
+            axum::routing::get(secrets::handle_list_secrets),
+        )
+        .route(
+            "/v1/secrets/resolve",
+            axum::routing::get(secrets::handle_resolve_secret),
+        )
+        .route(
+            "/v1/vault/status",
+            axum::routing::get(secrets::handle_vault_status),
+        )
+        .route(
+            "/v1/skills/:skill_id",
+            axum::routing::get(skills::handle_get_skill),
+        )
+        .route(
+            "/v1/skills/:skill_id/verify",
+            axum::routing::get(skills::handle_verify_skill),
+        )
+        .route(
+            "/v1/policy/explain",
+            axum::routing::post(policy::handle_explain),
+        )
+        .route(
+            "/v1/tools/search",
+            axum::routing::get(catalog::handle_tool_search),
+        )
+        .route(
+            "/v1/tools/run",
+            axum::routing::post(catalog::handle_tool_run),
+        )
+        .route(
+            "/v1/tools/:tool_id",
+            axum::routing::get(catalog::handle_tool_load),
+        )
+        .route(
+            "/v1/artifacts",
+            axum::routing::put(artifacts::handle_artifact_put),
+        )
+        .route(
+            "/v1/artifacts/:hash",
+            axum::routing::get(artifacts::handle_artifact_get),
+        )
         .route("/v1/events", axum::routing::get(handle_events_read))
         .route(
             "/v1/events/stream",
@@ -163,7 +459,7 @@ pub async fn run_daemon(config: DaemonConfig) -> anyhow::Result<()> {
             axum::routing::get(handle_events_stream_ndjson),
         )
         .fallback(handle_not_found)
-        .with_state(state);
+        .with_state(state.clone());
 
     tracing::info!("mpd listening on {}", local_addr);
 
@@ -178,6 +474,7 @@ pub async fn run_daemon(config: DaemonConfig) -> anyhow::Result<()> {
         _ = tokio::signal::ctrl_c() => {
             tracing::info!("shutdown requested");
         }
+        _ = scheduler::run(state), if !config.safe_mode => {}
     }
 
     cleanup_runtime_file(&config.runtime_dir);
@@ -209,13 +506,25 @@ async fn handle_ping(
 async fn handle_list_workspaces(
     State(state): State<AppState>,
     headers: HeaderMap,
+    query: Result<Query<WorkspacesQuery>, QueryRejection>,
 ) -> Result<Json<Vec<mp_kernel::WorkspaceListEntry>>, ApiError> {
     authorize(&state, &headers)?;
-    let store = state.store.lock().await;
-    let workspaces = store.list_workspaces().map_err(|err| {
-        tracing::error!("list_workspaces failed: {err}");
-        internal_error(None)
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
     })?;
+    let store = state.store.lock().await;
+    let workspaces = store
+        .list_workspaces(query.include_archived)
+        .map_err(|err| {
+            tracing::error!("list_workspaces failed: {err}");
+            internal_error(None)
+        })?;
     Ok(Json(workspaces))
 }
 
@@ -235,11 +544,364 @@ async fn handle_list_projects(
         )
     })?;
     let store = state.store.lock().await;
-    let projects = store.list_projects(&query.workspace_id).map_err(|err| {
-        tracing::error!("list_projects failed: {err}");
+    let projects = store
+        .list_projects(&query.workspace_id, query.include_archived)
+        .map_err(|err| {
+            tracing::error!("list_projects failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(projects))
+}
+
+async fn handle_list_sessions(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<SessionsQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::SessionListEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let sessions = store
+        .list_sessions(&query.workspace_id, query.root_session_id.as_deref())
+        .map_err(|err| {
+            tracing::error!("list_sessions failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(sessions))
+}
+
+async fn handle_list_tasks(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<TasksQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::TaskListEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let tasks = store
+        .list_tasks(
+            &query.workspace_id,
+            query.project_id.as_deref(),
+            query.state,
+        )
+        .map_err(|err| {
+            tracing::error!("list_tasks failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(tasks))
+}
+
+async fn handle_list_pipeline_templates(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WorkspaceQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::PipelineTemplateEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let templates = store
+        .list_pipeline_templates(&query.workspace_id)
+        .map_err(|err| {
+            tracing::error!("list_pipeline_templates failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(templates))
+}
+
+async fn handle_list_gates(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<GatesQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::GateEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let gates = store
+        .list_gates(&query.workspace_id, query.command_type.as_deref())
+        .map_err(|err| {
+            tracing::error!("list_gates failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(gates))
+}
+
+async fn handle_list_worktrees(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WorkspaceQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::WorktreeEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let worktrees = store.list_worktrees(&query.workspace_id).map_err(|err| {
+        tracing::error!("list_worktrees failed: {err}");
         internal_error(None)
     })?;
-    Ok(Json(projects))
+    Ok(Json(worktrees))
+}
+
+async fn handle_get_board(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<WorkspaceQuery>, QueryRejection>,
+) -> Result<Json<mp_kernel::BoardSnapshot>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let board = store.get_board(&query.workspace_id).map_err(|err| {
+        tracing::error!("get_board failed: {err}");
+        internal_error(None)
+    })?;
+    Ok(Json(board))
+}
+
+async fn handle_list_capsules(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<CapsulesQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::CapsuleEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let subject = match (query.subject_kind, query.subject_id) {
+        (Some(kind), Some(id)) => Some(mp_kernel::Subject { kind, id }),
+        (None, None) => None,
+        _ => {
+            return Err(ApiError::new(
+                StatusCode::BAD_REQUEST,
+                ErrorCode::InvalidSchema,
+                "subject_kind and subject_id must be given together".to_string(),
+                None,
+                None,
+            ))
+        }
+    };
+    let store = state.store.lock().await;
+    let capsules = store
+        .list_capsules(
+            &query.workspace_id,
+            subject.as_ref(),
+            query.kind,
+            query.all_versions,
+        )
+        .map_err(|err| {
+            tracing::error!("list_capsules failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(capsules))
+}
+
+async fn handle_list_blueprints(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<BlueprintsQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::BlueprintEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let blueprints = store
+        .list_blueprints(&query.workspace_id, query.all_versions)
+        .map_err(|err| {
+            tracing::error!("list_blueprints failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(blueprints))
+}
+
+async fn handle_list_tool_calls(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<ToolCallsQuery>, QueryRejection>,
+) -> Result<Json<Vec<mp_kernel::ToolCallEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let calls = store
+        .list_tool_calls(
+            &query.workspace_id,
+            query.status,
+            query.session_id.as_deref(),
+        )
+        .map_err(|err| {
+            tracing::error!("list_tool_calls failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(calls))
+}
+
+async fn handle_get_tool_call(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(request_id): axum::extract::Path<String>,
+    query: Result<Query<ToolCallQuery>, QueryRejection>,
+) -> Result<Json<mp_kernel::ToolCallEntry>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let call = store
+        .get_tool_call(&query.workspace_id, &request_id)
+        .map_err(|err| {
+            tracing::error!("get_tool_call failed: {err}");
+            internal_error(None)
+        })?;
+    call.map(Json).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("tool call {request_id} not found"),
+            None,
+            None,
+        )
+    })
+}
+
+async fn handle_get_blueprint(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    axum::extract::Path(blueprint_id): axum::extract::Path<String>,
+    query: Result<Query<BlueprintQuery>, QueryRejection>,
+) -> Result<Json<mp_kernel::BlueprintEntry>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let blueprint = store
+        .get_blueprint(&query.workspace_id, &blueprint_id, query.version)
+        .map_err(|err| {
+            tracing::error!("get_blueprint failed: {err}");
+            internal_error(None)
+        })?;
+    blueprint.map(Json).ok_or_else(|| {
+        let message = match query.version {
+            Some(version) => format!("blueprint {blueprint_id} version {version} not found"),
+            None => format!("blueprint {blueprint_id} not found"),
+        };
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            message,
+            None,
+            None,
+        )
+    })
+}
+
+async fn handle_pipeline_stages(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<PipelineStagesQuery>, QueryRejection>,
+) -> Result<Json<mp_kernel::PipelineStageView>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    let store = state.store.lock().await;
+    let view =
+        pipelines::stage_view(&*store, &query.workspace_id, &query.project_id).map_err(|err| {
+            tracing::error!("pipeline stage view failed: {err}");
+            internal_error(None)
+        })?;
+    view.map(Json).ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("project {} has no pipeline bound", query.project_id),
+            None,
+            None,
+        )
+    })
 }
 
 async fn handle_events_read(
@@ -258,13 +920,15 @@ async fn handle_events_read(
         )
     })?;
     let from = query.from.unwrap_or(0);
+    let filter = query.filter();
     let store = state.store.lock().await;
-    let events = store
+    let mut events = store
         .read_from(&query.workspace_id, from, None)
         .map_err(|err| {
             tracing::error!("read_from failed: {err}");
             internal_error(None)
         })?;
+    events.retain(|event| filter.matches(event));
     Ok(Json(events))
 }
 
@@ -272,6 +936,7 @@ async fn build_event_stream(
     state: &AppState,
     workspace_id: String,
     from: i64,
+    filter: EventFilter,
 ) -> Result<impl Stream<Item = mp_protocol::EventEnvelope>, ApiError> {
     let initial_events = {
         let store = state.store.lock().await;
@@ -287,7 +952,9 @@ async fn build_event_stream(
     Ok(async_stream::stream! {
         for event in initial_events {
             last_seq = event.seq_global;
-            yield event;
+            if filter.matches(&event) {
+                yield event;
+            }
         }
 
         let mut broadcast_stream = BroadcastStream::new(rx);
@@ -295,7 +962,9 @@ async fn build_event_stream(
             if let Ok(event) = item {
                 if event.workspace_id == workspace_id && event.seq_global > last_seq {
                     last_seq = event.seq_global;
-                    yield event;
+                    if filter.matches(&event) {
+                        yield event;
+                    }
                 }
             }
         }
@@ -319,8 +988,9 @@ async fn handle_events_stream(
         )
     })?;
     let from = query.from.unwrap_or(0);
+    let filter = query.filter();
     let workspace_id = query.workspace_id.clone();
-    let stream = build_event_stream(&state, workspace_id, from).await?;
+    let stream = build_event_stream(&state, workspace_id, from, filter).await?;
     let stream = stream.map(|event| {
         let data = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
         Ok(Event::default().data(data))
@@ -345,8 +1015,9 @@ async fn handle_events_stream_ndjson(
         )
     })?;
     let from = query.from.unwrap_or(0);
+    let filter = query.filter();
     let workspace_id = query.workspace_id.clone();
-    let stream = build_event_stream(&state, workspace_id, from).await?;
+    let stream = build_event_stream(&state, workspace_id, from, filter).await?;
     let body_stream = stream.map(|event| {
         let line = serde_json::to_string(&event).unwrap_or_else(|_| "{}".to_string());
         Ok::<Bytes, Infallible>(Bytes::from(format!("{line}\n")))
@@ -389,8 +1060,11 @@ async fn handle_submit(
 
 async fn submit_command_inner(
     state: &AppState,
-    command: CommandEnvelope,
+    mut command: CommandEnvelope,
 ) -> Result<SubmitCommandResponse, ApiError> {
+    // Nothing past this point, rejections included, sees a secret value.
+    let mut secret_value = secrets::take_value(&mut command);
+
     if state.safe_mode {
         let rejection = CommandRejection {
             code: ErrorCode::PolicyDenied,
@@ -446,7 +1120,57 @@ async fn submit_command_inner(
         return reject_command(state, &command, ErrorCode::InvalidSchema, &err.message).await;
     }
 
-    let actor = Actor::system();
+    if let Some(claimed) = &command.actor {
+        if !CLAIMABLE_ACTOR_KINDS.contains(&claimed.kind.as_str()) || claimed.id.is_empty() {
+            return reject_command(
+                state,
+                &command,
+                ErrorCode::InvalidSchema,
+                &format!("actor kind {} cannot be claimed", claimed.kind),
+            )
+            .await;
+        }
+    }
+    // The daemon token does not identify who is behind it, so a named actor is only a claim.
+    if let Some(claimed) = &mut command.actor {
+        claimed.claimed = true;
+    }
+    let actor = command.actor.clone().unwrap_or_else(Actor::token_holder);
+
+    // A retry is answered from the log before hooks or planners run, so neither their side
+    // effects nor a refusal of the retry land a second time under one key.
+    if let Some(replayed) = replay_command(state, &command).await? {
+        return Ok(replayed);
+    }
+    let hook_events = match hooks::intercept_command(state, &mut command).await? {
+        Ok(events) => events,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    // Vault keys are derived ahead of the planning lock; it would stall every other command.
+    let derived_key = secrets::derive_key(state, &command, secret_value.as_deref()).await?;
+
+    // Planners check state (lock holders, task states, lease validity) before their events
+    // are appended; serialising the two keeps a concurrent command from slipping in between.
+    let planning = state.planning.lock().await;
+
+    // A concurrent submission of the same key may have been appended while hooks ran.
+    if let Some(replayed) = replay_command(state, &command).await? {
+        return Ok(replayed);
+    }
+    let admission = match policy::enforce(state, &command, &actor, hook_events).await? {
+        Ok(admission) => admission,
+        Err(rejected) => return Ok(rejected),
+    };
+
+    if let Some(rejected) = gates::enforce(state, &command).await? {
+        return Ok(rejected);
+    }
+
+    // Vault and secret commands store keys and ciphertext in the transaction that appends
+    // their events.
+    let mut vault_effect = None;
+    let mut launch = None;
     let events = match command_type.as_str() {
         mp_kernel::COMMAND_WORKSPACE_CREATE => {
             let payload: WorkspaceCreatePayload = serde_json::from_value(command.payload.clone())
@@ -509,23 +1233,10 @@ async fn submit_command_inner(
                     )
                 })?;
 
-            if let Some(expected_version) = command.expected_version {
-                let current = {
-                    let store = state.store.lock().await;
-                    store.head_seq(&payload.workspace_id).map_err(|err| {
-                        tracing::error!("head_seq failed: {err}");
-                        internal_error(Some(command.trace_id.clone()))
-                    })?
-                };
-                if current != expected_version {
-                    return reject_command(
-                        state,
-                        &command,
-                        ErrorCode::ExpectedVersionMismatch,
-                        &format!("expected version {expected_version} does not match {current}"),
-                    )
-                    .await;
-                }
+            if let Some(rejected) =
+                ensure_expected_version(state, &command, &payload.workspace_id).await?
+            {
+                return Ok(rejected);
             }
 
             let project_id = mp_kernel::new_uuid();
@@ -552,15 +1263,273 @@ async fn submit_command_inner(
                 stream_id: None,
             }]
         }
-        _ => {
-            return reject_command(
-                state,
-                &command,
-                ErrorCode::UnknownCommand,
-                "unsupported command",
-            )
-            .await;
+        mp_kernel::COMMAND_WORKSPACE_RENAME => {
+            match workspaces::plan_workspace_rename(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKSPACE_ARCHIVE => {
+            match workspaces::plan_workspace_archive(state, &command, actor, true).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKSPACE_RESTORE => {
+            match workspaces::plan_workspace_archive(state, &command, actor, false).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_PROJECT_RENAME => {
+            match workspaces::plan_project_rename(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_PROJECT_ARCHIVE => {
+            match workspaces::plan_project_archive(state, &command, actor, true).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_PROJECT_RESTORE => {
+            match workspaces::plan_project_archive(state, &command, actor, false).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SESSION_SPAWN => {
+            match sessions::plan_spawn(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SESSION_FORK => {
+            match sessions::plan_fork(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_TASK_CREATE => match tasks::plan_create(state, &command, actor).await? {
+            CommandOutcome::Append(events) => events,
+            CommandOutcome::Rejected(response) => return Ok(response),
+        },
+        mp_kernel::COMMAND_PIPELINE_TEMPLATE_DEFINE => {
+            match pipelines::plan_define_template(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_PIPELINE_BIND => {
+            match pipelines::plan_bind(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_STAGE_TRANSITION => {
+            match pipelines::plan_stage_transition(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_GATE_DEFINE => match gates::plan_define(state, &command, actor).await? {
+            CommandOutcome::Append(events) => events,
+            CommandOutcome::Rejected(response) => return Ok(response),
+        },
+        mp_kernel::COMMAND_GATE_APPROVE => {
+            match gates::plan_decision(state, &command, actor, true).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_GATE_REJECT => {
+            match gates::plan_decision(state, &command, actor, false).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKTREE_REGISTER => {
+            match worktrees::plan_register(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKTREE_ATTACH => {
+            match worktrees::plan_attach(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKTREE_DETACH => {
+            match worktrees::plan_detach(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKTREE_LOCK_ACQUIRE => {
+            match worktrees::plan_lock_acquire(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_WORKTREE_LOCK_RELEASE => {
+            match worktrees::plan_lock_release(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_BOARD_GROUP_CREATE => {
+            match boards::plan_group_create(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_BOARD_COMMENT_ADD => {
+            match boards::plan_comment_add(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_BOARD_NODE_MOVE => {
+            match boards::plan_node_move(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_CAPSULE_WRITE => {
+            match capsules::plan_write(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_MESSAGE_SEND => {
+            match messages::plan_send(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_ARTIFACT_PUT => {
+            match artifacts::plan_put(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_BLUEPRINT_SYNC => {
+            match blueprints::plan_sync(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SKILL_SYNC => match skills::plan_sync(state, &command, actor).await? {
+            CommandOutcome::Append(events) => events,
+            CommandOutcome::Rejected(response) => return Ok(response),
+        },
+        mp_kernel::COMMAND_SKILL_APPROVE => {
+            match skills::plan_approve(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_LEASE_MINT => match leases::plan_mint(state, &command, actor).await? {
+            CommandOutcome::Append(events) => events,
+            CommandOutcome::Rejected(response) => return Ok(response),
+        },
+        mp_kernel::COMMAND_LEASE_REVOKE => {
+            match leases::plan_revoke(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_VAULT_INIT => {
+            match secrets::plan_vault_init(state, &command, actor, secret_value.take(), derived_key)
+                .await?
+            {
+                Ok((events, effect)) => {
+                    vault_effect = Some(effect);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_VAULT_UNLOCK => {
+            match secrets::plan_vault_unlock(state, &command, actor, derived_key).await? {
+                Ok((events, effect)) => {
+                    vault_effect = Some(effect);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_VAULT_LOCK => {
+            match secrets::plan_vault_lock(state, &command, actor).await? {
+                Ok((events, effect)) => {
+                    vault_effect = Some(effect);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SECRET_SET => {
+            match secrets::plan_set(state, &command, actor, secret_value.take()).await? {
+                Ok((events, effect)) => {
+                    vault_effect = Some(effect);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SECRET_ROTATE => {
+            match secrets::plan_rotate(state, &command, actor, secret_value.take()).await? {
+                Ok((events, effect)) => {
+                    vault_effect = Some(effect);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SECRET_EXEC => {
+            match injection::plan_exec(state, &command, actor).await? {
+                Ok((events, planned)) => {
+                    launch = Some(planned);
+                    events
+                }
+                Err(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_SECRET_DELETE => {
+            match secrets::plan_delete(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        mp_kernel::COMMAND_TOOL_REQUEST => {
+            match tools::plan_request(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
         }
+        mp_kernel::COMMAND_TOOL_COMPLETE => {
+            match tools::plan_complete(state, &command, actor).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            }
+        }
+        other => match TaskAction::from_command(other) {
+            Some(action) => match tasks::plan_transition(state, &command, actor, action).await? {
+                CommandOutcome::Append(events) => events,
+                CommandOutcome::Rejected(response) => return Ok(response),
+            },
+            None => {
+                return reject_command(
+                    state,
+                    &command,
+                    ErrorCode::UnknownCommand,
+                    "unsupported command",
+                )
+                .await;
+            }
+        },
     };
 
     let meta = CommandMeta {
@@ -570,6 +1539,30 @@ async fn submit_command_inner(
         trace_id: command.trace_id.clone(),
     };
 
+    // Hook and lease events name the workspace in the command's payload, which is "global"
+    // for a `workspace.create`; a batch stays in one workspace, so they take the one the
+    // command's own events land in.
+    let mut hook_events = admission.hook_events;
+    let mut lease_used = admission.lease_used;
+    if let Some(first) = events.first() {
+        for event in hook_events.iter_mut().chain(lease_used.iter_mut()) {
+            event.workspace_id = first.workspace_id.clone();
+        }
+    }
+    // Failures tolerated in fail-open mode are recorded ahead of the command's own events.
+    let mut events = events;
+    if !hook_events.is_empty() {
+        events.splice(0..0, hook_events);
+    }
+    // A command that records nothing also records no decision and no lease use.
+    if !events.is_empty() {
+        if let Some(decision) = admission.decision {
+            let evaluated = policy::evaluated_event(&command, decision, &events)?;
+            events.push(evaluated);
+        }
+        events.extend(lease_used);
+    }
+
     for event in &events {
         if let Err(err) = state.schema_registry.validate_event_payload(
             &event.event_type,
@@ -581,7 +1574,11 @@ async fn submit_command_inner(
     }
 
     let mut store = state.store.lock().await;
-    let append_result = match store.append(&meta, events) {
+    let appended = match vault_effect {
+        Some(effect) => secrets::append(state, &mut store, &meta, events, effect),
+        None => store.append(&meta, events),
+    };
+    let append_result = match appended {
         Ok(result) => result,
         Err(err) => {
             tracing::error!("append failed: {err}");
@@ -591,16 +1588,128 @@ async fn submit_command_inner(
     for event in &append_result.events {
         let _ = state.broadcaster.send(event.clone());
     }
-
-    let rejection = extract_rejection(&append_result.events);
+    drop(store);
+    drop(planning);
+
+    let mut events = append_result.events;
+    let rejection = extract_rejection(&events);
+    // Kernel-run tools execute only once their request is on record.
+    if command_type == mp_kernel::COMMAND_TOOL_REQUEST
+        && rejection.is_none()
+        && !append_result.idempotent
+    {
+        let settled = tools::run_requested(state, &command, &events).await?;
+        events.extend(settled);
+    }
+    // So do processes launched with secrets.
+    if let Some(launch) = launch.filter(|_| rejection.is_none() && !append_result.idempotent) {
+        let exited = injection::run_launched(state, &command, &events, launch).await?;
+        events.extend(exited);
+    }
     Ok(SubmitCommandResponse {
         accepted: rejection.is_none(),
-        events: append_result.events,
+        events,
         rejection,
         trace_id: command.trace_id,
     })
 }
 
+/// Appends events outside a command's own batch, e.g. a vault unlocking or a kernel-run tool's
+/// result, validating and broadcasting them like command events. Returns what was appended.
+fn record_events(
+    state: &AppState,
+    store: &mut SqliteStore,
+    operation: &str,
+    events: Vec<NewEvent>,
+) -> Result<Vec<mp_protocol::EventEnvelope>, ApiError> {
+    let trace_id = events
+        .first()
+        .and_then(|event| event.trace_id.clone())
+        .unwrap_or_default();
+    for event in &events {
+        if let Err(err) = state.schema_registry.validate_event_payload(
+            &event.event_type,
+            event.schema_version,
+            &event.payload,
+        ) {
+            tracing::error!("{operation} produced invalid event: {}", err.message);
+            return Err(internal_error(Some(trace_id)));
+        }
+    }
+    let meta = CommandMeta {
+        command_type: operation.to_string(),
+        idempotency_key: None,
+        expected_version: None,
+        trace_id: trace_id.clone(),
+    };
+    let result = store.append(&meta, events).map_err(|err| {
+        tracing::error!("append failed: {err}");
+        internal_error(Some(trace_id))
+    })?;
+    for event in &result.events {
+        let _ = state.broadcaster.send(event.clone());
+    }
+    Ok(result.events)
+}
+
+fn decode_payload<T: DeserializeOwned>(command: &CommandEnvelope) -> Result<T, ApiError> {
+    serde_json::from_value(command.payload.clone()).map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            Some(command.trace_id.clone()),
+        )
+    })
+}
+
+/// Checks `expected_version` against the workspace head, recording a rejection on mismatch.
+async fn ensure_expected_version(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    let Some(expected_version) = command.expected_version else {
+        return Ok(None);
+    };
+    let current = {
+        let store = state.store.lock().await;
+        store.head_seq(workspace_id).map_err(|err| {
+            tracing::error!("head_seq failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?
+    };
+    if current == expected_version {
+        return Ok(None);
+    }
+    reject_command(
+        state,
+        command,
+        ErrorCode::ExpectedVersionMismatch,
+        &format!("expected version {expected_version} does not match {current}"),
+    )
+    .await
+    .map(Some)
+}
+
+/// Whether `project_id` is a known project of `workspace_id`.
+async fn project_exists(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    project_id: &str,
+) -> Result<bool, ApiError> {
+    let store = state.store.lock().await;
+    let projects = store.list_projects(workspace_id, true).map_err(|err| {
+        tracing::error!("list_projects failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(projects
+        .iter()
+        .any(|project| project.project_id == project_id))
+}
+
 pub async fn run_stdio_with_io<R, W>(
     config: DaemonConfig,
     stdio: StdioConfig,
@@ -611,12 +1720,14 @@ where
     R: AsyncRead + Unpin + Send + 'static,
     W: AsyncWrite + Unpin + Send + 'static,
 {
-    let store = SqliteStore::open(&config.db_path)?;
+    let store =
+        SqliteStore::open(&config.db_path)?.with_artifact_dir(config.data_dir.join("artifacts"));
     if !config.safe_mode {
         store.rebuild_projections()?;
     }
 
-    let registry = SchemaRegistry::new()?;
+    let registry = schema_registry_for(&config)?;
+    let catalog = catalog::Catalog::build(&registry);
     let (tx, _) = broadcast::channel(1024);
 
     let token = match &stdio.auth {
@@ -630,6 +1741,22 @@ where
         broadcaster: tx,
         token,
         safe_mode: config.safe_mode,
+        pin_manifest: Arc::new(pin_manifest_for(&config)?),
+        allow_floating_pins: config.allow_floating_pins,
+        hook_chain: Arc::new(config.hook_chain.clone()),
+        hooks_fail_open: config.hooks_fail_open,
+        record_hook_inputs: config.record_hook_inputs,
+        sandbox: Arc::new(sandbox_for(&config)?),
+        catalog: Arc::new(catalog),
+        skill_sources: Arc::new(skills::SkillSources {
+            global_dir: config.skill_dir.clone(),
+            trust_keys: config.skill_trust_keys.clone(),
+        }),
+        policy: Arc::new(config.policy.clone()),
+        allow_without_policy: config.allow_without_policy,
+        vaults: Arc::new(secrets::Vaults::default()),
+        planning: Arc::new(Mutex::new(())),
+        clock: Arc::new(SystemClock),
     };
     let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
     let mut stdout = BufWriter::new(output);
@@ -650,6 +1777,7 @@ where
         StdioAuth::None => None,
     };
     let mut subscription_task = None;
+    let scheduler_task = (!config.safe_mode).then(|| tokio::spawn(scheduler::run(state.clone())));
     let mut reader = BufReader::new(input).lines();
 
     while let Some(line) = reader.next_line().await? {
@@ -754,17 +1882,21 @@ where
                 }
             }
             "query.workspaces" => {
-                if let Err(err) = serde_json::from_value::<EmptyPayload>(frame.payload.clone()) {
-                    send_stdio_error(
-                        &out_tx,
-                        frame.request_id.clone(),
-                        ErrorCode::InvalidSchema,
-                        err.to_string(),
-                    );
-                    continue;
-                }
+                let query: StdioWorkspacesQuery =
+                    match serde_json::from_value(frame.payload.clone()) {
+                        Ok(query) => query,
+                        Err(err) => {
+                            send_stdio_error(
+                                &out_tx,
+                                frame.request_id.clone(),
+                                ErrorCode::InvalidSchema,
+                                err.to_string(),
+                            );
+                            continue;
+                        }
+                    };
                 let store = state.store.lock().await;
-                match store.list_workspaces() {
+                match store.list_workspaces(query.include_archived) {
                     Ok(workspaces) => {
                         let payload = serde_json::to_value(workspaces)
                             .unwrap_or_else(|_| serde_json::json!([]));
@@ -802,7 +1934,7 @@ where
                     }
                 };
                 let store = state.store.lock().await;
-                match store.list_projects(&query.workspace_id) {
+                match store.list_projects(&query.workspace_id, query.include_archived) {
                     Ok(projects) => {
                         let payload = serde_json::to_value(projects)
                             .unwrap_or_else(|_| serde_json::json!([]));
@@ -824,6 +1956,124 @@ where
                     }
                 }
             }
+            "query.sessions" => {
+                let query: StdioSessionsQuery = match serde_json::from_value(frame.payload.clone())
+                    .map_err(|err| err.to_string())
+                {
+                    Ok(query) => query,
+                    Err(err) => {
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::InvalidSchema,
+                            err,
+                        );
+                        continue;
+                    }
+                };
+                let store = state.store.lock().await;
+                match store.list_sessions(&query.workspace_id, query.root_session_id.as_deref()) {
+                    Ok(sessions) => {
+                        let payload = serde_json::to_value(sessions)
+                            .unwrap_or_else(|_| serde_json::json!([]));
+                        send_stdio_response(
+                            &out_tx,
+                            frame.request_id,
+                            "query.sessions.response",
+                            payload,
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!("stdio query sessions failed: {err}");
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::Internal,
+                            "query failed".to_string(),
+                        );
+                    }
+                }
+            }
+            "query.tasks" => {
+                let query: StdioTasksQuery = match serde_json::from_value(frame.payload.clone())
+                    .map_err(|err| err.to_string())
+                {
+                    Ok(query) => query,
+                    Err(err) => {
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::InvalidSchema,
+                            err,
+                        );
+                        continue;
+                    }
+                };
+                let store = state.store.lock().await;
+                match store.list_tasks(
+                    &query.workspace_id,
+                    query.project_id.as_deref(),
+                    query.state,
+                ) {
+                    Ok(tasks) => {
+                        let payload =
+                            serde_json::to_value(tasks).unwrap_or_else(|_| serde_json::json!([]));
+                        send_stdio_response(
+                            &out_tx,
+                            frame.request_id,
+                            "query.tasks.response",
+                            payload,
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!("stdio query tasks failed: {err}");
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::Internal,
+                            "query failed".to_string(),
+                        );
+                    }
+                }
+            }
+            "query.board" => {
+                let query: StdioBoardQuery = match serde_json::from_value(frame.payload.clone())
+                    .map_err(|err| err.to_string())
+                {
+                    Ok(query) => query,
+                    Err(err) => {
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::InvalidSchema,
+                            err,
+                        );
+                        continue;
+                    }
+                };
+                let store = state.store.lock().await;
+                match store.get_board(&query.workspace_id) {
+                    Ok(board) => {
+                        let payload =
+                            serde_json::to_value(board).unwrap_or_else(|_| serde_json::json!({}));
+                        send_stdio_response(
+                            &out_tx,
+                            frame.request_id,
+                            "query.board.response",
+                            payload,
+                        );
+                    }
+                    Err(err) => {
+                        tracing::error!("stdio query board failed: {err}");
+                        send_stdio_error(
+                            &out_tx,
+                            frame.request_id.clone(),
+                            ErrorCode::Internal,
+                            "query failed".to_string(),
+                        );
+                    }
+                }
+            }
             "events.subscribe" => {
                 if subscription_task.is_some() {
                     send_stdio_error(
@@ -850,13 +2100,16 @@ where
                 };
                 let from = sub.from.unwrap_or(0);
                 let workspace_id = sub.workspace_id.clone();
-                let stream = match build_event_stream(&state, workspace_id, from).await {
-                    Ok(stream) => stream,
-                    Err(err) => {
-                        send_stdio_error_response(&out_tx, frame.request_id.clone(), err.error);
-                        continue;
-                    }
-                };
+                let stream =
+                    match build_event_stream(&state, workspace_id, from, EventFilter::default())
+                        .await
+                    {
+                        Ok(stream) => stream,
+                        Err(err) => {
+                            send_stdio_error_response(&out_tx, frame.request_id.clone(), err.error);
+                            continue;
+                        }
+                    };
                 send_stdio_response(
                     &out_tx,
                     frame.request_id,
@@ -895,6 +2148,9 @@ where
     if let Some(task) = subscription_task {
         task.abort();
     }
+    if let Some(task) = scheduler_task {
+        task.abort();
+    }
     drop(out_tx);
     let _ = writer_task.await;
     Ok(())
@@ -921,18 +2177,36 @@ async fn reject_command(
     code: ErrorCode,
     message: &str,
 ) -> Result<SubmitCommandResponse, ApiError> {
-    let workspace_id = command
-        .payload
-        .get("workspace_id")
-        .and_then(|value| value.as_str())
-        .map(|value| value.to_string())
-        .unwrap_or_else(|| "global".to_string());
+    reject_command_with_details(state, command, code, message, None).await
+}
+
+/// Like `reject_command`, attaching structured `details` to the `command.rejected` event.
+async fn reject_command_with_details(
+    state: &AppState,
+    command: &CommandEnvelope,
+    code: ErrorCode,
+    message: &str,
+    details: Option<serde_json::Value>,
+) -> Result<SubmitCommandResponse, ApiError> {
+    reject_command_after(state, command, code, message, details, Vec::new()).await
+}
+
+/// Like `reject_command_with_details`, appending `preceding` in the same batch before the rejection.
+async fn reject_command_after(
+    state: &AppState,
+    command: &CommandEnvelope,
+    code: ErrorCode,
+    message: &str,
+    details: Option<serde_json::Value>,
+    preceding: Vec<NewEvent>,
+) -> Result<SubmitCommandResponse, ApiError> {
+    let workspace_id = command_workspace_id(command);
 
     let payload = CommandRejectedPayload {
         command_type: command.command_type.clone(),
         code: code.clone(),
         message: message.to_string(),
-        details: None,
+        details,
     };
     let payload_json = serde_json::to_value(payload).map_err(|err| {
         tracing::error!("serialize command.rejected payload failed: {err}");
@@ -961,8 +2235,10 @@ async fn reject_command(
         trace_id: command.trace_id.clone(),
     };
 
+    let mut events = preceding;
+    events.push(event);
     let mut store = state.store.lock().await;
-    let append_result = match store.append(&meta, vec![event]) {
+    let append_result = match store.append(&meta, events) {
         Ok(result) => result,
         Err(err) => {
             tracing::error!("append failed: {err}");
@@ -984,6 +2260,43 @@ async fn reject_command(
     })
 }
 
+/// The workspace a command's events are recorded under; `global` when the payload names none.
+/// Answers a command whose idempotency key is already on record with the events appended
+/// for it.
+async fn replay_command(
+    state: &AppState,
+    command: &CommandEnvelope,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    let replayed = {
+        let store = state.store.lock().await;
+        let key = command.idempotency_key.as_deref().unwrap_or_default();
+        store
+            .replay_idempotent(key, &command.command_type)
+            .map_err(|err| {
+                tracing::error!("idempotency lookup failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    Ok(replayed.map(|events| {
+        let rejection = extract_rejection(&events);
+        SubmitCommandResponse {
+            accepted: rejection.is_none(),
+            events,
+            rejection,
+            trace_id: command.trace_id.clone(),
+        }
+    }))
+}
+
+fn command_workspace_id(command: &CommandEnvelope) -> String {
+    command
+        .payload
+        .get("workspace_id")
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "global".to_string())
+}
+
 fn send_stdio_frame(out_tx: &mpsc::UnboundedSender<String>, frame: StdioFrame) {
     if let Ok(mut line) = serde_json::to_string(&frame) {
         line.push('\n');
@@ -1126,14 +2439,138 @@ fn set_private_file_perms(path: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Reads a pin manifest (YAML or JSON) and validates it against its document schema.
+pub fn load_pin_manifest(path: &Path) -> anyhow::Result<PinManifest> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", path.display()))?;
+    let document: Value = serde_yaml::from_slice(&bytes)
+        .map_err(|err| anyhow::anyhow!("invalid pin manifest {}: {err}", path.display()))?;
+    SchemaRegistry::new()?
+        .validate_document("pin_manifest", 1, &document)
+        .map_err(|err| {
+            anyhow::anyhow!("invalid pin manifest {}: {}", path.display(), err.message)
+        })?;
+    Ok(serde_json::from_value(document)?)
+}
+
+/// The built-in schemas plus the configured tools; a malformed tool stops startup.
+fn schema_registry_for(config: &DaemonConfig) -> anyhow::Result<SchemaRegistry> {
+    let mut registry = SchemaRegistry::new()?;
+    registry
+        .register_tools(&config.tool_registry)
+        .map_err(|err| anyhow::anyhow!("invalid tool registry: {err}"))?;
+    Ok(registry)
+}
+
+fn sandbox_for(config: &DaemonConfig) -> anyhow::Result<Sandbox> {
+    let mut sandbox = Sandbox::for_hook_chain(&config.hook_chain)?;
+    sandbox.load_tools(&config.tool_registry)?;
+    Ok(sandbox)
+}
+
+/// The loaded tool registry's version and policy set hash fill `tool_registry` and
+/// `policy_bundle` when the manifest leaves them unset; a manifest naming something else is
+/// refused rather than silently overridden.
+fn pin_manifest_for(config: &DaemonConfig) -> anyhow::Result<PinManifest> {
+    let mut manifest = config.pin_manifest.clone();
+    if let Some(hash) = config.policy.hash() {
+        match &manifest.policy_bundle {
+            None => manifest.policy_bundle = Some(hash.to_string()),
+            Some(pinned) if pinned != hash => {
+                anyhow::bail!("pin manifest names policy bundle {pinned}, but {hash} is loaded")
+            }
+            Some(_) => {}
+        }
+    }
+    if config.tool_registry.tools.is_empty() {
+        return Ok(manifest);
+    }
+    match manifest.tool_registry {
+        None => manifest.tool_registry = Some(config.tool_registry.version),
+        Some(pinned) if pinned != config.tool_registry.version => anyhow::bail!(
+            "pin manifest names tool registry {pinned}, but version {} is loaded",
+            config.tool_registry.version
+        ),
+        Some(_) => {}
+    }
+    Ok(manifest)
+}
+
+/// Reads and validates a `tool_registry` document (YAML or JSON).
+pub fn load_tool_registry(path: &Path) -> anyhow::Result<ToolRegistry> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", path.display()))?;
+    let document: Value = serde_yaml::from_slice(&bytes)
+        .map_err(|err| anyhow::anyhow!("invalid tool registry {}: {err}", path.display()))?;
+    let mut schemas = SchemaRegistry::new()?;
+    schemas
+        .validate_document("tool_registry", 1, &document)
+        .map_err(|err| {
+            anyhow::anyhow!("invalid tool registry {}: {}", path.display(), err.message)
+        })?;
+    let mut registry: ToolRegistry = serde_json::from_value(document)?;
+    schemas.register_tools(&registry).map_err(|err| {
+        anyhow::anyhow!("invalid tool registry {}: {}", path.display(), err.message)
+    })?;
+    // Module paths are relative to the registry document, like hook chain modules.
+    let base = path.parent().unwrap_or(Path::new("."));
+    for tool in &mut registry.tools {
+        if let Some(mp_kernel::ToolRuntime::Wasm { module, .. }) = &mut tool.runtime {
+            module.path = base.join(&module.path).display().to_string();
+        }
+    }
+    Ok(registry)
+}
+
+/// Reads and validates a `hook_chain` document (YAML or JSON).
+pub fn load_hook_chain(path: &Path) -> anyhow::Result<HookChain> {
+    let bytes = std::fs::read(path)
+        .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", path.display()))?;
+    let document: Value = serde_yaml::from_slice(&bytes)
+        .map_err(|err| anyhow::anyhow!("invalid hook chain {}: {err}", path.display()))?;
+    SchemaRegistry::new()?
+        .validate_document("hook_chain", 1, &document)
+        .map_err(|err| anyhow::anyhow!("invalid hook chain {}: {}", path.display(), err.message))?;
+    let mut chain: HookChain = serde_json::from_value(document)?;
+    let mut seen = std::collections::HashSet::new();
+    if let Some(hook) = chain
+        .hooks
+        .iter()
+        .find(|hook| !seen.insert(hook.id.as_str()))
+    {
+        anyhow::bail!("hook {} appears twice in {}", hook.id, path.display());
+    }
+    // Module paths are relative to the chain document, so the daemon can start from anywhere.
+    let base = path.parent().unwrap_or(Path::new("."));
+    for hook in &mut chain.hooks {
+        if let mp_kernel::HookAction::Wasm { module, .. } = &mut hook.action {
+            module.path = base.join(&module.path).display().to_string();
+        }
+    }
+    Ok(chain)
+}
+
 pub fn default_runtime_dir() -> PathBuf {
     runtime_dir_impl()
 }
 
+pub fn default_data_dir() -> PathBuf {
+    data_dir_impl()
+}
+
 pub fn default_db_path() -> PathBuf {
     default_db_path_impl()
 }
 
+/// Global skills live in the config dir, beside other operator-managed files.
+pub fn default_skill_dir() -> PathBuf {
+    mp_dirs::config_dir().join("skills")
+}
+
+pub fn default_policy_dir() -> PathBuf {
+    mp_dirs::config_dir().join("policy")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1147,4 +2584,53 @@ mod tests {
         assert_eq!(err.error.message, "internal error");
         assert_eq!(err.error.trace_id, trace_id);
     }
+
+    #[test]
+    fn parse_byte_range_resolves_single_ranges() {
+        use artifacts::{parse_byte_range, ByteRange};
+        let partial = |start, end| ByteRange::Partial { start, end };
+        assert_eq!(parse_byte_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_byte_range(Some("bytes=0-9"), 100), partial(0, 9));
+        assert_eq!(parse_byte_range(Some("bytes=90-"), 100), partial(90, 99));
+        assert_eq!(parse_byte_range(Some("bytes=95-200"), 100), partial(95, 99));
+        assert_eq!(parse_byte_range(Some("bytes=-10"), 100), partial(90, 99));
+        assert_eq!(parse_byte_range(Some("bytes=-500"), 100), partial(0, 99));
+        assert_eq!(
+            parse_byte_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(
+            parse_byte_range(Some("bytes=9-0"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(
+            parse_byte_range(Some("bytes=0-1,5-6"), 100),
+            ByteRange::Full
+        );
+        assert_eq!(parse_byte_range(Some("items=0-1"), 100), ByteRange::Full);
+        assert_eq!(parse_byte_range(Some("bytes=a-b"), 100), ByteRange::Full);
+    }
+
+    #[test]
+    fn hook_test_mutations_and_diffs_are_deterministic() {
+        use hook_test::{diff_values, mutate, SplitMix64};
+        let input = serde_json::json!({"title": "release", "labels": ["a", "b"]});
+        let run = |seed| {
+            let mut rng = SplitMix64::new(seed);
+            (0..20)
+                .map(|_| mutate(&input, &mut rng))
+                .collect::<Vec<_>>()
+        };
+        let mutations = run(7);
+        assert_eq!(mutations, run(7));
+        assert!(mutations.iter().all(|(_, mutated)| *mutated != input));
+
+        let mut diff = Vec::new();
+        let actual = serde_json::json!({"title": "release", "labels": ["a"], "extra": 1});
+        diff_values("", Some(&input), Some(&actual), &mut diff);
+        let pointers: Vec<&str> = diff.iter().map(|entry| entry.pointer.as_str()).collect();
+        assert_eq!(pointers, vec!["/extra", "/labels/1"]);
+        assert_eq!(diff[1].expected, Some(serde_json::json!("b")));
+        assert_eq!(diff[1].actual, None);
+    }
 }
@@ -1,7 +1,8 @@
 use clap::{Parser, Subcommand, ValueEnum};
 use mp_daemon::{
-    default_db_path, default_runtime_dir, run_daemon, run_stdio, DaemonConfig, StdioAuth,
-    StdioConfig,
+    default_data_dir, default_db_path, default_policy_dir, default_runtime_dir, default_skill_dir,
+    load_hook_chain, load_pin_manifest, load_policy_dir, load_skill_trust_key, load_tool_registry,
+    run_daemon, run_stdio, DaemonConfig, StdioAuth, StdioConfig,
 };
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -22,20 +23,84 @@ enum Commands {
         db: Option<PathBuf>,
         #[arg(long)]
         runtime_dir: Option<PathBuf>,
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
         #[arg(long, default_value_t = false)]
         safe_mode: bool,
+        /// Manifest of loaded hooks, tools, skills and policy that blueprint pins must match.
+        #[arg(long)]
+        pin_manifest: Option<PathBuf>,
+        /// Start sessions from blueprints that leave components unpinned.
+        #[arg(long, default_value_t = false)]
+        allow_floating_pins: bool,
+        /// Hook chain document run at each interception phase.
+        #[arg(long)]
+        hook_chain: Option<PathBuf>,
+        /// Let actions proceed when a hook fails (local development only).
+        #[arg(long, default_value_t = false)]
+        hooks_fail_open: bool,
+        /// Record what each hooked phase receives, for `mpctl hooks test` replays.
+        #[arg(long, default_value_t = false)]
+        record_hook_inputs: bool,
+        /// Tool registry document listing the tools agents may request.
+        #[arg(long)]
+        tool_registry: Option<PathBuf>,
+        /// Directory of skills shared by every workspace (defaults to the config dir).
+        #[arg(long)]
+        skill_dir: Option<PathBuf>,
+        /// Ed25519 public key (PEM or base64) whose signatures make a skill trusted; repeatable.
+        #[arg(long)]
+        skill_trust_key: Vec<PathBuf>,
+        /// Directory of policy bundles evaluated at command submission (defaults to the config dir).
+        #[arg(long)]
+        policy_dir: Option<PathBuf>,
+        /// Admit commands while no policy is loaded (local development only).
+        #[arg(long, default_value_t = false)]
+        allow_without_policy: bool,
     },
     ServeStdio {
         #[arg(long)]
         db: Option<PathBuf>,
         #[arg(long)]
         runtime_dir: Option<PathBuf>,
+        #[arg(long)]
+        data_dir: Option<PathBuf>,
         #[arg(long, value_enum, default_value_t = AuthMode::Token)]
         auth: AuthMode,
         #[arg(long)]
         token: Option<String>,
         #[arg(long, default_value_t = false)]
         safe_mode: bool,
+        /// Manifest of loaded hooks, tools, skills and policy that blueprint pins must match.
+        #[arg(long)]
+        pin_manifest: Option<PathBuf>,
+        /// Start sessions from blueprints that leave components unpinned.
+        #[arg(long, default_value_t = false)]
+        allow_floating_pins: bool,
+        /// Hook chain document run at each interception phase.
+        #[arg(long)]
+        hook_chain: Option<PathBuf>,
+        /// Let actions proceed when a hook fails (local development only).
+        #[arg(long, default_value_t = false)]
+        hooks_fail_open: bool,
+        /// Record what each hooked phase receives, for `mpctl hooks test` replays.
+        #[arg(long, default_value_t = false)]
+        record_hook_inputs: bool,
+        /// Tool registry document listing the tools agents may request.
+        #[arg(long)]
+        tool_registry: Option<PathBuf>,
+        /// Directory of skills shared by every workspace (defaults to the config dir).
+        #[arg(long)]
+        skill_dir: Option<PathBuf>,
+        /// Ed25519 public key (PEM or base64) whose signatures make a skill trusted; repeatable.
+        #[arg(long)]
+        skill_trust_key: Vec<PathBuf>,
+        /// Directory of policy bundles evaluated at command submission (defaults to the config dir).
+        #[arg(long)]
+        policy_dir: Option<PathBuf>,
+        /// Admit commands while no policy is loaded (local development only).
+        #[arg(long, default_value_t = false)]
+        allow_without_policy: bool,
     },
 }
 
@@ -53,22 +118,70 @@ async fn main() -> anyhow::Result<()> {
             addr,
             db,
             runtime_dir,
+            data_dir,
             safe_mode,
+            pin_manifest,
+            allow_floating_pins,
+            hook_chain,
+            hooks_fail_open,
+            record_hook_inputs,
+            tool_registry,
+            skill_dir,
+            skill_trust_key,
+            policy_dir,
+            allow_without_policy,
         } => {
             let config = DaemonConfig {
                 db_path: db.unwrap_or_else(default_db_path),
                 addr,
                 runtime_dir: runtime_dir.unwrap_or_else(default_runtime_dir),
+                data_dir: data_dir.unwrap_or_else(default_data_dir),
                 safe_mode,
+                pin_manifest: pin_manifest
+                    .as_deref()
+                    .map(load_pin_manifest)
+                    .transpose()?
+                    .unwrap_or_default(),
+                allow_floating_pins,
+                hook_chain: hook_chain
+                    .as_deref()
+                    .map(load_hook_chain)
+                    .transpose()?
+                    .unwrap_or_default(),
+                hooks_fail_open,
+                record_hook_inputs,
+                tool_registry: tool_registry
+                    .as_deref()
+                    .map(load_tool_registry)
+                    .transpose()?
+                    .unwrap_or_default(),
+                skill_dir: Some(skill_dir.unwrap_or_else(default_skill_dir)),
+                skill_trust_keys: skill_trust_key
+                    .iter()
+                    .map(|path| load_skill_trust_key(path))
+                    .collect::<anyhow::Result<_>>()?,
+                policy: load_policy_dir(&policy_dir.unwrap_or_else(default_policy_dir))?,
+                allow_without_policy,
             };
             run_daemon(config).await?;
         }
         Commands::ServeStdio {
             db,
             runtime_dir,
+            data_dir,
             auth,
             token,
             safe_mode,
+            pin_manifest,
+            allow_floating_pins,
+            hook_chain,
+            hooks_fail_open,
+            record_hook_inputs,
+            tool_registry,
+            skill_dir,
+            skill_trust_key,
+            policy_dir,
+            allow_without_policy,
         } => {
             let auth = match auth {
                 AuthMode::None => StdioAuth::None,
@@ -82,7 +195,33 @@ async fn main() -> anyhow::Result<()> {
                 db_path: db.unwrap_or_else(default_db_path),
                 addr: "127.0.0.1:0".parse::<SocketAddr>()?,
                 runtime_dir: runtime_dir.unwrap_or_else(default_runtime_dir),
+                data_dir: data_dir.unwrap_or_else(default_data_dir),
                 safe_mode,
+                pin_manifest: pin_manifest
+                    .as_deref()
+                    .map(load_pin_manifest)
+                    .transpose()?
+                    .unwrap_or_default(),
+                allow_floating_pins,
+                hook_chain: hook_chain
+                    .as_deref()
+                    .map(load_hook_chain)
+                    .transpose()?
+                    .unwrap_or_default(),
+                hooks_fail_open,
+                record_hook_inputs,
+                tool_registry: tool_registry
+                    .as_deref()
+                    .map(load_tool_registry)
+                    .transpose()?
+                    .unwrap_or_default(),
+                skill_dir: Some(skill_dir.unwrap_or_else(default_skill_dir)),
+                skill_trust_keys: skill_trust_key
+                    .iter()
+                    .map(|path| load_skill_trust_key(path))
+                    .collect::<anyhow::Result<_>>()?,
+                policy: load_policy_dir(&policy_dir.unwrap_or_else(default_policy_dir))?,
+                allow_without_policy,
             };
             run_stdio(config, StdioConfig { auth }).await?;
         }
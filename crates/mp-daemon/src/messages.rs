@@ -0,0 +1,163 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, reject_command,
+    reject_command_with_details, sessions, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    is_valid_message_type, message_recipients, Actor, ErrorCode, MessageDeliveredPayload,
+    MessageSendPayload, MessageSentPayload, Subject, EVENT_MESSAGE_DELIVERED, EVENT_MESSAGE_SENT,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Serialize;
+
+/// Fans a message out to the sessions its scope resolves to under the default lineage rules.
+/// Each recipient gets a `message.delivered` on its own session stream; the sender's session
+/// records one `message.sent` listing recipients and any denied sessions.
+pub(crate) async fn plan_send(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: MessageSendPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    if !is_valid_message_type(&payload.message_type) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("unknown message type {}", payload.message_type),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    let Some(sender) = sessions::load_session(
+        state,
+        command,
+        &payload.workspace_id,
+        &payload.from_session_id,
+    )
+    .await?
+    else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("session {} not found", payload.from_session_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+
+    let workspace_sessions = {
+        let store = state.store.lock().await;
+        store
+            .list_sessions(&payload.workspace_id, None)
+            .map_err(|err| {
+                tracing::error!("list_sessions failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let recipients = message_recipients(&sender, &workspace_sessions, payload.scope);
+    if recipients.delivered.is_empty() {
+        if recipients.denied.is_empty() {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!(
+                    "scope {} has no recipients for session {}",
+                    payload.scope, sender.session_id
+                ),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+        return reject_command_with_details(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            &format!(
+                "lineage rules deny every {} recipient of session {}",
+                payload.scope, sender.session_id
+            ),
+            Some(serde_json::json!({
+                "scope": payload.scope,
+                "denied_session_ids": recipients.denied,
+            })),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let message_id = mp_kernel::new_uuid();
+    let project_of = |session_id: &str| {
+        workspace_sessions
+            .iter()
+            .find(|session| session.session_id == session_id)
+            .and_then(|session| session.project_id.clone())
+    };
+    let mut events = vec![message_event(
+        command,
+        actor.clone(),
+        &payload.workspace_id,
+        sender.project_id.clone(),
+        &sender.session_id,
+        EVENT_MESSAGE_SENT,
+        MessageSentPayload {
+            message_id: message_id.clone(),
+            scope: payload.scope,
+            message_type: payload.message_type.clone(),
+            recipient_session_ids: recipients.delivered.clone(),
+            denied_session_ids: recipients.denied,
+        },
+    )?];
+    for recipient in &recipients.delivered {
+        events.push(message_event(
+            command,
+            actor.clone(),
+            &payload.workspace_id,
+            project_of(recipient),
+            recipient,
+            EVENT_MESSAGE_DELIVERED,
+            MessageDeliveredPayload {
+                message_id: message_id.clone(),
+                from_session_id: sender.session_id.clone(),
+                scope: payload.scope,
+                message_type: payload.message_type.clone(),
+                body: payload.body.clone(),
+            },
+        )?);
+    }
+    Ok(CommandOutcome::Append(events))
+}
+
+fn message_event<P: Serialize>(
+    command: &CommandEnvelope,
+    actor: Actor,
+    workspace_id: &str,
+    project_id: Option<String>,
+    session_id: &str,
+    event_type: &str,
+    payload: P,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id,
+        subject: Subject {
+            kind: "session".to_string(),
+            id: session_id.to_string(),
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
@@ -0,0 +1,299 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, project_exists, reject_command,
+    tasks::load_task, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    Actor, ErrorCode, PipelineBindPayload, PipelineBoundPayload, PipelineStageView,
+    PipelineTemplateDefinePayload, PipelineTemplateDefinedPayload, PipelineTemplateEntry,
+    StageTransitionPayload, Subject, TaskStageChangedPayload, EVENT_PIPELINE_BOUND,
+    EVENT_PIPELINE_TEMPLATE_DEFINED, EVENT_TASK_STAGE_CHANGED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader, StoreError};
+use std::collections::HashSet;
+
+pub(crate) async fn plan_define_template(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: PipelineTemplateDefinePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = payload
+        .stages
+        .iter()
+        .find(|stage| !seen.insert(stage.name.as_str()))
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("duplicate stage {}", duplicate.name),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    // Redefining a name publishes the next version of the same template.
+    let latest = {
+        let store = state.store.lock().await;
+        let templates = store
+            .list_pipeline_templates(&payload.workspace_id)
+            .map_err(|err| {
+                tracing::error!("list_pipeline_templates failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?;
+        templates
+            .into_iter()
+            .filter(|template| template.name == payload.name)
+            .max_by_key(|template| template.version)
+    };
+    let (template_id, version) = match latest {
+        Some(latest) => (latest.template_id, latest.version + 1),
+        None => (mp_kernel::new_uuid(), 1),
+    };
+
+    let payload_json = serde_json::to_value(PipelineTemplateDefinedPayload {
+        name: payload.name,
+        version,
+        stages: payload.stages,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize pipeline.template.defined payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_PIPELINE_TEMPLATE_DEFINED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: None,
+        subject: Subject {
+            kind: "pipeline_template".to_string(),
+            id: template_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+pub(crate) async fn plan_bind(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: PipelineBindPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    if !project_exists(state, command, &payload.workspace_id, &payload.project_id).await? {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("project {} not found", payload.project_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    let Some(template) = load_template(
+        state,
+        command,
+        &payload.workspace_id,
+        &payload.template_id,
+        payload.template_version,
+    )
+    .await?
+    else {
+        let message = match payload.template_version {
+            Some(version) => format!(
+                "pipeline template {} version {version} not found",
+                payload.template_id
+            ),
+            None => format!("pipeline template {} not found", payload.template_id),
+        };
+        return reject_command(state, command, ErrorCode::NotFound, &message)
+            .await
+            .map(CommandOutcome::Rejected);
+    };
+
+    let payload_json = serde_json::to_value(PipelineBoundPayload {
+        template_id: template.template_id,
+        template_version: template.version,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize pipeline.bound payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_PIPELINE_BOUND.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: Some(payload.project_id.clone()),
+        subject: Subject {
+            kind: "project".to_string(),
+            id: payload.project_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+pub(crate) async fn plan_stage_transition(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: StageTransitionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let Some(task) = load_task(state, command, &payload.workspace_id, &payload.task_id).await?
+    else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("task {} not found", payload.task_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    let binding = match &task.project_id {
+        Some(project_id) => {
+            let store = state.store.lock().await;
+            store
+                .get_pipeline_binding(&payload.workspace_id, project_id)
+                .map_err(|err| {
+                    tracing::error!("get_pipeline_binding failed: {err}");
+                    internal_error(Some(command.trace_id.clone()))
+                })?
+        }
+        None => None,
+    };
+    let Some(binding) = binding else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("task {} has no pipeline bound to its project", task.task_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    let Some(template) = load_template(
+        state,
+        command,
+        &payload.workspace_id,
+        &binding.template_id,
+        Some(binding.template_version),
+    )
+    .await?
+    else {
+        tracing::error!(
+            "bound pipeline template {} v{} missing from projection",
+            binding.template_id,
+            binding.template_version
+        );
+        return Err(internal_error(Some(command.trace_id.clone())));
+    };
+
+    if !template.has_stage(&payload.to_stage) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "unknown stage {} for pipeline {} v{}",
+                payload.to_stage, template.name, template.version
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if task.current_stage.as_deref() == Some(payload.to_stage.as_str()) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("task is already in stage {}", payload.to_stage),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let payload_json = serde_json::to_value(TaskStageChangedPayload {
+        from_stage: task.current_stage,
+        to_stage: payload.to_stage,
+        template_id: template.template_id,
+        template_version: template.version,
+        reason: payload.reason,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize task.stage_changed payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_TASK_STAGE_CHANGED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: task.project_id,
+        subject: Subject {
+            kind: "task".to_string(),
+            id: task.task_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+/// Tasks of `project_id` laid out by the stages of its bound pipeline, if it has one.
+pub(crate) fn stage_view<R: ProjectionReader>(
+    store: &R,
+    workspace_id: &str,
+    project_id: &str,
+) -> Result<Option<PipelineStageView>, StoreError> {
+    let Some(binding) = store.get_pipeline_binding(workspace_id, project_id)? else {
+        return Ok(None);
+    };
+    let Some(template) = store.get_pipeline_template(
+        workspace_id,
+        &binding.template_id,
+        Some(binding.template_version),
+    )?
+    else {
+        return Ok(None);
+    };
+    let tasks = store.list_tasks(workspace_id, Some(project_id), None)?;
+    Ok(Some(PipelineStageView::build(&binding, &template, tasks)))
+}
+
+async fn load_template(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    template_id: &str,
+    version: Option<u32>,
+) -> Result<Option<PipelineTemplateEntry>, ApiError> {
+    let store = state.store.lock().await;
+    store
+        .get_pipeline_template(workspace_id, template_id, version)
+        .map_err(|err| {
+            tracing::error!("get_pipeline_template failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })
+}
@@ -0,0 +1,255 @@
+use crate::{
+    authorize, command_workspace_id, internal_error, leases, reject_command_after, tasks, ApiError,
+    AppState,
+};
+use axum::{
+    extract::{rejection::JsonRejection, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use mp_kernel::{
+    command_kind, Actor, CommandKind, ErrorCode, PolicyDecision, PolicyEffect,
+    PolicyEvaluatedPayload, PolicyExplainRequest, PolicyExplanation, Subject, TaskAction,
+    COMMAND_SECRET_EXEC, EVENT_POLICY_EVALUATED,
+};
+use mp_policy::{bundle_hash, PolicyBundle, PolicyRequest, PolicySet, RoleBindings};
+use mp_protocol::{CommandEnvelope, SchemaRegistry, SubmitCommandResponse};
+use mp_storage::NewEvent;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// What a command was admitted with; recorded alongside its events.
+pub(crate) struct Admission {
+    /// Events hooks produced, recorded ahead of the command's own.
+    pub(crate) hook_events: Vec<NewEvent>,
+    /// An allow decision, recorded as `policy.evaluated`.
+    pub(crate) decision: Option<PolicyDecision>,
+    /// `lease.used`, when the command presented a lease.
+    pub(crate) lease_used: Option<NewEvent>,
+}
+
+/// Evaluates the command against the loaded bundles. A deny is recorded as `command.rejected`
+/// (after any events hooks already produced, and a running task's move to `blocked_by_policy`
+/// when the command is one of its lifecycle commands) unless it is a bundle default and the command
+/// presents a lease covering it; an allow is returned so it can be recorded with the command's
+/// events. Commands in [`DENIED_BY_DEFAULT`] are treated as default denies unless a rule
+/// decides them.
+pub(crate) async fn enforce(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: &Actor,
+    hook_events: Vec<NewEvent>,
+) -> Result<Result<Admission, SubmitCommandResponse>, ApiError> {
+    if unavailable(state) {
+        return reject_command_after(
+            state,
+            command,
+            ErrorCode::PolicyDenied,
+            NO_POLICY_MESSAGE,
+            None,
+            hook_events,
+        )
+        .await
+        .map(Err);
+    }
+    let request = PolicyRequest::for_command(actor, &command.command_type, &command.payload);
+    let decision = by_default(
+        state,
+        &command.command_type,
+        state.policy.evaluate(&request),
+    );
+    if let Some(lease_id) = &command.lease_id {
+        return leases::admit(
+            state,
+            command,
+            actor,
+            &request,
+            decision,
+            lease_id,
+            hook_events,
+        )
+        .await;
+    }
+    let decision = match decision {
+        Some(decision) if decision.effect == PolicyEffect::Deny => decision,
+        decision => {
+            return Ok(Ok(Admission {
+                hook_events,
+                decision,
+                lease_used: None,
+            }))
+        }
+    };
+    let message = format!("denied by policy: {}", decision.rationale);
+    let mut preceding = hook_events;
+    preceding.extend(tasks::hold_event(state, command, TaskAction::BlockOnPolicy, &message).await?);
+    reject_command_after(
+        state,
+        command,
+        ErrorCode::PolicyDenied,
+        &message,
+        Some(json!({ "policy": decision })),
+        preceding,
+    )
+    .await
+    .map(Err)
+}
+
+/// Commands that no bundle default, and no running without policy, admits: only a rule that
+/// allows them, or a lease, does.
+const DENIED_BY_DEFAULT: &[&str] = &[COMMAND_SECRET_EXEC];
+
+/// Replaces a default decision, or the lack of one, with a default deny for commands in
+/// [`DENIED_BY_DEFAULT`].
+fn by_default(
+    state: &AppState,
+    command_type: &str,
+    decision: Option<PolicyDecision>,
+) -> Option<PolicyDecision> {
+    if !DENIED_BY_DEFAULT.contains(&command_type) {
+        return decision;
+    }
+    match decision {
+        Some(decision) if decision.rule_id.is_some() => Some(decision),
+        decision => Some(PolicyDecision {
+            effect: PolicyEffect::Deny,
+            policy_hash: state.policy.hash().unwrap_or_default().to_string(),
+            bundle_id: decision.and_then(|decision| decision.bundle_id),
+            rule_id: None,
+            rationale: format!("{command_type} is denied unless a rule allows it"),
+        }),
+    }
+}
+
+pub(crate) const NO_POLICY_MESSAGE: &str =
+    "no policy is loaded; add bundles to the policy directory or start mpd with --allow-without-policy";
+
+/// Whether actions must be refused because no policy is loaded and the daemon was not
+/// started with `allow_without_policy`.
+pub(crate) fn unavailable(state: &AppState) -> bool {
+    state.policy.is_empty() && !state.allow_without_policy
+}
+
+/// Shows how the loaded policy would decide a command, without submitting or recording it.
+pub(crate) async fn handle_explain(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    payload: Result<Json<PolicyExplainRequest>, JsonRejection>,
+) -> Result<Json<PolicyExplanation>, ApiError> {
+    authorize(&state, &headers)?;
+    let Json(request) = payload.map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })?;
+    if command_kind(&request.command_type) != Some(CommandKind::StateChanging) {
+        return Err(ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::UnknownCommand,
+            format!("{} is not a state-changing command", request.command_type),
+            None,
+            None,
+        ));
+    }
+    let policy_request =
+        PolicyRequest::for_command(&request.actor, &request.command_type, &request.payload);
+    let (decision, matched) = state.policy.explain(&policy_request);
+    let decision = by_default(&state, &request.command_type, decision);
+    Ok(Json(PolicyExplanation {
+        command_type: request.command_type,
+        actor: request.actor,
+        decision,
+        matched,
+        attributes: policy_request.attributes,
+    }))
+}
+
+/// Ties an allowed command's events to the decision that admitted them.
+pub(crate) fn evaluated_event(
+    command: &CommandEnvelope,
+    decision: PolicyDecision,
+    events: &[NewEvent],
+) -> Result<NewEvent, ApiError> {
+    let (workspace_id, project_id) = match events.last() {
+        Some(event) => (event.workspace_id.clone(), event.project_id.clone()),
+        None => (command_workspace_id(command), None),
+    };
+    let payload = serde_json::to_value(PolicyEvaluatedPayload {
+        command_type: command.command_type.clone(),
+        decision,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize policy.evaluated payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: EVENT_POLICY_EVALUATED.to_string(),
+        schema_version: 1,
+        actor: Actor::system(),
+        workspace_id,
+        project_id,
+        subject: Subject {
+            kind: "command".to_string(),
+            id: command.trace_id.clone(),
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
+
+/// Loads every `*.yaml`, `*.yml` and `*.json` document in `dir`, in file name order. Files
+/// named `*.roles.<ext>` hold role bindings and are compiled into a bundle; the rest are
+/// bundles. A missing directory yields an empty set, under which every command is rejected
+/// unless the daemon runs with `allow_without_policy`.
+pub fn load_policy_dir(dir: &Path) -> anyhow::Result<PolicySet> {
+    if !dir.exists() {
+        return Ok(PolicySet::default());
+    }
+    let mut paths = Vec::new();
+    for entry in std::fs::read_dir(dir)
+        .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", dir.display()))?
+    {
+        let path = entry?.path();
+        let is_bundle = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| matches!(ext, "yaml" | "yml" | "json"));
+        if is_bundle && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let schemas = SchemaRegistry::new()?;
+    let mut bundles = Vec::new();
+    for path in paths {
+        let bytes = std::fs::read(&path)
+            .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", path.display()))?;
+        let is_roles = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.ends_with(".roles"));
+        let kind = if is_roles {
+            "role_bindings"
+        } else {
+            "policy_bundle"
+        };
+        let document: Value = serde_yaml::from_slice(&bytes)
+            .map_err(|err| anyhow::anyhow!("invalid {kind} {}: {err}", path.display()))?;
+        schemas
+            .validate_document(kind, 1, &document)
+            .map_err(|err| anyhow::anyhow!("invalid {kind} {}: {}", path.display(), err.message))?;
+        let bundle = if is_roles {
+            serde_json::from_value::<RoleBindings>(document)?.compile()
+        } else {
+            serde_json::from_value::<PolicyBundle>(document)?
+        };
+        bundles.push((bundle, bundle_hash(&bytes)));
+    }
+    PolicySet::new(bundles).map_err(|err| anyhow::anyhow!("{}: {err}", dir.display()))
+}
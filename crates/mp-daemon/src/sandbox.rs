@@ -0,0 +1,709 @@
+//! Embedded WASM runtime for hook and tool modules.
+//!
+//! A module exports `memory`, `alloc(len) -> ptr` and `run(ptr, len) -> packed`, where JSON
+//! crosses the boundary as UTF-8 in guest memory and `packed` is `ptr << 32 | len`. Hostcalls
+//! are imported from the `mp` namespace, take and return JSON the same way, and trap when a
+//! capability is missing or a budget is exceeded. Every hostcall is recorded as a
+//! `sandbox.hostcall` event.
+
+use crate::scheduler::Clock;
+use anyhow::Context;
+use mp_kernel::{
+    board_node_id, capsule_chars, redact_for_log, Actor, CapsuleKind, CapsuleWrittenPayload,
+    HookAction, HookChain, Hostcall, SandboxCapabilities, SandboxEventEmittedPayload,
+    SandboxHostcallPayload, SandboxLimits, Subject, ToolRegistry, ToolRuntime, WasmModuleRef,
+    ARTIFACT_HASH_PREFIX, EVENT_CAPSULE_WRITTEN, EVENT_SANDBOX_EVENT_EMITTED,
+    EVENT_SANDBOX_HOSTCALL,
+};
+use mp_storage::{NewEvent, ProjectionReader};
+use mp_storage_sqlite::SqliteStore;
+use rand::RngCore;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use wasmtime::{
+    Caller, Config, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder, Trap,
+};
+
+/// Wall-clock limits are enforced in epochs of this length.
+const EPOCH_TICK: Duration = Duration::from_millis(10);
+
+const FUEL_EXHAUSTED: &str = "fuel limit exhausted";
+const MEMORY_EXCEEDED: &str = "memory limit exceeded";
+const TIMEOUT_EXCEEDED: &str = "wall-clock limit exceeded";
+
+/// Largest `crypto_random` request a module may make in one call.
+const MAX_RANDOM_BYTES: i32 = 4096;
+
+/// Compiled modules, keyed by path so hooks and tools sharing a module compile it once.
+pub(crate) struct Sandbox {
+    engine: Engine,
+    modules: HashMap<String, LoadedModule>,
+}
+
+#[derive(Clone)]
+pub(crate) struct LoadedModule {
+    module: Module,
+    pub(crate) hash: String,
+}
+
+/// Who is running a module and what it may touch.
+pub(crate) struct Invocation {
+    pub(crate) module_id: String,
+    /// Subject the hostcall log is recorded against, e.g. the hook.
+    pub(crate) subject: Subject,
+    pub(crate) workspace_id: String,
+    pub(crate) trace_id: String,
+    pub(crate) capabilities: SandboxCapabilities,
+    pub(crate) limits: SandboxLimits,
+    pub(crate) store: Arc<Mutex<SqliteStore>>,
+    pub(crate) clock: Arc<dyn Clock>,
+}
+
+pub(crate) struct InvocationResult {
+    /// The module's JSON output, or why it failed.
+    pub(crate) output: Result<Value, String>,
+    /// Hostcall log and anything the module wrote, in call order; recorded even on failure.
+    pub(crate) events: Vec<NewEvent>,
+    /// Fuel the invocation burned, out of `limits.fuel`.
+    pub(crate) fuel_used: u64,
+}
+
+impl Sandbox {
+    pub(crate) fn new() -> anyhow::Result<Self> {
+        let mut config = Config::new();
+        config.consume_fuel(true).epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        let weak = engine.weak();
+        std::thread::Builder::new()
+            .name("mp-sandbox-epoch".to_string())
+            .spawn(move || {
+                while let Some(engine) = weak.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    std::thread::sleep(EPOCH_TICK);
+                }
+            })?;
+        Ok(Self {
+            engine,
+            modules: HashMap::new(),
+        })
+    }
+
+    /// Compiles every module the chain's `wasm` hooks name.
+    pub(crate) fn for_hook_chain(chain: &HookChain) -> anyhow::Result<Self> {
+        let mut sandbox = Self::new()?;
+        for hook in &chain.hooks {
+            if let HookAction::Wasm { module, .. } = &hook.action {
+                sandbox
+                    .load(module)
+                    .with_context(|| format!("failed to load hook {}", hook.id))?;
+            }
+        }
+        Ok(sandbox)
+    }
+
+    /// Compiles every module the registry's `wasm` tools name.
+    pub(crate) fn load_tools(&mut self, registry: &ToolRegistry) -> anyhow::Result<()> {
+        for tool in &registry.tools {
+            if let Some(ToolRuntime::Wasm { module, .. }) = &tool.runtime {
+                self.load(module)
+                    .with_context(|| format!("failed to load tool {}", tool.id))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads, hashes and compiles `module`, refusing it if the declared hash differs.
+    pub(crate) fn load(&mut self, module: &WasmModuleRef) -> anyhow::Result<()> {
+        if self.modules.contains_key(&module.path) {
+            return Ok(());
+        }
+        let bytes = std::fs::read(&module.path)
+            .with_context(|| format!("failed to read module {}", module.path))?;
+        let hash = format!("{ARTIFACT_HASH_PREFIX}{}", blake3::hash(&bytes).to_hex());
+        if let Some(expected) = &module.hash {
+            if *expected != hash {
+                anyhow::bail!(
+                    "module {} hashes to {hash}, expected {expected}",
+                    module.path
+                );
+            }
+        }
+        let compiled = Module::new(&self.engine, &bytes)
+            .with_context(|| format!("failed to compile module {}", module.path))?;
+        self.modules.insert(
+            module.path.clone(),
+            LoadedModule {
+                module: compiled,
+                hash,
+            },
+        );
+        Ok(())
+    }
+
+    pub(crate) fn module(&self, path: &str) -> Option<LoadedModule> {
+        self.modules.get(path).cloned()
+    }
+}
+
+impl LoadedModule {
+    /// Runs the module's `run` export on `input`. Blocks; call from a blocking thread.
+    pub(crate) fn invoke(&self, invocation: Invocation, input: &Value) -> InvocationResult {
+        let limits = invocation.limits;
+        let host = HostEnv {
+            limiter: StoreLimitsBuilder::new()
+                .memory_size(usize::try_from(limits.memory_bytes).unwrap_or(usize::MAX))
+                .instances(1)
+                .trap_on_grow_failure(true)
+                .build(),
+            module_hash: self.hash.clone(),
+            invocation,
+            events: Vec::new(),
+            capsule_versions: HashMap::new(),
+        };
+        let mut store = Store::new(self.module.engine(), host);
+        store.limiter(|host| &mut host.limiter);
+        let output = run_module(&mut store, &self.module, &limits, input);
+        let fuel_used = limits.fuel - store.get_fuel().unwrap_or(0).min(limits.fuel);
+        InvocationResult {
+            output,
+            events: store.into_data().events,
+            fuel_used,
+        }
+    }
+}
+
+fn run_module(
+    store: &mut Store<HostEnv>,
+    module: &Module,
+    limits: &SandboxLimits,
+    input: &Value,
+) -> Result<Value, String> {
+    store.set_fuel(limits.fuel).map_err(describe)?;
+    let ticks = limits
+        .timeout_ms
+        .div_ceil(EPOCH_TICK.as_millis() as u64)
+        .max(1);
+    store.set_epoch_deadline(ticks);
+
+    let linker = linker(module.engine()).map_err(describe)?;
+    let instance = linker.instantiate(&mut *store, module).map_err(describe)?;
+    let memory = instance
+        .get_memory(&mut *store, "memory")
+        .ok_or_else(|| "module does not export memory".to_string())?;
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut *store, "alloc")
+        .map_err(describe)?;
+    let run = instance
+        .get_typed_func::<(i32, i32), i64>(&mut *store, "run")
+        .map_err(describe)?;
+
+    let bytes = serde_json::to_vec(input).map_err(|err| err.to_string())?;
+    let len = i32::try_from(bytes.len()).map_err(|_| "input too large".to_string())?;
+    let ptr = alloc.call(&mut *store, len).map_err(describe)?;
+    memory
+        .write(&mut *store, ptr as u32 as usize, &bytes)
+        .map_err(|err| format!("input out of bounds: {err}"))?;
+    let packed = run.call(&mut *store, (ptr, len)).map_err(describe)?;
+
+    let (out_ptr, out_len) = unpack(packed);
+    let output = memory
+        .data(&*store)
+        .get(out_ptr..out_ptr.saturating_add(out_len))
+        .ok_or_else(|| "module output is out of bounds".to_string())?;
+    serde_json::from_slice(output).map_err(|err| format!("module output is not JSON: {err}"))
+}
+
+/// Turns a trap into the reason recorded on the failed hook or tool.
+fn describe(err: anyhow::Error) -> String {
+    match err.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => FUEL_EXHAUSTED.to_string(),
+        Some(Trap::Interrupt) => TIMEOUT_EXCEEDED.to_string(),
+        _ if err.chain().any(|cause| {
+            cause
+                .to_string()
+                .starts_with("forcing trap when growing memory")
+        }) =>
+        {
+            MEMORY_EXCEEDED.to_string()
+        }
+        _ => err.root_cause().to_string(),
+    }
+}
+
+/// Whether a failure reason from [`LoadedModule::invoke`] means a limit was hit.
+pub(crate) fn is_limit_error(error: &str) -> bool {
+    [FUEL_EXHAUSTED, MEMORY_EXCEEDED, TIMEOUT_EXCEEDED].contains(&error)
+}
+
+fn pack(ptr: i32, len: usize) -> i64 {
+    ((ptr as u32 as i64) << 32) | (len as u32 as i64)
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    (
+        (packed as u64 >> 32) as usize,
+        (packed as u64 & 0xffff_ffff) as usize,
+    )
+}
+
+struct HostEnv {
+    invocation: Invocation,
+    module_hash: String,
+    limiter: StoreLimits,
+    events: Vec<NewEvent>,
+    /// Capsule versions written during this invocation but not yet appended.
+    capsule_versions: HashMap<(String, String, CapsuleKind), u32>,
+}
+
+fn linker(engine: &Engine) -> anyhow::Result<Linker<HostEnv>> {
+    let mut linker = Linker::new(engine);
+    linker.func_wrap(
+        "mp",
+        "emit_event",
+        |mut caller: Caller<'_, HostEnv>, ptr: i32, len: i32| {
+            json_hostcall(
+                &mut caller,
+                Hostcall::EmitEvent,
+                ptr,
+                len,
+                HostEnv::emit_event,
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "read_capsule",
+        |mut caller: Caller<'_, HostEnv>, ptr: i32, len: i32| {
+            json_hostcall(
+                &mut caller,
+                Hostcall::ReadCapsule,
+                ptr,
+                len,
+                HostEnv::read_capsule,
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "write_capsule",
+        |mut caller: Caller<'_, HostEnv>, ptr: i32, len: i32| {
+            json_hostcall(
+                &mut caller,
+                Hostcall::WriteCapsule,
+                ptr,
+                len,
+                HostEnv::write_capsule,
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "request_tool_call",
+        |mut caller: Caller<'_, HostEnv>, ptr: i32, len: i32| {
+            json_hostcall(
+                &mut caller,
+                Hostcall::RequestToolCall,
+                ptr,
+                len,
+                HostEnv::request_tool_call,
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "filesystem",
+        |mut caller: Caller<'_, HostEnv>, ptr: i32, len: i32| {
+            json_hostcall(
+                &mut caller,
+                Hostcall::Filesystem,
+                ptr,
+                len,
+                HostEnv::filesystem,
+            )
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "crypto_random",
+        |mut caller: Caller<'_, HostEnv>, len: i32| -> anyhow::Result<i64> {
+            let started = Instant::now();
+            let input = json!({ "len": len });
+            let allowed = if !caller.data().invocation.capabilities.crypto_random {
+                Err("crypto_random capability not granted".to_string())
+            } else if !(0..=MAX_RANDOM_BYTES).contains(&len) {
+                Err(format!("len must be between 0 and {MAX_RANDOM_BYTES}"))
+            } else {
+                Ok(input.clone())
+            };
+            // Random bytes are never logged; the record shows only how many were handed out.
+            caller
+                .data_mut()
+                .log(Hostcall::CryptoRandom, &input, &allowed, started);
+            allowed.map_err(|err| anyhow::anyhow!("crypto_random failed: {err}"))?;
+            let mut bytes = vec![0u8; len as usize];
+            rand::rngs::OsRng.fill_bytes(&mut bytes);
+            write_guest(&mut caller, &bytes)
+        },
+    )?;
+    linker.func_wrap(
+        "mp",
+        "clock_now",
+        |mut caller: Caller<'_, HostEnv>| -> anyhow::Result<i64> {
+            let started = Instant::now();
+            let result = if caller.data().invocation.capabilities.clock {
+                let now = caller.data().invocation.clock.now();
+                let unix_ms = (now.unix_timestamp_nanos() / 1_000_000) as i64;
+                Ok(json!({ "unix_ms": unix_ms }))
+            } else {
+                Err("clock capability not granted".to_string())
+            };
+            caller
+                .data_mut()
+                .log(Hostcall::ClockNow, &Value::Null, &result, started);
+            let output = result.map_err(|err| anyhow::anyhow!("clock_now failed: {err}"))?;
+            Ok(output["unix_ms"].as_i64().unwrap_or_default())
+        },
+    )?;
+    Ok(linker)
+}
+
+/// Reads a JSON request from guest memory, runs `handler`, logs the call, and writes the
+/// JSON response back. Denials and handler errors trap the module.
+fn json_hostcall(
+    caller: &mut Caller<'_, HostEnv>,
+    hostcall: Hostcall,
+    ptr: i32,
+    len: i32,
+    handler: fn(&mut HostEnv, &Value) -> Result<Value, String>,
+) -> anyhow::Result<i64> {
+    let started = Instant::now();
+    let parsed = read_guest(caller, ptr, len).and_then(|bytes| {
+        serde_json::from_slice::<Value>(&bytes).map_err(|err| format!("invalid JSON input: {err}"))
+    });
+    let (input, result) = match parsed {
+        Ok(input) => {
+            let result = handler(caller.data_mut(), &input);
+            (input, result)
+        }
+        Err(err) => (Value::Null, Err(err)),
+    };
+    caller.data_mut().log(hostcall, &input, &result, started);
+    let output = result.map_err(|err| anyhow::anyhow!("{hostcall} failed: {err}"))?;
+    write_guest(caller, &serde_json::to_vec(&output)?)
+}
+
+fn read_guest(caller: &mut Caller<'_, HostEnv>, ptr: i32, len: i32) -> Result<Vec<u8>, String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| "module does not export memory".to_string())?;
+    let start = ptr as u32 as usize;
+    memory
+        .data(&*caller)
+        .get(start..start.saturating_add(len as u32 as usize))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| "input is out of bounds".to_string())
+}
+
+fn write_guest(caller: &mut Caller<'_, HostEnv>, bytes: &[u8]) -> anyhow::Result<i64> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(|export| export.into_func())
+        .ok_or_else(|| anyhow::anyhow!("module does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, i32::try_from(bytes.len())?)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .ok_or_else(|| anyhow::anyhow!("module does not export memory"))?;
+    memory.write(&mut *caller, ptr as u32 as usize, bytes)?;
+    Ok(pack(ptr, bytes.len()))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct EmitEventRequest {
+    #[serde(rename = "type")]
+    event_type: String,
+    payload: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CapsuleRequest {
+    subject: Subject,
+    kind: CapsuleKind,
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ToolCallRequest {
+    tool_id: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case", tag = "op", deny_unknown_fields)]
+enum FilesystemRequest {
+    Read { path: String },
+    List { path: String },
+    Write { path: String, content: String },
+}
+
+fn parse<T: serde::de::DeserializeOwned>(input: &Value) -> Result<T, String> {
+    serde_json::from_value(input.clone()).map_err(|err| format!("invalid request: {err}"))
+}
+
+impl HostEnv {
+    fn actor(&self) -> Actor {
+        Actor {
+            kind: "module".to_string(),
+            id: self.invocation.module_id.clone(),
+            label: None,
+            claimed: false,
+        }
+    }
+
+    fn record(
+        &mut self,
+        event_type: &str,
+        subject: Subject,
+        project_id: Option<String>,
+        payload: Value,
+    ) {
+        self.events.push(NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: self.actor(),
+            workspace_id: self.invocation.workspace_id.clone(),
+            project_id,
+            subject,
+            payload,
+            trace_id: Some(self.invocation.trace_id.clone()),
+            stream_id: None,
+        });
+    }
+
+    fn log(
+        &mut self,
+        hostcall: Hostcall,
+        input: &Value,
+        result: &Result<Value, String>,
+        started: Instant,
+    ) {
+        let payload = SandboxHostcallPayload {
+            module_id: self.invocation.module_id.clone(),
+            module_hash: self.module_hash.clone(),
+            hostcall,
+            input: redact_for_log(input),
+            output: result.as_ref().ok().map(redact_for_log),
+            error: result.as_ref().err().cloned(),
+            duration_us: started.elapsed().as_micros() as u64,
+        };
+        let payload = serde_json::to_value(payload).unwrap_or(Value::Null);
+        let subject = self.invocation.subject.clone();
+        self.record(EVENT_SANDBOX_HOSTCALL, subject, None, payload);
+    }
+
+    fn emit_event(&mut self, input: &Value) -> Result<Value, String> {
+        let request: EmitEventRequest = parse(input)?;
+        if !self
+            .invocation
+            .capabilities
+            .emit_events
+            .contains(&request.event_type)
+        {
+            return Err(format!("event type {} not granted", request.event_type));
+        }
+        if !request.event_type.starts_with("x.") {
+            return Err("modules may only emit x.<org>.<name> extension events".to_string());
+        }
+        let payload = serde_json::to_value(SandboxEventEmittedPayload {
+            module_id: self.invocation.module_id.clone(),
+            module_hash: self.module_hash.clone(),
+            event_type: request.event_type,
+            payload: request.payload,
+        })
+        .map_err(|err| err.to_string())?;
+        let subject = self.invocation.subject.clone();
+        self.record(EVENT_SANDBOX_EVENT_EMITTED, subject, None, payload);
+        Ok(json!({}))
+    }
+
+    fn read_capsule(&mut self, input: &Value) -> Result<Value, String> {
+        if !self.invocation.capabilities.read_capsules {
+            return Err("read_capsules capability not granted".to_string());
+        }
+        let request: CapsuleRequest = parse(input)?;
+        let store = self.invocation.store.blocking_lock();
+        let latest = store
+            .list_capsules(
+                &self.invocation.workspace_id,
+                Some(&request.subject),
+                Some(request.kind),
+                false,
+            )
+            .map_err(|err| format!("capsule lookup failed: {err}"))?;
+        Ok(
+            match latest.into_iter().max_by_key(|capsule| capsule.version) {
+                Some(capsule) => json!({ "version": capsule.version, "content": capsule.content }),
+                None => Value::Null,
+            },
+        )
+    }
+
+    fn write_capsule(&mut self, input: &Value) -> Result<Value, String> {
+        if !self.invocation.capabilities.write_capsules {
+            return Err("write_capsules capability not granted".to_string());
+        }
+        let request: CapsuleRequest = parse(input)?;
+        let content = request
+            .content
+            .ok_or_else(|| "content is required".to_string())?;
+        let chars = capsule_chars(&content);
+        let budget = request.kind.budget_chars();
+        if chars > budget {
+            return Err(format!(
+                "budget exceeded: {} capsule is {chars} characters; budget is {budget}",
+                request.kind
+            ));
+        }
+
+        let key = (
+            request.subject.kind.clone(),
+            request.subject.id.clone(),
+            request.kind,
+        );
+        let (project_id, stored) = {
+            let store = self.invocation.store.blocking_lock();
+            let node = store
+                .get_board_node(
+                    &self.invocation.workspace_id,
+                    &board_node_id(&request.subject.kind, &request.subject.id),
+                )
+                .map_err(|err| format!("subject lookup failed: {err}"))?
+                .ok_or_else(|| {
+                    format!(
+                        "subject {}:{} not found",
+                        request.subject.kind, request.subject.id
+                    )
+                })?;
+            let stored = store
+                .list_capsules(
+                    &self.invocation.workspace_id,
+                    Some(&request.subject),
+                    Some(request.kind),
+                    false,
+                )
+                .map_err(|err| format!("capsule lookup failed: {err}"))?
+                .iter()
+                .map(|capsule| capsule.version)
+                .max()
+                .unwrap_or(0);
+            (node.project_id, stored)
+        };
+        let version = self.capsule_versions.get(&key).copied().unwrap_or(stored) + 1;
+        self.capsule_versions.insert(key, version);
+
+        let payload = serde_json::to_value(CapsuleWrittenPayload {
+            kind: request.kind,
+            version,
+            content,
+            chars: chars as u32,
+        })
+        .map_err(|err| err.to_string())?;
+        self.record(EVENT_CAPSULE_WRITTEN, request.subject, project_id, payload);
+        Ok(json!({ "version": version }))
+    }
+
+    /// Not supported yet: a call requested from inside a hook or tool would have to pass through
+    /// the command pipeline that is already running it. The request is still checked, so a
+    /// module learns whether it was granted the tool, but it always fails and nothing is queued.
+    fn request_tool_call(&mut self, input: &Value) -> Result<Value, String> {
+        let request: ToolCallRequest = parse(input)?;
+        if !self
+            .invocation
+            .capabilities
+            .tools
+            .contains(&request.tool_id)
+        {
+            return Err(format!("tool {} not granted", request.tool_id));
+        }
+        if !(request.args.is_object() || request.args.is_null()) {
+            return Err("args must be an object".to_string());
+        }
+        Err(format!(
+            "request_tool_call is unsupported; submit tool.request for {}",
+            request.tool_id
+        ))
+    }
+
+    fn filesystem(&mut self, input: &Value) -> Result<Value, String> {
+        let capabilities = &self.invocation.capabilities;
+        match parse::<FilesystemRequest>(input)? {
+            FilesystemRequest::Read { path } => {
+                let path = scoped_path(&path, &capabilities.fs_read, &capabilities.fs_write)?;
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("read {} failed: {err}", path.display()))?;
+                Ok(json!({ "content": content }))
+            }
+            FilesystemRequest::List { path } => {
+                let path = scoped_path(&path, &capabilities.fs_read, &capabilities.fs_write)?;
+                let mut entries = std::fs::read_dir(&path)
+                    .map_err(|err| format!("list {} failed: {err}", path.display()))?
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                    .collect::<Vec<_>>();
+                entries.sort();
+                Ok(json!({ "entries": entries }))
+            }
+            FilesystemRequest::Write { path, content } => {
+                let path = scoped_path(&path, &[], &capabilities.fs_write)?;
+                std::fs::write(&path, content.as_bytes())
+                    .map_err(|err| format!("write {} failed: {err}", path.display()))?;
+                Ok(json!({ "bytes": content.len() }))
+            }
+        }
+    }
+}
+
+/// Resolves `path` (following symlinks) and checks it falls under one of the granted roots.
+fn scoped_path(path: &str, read: &[String], write: &[String]) -> Result<PathBuf, String> {
+    let requested = Path::new(path);
+    if !requested.is_absolute() {
+        return Err(format!("{path} is not absolute"));
+    }
+    // Files being created do not exist yet, so resolve their directory instead.
+    let resolved = match requested.canonicalize() {
+        Ok(resolved) => resolved,
+        Err(_) => {
+            let parent = requested
+                .parent()
+                .and_then(|parent| parent.canonicalize().ok())
+                .ok_or_else(|| format!("{path} does not exist"))?;
+            let name = requested
+                .file_name()
+                .ok_or_else(|| format!("{path} has no file name"))?;
+            parent.join(name)
+        }
+    };
+    let granted = read.iter().chain(write).any(|root| {
+        Path::new(root)
+            .canonicalize()
+            .is_ok_and(|root| resolved.starts_with(root))
+    });
+    if granted {
+        Ok(resolved)
+    } else {
+        Err(format!("{path} is outside the granted filesystem scope"))
+    }
+}
@@ -0,0 +1,251 @@
+//! Drives task timeouts, retries, lease expiry and vault auto-lock from inside the daemon.
+//!
+//! Every decision is appended as an event (`task.timed_out`, `task.retry_scheduled`,
+//! `lease.expired`, `vault.locked`), so projections never consult the clock and a rebuild
+//! reproduces the same attempt counts and lease states.
+
+use crate::{internal_error, leases, secrets, ApiError, AppState};
+use mp_kernel::{
+    format_rfc3339, parse_rfc3339, Actor, FailureClass, LeaseStatus, Subject, TaskListEntry,
+    TaskRetryScheduledPayload, TaskState, TaskTimedOutPayload, EVENT_TASK_RETRY_SCHEDULED,
+    EVENT_TASK_TIMED_OUT,
+};
+use mp_storage::{CommandMeta, EventStore, NewEvent, ProjectionReader};
+use mp_storage_sqlite::SqliteStore;
+use std::sync::Mutex;
+use time::{Duration, OffsetDateTime};
+
+const TICK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Source of "now" for scheduling decisions; swapped for a `ManualClock` in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> OffsetDateTime;
+}
+
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// Clock that only moves when told to.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<OffsetDateTime>,
+}
+
+impl ManualClock {
+    pub fn new(start: OffsetDateTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().expect("clock lock");
+        *now += by;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> OffsetDateTime {
+        *self.now.lock().expect("clock lock")
+    }
+}
+
+pub(crate) async fn run(state: AppState) {
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(err) = tick(&state).await {
+            tracing::error!("scheduler tick failed: {}", err.error.message);
+        }
+    }
+}
+
+/// Times out running attempts that outlived `timeout_ms`, re-queueing them when policy allows,
+/// expires leases that outlived their TTL and locks vaults left idle.
+pub(crate) async fn tick(state: &AppState) -> Result<(), ApiError> {
+    let now = state.clock.now();
+    // Commands planned against the same tasks and leases must not interleave with a tick.
+    let _planning = state.planning.lock().await;
+    let mut store = state.store.lock().await;
+    // Archiving only hides a workspace; its running tasks still time out.
+    let workspaces = store.list_workspaces(true).map_err(|err| {
+        tracing::error!("list_workspaces failed: {err}");
+        internal_error(None)
+    })?;
+    for workspace in workspaces {
+        let running = store
+            .list_tasks(&workspace.workspace_id, None, Some(TaskState::Running))
+            .map_err(|err| {
+                tracing::error!("list_tasks failed: {err}");
+                internal_error(None)
+            })?;
+        for task in running.iter().filter(|task| attempt_expired(task, now)) {
+            let trace_id = format!("tr_{}", mp_kernel::new_uuid());
+            let events = timeout_events(task, now, &trace_id)?;
+            // Keyed per attempt so a tick racing a restart cannot time the same attempt out twice.
+            let key = format!("scheduler:{}:{}", task.task_id, task.attempt);
+            append_decision(state, &mut store, key, trace_id, events)?;
+        }
+
+        let active = store
+            .list_leases(&workspace.workspace_id, Some(LeaseStatus::Active))
+            .map_err(|err| {
+                tracing::error!("list_leases failed: {err}");
+                internal_error(None)
+            })?;
+        for lease in active.iter().filter(|lease| leases::is_expired(lease, now)) {
+            let trace_id = format!("tr_{}", mp_kernel::new_uuid());
+            let events = vec![leases::expired_event(lease, &trace_id)?];
+            let key = format!("lease-expiry:{}:{}", lease.workspace_id, lease.lease_id);
+            append_decision(state, &mut store, key, trace_id, events)?;
+        }
+    }
+
+    // The key is already gone by the time the lock is recorded.
+    for workspace_id in state.vaults.lock_idle(now) {
+        let trace_id = format!("tr_{}", mp_kernel::new_uuid());
+        let event = secrets::idle_lock_event(&workspace_id, &trace_id)?;
+        secrets::record_vault_event(state, &mut store, "scheduler.tick", event)?;
+    }
+    Ok(())
+}
+
+fn append_decision(
+    state: &AppState,
+    store: &mut SqliteStore,
+    idempotency_key: String,
+    trace_id: String,
+    events: Vec<NewEvent>,
+) -> Result<(), ApiError> {
+    for event in &events {
+        if let Err(err) = state.schema_registry.validate_event_payload(
+            &event.event_type,
+            event.schema_version,
+            &event.payload,
+        ) {
+            tracing::error!("scheduler produced invalid event: {}", err.message);
+            return Err(internal_error(Some(trace_id)));
+        }
+    }
+    let meta = CommandMeta {
+        command_type: "scheduler.tick".to_string(),
+        idempotency_key: Some(idempotency_key),
+        expected_version: None,
+        trace_id: trace_id.clone(),
+    };
+    let result = store.append(&meta, events).map_err(|err| {
+        tracing::error!("append failed: {err}");
+        internal_error(Some(trace_id))
+    })?;
+    for event in result.events {
+        let _ = state.broadcaster.send(event);
+    }
+    Ok(())
+}
+
+fn attempt_expired(task: &TaskListEntry, now: OffsetDateTime) -> bool {
+    let (Some(timeout_ms), Some(started_at)) = (task.timeout_ms, &task.attempt_started_at) else {
+        return false;
+    };
+    let Some(started_at) = parse_rfc3339(started_at) else {
+        return false;
+    };
+    now >= started_at + Duration::milliseconds(timeout_ms as i64)
+}
+
+fn timeout_events(
+    task: &TaskListEntry,
+    now: OffsetDateTime,
+    trace_id: &str,
+) -> Result<Vec<NewEvent>, ApiError> {
+    let payload = serde_json::to_value(TaskTimedOutPayload {
+        from: task.state,
+        to: TaskState::Failed,
+        attempt: task.attempt,
+        timeout_ms: task.timeout_ms.unwrap_or_default(),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize task.timed_out payload failed: {err}");
+        internal_error(Some(trace_id.to_string()))
+    })?;
+    let mut events = vec![task_event(
+        task,
+        EVENT_TASK_TIMED_OUT,
+        payload,
+        Actor::system(),
+        trace_id,
+    )];
+    events.extend(retry_event(
+        task,
+        FailureClass::Timeout,
+        now,
+        Actor::system(),
+        trace_id,
+    )?);
+    Ok(events)
+}
+
+/// The `task.retry_scheduled` event for a failed attempt, if the task's policy grants another.
+pub(crate) fn retry_event(
+    task: &TaskListEntry,
+    failure_class: FailureClass,
+    now: OffsetDateTime,
+    actor: Actor,
+    trace_id: &str,
+) -> Result<Option<NewEvent>, ApiError> {
+    let Some(policy) = &task.retry else {
+        return Ok(None);
+    };
+    if !policy.should_retry(task.attempt, failure_class) {
+        return Ok(None);
+    }
+    let delay_ms = policy.delay_ms(task.attempt, &task.task_id);
+    let retry_at = now + Duration::milliseconds(delay_ms as i64);
+    let payload = serde_json::to_value(TaskRetryScheduledPayload {
+        attempt: task.attempt,
+        next_attempt: task.attempt + 1,
+        failure_class,
+        delay_ms,
+        retry_at: format_rfc3339(retry_at),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize task.retry_scheduled payload failed: {err}");
+        internal_error(Some(trace_id.to_string()))
+    })?;
+    Ok(Some(task_event(
+        task,
+        EVENT_TASK_RETRY_SCHEDULED,
+        payload,
+        actor,
+        trace_id,
+    )))
+}
+
+fn task_event(
+    task: &TaskListEntry,
+    event_type: &str,
+    payload: serde_json::Value,
+    actor: Actor,
+    trace_id: &str,
+) -> NewEvent {
+    NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: task.workspace_id.clone(),
+        project_id: task.project_id.clone(),
+        subject: Subject {
+            kind: "task".to_string(),
+            id: task.task_id.clone(),
+        },
+        payload,
+        trace_id: Some(trace_id.to_string()),
+        stream_id: None,
+    }
+}
@@ -0,0 +1,1080 @@
+//! Encrypted secrets: the `vault.*` and `secret.*` commands and idle auto-lock.
+//!
+//! A vault's key is derived from its password on unlock and only ever held in memory, until
+//! the vault is locked on request or by the scheduler once it sits idle. Vault records and
+//! sealed values are stored in the transaction that appends their command's events, and those
+//! events carry redacted metadata only.
+
+use crate::{
+    authorize, decode_payload, ensure_expected_version, internal_error, project_exists,
+    record_events, reject_command, workspaces::load_workspace, ApiError, AppState, CommandOutcome,
+};
+use axum::{
+    extract::rejection::QueryRejection,
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use mp_kernel::{
+    format_rfc3339, resolve_secret, Actor, ErrorCode, SecretContext, SecretDeletePayload,
+    SecretDeletedPayload, SecretEntry, SecretOwner, SecretRotatePayload, SecretScope,
+    SecretSetPayload, SecretVersionPayload, Subject, VaultInitPayload, VaultInitializedPayload,
+    VaultLockPayload, VaultLockReason, VaultLockedPayload, VaultStatus, VaultUnlockPayload,
+    VaultUnlockedPayload, COMMAND_SECRET_ROTATE, COMMAND_SECRET_SET, COMMAND_VAULT_INIT,
+    COMMAND_VAULT_UNLOCK, EVENT_SECRET_CREATED, EVENT_SECRET_DELETED, EVENT_SECRET_ROTATED,
+    EVENT_VAULT_INITIALIZED, EVENT_VAULT_LOCKED, EVENT_VAULT_UNLOCKED, SECRET_MAX_BYTES,
+    SECRET_REDACTED, VAULT_DEFAULT_IDLE_TIMEOUT_MS, VAULT_MAX_IDLE_TIMEOUT_MS,
+    VAULT_MIN_PASSWORD_CHARS,
+};
+use mp_protocol::{CommandEnvelope, SubmitCommandResponse};
+use mp_storage::{
+    AppendResult, CommandMeta, EventStore, NewEvent, ProjectionReader, SecretCiphertext,
+    SecretStore, StoreError, VaultRecord, VaultWrite,
+};
+use mp_storage_sqlite::SqliteStore;
+use mp_vault::{VaultError, VaultHeader, VaultKey, DEFAULT_KDF};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use time::{Duration, OffsetDateTime};
+
+/// Keys of the vaults that are currently unlocked, by workspace.
+#[derive(Default)]
+pub(crate) struct Vaults {
+    unlocked: Mutex<HashMap<String, UnlockedVault>>,
+}
+
+struct UnlockedVault {
+    key: Arc<VaultKey>,
+    idle_timeout: Duration,
+    last_used: OffsetDateTime,
+}
+
+impl UnlockedVault {
+    fn locks_at(&self) -> OffsetDateTime {
+        self.last_used + self.idle_timeout
+    }
+}
+
+impl Vaults {
+    fn unlocked(&self) -> std::sync::MutexGuard<'_, HashMap<String, UnlockedVault>> {
+        self.unlocked.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn unlock(
+        &self,
+        workspace_id: &str,
+        key: VaultKey,
+        idle_timeout: Duration,
+        now: OffsetDateTime,
+    ) {
+        self.unlocked().insert(
+            workspace_id.to_string(),
+            UnlockedVault {
+                key: Arc::new(key),
+                idle_timeout,
+                last_used: now,
+            },
+        );
+    }
+
+    /// Whether a key is held for the vault, even one past its idle deadline.
+    fn holds(&self, workspace_id: &str) -> bool {
+        self.unlocked().contains_key(workspace_id)
+    }
+
+    fn lock(&self, workspace_id: &str) {
+        self.unlocked().remove(workspace_id);
+    }
+
+    /// The vault key, counting the call as use. A vault past its idle deadline yields nothing;
+    /// the scheduler records the lock on its next tick.
+    pub(crate) fn key(&self, workspace_id: &str, now: OffsetDateTime) -> Option<Arc<VaultKey>> {
+        let mut unlocked = self.unlocked();
+        let vault = unlocked.get_mut(workspace_id)?;
+        if now >= vault.locks_at() {
+            return None;
+        }
+        vault.last_used = now;
+        Some(vault.key.clone())
+    }
+
+    fn locks_at(&self, workspace_id: &str, now: OffsetDateTime) -> Option<OffsetDateTime> {
+        self.unlocked()
+            .get(workspace_id)
+            .map(UnlockedVault::locks_at)
+            .filter(|locks_at| now < *locks_at)
+    }
+
+    /// Drops every key left idle past its deadline and returns their workspaces.
+    pub(crate) fn lock_idle(&self, now: OffsetDateTime) -> Vec<String> {
+        let mut unlocked = self.unlocked();
+        let idle: Vec<String> = unlocked
+            .iter()
+            .filter(|(_, vault)| now >= vault.locks_at())
+            .map(|(workspace_id, _)| workspace_id.clone())
+            .collect();
+        for workspace_id in &idle {
+            unlocked.remove(workspace_id);
+        }
+        idle
+    }
+}
+
+/// Takes the `value` off a `secret.set` or `secret.rotate` command, or the `password` off a
+/// `vault.init` or `vault.unlock`, before hooks, policy or a validation error can see it,
+/// leaving [`SECRET_REDACTED`] in its place. A value that is not a string is dropped rather
+/// than echoed back by schema validation.
+pub(crate) fn take_value(command: &mut CommandEnvelope) -> Option<String> {
+    let field = match command.command_type.as_str() {
+        COMMAND_SECRET_SET | COMMAND_SECRET_ROTATE => "value",
+        COMMAND_VAULT_INIT | COMMAND_VAULT_UNLOCK => "password",
+        _ => return None,
+    };
+    let slot = command.payload.get_mut(field)?;
+    match std::mem::replace(slot, Value::String(SECRET_REDACTED.to_string())) {
+        Value::String(value) => Some(value),
+        _ => {
+            *slot = Value::Null;
+            None
+        }
+    }
+}
+
+/// What a vault or secret command stores beside its events, and changes in memory once they
+/// are appended.
+pub(crate) enum VaultEffect {
+    /// Seals `value` for the version the append derives.
+    Seal {
+        key: Arc<VaultKey>,
+        value: String,
+    },
+    Create(VaultRecord),
+    /// Holds `key` until the vault locks, storing the re-keyed vault first when `rekey` is set.
+    Unlock {
+        workspace_id: String,
+        key: Box<VaultKey>,
+        idle_timeout: Duration,
+        rekey: Option<(VaultRecord, Vec<SecretCiphertext>)>,
+    },
+    Lock {
+        workspace_id: String,
+    },
+}
+
+/// A planned vault or secret command: its events and their effect, or its rejection.
+pub(crate) type VaultPlan = Result<(Vec<NewEvent>, VaultEffect), SubmitCommandResponse>;
+
+/// Appends a vault or secret command's events, storing its effect in the same transaction and
+/// applying it in memory only once the append succeeds.
+pub(crate) fn append(
+    state: &AppState,
+    store: &mut SqliteStore,
+    meta: &CommandMeta,
+    events: Vec<NewEvent>,
+    effect: VaultEffect,
+) -> Result<AppendResult, StoreError> {
+    match effect {
+        VaultEffect::Seal { key, value } => {
+            let seal = |workspace_id: &str, secret_id: &str, version: u32| {
+                let sealed = key
+                    .seal_secret(
+                        &secret_location(workspace_id, secret_id, version),
+                        value.as_bytes(),
+                    )
+                    .map_err(|err| StoreError::Internal(format!("seal secret failed: {err}")))?;
+                Ok(SecretCiphertext {
+                    workspace_id: workspace_id.to_string(),
+                    secret_id: secret_id.to_string(),
+                    version,
+                    wrapped_key: sealed.wrapped_key,
+                    ciphertext: sealed.ciphertext,
+                })
+            };
+            store.append_with_vault(meta, events, VaultWrite::SealVersion(&seal))
+        }
+        VaultEffect::Create(vault) => {
+            store.append_with_vault(meta, events, VaultWrite::Create(&vault))
+        }
+        VaultEffect::Unlock {
+            workspace_id,
+            key,
+            idle_timeout,
+            rekey,
+        } => {
+            let result = match &rekey {
+                Some((vault, rewrapped)) => {
+                    store.append_with_vault(meta, events, VaultWrite::Rekey(vault, rewrapped))?
+                }
+                None => store.append(meta, events)?,
+            };
+            state
+                .vaults
+                .unlock(&workspace_id, *key, idle_timeout, state.clock.now());
+            Ok(result)
+        }
+        VaultEffect::Lock { workspace_id } => {
+            let result = store.append(meta, events)?;
+            state.vaults.lock(&workspace_id);
+            Ok(result)
+        }
+    }
+}
+
+pub(crate) async fn plan_set(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    value: Option<String>,
+) -> Result<VaultPlan, ApiError> {
+    let payload: SecretSetPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    if load_workspace(state, command, &payload.workspace_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(Err);
+    }
+    if let Some(rejected) =
+        check_owner(state, command, &payload.workspace_id, &payload.owner).await?
+    {
+        return Ok(Err(rejected));
+    }
+    let value = value.unwrap_or_default();
+    if let Some(rejected) = check_value(state, command, &value).await? {
+        return Ok(Err(rejected));
+    }
+    let key = match unlocked_key(state, command, &payload.workspace_id).await? {
+        Ok(key) => key,
+        Err(rejected) => return Ok(Err(rejected)),
+    };
+
+    let secrets = {
+        let store = state.store.lock().await;
+        store.list_secrets(&payload.workspace_id).map_err(|err| {
+            tracing::error!("list_secrets failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?
+    };
+    if secrets
+        .iter()
+        .any(|secret| secret.name == payload.name && secret.owner == payload.owner)
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "secret {} already exists in {} scope; rotate it instead",
+                payload.name, payload.owner.scope
+            ),
+        )
+        .await
+        .map(Err);
+    }
+
+    let event_payload = version_payload(command, payload.name, payload.owner, 1, &value)?;
+    let event = secret_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_SECRET_CREATED,
+        mp_kernel::new_uuid(),
+        event_payload,
+        &command.trace_id,
+    );
+    Ok(Ok((vec![event], VaultEffect::Seal { key, value })))
+}
+
+/// Seals a new version of an existing secret; earlier versions stay stored until the secret
+/// is deleted.
+pub(crate) async fn plan_rotate(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    value: Option<String>,
+) -> Result<VaultPlan, ApiError> {
+    let payload: SecretRotatePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    let secret =
+        match load_secret(state, command, &payload.workspace_id, &payload.secret_id).await? {
+            Ok(secret) => secret,
+            Err(rejected) => return Ok(Err(rejected)),
+        };
+    let value = value.unwrap_or_default();
+    if let Some(rejected) = check_value(state, command, &value).await? {
+        return Ok(Err(rejected));
+    }
+    let key = match unlocked_key(state, command, &payload.workspace_id).await? {
+        Ok(key) => key,
+        Err(rejected) => return Ok(Err(rejected)),
+    };
+
+    // The append derives the version it stores and records; this one only fills the payload.
+    let event_payload = version_payload(
+        command,
+        secret.name,
+        secret.owner,
+        secret.version + 1,
+        &value,
+    )?;
+    let event = secret_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_SECRET_ROTATED,
+        secret.secret_id,
+        event_payload,
+        &command.trace_id,
+    );
+    Ok(Ok((vec![event], VaultEffect::Seal { key, value })))
+}
+
+/// Deleting needs no key, so a locked vault can still shed a leaked secret. The stored
+/// ciphertext goes with the projection row.
+pub(crate) async fn plan_delete(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: SecretDeletePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let secret =
+        match load_secret(state, command, &payload.workspace_id, &payload.secret_id).await? {
+            Ok(secret) => secret,
+            Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+        };
+    let event_payload = serde_json::to_value(SecretDeletedPayload {
+        name: secret.name,
+        owner: secret.owner,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_SECRET_DELETED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![secret_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_SECRET_DELETED,
+        secret.secret_id,
+        event_payload,
+        &command.trace_id,
+    )]))
+}
+
+/// Scoped secrets name their user, project or session; global ones name nothing.
+async fn check_owner(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    owner: &SecretOwner,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    let refusal = match (owner.scope, owner.id.as_deref()) {
+        (SecretScope::Global, None) | (SecretScope::User, Some(_)) => None,
+        (SecretScope::Global, Some(_)) => Some((
+            ErrorCode::ValidationFailed,
+            "global secrets take no owner id".to_string(),
+        )),
+        (scope, None) => Some((
+            ErrorCode::ValidationFailed,
+            format!("{scope} secrets need an owner id"),
+        )),
+        (SecretScope::Project, Some(project_id)) => {
+            (!project_exists(state, command, workspace_id, project_id).await?).then(|| {
+                (
+                    ErrorCode::NotFound,
+                    format!("project {project_id} not found"),
+                )
+            })
+        }
+        (SecretScope::Session, Some(session_id)) => {
+            let session = {
+                let store = state.store.lock().await;
+                store.get_session(workspace_id, session_id).map_err(|err| {
+                    tracing::error!("get_session failed: {err}");
+                    internal_error(Some(command.trace_id.clone()))
+                })?
+            };
+            session.is_none().then(|| {
+                (
+                    ErrorCode::NotFound,
+                    format!("session {session_id} not found"),
+                )
+            })
+        }
+    };
+    match refusal {
+        Some((code, message)) => reject_command(state, command, code, &message)
+            .await
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Rejections never include the value itself.
+async fn check_value(
+    state: &AppState,
+    command: &CommandEnvelope,
+    value: &str,
+) -> Result<Option<SubmitCommandResponse>, ApiError> {
+    if !value.is_empty() && value.len() <= SECRET_MAX_BYTES {
+        return Ok(None);
+    }
+    reject_command(
+        state,
+        command,
+        ErrorCode::ValidationFailed,
+        &format!("secret value must be between 1 and {SECRET_MAX_BYTES} bytes"),
+    )
+    .await
+    .map(Some)
+}
+
+pub(crate) async fn unlocked_key(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+) -> Result<Result<Arc<VaultKey>, SubmitCommandResponse>, ApiError> {
+    if let Some(key) = state.vaults.key(workspace_id, state.clock.now()) {
+        return Ok(Ok(key));
+    }
+    let vault = {
+        let store = state.store.lock().await;
+        store.get_vault(workspace_id).map_err(|err| {
+            tracing::error!("get_vault failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?
+    };
+    let (code, message) = match vault {
+        Some(_) => (
+            ErrorCode::Unauthorized,
+            format!("vault for workspace {workspace_id} is locked"),
+        ),
+        None => (
+            ErrorCode::NotFound,
+            format!("workspace {workspace_id} has no vault"),
+        ),
+    };
+    reject_command(state, command, code, &message)
+        .await
+        .map(Err)
+}
+
+async fn load_secret(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    secret_id: &str,
+) -> Result<Result<SecretEntry, SubmitCommandResponse>, ApiError> {
+    let secret = {
+        let store = state.store.lock().await;
+        store.get_secret(workspace_id, secret_id).map_err(|err| {
+            tracing::error!("get_secret failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?
+    };
+    match secret {
+        Some(secret) => Ok(Ok(secret)),
+        None => reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("secret {secret_id} not found"),
+        )
+        .await
+        .map(Err),
+    }
+}
+
+/// Where a sealed version is stored; bound into both seals as associated data.
+pub(crate) fn secret_location(workspace_id: &str, secret_id: &str, version: u32) -> String {
+    format!("{workspace_id}/{secret_id}/{version}")
+}
+
+fn version_payload(
+    command: &CommandEnvelope,
+    name: String,
+    owner: SecretOwner,
+    version: u32,
+    value: &str,
+) -> Result<Value, ApiError> {
+    serde_json::to_value(SecretVersionPayload {
+        name,
+        owner,
+        version,
+        redacted: SECRET_REDACTED.to_string(),
+        length: value.len(),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize secret version payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
+
+pub(crate) fn secret_event(
+    actor: Actor,
+    workspace_id: &str,
+    event_type: &str,
+    secret_id: String,
+    payload: Value,
+    trace_id: &str,
+) -> NewEvent {
+    NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id: None,
+        subject: Subject {
+            kind: "secret".to_string(),
+            id: secret_id,
+        },
+        payload,
+        trace_id: Some(trace_id.to_string()),
+        stream_id: None,
+    }
+}
+
+fn vault_event(
+    actor: Actor,
+    workspace_id: &str,
+    event_type: &str,
+    payload: Value,
+    trace_id: &str,
+) -> NewEvent {
+    NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id: None,
+        subject: Subject {
+            kind: "vault".to_string(),
+            id: workspace_id.to_string(),
+        },
+        payload,
+        trace_id: Some(trace_id.to_string()),
+        stream_id: None,
+    }
+}
+
+/// Appends a vault event outside command submission, such as the scheduler's idle lock.
+/// Unlocked keys live in memory only, so vault events are the record of when each was open.
+pub(crate) fn record_vault_event(
+    state: &AppState,
+    store: &mut SqliteStore,
+    operation: &str,
+    event: NewEvent,
+) -> Result<(), ApiError> {
+    record_events(state, store, operation, vec![event]).map(|_| ())
+}
+
+/// `vault.locked` for a vault the scheduler found idle past its deadline.
+pub(crate) fn idle_lock_event(workspace_id: &str, trace_id: &str) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(VaultLockedPayload {
+        reason: VaultLockReason::Idle,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_VAULT_LOCKED} payload failed: {err}");
+        internal_error(Some(trace_id.to_string()))
+    })?;
+    Ok(vault_event(
+        Actor::system(),
+        workspace_id,
+        EVENT_VAULT_LOCKED,
+        payload,
+        trace_id,
+    ))
+}
+
+fn query_params<T>(query: Result<Query<T>, QueryRejection>) -> Result<T, ApiError> {
+    query.map(|Query(query)| query).map_err(|err| {
+        ApiError::new(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::InvalidSchema,
+            err.to_string(),
+            None,
+            None,
+        )
+    })
+}
+
+fn vault_header(vault: &VaultRecord) -> VaultHeader {
+    VaultHeader {
+        kdf: vault.kdf,
+        salt: vault.salt.clone(),
+        check: vault.check.clone(),
+    }
+}
+
+/// Key derivation is deliberately slow, so it runs off the async workers. `None` means the
+/// password was wrong.
+async fn derive<T: Send + 'static>(
+    command: &CommandEnvelope,
+    work: impl FnOnce() -> Result<T, VaultError> + Send + 'static,
+) -> Result<Option<T>, ApiError> {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(Ok(value)) => Ok(Some(value)),
+        Ok(Err(VaultError::WrongPassword)) => Ok(None),
+        Ok(Err(err)) => {
+            tracing::error!("vault key derivation failed: {err}");
+            Err(internal_error(Some(command.trace_id.clone())))
+        }
+        Err(err) => {
+            tracing::error!("vault key derivation panicked: {err}");
+            Err(internal_error(Some(command.trace_id.clone())))
+        }
+    }
+}
+
+fn vault_status(state: &AppState, workspace_id: &str, vault: Option<&VaultRecord>) -> VaultStatus {
+    let locks_at = vault.and_then(|_| state.vaults.locks_at(workspace_id, state.clock.now()));
+    VaultStatus {
+        workspace_id: workspace_id.to_string(),
+        initialized: vault.is_some(),
+        unlocked: locks_at.is_some(),
+        kdf: vault.map(|vault| vault.kdf),
+        locks_at: locks_at.map(format_rfc3339),
+    }
+}
+
+/// A vault key derived before the planning lock is taken, so a slow key derivation (or a run
+/// of wrong passwords) never holds up other commands. Planners check the vault is still the
+/// one it was derived against.
+pub(crate) enum DerivedKey {
+    /// The header of a new key, for `vault.init`.
+    Created(VaultHeader),
+    /// The outcome of unlocking `vault`: its key plus a re-keyed replacement when its key
+    /// derivation parameters are out of date, or `None` for a wrong password.
+    Unlocked {
+        vault: VaultRecord,
+        derived: Option<Box<UnlockedKey>>,
+    },
+}
+
+/// A vault's key and, when it is re-keyed on unlock, the new key and its header.
+pub(crate) struct UnlockedKey {
+    key: VaultKey,
+    upgraded: Option<(VaultKey, VaultHeader)>,
+}
+
+/// Derives the key a `vault.init` or `vault.unlock` needs; `None` for other commands and for
+/// ones their planner rejects before any key is needed.
+pub(crate) async fn derive_key(
+    state: &AppState,
+    command: &CommandEnvelope,
+    password: Option<&str>,
+) -> Result<Option<DerivedKey>, ApiError> {
+    let password = password.unwrap_or_default().to_string();
+    match command.command_type.as_str() {
+        COMMAND_VAULT_INIT => {
+            let payload: VaultInitPayload = decode_payload(command)?;
+            if password.chars().count() < VAULT_MIN_PASSWORD_CHARS
+                || find_vault(state, command, &payload.workspace_id)
+                    .await?
+                    .is_some()
+            {
+                return Ok(None);
+            }
+            let Some((_, header)) =
+                derive(command, move || VaultKey::create(&password, DEFAULT_KDF)).await?
+            else {
+                tracing::error!("creating a vault key reported a wrong password");
+                return Err(internal_error(Some(command.trace_id.clone())));
+            };
+            Ok(Some(DerivedKey::Created(header)))
+        }
+        COMMAND_VAULT_UNLOCK => {
+            let payload: VaultUnlockPayload = decode_payload(command)?;
+            let Some(vault) = find_vault(state, command, &payload.workspace_id).await? else {
+                return Ok(None);
+            };
+            let header = vault_header(&vault);
+            let upgrade = mp_vault::needs_upgrade(&vault.kdf);
+            let derived = derive(command, move || {
+                let key = VaultKey::unlock(&password, &header)?;
+                if !upgrade {
+                    return Ok((key, None));
+                }
+                let (new_key, new_header) = VaultKey::create(&password, DEFAULT_KDF)?;
+                Ok((key, Some((new_key, new_header))))
+            })
+            .await?;
+            Ok(Some(DerivedKey::Unlocked {
+                vault,
+                derived: derived.map(|(key, upgraded)| Box::new(UnlockedKey { key, upgraded })),
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Rejects a vault command whose vault changed between deriving its key and planning.
+async fn reject_stale_key(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+) -> Result<VaultPlan, ApiError> {
+    reject_command(
+        state,
+        command,
+        ErrorCode::ExpectedVersionMismatch,
+        &format!("vault for workspace {workspace_id} changed while its key was derived"),
+    )
+    .await
+    .map(Err)
+}
+
+/// Creates a workspace's vault. It starts locked.
+pub(crate) async fn plan_vault_init(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    password: Option<String>,
+    derived: Option<DerivedKey>,
+) -> Result<VaultPlan, ApiError> {
+    let payload: VaultInitPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    let password = password.unwrap_or_default();
+    if password.chars().count() < VAULT_MIN_PASSWORD_CHARS {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("vault password must be at least {VAULT_MIN_PASSWORD_CHARS} characters"),
+        )
+        .await
+        .map(Err);
+    }
+    if load_workspace(state, command, &payload.workspace_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(Err);
+    }
+    if find_vault(state, command, &payload.workspace_id)
+        .await?
+        .is_some()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("workspace {} already has a vault", payload.workspace_id),
+        )
+        .await
+        .map(Err);
+    }
+
+    let Some(DerivedKey::Created(header)) = derived else {
+        return reject_stale_key(state, command, &payload.workspace_id).await;
+    };
+    let now = format_rfc3339(state.clock.now());
+    let vault = VaultRecord {
+        workspace_id: payload.workspace_id.clone(),
+        kdf: header.kdf,
+        salt: header.salt,
+        check: header.check,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    let event_payload =
+        serde_json::to_value(VaultInitializedPayload { kdf: vault.kdf }).map_err(|err| {
+            tracing::error!("serialize {EVENT_VAULT_INITIALIZED} payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+    let event = vault_event(
+        actor,
+        &payload.workspace_id,
+        EVENT_VAULT_INITIALIZED,
+        event_payload,
+        &command.trace_id,
+    );
+    Ok(Ok((vec![event], VaultEffect::Create(vault))))
+}
+
+/// Holds the key [`derive_key`] derived once `vault.unlocked` is appended, until the vault is
+/// locked or sits idle for `idle_timeout_ms`. A vault created with weaker key derivation
+/// parameters than the current defaults is re-keyed on the way. A wrong password is rejected,
+/// so failed attempts are on record too.
+pub(crate) async fn plan_vault_unlock(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    derived: Option<DerivedKey>,
+) -> Result<VaultPlan, ApiError> {
+    let payload: VaultUnlockPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    let idle_timeout_ms = payload
+        .idle_timeout_ms
+        .unwrap_or(VAULT_DEFAULT_IDLE_TIMEOUT_MS);
+    if idle_timeout_ms > VAULT_MAX_IDLE_TIMEOUT_MS {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("idle timeout must be between 1 and {VAULT_MAX_IDLE_TIMEOUT_MS} ms"),
+        )
+        .await
+        .map(Err);
+    }
+    let Some(vault) = find_vault(state, command, &payload.workspace_id).await? else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} has no vault", payload.workspace_id),
+        )
+        .await
+        .map(Err);
+    };
+
+    let derived = match derived {
+        Some(DerivedKey::Unlocked {
+            vault: derived_for,
+            derived,
+        }) if derived_for == vault => derived,
+        _ => return reject_stale_key(state, command, &payload.workspace_id).await,
+    };
+    let Some(UnlockedKey { key, upgraded }) = derived.map(|derived| *derived) else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::Unauthorized,
+            "vault password is incorrect",
+        )
+        .await
+        .map(Err);
+    };
+
+    let (key, rekey) = match upgraded {
+        None => (key, None),
+        Some((new_key, header)) => {
+            let versions = {
+                let store = state.store.lock().await;
+                store
+                    .list_secret_versions(&vault.workspace_id)
+                    .map_err(|err| {
+                        tracing::error!("list_secret_versions failed: {err}");
+                        internal_error(Some(command.trace_id.clone()))
+                    })?
+            };
+            let mut rewrapped = Vec::with_capacity(versions.len());
+            for version in versions {
+                let location =
+                    secret_location(&version.workspace_id, &version.secret_id, version.version);
+                let wrapped_key = key
+                    .rewrap(&new_key, &location, &version.wrapped_key)
+                    .map_err(|err| {
+                        tracing::error!("rewrap {location} failed: {err}");
+                        internal_error(Some(command.trace_id.clone()))
+                    })?;
+                rewrapped.push(SecretCiphertext {
+                    wrapped_key,
+                    ..version
+                });
+            }
+            let rekeyed = VaultRecord {
+                kdf: header.kdf,
+                salt: header.salt,
+                check: header.check,
+                updated_at: format_rfc3339(state.clock.now()),
+                ..vault.clone()
+            };
+            (new_key, Some((rekeyed, rewrapped)))
+        }
+    };
+
+    let event_payload = serde_json::to_value(VaultUnlockedPayload {
+        idle_timeout_ms,
+        upgraded_kdf: rekey.as_ref().map(|(rekeyed, _)| rekeyed.kdf),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_VAULT_UNLOCKED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    let event = vault_event(
+        actor,
+        &vault.workspace_id,
+        EVENT_VAULT_UNLOCKED,
+        event_payload,
+        &command.trace_id,
+    );
+    Ok(Ok((
+        vec![event],
+        VaultEffect::Unlock {
+            workspace_id: vault.workspace_id,
+            key: Box::new(key),
+            idle_timeout: Duration::milliseconds(idle_timeout_ms as i64),
+            rekey,
+        },
+    )))
+}
+
+/// Drops the vault key. Locking a vault that is already locked records nothing.
+pub(crate) async fn plan_vault_lock(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<VaultPlan, ApiError> {
+    let payload: VaultLockPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(Err(rejected));
+    }
+    if find_vault(state, command, &payload.workspace_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} has no vault", payload.workspace_id),
+        )
+        .await
+        .map(Err);
+    }
+    let mut events = Vec::new();
+    if state.vaults.holds(&payload.workspace_id) {
+        let event_payload = serde_json::to_value(VaultLockedPayload {
+            reason: VaultLockReason::Requested,
+        })
+        .map_err(|err| {
+            tracing::error!("serialize {EVENT_VAULT_LOCKED} payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+        events.push(vault_event(
+            actor,
+            &payload.workspace_id,
+            EVENT_VAULT_LOCKED,
+            event_payload,
+            &command.trace_id,
+        ));
+    }
+    Ok(Ok((
+        events,
+        VaultEffect::Lock {
+            workspace_id: payload.workspace_id,
+        },
+    )))
+}
+
+async fn find_vault(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+) -> Result<Option<VaultRecord>, ApiError> {
+    let store = state.store.lock().await;
+    store.get_vault(workspace_id).map_err(|err| {
+        tracing::error!("get_vault failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct VaultQuery {
+    workspace_id: String,
+}
+
+pub(crate) async fn handle_vault_status(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<VaultQuery>, QueryRejection>,
+) -> Result<Json<VaultStatus>, ApiError> {
+    authorize(&state, &headers)?;
+    let query = query_params(query)?;
+    let store = state.store.lock().await;
+    let vault = store.get_vault(&query.workspace_id).map_err(|err| {
+        tracing::error!("get_vault failed: {err}");
+        internal_error(None)
+    })?;
+    Ok(Json(vault_status(
+        &state,
+        &query.workspace_id,
+        vault.as_ref(),
+    )))
+}
+
+pub(crate) async fn handle_list_secrets(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<VaultQuery>, QueryRejection>,
+) -> Result<Json<Vec<SecretEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let query = query_params(query)?;
+    let store = state.store.lock().await;
+    let secrets = store.list_secrets(&query.workspace_id).map_err(|err| {
+        tracing::error!("list_secrets failed: {err}");
+        internal_error(None)
+    })?;
+    Ok(Json(secrets))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SecretResolveQuery {
+    workspace_id: String,
+    name: String,
+    #[serde(default)]
+    user_id: Option<String>,
+    #[serde(default)]
+    project_id: Option<String>,
+    #[serde(default)]
+    session_id: Option<String>,
+}
+
+/// Metadata of the secret `name` resolves to for the given user, project and session.
+pub(crate) async fn handle_resolve_secret(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<SecretResolveQuery>, QueryRejection>,
+) -> Result<Json<SecretEntry>, ApiError> {
+    authorize(&state, &headers)?;
+    let query = query_params(query)?;
+    let store = state.store.lock().await;
+    let secrets = store.list_secrets(&query.workspace_id).map_err(|err| {
+        tracing::error!("list_secrets failed: {err}");
+        internal_error(None)
+    })?;
+    let context = SecretContext {
+        user_id: query.user_id,
+        project_id: query.project_id,
+        session_id: query.session_id,
+    };
+    resolve_secret(&secrets, &query.name, &context)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| {
+            ApiError::new(
+                StatusCode::NOT_FOUND,
+                ErrorCode::NotFound,
+                format!("no secret {} is visible here", query.name),
+                None,
+                None,
+            )
+        })
+}
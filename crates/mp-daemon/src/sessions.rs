@@ -0,0 +1,173 @@
+use crate::{
+    blueprints, decode_payload, ensure_expected_version, internal_error, project_exists,
+    reject_command, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    lineage_path, Actor, ErrorCode, ForkMode, SessionForkPayload, SessionForkedPayload,
+    SessionListEntry, SessionSpawnPayload, SessionSpawnedPayload, Subject, EVENT_SESSION_FORKED,
+    EVENT_SESSION_SPAWNED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+
+pub(crate) async fn plan_spawn(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: SessionSpawnPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let parent = match &payload.parent_session_id {
+        Some(parent_id) => {
+            match load_session(state, command, &payload.workspace_id, parent_id).await? {
+                Some(parent) => Some(parent),
+                None => {
+                    return reject_command(
+                        state,
+                        command,
+                        ErrorCode::NotFound,
+                        &format!("parent session {parent_id} not found"),
+                    )
+                    .await
+                    .map(CommandOutcome::Rejected);
+                }
+            }
+        }
+        None => None,
+    };
+
+    // Children inherit the parent's project unless the spawn targets another one.
+    let project_id = payload
+        .project_id
+        .clone()
+        .or_else(|| parent.as_ref().and_then(|p| p.project_id.clone()));
+    if let Some(project_id) = &payload.project_id {
+        if !project_exists(state, command, &payload.workspace_id, project_id).await? {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("project {project_id} not found"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    }
+
+    let blueprint = match &payload.blueprint {
+        Some(reference) => {
+            match blueprints::resolve_for_session(state, command, &payload.workspace_id, reference)
+                .await?
+            {
+                Ok(blueprint) => Some(blueprint),
+                Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+            }
+        }
+        None => None,
+    };
+
+    let session_id = mp_kernel::new_uuid();
+    let (root_session_id, path) = match &parent {
+        Some(parent) => (
+            parent.root_session_id.clone(),
+            lineage_path(Some(&parent.lineage_path), &session_id),
+        ),
+        None => (session_id.clone(), lineage_path(None, &session_id)),
+    };
+    let payload_json = serde_json::to_value(SessionSpawnedPayload {
+        parent_session_id: payload.parent_session_id,
+        root_session_id,
+        lineage_path: path,
+        label: payload.label,
+        blueprint,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize session.spawned payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_SESSION_SPAWNED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id,
+        subject: Subject {
+            kind: "session".to_string(),
+            id: session_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+pub(crate) async fn plan_fork(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: SessionForkPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let Some(source) =
+        load_session(state, command, &payload.workspace_id, &payload.session_id).await?
+    else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("session {} not found", payload.session_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+
+    // A fork hangs off the session it was forked from so the lineage stays a single tree.
+    let session_id = mp_kernel::new_uuid();
+    let payload_json = serde_json::to_value(SessionForkedPayload {
+        forked_from_session_id: source.session_id.clone(),
+        mode: payload.mode.unwrap_or(ForkMode::Default),
+        parent_session_id: source.session_id.clone(),
+        root_session_id: source.root_session_id.clone(),
+        lineage_path: lineage_path(Some(&source.lineage_path), &session_id),
+        label: payload.label,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize session.forked payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_SESSION_FORKED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id: source.project_id,
+        subject: Subject {
+            kind: "session".to_string(),
+            id: session_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+pub(crate) async fn load_session(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    session_id: &str,
+) -> Result<Option<SessionListEntry>, ApiError> {
+    let store = state.store.lock().await;
+    store.get_session(workspace_id, session_id).map_err(|err| {
+        tracing::error!("get_session failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
@@ -0,0 +1,825 @@
+use crate::{
+    authorize, decode_payload, ensure_expected_version, internal_error, reject_command,
+    reject_command_with_details, workspaces::load_workspace, ApiError, AppState, CommandOutcome,
+};
+use axum::{
+    extract::rejection::QueryRejection,
+    extract::{Path as UrlPath, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use base64::Engine;
+use mp_kernel::{
+    skill_trust, Actor, ErrorCode, SkillApprovePayload, SkillApprovedPayload, SkillComponents,
+    SkillEntry, SkillFrontmatter, SkillRemovedPayload, SkillScope, SkillSignature,
+    SkillSignatureStatus, SkillSyncPayload, SkillTrust, SkillVerifyReport, SkillVersionPayload,
+    Subject, ARTIFACT_HASH_PREFIX, EVENT_SKILL_APPROVED, EVENT_SKILL_REGISTERED,
+    EVENT_SKILL_REMOVED, EVENT_SKILL_UPDATED, SKILL_API_VERSION, SKILL_DIR, SKILL_MANIFEST,
+    SKILL_SIGNATURE,
+};
+use mp_protocol::{CommandEnvelope, SchemaRegistry};
+use mp_storage::{NewEvent, ProjectionReader};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// DER prefix of an ed25519 `SubjectPublicKeyInfo`; the raw 32-byte key follows it.
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const ED25519_PUBLIC_KEY_LEN: usize = 32;
+
+/// An ed25519 public key whose signatures make a skill trusted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SkillTrustKey {
+    /// Reported as the signature's `key_id`; the key file's stem when loaded from disk.
+    pub id: String,
+    pub public_key: Vec<u8>,
+}
+
+/// Where global skills come from and which keys may vouch for a skill.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SkillSources {
+    pub(crate) global_dir: Option<PathBuf>,
+    pub(crate) trust_keys: Vec<SkillTrustKey>,
+}
+
+/// Reads an ed25519 public key: a PEM `PUBLIC KEY` block, or the raw 32 bytes in base64.
+pub fn load_skill_trust_key(path: &Path) -> anyhow::Result<SkillTrustKey> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| anyhow::anyhow!("failed to read {}: {err}", path.display()))?;
+    let id = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("skill trust key {} has no name", path.display()))?;
+    let encoded: String = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .map_err(|err| anyhow::anyhow!("invalid skill trust key {}: {err}", path.display()))?;
+    let public_key = match bytes.strip_prefix(&ED25519_SPKI_PREFIX) {
+        Some(key) => key.to_vec(),
+        None => bytes,
+    };
+    if public_key.len() != ED25519_PUBLIC_KEY_LEN {
+        anyhow::bail!(
+            "invalid skill trust key {}: expected an ed25519 public key",
+            path.display()
+        );
+    }
+    Ok(SkillTrustKey { id, public_key })
+}
+
+/// One skill directory that parsed and validated, before trust is settled.
+#[derive(Debug)]
+struct LoadedSkill {
+    scope: SkillScope,
+    path: String,
+    content_hash: String,
+    signature: SkillSignature,
+    frontmatter: SkillFrontmatter,
+    components: SkillComponents,
+}
+
+#[derive(Debug, Serialize)]
+struct FileError {
+    path: String,
+    message: String,
+}
+
+/// Like blueprint sync, the directories are the source of truth and one invalid skill rejects
+/// the whole sync. Trust is recomputed on every sync, so a key added to the daemon or an
+/// edit that breaks a signature shows up as `skill.updated`.
+pub(crate) async fn plan_sync(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: SkillSyncPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(workspace) = load_workspace(state, command, &payload.workspace_id).await? else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+
+    let loaded = match load_skills(
+        &state.schema_registry,
+        &state.skill_sources,
+        Path::new(&workspace.root_path),
+    ) {
+        Ok(loaded) => loaded,
+        Err(errors) => {
+            return reject_command_with_details(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!("{} skill(s) failed validation", errors.len()),
+                Some(serde_json::json!({ "errors": errors })),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+
+    let store = state.store.lock().await;
+    let registered = store
+        .list_skills(&payload.workspace_id, None)
+        .map_err(|err| {
+            tracing::error!("list_skills failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+    drop(store);
+
+    let mut events = Vec::new();
+    for skill in &loaded {
+        let skill_id = skill.frontmatter.name.clone();
+        let existing = registered.iter().find(|entry| entry.skill_id == skill_id);
+        let trust = skill_trust(
+            skill.scope,
+            skill.signature.status,
+            &skill.content_hash,
+            existing.and_then(|entry| entry.approved_hash.as_deref()),
+        );
+        let (event_type, revision) = match existing {
+            None => (EVENT_SKILL_REGISTERED, 1),
+            Some(entry)
+                if entry.content_hash == skill.content_hash
+                    && entry.scope == skill.scope
+                    && entry.path == skill.path
+                    && entry.trust == trust
+                    && entry.signature == skill.signature =>
+            {
+                continue
+            }
+            Some(entry) => (EVENT_SKILL_UPDATED, entry.revision + 1),
+        };
+        let event_payload = serde_json::to_value(SkillVersionPayload {
+            skill_id: skill_id.clone(),
+            revision,
+            scope: skill.scope,
+            path: skill.path.clone(),
+            content_hash: skill.content_hash.clone(),
+            trust,
+            signature: skill.signature.clone(),
+            frontmatter: skill.frontmatter.clone(),
+            components: skill.components.clone(),
+        })
+        .map_err(|err| {
+            tracing::error!("serialize {event_type} payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+        events.push(skill_event(
+            command,
+            &actor,
+            &payload.workspace_id,
+            event_type,
+            skill_id,
+            event_payload,
+        ));
+    }
+    for entry in &registered {
+        if loaded
+            .iter()
+            .any(|skill| skill.frontmatter.name == entry.skill_id)
+        {
+            continue;
+        }
+        let event_payload = serde_json::to_value(SkillRemovedPayload {
+            skill_id: entry.skill_id.clone(),
+        })
+        .map_err(|err| {
+            tracing::error!("serialize {EVENT_SKILL_REMOVED} payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+        events.push(skill_event(
+            command,
+            &actor,
+            &payload.workspace_id,
+            EVENT_SKILL_REMOVED,
+            entry.skill_id.clone(),
+            event_payload,
+        ));
+    }
+    Ok(CommandOutcome::Append(events))
+}
+
+/// Approval names the content hash the reviewer saw, so a skill edited after review cannot be
+/// approved by accident.
+pub(crate) async fn plan_approve(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: SkillApprovePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let skill = {
+        let store = state.store.lock().await;
+        store
+            .get_skill(&payload.workspace_id, &payload.skill_id)
+            .map_err(|err| {
+                tracing::error!("get_skill failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let Some(skill) = skill else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("skill {} not found", payload.skill_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if skill.content_hash != payload.content_hash {
+        return reject_command_with_details(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "skill {} changed since it was reviewed; inspect it again",
+                skill.skill_id
+            ),
+            Some(serde_json::json!({
+                "expected": payload.content_hash,
+                "actual": skill.content_hash,
+            })),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if skill.trust != SkillTrust::Proposed {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("skill {} is already {}", skill.skill_id, skill.trust),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let event_payload = serde_json::to_value(SkillApprovedPayload {
+        skill_id: skill.skill_id.clone(),
+        content_hash: skill.content_hash,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {EVENT_SKILL_APPROVED} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![skill_event(
+        command,
+        &actor,
+        &payload.workspace_id,
+        EVENT_SKILL_APPROVED,
+        skill.skill_id,
+        event_payload,
+    )]))
+}
+
+fn skill_event(
+    command: &CommandEnvelope,
+    actor: &Actor,
+    workspace_id: &str,
+    event_type: &str,
+    skill_id: String,
+    payload: serde_json::Value,
+) -> NewEvent {
+    NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor: actor.clone(),
+        workspace_id: workspace_id.to_string(),
+        project_id: None,
+        subject: Subject {
+            kind: "skill".to_string(),
+            id: skill_id,
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SkillsQuery {
+    workspace_id: String,
+    #[serde(default)]
+    trust: Option<SkillTrust>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct SkillQuery {
+    workspace_id: String,
+}
+
+pub(crate) async fn handle_list_skills(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    query: Result<Query<SkillsQuery>, QueryRejection>,
+) -> Result<Json<Vec<SkillEntry>>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(invalid_query)?;
+    let store = state.store.lock().await;
+    let skills = store
+        .list_skills(&query.workspace_id, query.trust)
+        .map_err(|err| {
+            tracing::error!("list_skills failed: {err}");
+            internal_error(None)
+        })?;
+    Ok(Json(skills))
+}
+
+pub(crate) async fn handle_get_skill(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    UrlPath(skill_id): UrlPath<String>,
+    query: Result<Query<SkillQuery>, QueryRejection>,
+) -> Result<Json<SkillEntry>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(invalid_query)?;
+    get_skill(&state, &query.workspace_id, &skill_id)
+        .await
+        .map(Json)
+}
+
+/// Reloads a registered skill from disk and reports drift and the trust it would get now,
+/// without recording anything.
+pub(crate) async fn handle_verify_skill(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    UrlPath(skill_id): UrlPath<String>,
+    query: Result<Query<SkillQuery>, QueryRejection>,
+) -> Result<Json<SkillVerifyReport>, ApiError> {
+    authorize(&state, &headers)?;
+    let Query(query) = query.map_err(invalid_query)?;
+    let skill = get_skill(&state, &query.workspace_id, &skill_id).await?;
+    let dir = match skill.scope {
+        SkillScope::Workspace => {
+            let store = state.store.lock().await;
+            let workspaces = store.list_workspaces(true).map_err(|err| {
+                tracing::error!("list_workspaces failed: {err}");
+                internal_error(None)
+            })?;
+            workspaces
+                .into_iter()
+                .find(|workspace| workspace.workspace_id == query.workspace_id)
+                .map(|workspace| Path::new(&workspace.root_path).join(&skill.path))
+        }
+        SkillScope::Global => state
+            .skill_sources
+            .global_dir
+            .as_ref()
+            .map(|dir| dir.join(&skill.path)),
+    };
+    let loaded = match dir {
+        Some(dir) => load_skill(
+            &state.schema_registry,
+            &state.skill_sources.trust_keys,
+            skill.scope,
+            &dir,
+            skill.path.clone(),
+        ),
+        None => Err(format!("{} skill directory is not configured", skill.scope)),
+    };
+    let report = match loaded {
+        Ok(loaded) => SkillVerifyReport {
+            drift: loaded.content_hash != skill.content_hash,
+            trust: skill_trust(
+                skill.scope,
+                loaded.signature.status,
+                &loaded.content_hash,
+                skill.approved_hash.as_deref(),
+            ),
+            skill_id: skill.skill_id,
+            registered_hash: skill.content_hash,
+            disk_hash: Some(loaded.content_hash),
+            signature: loaded.signature,
+            error: None,
+        },
+        Err(message) => SkillVerifyReport {
+            skill_id: skill.skill_id,
+            registered_hash: skill.content_hash,
+            disk_hash: None,
+            drift: true,
+            signature: skill.signature,
+            trust: SkillTrust::Proposed,
+            error: Some(message),
+        },
+    };
+    Ok(Json(report))
+}
+
+async fn get_skill(
+    state: &AppState,
+    workspace_id: &str,
+    skill_id: &str,
+) -> Result<SkillEntry, ApiError> {
+    let store = state.store.lock().await;
+    let skill = store.get_skill(workspace_id, skill_id).map_err(|err| {
+        tracing::error!("get_skill failed: {err}");
+        internal_error(None)
+    })?;
+    skill.ok_or_else(|| {
+        ApiError::new(
+            StatusCode::NOT_FOUND,
+            ErrorCode::NotFound,
+            format!("skill {skill_id} not found"),
+            None,
+            None,
+        )
+    })
+}
+
+fn invalid_query(err: QueryRejection) -> ApiError {
+    ApiError::new(
+        StatusCode::BAD_REQUEST,
+        ErrorCode::InvalidSchema,
+        err.to_string(),
+        None,
+        None,
+    )
+}
+
+/// Reads `<root>/agents/skills/*/` and then the global skill directory. A missing directory
+/// holds no skills. A name defined in both places is an error rather than an override, so a
+/// workspace cannot shadow a skill the operator installed.
+fn load_skills(
+    registry: &SchemaRegistry,
+    sources: &SkillSources,
+    root: &Path,
+) -> Result<Vec<LoadedSkill>, Vec<FileError>> {
+    let mut dirs = vec![(
+        SkillScope::Workspace,
+        root.join(SKILL_DIR),
+        SKILL_DIR.to_string(),
+    )];
+    if let Some(global_dir) = &sources.global_dir {
+        dirs.push((
+            SkillScope::Global,
+            global_dir.clone(),
+            global_dir.display().to_string(),
+        ));
+    }
+
+    let mut loaded: Vec<LoadedSkill> = Vec::new();
+    let mut errors = Vec::new();
+    for (scope, dir, label) in dirs {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                errors.push(FileError {
+                    path: label,
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let mut skill_dirs: Vec<_> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_dir()))
+            .map(|entry| entry.path())
+            .collect();
+        skill_dirs.sort();
+        for skill_dir in skill_dirs {
+            let name = skill_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let path = match scope {
+                SkillScope::Workspace => format!("{SKILL_DIR}/{name}"),
+                SkillScope::Global => name,
+            };
+            let shown = format!(
+                "{label}/{}",
+                skill_dir.file_name().unwrap_or_default().to_string_lossy()
+            );
+            match load_skill(registry, &sources.trust_keys, scope, &skill_dir, path) {
+                Ok(skill) => {
+                    if let Some(existing) = loaded
+                        .iter()
+                        .find(|other| other.frontmatter.name == skill.frontmatter.name)
+                    {
+                        errors.push(FileError {
+                            path: shown,
+                            message: format!(
+                                "skill {} is already defined by the {} skill {}",
+                                skill.frontmatter.name, existing.scope, existing.path
+                            ),
+                        });
+                        continue;
+                    }
+                    loaded.push(skill);
+                }
+                Err(message) => errors.push(FileError {
+                    path: shown,
+                    message,
+                }),
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(loaded)
+    } else {
+        Err(errors)
+    }
+}
+
+fn load_skill(
+    registry: &SchemaRegistry,
+    trust_keys: &[SkillTrustKey],
+    scope: SkillScope,
+    dir: &Path,
+    path: String,
+) -> Result<LoadedSkill, String> {
+    let files = list_files(dir)?;
+    if !files.iter().any(|file| file == SKILL_MANIFEST) {
+        return Err(format!("missing {SKILL_MANIFEST}"));
+    }
+
+    let mut hasher = blake3::Hasher::new();
+    let mut components = SkillComponents::default();
+    let mut manifest = Vec::new();
+    for file in &files {
+        if file == SKILL_SIGNATURE {
+            continue;
+        }
+        let bytes = std::fs::read(dir.join(file)).map_err(|err| format!("{file}: {err}"))?;
+        // Paths and lengths are framed so moving bytes between files changes the hash.
+        hasher.update(file.as_bytes());
+        hasher.update(&[0]);
+        hasher.update(&(bytes.len() as u64).to_le_bytes());
+        hasher.update(&bytes);
+        match file.split_once('/').map(|(top, _)| top) {
+            Some("wasm") => components.wasm.push(file.clone()),
+            Some("scripts") => components.scripts.push(file.clone()),
+            Some("refs") => components.refs.push(file.clone()),
+            Some("tests") => components.tests.push(file.clone()),
+            _ => {}
+        }
+        if file == SKILL_MANIFEST {
+            manifest = bytes;
+        }
+    }
+    let content_hash = format!("{ARTIFACT_HASH_PREFIX}{}", hasher.finalize().to_hex());
+
+    let frontmatter = parse_frontmatter(registry, &manifest)?;
+    let name = dir
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if frontmatter.name != name {
+        return Err(format!(
+            "skill name {} does not match directory name {name}",
+            frontmatter.name
+        ));
+    }
+
+    let signature = if files.iter().any(|file| file == SKILL_SIGNATURE) {
+        let encoded =
+            std::fs::read_to_string(dir.join(SKILL_SIGNATURE)).map_err(|err| err.to_string())?;
+        verify_signature(trust_keys, &content_hash, encoded.trim())
+    } else {
+        SkillSignature {
+            status: SkillSignatureStatus::Unsigned,
+            key_id: None,
+        }
+    };
+
+    Ok(LoadedSkill {
+        scope,
+        path,
+        content_hash,
+        signature,
+        frontmatter,
+        components,
+    })
+}
+
+/// Every regular file under `dir` as a sorted, `/`-separated relative path. Symlinks are
+/// refused: a skill's hash must cover every byte it can reach.
+fn list_files(dir: &Path) -> Result<Vec<String>, String> {
+    let mut files = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), String::new())];
+    while let Some((current, prefix)) = pending.pop() {
+        let entries = std::fs::read_dir(&current).map_err(|err| err.to_string())?;
+        for entry in entries {
+            let entry = entry.map_err(|err| err.to_string())?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let relative = format!("{prefix}{name}");
+            let kind = entry.file_type().map_err(|err| err.to_string())?;
+            if kind.is_symlink() {
+                return Err(format!("{relative}: symlinks are not allowed in skills"));
+            } else if kind.is_dir() {
+                pending.push((entry.path(), format!("{relative}/")));
+            } else {
+                files.push(relative);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// `SKILL.md` opens with YAML frontmatter between `---` lines; the rest is instructions.
+fn parse_frontmatter(
+    registry: &SchemaRegistry,
+    manifest: &[u8],
+) -> Result<SkillFrontmatter, String> {
+    let text = std::str::from_utf8(manifest)
+        .map_err(|_| format!("{SKILL_MANIFEST} is not valid UTF-8"))?;
+    let mut lines = text.lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return Err(format!("{SKILL_MANIFEST} must start with --- frontmatter"));
+    }
+    let mut yaml = String::new();
+    let mut closed = false;
+    for line in lines {
+        if line.trim_end() == "---" {
+            closed = true;
+            break;
+        }
+        yaml.push_str(line);
+        yaml.push('\n');
+    }
+    if !closed {
+        return Err(format!("{SKILL_MANIFEST} frontmatter is not closed by ---"));
+    }
+    let document: serde_json::Value =
+        serde_yaml::from_str(&yaml).map_err(|err| format!("invalid frontmatter: {err}"))?;
+    registry
+        .validate_document("skill", SKILL_API_VERSION as i32, &document)
+        .map_err(|err| err.message)?;
+    serde_json::from_value(document).map_err(|err| err.to_string())
+}
+
+/// The signature covers the content hash string, so signing needs only the hash that
+/// `mpctl skill inspect` prints.
+fn verify_signature(
+    trust_keys: &[SkillTrustKey],
+    content_hash: &str,
+    encoded: &str,
+) -> SkillSignature {
+    let invalid = SkillSignature {
+        status: SkillSignatureStatus::Invalid,
+        key_id: None,
+    };
+    let Ok(signature) = base64::engine::general_purpose::STANDARD.decode(encoded) else {
+        return invalid;
+    };
+    trust_keys
+        .iter()
+        .find(|key| {
+            UnparsedPublicKey::new(&ED25519, &key.public_key)
+                .verify(content_hash.as_bytes(), &signature)
+                .is_ok()
+        })
+        .map(|key| SkillSignature {
+            status: SkillSignatureStatus::Valid,
+            key_id: Some(key.id.clone()),
+        })
+        .unwrap_or(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    fn write(dir: &Path, file: &str, contents: &str) {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().expect("parent")).expect("mkdir");
+        std::fs::write(path, contents).expect("write");
+    }
+
+    fn key_pair() -> Ed25519KeyPair {
+        Ed25519KeyPair::from_seed_unchecked(&[7; 32]).expect("key pair")
+    }
+
+    #[test]
+    fn skill_hash_covers_components_but_not_the_signature() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let dir = tempfile::tempdir().expect("tempdir");
+        let skill_dir = dir.path().join("pdf-tools");
+        write(
+            &skill_dir,
+            SKILL_MANIFEST,
+            "---\nname: pdf-tools\ndescription: Extracts text from PDFs.\n---\n# Use\n",
+        );
+        write(&skill_dir, "scripts/extract.sh", "#!/bin/sh\n");
+        write(&skill_dir, "refs/spec/pdf.md", "PDF notes\n");
+        let load = |keys: &[SkillTrustKey]| {
+            load_skill(
+                &registry,
+                keys,
+                SkillScope::Workspace,
+                &skill_dir,
+                "agents/skills/pdf-tools".to_string(),
+            )
+            .expect("load")
+        };
+
+        let unsigned = load(&[]);
+        assert_eq!(unsigned.signature.status, SkillSignatureStatus::Unsigned);
+        assert_eq!(unsigned.components.scripts, vec!["scripts/extract.sh"]);
+        assert_eq!(unsigned.components.refs, vec!["refs/spec/pdf.md"]);
+
+        let pair = key_pair();
+        let key = SkillTrustKey {
+            id: "docs-team".to_string(),
+            public_key: pair.public_key().as_ref().to_vec(),
+        };
+        let signature = pair.sign(unsigned.content_hash.as_bytes());
+        write(
+            &skill_dir,
+            SKILL_SIGNATURE,
+            &base64::engine::general_purpose::STANDARD.encode(signature.as_ref()),
+        );
+        let signed = load(std::slice::from_ref(&key));
+        assert_eq!(signed.content_hash, unsigned.content_hash);
+        assert_eq!(signed.signature.status, SkillSignatureStatus::Valid);
+        assert_eq!(signed.signature.key_id.as_deref(), Some("docs-team"));
+        assert_eq!(load(&[]).signature.status, SkillSignatureStatus::Invalid);
+
+        write(&skill_dir, "scripts/extract.sh", "#!/bin/sh\nrm -rf /\n");
+        let tampered = load(std::slice::from_ref(&key));
+        assert_ne!(tampered.content_hash, unsigned.content_hash);
+        assert_eq!(tampered.signature.status, SkillSignatureStatus::Invalid);
+    }
+
+    #[test]
+    fn skill_manifest_must_match_its_directory() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let dir = tempfile::tempdir().expect("tempdir");
+        let load = |name: &str| {
+            load_skill(
+                &registry,
+                &[],
+                SkillScope::Global,
+                &dir.path().join(name),
+                name.to_string(),
+            )
+        };
+        write(&dir.path().join("empty"), "refs/a.md", "a");
+        assert!(load("empty").unwrap_err().contains("missing SKILL.md"));
+        write(
+            &dir.path().join("renamed"),
+            SKILL_MANIFEST,
+            "---\nname: other\ndescription: Elsewhere.\n---\n",
+        );
+        assert!(load("renamed").unwrap_err().contains("does not match"));
+        write(
+            &dir.path().join("open"),
+            SKILL_MANIFEST,
+            "---\nname: open\ndescription: Never closed.\n",
+        );
+        assert!(load("open").unwrap_err().contains("not closed"));
+    }
+
+    #[test]
+    fn trust_keys_load_from_pem_or_raw_base64() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let public_key = key_pair().public_key().as_ref().to_vec();
+        let engine = base64::engine::general_purpose::STANDARD;
+        let spki = [ED25519_SPKI_PREFIX.as_slice(), &public_key].concat();
+        let pem_path = dir.path().join("release.pem");
+        std::fs::write(
+            &pem_path,
+            format!(
+                "-----BEGIN PUBLIC KEY-----\n{}\n-----END PUBLIC KEY-----\n",
+                engine.encode(spki)
+            ),
+        )
+        .expect("write pem");
+        let raw_path = dir.path().join("docs.pub");
+        std::fs::write(&raw_path, engine.encode(&public_key)).expect("write raw");
+
+        let pem = load_skill_trust_key(&pem_path).expect("pem");
+        assert_eq!(pem.id, "release");
+        assert_eq!(pem.public_key, public_key);
+        assert_eq!(
+            load_skill_trust_key(&raw_path).expect("raw").public_key,
+            public_key
+        );
+
+        std::fs::write(&raw_path, engine.encode([1u8; 16])).expect("write short");
+        assert!(load_skill_trust_key(&raw_path).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, project_exists, reject_command,
+    scheduler, sessions::load_session, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    format_rfc3339, next_task_state, parse_rfc3339, Actor, ErrorCode, FailureClass, Subject,
+    TaskAction, TaskCreatePayload, TaskCreatedPayload, TaskListEntry, TaskState,
+    TaskTransitionPayload, TaskTransitionedPayload, EVENT_TASK_CREATED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+
+pub(crate) async fn plan_create(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: TaskCreatePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    if let Some(project_id) = &payload.project_id {
+        if !project_exists(state, command, &payload.workspace_id, project_id).await? {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("project {project_id} not found"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    }
+    let mut project_id = payload.project_id.clone();
+    if let Some(session_id) = &payload.session_id {
+        let Some(session) = load_session(state, command, &payload.workspace_id, session_id).await?
+        else {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("session {session_id} not found"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        };
+        // Tasks owned by a session default to the session's project.
+        project_id = project_id.or(session.project_id);
+    }
+
+    let payload_json = serde_json::to_value(TaskCreatedPayload {
+        title: payload.title,
+        state: TaskState::Queued,
+        session_id: payload.session_id,
+        deadline_at: payload.deadline_at,
+        retry: payload.retry,
+        timeout_ms: payload.timeout_ms,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize task.created payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    Ok(CommandOutcome::Append(vec![NewEvent {
+        event_type: EVENT_TASK_CREATED.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: payload.workspace_id,
+        project_id,
+        subject: Subject {
+            kind: "task".to_string(),
+            id: mp_kernel::new_uuid(),
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }]))
+}
+
+pub(crate) async fn plan_transition(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    action: TaskAction,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: TaskTransitionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    let Some(task) = load_task(state, command, &payload.workspace_id, &payload.task_id).await?
+    else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("task {} not found", payload.task_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    let Some(next) = next_task_state(task.state, action) else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "cannot apply {} to task in state {}",
+                command.command_type, task.state
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if payload.failure_class.is_some() && action != TaskAction::Fail {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            "failure_class is only accepted by task.fail",
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let now = state.clock.now();
+    if action == TaskAction::Start {
+        if let Some(retry_at) = task.retry_at.as_deref() {
+            if parse_rfc3339(retry_at).is_some_and(|retry_at| now < retry_at) {
+                return reject_command(
+                    state,
+                    command,
+                    ErrorCode::ValidationFailed,
+                    &format!("task is backing off until {retry_at}"),
+                )
+                .await
+                .map(CommandOutcome::Rejected);
+            }
+        }
+    }
+
+    let failure_class = (action == TaskAction::Fail)
+        .then(|| payload.failure_class.unwrap_or(FailureClass::Transient));
+    let event_type = action.event_type();
+    let payload_json = serde_json::to_value(TaskTransitionedPayload {
+        from: task.state,
+        to: next,
+        reason: payload.reason,
+        failure_class,
+        started_at: (action == TaskAction::Start).then(|| format_rfc3339(now)),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+
+    let mut events = vec![NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor: actor.clone(),
+        workspace_id: payload.workspace_id,
+        project_id: task.project_id.clone(),
+        subject: Subject {
+            kind: "task".to_string(),
+            id: task.task_id.clone(),
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }];
+    if let Some(failure_class) = failure_class {
+        events.extend(scheduler::retry_event(
+            &task,
+            failure_class,
+            now,
+            actor,
+            &command.trace_id,
+        )?);
+    }
+    Ok(CommandOutcome::Append(events))
+}
+
+/// The event halting a running task whose lifecycle command is refused, with `hold` being
+/// [`TaskAction::BlockOnPolicy`] or [`TaskAction::BlockOnApproval`]. `None` for other commands
+/// and for tasks that are not running.
+pub(crate) async fn hold_event(
+    state: &AppState,
+    command: &CommandEnvelope,
+    hold: TaskAction,
+    reason: &str,
+) -> Result<Option<NewEvent>, ApiError> {
+    if TaskAction::from_command(&command.command_type).is_none() {
+        return Ok(None);
+    }
+    let payload: TaskTransitionPayload = decode_payload(command)?;
+    let Some(task) = load_task(state, command, &payload.workspace_id, &payload.task_id).await?
+    else {
+        return Ok(None);
+    };
+    let Some(next) = next_task_state(task.state, hold) else {
+        return Ok(None);
+    };
+    let event_type = hold.event_type();
+    let payload_json = serde_json::to_value(TaskTransitionedPayload {
+        from: task.state,
+        to: next,
+        reason: Some(reason.to_string()),
+        failure_class: None,
+        started_at: None,
+    })
+    .map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(Some(NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor: Actor::system(),
+        workspace_id: payload.workspace_id,
+        project_id: task.project_id,
+        subject: Subject {
+            kind: "task".to_string(),
+            id: task.task_id,
+        },
+        payload: payload_json,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    }))
+}
+
+pub(crate) async fn load_task(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    task_id: &str,
+) -> Result<Option<TaskListEntry>, ApiError> {
+    let store = state.store.lock().await;
+    store.get_task(workspace_id, task_id).map_err(|err| {
+        tracing::error!("get_task failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })
+}
@@ -0,0 +1,442 @@
+use crate::hooks::{self, Refusal};
+use crate::sandbox::{is_limit_error, Invocation};
+use crate::{
+    command_workspace_id, decode_payload, ensure_expected_version, internal_error, record_events,
+    reject_command, reject_command_after, reject_command_with_details, sessions, workspaces,
+    ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    Actor, ErrorCode, HookPhase, Subject, ToolCallStatus, ToolCompletePayload, ToolDefinition,
+    ToolError, ToolRequestPayload, ToolRequestedPayload, ToolResultPayload, ToolRuntime,
+    EVENT_TOOL_REQUESTED, EVENT_TOOL_RESULT,
+};
+use mp_protocol::{CommandEnvelope, EventEnvelope};
+use mp_storage::{NewEvent, ProjectionReader};
+use serde_json::Value;
+
+/// `tool.result` error codes the kernel itself assigns.
+const ERROR_INVALID_OUTPUT: &str = "invalid_output";
+const ERROR_LIMIT_EXCEEDED: &str = "limit_exceeded";
+const ERROR_MODULE_FAILED: &str = "module_failed";
+const ERROR_REFUSED_BY_HOOK: &str = "refused_by_hook";
+
+/// Input is checked against the tool's schema before anything is recorded, so a refused
+/// request leaves only `command.rejected`. Planning only records `tool.requested`; tools with a
+/// `wasm` runtime run once that is on record (see [`run_requested`]), and others stay pending
+/// until a runner sends `tool.complete`.
+pub(crate) async fn plan_request(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: ToolRequestPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    if workspaces::load_workspace(state, command, &payload.workspace_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("workspace {} not found", payload.workspace_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    let mut project_id = None;
+    if let Some(session_id) = &payload.session_id {
+        let Some(session) =
+            sessions::load_session(state, command, &payload.workspace_id, session_id).await?
+        else {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("session {session_id} not found"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        };
+        project_id = session.project_id;
+    }
+    let Some(tool) = state.schema_registry.tool(&payload.tool_id) else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("tool {} is not registered", payload.tool_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if let Err(err) = state
+        .schema_registry
+        .validate_tool_input(&tool.id, &payload.input)
+    {
+        return reject_command_with_details(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("input does not match {}: {}", tool.id, err.message),
+            Some(serde_json::json!({ "tool_id": tool.id, "field": "input" })),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let request_id = mp_kernel::new_uuid();
+    let workspace_id = payload.workspace_id.clone();
+    let event = |event_type: &str, body: Value| NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor: actor.clone(),
+        workspace_id: workspace_id.clone(),
+        project_id: project_id.clone(),
+        subject: call_subject(&request_id),
+        payload: body,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    };
+    let requested = serde_json::to_value(ToolRequestedPayload {
+        tool_id: tool.id.clone(),
+        tool_version: tool.version.clone(),
+        side_effect: tool.side_effect,
+        input: payload.input.clone(),
+        session_id: payload.session_id.clone(),
+    })
+    .map_err(|err| {
+        tracing::error!("serialize tool.requested payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(CommandOutcome::Append(vec![event(
+        EVENT_TOOL_REQUESTED,
+        requested,
+    )]))
+}
+
+/// Runs a kernel-run tool whose `tool.requested` was just appended, then records its
+/// hostcalls and `tool.result`. Running only after the request is on record means a retried
+/// command is answered from the log rather than by running the module again, and a module
+/// that ran is never left without a record of its request. Returns the events appended.
+pub(crate) async fn run_requested(
+    state: &AppState,
+    command: &CommandEnvelope,
+    appended: &[EventEnvelope],
+) -> Result<Vec<EventEnvelope>, ApiError> {
+    let Some(requested) = appended
+        .iter()
+        .find(|event| event.event_type == EVENT_TOOL_REQUESTED)
+    else {
+        return Ok(Vec::new());
+    };
+    let payload: ToolRequestedPayload =
+        serde_json::from_value(requested.payload.clone()).map_err(|err| {
+            tracing::error!("invalid tool.requested payload: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?;
+    let Some(tool) = state.schema_registry.tool(&payload.tool_id) else {
+        return Ok(Vec::new());
+    };
+    let Some(runtime) = &tool.runtime else {
+        return Ok(Vec::new());
+    };
+    let request_id = &requested.subject.id;
+    let (result, sandbox_events) =
+        run_tool(state, command, tool, runtime, request_id, payload.input).await;
+    // The module's hostcalls are recorded whatever its hooks decide about the result.
+    let mut events = sandbox_events;
+    let result = match intercept_result(state, command, tool, result, &mut events).await? {
+        Ok(result) => result,
+        Err(refusal) => serde_json::to_value(ToolResultPayload {
+            tool_id: tool.id.clone(),
+            status: ToolCallStatus::Failed,
+            output: None,
+            error: Some(ToolError {
+                code: ERROR_REFUSED_BY_HOOK.to_string(),
+                message: refusal.message,
+            }),
+        })
+        .map_err(|err| {
+            tracing::error!("serialize tool.result payload failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?,
+    };
+    events.push(NewEvent {
+        event_type: EVENT_TOOL_RESULT.to_string(),
+        schema_version: 1,
+        actor: requested.actor.clone(),
+        workspace_id: requested.workspace_id.clone(),
+        project_id: requested.project_id.clone(),
+        subject: call_subject(request_id),
+        payload: result,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    });
+    let mut store = state.store.lock().await;
+    record_events(state, &mut store, &command.command_type, events)
+}
+
+/// Runs `post_tool` over a result before it is recorded. Hooks may rewrite it, but what they
+/// leave must still be a result for the same tool whose output matches the tool's schema.
+/// Events the chain records are pushed to `recorded`.
+async fn intercept_result(
+    state: &AppState,
+    command: &CommandEnvelope,
+    tool: &ToolDefinition,
+    result: ToolResultPayload,
+    recorded: &mut Vec<NewEvent>,
+) -> Result<Result<Value, Refusal>, ApiError> {
+    let mut target = serde_json::to_value(result).map_err(|err| {
+        tracing::error!("serialize tool.result payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    let check = |candidate: &Value| {
+        let rewritten: ToolResultPayload = serde_json::from_value(candidate.clone())
+            .map_err(|err| format!("rewritten result is invalid: {err}"))?;
+        if rewritten.tool_id != tool.id {
+            return Err(format!("rewritten result names tool {}", rewritten.tool_id));
+        }
+        match &rewritten.output {
+            Some(output) => state
+                .schema_registry
+                .validate_tool_output(&tool.id, output)
+                .map_err(|err| format!("rewritten output is invalid: {}", err.message)),
+            None => Ok(()),
+        }
+    };
+    match hooks::check_phase(
+        state,
+        command,
+        HookPhase::PostTool,
+        &mut target,
+        &check,
+        recorded,
+    )
+    .await?
+    {
+        Some(refusal) => Ok(Err(refusal)),
+        None => Ok(Ok(target)),
+    }
+}
+
+/// Settles a pending call. Output that does not match the tool's schema, or that a `post_tool`
+/// hook refuses, is rejected and the call stays pending, so the runner can retry with a
+/// corrected result or report an error.
+pub(crate) async fn plan_complete(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: ToolCompletePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let call = {
+        let store = state.store.lock().await;
+        store
+            .get_tool_call(&payload.workspace_id, &payload.request_id)
+            .map_err(|err| {
+                tracing::error!("get_tool_call failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    let Some(call) = call else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("tool call {} not found", payload.request_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if call.status != ToolCallStatus::Pending {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("tool call {} is already {}", call.request_id, call.status),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    let Some(tool) = state.schema_registry.tool(&call.tool_id) else {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("tool {} is no longer registered", call.tool_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    };
+    if tool.runtime.is_some() {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!("tool {} is run by the kernel", tool.id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let result = match (payload.output, payload.error) {
+        (Some(output), None) => {
+            if let Err(err) = state
+                .schema_registry
+                .validate_tool_output(&tool.id, &output)
+            {
+                return reject_command_with_details(
+                    state,
+                    command,
+                    ErrorCode::ValidationFailed,
+                    &format!("output does not match {}: {}", tool.id, err.message),
+                    Some(serde_json::json!({ "tool_id": tool.id, "field": "output" })),
+                )
+                .await
+                .map(CommandOutcome::Rejected);
+            }
+            ToolResultPayload {
+                tool_id: tool.id.clone(),
+                status: ToolCallStatus::Succeeded,
+                output: Some(output),
+                error: None,
+            }
+        }
+        (None, Some(error)) => ToolResultPayload {
+            tool_id: tool.id.clone(),
+            status: ToolCallStatus::Failed,
+            output: None,
+            error: Some(error),
+        },
+        // The command schema requires exactly one of the two.
+        _ => {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::InvalidSchema,
+                "exactly one of output and error is required",
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+    let mut events = Vec::new();
+    let event_payload = match intercept_result(state, command, tool, result, &mut events).await? {
+        Ok(result) => result,
+        Err(refusal) => {
+            return reject_command_after(
+                state,
+                command,
+                ErrorCode::PolicyDenied,
+                &refusal.message,
+                Some(refusal.details),
+                events,
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    };
+    let project_id = match &call.session_id {
+        Some(session_id) => sessions::load_session(state, command, &call.workspace_id, session_id)
+            .await?
+            .and_then(|session| session.project_id),
+        None => None,
+    };
+    events.push(NewEvent {
+        event_type: EVENT_TOOL_RESULT.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: call.workspace_id,
+        project_id,
+        subject: call_subject(&call.request_id),
+        payload: event_payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    });
+    Ok(CommandOutcome::Append(events))
+}
+
+/// Runs a `wasm` tool on a blocking thread. The module's hostcalls are logged against the
+/// call, and its output must match the tool's output schema to count as a success.
+async fn run_tool(
+    state: &AppState,
+    command: &CommandEnvelope,
+    tool: &ToolDefinition,
+    runtime: &ToolRuntime,
+    request_id: &str,
+    input: Value,
+) -> (ToolResultPayload, Vec<NewEvent>) {
+    let failed = |code: &str, message: String| ToolResultPayload {
+        tool_id: tool.id.clone(),
+        status: ToolCallStatus::Failed,
+        output: None,
+        error: Some(ToolError {
+            code: code.to_string(),
+            message,
+        }),
+    };
+    let ToolRuntime::Wasm {
+        module,
+        capabilities,
+        limits,
+    } = runtime;
+    let Some(loaded) = state.sandbox.module(&module.path) else {
+        return (
+            failed(
+                ERROR_MODULE_FAILED,
+                format!("module {} is not loaded", module.path),
+            ),
+            Vec::new(),
+        );
+    };
+    let invocation = Invocation {
+        module_id: tool.id.clone(),
+        subject: call_subject(request_id),
+        workspace_id: command_workspace_id(command),
+        trace_id: command.trace_id.clone(),
+        capabilities: capabilities.clone(),
+        limits: *limits,
+        store: state.store.clone(),
+        clock: state.clock.clone(),
+    };
+    let invoked = match tokio::task::spawn_blocking(move || loaded.invoke(invocation, &input)).await
+    {
+        Ok(invoked) => invoked,
+        Err(err) => {
+            tracing::error!("sandbox task for tool {} failed: {err}", tool.id);
+            return (
+                failed(ERROR_MODULE_FAILED, "sandbox task failed".to_string()),
+                Vec::new(),
+            );
+        }
+    };
+    let result = match invoked.output {
+        Ok(output) => match state
+            .schema_registry
+            .validate_tool_output(&tool.id, &output)
+        {
+            Ok(()) => ToolResultPayload {
+                tool_id: tool.id.clone(),
+                status: ToolCallStatus::Succeeded,
+                output: Some(output),
+                error: None,
+            },
+            Err(err) => failed(ERROR_INVALID_OUTPUT, err.message),
+        },
+        Err(error) if is_limit_error(&error) => failed(ERROR_LIMIT_EXCEEDED, error),
+        Err(error) => failed(ERROR_MODULE_FAILED, error),
+    };
+    (result, invoked.events)
+}
+
+fn call_subject(request_id: &str) -> Subject {
+    Subject {
+        kind: "tool_call".to_string(),
+        id: request_id.to_string(),
+    }
+}
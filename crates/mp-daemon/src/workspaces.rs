@@ -0,0 +1,286 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, reject_command, ApiError, AppState,
+    CommandOutcome,
+};
+use mp_kernel::{
+    Actor, ErrorCode, LifecycleChangedPayload, ProjectLifecyclePayload, ProjectListEntry,
+    ProjectRenamePayload, RenamedPayload, Subject, WorkspaceLifecyclePayload, WorkspaceListEntry,
+    WorkspaceRenamePayload, EVENT_PROJECT_ARCHIVED, EVENT_PROJECT_RENAMED, EVENT_PROJECT_RESTORED,
+    EVENT_WORKSPACE_ARCHIVED, EVENT_WORKSPACE_RENAMED, EVENT_WORKSPACE_RESTORED,
+};
+use mp_protocol::CommandEnvelope;
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Serialize;
+
+pub(crate) async fn plan_workspace_rename(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorkspaceRenamePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(workspace) = load_workspace(state, command, &payload.workspace_id).await? else {
+        return reject_missing(state, command, "workspace", &payload.workspace_id).await;
+    };
+    if let Some(rejected) = check_rename(
+        state,
+        command,
+        "workspace",
+        &workspace.name,
+        &payload.name,
+        workspace.archived_at.is_some(),
+    )
+    .await?
+    {
+        return Ok(rejected);
+    }
+
+    let event = lifecycle_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        None,
+        EVENT_WORKSPACE_RENAMED,
+        RenamedPayload {
+            previous_name: workspace.name,
+            name: payload.name,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+/// Plans `workspace.archive` (`archive == true`) or `workspace.restore`.
+pub(crate) async fn plan_workspace_archive(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    archive: bool,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorkspaceLifecyclePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(workspace) = load_workspace(state, command, &payload.workspace_id).await? else {
+        return reject_missing(state, command, "workspace", &payload.workspace_id).await;
+    };
+    if workspace.archived_at.is_some() == archive {
+        return reject_archive_state(state, command, "workspace", &workspace.name, archive).await;
+    }
+
+    let event = lifecycle_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        None,
+        if archive {
+            EVENT_WORKSPACE_ARCHIVED
+        } else {
+            EVENT_WORKSPACE_RESTORED
+        },
+        LifecycleChangedPayload {
+            reason: payload.reason,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+pub(crate) async fn plan_project_rename(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: ProjectRenamePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(project) =
+        load_project(state, command, &payload.workspace_id, &payload.project_id).await?
+    else {
+        return reject_missing(state, command, "project", &payload.project_id).await;
+    };
+    if let Some(rejected) = check_rename(
+        state,
+        command,
+        "project",
+        &project.name,
+        &payload.name,
+        project.archived_at.is_some(),
+    )
+    .await?
+    {
+        return Ok(rejected);
+    }
+
+    let event = lifecycle_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        Some(project.project_id),
+        EVENT_PROJECT_RENAMED,
+        RenamedPayload {
+            previous_name: project.name,
+            name: payload.name,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+/// Plans `project.archive` (`archive == true`) or `project.restore`.
+pub(crate) async fn plan_project_archive(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+    archive: bool,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: ProjectLifecyclePayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let Some(project) =
+        load_project(state, command, &payload.workspace_id, &payload.project_id).await?
+    else {
+        return reject_missing(state, command, "project", &payload.project_id).await;
+    };
+    if project.archived_at.is_some() == archive {
+        return reject_archive_state(state, command, "project", &project.name, archive).await;
+    }
+
+    let event = lifecycle_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        Some(project.project_id),
+        if archive {
+            EVENT_PROJECT_ARCHIVED
+        } else {
+            EVENT_PROJECT_RESTORED
+        },
+        LifecycleChangedPayload {
+            reason: payload.reason,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+pub(crate) async fn load_workspace(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+) -> Result<Option<WorkspaceListEntry>, ApiError> {
+    let store = state.store.lock().await;
+    let workspaces = store.list_workspaces(true).map_err(|err| {
+        tracing::error!("list_workspaces failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(workspaces
+        .into_iter()
+        .find(|workspace| workspace.workspace_id == workspace_id))
+}
+
+async fn load_project(
+    state: &AppState,
+    command: &CommandEnvelope,
+    workspace_id: &str,
+    project_id: &str,
+) -> Result<Option<ProjectListEntry>, ApiError> {
+    let store = state.store.lock().await;
+    let projects = store.list_projects(workspace_id, true).map_err(|err| {
+        tracing::error!("list_projects failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(projects
+        .into_iter()
+        .find(|project| project.project_id == project_id))
+}
+
+async fn reject_missing(
+    state: &AppState,
+    command: &CommandEnvelope,
+    kind: &str,
+    id: &str,
+) -> Result<CommandOutcome, ApiError> {
+    reject_command(
+        state,
+        command,
+        ErrorCode::NotFound,
+        &format!("{kind} {id} not found"),
+    )
+    .await
+    .map(CommandOutcome::Rejected)
+}
+
+/// Archived entries must be restored before they can be renamed.
+async fn check_rename(
+    state: &AppState,
+    command: &CommandEnvelope,
+    kind: &str,
+    current: &str,
+    requested: &str,
+    archived: bool,
+) -> Result<Option<CommandOutcome>, ApiError> {
+    let message = if archived {
+        format!("{kind} {current} is archived; restore it before renaming")
+    } else if current == requested {
+        format!("{kind} is already named {current}")
+    } else {
+        return Ok(None);
+    };
+    reject_command(state, command, ErrorCode::ValidationFailed, &message)
+        .await
+        .map(|response| Some(CommandOutcome::Rejected(response)))
+}
+
+async fn reject_archive_state(
+    state: &AppState,
+    command: &CommandEnvelope,
+    kind: &str,
+    name: &str,
+    archive: bool,
+) -> Result<CommandOutcome, ApiError> {
+    let message = if archive {
+        format!("{kind} {name} is already archived")
+    } else {
+        format!("{kind} {name} is not archived")
+    };
+    reject_command(state, command, ErrorCode::ValidationFailed, &message)
+        .await
+        .map(CommandOutcome::Rejected)
+}
+
+/// Builds a workspace event, or a project event when `project_id` is set.
+fn lifecycle_event<P: Serialize>(
+    command: &CommandEnvelope,
+    actor: Actor,
+    workspace_id: &str,
+    project_id: Option<String>,
+    event_type: &str,
+    payload: P,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    let subject = match &project_id {
+        Some(project_id) => Subject {
+            kind: "project".to_string(),
+            id: project_id.clone(),
+        },
+        None => Subject {
+            kind: "workspace".to_string(),
+            id: workspace_id.to_string(),
+        },
+    };
+    Ok(NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id,
+        subject,
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
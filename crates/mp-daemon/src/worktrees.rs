@@ -0,0 +1,371 @@
+use crate::{
+    decode_payload, ensure_expected_version, internal_error, project_exists, reject_command,
+    reject_command_with_details, sessions::load_session, ApiError, AppState, CommandOutcome,
+};
+use mp_kernel::{
+    Actor, ErrorCode, Subject, WorktreeEntry, WorktreeLockReleasedPayload, WorktreeRegisterPayload,
+    WorktreeRegisteredPayload, WorktreeSessionChangedPayload, WorktreeSessionPayload,
+    EVENT_WORKTREE_ATTACHED, EVENT_WORKTREE_DETACHED, EVENT_WORKTREE_LOCK_ACQUIRED,
+    EVENT_WORKTREE_LOCK_RELEASED, EVENT_WORKTREE_REGISTERED,
+};
+use mp_protocol::{CommandEnvelope, SubmitCommandResponse};
+use mp_storage::{NewEvent, ProjectionReader};
+use serde::Serialize;
+use serde_json::json;
+
+pub(crate) async fn plan_register(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorktreeRegisterPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+
+    if let Some(project_id) = &payload.project_id {
+        if !project_exists(state, command, &payload.workspace_id, project_id).await? {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::NotFound,
+                &format!("project {project_id} not found"),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+    }
+    let existing = {
+        let store = state.store.lock().await;
+        store.list_worktrees(&payload.workspace_id).map_err(|err| {
+            tracing::error!("list_worktrees failed: {err}");
+            internal_error(Some(command.trace_id.clone()))
+        })?
+    };
+    if let Some(existing) = existing
+        .iter()
+        .find(|worktree| worktree.path == payload.path)
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "{} is already registered as worktree {}",
+                payload.path, existing.worktree_id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let event = worktree_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        payload.project_id.clone(),
+        &mp_kernel::new_uuid(),
+        EVENT_WORKTREE_REGISTERED,
+        WorktreeRegisteredPayload {
+            path: payload.path,
+            branch: payload.branch,
+            repo: payload.repo,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+pub(crate) async fn plan_attach(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorktreeSessionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let worktree = match load_worktree(state, command, &payload).await? {
+        Ok(worktree) => worktree,
+        Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+    };
+    if load_session(state, command, &payload.workspace_id, &payload.session_id)
+        .await?
+        .is_none()
+    {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("session {} not found", payload.session_id),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+    if worktree.is_attached(&payload.session_id) {
+        return reject_command(
+            state,
+            command,
+            ErrorCode::ValidationFailed,
+            &format!(
+                "session {} is already attached to worktree {}",
+                payload.session_id, worktree.worktree_id
+            ),
+        )
+        .await
+        .map(CommandOutcome::Rejected);
+    }
+
+    let event = worktree_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        worktree.project_id,
+        &worktree.worktree_id,
+        EVENT_WORKTREE_ATTACHED,
+        WorktreeSessionChangedPayload {
+            session_id: payload.session_id,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+/// Detaching the lock holder releases the lock in the same append.
+pub(crate) async fn plan_detach(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorktreeSessionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let worktree = match load_worktree(state, command, &payload).await? {
+        Ok(worktree) => worktree,
+        Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+    };
+    if !worktree.is_attached(&payload.session_id) {
+        return reject_not_attached(state, command, &payload).await;
+    }
+
+    let mut events = Vec::new();
+    if worktree.lock_holder() == Some(payload.session_id.as_str()) {
+        events.push(worktree_event(
+            command,
+            actor.clone(),
+            &payload.workspace_id,
+            worktree.project_id.clone(),
+            &worktree.worktree_id,
+            EVENT_WORKTREE_LOCK_RELEASED,
+            WorktreeLockReleasedPayload {
+                session_id: payload.session_id.clone(),
+                reason: Some("detached".to_string()),
+            },
+        )?);
+    }
+    events.push(worktree_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        worktree.project_id,
+        &worktree.worktree_id,
+        EVENT_WORKTREE_DETACHED,
+        WorktreeSessionChangedPayload {
+            session_id: payload.session_id,
+        },
+    )?);
+    Ok(CommandOutcome::Append(events))
+}
+
+pub(crate) async fn plan_lock_acquire(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorktreeSessionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let worktree = match load_worktree(state, command, &payload).await? {
+        Ok(worktree) => worktree,
+        Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+    };
+    if !worktree.is_attached(&payload.session_id) {
+        return reject_not_attached(state, command, &payload).await;
+    }
+    match worktree.lock_holder() {
+        Some(holder) if holder == payload.session_id => {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!(
+                    "session {holder} already holds the lock on worktree {}",
+                    worktree.worktree_id
+                ),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+        Some(_) => return reject_lock_conflict(state, command, &worktree).await,
+        None => {}
+    }
+
+    let event = worktree_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        worktree.project_id,
+        &worktree.worktree_id,
+        EVENT_WORKTREE_LOCK_ACQUIRED,
+        WorktreeSessionChangedPayload {
+            session_id: payload.session_id,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+pub(crate) async fn plan_lock_release(
+    state: &AppState,
+    command: &CommandEnvelope,
+    actor: Actor,
+) -> Result<CommandOutcome, ApiError> {
+    let payload: WorktreeSessionPayload = decode_payload(command)?;
+    if let Some(rejected) = ensure_expected_version(state, command, &payload.workspace_id).await? {
+        return Ok(CommandOutcome::Rejected(rejected));
+    }
+    let worktree = match load_worktree(state, command, &payload).await? {
+        Ok(worktree) => worktree,
+        Err(rejected) => return Ok(CommandOutcome::Rejected(rejected)),
+    };
+    match worktree.lock_holder() {
+        None => {
+            return reject_command(
+                state,
+                command,
+                ErrorCode::ValidationFailed,
+                &format!("worktree {} is not locked", worktree.worktree_id),
+            )
+            .await
+            .map(CommandOutcome::Rejected);
+        }
+        Some(holder) if holder != payload.session_id => {
+            return reject_lock_conflict(state, command, &worktree).await;
+        }
+        Some(_) => {}
+    }
+
+    let event = worktree_event(
+        command,
+        actor,
+        &payload.workspace_id,
+        worktree.project_id,
+        &worktree.worktree_id,
+        EVENT_WORKTREE_LOCK_RELEASED,
+        WorktreeLockReleasedPayload {
+            session_id: payload.session_id,
+            reason: None,
+        },
+    )?;
+    Ok(CommandOutcome::Append(vec![event]))
+}
+
+/// Loads the worktree named by `payload`, or the `NotFound` rejection to return.
+async fn load_worktree(
+    state: &AppState,
+    command: &CommandEnvelope,
+    payload: &WorktreeSessionPayload,
+) -> Result<Result<WorktreeEntry, SubmitCommandResponse>, ApiError> {
+    let worktree = {
+        let store = state.store.lock().await;
+        store
+            .get_worktree(&payload.workspace_id, &payload.worktree_id)
+            .map_err(|err| {
+                tracing::error!("get_worktree failed: {err}");
+                internal_error(Some(command.trace_id.clone()))
+            })?
+    };
+    match worktree {
+        Some(worktree) => Ok(Ok(worktree)),
+        None => reject_command(
+            state,
+            command,
+            ErrorCode::NotFound,
+            &format!("worktree {} not found", payload.worktree_id),
+        )
+        .await
+        .map(Err),
+    }
+}
+
+async fn reject_not_attached(
+    state: &AppState,
+    command: &CommandEnvelope,
+    payload: &WorktreeSessionPayload,
+) -> Result<CommandOutcome, ApiError> {
+    reject_command(
+        state,
+        command,
+        ErrorCode::ValidationFailed,
+        &format!(
+            "session {} is not attached to worktree {}",
+            payload.session_id, payload.worktree_id
+        ),
+    )
+    .await
+    .map(CommandOutcome::Rejected)
+}
+
+/// Rejects with `LockConflict`, naming the current holder in the details.
+async fn reject_lock_conflict(
+    state: &AppState,
+    command: &CommandEnvelope,
+    worktree: &WorktreeEntry,
+) -> Result<CommandOutcome, ApiError> {
+    let lock = worktree.lock.as_ref();
+    reject_command_with_details(
+        state,
+        command,
+        ErrorCode::LockConflict,
+        &format!(
+            "worktree {} is locked by session {}",
+            worktree.worktree_id,
+            worktree.lock_holder().unwrap_or_default()
+        ),
+        Some(json!({
+            "worktree_id": worktree.worktree_id,
+            "holder_session_id": lock.map(|lock| lock.session_id.as_str()),
+            "acquired_at": lock.map(|lock| lock.acquired_at.as_str()),
+        })),
+    )
+    .await
+    .map(CommandOutcome::Rejected)
+}
+
+fn worktree_event<P: Serialize>(
+    command: &CommandEnvelope,
+    actor: Actor,
+    workspace_id: &str,
+    project_id: Option<String>,
+    worktree_id: &str,
+    event_type: &str,
+    payload: P,
+) -> Result<NewEvent, ApiError> {
+    let payload = serde_json::to_value(payload).map_err(|err| {
+        tracing::error!("serialize {event_type} payload failed: {err}");
+        internal_error(Some(command.trace_id.clone()))
+    })?;
+    Ok(NewEvent {
+        event_type: event_type.to_string(),
+        schema_version: 1,
+        actor,
+        workspace_id: workspace_id.to_string(),
+        project_id,
+        subject: Subject {
+            kind: "worktree".to_string(),
+            id: worktree_id.to_string(),
+        },
+        payload,
+        trace_id: Some(command.trace_id.clone()),
+        stream_id: None,
+    })
+}
@@ -1,12 +1,39 @@
+use base64::Engine;
 use futures::StreamExt;
 use mp_client::Client;
-use mp_daemon::{run_daemon, run_stdio_with_io, DaemonConfig, StdioAuth, StdioConfig};
-use mp_kernel::{ErrorCode, RuntimeInfo};
-use mp_protocol::{CommandEnvelope, ErrorResponse, StdioFrame, SubmitCommandResponse};
+use mp_daemon::{
+    cases_from_commands, cases_from_events, load_hook_chain, load_pin_manifest, load_policy_dir,
+    load_tool_registry, run_daemon, run_daemon_with_clock, run_hook_tests, run_stdio_with_io,
+    DaemonConfig, FixtureStatus, HookTestOptions, ManualClock, PolicySet, ReplayDecision,
+    SkillTrustKey, StdioAuth, StdioConfig,
+};
+use mp_kernel::{
+    Actor, Approver, ApproverKind, ArtifactPutPayload, Backoff, BlueprintRef, BlueprintSyncPayload,
+    BoardCommentAddPayload, BoardEdgeKind, BoardGroupCreatePayload, BoardNodeMovePayload,
+    BoardPosition, CapsuleKind, CapsuleWritePayload, CatalogKind, ErrorCode, FailureClass,
+    ForkMode, GateDecisionPayload, GateDefinePayload, GateKind, GateScope, GateStatus, HookAction,
+    HookChain, JitterMode, LeaseHolder, LeaseMintPayload, LeaseRevokePayload, LeaseScope,
+    LeaseStatus, MessageScope, MessageSendPayload, PinManifest, PipelineBindPayload,
+    PipelineTemplateDefinePayload, PolicyEffect, PolicyExplainRequest, ProcessExitedPayload,
+    ProjectLifecyclePayload, ProjectRenamePayload, RetryPolicy, RuntimeInfo, SecretContext,
+    SecretDeletePayload, SecretExecPayload, SecretInjectVia, SecretInjection, SecretOwner,
+    SecretRotatePayload, SecretScope, SecretSetPayload, SessionForkPayload, SessionSpawnPayload,
+    SideEffect, SkillApprovePayload, SkillScope, SkillSignatureStatus, SkillSyncPayload,
+    SkillTrust, StageDefinition, StageTransitionPayload, Subject, TaskCreatePayload, TaskState,
+    TaskTransitionPayload, ToolCallStatus, ToolCompletePayload, ToolError, ToolRegistry,
+    ToolRequestPayload, VaultInitPayload, VaultLockPayload, VaultUnlockPayload,
+    WorkspaceLifecyclePayload, WorkspaceRenamePayload, WorktreeRegisterPayload,
+    WorktreeSessionPayload, TOOL_LOAD_BUDGET_BYTES, TOOL_SEARCH_BUDGET_BYTES,
+};
+use mp_protocol::{CommandEnvelope, ErrorResponse, EventFilter, StdioFrame, SubmitCommandResponse};
+use mp_storage::ProjectionReader;
+use mp_storage_sqlite::SqliteStore;
 use reqwest::header::AUTHORIZATION;
 use reqwest::StatusCode;
 use std::net::SocketAddr;
+use std::sync::Arc;
 use tempfile::TempDir;
+use time::OffsetDateTime;
 use tokio::io::{duplex, AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::time::{sleep, timeout, Duration};
 
@@ -90,7 +117,18 @@ async fn workspace_persists_across_restart() -> anyhow::Result<()> {
         db_path: db_path.clone(),
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config.clone()));
@@ -101,7 +139,7 @@ async fn workspace_persists_across_restart() -> anyhow::Result<()> {
         .await?;
     assert!(create.accepted);
 
-    let list = client.workspace_list().await?;
+    let list = client.workspace_list(false).await?;
     assert_eq!(list.len(), 1);
 
     handle.abort();
@@ -109,7 +147,7 @@ async fn workspace_persists_across_restart() -> anyhow::Result<()> {
 
     let handle2 = tokio::spawn(run_daemon(config));
     let client2 = wait_for_client(&runtime_dir).await?;
-    let list2 = client2.workspace_list().await?;
+    let list2 = client2.workspace_list(false).await?;
     assert_eq!(list2.len(), 1);
 
     handle2.abort();
@@ -126,7 +164,18 @@ async fn idempotency_reuses_events() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -167,7 +216,18 @@ async fn expected_version_mismatch_rejects() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -176,7 +236,7 @@ async fn expected_version_mismatch_rejects() -> anyhow::Result<()> {
     client
         .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
         .await?;
-    let workspaces = client.workspace_list().await?;
+    let workspaces = client.workspace_list(false).await?;
     let workspace_id = workspaces[0].workspace_id.clone();
 
     let response = client
@@ -193,6 +253,4946 @@ async fn expected_version_mismatch_rejects() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn archived_projects_are_hidden_until_restored() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let project = client
+        .project_create(workspace_id.clone(), "croe".to_string(), None, None)
+        .await?;
+    let project_id = project.events[0].subject.id.clone();
+
+    let renamed = client
+        .project_rename(
+            ProjectRenamePayload {
+                workspace_id: workspace_id.clone(),
+                project_id: project_id.clone(),
+                name: "core".to_string(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(renamed.accepted);
+    assert_eq!(renamed.events[0].event_type, "project.renamed");
+    assert_eq!(renamed.events[0].payload["previous_name"], "croe");
+
+    let lifecycle = |reason: Option<&str>| ProjectLifecyclePayload {
+        workspace_id: workspace_id.clone(),
+        project_id: project_id.clone(),
+        reason: reason.map(str::to_string),
+    };
+    let archived = client
+        .project_lifecycle("project.archive", lifecycle(Some("merged")), None, None)
+        .await?;
+    assert!(archived.accepted);
+    assert!(client.project_list(&workspace_id, false).await?.is_empty());
+    let all = client.project_list(&workspace_id, true).await?;
+    assert_eq!(all[0].name, "core");
+    assert!(all[0].archived_at.is_some());
+
+    let again = client
+        .project_lifecycle("project.archive", lifecycle(None), None, None)
+        .await?;
+    assert_eq!(
+        again.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let restored = client
+        .project_lifecycle("project.restore", lifecycle(None), None, None)
+        .await?;
+    assert!(restored.accepted);
+    assert_eq!(client.project_list(&workspace_id, false).await?.len(), 1);
+
+    let archive_workspace = client
+        .workspace_lifecycle(
+            "workspace.archive",
+            WorkspaceLifecyclePayload {
+                workspace_id: workspace_id.clone(),
+                reason: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(archive_workspace.accepted);
+    assert!(client.workspace_list(false).await?.is_empty());
+    let rename_archived = client
+        .workspace_rename(
+            WorkspaceRenamePayload {
+                workspace_id: workspace_id.clone(),
+                name: "demo2".to_string(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        rename_archived.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    assert_eq!(client.workspace_list(true).await?.len(), 1);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_spawn_and_fork_build_lineage_tree() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let project = client
+        .project_create(workspace_id.clone(), "core".to_string(), None, None)
+        .await?;
+    let project_id = project.events[0].subject.id.clone();
+
+    let root = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: Some(project_id.clone()),
+                parent_session_id: None,
+                label: Some("planner".to_string()),
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(root.accepted);
+    assert_eq!(root.events[0].event_type, "session.spawned");
+    let root_id = root.events[0].subject.id.clone();
+
+    let child = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: Some(root_id.clone()),
+                label: None,
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(child.accepted);
+    let child_id = child.events[0].subject.id.clone();
+    assert_eq!(
+        child.events[0].project_id.as_deref(),
+        Some(project_id.as_str())
+    );
+
+    let fork = client
+        .session_fork(
+            SessionForkPayload {
+                workspace_id: workspace_id.clone(),
+                session_id: child_id.clone(),
+                mode: Some(ForkMode::PlanningOnly),
+                label: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(fork.accepted);
+    assert_eq!(fork.events[0].event_type, "session.forked");
+    let fork_id = fork.events[0].subject.id.clone();
+
+    let tree = client.session_list(&workspace_id, Some(&root_id)).await?;
+    let ids: Vec<_> = tree.iter().map(|s| s.session_id.clone()).collect();
+    assert_eq!(
+        ids,
+        vec![root_id.clone(), child_id.clone(), fork_id.clone()]
+    );
+    assert_eq!(
+        tree[2].parent_session_id.as_deref(),
+        Some(child_id.as_str())
+    );
+    assert_eq!(tree[2].root_session_id, root_id);
+    assert_eq!(
+        tree[2].lineage_path,
+        format!("/{root_id}/{child_id}/{fork_id}")
+    );
+    assert_eq!(tree[2].fork_mode, Some(ForkMode::PlanningOnly));
+
+    let orphan = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: Some("missing".to_string()),
+                label: None,
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(!orphan.accepted);
+    assert_eq!(
+        orphan.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn task_lifecycle_enforces_transitions() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+
+    let task = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "build".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(task.accepted);
+    assert_eq!(task.events[0].event_type, "task.created");
+    let task_id = task.events[0].subject.id.clone();
+    let transition = |reason: Option<&str>| TaskTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task_id.clone(),
+        reason: reason.map(|r| r.to_string()),
+        failure_class: None,
+    };
+
+    let early = client
+        .task_transition("task.succeed", transition(None), None, None)
+        .await?;
+    assert!(!early.accepted);
+    assert_eq!(
+        early.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let started = client
+        .task_transition("task.start", transition(None), None, None)
+        .await?;
+    assert!(started.accepted);
+    assert_eq!(started.events[0].event_type, "task.started");
+    let running = client
+        .task_list(&workspace_id, None, Some(TaskState::Running))
+        .await?;
+    assert_eq!(running.len(), 1);
+
+    // Concurrent transitions from the same state: only one may be recorded.
+    for _ in 0..8 {
+        let (first, second) = tokio::join!(
+            client.task_transition("task.pause", transition(None), None, None),
+            client.task_transition("task.pause", transition(None), None, None),
+        );
+        let (first, second) = (first?, second?);
+        assert!(first.accepted != second.accepted);
+        let resumed = client
+            .task_transition("task.resume", transition(None), None, None)
+            .await?;
+        assert!(resumed.accepted);
+    }
+
+    let done = client
+        .task_transition("task.succeed", transition(Some("green")), None, None)
+        .await?;
+    assert!(done.accepted);
+    assert_eq!(done.events[0].payload["from"], "running");
+    assert_eq!(done.events[0].payload["to"], "succeeded");
+
+    let cancel = client
+        .task_transition("task.cancel", transition(None), None, None)
+        .await?;
+    assert!(!cancel.accepted);
+
+    let tasks = client.task_list(&workspace_id, None, None).await?;
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0].state, TaskState::Succeeded);
+
+    let missing = client
+        .task_transition(
+            "task.start",
+            TaskTransitionPayload {
+                workspace_id: workspace_id.clone(),
+                task_id: "missing".to_string(),
+                reason: None,
+                failure_class: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        missing.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pipeline_binding_validates_stage_transitions() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let project = client
+        .project_create(workspace_id.clone(), "core".to_string(), None, None)
+        .await?;
+    let project_id = project.events[0].subject.id.clone();
+
+    let define = |stages: &[&str]| PipelineTemplateDefinePayload {
+        workspace_id: workspace_id.clone(),
+        name: "delivery".to_string(),
+        stages: stages
+            .iter()
+            .map(|name| StageDefinition {
+                name: name.to_string(),
+                description: None,
+            })
+            .collect(),
+    };
+    let v1 = client
+        .pipeline_template_define(define(&["plan", "build"]), None, None)
+        .await?;
+    assert!(v1.accepted);
+    let template_id = v1.events[0].subject.id.clone();
+    let v2 = client
+        .pipeline_template_define(define(&["plan", "build", "review"]), None, None)
+        .await?;
+    assert_eq!(v2.events[0].subject.id, template_id);
+    assert_eq!(v2.events[0].payload["version"], 2);
+
+    let duplicate = client
+        .pipeline_template_define(define(&["plan", "plan"]), None, None)
+        .await?;
+    assert_eq!(
+        duplicate.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let task = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "ship".to_string(),
+                project_id: Some(project_id.clone()),
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let task_id = task.events[0].subject.id.clone();
+    let stage = |to: &str| StageTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task_id.clone(),
+        to_stage: to.to_string(),
+        reason: None,
+    };
+
+    let unbound = client.stage_transition(stage("plan"), None, None).await?;
+    assert_eq!(
+        unbound.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let bound = client
+        .pipeline_bind(
+            PipelineBindPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: project_id.clone(),
+                template_id: template_id.clone(),
+                template_version: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(bound.accepted);
+    assert_eq!(bound.events[0].payload["template_version"], 2);
+
+    let moved = client.stage_transition(stage("review"), None, None).await?;
+    assert!(moved.accepted);
+    assert_eq!(moved.events[0].event_type, "task.stage_changed");
+    assert!(moved.events[0].payload.get("from_stage").is_none());
+
+    let unknown = client.stage_transition(stage("deploy"), None, None).await?;
+    assert_eq!(
+        unknown.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let view = client.pipeline_stages(&workspace_id, &project_id).await?;
+    let lanes: Vec<_> = view
+        .lanes
+        .iter()
+        .map(|lane| (lane.stage.as_str(), lane.tasks.len()))
+        .collect();
+    assert_eq!(lanes, vec![("plan", 0), ("build", 0), ("review", 1)]);
+    assert!(view.unstaged.is_empty());
+
+    let templates = client.pipeline_template_list(&workspace_id).await?;
+    assert_eq!(templates.len(), 2);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn gates_block_guarded_commands_until_approved() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let task = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "release".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let task_id = task.events[0].subject.id.clone();
+
+    let gate = client
+        .gate_define(
+            GateDefinePayload {
+                workspace_id: workspace_id.clone(),
+                scope: GateScope {
+                    command_type: "task.start".to_string(),
+                    subject: Subject {
+                        kind: "task".to_string(),
+                        id: task_id.clone(),
+                    },
+                },
+                kind: GateKind::ManualApproval,
+                required_approvals: None,
+                delay_ms: None,
+                description: Some("release sign-off".to_string()),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(gate.accepted);
+    let gate_id = gate.events[0].subject.id.clone();
+
+    let start = TaskTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task_id.clone(),
+        reason: None,
+        failure_class: None,
+    };
+    let blocked = client
+        .task_transition("task.start", start.clone(), None, None)
+        .await?;
+    assert!(!blocked.accepted);
+    assert_eq!(
+        blocked.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    assert_eq!(
+        blocked.events[0].payload["details"]["gates"][0]["gate_id"],
+        gate_id.as_str()
+    );
+
+    let decision = |kind: ApproverKind, id: &str| GateDecisionPayload {
+        workspace_id: workspace_id.clone(),
+        gate_id: gate_id.clone(),
+        approver: Approver {
+            kind,
+            id: id.to_string(),
+            label: None,
+        },
+        reason: Some("changelog reviewed".to_string()),
+        evidence: vec!["blake3:abc".to_string()],
+    };
+    let scorer = client
+        .gate_decide(
+            "gate.approve",
+            decision(ApproverKind::AiScorer, "scorer"),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        scorer.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let approved = client
+        .gate_decide(
+            "gate.approve",
+            decision(ApproverKind::Human, "alice"),
+            None,
+            None,
+        )
+        .await?;
+    assert!(approved.accepted);
+    assert_eq!(approved.events[0].event_type, "gate.approved");
+    assert_eq!(approved.events[0].payload["approver"]["id"], "alice");
+    assert_eq!(approved.events[0].payload["status"], "approved");
+
+    let late = client
+        .gate_decide(
+            "gate.reject",
+            decision(ApproverKind::Human, "bob"),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        late.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let started = client
+        .task_transition("task.start", start.clone(), None, None)
+        .await?;
+    assert!(started.accepted);
+
+    let gates = client.gate_list(&workspace_id, Some("task.start")).await?;
+    assert_eq!(gates.len(), 1);
+    assert_eq!(gates[0].status, GateStatus::Approved);
+    assert_eq!(
+        gates[0].decisions[0].evidence,
+        vec!["blake3:abc".to_string()]
+    );
+
+    // A running task whose lifecycle command a gate refuses is held until it is resumed.
+    let awaiting = client
+        .task_transition("task.await_input", start.clone(), None, None)
+        .await?;
+    assert_eq!(awaiting.events[0].event_type, "task.awaiting_input");
+    let resumed = client
+        .task_transition("task.resume", start.clone(), None, None)
+        .await?;
+    assert!(resumed.accepted);
+    let succeed_gate = client
+        .gate_define(
+            GateDefinePayload {
+                workspace_id: workspace_id.clone(),
+                scope: GateScope {
+                    command_type: "task.succeed".to_string(),
+                    subject: Subject {
+                        kind: "task".to_string(),
+                        id: task_id.clone(),
+                    },
+                },
+                kind: GateKind::ManualApproval,
+                required_approvals: None,
+                delay_ms: None,
+                description: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let held = client
+        .task_transition("task.succeed", start.clone(), None, None)
+        .await?;
+    assert!(!held.accepted);
+    let kinds: Vec<&str> = held
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["task.blocked_by_approval", "command.rejected"]);
+    let blocked_tasks = client
+        .task_list(&workspace_id, None, Some(TaskState::BlockedByApproval))
+        .await?;
+    assert_eq!(blocked_tasks.len(), 1);
+
+    let approved = client
+        .gate_decide(
+            "gate.approve",
+            GateDecisionPayload {
+                gate_id: succeed_gate.events[0].subject.id.clone(),
+                ..decision(ApproverKind::Human, "alice")
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(approved.accepted);
+    let resumed = client
+        .task_transition("task.resume", start.clone(), None, None)
+        .await?;
+    assert_eq!(resumed.events[0].payload["from"], "blocked_by_approval");
+    let succeeded = client
+        .task_transition("task.succeed", start, None, None)
+        .await?;
+    assert!(succeeded.accepted);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn worktree_lock_allows_a_single_writer() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let mut session_ids = Vec::new();
+    for label in ["writer", "reviewer"] {
+        let spawned = client
+            .session_spawn(
+                SessionSpawnPayload {
+                    workspace_id: workspace_id.clone(),
+                    project_id: None,
+                    parent_session_id: None,
+                    label: Some(label.to_string()),
+                    blueprint: None,
+                },
+                None,
+                None,
+            )
+            .await?;
+        session_ids.push(spawned.events[0].subject.id.clone());
+    }
+
+    let register = WorktreeRegisterPayload {
+        workspace_id: workspace_id.clone(),
+        project_id: None,
+        path: "/repo/.worktrees/feature".to_string(),
+        branch: Some("feature".to_string()),
+        repo: None,
+    };
+    let registered = client
+        .worktree_register(register.clone(), None, None)
+        .await?;
+    assert!(registered.accepted);
+    let worktree_id = registered.events[0].subject.id.clone();
+    let duplicate = client.worktree_register(register, None, None).await?;
+    assert_eq!(
+        duplicate.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    let payload = |session_id: &str| WorktreeSessionPayload {
+        workspace_id: workspace_id.clone(),
+        worktree_id: worktree_id.clone(),
+        session_id: session_id.to_string(),
+    };
+    let unattached = client
+        .worktree_command(
+            "worktree.lock.acquire",
+            payload(&session_ids[0]),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        unattached.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    for session_id in &session_ids {
+        let attached = client
+            .worktree_command("worktree.attach", payload(session_id), None, None)
+            .await?;
+        assert!(attached.accepted);
+    }
+
+    let locked = client
+        .worktree_command(
+            "worktree.lock.acquire",
+            payload(&session_ids[0]),
+            None,
+            None,
+        )
+        .await?;
+    assert!(locked.accepted);
+    let conflict = client
+        .worktree_command(
+            "worktree.lock.acquire",
+            payload(&session_ids[1]),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        conflict.rejection.expect("rejection").code,
+        ErrorCode::LockConflict
+    );
+    assert_eq!(
+        conflict.events[0].payload["details"]["holder_session_id"],
+        session_ids[0].as_str()
+    );
+    let foreign_release = client
+        .worktree_command(
+            "worktree.lock.release",
+            payload(&session_ids[1]),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        foreign_release.rejection.expect("rejection").code,
+        ErrorCode::LockConflict
+    );
+
+    let worktrees = client.worktree_list(&workspace_id).await?;
+    assert_eq!(worktrees[0].sessions, session_ids);
+    assert_eq!(worktrees[0].lock_holder(), Some(session_ids[0].as_str()));
+
+    let detached = client
+        .worktree_command("worktree.detach", payload(&session_ids[0]), None, None)
+        .await?;
+    let event_types: Vec<&str> = detached
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(
+        event_types,
+        vec!["worktree.lock.released", "worktree.detached"]
+    );
+    let handed_over = client
+        .worktree_command(
+            "worktree.lock.acquire",
+            payload(&session_ids[1]),
+            None,
+            None,
+        )
+        .await?;
+    assert!(handed_over.accepted);
+
+    let worktrees = client.worktree_list(&workspace_id).await?;
+    assert_eq!(worktrees[0].sessions, vec![session_ids[1].clone()]);
+    assert_eq!(worktrees[0].lock_holder(), Some(session_ids[1].as_str()));
+
+    // Racing acquires must leave exactly one holder, never two accepted lock events.
+    let reattached = client
+        .worktree_command("worktree.attach", payload(&session_ids[0]), None, None)
+        .await?;
+    assert!(reattached.accepted);
+    let released = client
+        .worktree_command(
+            "worktree.lock.release",
+            payload(&session_ids[1]),
+            None,
+            None,
+        )
+        .await?;
+    assert!(released.accepted);
+    for _ in 0..8 {
+        let (first, second) = tokio::join!(
+            client.worktree_command(
+                "worktree.lock.acquire",
+                payload(&session_ids[0]),
+                None,
+                None,
+            ),
+            client.worktree_command(
+                "worktree.lock.acquire",
+                payload(&session_ids[1]),
+                None,
+                None,
+            ),
+        );
+        let (first, second) = (first?, second?);
+        assert!(first.accepted != second.accepted);
+        let loser = if first.accepted { &second } else { &first };
+        assert_eq!(
+            loser.rejection.as_ref().expect("rejection").code,
+            ErrorCode::LockConflict
+        );
+        let winner = if first.accepted { 0 } else { 1 };
+        let released = client
+            .worktree_command(
+                "worktree.lock.release",
+                payload(&session_ids[winner]),
+                None,
+                None,
+            )
+            .await?;
+        assert!(released.accepted);
+    }
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn board_snapshot_covers_subjects_edges_and_layout() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let parent = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: Some("planner".to_string()),
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let parent_id = parent.events[0].subject.id.clone();
+    let child = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: Some(parent_id.clone()),
+                label: Some("builder".to_string()),
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let child_node = format!("session:{}", child.events[0].subject.id);
+
+    let group = client
+        .board_group_create(
+            BoardGroupCreatePayload {
+                workspace_id: workspace_id.clone(),
+                label: "Review".to_string(),
+                position: Some(BoardPosition { x: 0.0, y: 0.0 }),
+            },
+            None,
+            None,
+        )
+        .await?;
+    let group_node = format!("group:{}", group.events[0].subject.id);
+    let moved = client
+        .board_node_move(
+            BoardNodeMovePayload {
+                workspace_id: workspace_id.clone(),
+                node_id: child_node.clone(),
+                position: BoardPosition { x: 120.0, y: -40.0 },
+                group_node_id: Some(group_node.clone()),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(moved.accepted);
+    assert_eq!(moved.events[0].subject.kind, "session");
+    let into_session = client
+        .board_node_move(
+            BoardNodeMovePayload {
+                workspace_id: workspace_id.clone(),
+                node_id: group_node.clone(),
+                position: BoardPosition { x: 1.0, y: 1.0 },
+                group_node_id: Some(child_node.clone()),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        into_session.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let dangling = client
+        .board_comment_add(
+            BoardCommentAddPayload {
+                workspace_id: workspace_id.clone(),
+                body: "who owns this?".to_string(),
+                target_node_id: Some("task:missing".to_string()),
+                position: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        dangling.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+    let comment = client
+        .board_comment_add(
+            BoardCommentAddPayload {
+                workspace_id: workspace_id.clone(),
+                body: "ship after review".to_string(),
+                target_node_id: Some(child_node.clone()),
+                position: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(comment.accepted);
+
+    let board = client.board(&workspace_id).await?;
+    let kinds: Vec<&str> = board.nodes.iter().map(|node| node.kind.as_str()).collect();
+    assert_eq!(
+        kinds,
+        vec!["workspace", "session", "session", "group", "comment"]
+    );
+    let moved_node = board.node(&child_node).expect("session node");
+    assert_eq!(moved_node.label.as_deref(), Some("builder"));
+    assert_eq!(
+        moved_node.position,
+        Some(BoardPosition { x: 120.0, y: -40.0 })
+    );
+    assert_eq!(
+        moved_node.group_node_id.as_deref(),
+        Some(group_node.as_str())
+    );
+    let edges: Vec<(BoardEdgeKind, &str)> = board
+        .edges
+        .iter()
+        .map(|edge| (edge.kind, edge.to_node_id.as_str()))
+        .collect();
+    assert_eq!(
+        edges,
+        vec![
+            (BoardEdgeKind::Lineage, child_node.as_str()),
+            (BoardEdgeKind::Annotation, child_node.as_str()),
+        ]
+    );
+    assert!(board.nodes.iter().all(|node| node.kind != "command"));
+    assert_eq!(board.seq_global, comment.events[0].seq_global);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn messages_follow_lineage_scopes_and_deny_cousins() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let spawn = |parent: Option<String>| {
+        let client = &client;
+        let payload = SessionSpawnPayload {
+            workspace_id: workspace_id.clone(),
+            project_id: None,
+            parent_session_id: parent,
+            label: None,
+            blueprint: None,
+        };
+        async move {
+            let response = client.session_spawn(payload, None, None).await?;
+            anyhow::Ok(response.events[0].subject.id.clone())
+        }
+    };
+    let root = spawn(None).await?;
+    let a = spawn(Some(root.clone())).await?;
+    let b = spawn(Some(root.clone())).await?;
+    let a1 = spawn(Some(a.clone())).await?;
+    let b1 = spawn(Some(b.clone())).await?;
+    let other_root = spawn(None).await?;
+    let send = |from: &str, scope: MessageScope| MessageSendPayload {
+        workspace_id: workspace_id.clone(),
+        from_session_id: from.to_string(),
+        scope,
+        message_type: "status_capsule".to_string(),
+        body: serde_json::json!({"text": "tests green"}),
+    };
+
+    let to_parent = client
+        .message_send(send(&a1, MessageScope::Parent), None, None)
+        .await?;
+    assert!(to_parent.accepted);
+    let kinds: Vec<(&str, &str)> = to_parent
+        .events
+        .iter()
+        .map(|event| (event.event_type.as_str(), event.subject.id.as_str()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            ("message.sent", a1.as_str()),
+            ("message.delivered", a.as_str())
+        ]
+    );
+    assert_eq!(to_parent.events[1].payload["from_session_id"], a1.as_str());
+
+    let broadcast = client
+        .message_send(send(&a1, MessageScope::Workspace), None, None)
+        .await?;
+    assert_eq!(
+        broadcast.events[0].payload["recipient_session_ids"],
+        serde_json::json!([root, a])
+    );
+    assert_eq!(
+        broadcast.events[0].payload["denied_session_ids"],
+        serde_json::json!([b, b1, other_root])
+    );
+
+    let no_siblings = client
+        .message_send(send(&a1, MessageScope::Siblings), None, None)
+        .await?;
+    assert_eq!(
+        no_siblings.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let cross_tree = client
+        .message_send(send(&other_root, MessageScope::Workspace), None, None)
+        .await?;
+    assert_eq!(
+        cross_tree.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    assert_eq!(
+        cross_tree.events[0].payload["details"]["denied_session_ids"]
+            .as_array()
+            .map(Vec::len),
+        Some(5)
+    );
+
+    let filter = EventFilter {
+        subject_kind: Some("session".to_string()),
+        subject_id: Some(a.clone()),
+        event_type: Some("message.*".to_string()),
+    };
+    let response = client
+        .events_stream_filtered(&workspace_id, 0, &filter)
+        .await?;
+    let mut stream = response.bytes_stream();
+    let first = timeout(Duration::from_secs(2), next_sse_event(&mut stream)).await??;
+    assert_eq!(first.event_type, "message.delivered");
+    assert_eq!(first.seq_global, to_parent.events[1].seq_global);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn capsules_enforce_budgets_and_version_per_subject() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let child = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: Some("builder".to_string()),
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let session = Subject {
+        kind: "session".to_string(),
+        id: child.events[0].subject.id.clone(),
+    };
+    let write = |subject: &Subject, kind: CapsuleKind, content: String| CapsuleWritePayload {
+        workspace_id: workspace_id.clone(),
+        subject: subject.clone(),
+        kind,
+        content,
+    };
+
+    let over = client
+        .capsule_write(
+            write(&session, CapsuleKind::Status, "é".repeat(1_501)),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        over.rejection.expect("rejection").code,
+        ErrorCode::BudgetExceeded
+    );
+    assert_eq!(over.events[0].payload["details"]["budget_chars"], 1_500);
+    assert_eq!(over.events[0].payload["details"]["actual_chars"], 1_501);
+    let missing = client
+        .capsule_write(
+            write(
+                &Subject {
+                    kind: "task".to_string(),
+                    id: "missing".to_string(),
+                },
+                CapsuleKind::Plan,
+                "plan".to_string(),
+            ),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        missing.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    let first = client
+        .capsule_write(
+            write(&session, CapsuleKind::Status, "é".repeat(1_500)),
+            None,
+            None,
+        )
+        .await?;
+    assert!(first.accepted);
+    let second = client
+        .capsule_write(
+            write(&session, CapsuleKind::Status, "tests green".to_string()),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(second.events[0].subject.id, session.id);
+    assert_eq!(second.events[0].payload["version"], 2);
+    client
+        .capsule_write(
+            write(&session, CapsuleKind::Risk, "flaky network".to_string()),
+            None,
+            None,
+        )
+        .await?;
+
+    let latest = client
+        .capsule_list(&workspace_id, Some(("session", &session.id)), None, false)
+        .await?;
+    let summary: Vec<(CapsuleKind, u32, &str)> = latest
+        .iter()
+        .map(|capsule| (capsule.kind, capsule.version, capsule.content.as_str()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (CapsuleKind::Status, 2, "tests green"),
+            (CapsuleKind::Risk, 1, "flaky network"),
+        ]
+    );
+    let history = client
+        .capsule_list(&workspace_id, None, Some(CapsuleKind::Status), true)
+        .await?;
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].chars, 1_500);
+
+    let streamed = client.events_read_from(&workspace_id, 0).await?;
+    let followed: Vec<i64> = streamed
+        .iter()
+        .filter(|event| event.event_type == "capsule.written" && event.subject.id == session.id)
+        .map(|event| event.seq_global)
+        .collect();
+    assert_eq!(followed.len(), 3);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blueprint_sync_versions_changed_files_only() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let root = temp.path().join("demo");
+    let blueprint_dir = root.join("agents").join("blueprints");
+    std::fs::create_dir_all(&blueprint_dir)?;
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create(
+            "demo".to_string(),
+            Some(root.display().to_string()),
+            None,
+            None,
+        )
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let sync = || {
+        client.blueprint_sync(
+            BlueprintSyncPayload {
+                workspace_id: workspace_id.clone(),
+            },
+            None,
+            None,
+        )
+    };
+    let reviewer = |name: &str| {
+        format!(
+            "api_version: 1\nid: reviewer\nname: \"{name}\"\nruntime:\n  kind: external_cli\n  adapter: claude-code\nhooks:\n  chain:\n    - id: core.audit\n      version: \"1.0.0\"\n      trust: trusted\n"
+        )
+    };
+
+    std::fs::write(blueprint_dir.join("reviewer.yaml"), reviewer("Reviewer"))?;
+    std::fs::write(
+        blueprint_dir.join("planner.yml"),
+        "api_version: 1\nid: planner\nname: Planner\nruntime: {kind: internal, adapter: mp}\n",
+    )?;
+    let first = sync().await?;
+    assert!(first.accepted);
+    let registered: Vec<(&str, &str)> = first
+        .events
+        .iter()
+        .map(|event| (event.event_type.as_str(), event.subject.id.as_str()))
+        .collect();
+    assert_eq!(
+        registered,
+        vec![
+            ("blueprint.registered", "planner"),
+            ("blueprint.registered", "reviewer"),
+        ]
+    );
+
+    let unchanged = sync().await?;
+    assert!(unchanged.accepted);
+    assert!(unchanged.events.is_empty());
+
+    std::fs::write(
+        blueprint_dir.join("reviewer.yaml"),
+        reviewer("Strict reviewer"),
+    )?;
+    let changed = sync().await?;
+    assert_eq!(changed.events.len(), 1);
+    assert_eq!(changed.events[0].event_type, "blueprint.version_created");
+    assert_eq!(changed.events[0].payload["version"], 2);
+    assert_eq!(
+        changed.events[0].payload["path"],
+        "agents/blueprints/reviewer.yaml"
+    );
+
+    std::fs::write(
+        blueprint_dir.join("broken.yaml"),
+        "api_version: 1\nid: broken\nname: Broken\nruntime: {kind: quantum, adapter: x}\n",
+    )?;
+    std::fs::write(
+        blueprint_dir.join("misnamed.yaml"),
+        "api_version: 1\nid: other\nname: Other\nruntime: {kind: internal, adapter: mp}\n",
+    )?;
+    let invalid = sync().await?;
+    assert_eq!(
+        invalid.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let errors = invalid.events[0].payload["details"]["errors"]
+        .as_array()
+        .expect("errors")
+        .clone();
+    let paths: Vec<&str> = errors
+        .iter()
+        .map(|error| error["path"].as_str().expect("path"))
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            "agents/blueprints/broken.yaml",
+            "agents/blueprints/misnamed.yaml"
+        ]
+    );
+
+    let latest = client.blueprint_list(&workspace_id, false).await?;
+    let versions: Vec<(&str, u32)> = latest
+        .iter()
+        .map(|entry| (entry.blueprint_id.as_str(), entry.version))
+        .collect();
+    assert_eq!(versions, vec![("planner", 1), ("reviewer", 2)]);
+    assert_eq!(client.blueprint_list(&workspace_id, true).await?.len(), 3);
+
+    let pinned = client
+        .blueprint_get(&workspace_id, "reviewer", Some(1))
+        .await?;
+    assert_eq!(pinned.name, "Reviewer");
+    assert_eq!(
+        pinned.blueprint.hooks.expect("hooks").chain[0].id,
+        "core.audit"
+    );
+    let current = client
+        .blueprint_get(&workspace_id, "reviewer", None)
+        .await?;
+    assert_eq!(current.name, "Strict reviewer");
+    assert_ne!(current.content_hash, pinned.content_hash);
+    let missing = client
+        .blueprint_get(&workspace_id, "reviewer", Some(3))
+        .await
+        .expect_err("missing version");
+    let missing = missing
+        .downcast_ref::<mp_client::ClientError>()
+        .expect("client error");
+    assert_eq!(missing.error.code, ErrorCode::NotFound);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn skill_sync_classifies_trust_and_tracks_approvals() -> anyhow::Result<()> {
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let root = temp.path().join("demo");
+    let skill_dir = root.join("agents").join("skills");
+    let global_dir = temp.path().join("config").join("skills");
+    let write = |dir: &std::path::Path, file: &str, contents: &str| -> anyhow::Result<()> {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().expect("parent"))?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    };
+    let manifest = |name: &str, description: &str| {
+        format!("---\nname: {name}\ndescription: {description}\n---\n# {name}\n")
+    };
+    write(
+        &skill_dir.join("pdf-tools"),
+        "SKILL.md",
+        &manifest("pdf-tools", "Extracts text from PDFs."),
+    )?;
+    write(
+        &skill_dir.join("pdf-tools"),
+        "scripts/extract.sh",
+        "#!/bin/sh\n",
+    )?;
+    write(
+        &skill_dir.join("release-notes"),
+        "SKILL.md",
+        &manifest("release-notes", "Drafts release notes."),
+    )?;
+    write(
+        &skill_dir.join("release-notes"),
+        "wasm/notes.wat",
+        "(module)",
+    )?;
+    write(
+        &global_dir.join("house-style"),
+        "SKILL.md",
+        &manifest("house-style", "Applies the house style guide."),
+    )?;
+
+    let pair = Ed25519KeyPair::from_seed_unchecked(&[9; 32])
+        .map_err(|err| anyhow::anyhow!("key pair: {err}"))?;
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: Some(global_dir.clone()),
+        skill_trust_keys: vec![SkillTrustKey {
+            id: "platform".to_string(),
+            public_key: pair.public_key().as_ref().to_vec(),
+        }],
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create(
+            "demo".to_string(),
+            Some(root.display().to_string()),
+            None,
+            None,
+        )
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let sync = || {
+        client.skill_sync(
+            SkillSyncPayload {
+                workspace_id: workspace_id.clone(),
+            },
+            None,
+            None,
+        )
+    };
+    let approve = |content_hash: String| {
+        client.skill_approve(
+            SkillApprovePayload {
+                workspace_id: workspace_id.clone(),
+                skill_id: "pdf-tools".to_string(),
+                content_hash,
+            },
+            None,
+            None,
+        )
+    };
+
+    // Register unsigned first, then sign the hash the daemon reports.
+    let first = sync().await?;
+    assert!(first.accepted);
+    assert_eq!(first.events.len(), 3);
+    let release = client.skill_get(&workspace_id, "release-notes").await?;
+    assert_eq!(release.trust, SkillTrust::Proposed);
+    assert_eq!(release.components.wasm, vec!["wasm/notes.wat"]);
+    let signature = pair.sign(release.content_hash.as_bytes());
+    write(
+        &skill_dir.join("release-notes"),
+        "SKILL.sig",
+        &base64::engine::general_purpose::STANDARD.encode(signature.as_ref()),
+    )?;
+    let signed = sync().await?;
+    assert_eq!(signed.events.len(), 1);
+    assert_eq!(signed.events[0].event_type, "skill.updated");
+    assert_eq!(signed.events[0].payload["revision"], 2);
+
+    let tiers: Vec<(String, SkillTrust, SkillScope)> = client
+        .skill_list(&workspace_id, None)
+        .await?
+        .into_iter()
+        .map(|skill| (skill.skill_id, skill.trust, skill.scope))
+        .collect();
+    assert_eq!(
+        tiers,
+        vec![
+            (
+                "house-style".to_string(),
+                SkillTrust::Sandboxed,
+                SkillScope::Global
+            ),
+            (
+                "pdf-tools".to_string(),
+                SkillTrust::Proposed,
+                SkillScope::Workspace
+            ),
+            (
+                "release-notes".to_string(),
+                SkillTrust::Trusted,
+                SkillScope::Workspace
+            ),
+        ]
+    );
+    let release = client.skill_get(&workspace_id, "release-notes").await?;
+    assert_eq!(release.signature.key_id.as_deref(), Some("platform"));
+    assert!(sync().await?.events.is_empty());
+
+    // Approval is pinned to the reviewed hash.
+    let pdf = client.skill_get(&workspace_id, "pdf-tools").await?;
+    let stale = approve(format!("blake3:{}", "0".repeat(64))).await?;
+    let stale = stale.rejection.expect("rejection");
+    assert_eq!(stale.code, ErrorCode::ValidationFailed);
+    let approved = approve(pdf.content_hash.clone()).await?;
+    assert!(approved.accepted);
+    assert_eq!(approved.events[0].event_type, "skill.approved");
+    let sandboxed = client
+        .skill_list(&workspace_id, Some(SkillTrust::Sandboxed))
+        .await?;
+    assert_eq!(sandboxed.len(), 2);
+    let again = approve(pdf.content_hash.clone()).await?;
+    assert_eq!(
+        again.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    // Edits show up as drift before a sync and demote the skill after it.
+    write(
+        &skill_dir.join("pdf-tools"),
+        "scripts/extract.sh",
+        "#!/bin/sh\ncurl example.com\n",
+    )?;
+    write(
+        &skill_dir.join("release-notes"),
+        "wasm/notes.wat",
+        "(module )",
+    )?;
+    let drift = client.skill_verify(&workspace_id, "pdf-tools").await?;
+    assert!(drift.drift);
+    assert_eq!(drift.trust, SkillTrust::Proposed);
+    let tampered = client.skill_verify(&workspace_id, "release-notes").await?;
+    assert_eq!(tampered.signature.status, SkillSignatureStatus::Invalid);
+    assert_eq!(tampered.trust, SkillTrust::Proposed);
+    let untouched = client.skill_verify(&workspace_id, "house-style").await?;
+    assert!(!untouched.drift);
+
+    let resynced = sync().await?;
+    assert_eq!(resynced.events.len(), 2);
+    let pdf = client.skill_get(&workspace_id, "pdf-tools").await?;
+    assert_eq!(pdf.trust, SkillTrust::Proposed);
+    assert_eq!(pdf.revision, 2);
+    assert!(pdf.approved_hash.is_some());
+
+    // One bad skill rejects the sync; a removed directory records `skill.removed`.
+    write(
+        &skill_dir.join("misnamed"),
+        "SKILL.md",
+        &manifest("other", "Wrong name."),
+    )?;
+    write(
+        &global_dir.join("pdf-tools"),
+        "SKILL.md",
+        &manifest("pdf-tools", "Shadows the workspace skill."),
+    )?;
+    let invalid = sync().await?;
+    assert_eq!(
+        invalid.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    assert_eq!(
+        invalid.events[0].payload["details"]["errors"]
+            .as_array()
+            .expect("errors")
+            .len(),
+        2
+    );
+    std::fs::remove_dir_all(skill_dir.join("misnamed"))?;
+    std::fs::remove_dir_all(global_dir.join("pdf-tools"))?;
+    std::fs::remove_dir_all(global_dir.join("house-style"))?;
+    let removed = sync().await?;
+    assert_eq!(removed.events.len(), 1);
+    assert_eq!(removed.events[0].event_type, "skill.removed");
+    assert_eq!(removed.events[0].subject.id, "house-style");
+    let missing = client
+        .skill_get(&workspace_id, "house-style")
+        .await
+        .expect_err("removed");
+    let missing = missing
+        .downcast_ref::<mp_client::ClientError>()
+        .expect("client error");
+    assert_eq!(missing.error.code, ErrorCode::NotFound);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn policy_bundles_gate_commands_by_actor() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let policy_dir = temp.path().join("config").join("policy");
+    std::fs::create_dir_all(&policy_dir)?;
+    std::fs::write(
+        policy_dir.join("agents.yaml"),
+        "api_version: 1\nid: agents\ndefault: allow\nrules:\n  - id: no-agent-projects\n    effect: deny\n    actions: [\"project.*\"]\n    subject: {kind: session}\n    rationale: agents may not create projects\n  - id: no-agent-signoff\n    effect: deny\n    actions: [task.succeed]\n    subject: {kind: session}\n    rationale: agents may not sign off tasks\n",
+    )?;
+    std::fs::write(policy_dir.join("README.md"), "not a bundle")?;
+    let policy = load_policy_dir(&policy_dir)?;
+    let policy_hash = policy.hash().expect("hash").to_string();
+    assert!(load_policy_dir(&temp.path().join("missing"))?.is_empty());
+
+    let config = |pin_manifest: PinManifest| DaemonConfig {
+        db_path: db_path.clone(),
+        addr: "127.0.0.1:0".parse::<SocketAddr>().expect("addr"),
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest,
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: policy.clone(),
+        allow_without_policy: false,
+    };
+    let stale = run_daemon(config(PinManifest {
+        policy_bundle: Some("pol_0".to_string()),
+        ..PinManifest::default()
+    }))
+    .await
+    .expect_err("pinned policy mismatch");
+    assert!(stale.to_string().contains("policy bundle pol_0"));
+
+    let handle = tokio::spawn(run_daemon(config(PinManifest::default())));
+    let client = wait_for_client(&runtime_dir).await?;
+    let actor = |kind: &str, id: &str| Actor {
+        kind: kind.to_string(),
+        id: id.to_string(),
+        label: None,
+        claimed: false,
+    };
+    let create = client
+        .workspace_create("demo".to_string(), None, None, None)
+        .await?;
+    assert!(create.accepted);
+    let workspace_id = create.events[0].workspace_id.clone();
+    let evaluated = create.events.last().expect("event");
+    assert_eq!(evaluated.event_type, "policy.evaluated");
+    assert_eq!(evaluated.workspace_id, workspace_id);
+    assert_eq!(evaluated.payload["decision"]["effect"], "allow");
+    assert_eq!(evaluated.payload["decision"]["policy_hash"], policy_hash);
+    assert!(evaluated.payload["decision"].get("rule_id").is_none());
+
+    let agent = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_1"));
+    let denied = agent
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?;
+    let rejection = denied.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("agents may not create projects"));
+    let details = &denied.events[0].payload["details"]["policy"];
+    assert_eq!(details["effect"], "deny");
+    assert_eq!(details["bundle_id"], "agents");
+    assert_eq!(details["rule_id"], "no-agent-projects");
+    assert_eq!(details["policy_hash"], policy_hash);
+
+    let user = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("user", "alice"));
+    let allowed = user
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?;
+    assert!(allowed.accepted);
+    assert_eq!(allowed.events[0].actor.kind, "user");
+    assert_eq!(allowed.events[0].actor.id, "alice");
+    assert!(allowed.events[0].actor.claimed);
+
+    // A denied lifecycle command holds the running task it targets.
+    let task = user
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "release".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let transition = TaskTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task.events[0].subject.id.clone(),
+        reason: None,
+        failure_class: None,
+    };
+    user.task_transition("task.start", transition.clone(), None, None)
+        .await?;
+    let held = agent
+        .task_transition("task.succeed", transition, None, None)
+        .await?;
+    let kinds: Vec<&str> = held
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["task.blocked_by_policy", "command.rejected"]);
+    assert_eq!(held.events[0].payload["to"], "blocked_by_policy");
+
+    let impostor = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(Actor::system());
+    let refused = impostor
+        .project_create(workspace_id, "web".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        refused.rejection.expect("rejection").code,
+        ErrorCode::InvalidSchema
+    );
+    handle.abort();
+
+    let unguarded = |addr: &str, allow_without_policy: bool| DaemonConfig {
+        db_path: temp.path().join("unguarded.sqlite"),
+        addr: addr.parse::<SocketAddr>().expect("addr"),
+        runtime_dir: temp.path().join("unguarded"),
+        policy: PolicySet::default(),
+        allow_without_policy,
+        ..config(PinManifest::default())
+    };
+    let exposed = run_daemon(unguarded("0.0.0.0:0", true))
+        .await
+        .expect_err("opt-out off loopback");
+    assert!(exposed.to_string().contains("without policy"));
+    let handle = tokio::spawn(run_daemon(unguarded("127.0.0.1:0", false)));
+    let client = wait_for_client(&temp.path().join("unguarded")).await?;
+    let closed = client
+        .workspace_create("demo".to_string(), None, None, None)
+        .await?;
+    let rejection = closed.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("no policy is loaded"));
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn capability_leases_widen_default_deny_until_they_expire() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let runtime_dir = temp.path().join("run");
+    let policy_dir = temp.path().join("policy");
+    std::fs::create_dir_all(&policy_dir)?;
+    std::fs::write(
+        policy_dir.join("agents.yaml"),
+        "api_version: 1\nid: agents\ndefault: deny\nrules:\n  - id: token-holder\n    effect: allow\n    actions: [\"*\"]\n    subject: {kind: token}\n  - id: users-mint\n    effect: allow\n    actions: [\"lease.*\"]\n    subject: {kind: user}\n  - id: no-agent-workspaces\n    effect: deny\n    actions: [\"workspace.*\"]\n    subject: {kind: session}\n    rationale: agents may not manage workspaces\n",
+    )?;
+    let config = DaemonConfig {
+        db_path: temp.path().join("mpd.sqlite"),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: load_policy_dir(&policy_dir)?,
+        allow_without_policy: false,
+    };
+    let clock = Arc::new(ManualClock::new(OffsetDateTime::now_utc()));
+    let handle = tokio::spawn(run_daemon_with_clock(config, clock.clone()));
+    let client = wait_for_client(&runtime_dir).await?;
+    let actor = |kind: &str, id: &str| Actor {
+        kind: kind.to_string(),
+        id: id.to_string(),
+        label: None,
+        claimed: false,
+    };
+    let create = client
+        .workspace_create("demo".to_string(), None, None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let mint = |holder: &str, actions: &[&str], ttl_ms: u64| LeaseMintPayload {
+        workspace_id: workspace_id.clone(),
+        holder: LeaseHolder {
+            kind: "session".to_string(),
+            id: holder.to_string(),
+        },
+        scope: LeaseScope {
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            attributes: [("payload.name".to_string(), vec!["api".to_string()])].into(),
+        },
+        ttl_ms,
+        reason: "bootstrap the api project".to_string(),
+    };
+
+    let agent = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_1"));
+    let self_granted = agent
+        .lease_mint(mint("s_1", &["project.create"], 60_000), None)
+        .await?;
+    assert_eq!(
+        self_granted.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+
+    // Anyone can claim to be alice, so her claim is no grounds to hand out exceptions.
+    let user = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("user", "alice"));
+    let claimed = user
+        .lease_mint(mint("s_1", &["project.create"], 3_600_000), None)
+        .await?;
+    let rejection = claimed.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("only claimed"));
+
+    let too_broad = client.lease_mint(mint("s_1", &["*"], 60_000), None).await?;
+    assert_eq!(
+        too_broad.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let too_long = client
+        .lease_mint(mint("s_1", &["project.create"], 2 * 86_400_000), None)
+        .await?;
+    assert_eq!(
+        too_long.rejection.expect("rejection").code,
+        ErrorCode::InvalidSchema
+    );
+
+    let minted = client
+        .lease_mint(mint("s_1", &["project.create"], 3_600_000), None)
+        .await?;
+    assert!(minted.accepted);
+    assert_eq!(minted.events[0].event_type, "lease.minted");
+    assert_eq!(minted.events[0].actor.kind, "token");
+    let lease_id = minted.events[0].subject.id.clone();
+
+    let leased = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_1"))
+        .with_lease(lease_id.clone());
+    let uncovered = leased
+        .project_create(workspace_id.clone(), "web".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        uncovered.events[0].payload["details"]["lease_id"],
+        lease_id.as_str()
+    );
+    let rejection = uncovered.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("does not cover project.create"));
+
+    let covered = leased
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?;
+    assert!(covered.accepted);
+    let types: Vec<_> = covered
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(types, ["project.created", "lease.used"]);
+    let overrode = &covered.events[1].payload["overrode"];
+    assert_eq!(overrode["effect"], "deny");
+    assert_eq!(overrode["bundle_id"], "agents");
+    assert!(overrode.get("rule_id").is_none());
+
+    let borrowed = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_2"))
+        .with_lease(lease_id.clone());
+    let rejection = borrowed
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?
+        .rejection
+        .expect("rejection");
+    assert!(rejection.message.contains("held by session:s_1"));
+    let unknown = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_1"))
+        .with_lease("missing".to_string())
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        unknown.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    let leases = client.lease_list(&workspace_id, None).await?;
+    assert_eq!(leases.len(), 1);
+    assert_eq!(leases[0].use_count, 1);
+    assert_eq!(leases[0].minted_by, "token:daemon");
+
+    // The scheduler records expiry; no command is needed to clean the exception up.
+    clock.advance(time::Duration::hours(2));
+    let expired = timeout(Duration::from_secs(5), async {
+        loop {
+            let leases = client
+                .lease_list(&workspace_id, Some(LeaseStatus::Expired))
+                .await?;
+            if let Some(lease) = leases.into_iter().next() {
+                return Ok::<_, anyhow::Error>(lease);
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await??;
+    assert_eq!(expired.lease_id, lease_id);
+    let events = client.events_read_from(&workspace_id, 0).await?;
+    let expiry = events
+        .iter()
+        .find(|event| event.event_type == "lease.expired")
+        .expect("lease.expired");
+    assert_eq!(expiry.actor.kind, "system");
+    let rejection = leased
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?
+        .rejection
+        .expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("is expired"));
+
+    let second = client
+        .lease_mint(mint("s_1", &["project.*"], 3_600_000), None)
+        .await?;
+    let second_id = second.events[0].subject.id.clone();
+    let revoke = |lease_id: &str| LeaseRevokePayload {
+        workspace_id: workspace_id.clone(),
+        lease_id: lease_id.to_string(),
+        reason: Some("no longer needed".to_string()),
+    };
+    let refused = user.lease_revoke(revoke(&second_id), None).await?;
+    assert!(refused
+        .rejection
+        .expect("rejection")
+        .message
+        .contains("cannot revoke"));
+    assert!(
+        client
+            .lease_revoke(revoke(&second_id), None)
+            .await?
+            .accepted
+    );
+    let again = client.lease_revoke(revoke(&second_id), None).await?;
+    assert_eq!(
+        again.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let revoked = client
+        .lease_list(&workspace_id, Some(LeaseStatus::Revoked))
+        .await?;
+    assert_eq!(revoked.len(), 1);
+    assert_eq!(revoked[0].lease_id, second_id);
+
+    // A lease widens what policy leaves unsaid; a deny rule still wins over it.
+    let pinned = client
+        .lease_mint(mint("s_1", &["workspace.rename"], 3_600_000), None)
+        .await?;
+    let pinned_id = pinned.events[0].subject.id.clone();
+    let renamed = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("session", "s_1"))
+        .with_lease(pinned_id.clone())
+        .workspace_rename(
+            WorkspaceRenamePayload {
+                workspace_id: workspace_id.clone(),
+                name: "api".to_string(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        renamed.events[0].payload["details"]["lease_id"],
+        pinned_id.as_str()
+    );
+    let rejection = renamed.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection
+        .message
+        .contains("agents may not manage workspaces"));
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn role_bindings_compile_into_policy_and_explain_decisions() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let runtime_dir = temp.path().join("run");
+    let policy_dir = temp.path().join("policy");
+    std::fs::create_dir_all(&policy_dir)?;
+    std::fs::write(
+        policy_dir.join("team.roles.yaml"),
+        "api_version: 1\nid: team\nbindings:\n  - role: operator\n    subject: {kind: user, ids: [alice]}\n  - role: viewer\n    subject: {kind: user, ids: [victor]}\n  - role: admin\n    subject: {kind: token}\n",
+    )?;
+    let invalid = temp.path().join("invalid");
+    std::fs::create_dir_all(&invalid)?;
+    std::fs::write(
+        invalid.join("bad.roles.yaml"),
+        "api_version: 1\nid: bad\nbindings:\n  - role: owner\n    subject: {kind: user}\n",
+    )?;
+    let err = load_policy_dir(&invalid).expect_err("unknown role");
+    assert!(err.to_string().contains("invalid role_bindings"));
+
+    let config = DaemonConfig {
+        db_path: temp.path().join("mpd.sqlite"),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: load_policy_dir(&policy_dir)?,
+        allow_without_policy: false,
+    };
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let actor = |id: &str| Actor {
+        kind: "user".to_string(),
+        id: id.to_string(),
+        label: None,
+        claimed: false,
+    };
+    let create = client
+        .workspace_create("demo".to_string(), None, None, None)
+        .await?;
+    assert!(create.accepted);
+    assert_eq!(create.events[0].actor.kind, "token");
+    let workspace_id = create.events[0].workspace_id.clone();
+
+    let explain = |id: &str, command_type: &str| {
+        client.policy_explain(PolicyExplainRequest {
+            actor: actor(id),
+            command_type: command_type.to_string(),
+            payload: serde_json::json!({"workspace_id": workspace_id}),
+        })
+    };
+    let granted = explain("alice", "project.create").await?;
+    let decision = granted.decision.expect("decision");
+    assert_eq!(decision.effect, PolicyEffect::Allow);
+    assert_eq!(decision.bundle_id.as_deref(), Some("roles.team"));
+    assert_eq!(decision.rule_id.as_deref(), Some("operator-0"));
+    assert_eq!(granted.matched.len(), 1);
+    assert_eq!(granted.attributes["object.workspace_id"], workspace_id);
+    let admin_only = explain("alice", "workspace.rename").await?;
+    assert_eq!(
+        admin_only.decision.expect("decision").effect,
+        PolicyEffect::Deny
+    );
+    assert!(admin_only.matched.is_empty());
+    let viewer = explain("victor", "project.create").await?;
+    assert_eq!(
+        viewer.decision.expect("decision").effect,
+        PolicyEffect::Deny
+    );
+    let unknown = explain("alice", "project.list")
+        .await
+        .expect_err("read-only command");
+    let unknown = unknown
+        .downcast_ref::<mp_client::ClientError>()
+        .expect("client error");
+    assert_eq!(unknown.error.code, ErrorCode::UnknownCommand);
+
+    // A client naming itself alice only claims to be her, so it gets none of her roles.
+    let alice = wait_for_client(&runtime_dir)
+        .await?
+        .with_actor(actor("alice"));
+    let denied = alice
+        .project_create(workspace_id.clone(), "api".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        denied.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    assert_eq!(
+        denied.events[0].payload["details"]["policy"]["bundle_id"],
+        "roles.team"
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn session_spawn_verifies_blueprint_pins() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let root = temp.path().join("demo");
+    let blueprint_dir = root.join("agents").join("blueprints");
+    std::fs::create_dir_all(&blueprint_dir)?;
+    let manifest_path = temp.path().join("pins.yaml");
+    std::fs::write(
+        &manifest_path,
+        "policy_bundle: pol_1\ntool_registry: 1\nhooks:\n  - {id: core.audit, version: \"sha256:aa\"}\n",
+    )?;
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: load_pin_manifest(&manifest_path)?,
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create(
+            "demo".to_string(),
+            Some(root.display().to_string()),
+            None,
+            None,
+        )
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let blueprint = |id: &str, pins: &str| {
+        format!(
+            "api_version: 1\nid: {id}\nname: {id}\n{pins}runtime: {{kind: internal, adapter: mp}}\nhooks:\n  chain:\n    - {{id: core.audit, version: \"1.0.0\", trust: trusted}}\n"
+        )
+    };
+    std::fs::write(
+        blueprint_dir.join("pinned.yaml"),
+        blueprint(
+            "pinned",
+            "pins:\n  policy_bundle: pol_1\n  tool_registry: 1\n  hooks: [{id: core.audit, version: \"sha256:aa\"}]\n",
+        ),
+    )?;
+    std::fs::write(
+        blueprint_dir.join("stale.yaml"),
+        blueprint(
+            "stale",
+            "pins:\n  policy_bundle: pol_0\n  tool_registry: 1\n  hooks: [{id: core.audit, version: \"sha256:00\"}]\n",
+        ),
+    )?;
+    std::fs::write(
+        blueprint_dir.join("floating.yaml"),
+        blueprint("floating", ""),
+    )?;
+    let sync = client
+        .blueprint_sync(
+            BlueprintSyncPayload {
+                workspace_id: workspace_id.clone(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(sync.events.len(), 3);
+
+    let spawn = |id: &str| SessionSpawnPayload {
+        workspace_id: workspace_id.clone(),
+        project_id: None,
+        parent_session_id: None,
+        label: None,
+        blueprint: Some(BlueprintRef {
+            id: id.to_string(),
+            version: None,
+        }),
+    };
+
+    let started = client.session_spawn(spawn("pinned"), None, None).await?;
+    assert!(started.accepted);
+    let recorded = &started.events[0].payload["blueprint"];
+    assert_eq!(recorded["blueprint_id"], "pinned");
+    assert_eq!(recorded["version"], 1);
+    assert_eq!(recorded["pins"]["policy_bundle"], "pol_1");
+    assert_eq!(recorded["pins"]["hooks"][0]["version"], "sha256:aa");
+
+    let stale = client.session_spawn(spawn("stale"), None, None).await?;
+    assert_eq!(
+        stale.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    let details = &stale.events[0].payload["details"];
+    assert_eq!(details["blueprint_id"], "stale");
+    let mismatches = details["mismatches"].as_array().expect("mismatches");
+    assert_eq!(mismatches.len(), 2);
+    assert_eq!(mismatches[0]["component"], "policy_bundle");
+    assert_eq!(mismatches[0]["declared"], "pol_0");
+    assert_eq!(mismatches[0]["loaded"], "pol_1");
+    assert_eq!(mismatches[1]["component"], "hook");
+    assert_eq!(mismatches[1]["id"], "core.audit");
+    assert_eq!(mismatches[1]["reason"], "mismatch");
+
+    let floating = client.session_spawn(spawn("floating"), None, None).await?;
+    assert!(!floating.accepted);
+    let reasons: Vec<&str> = floating.events[0].payload["details"]["mismatches"]
+        .as_array()
+        .expect("mismatches")
+        .iter()
+        .map(|mismatch| mismatch["reason"].as_str().expect("reason"))
+        .collect();
+    assert_eq!(reasons, vec!["floating"; 3]);
+
+    let missing = client
+        .session_spawn(
+            SessionSpawnPayload {
+                blueprint: Some(BlueprintRef {
+                    id: "pinned".to_string(),
+                    version: Some(2),
+                }),
+                ..spawn("pinned")
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        missing.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+    let sessions = client.session_list(&workspace_id, None).await?;
+    assert_eq!(sessions.len(), 1);
+
+    handle.abort();
+    Ok(())
+}
+
+const HOOK_CHAIN: &str = r#"
+api_version: 1
+hooks:
+  - id: org.redact
+    version: "1.0.0"
+    phases: [pre_send]
+    command_types: [message.send]
+    action: {kind: redact, pointer: /body/text, needles: [hunter2]}
+  - id: org.timeouts
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [task.create]
+    action: {kind: rewrite, pointer: /timeout_ms, value: 60000}
+  - id: org.freeze
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [project.create]
+    action: {kind: block, reason: projects are frozen}
+  - id: org.release
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [task.start]
+    action: {kind: require_approval, reason: release sign-off}
+  - id: org.broken
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [session.fork]
+    action: {kind: rewrite, pointer: /missing/label, value: x}
+"#;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hook_chain_rewrites_blocks_and_fails_closed() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(&chain_path, HOOK_CHAIN)?;
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: load_hook_chain(&chain_path)?,
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+
+    let frozen = client
+        .project_create(workspace_id.clone(), "core".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        frozen.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    let details = &frozen.events[0].payload["details"];
+    assert_eq!(details["hook_id"], "org.freeze");
+    assert_eq!(details["decision"], "block");
+    assert_eq!(details["reason"], "projects are frozen");
+
+    let task = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "release".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: Some(5_000_000),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(task.events[0].payload["timeout_ms"], 60000);
+    let task_id = task.events[0].subject.id.clone();
+
+    let start = TaskTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task_id.clone(),
+        reason: None,
+        failure_class: None,
+    };
+    let held = client
+        .task_transition("task.start", start.clone(), None, None)
+        .await?;
+    assert_eq!(
+        held.events[0].payload["details"]["decision"],
+        "require_approval"
+    );
+    let gate = client
+        .gate_define(
+            GateDefinePayload {
+                workspace_id: workspace_id.clone(),
+                scope: GateScope {
+                    command_type: "task.start".to_string(),
+                    subject: Subject {
+                        kind: "task".to_string(),
+                        id: task_id.clone(),
+                    },
+                },
+                kind: GateKind::ManualApproval,
+                required_approvals: None,
+                delay_ms: None,
+                description: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let approval = client
+        .gate_decide(
+            "gate.approve",
+            GateDecisionPayload {
+                workspace_id: workspace_id.clone(),
+                gate_id: gate.events[0].subject.id.clone(),
+                approver: Approver {
+                    kind: ApproverKind::Human,
+                    id: "alice".to_string(),
+                    label: None,
+                },
+                reason: None,
+                evidence: Vec::new(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(approval.accepted);
+    let started = client
+        .task_transition("task.start", start, None, None)
+        .await?;
+    assert!(started.accepted);
+
+    let spawn = |parent: Option<String>| SessionSpawnPayload {
+        workspace_id: workspace_id.clone(),
+        project_id: None,
+        parent_session_id: parent,
+        label: None,
+        blueprint: None,
+    };
+    let root = client.session_spawn(spawn(None), None, None).await?.events[0]
+        .subject
+        .id
+        .clone();
+    let child = client
+        .session_spawn(spawn(Some(root.clone())), None, None)
+        .await?
+        .events[0]
+        .subject
+        .id
+        .clone();
+    let sent = client
+        .message_send(
+            MessageSendPayload {
+                workspace_id: workspace_id.clone(),
+                from_session_id: child,
+                scope: MessageScope::Parent,
+                message_type: "status_capsule".to_string(),
+                body: serde_json::json!({"text": "password is hunter2"}),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        sent.events[1].payload["body"]["text"],
+        "password is [REDACTED]"
+    );
+
+    let fork = client
+        .session_fork(
+            SessionForkPayload {
+                workspace_id: workspace_id.clone(),
+                session_id: root,
+                mode: None,
+                label: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        fork.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    let kinds: Vec<&str> = fork
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["hook.failed", "command.rejected"]);
+    assert_eq!(fork.events[0].subject.id, "org.broken");
+    assert_eq!(fork.events[0].payload["phase"], "pre_command");
+    assert_eq!(fork.events[0].payload["fail_open"], false);
+    assert_eq!(fork.events[1].payload["details"]["decision"], "failed");
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hook_failures_fail_open_only_on_loopback() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(&chain_path, HOOK_CHAIN)?;
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: load_hook_chain(&chain_path)?,
+        hooks_fail_open: true,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let exposed = DaemonConfig {
+        addr: "0.0.0.0:0".parse::<SocketAddr>()?,
+        ..config.clone()
+    };
+    assert!(run_daemon(exposed).await.is_err());
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let root = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: None,
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let fork = client
+        .session_fork(
+            SessionForkPayload {
+                workspace_id: workspace_id.clone(),
+                session_id: root.events[0].subject.id.clone(),
+                mode: None,
+                label: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(fork.accepted);
+    let kinds: Vec<&str> = fork
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["hook.failed", "session.forked"]);
+    assert_eq!(fork.events[0].payload["fail_open"], true);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn hook_events_land_in_the_workspace_a_create_makes() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let runtime_dir = temp.path().join("run");
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(
+        &chain_path,
+        r#"
+api_version: 1
+hooks:
+  - id: org.broken
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [workspace.create]
+    action: {kind: rewrite, pointer: /missing/label, value: x}
+"#,
+    )?;
+    let config = DaemonConfig {
+        db_path: temp.path().join("mpd.sqlite"),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: load_hook_chain(&chain_path)?,
+        hooks_fail_open: true,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let key = Some("ik_create_hooked".to_string());
+    let create = client
+        .workspace_create("demo".to_string(), None, key.clone(), None)
+        .await?;
+    assert!(create.accepted, "{:?}", create.rejection);
+    let kinds: Vec<&str> = create
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["hook.failed", "workspace.created"]);
+    let workspace_id = create.events[1].workspace_id.clone();
+    assert_ne!(workspace_id, "global");
+    assert_eq!(create.events[0].workspace_id, workspace_id);
+    assert_eq!(create.events[0].seq_global, 1);
+    assert_eq!(create.events[1].seq_global, 2);
+
+    let retried = client
+        .workspace_create("demo".to_string(), None, key, None)
+        .await?;
+    assert!(retried.accepted, "{:?}", retried.rejection);
+    let ids = |response: &SubmitCommandResponse| -> Vec<String> {
+        response
+            .events
+            .iter()
+            .map(|event| event.event_id.clone())
+            .collect()
+    };
+    assert_eq!(ids(&retried), ids(&create));
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recorded_hook_inputs_for_a_create_replay_with_it() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let runtime_dir = temp.path().join("run");
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(
+        &chain_path,
+        r#"
+api_version: 1
+hooks:
+  - id: acme.name
+    version: "1"
+    phases: [pre_command]
+    command_types: [workspace.create]
+    action: {kind: rewrite, pointer: /name, value: reviewed}
+"#,
+    )?;
+    let handle = tokio::spawn(run_daemon(DaemonConfig {
+        db_path: temp.path().join("mpd.sqlite"),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: load_hook_chain(&chain_path)?,
+        hooks_fail_open: false,
+        record_hook_inputs: true,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    }));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let key = Some("ik_create_recorded".to_string());
+    let create = client
+        .workspace_create("demo".to_string(), None, key.clone(), None)
+        .await?;
+    assert!(create.accepted, "{:?}", create.rejection);
+    let kinds: Vec<&str> = create
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["hook.input_recorded", "workspace.created"]);
+    let workspace_id = create.events[1].workspace_id.clone();
+    assert_eq!(create.events[0].workspace_id, workspace_id);
+    assert_eq!(create.events[0].payload["input"]["name"], "demo");
+    assert_eq!(create.events[1].payload["name"], "reviewed");
+
+    let retried = client
+        .workspace_create("demo".to_string(), None, key, None)
+        .await?;
+    assert!(retried.accepted, "{:?}", retried.rejection);
+    let ids = |response: &SubmitCommandResponse| -> Vec<String> {
+        response
+            .events
+            .iter()
+            .map(|event| event.event_id.clone())
+            .collect()
+    };
+    assert_eq!(ids(&retried), ids(&create));
+
+    let events = client.events_read_from(&workspace_id, 0).await?;
+    assert_eq!(cases_from_events(&events)?.len(), 1);
+    assert!(client.events_read_from("global", 0).await?.is_empty());
+
+    handle.abort();
+    Ok(())
+}
+
+/// Builds a WAT hook module that makes `calls` (hostcall name and JSON request) in order and
+/// then returns `output`; `body` replaces the calls when given.
+fn wasm_hook(calls: &[(&str, &str)], output: &str, body: Option<&str>) -> String {
+    let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+    let (mut imports, mut data, mut code) = (String::new(), String::new(), String::new());
+    let mut offset = 0;
+    for (index, (name, request)) in calls.iter().enumerate() {
+        match *name {
+            "clock_now" => {
+                imports +=
+                    &format!("(import \"mp\" \"clock_now\" (func $h{index} (result i64)))\n");
+                code += &format!("call $h{index} drop\n");
+            }
+            "crypto_random" => {
+                imports += &format!(
+                    "(import \"mp\" \"crypto_random\" (func $h{index} (param i32) (result i64)))\n"
+                );
+                code += &format!("(call $h{index} (i32.const 16)) drop\n");
+            }
+            _ => {
+                imports += &format!(
+                    "(import \"mp\" \"{name}\" (func $h{index} (param i32 i32) (result i64)))\n"
+                );
+                data += &format!("(data (i32.const {offset}) \"{}\")\n", quote(request));
+                code += &format!(
+                    "(call $h{index} (i32.const {offset}) (i32.const {})) drop\n",
+                    request.len()
+                );
+                offset += request.len();
+            }
+        }
+    }
+    data += &format!("(data (i32.const {offset}) \"{}\")\n", quote(output));
+    code += &format!(
+        "(i64.or (i64.shl (i64.const {offset}) (i64.const 32)) (i64.const {}))",
+        output.len()
+    );
+    format!(
+        r#"(module
+{imports}(memory (export "memory") 1)
+(global $heap (mut i32) (i32.const 8192))
+(func (export "alloc") (param $len i32) (result i32)
+  (local $ptr i32)
+  (local.set $ptr (global.get $heap))
+  (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+  (local.get $ptr))
+{data}(func (export "run") (param i32 i32) (result i64)
+{}))"#,
+        body.unwrap_or(&code)
+    )
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wasm_hooks_run_sandboxed_with_logged_hostcalls() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    // The module embeds a session id, so create the session before the hook is loaded.
+    let handle = tokio::spawn(run_daemon(config.clone()));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let session = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: None,
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let session_id = session.events[0].subject.id.clone();
+    handle.abort();
+    sleep(Duration::from_millis(200)).await;
+
+    let capsule = format!(
+        r#"{{"subject":{{"kind":"session","id":"{session_id}"}},"kind":"status","content":"checked by policy"}}"#
+    );
+    let scratch = temp.path().join("scratch");
+    std::fs::create_dir(&scratch)?;
+    let marker = scratch.join("ran");
+    let write = format!(
+        r#"{{"op":"write","path":"{}","content":"ran"}}"#,
+        marker.display()
+    );
+    let policy = wasm_hook(
+        &[
+            ("clock_now", ""),
+            ("write_capsule", &capsule),
+            ("filesystem", &write),
+            (
+                "emit_event",
+                r#"{"type":"x.acme.audit","payload":{"ok":true}}"#,
+            ),
+        ],
+        r#"{"decision":"continue"}"#,
+        None,
+    );
+    let blocker = wasm_hook(&[], r#"{"decision":"block","reason":"wasm says no"}"#, None);
+    std::fs::write(temp.path().join("policy.wat"), &policy)?;
+    std::fs::write(temp.path().join("blocker.wat"), &blocker)?;
+    let policy_hash = format!("blake3:{}", blake3::hash(policy.as_bytes()).to_hex());
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(
+        &chain_path,
+        format!(
+            r#"api_version: 1
+hooks:
+  - id: acme.policy
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [task.create]
+    action:
+      kind: wasm
+      module: {{path: policy.wat, hash: "{policy_hash}"}}
+      capabilities:
+        clock: true
+        write_capsules: true
+        emit_events: [x.acme.audit]
+        fs_write: ["{}"]
+  - id: acme.blocker
+    version: "1.0.0"
+    phases: [pre_command]
+    command_types: [project.create]
+    action: {{kind: wasm, module: {{path: blocker.wat}}}}
+"#,
+            scratch.display()
+        ),
+    )?;
+
+    let handle = tokio::spawn(run_daemon(DaemonConfig {
+        hook_chain: load_hook_chain(&chain_path)?,
+        ..config
+    }));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let task_payload = TaskCreatePayload {
+        workspace_id: workspace_id.clone(),
+        title: "release".to_string(),
+        project_id: None,
+        session_id: None,
+        deadline_at: None,
+        retry: None,
+        timeout_ms: None,
+    };
+    let key = Some("ik_task_hooked".to_string());
+    let task = client
+        .task_create(task_payload.clone(), key.clone(), None)
+        .await?;
+    assert!(task.accepted);
+    let hostcalls: Vec<&str> = task
+        .events
+        .iter()
+        .filter(|event| event.event_type == "sandbox.hostcall")
+        .map(|event| event.payload["hostcall"].as_str().expect("hostcall"))
+        .collect();
+    assert_eq!(
+        hostcalls,
+        vec!["clock_now", "write_capsule", "filesystem", "emit_event"]
+    );
+    for event in task
+        .events
+        .iter()
+        .filter(|event| event.event_type.starts_with("sandbox."))
+    {
+        assert_eq!(event.payload["module_id"], "acme.policy");
+        assert_eq!(event.payload["module_hash"], policy_hash.as_str());
+    }
+    let emitted = task
+        .events
+        .iter()
+        .find(|event| event.event_type == "sandbox.event_emitted")
+        .expect("emitted event");
+    assert_eq!(emitted.payload["type"], "x.acme.audit");
+    assert_eq!(
+        task.events.last().expect("task event").event_type,
+        "task.created"
+    );
+    let capsules = client
+        .capsule_list(&workspace_id, Some(("session", &session_id)), None, false)
+        .await?;
+    assert_eq!(capsules[0].content, "checked by policy");
+    std::fs::remove_file(&marker)?;
+
+    // A retry is answered from the log without running the module again.
+    let retried = client.task_create(task_payload, key, None).await?;
+    assert!(retried.accepted);
+    let ids = |response: &SubmitCommandResponse| -> Vec<String> {
+        response
+            .events
+            .iter()
+            .map(|event| event.event_id.clone())
+            .collect()
+    };
+    assert_eq!(ids(&retried), ids(&task));
+    let capsules = client
+        .capsule_list(&workspace_id, Some(("session", &session_id)), None, false)
+        .await?;
+    assert_eq!(capsules.len(), 1);
+    assert!(!marker.exists());
+
+    let blocked = client
+        .project_create(workspace_id.clone(), "core".to_string(), None, None)
+        .await?;
+    assert_eq!(
+        blocked.rejection.expect("rejection").code,
+        ErrorCode::PolicyDenied
+    );
+    let details = &blocked.events[0].payload["details"];
+    assert_eq!(details["hook_id"], "acme.blocker");
+    assert_eq!(details["reason"], "wasm says no");
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wasm_hooks_fail_closed_on_limits_and_missing_capabilities() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let modules = [
+        (
+            "spin",
+            wasm_hook(&[], "", Some("(loop $spin (br $spin)) (i64.const 0)")),
+        ),
+        (
+            "hog",
+            wasm_hook(
+                &[],
+                "",
+                Some("(drop (memory.grow (i32.const 64))) (i64.const 0)"),
+            ),
+        ),
+        (
+            "random",
+            wasm_hook(&[("crypto_random", "")], r#"{"decision":"continue"}"#, None),
+        ),
+        (
+            "caller",
+            wasm_hook(
+                &[("request_tool_call", r#"{"tool_id":"acme.add","args":{}}"#)],
+                r#"{"decision":"continue"}"#,
+                None,
+            ),
+        ),
+    ];
+    for (name, module) in &modules {
+        std::fs::write(temp.path().join(format!("{name}.wat")), module)?;
+    }
+    let chain_path = temp.path().join("hooks.yaml");
+    std::fs::write(
+        &chain_path,
+        r#"api_version: 1
+hooks:
+  - id: acme.fuel
+    version: "1"
+    phases: [pre_command]
+    command_types: [project.create]
+    action: {kind: wasm, module: {path: spin.wat}, limits: {fuel: 100000}}
+  - id: acme.clock
+    version: "1"
+    phases: [pre_command]
+    command_types: [task.create]
+    action: {kind: wasm, module: {path: spin.wat}, limits: {fuel: 1000000000000, timeout_ms: 50}}
+  - id: acme.memory
+    version: "1"
+    phases: [pre_command]
+    command_types: [session.spawn]
+    action: {kind: wasm, module: {path: hog.wat}, limits: {memory_bytes: 1048576}}
+  - id: acme.random
+    version: "1"
+    phases: [pre_command]
+    command_types: [workspace.rename]
+    action: {kind: wasm, module: {path: random.wat}}
+  - id: acme.caller
+    version: "1"
+    phases: [pre_command]
+    command_types: [workspace.archive]
+    action: {kind: wasm, module: {path: caller.wat}, capabilities: {tools: [acme.add]}}
+"#,
+    )?;
+    let hook_chain = load_hook_chain(&chain_path)?;
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain,
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let mut tampered = config.hook_chain.clone();
+    if let HookAction::Wasm { module, .. } = &mut tampered.hooks[0].action {
+        module.hash = Some(format!("blake3:{}", "0".repeat(64)));
+    }
+    let refused = run_daemon(DaemonConfig {
+        hook_chain: tampered,
+        ..config.clone()
+    })
+    .await;
+    assert!(refused.is_err());
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+
+    let failure = |response: &SubmitCommandResponse| {
+        assert!(!response.accepted);
+        let failed = response
+            .events
+            .iter()
+            .find(|event| event.event_type == "hook.failed")
+            .expect("hook.failed");
+        failed.payload["error"].as_str().expect("error").to_string()
+    };
+
+    let fuel = client
+        .project_create(workspace_id.clone(), "core".to_string(), None, None)
+        .await?;
+    assert_eq!(failure(&fuel), "fuel limit exhausted");
+
+    let clock = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "release".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(failure(&clock), "wall-clock limit exceeded");
+
+    let memory = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: None,
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(failure(&memory), "memory limit exceeded");
+
+    let random = client
+        .workspace_rename(
+            WorkspaceRenamePayload {
+                workspace_id: workspace_id.clone(),
+                name: "renamed".to_string(),
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(failure(&random).contains("crypto_random capability not granted"));
+    let logged = random
+        .events
+        .iter()
+        .find(|event| event.event_type == "sandbox.hostcall")
+        .expect("hostcall log");
+    assert_eq!(logged.payload["hostcall"], "crypto_random");
+    assert_eq!(
+        logged.payload["error"],
+        "crypto_random capability not granted"
+    );
+    let list = client.workspace_list(false).await?;
+    assert_eq!(list[0].name, "demo");
+
+    // Nothing is queued behind the module's back: the hostcall fails and the hook with it.
+    let caller = client
+        .workspace_lifecycle(
+            "workspace.archive",
+            WorkspaceLifecyclePayload {
+                workspace_id: workspace_id.clone(),
+                reason: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(failure(&caller).contains("request_tool_call is unsupported"));
+    assert!(client
+        .tool_call_list(&workspace_id, None, None)
+        .await?
+        .is_empty());
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recorded_hook_inputs_replay_against_fixtures_and_fuzz() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+    std::fs::write(
+        temp.path().join("allow.wat"),
+        wasm_hook(&[], r#"{"decision":"continue"}"#, None),
+    )?;
+    std::fs::write(
+        temp.path().join("spin.wat"),
+        wasm_hook(&[], "", Some("(loop $spin (br $spin)) (i64.const 0)")),
+    )?;
+    let chain = |title: &str, module: &str| -> anyhow::Result<HookChain> {
+        let path = temp.path().join("hooks.yaml");
+        std::fs::write(
+            &path,
+            format!(
+                r#"api_version: 1
+hooks:
+  - id: acme.title
+    version: "1"
+    phases: [pre_command]
+    command_types: [task.create]
+    action: {{kind: rewrite, pointer: /title, value: "{title}"}}
+  - id: acme.module
+    version: "1"
+    phases: [pre_command]
+    command_types: [task.create]
+    action: {{kind: wasm, module: {{path: {module}}}, limits: {{fuel: 100000}}}}
+"#
+            ),
+        )?;
+        load_hook_chain(&path)
+    };
+
+    let handle = tokio::spawn(run_daemon(DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: chain("reviewed", "allow.wat")?,
+        hooks_fail_open: false,
+        record_hook_inputs: true,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    }));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    // Phases without hooks record nothing.
+    assert!(create
+        .events
+        .iter()
+        .all(|event| event.event_type != "hook.input_recorded"));
+
+    let payload = TaskCreatePayload {
+        workspace_id: workspace_id.clone(),
+        title: "release".to_string(),
+        project_id: None,
+        session_id: None,
+        deadline_at: None,
+        retry: None,
+        timeout_ms: None,
+    };
+    let task = client.task_create(payload.clone(), None, None).await?;
+    assert!(task.accepted);
+    assert_eq!(task.events[0].event_type, "hook.input_recorded");
+    assert_eq!(task.events[0].payload["input"]["title"], "release");
+    let events = client.events_read_from(&workspace_id, 0).await?;
+    handle.abort();
+
+    // Replays run offline, without the daemon.
+    let cases = cases_from_events(&events)?;
+    assert_eq!(cases.len(), 1);
+    let from_command = cases_from_commands(&[CommandEnvelope {
+        command_type: "task.create".to_string(),
+        schema_version: 1,
+        payload: serde_json::to_value(&payload)?,
+        idempotency_key: None,
+        expected_version: None,
+        trace_id: task.trace_id.clone(),
+        actor: None,
+        lease_id: None,
+    }]);
+    assert_eq!(from_command, cases);
+
+    let fixtures = temp.path().join("fixtures");
+    let mut options = HookTestOptions {
+        fixtures: Some(fixtures.clone()),
+        update_fixtures: true,
+        ..HookTestOptions::default()
+    };
+    let written = run_hook_tests(&chain("reviewed", "allow.wat")?, &cases, &options).await?;
+    assert_eq!(written.cases[0].status, FixtureStatus::Updated);
+    assert!(fixtures.join(format!("{}.json", cases[0].name)).exists());
+
+    options.update_fixtures = false;
+    let passed = run_hook_tests(&chain("reviewed", "allow.wat")?, &cases, &options).await?;
+    assert!(passed.passed());
+    assert_eq!(passed.cases[0].status, FixtureStatus::Pass);
+    assert_eq!(passed.cases[0].outcome.output["title"], "reviewed");
+    assert!(passed.cases[0].steps[1].fuel_used > 0);
+
+    let changed = run_hook_tests(&chain("approved", "allow.wat")?, &cases, &options).await?;
+    assert!(!changed.passed());
+    assert_eq!(changed.cases[0].status, FixtureStatus::Fail);
+    assert_eq!(changed.cases[0].diff[0].pointer, "/output/title");
+    assert_eq!(
+        changed.cases[0].diff[0].expected,
+        Some(serde_json::json!("reviewed"))
+    );
+
+    options.fuzz_budget = 6;
+    options.seed = 42;
+    let first = run_hook_tests(&chain("reviewed", "allow.wat")?, &cases, &options).await?;
+    let second = run_hook_tests(&chain("reviewed", "allow.wat")?, &cases, &options).await?;
+    assert_eq!(first.to_json()?, second.to_json()?);
+    assert_eq!(first.summary.fuzz_runs, 12);
+    let module = &first.fuzz[1];
+    assert_eq!(module.hook_id, "acme.module");
+    // Inputs too big for the module's memory fail; none may exhaust its budget.
+    assert_eq!(module.runs, 6);
+    assert_eq!(module.continued + module.failed, 6);
+    assert_eq!(module.limits_hit, 0);
+    assert_eq!(module.fuel_limit, Some(100_000));
+
+    let spinning = run_hook_tests(&chain("reviewed", "spin.wat")?, &cases, &options).await?;
+    assert!(!spinning.passed());
+    let spun = &spinning.fuzz[1];
+    assert_eq!(spun.failed, 6);
+    for finding in &spun.findings {
+        if finding.mutation.starts_with("oversize") {
+            assert!(finding.error.starts_with("input out of bounds"));
+        } else {
+            assert_eq!(finding.error, "fuel limit exhausted");
+        }
+    }
+    let oversized = spun
+        .findings
+        .iter()
+        .filter(|finding| finding.mutation.starts_with("oversize"))
+        .count() as u64;
+    assert_eq!(spun.limits_hit, 6 - oversized);
+    assert_eq!(spinning.cases[0].outcome.decision, ReplayDecision::Failed);
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tool_requests_validate_inputs_and_outputs_fail_closed() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    std::fs::write(
+        temp.path().join("fixed.wat"),
+        wasm_hook(&[], r#"{"sum":3}"#, None),
+    )?;
+    std::fs::write(
+        temp.path().join("loose.wat"),
+        wasm_hook(&[], r#"{"total":3}"#, None),
+    )?;
+    let registry_path = temp.path().join("tools.yaml");
+    let schemas = r#"
+    input_schema:
+      type: object
+      additionalProperties: false
+      required: [a, b]
+      properties: {a: {type: integer}, b: {type: integer}}
+    output_schema:
+      type: object
+      required: [sum]
+      properties: {sum: {type: integer}}"#;
+    std::fs::write(
+        &registry_path,
+        format!(
+            r#"api_version: 1
+version: 4
+tools:
+  - id: acme.add
+    version: "1.0.0"
+    title: Add
+    description: Adds two integers in an external runner.
+    side_effect: pure{schemas}
+    examples: [{{description: small, input: {{a: 1, b: 2}}, output: {{sum: 3}}}}]
+  - id: acme.fixed
+    version: "1.0.0"
+    title: Fixed
+    description: Always answers three.
+    side_effect: pure{schemas}
+    examples: []
+    runtime: {{kind: wasm, module: {{path: fixed.wat}}}}
+  - id: acme.loose
+    version: "1.0.0"
+    title: Loose
+    description: Answers with the wrong shape.
+    side_effect: workspace{schemas}
+    examples: []
+    runtime: {{kind: wasm, module: {{path: loose.wat}}}}
+"#
+        ),
+    )?;
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: load_tool_registry(&registry_path)?,
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    // A pin manifest naming another registry version must not be silently overridden.
+    let mismatched = run_daemon(DaemonConfig {
+        pin_manifest: PinManifest {
+            tool_registry: Some(3),
+            ..PinManifest::default()
+        },
+        ..config.clone()
+    })
+    .await;
+    assert!(mismatched.is_err());
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let request = |tool_id: &str, input: serde_json::Value| ToolRequestPayload {
+        workspace_id: workspace_id.clone(),
+        tool_id: tool_id.to_string(),
+        input,
+        session_id: None,
+    };
+
+    let unknown = client
+        .tool_request(request("acme.nope", serde_json::json!({})), None, None)
+        .await?;
+    assert_eq!(
+        unknown.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+    let extra = client
+        .tool_request(
+            request("acme.add", serde_json::json!({"a": 1, "b": 2, "c": 3})),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        extra.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    assert_eq!(extra.events.len(), 1);
+    assert_eq!(extra.events[0].payload["details"]["field"], "input");
+
+    let requested = client
+        .tool_request(
+            request("acme.add", serde_json::json!({"a": 1, "b": 2})),
+            None,
+            None,
+        )
+        .await?;
+    assert!(requested.accepted);
+    assert_eq!(requested.events.len(), 1);
+    let event = &requested.events[0];
+    assert_eq!(event.event_type, "tool.requested");
+    assert_eq!(event.subject.kind, "tool_call");
+    assert_eq!(event.payload["side_effect"], "pure");
+    let request_id = event.subject.id.clone();
+
+    let complete =
+        |output: Option<serde_json::Value>, error: Option<ToolError>| ToolCompletePayload {
+            workspace_id: workspace_id.clone(),
+            request_id: request_id.clone(),
+            output,
+            error,
+        };
+    let bad_output = client
+        .tool_complete(
+            complete(Some(serde_json::json!({"sum": "3"})), None),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        bad_output.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let pending = client
+        .tool_call_list(&workspace_id, Some(ToolCallStatus::Pending), None)
+        .await?;
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].request_id, request_id);
+
+    let both = client
+        .tool_complete(
+            complete(
+                Some(serde_json::json!({"sum": 3})),
+                Some(ToolError {
+                    code: "x".to_string(),
+                    message: "y".to_string(),
+                }),
+            ),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        both.rejection.expect("rejection").code,
+        ErrorCode::InvalidSchema
+    );
+    let completed = client
+        .tool_complete(
+            complete(Some(serde_json::json!({"sum": 3})), None),
+            None,
+            None,
+        )
+        .await?;
+    assert!(completed.accepted);
+    assert_eq!(completed.events[0].event_type, "tool.result");
+    assert_eq!(completed.events[0].payload["status"], "succeeded");
+    let again = client
+        .tool_complete(
+            complete(Some(serde_json::json!({"sum": 3})), None),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        again.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let call = client.tool_call_get(&workspace_id, &request_id).await?;
+    assert_eq!(call.status, ToolCallStatus::Succeeded);
+    assert_eq!(call.output, Some(serde_json::json!({"sum": 3})));
+
+    let fixed = client
+        .tool_request(
+            request("acme.fixed", serde_json::json!({"a": 1, "b": 2})),
+            None,
+            None,
+        )
+        .await?;
+    assert!(fixed.accepted);
+    let result = fixed.events.last().expect("result");
+    assert_eq!(result.event_type, "tool.result");
+    assert_eq!(result.payload["status"], "succeeded");
+    assert_eq!(result.payload["output"], serde_json::json!({"sum": 3}));
+    let run_by_kernel = client
+        .tool_complete(
+            ToolCompletePayload {
+                workspace_id: workspace_id.clone(),
+                request_id: result.subject.id.clone(),
+                output: Some(serde_json::json!({"sum": 3})),
+                error: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(run_by_kernel.rejection.is_some());
+
+    let loose = client
+        .tool_request(
+            request("acme.loose", serde_json::json!({"a": 1, "b": 2})),
+            None,
+            None,
+        )
+        .await?;
+    assert!(loose.accepted);
+    let result = loose.events.last().expect("result");
+    assert_eq!(result.payload["status"], "failed");
+    assert_eq!(result.payload["error"]["code"], "invalid_output");
+    assert!(result.payload.get("output").is_none());
+
+    let calls = client.tool_call_list(&workspace_id, None, None).await?;
+    assert_eq!(calls.len(), 3);
+    let failed = client
+        .tool_call_list(&workspace_id, Some(ToolCallStatus::Failed), None)
+        .await?;
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].tool_id, "acme.loose");
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tool_hooks_block_before_and_rewrite_after_tools_run() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    std::fs::write(
+        temp.path().join("fixed.wat"),
+        wasm_hook(&[("clock_now", "")], r#"{"sum":3}"#, None),
+    )?;
+    let registry_path = temp.path().join("tools.yaml");
+    std::fs::write(
+        &registry_path,
+        r#"api_version: 1
+version: 1
+tools:
+  - id: acme.fixed
+    version: "1.0.0"
+    title: Fixed
+    description: Always answers three.
+    side_effect: pure
+    input_schema: {type: object, additionalProperties: false}
+    output_schema: {type: object, required: [sum], properties: {sum: {type: integer}}}
+    examples: []
+    runtime: {kind: wasm, module: {path: fixed.wat}, capabilities: {clock: true}}
+"#,
+    )?;
+    let blocking = temp.path().join("block.yaml");
+    std::fs::write(
+        &blocking,
+        r#"api_version: 1
+hooks:
+  - id: org.no-tools
+    version: "1.0.0"
+    phases: [pre_tool]
+    action: {kind: block, reason: tools are disabled}
+"#,
+    )?;
+    let rewriting = temp.path().join("rewrite.yaml");
+    std::fs::write(
+        &rewriting,
+        r#"api_version: 1
+hooks:
+  - id: org.bump
+    version: "1.0.0"
+    phases: [post_tool]
+    action: {kind: rewrite, pointer: /output/sum, value: 4}
+"#,
+    )?;
+
+    for (name, chain_path) in [("block", &blocking), ("rewrite", &rewriting)] {
+        let runtime_dir = temp.path().join(name).join("run");
+        let config = DaemonConfig {
+            db_path: temp.path().join(name).join("mpd.sqlite"),
+            addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+            runtime_dir: runtime_dir.clone(),
+            data_dir: temp.path().join(name).join("data"),
+            safe_mode: false,
+            pin_manifest: PinManifest::default(),
+            allow_floating_pins: false,
+            hook_chain: load_hook_chain(chain_path)?,
+            hooks_fail_open: false,
+            record_hook_inputs: false,
+            tool_registry: load_tool_registry(&registry_path)?,
+            skill_dir: None,
+            skill_trust_keys: Vec::new(),
+            policy: PolicySet::default(),
+            allow_without_policy: true,
+        };
+        let handle = tokio::spawn(run_daemon(config));
+        let client = wait_for_client(&runtime_dir).await?;
+        let create = client
+            .workspace_create("demo".to_string(), None, None, None)
+            .await?;
+        let workspace_id = create.events[0].workspace_id.clone();
+        let request = ToolRequestPayload {
+            workspace_id: workspace_id.clone(),
+            tool_id: "acme.fixed".to_string(),
+            input: serde_json::json!({}),
+            session_id: None,
+        };
+        let key = Some(format!("ik_{name}"));
+        let response = client
+            .tool_request(request.clone(), key.clone(), None)
+            .await?;
+        let events = client.events_read_from(&workspace_id, 0).await?;
+        if name == "block" {
+            // The module never ran: no call, no hostcalls.
+            let rejection = response.rejection.expect("rejection");
+            assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+            assert_eq!(response.events[0].payload["details"]["phase"], "pre_tool");
+            assert!(client
+                .tool_call_list(&workspace_id, None, None)
+                .await?
+                .is_empty());
+            assert!(!events
+                .iter()
+                .any(|event| event.event_type == "sandbox.hostcall"));
+        } else {
+            assert!(response.accepted, "{:?}", response.rejection);
+            let result = response.events.last().expect("result");
+            assert_eq!(result.event_type, "tool.result");
+            assert_eq!(result.payload["output"], serde_json::json!({"sum": 4}));
+            assert!(events
+                .iter()
+                .any(|event| event.event_type == "sandbox.hostcall"));
+
+            // A retry is answered from the log; the module does not run again.
+            let retried = client.tool_request(request, key, None).await?;
+            assert_eq!(retried.events[0].event_id, response.events[0].event_id);
+            let replayed = client.events_read_from(&workspace_id, 0).await?;
+            assert_eq!(replayed.len(), events.len());
+            assert_eq!(
+                client
+                    .tool_call_list(&workspace_id, None, None)
+                    .await?
+                    .len(),
+                1
+            );
+        }
+        handle.abort();
+    }
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tool_catalog_searches_loads_and_runs_within_budgets() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    std::fs::write(
+        temp.path().join("fixed.wat"),
+        wasm_hook(&[], r#"{"sum":3}"#, None),
+    )?;
+    let note = "n".repeat(2000);
+    let bulky_examples = (0..12)
+        .map(|index| format!("      - {{description: case {index}, input: {{note: {note}}}}}\n"))
+        .collect::<String>();
+    let registry_path = temp.path().join("tools.yaml");
+    let adder = r#"
+    input_schema:
+      type: object
+      additionalProperties: false
+      required: [a, b]
+      properties: {a: {type: integer}, b: {type: integer}}
+    output_schema:
+      type: object
+      required: [sum]
+      properties: {sum: {type: integer}}"#;
+    std::fs::write(
+        &registry_path,
+        format!(
+            r#"api_version: 1
+version: 1
+tools:
+  - id: acme.add
+    version: "1.0.0"
+    title: Add integers
+    description: Adds two integers in an external runner. Results arrive later.
+    side_effect: pure{adder}
+    examples: []
+  - id: acme.fixed
+    version: "1.0.0"
+    title: Fixed sum
+    description: Always answers three.
+    side_effect: pure{adder}
+    examples: []
+    runtime: {{kind: wasm, module: {{path: fixed.wat}}}}
+  - id: acme.notes
+    version: "1.0.0"
+    title: File notes
+    description: Writes notes into the workspace directory.
+    side_effect: filesystem
+    input_schema:
+      type: object
+      additionalProperties: false
+      properties: {{note: {{type: string}}}}
+    output_schema: {{type: object}}
+    examples:
+{bulky_examples}"#
+        ),
+    )?;
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: load_tool_registry(&registry_path)?,
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let error_code = |err: anyhow::Error| {
+        err.downcast_ref::<mp_client::ClientError>()
+            .expect("client error")
+            .error
+            .code
+            .clone()
+    };
+
+    let found = client.tool_search("add integers", None, None, None).await?;
+    assert_eq!(found.hits[0].id, "acme.add");
+    assert_eq!(found.hits[0].kind, CatalogKind::Tool);
+    assert_eq!(
+        found.hits[0].summary,
+        "Adds two integers in an external runner."
+    );
+    let commands = client
+        .tool_search("task", Some(2), None, Some(CatalogKind::Command))
+        .await?;
+    assert_eq!(commands.hits.len(), 2);
+    assert!(commands.total > 2);
+    assert!(commands.hits.iter().all(|hit| hit.id.starts_with("task.")));
+    let filesystem = client
+        .tool_search("notes", None, Some(SideEffect::Filesystem), None)
+        .await?;
+    assert_eq!(filesystem.hits.len(), 1);
+    let wide = client
+        .tool_search("workspace task project", Some(25), None, None)
+        .await?;
+    assert!(serde_json::to_vec(&wide)?.len() <= TOOL_SEARCH_BUDGET_BYTES);
+    let empty = client
+        .tool_search("  ", None, None, None)
+        .await
+        .expect_err("empty query");
+    assert_eq!(error_code(empty), ErrorCode::InvalidSchema);
+
+    let notes = client.tool_load("acme.notes").await?;
+    assert!(notes.omitted_examples > 0);
+    assert_eq!(notes.examples.len() + notes.omitted_examples, 12);
+    assert!(serde_json::to_vec(&notes)?.len() <= TOOL_LOAD_BUDGET_BYTES);
+    let task_create = client.tool_load("task.create").await?;
+    assert_eq!(task_create.kind, CatalogKind::Command);
+    assert!(task_create.output_schema.is_none());
+    assert_eq!(task_create.input_schema["required"][0], "workspace_id");
+    let missing = client
+        .tool_load("acme.nope")
+        .await
+        .expect_err("unknown tool");
+    assert_eq!(error_code(missing), ErrorCode::NotFound);
+    let hidden = client
+        .tool_load("tool.request")
+        .await
+        .expect_err("meta command");
+    assert_eq!(error_code(hidden), ErrorCode::NotFound);
+
+    let fixed = client
+        .tool_run(
+            "acme.fixed",
+            serde_json::json!({"a": 1, "b": 2}),
+            Some(workspace_id.clone()),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    assert!(fixed.accepted);
+    assert_eq!(fixed.status, Some(ToolCallStatus::Succeeded));
+    assert_eq!(fixed.output, Some(serde_json::json!({"sum": 3})));
+    let subject = fixed.subject.expect("subject");
+    assert_eq!(subject.kind, "tool_call");
+    let call = client.tool_call_get(&workspace_id, &subject.id).await?;
+    assert_eq!(call.tool_id, "acme.fixed");
+
+    let pending = client
+        .tool_run(
+            "acme.add",
+            serde_json::json!({"a": 1, "b": 2}),
+            Some(workspace_id.clone()),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(pending.status, Some(ToolCallStatus::Pending));
+    assert!(pending.output.is_none());
+    let invalid = client
+        .tool_run(
+            "acme.add",
+            serde_json::json!({"a": 1}),
+            Some(workspace_id.clone()),
+            None,
+            None,
+            None,
+        )
+        .await?;
+    assert!(!invalid.accepted);
+    assert_eq!(
+        invalid.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+    let no_workspace = client
+        .tool_run(
+            "acme.add",
+            serde_json::json!({"a": 1, "b": 2}),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .expect_err("workspace required");
+    assert_eq!(error_code(no_workspace), ErrorCode::InvalidSchema);
+
+    let task = client
+        .tool_run(
+            "task.create",
+            serde_json::json!({"workspace_id": workspace_id, "title": "from catalog"}),
+            None,
+            None,
+            Some("ik-catalog-task".to_string()),
+            None,
+        )
+        .await?;
+    assert!(task.accepted);
+    let task_subject = task.subject.expect("subject");
+    assert_eq!(task_subject.kind, "task");
+    assert!(task.status.is_none());
+    let replay = client
+        .tool_run(
+            "task.create",
+            serde_json::json!({"workspace_id": workspace_id, "title": "from catalog"}),
+            None,
+            None,
+            Some("ik-catalog-task".to_string()),
+            None,
+        )
+        .await?;
+    assert_eq!(replay.subject.expect("subject").id, task_subject.id);
+    let tasks = client.task_list(&workspace_id, None, None).await?;
+    assert_eq!(tasks.len(), 1);
+    let bad_payload = client
+        .tool_run(
+            "task.create",
+            serde_json::json!({"workspace_id": workspace_id}),
+            None,
+            None,
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        bad_payload.rejection.expect("rejection").code,
+        ErrorCode::InvalidSchema
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn artifacts_are_content_addressed_and_deduplicated() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let session = client
+        .session_spawn(
+            SessionSpawnPayload {
+                workspace_id: workspace_id.clone(),
+                project_id: None,
+                parent_session_id: None,
+                label: Some("builder".to_string()),
+                blueprint: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    let producer = Subject {
+        kind: "session".to_string(),
+        id: session.events[0].subject.id.clone(),
+    };
+    let put = |content: &[u8], producer: Option<Subject>| ArtifactPutPayload {
+        workspace_id: workspace_id.clone(),
+        content_base64: Some(base64::engine::general_purpose::STANDARD.encode(content)),
+        hash: None,
+        name: Some("build.log".to_string()),
+        mime_type: Some("text/plain".to_string()),
+        metadata: None,
+        producer,
+    };
+
+    let first = client
+        .artifact_record(
+            put(b"cargo build\nok\n", Some(producer.clone())),
+            None,
+            None,
+        )
+        .await?;
+    assert!(first.accepted);
+    let stored = &first.events[0];
+    assert_eq!(stored.event_type, "artifact.stored");
+    let hash = stored.payload["hash"].as_str().expect("hash").to_string();
+    assert_eq!(stored.subject.id, hash);
+    assert_eq!(stored.payload["size_bytes"], 15);
+    assert_eq!(stored.payload["deduplicated"], false);
+    let hex = hash.strip_prefix("blake3:").expect("blake3 prefix");
+    let blob = temp
+        .path()
+        .join("data")
+        .join("artifacts")
+        .join("blake3")
+        .join(&hex[..2])
+        .join(hex);
+    assert_eq!(std::fs::read(&blob)?, b"cargo build\nok\n");
+
+    let second = client
+        .artifact_record(put(b"cargo build\nok\n", None), None, None)
+        .await?;
+    assert_eq!(second.events[0].payload["hash"], hash.as_str());
+    assert_eq!(second.events[0].payload["deduplicated"], true);
+
+    let invalid = client
+        .artifact_record(
+            ArtifactPutPayload {
+                content_base64: Some("not base64!".to_string()),
+                ..put(b"", None)
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        invalid.rejection.expect("rejection").code,
+        ErrorCode::InvalidSchema
+    );
+    let orphan = client
+        .artifact_record(
+            put(
+                b"patch",
+                Some(Subject {
+                    kind: "task".to_string(),
+                    id: "missing".to_string(),
+                }),
+            ),
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        orphan.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    let board = client.board(&workspace_id).await?;
+    let provenance: Vec<_> = board
+        .edges
+        .iter()
+        .filter(|edge| edge.kind == BoardEdgeKind::Provenance)
+        .map(|edge| (edge.from_node_id.as_str(), edge.to_node_id.as_str()))
+        .collect();
+    assert_eq!(
+        provenance,
+        vec![(
+            format!("session:{}", producer.id).as_str(),
+            format!("artifact:{hash}").as_str()
+        )]
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn artifacts_stream_over_http_with_hash_check_and_ranges() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path,
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let info = read_runtime_info(&runtime_dir).await?;
+
+    // Several chunks' worth, so both directions actually stream.
+    let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let source = temp.path().join("build.log");
+    std::fs::write(&source, &content)?;
+
+    let uploaded = client.artifact_put(&source, Some("text/plain")).await?;
+    assert!(!uploaded.deduplicated);
+    assert_eq!(uploaded.artifact.size_bytes, 300_000);
+    assert_eq!(uploaded.artifact.mime_type.as_deref(), Some("text/plain"));
+    let hash = uploaded.artifact.hash.clone();
+    let again = client.artifact_put(&source, None).await?;
+    assert!(again.deduplicated);
+    assert_eq!(again.artifact.hash, hash);
+
+    let mut full = Vec::new();
+    let written = client.artifact_get(&hash, None, &mut full).await?;
+    assert_eq!(written, 300_000);
+    assert_eq!(full, content);
+    let mut tail = Vec::new();
+    client
+        .artifact_get(&hash, Some("299990-".parse().expect("range")), &mut tail)
+        .await?;
+    assert_eq!(tail, &content[299_990..]);
+
+    let http = reqwest::Client::new();
+    let url = format!("{}/v1/artifacts/{hash}", info.addr);
+    let partial = http
+        .get(&url)
+        .header(AUTHORIZATION, format!("Bearer {}", info.token))
+        .header(reqwest::header::RANGE, "bytes=10-19")
+        .send()
+        .await?;
+    assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        partial.headers()[reqwest::header::CONTENT_RANGE],
+        "bytes 10-19/300000"
+    );
+    assert_eq!(partial.bytes().await?.as_ref(), &content[10..20]);
+    let unsatisfiable = http
+        .get(&url)
+        .header(AUTHORIZATION, format!("Bearer {}", info.token))
+        .header(reqwest::header::RANGE, "bytes=300000-")
+        .send()
+        .await?;
+    assert_eq!(unsatisfiable.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    let anonymous = http.get(&url).send().await?;
+    assert_eq!(anonymous.status(), StatusCode::UNAUTHORIZED);
+
+    let mismatched = http
+        .put(format!(
+            "{}/v1/artifacts?hash=blake3:{}",
+            info.addr,
+            "00".repeat(32)
+        ))
+        .header(AUTHORIZATION, format!("Bearer {}", info.token))
+        .body(b"tampered".to_vec())
+        .send()
+        .await?;
+    assert_eq!(mismatched.status(), StatusCode::BAD_REQUEST);
+    let error: ErrorResponse = mismatched.json().await?;
+    assert_eq!(error.code, ErrorCode::ValidationFailed);
+    let staging = temp.path().join("data").join("artifacts").join("staging");
+    assert_eq!(std::fs::read_dir(staging)?.count(), 0);
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let recorded = client
+        .artifact_record(
+            ArtifactPutPayload {
+                workspace_id: workspace_id.clone(),
+                content_base64: None,
+                hash: Some(hash.clone()),
+                name: Some("build.log".to_string()),
+                mime_type: Some("text/plain".to_string()),
+                metadata: None,
+                producer: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert!(recorded.accepted);
+    assert_eq!(recorded.events[0].subject.id, hash);
+    assert_eq!(recorded.events[0].payload["size_bytes"], 300_000);
+    let unknown = client
+        .artifact_record(
+            ArtifactPutPayload {
+                workspace_id,
+                content_base64: None,
+                hash: Some(format!("blake3:{}", "11".repeat(32))),
+                name: None,
+                mime_type: None,
+                metadata: None,
+                producer: None,
+            },
+            None,
+            None,
+        )
+        .await?;
+    assert_eq!(
+        unknown.rejection.expect("rejection").code,
+        ErrorCode::NotFound
+    );
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn secrets_vault_encrypts_redacts_and_locks_when_idle() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path: db_path.clone(),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    let clock = Arc::new(ManualClock::new(OffsetDateTime::now_utc()));
+    let handle = tokio::spawn(run_daemon_with_clock(config, clock.clone()));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    const VALUE: &str = "sk-live-4242424242";
+    let set = |name: &str, value: &str| SecretSetPayload {
+        workspace_id: workspace_id.clone(),
+        name: name.to_string(),
+        owner: SecretOwner {
+            scope: SecretScope::Global,
+            id: None,
+        },
+        value: value.to_string(),
+    };
+
+    let init = client
+        .vault_init(
+            VaultInitPayload {
+                workspace_id: workspace_id.clone(),
+                password: "correct horse battery".to_string(),
+            },
+            None,
+        )
+        .await?;
+    assert!(init.accepted, "{:?}", init.rejection);
+    assert_eq!(init.events[0].event_type, "vault.initialized");
+    assert_eq!(init.events[0].actor.kind, "token");
+    let status = client.vault_status(&workspace_id).await?;
+    assert!(status.initialized && !status.unlocked);
+
+    let locked = client.secret_set(set("API_KEY", VALUE), None).await?;
+    let rejection = locked.rejection.expect("rejection");
+    assert_eq!(rejection.code, ErrorCode::Unauthorized);
+    assert!(!rejection.message.contains(VALUE));
+
+    let wrong = client
+        .vault_unlock(
+            VaultUnlockPayload {
+                workspace_id: workspace_id.clone(),
+                password: "wrong password".to_string(),
+                idle_timeout_ms: None,
+            },
+            None,
+        )
+        .await?;
+    let rejection = wrong.rejection.expect("wrong password");
+    assert_eq!(rejection.code, ErrorCode::Unauthorized);
+
+    let unlocked = client
+        .vault_unlock(
+            VaultUnlockPayload {
+                workspace_id: workspace_id.clone(),
+                password: "correct horse battery".to_string(),
+                idle_timeout_ms: Some(60_000),
+            },
+            None,
+        )
+        .await?;
+    assert!(unlocked.accepted, "{:?}", unlocked.rejection);
+    let status = client.vault_status(&workspace_id).await?;
+    assert!(status.unlocked);
+    assert!(status.locks_at.is_some());
+
+    let created = client.secret_set(set("API_KEY", VALUE), None).await?;
+    assert!(created.accepted, "{:?}", created.rejection);
+    assert_eq!(created.events[0].event_type, "secret.created");
+    assert_eq!(created.events[0].payload["redacted"], "****");
+    let secret_id = created.events[0].subject.id.clone();
+
+    let duplicate = client.secret_set(set("API_KEY", "other"), None).await?;
+    assert!(!duplicate.accepted);
+
+    let rotated = client
+        .secret_rotate(
+            SecretRotatePayload {
+                workspace_id: workspace_id.clone(),
+                secret_id: secret_id.clone(),
+                value: "sk-live-rotated".to_string(),
+            },
+            None,
+        )
+        .await?;
+    assert!(rotated.accepted, "{:?}", rotated.rejection);
+    assert_eq!(rotated.events[0].payload["version"], 2);
+
+    let secrets = client.secret_list(&workspace_id).await?;
+    assert_eq!(secrets.len(), 1);
+    assert_eq!(secrets[0].version, 2);
+    assert_eq!(secrets[0].length, "sk-live-rotated".len());
+    let resolved = client
+        .secret_resolve(&workspace_id, "API_KEY", &SecretContext::default())
+        .await?;
+    assert_eq!(resolved.secret_id, secret_id);
+
+    let deleted = client
+        .secret_delete(
+            SecretDeletePayload {
+                workspace_id: workspace_id.clone(),
+                secret_id: secret_id.clone(),
+            },
+            None,
+        )
+        .await?;
+    assert!(deleted.accepted, "{:?}", deleted.rejection);
+    assert!(client.secret_list(&workspace_id).await?.is_empty());
+
+    clock.advance(time::Duration::seconds(61));
+    let locked = timeout(Duration::from_secs(5), async {
+        loop {
+            let status = client.vault_status(&workspace_id).await?;
+            if !status.unlocked {
+                return Ok::<_, anyhow::Error>(status);
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await??;
+    assert!(locked.initialized);
+    let events = timeout(Duration::from_secs(5), async {
+        loop {
+            let events = client.events_read_from(&workspace_id, 0).await?;
+            if events.iter().any(|e| e.event_type == "vault.locked") {
+                return Ok::<_, anyhow::Error>(events);
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await??;
+    let types: Vec<_> = events.iter().map(|e| e.event_type.as_str()).collect();
+    assert_eq!(
+        &types[1..],
+        [
+            "vault.initialized",
+            "command.rejected",
+            "command.rejected",
+            "vault.unlocked",
+            "secret.created",
+            "command.rejected",
+            "secret.rotated",
+            "secret.deleted",
+            "vault.locked"
+        ]
+    );
+    assert_eq!(events.last().unwrap().payload["reason"], "idle");
+    // Rejections are recorded too, and must be as redacted as the accepted commands.
+    let log = serde_json::to_string(&events)?;
+    assert!(!log.contains(VALUE) && !log.contains("sk-live-rotated"));
+    assert!(!log.contains("correct horse battery") && !log.contains("wrong password"));
+
+    handle.abort();
+    let _ = handle.await;
+
+    for suffix in ["", "-wal"] {
+        let path = temp.path().join(format!("mpd.sqlite{suffix}"));
+        if let Ok(bytes) = std::fs::read(&path) {
+            assert!(!bytes
+                .windows(VALUE.len())
+                .any(|window| window == VALUE.as_bytes()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+#[tokio::test(flavor = "multi_thread")]
+async fn secret_exec_is_denied_by_default_and_records_the_launch() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let runtime_dir = temp.path().join("run");
+    let policy_dir = temp.path().join("policy");
+    std::fs::create_dir_all(&policy_dir)?;
+    std::fs::write(
+        policy_dir.join("exec.yaml"),
+        "api_version: 1\nid: exec\ndefault: allow\nrules:\n  - id: users-exec\n    effect: allow\n    actions: [\"secret.exec\"]\n    subject: {kind: user}\n",
+    )?;
+    let config = DaemonConfig {
+        db_path: temp.path().join("mpd.sqlite"),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: load_policy_dir(&policy_dir)?,
+        allow_without_policy: false,
+    };
+
+    let handle = tokio::spawn(run_daemon(config));
+    let client = wait_for_client(&runtime_dir).await?;
+    let alice = wait_for_client(&runtime_dir).await?.with_actor(Actor {
+        kind: "user".to_string(),
+        id: "alice".to_string(),
+        label: None,
+        claimed: false,
+    });
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let init = client
+        .vault_init(
+            VaultInitPayload {
+                workspace_id: workspace_id.clone(),
+                password: "correct horse battery".to_string(),
+            },
+            None,
+        )
+        .await?;
+    assert!(init.accepted, "{:?}", init.rejection);
+
+    let exec = |secrets: Vec<SecretInjection>, args: Vec<&str>| SecretExecPayload {
+        workspace_id: workspace_id.clone(),
+        program: "sh".to_string(),
+        args: args.into_iter().map(str::to_string).collect(),
+        cwd: None,
+        context: SecretContext {
+            user_id: Some("alice".to_string()),
+            ..SecretContext::default()
+        },
+        secrets,
+        timeout_ms: None,
+    };
+    let inject = |name: &str, via: SecretInjectVia| SecretInjection {
+        name: name.to_string(),
+        via,
+    };
+    // The daemon runs with cargo's variables set; none of them may reach the process.
+    assert!(std::env::var("CARGO_MANIFEST_DIR").is_ok());
+    let script = vec![
+        "-c",
+        "read -r key; printf 'key=%s len=%s\\n' \"$key\" \"${#key}\"; cat \"$1\"; \
+         env | grep -c -e 1111 -e 2222 -e CARGO; echo \"$1\" >&2",
+        "sh",
+        "{secret:CERT}",
+    ];
+    let payload = exec(
+        vec![
+            inject("API_KEY", SecretInjectVia::Stdin),
+            inject("CERT", SecretInjectVia::File),
+        ],
+        script.clone(),
+    );
+
+    // A bundle default never admits a launch; only a rule naming it does.
+    let by_default = client.secret_exec(payload.clone(), None).await?;
+    let rejection = by_default.rejection.expect("denied by default");
+    assert_eq!(rejection.code, ErrorCode::PolicyDenied);
+    assert!(rejection.message.contains("denied unless a rule allows it"));
+
+    let locked = alice.secret_exec(payload.clone(), None).await?;
+    assert_eq!(
+        locked.rejection.expect("locked").code,
+        ErrorCode::Unauthorized
+    );
+
+    let unlocked = client
+        .vault_unlock(
+            VaultUnlockPayload {
+                workspace_id: workspace_id.clone(),
+                password: "correct horse battery".to_string(),
+                idle_timeout_ms: None,
+            },
+            None,
+        )
+        .await?;
+    assert!(unlocked.accepted, "{:?}", unlocked.rejection);
+    let set = |name: &str, owner: SecretOwner, value: &str| SecretSetPayload {
+        workspace_id: workspace_id.clone(),
+        name: name.to_string(),
+        owner,
+        value: value.to_string(),
+    };
+    let global = SecretOwner {
+        scope: SecretScope::Global,
+        id: None,
+    };
+    let user = SecretOwner {
+        scope: SecretScope::User,
+        id: Some("alice".to_string()),
+    };
+    for payload in [
+        set("API_KEY", global.clone(), "sk-global-0000"),
+        set("API_KEY", user, "sk-alice-1111"),
+        set("CERT", global, "sk-cert-2222"),
+    ] {
+        let response = client.secret_set(payload, None).await?;
+        assert!(response.accepted, "{:?}", response.rejection);
+    }
+
+    let missing_placeholder = alice
+        .secret_exec(
+            exec(
+                vec![inject("CERT", SecretInjectVia::File)],
+                vec!["-c", "true"],
+            ),
+            None,
+        )
+        .await?;
+    assert_eq!(
+        missing_placeholder
+            .rejection
+            .expect("file secret without placeholder")
+            .code,
+        ErrorCode::ValidationFailed
+    );
+    let unknown = alice
+        .secret_exec(
+            exec(
+                vec![inject("NOPE", SecretInjectVia::Stdin)],
+                vec!["-c", "true"],
+            ),
+            None,
+        )
+        .await?;
+    assert_eq!(
+        unknown.rejection.expect("unknown secret").code,
+        ErrorCode::NotFound
+    );
+
+    let response = alice.secret_exec(payload.clone(), None).await?;
+    assert!(response.accepted, "{:?}", response.rejection);
+    let types: Vec<_> = response
+        .events
+        .iter()
+        .map(|event| event.event_type.as_str())
+        .collect();
+    assert_eq!(
+        types,
+        [
+            "process.launched",
+            "secret.injected",
+            "secret.injected",
+            "policy.evaluated",
+            "process.exited"
+        ]
+    );
+    let launched = &response.events[0];
+    assert_eq!(launched.actor.id, "alice");
+    assert_eq!(launched.payload["program"], "sh");
+    // The recorded arguments keep the placeholder rather than the secret file's path.
+    assert_eq!(launched.payload["args"][3], "{secret:CERT}");
+    assert!(!launched.payload["env"]
+        .as_array()
+        .expect("env")
+        .iter()
+        .any(|name| name.as_str().is_some_and(|name| name.starts_with("CARGO"))));
+    let exited: ProcessExitedPayload = serde_json::from_value(response.events[4].payload.clone())?;
+    assert_eq!(response.events[4].subject, launched.subject);
+    assert_eq!(exited.exit_code, Some(0), "{exited:?}");
+    assert!(!exited.timed_out);
+    // The user-scoped key wins over the global one, and neither reaches the environment.
+    assert_eq!(exited.stdout, "key=**** len=13\n****0\n");
+    let secret_file = std::path::PathBuf::from(exited.stderr.trim());
+    assert!(secret_file.is_absolute());
+    assert!(!secret_file.exists());
+    assert!(!secret_file.parent().expect("secret dir").exists());
+
+    // A retry is answered from the log; the process does not run again.
+    let key = "exec-once".to_string();
+    let once = exec(
+        vec![inject("API_KEY", SecretInjectVia::Stdin)],
+        vec!["-c", "cat >/dev/null"],
+    );
+    let first = alice.secret_exec(once.clone(), Some(key.clone())).await?;
+    assert!(first.accepted, "{:?}", first.rejection);
+    let retried = alice.secret_exec(once, Some(key)).await?;
+    assert!(retried
+        .events
+        .iter()
+        .all(|event| event.event_type != "process.exited"));
+
+    let slow = alice
+        .secret_exec(
+            SecretExecPayload {
+                timeout_ms: Some(200),
+                ..exec(
+                    vec![inject("API_KEY", SecretInjectVia::Stdin)],
+                    vec!["-c", "sleep 5"],
+                )
+            },
+            None,
+        )
+        .await?;
+    let exited = slow
+        .events
+        .iter()
+        .find(|event| event.event_type == "process.exited")
+        .expect("process.exited");
+    assert_eq!(exited.payload["timed_out"], true);
+    assert!(exited.payload.get("exit_code").is_none());
+
+    let lock = || VaultLockPayload {
+        workspace_id: workspace_id.clone(),
+    };
+    let locked = client.vault_lock(lock(), None).await?;
+    assert!(locked.accepted, "{:?}", locked.rejection);
+    assert!(locked
+        .events
+        .iter()
+        .any(|event| event.event_type == "vault.locked" && event.payload["reason"] == "requested"));
+    let relocked = client.vault_lock(lock(), None).await?;
+    assert!(relocked.accepted, "{:?}", relocked.rejection);
+    assert!(relocked
+        .events
+        .iter()
+        .all(|event| event.event_type != "vault.locked"));
+    assert!(!client.vault_status(&workspace_id).await?.unlocked);
+
+    let events = client.events_read_from(&workspace_id, 0).await?;
+    let injected: Vec<_> = events
+        .iter()
+        .filter(|event| event.event_type == "secret.injected")
+        .collect();
+    assert_eq!(injected.len(), 4);
+    assert_eq!(injected[0].payload["name"], "API_KEY");
+    assert_eq!(injected[0].payload["owner"]["scope"], "user");
+    assert_eq!(injected[0].payload["via"], "stdin");
+    assert_eq!(injected[1].payload["name"], "CERT");
+    assert_eq!(injected[1].payload["via"], "file");
+    assert_eq!(injected[1].payload["program"], "sh");
+    assert_eq!(injected[0].actor.id, "alice");
+    assert_eq!(injected[0].trace_id, injected[1].trace_id);
+    let launches = events
+        .iter()
+        .filter(|event| event.event_type == "process.launched")
+        .count();
+    assert_eq!(launches, 3);
+    let log = serde_json::to_string(&events)?;
+    assert!(!log.contains("sk-alice") && !log.contains("sk-cert") && !log.contains("sk-global"));
+
+    handle.abort();
+    let _ = handle.await;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scheduler_times_out_and_retries_tasks() -> anyhow::Result<()> {
+    let temp = TempDir::new()?;
+    let db_path = temp.path().join("mpd.sqlite");
+    let runtime_dir = temp.path().join("run");
+
+    let config = DaemonConfig {
+        db_path: db_path.clone(),
+        addr: "127.0.0.1:0".parse::<SocketAddr>()?,
+        runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
+        safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
+    };
+
+    // A fixed epoch well before the wall clock: timeouts must be measured on the daemon clock.
+    let epoch = OffsetDateTime::from_unix_timestamp(1_577_836_800)?;
+    let clock = Arc::new(ManualClock::new(epoch));
+    let handle = tokio::spawn(run_daemon_with_clock(config, clock.clone()));
+    let client = wait_for_client(&runtime_dir).await?;
+
+    let create = client
+        .workspace_create("demo".to_string(), Some("./demo".to_string()), None, None)
+        .await?;
+    let workspace_id = create.events[0].workspace_id.clone();
+    let task = client
+        .task_create(
+            TaskCreatePayload {
+                workspace_id: workspace_id.clone(),
+                title: "deploy".to_string(),
+                project_id: None,
+                session_id: None,
+                deadline_at: None,
+                retry: Some(RetryPolicy {
+                    max_attempts: 2,
+                    backoff: Backoff::Fixed { delay_ms: 5_000 },
+                    jitter: JitterMode::None,
+                    retry_on: vec![FailureClass::Timeout],
+                }),
+                timeout_ms: Some(1_000),
+            },
+            None,
+            None,
+        )
+        .await?;
+    let task_id = task.events[0].subject.id.clone();
+    let transition = |failure_class: Option<FailureClass>| TaskTransitionPayload {
+        workspace_id: workspace_id.clone(),
+        task_id: task_id.clone(),
+        reason: None,
+        failure_class,
+    };
+
+    let started = client
+        .task_transition("task.start", transition(None), None, None)
+        .await?;
+    assert!(started.accepted);
+    assert_eq!(
+        started.events[0].payload["started_at"],
+        "2020-01-01T00:00:00Z"
+    );
+
+    clock.advance(time::Duration::seconds(2));
+    let requeued = timeout(Duration::from_secs(5), async {
+        loop {
+            let tasks = client
+                .task_list(&workspace_id, None, Some(TaskState::Queued))
+                .await?;
+            if let Some(task) = tasks.into_iter().next() {
+                return Ok::<_, anyhow::Error>(task);
+            }
+            sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await??;
+    assert_eq!(requeued.attempt, 1);
+    assert!(requeued.retry_at.is_some());
+    let events = client.events_read_from(&workspace_id, 0).await?;
+    let types: Vec<_> = events.iter().map(|e| e.event_type.as_str()).collect();
+    assert!(types.ends_with(&["task.timed_out", "task.retry_scheduled"]));
+
+    let early = client
+        .task_transition("task.start", transition(None), None, None)
+        .await?;
+    assert!(!early.accepted);
+    assert_eq!(
+        early.rejection.expect("rejection").code,
+        ErrorCode::ValidationFailed
+    );
+
+    clock.advance(time::Duration::seconds(6));
+    let retried = client
+        .task_transition("task.start", transition(None), None, None)
+        .await?;
+    assert!(retried.accepted);
+
+    // The policy is exhausted, so a second failure is final.
+    let failed = client
+        .task_transition(
+            "task.fail",
+            transition(Some(FailureClass::Timeout)),
+            None,
+            None,
+        )
+        .await?;
+    assert!(failed.accepted);
+    assert_eq!(failed.events.len(), 1);
+    assert_eq!(failed.events[0].payload["failure_class"], "timeout");
+
+    handle.abort();
+    let _ = handle.await;
+
+    let store = SqliteStore::open(&db_path)?;
+    store.rebuild_projections()?;
+    let task = store.get_task(&workspace_id, &task_id)?.expect("task");
+    assert_eq!(task.attempt, 2);
+    assert_eq!(task.state, TaskState::Failed);
+    Ok(())
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn http_auth_failure_returns_error_response() -> anyhow::Result<()> {
     let temp = TempDir::new()?;
@@ -203,7 +5203,18 @@ async fn http_auth_failure_returns_error_response() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -230,7 +5241,18 @@ async fn schema_failure_rejects_with_error_code() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -244,6 +5266,8 @@ async fn schema_failure_rejects_with_error_code() -> anyhow::Result<()> {
         idempotency_key: Some("ik_schema".to_string()),
         expected_version: None,
         trace_id: "tr_schema".to_string(),
+        actor: None,
+        lease_id: None,
     };
 
     let url = format!("{}/v1/commands/submit", info.addr);
@@ -273,7 +5297,18 @@ async fn unknown_query_fields_reject() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -324,7 +5359,18 @@ async fn event_stream_catches_up() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -353,7 +5399,18 @@ async fn event_stream_ndjson_catches_up() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -382,7 +5439,18 @@ async fn sse_stream_tails_new_events() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -420,7 +5488,18 @@ async fn ndjson_stream_tails_new_events() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir: runtime_dir.clone(),
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let handle = tokio::spawn(run_daemon(config));
@@ -458,7 +5537,18 @@ async fn stdio_transport_submit_and_subscribe() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir,
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let stdio = StdioConfig {
@@ -479,6 +5569,8 @@ async fn stdio_transport_submit_and_subscribe() -> anyhow::Result<()> {
         idempotency_key: Some("ik_test".to_string()),
         expected_version: None,
         trace_id: "tr_test".to_string(),
+        actor: None,
+        lease_id: None,
     };
     let frame = StdioFrame {
         request_id: Some("rq1".to_string()),
@@ -534,7 +5626,18 @@ async fn stdio_rejects_malformed_frame() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir,
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let stdio = StdioConfig {
@@ -567,7 +5670,18 @@ async fn stdio_requires_auth_when_configured() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir,
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let stdio = StdioConfig {
@@ -588,6 +5702,8 @@ async fn stdio_requires_auth_when_configured() -> anyhow::Result<()> {
         idempotency_key: Some("ik_auth".to_string()),
         expected_version: None,
         trace_id: "tr_auth".to_string(),
+        actor: None,
+        lease_id: None,
     };
     let frame = StdioFrame {
         request_id: Some("rq_auth_1".to_string()),
@@ -619,6 +5735,8 @@ async fn stdio_requires_auth_when_configured() -> anyhow::Result<()> {
         idempotency_key: Some("ik_auth_2".to_string()),
         expected_version: None,
         trace_id: "tr_auth_2".to_string(),
+        actor: None,
+        lease_id: None,
     };
     let frame = StdioFrame {
         request_id: Some("rq_auth_3".to_string()),
@@ -643,7 +5761,18 @@ async fn stdio_rejects_unknown_fields() -> anyhow::Result<()> {
         db_path,
         addr: "127.0.0.1:0".parse::<SocketAddr>()?,
         runtime_dir,
+        data_dir: temp.path().join("data"),
         safe_mode: false,
+        pin_manifest: PinManifest::default(),
+        allow_floating_pins: false,
+        hook_chain: HookChain::default(),
+        hooks_fail_open: false,
+        record_hook_inputs: false,
+        tool_registry: ToolRegistry::default(),
+        skill_dir: None,
+        skill_trust_keys: Vec::new(),
+        policy: PolicySet::default(),
+        allow_without_policy: true,
     };
 
     let stdio = StdioConfig {
@@ -0,0 +1,96 @@
+use crate::Subject;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const COMMAND_ARTIFACT_PUT: &str = "artifact.put";
+
+pub const EVENT_ARTIFACT_STORED: &str = "artifact.stored";
+
+/// Artifact ids are `blake3:<64 lowercase hex>`, so events reference content by hash.
+pub const ARTIFACT_HASH_PREFIX: &str = "blake3:";
+
+/// Returns the hex digest of a well-formed artifact hash.
+pub fn artifact_hash_hex(hash: &str) -> Option<&str> {
+    let hex = hash.strip_prefix(ARTIFACT_HASH_PREFIX)?;
+    let valid = hex.len() == 64
+        && hex
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c));
+    valid.then_some(hex)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactPutPayload {
+    pub workspace_id: String,
+    /// Standard base64 of the artifact bytes; small artifacts only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_base64: Option<String>,
+    /// Records a blob already uploaded through `PUT /v1/artifacts` instead of inline content.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    /// Display name, e.g. `build.log`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    /// Subject that produced the artifact; becomes a provenance edge on the board.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<Subject>,
+}
+
+/// Emitted against the `artifact` subject whose id is the content hash.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactStoredPayload {
+    pub hash: String,
+    pub size_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub producer: Option<Subject>,
+    /// This put wrote no new blob: the content was already stored, or it referenced an upload.
+    pub deduplicated: bool,
+}
+
+/// A row of the content-addressed artifact index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactEntry {
+    pub hash: String,
+    pub size_bytes: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub created_at: String,
+    pub metadata: Value,
+}
+
+/// Response of `PUT /v1/artifacts`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArtifactUploadResponse {
+    pub artifact: ArtifactEntry,
+    /// Identical content was already stored; the upload was discarded.
+    pub deduplicated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn artifact_hash_hex_requires_prefixed_lowercase_digest() {
+        let hex = "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262";
+        assert_eq!(artifact_hash_hex(&format!("blake3:{hex}")), Some(hex));
+        assert!(artifact_hash_hex(hex).is_none());
+        assert!(artifact_hash_hex("blake3:abc").is_none());
+        assert!(artifact_hash_hex(&format!("blake3:{}", hex.to_uppercase())).is_none());
+        assert!(artifact_hash_hex(&format!("blake3:../{}", &hex[3..])).is_none());
+    }
+}
@@ -0,0 +1,595 @@
+use crate::MessageScope;
+use serde::{Deserialize, Serialize};
+
+pub const COMMAND_BLUEPRINT_SYNC: &str = "blueprint.sync";
+
+pub const EVENT_BLUEPRINT_REGISTERED: &str = "blueprint.registered";
+pub const EVENT_BLUEPRINT_VERSION_CREATED: &str = "blueprint.version_created";
+
+/// Blueprint files live here, relative to the workspace root.
+pub const BLUEPRINT_DIR: &str = "agents/blueprints";
+
+/// The only blueprint file format understood so far.
+pub const BLUEPRINT_API_VERSION: u32 = 1;
+
+/// An agent definition as authored in `agents/blueprints/<id>.yaml`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentBlueprint {
+    pub api_version: u32,
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pins: Option<BlueprintPins>,
+    pub runtime: BlueprintRuntime,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<BlueprintExecution>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<BlueprintContext>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comms: Option<BlueprintComms>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<BlueprintTools>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<BlueprintHooks>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<BlueprintLimits>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintPins {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_bundle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_registry: Option<u32>,
+    /// Content hashes of the hooks in `hooks.chain`, by hook id.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<ComponentPin>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skills: Vec<ComponentPin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentPin {
+    pub id: String,
+    /// A semver or a content hash such as `sha256:…`.
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuntimeKind {
+    ExternalCli,
+    Internal,
+    Remote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EnvInjection {
+    Stdin,
+    Env,
+    File,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintRuntime {
+    pub kind: RuntimeKind,
+    /// Adapter id, e.g. `claude-code`.
+    pub adapter: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<RuntimeConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeConfig {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_injection: Option<EnvInjection>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionBackend {
+    LocalProcess,
+    Docker,
+    Podman,
+    K8s,
+    RemoteWorker,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintExecution {
+    pub backend: ExecutionBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workdir_scope: Option<WorkdirScope>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filesystem: Option<PathRules>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<NetworkPolicy>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkdirScope {
+    /// e.g. `worktree`.
+    pub mode: String,
+    #[serde(default)]
+    pub allow_write: bool,
+}
+
+/// Glob allow/deny lists; a deny match wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PathRules {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkDefault {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkPolicy {
+    pub default: NetworkDefault,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_domains: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budgets: Option<ContextBudgets>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sources: Option<ContextSources>,
+}
+
+/// Per-blueprint capsule budgets; the kernel's own budgets still cap them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContextBudgets {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_capsule_chars: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plan_capsule_chars: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision_capsule_chars: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manifest_chars: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContextSources {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transcript: Option<TranscriptAccess>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TranscriptAccess {
+    pub allow: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintComms {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<ScopeAllowList>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub groups: Option<GroupAccess>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leases: Option<LeaseAccess>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ScopeAllowList {
+    pub allow: Vec<MessageScope>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupAccess {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_join: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseAccess {
+    pub request_allowed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintTools {
+    /// Tool taxa the agent may invoke at all, e.g. `git`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow_taxa: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny_tools: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintHooks {
+    /// Interceptors in the order they run.
+    pub chain: Vec<HookRef>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookTrust {
+    Trusted,
+    Sandboxed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookRef {
+    pub id: String,
+    pub version: String,
+    pub trust: HookTrust,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_timeout_ms: Option<u64>,
+}
+
+/// Rescans the workspace's blueprint directory and records files whose content changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintSyncPayload {
+    pub workspace_id: String,
+}
+
+/// Payload of both `blueprint.registered` (version 1) and `blueprint.version_created`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintVersionPayload {
+    pub blueprint_id: String,
+    pub version: u32,
+    pub name: String,
+    /// File the version was loaded from, relative to the workspace root.
+    pub path: String,
+    /// `blake3:<hex>` of the file bytes; an unchanged hash never creates a version.
+    pub content_hash: String,
+    pub blueprint: AgentBlueprint,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintEntry {
+    pub workspace_id: String,
+    pub blueprint_id: String,
+    pub version: u32,
+    pub name: String,
+    pub path: String,
+    pub content_hash: String,
+    pub blueprint: AgentBlueprint,
+    pub created_at: String,
+    pub seq_global: i64,
+}
+
+/// Components the daemon has loaded, in the same shape as a blueprint's `pins`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinManifest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_bundle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_registry: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<ComponentPin>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skills: Vec<ComponentPin>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinComponent {
+    PolicyBundle,
+    ToolRegistry,
+    Hook,
+    Skill,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinMismatchReason {
+    /// The blueprint pins a different version than the daemon loaded.
+    Mismatch,
+    /// The blueprint pins a component the daemon has not loaded.
+    NotLoaded,
+    /// The blueprint uses a component without pinning it.
+    Floating,
+}
+
+/// One entry of the mismatch report attached to a rejected session start.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PinMismatch {
+    pub component: PinComponent,
+    /// Hook or skill id; absent for the policy bundle and tool registry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub declared: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loaded: Option<String>,
+    pub reason: PinMismatchReason,
+}
+
+/// Checks `blueprint.pins` against what the daemon loaded and returns the components the
+/// session will run with. The policy bundle, tool registry and every hook in the chain must be
+/// pinned unless `allow_floating`; a pinned or chained component that is not loaded is always
+/// a mismatch.
+pub fn resolve_pins(
+    blueprint: &AgentBlueprint,
+    loaded: &PinManifest,
+    allow_floating: bool,
+) -> Result<PinManifest, Vec<PinMismatch>> {
+    let declared = blueprint.pins.clone().unwrap_or_default();
+    let mut mismatches = Vec::new();
+    let mut check = |component: PinComponent,
+                     id: Option<&str>,
+                     declared: Option<String>,
+                     loaded: Option<String>| {
+        let reason = match (&declared, &loaded) {
+            (_, None) if declared.is_some() || id.is_some() => PinMismatchReason::NotLoaded,
+            (None, _) if allow_floating => return,
+            (None, _) => PinMismatchReason::Floating,
+            (Some(declared), Some(loaded)) if declared == loaded => return,
+            _ => PinMismatchReason::Mismatch,
+        };
+        mismatches.push(PinMismatch {
+            component,
+            id: id.map(str::to_string),
+            declared,
+            loaded,
+            reason,
+        });
+    };
+
+    check(
+        PinComponent::PolicyBundle,
+        None,
+        declared.policy_bundle.clone(),
+        loaded.policy_bundle.clone(),
+    );
+    check(
+        PinComponent::ToolRegistry,
+        None,
+        declared.tool_registry.map(|version| version.to_string()),
+        loaded.tool_registry.map(|version| version.to_string()),
+    );
+
+    let find = |pins: &[ComponentPin], id: &str| {
+        pins.iter()
+            .find(|pin| pin.id == id)
+            .map(|pin| pin.version.clone())
+    };
+    let mut hook_ids: Vec<&str> = declared.hooks.iter().map(|pin| pin.id.as_str()).collect();
+    if let Some(hooks) = &blueprint.hooks {
+        for hook in &hooks.chain {
+            if !hook_ids.contains(&hook.id.as_str()) {
+                hook_ids.push(&hook.id);
+            }
+        }
+    }
+    for id in &hook_ids {
+        check(
+            PinComponent::Hook,
+            Some(id),
+            find(&declared.hooks, id),
+            find(&loaded.hooks, id),
+        );
+    }
+    for pin in &declared.skills {
+        check(
+            PinComponent::Skill,
+            Some(&pin.id),
+            Some(pin.version.clone()),
+            find(&loaded.skills, &pin.id),
+        );
+    }
+
+    if !mismatches.is_empty() {
+        return Err(mismatches);
+    }
+    let pick = |pins: &[ComponentPin], ids: &[&str]| {
+        pins.iter()
+            .filter(|pin| ids.contains(&pin.id.as_str()))
+            .cloned()
+            .collect()
+    };
+    let skill_ids: Vec<&str> = declared.skills.iter().map(|pin| pin.id.as_str()).collect();
+    Ok(PinManifest {
+        policy_bundle: loaded.policy_bundle.clone(),
+        tool_registry: loaded.tool_registry,
+        hooks: pick(&loaded.hooks, &hook_ids),
+        skills: pick(&loaded.skills, &skill_ids),
+    })
+}
+
+/// Starts a session from a blueprint; `version` of `None` resolves the latest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlueprintRef {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+}
+
+/// The exact blueprint version and components a session was started with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionBlueprint {
+    pub blueprint_id: String,
+    pub version: u32,
+    pub content_hash: String,
+    pub pins: PinManifest,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agent_blueprint_round_trips_spec_shape() {
+        let blueprint: AgentBlueprint = serde_json::from_value(serde_json::json!({
+            "api_version": 1,
+            "id": "reviewer",
+            "name": "Reviewer",
+            "pins": {"tool_registry": 1, "skills": [{"id": "org.core.git", "version": "1.0.0"}]},
+            "runtime": {
+                "kind": "external_cli",
+                "adapter": "claude-code",
+                "config": {"command": ["claude", "--json"], "env_injection": "stdin"}
+            },
+            "execution": {
+                "backend": "local_process",
+                "network": {"default": "deny", "allow_domains": ["api.github.com"]}
+            },
+            "comms": {"scopes": {"allow": ["PARENT", "SIBLINGS"]}},
+            "hooks": {"chain": [{"id": "core.audit", "version": "1.0.0", "trust": "trusted"}]},
+            "limits": {"max_concurrency": 2}
+        }))
+        .expect("blueprint");
+        assert_eq!(blueprint.runtime.kind, RuntimeKind::ExternalCli);
+        assert_eq!(
+            blueprint
+                .comms
+                .as_ref()
+                .and_then(|comms| comms.scopes.as_ref()),
+            Some(&ScopeAllowList {
+                allow: vec![MessageScope::Parent, MessageScope::Siblings]
+            })
+        );
+        let value = serde_json::to_value(&blueprint).expect("serialize");
+        assert_eq!(value["execution"]["network"]["default"], "deny");
+        assert!(value.get("tools").is_none());
+    }
+
+    fn pinned_blueprint() -> AgentBlueprint {
+        serde_json::from_value(serde_json::json!({
+            "api_version": 1,
+            "id": "reviewer",
+            "name": "Reviewer",
+            "pins": {
+                "policy_bundle": "pol_1",
+                "tool_registry": 1,
+                "hooks": [{"id": "core.audit", "version": "sha256:aa"}],
+                "skills": [{"id": "org.core.git", "version": "sha256:bb"}]
+            },
+            "runtime": {"kind": "internal", "adapter": "mp"},
+            "hooks": {"chain": [
+                {"id": "core.audit", "version": "1.0.0", "trust": "trusted"},
+                {"id": "core.redaction", "version": "1.0.0", "trust": "sandboxed"}
+            ]}
+        }))
+        .expect("blueprint")
+    }
+
+    fn component(id: &str, version: &str) -> ComponentPin {
+        ComponentPin {
+            id: id.to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    #[test]
+    fn resolve_pins_reports_every_mismatch() {
+        let loaded = PinManifest {
+            policy_bundle: Some("pol_2".to_string()),
+            tool_registry: Some(1),
+            hooks: vec![
+                component("core.audit", "sha256:aa"),
+                component("core.redaction", "sha256:cc"),
+            ],
+            skills: Vec::new(),
+        };
+        let mismatches = resolve_pins(&pinned_blueprint(), &loaded, false).expect_err("mismatch");
+        let summary: Vec<(PinComponent, Option<&str>, PinMismatchReason)> = mismatches
+            .iter()
+            .map(|m| (m.component, m.id.as_deref(), m.reason))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    PinComponent::PolicyBundle,
+                    None,
+                    PinMismatchReason::Mismatch
+                ),
+                (
+                    PinComponent::Hook,
+                    Some("core.redaction"),
+                    PinMismatchReason::Floating
+                ),
+                (
+                    PinComponent::Skill,
+                    Some("org.core.git"),
+                    PinMismatchReason::NotLoaded
+                ),
+            ]
+        );
+        assert_eq!(mismatches[0].declared.as_deref(), Some("pol_1"));
+        assert_eq!(mismatches[0].loaded.as_deref(), Some("pol_2"));
+    }
+
+    #[test]
+    fn resolve_pins_allows_floating_only_when_enabled() {
+        let mut blueprint = pinned_blueprint();
+        blueprint.pins = None;
+        blueprint.hooks = None;
+        let loaded = PinManifest {
+            policy_bundle: Some("pol_1".to_string()),
+            tool_registry: Some(3),
+            hooks: vec![component("core.audit", "sha256:aa")],
+            skills: vec![component("org.core.git", "sha256:bb")],
+        };
+        let floating = resolve_pins(&blueprint, &loaded, false).expect_err("floating");
+        assert!(floating
+            .iter()
+            .all(|m| m.reason == PinMismatchReason::Floating));
+        assert_eq!(floating.len(), 2);
+
+        let resolved = resolve_pins(&blueprint, &loaded, true).expect("floating allowed");
+        assert_eq!(resolved.tool_registry, Some(3));
+        assert!(resolved.hooks.is_empty() && resolved.skills.is_empty());
+
+        let pinned = resolve_pins(&pinned_blueprint(), &loaded, true).expect_err("not loaded");
+        assert_eq!(pinned.len(), 2);
+        assert!(pinned
+            .iter()
+            .any(|m| m.id.as_deref() == Some("core.redaction")
+                && m.reason == PinMismatchReason::NotLoaded));
+    }
+}
@@ -0,0 +1,246 @@
+use crate::Subject;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const COMMAND_BOARD_GROUP_CREATE: &str = "board.group.create";
+pub const COMMAND_BOARD_COMMENT_ADD: &str = "board.comment.add";
+pub const COMMAND_BOARD_NODE_MOVE: &str = "board.node.move";
+
+pub const EVENT_BOARD_GROUP_CREATED: &str = "board.group.created";
+pub const EVENT_BOARD_COMMENT_ADDED: &str = "board.comment.added";
+pub const EVENT_BOARD_NODE_MOVED: &str = "board.node.moved";
+
+/// Subject kinds that become board nodes. Subjects that only record how the daemon works,
+/// such as commands, hooks, leases and secrets, stay off the board.
+pub const BOARD_NODE_KINDS: [&str; 9] = [
+    "workspace",
+    "project",
+    "session",
+    "task",
+    "worktree",
+    "artifact",
+    "gate",
+    "group",
+    "comment",
+];
+
+/// Payload fields consulted, in order, for a node's display label.
+pub const BOARD_LABEL_FIELDS: [&str; 5] = ["name", "label", "title", "path", "description"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BoardEdgeKind {
+    /// Parent session to child session.
+    Lineage,
+    /// Prerequisite to the node waiting on it, e.g. gate to guarded subject.
+    Dependency,
+    /// Session or task to the worktree it is attached to.
+    Attachment,
+    /// Producer to the artifact it produced.
+    Provenance,
+    /// Comment to the node it annotates.
+    Annotation,
+}
+
+impl BoardEdgeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BoardEdgeKind::Lineage => "lineage",
+            BoardEdgeKind::Dependency => "dependency",
+            BoardEdgeKind::Attachment => "attachment",
+            BoardEdgeKind::Provenance => "provenance",
+            BoardEdgeKind::Annotation => "annotation",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lineage" => Some(BoardEdgeKind::Lineage),
+            "dependency" => Some(BoardEdgeKind::Dependency),
+            "attachment" => Some(BoardEdgeKind::Attachment),
+            "provenance" => Some(BoardEdgeKind::Provenance),
+            "annotation" => Some(BoardEdgeKind::Annotation),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for BoardEdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Node ids are `<subject kind>:<subject id>` so any event subject maps to exactly one node.
+pub fn board_node_id(kind: &str, id: &str) -> String {
+    format!("{kind}:{id}")
+}
+
+/// Splits a node id back into its subject.
+pub fn parse_board_node_id(node_id: &str) -> Option<Subject> {
+    let (kind, id) = node_id.split_once(':')?;
+    if kind.is_empty() || id.is_empty() {
+        return None;
+    }
+    Some(Subject {
+        kind: kind.to_string(),
+        id: id.to_string(),
+    })
+}
+
+/// Picks a display label from the first non-empty string in [`BOARD_LABEL_FIELDS`].
+pub fn board_label(payload: &serde_json::Value) -> Option<String> {
+    BOARD_LABEL_FIELDS.iter().find_map(|field| {
+        payload
+            .get(field)
+            .and_then(|value| value.as_str())
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardPosition {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardGroupCreatePayload {
+    pub workspace_id: String,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<BoardPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardGroupCreatedPayload {
+    pub label: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<BoardPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardCommentAddPayload {
+    pub workspace_id: String,
+    pub body: String,
+    /// Node the comment annotates, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_node_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<BoardPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardCommentAddedPayload {
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_node_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<BoardPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardNodeMovePayload {
+    pub workspace_id: String,
+    pub node_id: String,
+    pub position: BoardPosition,
+    /// Group node to place the node in; omitted to leave any group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_node_id: Option<String>,
+}
+
+/// Emitted against the moved node's own subject.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardNodeMovedPayload {
+    pub position: BoardPosition,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_node_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardNode {
+    pub node_id: String,
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    /// The subject kind, e.g. `session`, `task`, `group`.
+    pub kind: String,
+    pub subject_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Comment text for `comment` nodes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
+    /// Persisted layout; `None` until the node is placed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<BoardPosition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group_node_id: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardEdge {
+    pub kind: BoardEdgeKind,
+    pub from_node_id: String,
+    pub to_node_id: String,
+    pub seq_global: i64,
+}
+
+/// Everything needed to render one workspace's board, consistent as of `seq_global`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BoardSnapshot {
+    pub workspace_id: String,
+    pub seq_global: i64,
+    pub nodes: Vec<BoardNode>,
+    pub edges: Vec<BoardEdge>,
+}
+
+impl BoardSnapshot {
+    pub fn node(&self, node_id: &str) -> Option<&BoardNode> {
+        self.nodes.iter().find(|node| node.node_id == node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn board_node_id_round_trips_subject() {
+        let node_id = board_node_id("session", "s1");
+        assert_eq!(node_id, "session:s1");
+        let subject = parse_board_node_id(&node_id).expect("subject");
+        assert_eq!(subject.kind, "session");
+        assert_eq!(subject.id, "s1");
+        assert!(parse_board_node_id("session").is_none());
+        assert!(parse_board_node_id(":s1").is_none());
+    }
+
+    #[test]
+    fn board_label_prefers_name_fields() {
+        assert_eq!(
+            board_label(&json!({"title": "Fix build", "path": "/tmp"})),
+            Some("Fix build".to_string())
+        );
+        assert_eq!(
+            board_label(&json!({"name": "", "path": "/tmp"})),
+            Some("/tmp".to_string())
+        );
+        assert_eq!(board_label(&json!({"reason": "x"})), None);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::Subject;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const COMMAND_CAPSULE_WRITE: &str = "capsule.write";
+
+pub const EVENT_CAPSULE_WRITTEN: &str = "capsule.written";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapsuleKind {
+    Status,
+    Plan,
+    Decision,
+    ArtifactManifest,
+    Risk,
+}
+
+impl CapsuleKind {
+    pub const ALL: [CapsuleKind; 5] = [
+        CapsuleKind::Status,
+        CapsuleKind::Plan,
+        CapsuleKind::Decision,
+        CapsuleKind::ArtifactManifest,
+        CapsuleKind::Risk,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CapsuleKind::Status => "status",
+            CapsuleKind::Plan => "plan",
+            CapsuleKind::Decision => "decision",
+            CapsuleKind::ArtifactManifest => "artifact_manifest",
+            CapsuleKind::Risk => "risk",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "status" => Some(CapsuleKind::Status),
+            "plan" => Some(CapsuleKind::Plan),
+            "decision" => Some(CapsuleKind::Decision),
+            "artifact_manifest" => Some(CapsuleKind::ArtifactManifest),
+            "risk" => Some(CapsuleKind::Risk),
+            _ => None,
+        }
+    }
+
+    /// Hard limit on `content`, in characters, enforced before the capsule is appended.
+    /// Risk flags are status-sized: they point at the concern, evidence lives in artifacts.
+    pub fn budget_chars(&self) -> usize {
+        match self {
+            CapsuleKind::Status => 1_500,
+            CapsuleKind::Plan => 3_000,
+            CapsuleKind::Decision => 3_000,
+            CapsuleKind::ArtifactManifest => 2_000,
+            CapsuleKind::Risk => 1_500,
+        }
+    }
+}
+
+impl fmt::Display for CapsuleKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Budgets count Unicode scalar values, matching JSON Schema `maxLength`.
+pub fn capsule_chars(content: &str) -> usize {
+    content.chars().count()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapsuleWritePayload {
+    pub workspace_id: String,
+    /// Any event-backed subject, e.g. the session reporting its status.
+    pub subject: Subject,
+    pub kind: CapsuleKind,
+    pub content: String,
+}
+
+/// Emitted against the capsule's subject so followers of that subject see it in its stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapsuleWrittenPayload {
+    pub kind: CapsuleKind,
+    pub version: u32,
+    pub content: String,
+    pub chars: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CapsuleEntry {
+    pub workspace_id: String,
+    pub subject: Subject,
+    pub kind: CapsuleKind,
+    /// Starts at 1 and counts per subject and kind.
+    pub version: u32,
+    pub content: String,
+    pub chars: u32,
+    pub created_at: String,
+    pub seq_global: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_match_capsule_contract() {
+        assert_eq!(CapsuleKind::Status.budget_chars(), 1_500);
+        assert_eq!(CapsuleKind::Plan.budget_chars(), 3_000);
+        assert_eq!(CapsuleKind::Decision.budget_chars(), 3_000);
+        assert_eq!(CapsuleKind::ArtifactManifest.budget_chars(), 2_000);
+        for kind in CapsuleKind::ALL {
+            assert_eq!(CapsuleKind::parse(kind.as_str()), Some(kind));
+        }
+    }
+
+    #[test]
+    fn capsule_chars_counts_characters_not_bytes() {
+        assert_eq!(capsule_chars("naïve ✓"), 7);
+    }
+}
@@ -0,0 +1,352 @@
+use crate::{parse_rfc3339, Subject};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use time::{Duration, OffsetDateTime};
+
+pub const COMMAND_GATE_DEFINE: &str = "gate.define";
+pub const COMMAND_GATE_APPROVE: &str = "gate.approve";
+pub const COMMAND_GATE_REJECT: &str = "gate.reject";
+
+pub const EVENT_GATE_DEFINED: &str = "gate.defined";
+pub const EVENT_GATE_APPROVED: &str = "gate.approved";
+pub const EVENT_GATE_REJECTED: &str = "gate.rejected";
+
+/// Subject kinds a gate can guard; commands are matched on their `<kind>_id` payload field.
+pub const GATE_SUBJECT_KINDS: [&str; 4] = ["workspace", "project", "session", "task"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateKind {
+    /// Satisfied by `required_approvals` distinct human approvers.
+    ManualApproval,
+    /// Satisfied by an approval from a policy bot or AI scorer.
+    PolicyEvaluation,
+    /// Satisfied once `delay_ms` has elapsed since the gate was defined.
+    Delay,
+}
+
+impl GateKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GateKind::ManualApproval => "manual_approval",
+            GateKind::PolicyEvaluation => "policy_evaluation",
+            GateKind::Delay => "delay",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "manual_approval" => Some(GateKind::ManualApproval),
+            "policy_evaluation" => Some(GateKind::PolicyEvaluation),
+            "delay" => Some(GateKind::Delay),
+            _ => None,
+        }
+    }
+
+    /// Whether an approval from `approver` counts towards this gate.
+    pub fn accepts(&self, approver: ApproverKind) -> bool {
+        match self {
+            GateKind::ManualApproval => approver == ApproverKind::Human,
+            GateKind::PolicyEvaluation => {
+                matches!(approver, ApproverKind::PolicyBot | ApproverKind::AiScorer)
+            }
+            GateKind::Delay => false,
+        }
+    }
+}
+
+impl fmt::Display for GateKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateStatus {
+    Open,
+    Approved,
+    Rejected,
+}
+
+impl GateStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GateStatus::Open => "open",
+            GateStatus::Approved => "approved",
+            GateStatus::Rejected => "rejected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "open" => Some(GateStatus::Open),
+            "approved" => Some(GateStatus::Approved),
+            "rejected" => Some(GateStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GateStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApproverKind {
+    Human,
+    PolicyBot,
+    AiScorer,
+}
+
+impl ApproverKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApproverKind::Human => "human",
+            ApproverKind::PolicyBot => "policy_bot",
+            ApproverKind::AiScorer => "ai_scorer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "human" => Some(ApproverKind::Human),
+            "policy_bot" => Some(ApproverKind::PolicyBot),
+            "ai_scorer" => Some(ApproverKind::AiScorer),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ApproverKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Who signed off; carried in the payload because command envelopes have no caller identity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Approver {
+    pub kind: ApproverKind,
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// What a gate guards: one command type applied to one subject.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateScope {
+    pub command_type: String,
+    pub subject: Subject,
+}
+
+impl GateScope {
+    /// Whether a command payload targets this scope's subject.
+    pub fn targets(&self, payload: &serde_json::Value) -> bool {
+        let field = format!("{}_id", self.subject.kind);
+        payload.get(&field).and_then(|value| value.as_str()) == Some(self.subject.id.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDefinePayload {
+    pub workspace_id: String,
+    pub scope: GateScope,
+    pub kind: GateKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_approvals: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDefinedPayload {
+    pub scope: GateScope,
+    pub kind: GateKind,
+    pub required_approvals: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Shared by `gate.approve` and `gate.reject`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDecisionPayload {
+    pub workspace_id: String,
+    pub gate_id: String,
+    pub approver: Approver,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Artifact references supporting the decision.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<String>,
+}
+
+/// Shared by `gate.approved` and `gate.rejected`; `status` is the gate status after the decision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDecidedPayload {
+    pub approver: Approver,
+    pub scope: GateScope,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<String>,
+    pub status: GateStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateDecisionEntry {
+    pub approved: bool,
+    pub approver: Approver,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub evidence: Vec<String>,
+    pub decided_at: String,
+    pub seq_global: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GateEntry {
+    pub gate_id: String,
+    pub workspace_id: String,
+    pub scope: GateScope,
+    pub kind: GateKind,
+    pub required_approvals: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub status: GateStatus,
+    pub decisions: Vec<GateDecisionEntry>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+impl GateEntry {
+    /// Approvals that count towards this gate, one per approver.
+    pub fn approval_count(&self) -> u32 {
+        let approvers: HashSet<(ApproverKind, &str)> = self
+            .decisions
+            .iter()
+            .filter(|decision| decision.approved && self.kind.accepts(decision.approver.kind))
+            .map(|decision| (decision.approver.kind, decision.approver.id.as_str()))
+            .collect();
+        approvers.len() as u32
+    }
+
+    pub fn has_decided(&self, approver: &Approver) -> bool {
+        self.decisions.iter().any(|decision| {
+            decision.approver.kind == approver.kind && decision.approver.id == approver.id
+        })
+    }
+
+    /// Whether commands in this gate's scope may proceed at `now`.
+    pub fn is_satisfied(&self, now: OffsetDateTime) -> bool {
+        match (self.status, self.kind) {
+            (GateStatus::Rejected, _) => false,
+            (_, GateKind::Delay) => {
+                let delay = Duration::milliseconds(self.delay_ms.unwrap_or_default() as i64);
+                parse_rfc3339(&self.created_at).is_some_and(|created_at| now >= created_at + delay)
+            }
+            (status, _) => status == GateStatus::Approved,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn gate(kind: GateKind, required_approvals: u32) -> GateEntry {
+        GateEntry {
+            gate_id: "g1".to_string(),
+            workspace_id: "w1".to_string(),
+            scope: GateScope {
+                command_type: "stage.transition".to_string(),
+                subject: Subject {
+                    kind: "task".to_string(),
+                    id: "t1".to_string(),
+                },
+            },
+            kind,
+            required_approvals,
+            delay_ms: (kind == GateKind::Delay).then_some(60_000),
+            description: None,
+            status: GateStatus::Open,
+            decisions: Vec::new(),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            updated_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 1,
+        }
+    }
+
+    fn decision(kind: ApproverKind, id: &str, approved: bool) -> GateDecisionEntry {
+        GateDecisionEntry {
+            approved,
+            approver: Approver {
+                kind,
+                id: id.to_string(),
+                label: None,
+            },
+            reason: None,
+            evidence: Vec::new(),
+            decided_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 2,
+        }
+    }
+
+    #[test]
+    fn scope_matches_subject_id_field() {
+        let scope = gate(GateKind::ManualApproval, 1).scope;
+        assert!(scope.targets(&json!({ "workspace_id": "w1", "task_id": "t1" })));
+        assert!(!scope.targets(&json!({ "workspace_id": "w1", "task_id": "t2" })));
+        assert!(!scope.targets(&json!({ "workspace_id": "w1" })));
+    }
+
+    #[test]
+    fn manual_gates_count_distinct_humans_only() {
+        let mut gate = gate(GateKind::ManualApproval, 2);
+        gate.decisions
+            .push(decision(ApproverKind::Human, "alice", true));
+        gate.decisions
+            .push(decision(ApproverKind::AiScorer, "scorer", true));
+        assert_eq!(gate.approval_count(), 1);
+        gate.decisions
+            .push(decision(ApproverKind::Human, "bob", true));
+        assert_eq!(gate.approval_count(), 2);
+        assert!(gate.has_decided(&decision(ApproverKind::Human, "bob", true).approver));
+    }
+
+    #[test]
+    fn delay_gates_open_after_delay_unless_rejected() {
+        let created = parse_rfc3339("2020-01-01T00:00:00Z").expect("time");
+        let mut gate = gate(GateKind::Delay, 0);
+        assert!(!gate.is_satisfied(created + Duration::seconds(59)));
+        assert!(gate.is_satisfied(created + Duration::seconds(60)));
+        gate.status = GateStatus::Rejected;
+        assert!(!gate.is_satisfied(created + Duration::seconds(60)));
+
+        let mut manual = self::gate(GateKind::ManualApproval, 1);
+        assert!(!manual.is_satisfied(created));
+        manual.status = GateStatus::Approved;
+        assert!(manual.is_satisfied(created));
+    }
+}
@@ -0,0 +1,331 @@
+use crate::{SandboxCapabilities, SandboxLimits, WasmModuleRef};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const EVENT_HOOK_FAILED: &str = "hook.failed";
+pub const EVENT_HOOK_INPUT_RECORDED: &str = "hook.input_recorded";
+
+/// Replacement written over redacted text when a hook does not name its own.
+pub const DEFAULT_REDACTION: &str = "[REDACTED]";
+
+/// Points in an action's life where the hook chain runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookPhase {
+    /// Every state-changing command, before it is planned.
+    PreCommand,
+    /// Outgoing messages, before delivery.
+    PreSend,
+    /// Agent output, before it is accepted.
+    PostReceive,
+    /// Tool arguments, before the tool runs.
+    PreTool,
+    /// Tool results, before they are returned.
+    PostTool,
+}
+
+impl HookPhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookPhase::PreCommand => "pre_command",
+            HookPhase::PreSend => "pre_send",
+            HookPhase::PostReceive => "post_receive",
+            HookPhase::PreTool => "pre_tool",
+            HookPhase::PostTool => "post_tool",
+        }
+    }
+}
+
+impl fmt::Display for HookPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The ordered hooks a daemon runs, loaded from a `hook_chain` document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookChain {
+    pub api_version: u32,
+    #[serde(default)]
+    pub hooks: Vec<HookSpec>,
+}
+
+impl HookChain {
+    /// Hooks registered for `phase` that apply to `command_type`, in chain order.
+    pub fn for_phase<'a>(
+        &'a self,
+        phase: HookPhase,
+        command_type: &'a str,
+    ) -> impl Iterator<Item = &'a HookSpec> + 'a {
+        self.hooks
+            .iter()
+            .filter(move |hook| hook.applies_to(phase, command_type))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookSpec {
+    pub id: String,
+    pub version: String,
+    pub phases: Vec<HookPhase>,
+    /// Restricts the hook to these command types; empty matches every command.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command_types: Vec<String>,
+    pub action: HookAction,
+}
+
+impl HookSpec {
+    pub fn applies_to(&self, phase: HookPhase, command_type: &str) -> bool {
+        self.phases.contains(&phase)
+            && (self.command_types.is_empty()
+                || self.command_types.iter().any(|ty| ty == command_type))
+    }
+}
+
+/// What a hook does to the value passing through its phase.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum HookAction {
+    /// Rejects the action outright.
+    Block { reason: String },
+    /// Holds the action until an approval gate covering it is approved.
+    RequireApproval { reason: String },
+    /// Replaces the value at `pointer` (RFC 6901), creating the last key if needed.
+    Rewrite { pointer: String, value: Value },
+    /// Replaces every occurrence of each needle in the string at `pointer`.
+    Redact {
+        pointer: String,
+        needles: Vec<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replacement: Option<String>,
+    },
+    /// Drops entries of the string array at `pointer` that are not in `allow`.
+    Clamp { pointer: String, allow: Vec<String> },
+    /// Runs a sandboxed module that returns a [`crate::WasmHookOutput`].
+    Wasm {
+        module: WasmModuleRef,
+        #[serde(default)]
+        capabilities: SandboxCapabilities,
+        #[serde(default)]
+        limits: SandboxLimits,
+    },
+}
+
+/// The verdict of one hook; rewrites are applied to the target in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookDecision {
+    Continue,
+    Block { reason: String },
+    RequireApproval { reason: String },
+}
+
+impl HookAction {
+    /// Applies the action to `target`. An `Err` is a hook failure, not a verdict.
+    ///
+    /// `Wasm` actions need the daemon's sandbox, so applying one here is a failure.
+    pub fn apply(&self, target: &mut Value) -> Result<HookDecision, String> {
+        match self {
+            HookAction::Wasm { module, .. } => {
+                Err(format!("{} must run in the sandbox", module.path))
+            }
+            HookAction::Block { reason } => Ok(HookDecision::Block {
+                reason: reason.clone(),
+            }),
+            HookAction::RequireApproval { reason } => Ok(HookDecision::RequireApproval {
+                reason: reason.clone(),
+            }),
+            HookAction::Rewrite { pointer, value } => {
+                set_pointer(target, pointer, value.clone())?;
+                Ok(HookDecision::Continue)
+            }
+            HookAction::Redact {
+                pointer,
+                needles,
+                replacement,
+            } => {
+                let Some(slot) = target.pointer_mut(pointer) else {
+                    return Ok(HookDecision::Continue);
+                };
+                let Value::String(text) = slot else {
+                    return Err(format!("{pointer} is not a string"));
+                };
+                let replacement = replacement.as_deref().unwrap_or(DEFAULT_REDACTION);
+                for needle in needles.iter().filter(|needle| !needle.is_empty()) {
+                    *text = text.replace(needle.as_str(), replacement);
+                }
+                Ok(HookDecision::Continue)
+            }
+            HookAction::Clamp { pointer, allow } => {
+                let Some(slot) = target.pointer_mut(pointer) else {
+                    return Ok(HookDecision::Continue);
+                };
+                let Value::Array(entries) = slot else {
+                    return Err(format!("{pointer} is not an array"));
+                };
+                if let Some(entry) = entries.iter().find(|entry| !entry.is_string()) {
+                    return Err(format!("{pointer} holds non-string entry {entry}"));
+                }
+                entries.retain(|entry| {
+                    entry
+                        .as_str()
+                        .is_some_and(|entry| allow.iter().any(|allowed| allowed == entry))
+                });
+                Ok(HookDecision::Continue)
+            }
+        }
+    }
+}
+
+fn set_pointer(target: &mut Value, pointer: &str, value: Value) -> Result<(), String> {
+    if pointer.is_empty() {
+        *target = value;
+        return Ok(());
+    }
+    let Some((parent, last)) = pointer.rsplit_once('/') else {
+        return Err(format!("{pointer} is not a JSON pointer"));
+    };
+    let key = last.replace("~1", "/").replace("~0", "~");
+    match target.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(key, value);
+            Ok(())
+        }
+        Some(Value::Array(items)) => {
+            let slot = key
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get_mut(index))
+                .ok_or_else(|| format!("{pointer} is out of range"))?;
+            *slot = value;
+            Ok(())
+        }
+        Some(_) => Err(format!("{parent} is not an object or array")),
+        None => Err(format!("{parent} does not exist")),
+    }
+}
+
+/// Recorded whenever a hook errors; `fail_open` says whether the action went ahead anyway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookFailedPayload {
+    pub hook_id: String,
+    pub hook_version: String,
+    pub phase: HookPhase,
+    pub command_type: String,
+    pub error: String,
+    pub fail_open: bool,
+}
+
+/// The value a phase's hooks were given, recorded when the daemon runs with input recording
+/// so the chain can be replayed offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HookInputRecordedPayload {
+    pub phase: HookPhase,
+    pub command_type: String,
+    pub input: Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(phases: Vec<HookPhase>, command_types: Vec<&str>) -> HookSpec {
+        HookSpec {
+            id: "org.test".to_string(),
+            version: "1".to_string(),
+            phases,
+            command_types: command_types.into_iter().map(str::to_string).collect(),
+            action: HookAction::Block {
+                reason: "no".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn hooks_match_phase_and_command_type() {
+        let any = spec(vec![HookPhase::PreCommand], vec![]);
+        assert!(any.applies_to(HookPhase::PreCommand, "task.create"));
+        assert!(!any.applies_to(HookPhase::PreSend, "task.create"));
+
+        let scoped = spec(
+            vec![HookPhase::PreCommand, HookPhase::PreSend],
+            vec!["message.send"],
+        );
+        assert!(scoped.applies_to(HookPhase::PreSend, "message.send"));
+        assert!(!scoped.applies_to(HookPhase::PreCommand, "task.create"));
+    }
+
+    #[test]
+    fn actions_rewrite_redact_and_clamp_in_place() {
+        let mut target = json!({
+            "body": {"text": "token=abc123 and abc123"},
+            "scopes": ["fs.read", "fs.write", "net"],
+        });
+
+        HookAction::Redact {
+            pointer: "/body/text".to_string(),
+            needles: vec!["abc123".to_string()],
+            replacement: None,
+        }
+        .apply(&mut target)
+        .expect("redact");
+        assert_eq!(target["body"]["text"], "token=[REDACTED] and [REDACTED]");
+
+        HookAction::Clamp {
+            pointer: "/scopes".to_string(),
+            allow: vec!["fs.read".to_string()],
+        }
+        .apply(&mut target)
+        .expect("clamp");
+        assert_eq!(target["scopes"], json!(["fs.read"]));
+
+        HookAction::Rewrite {
+            pointer: "/body/label".to_string(),
+            value: json!("reviewed"),
+        }
+        .apply(&mut target)
+        .expect("rewrite");
+        assert_eq!(target["body"]["label"], "reviewed");
+
+        // Optional fields that are absent leave nothing to redact or clamp.
+        let decision = HookAction::Clamp {
+            pointer: "/missing".to_string(),
+            allow: vec![],
+        }
+        .apply(&mut target)
+        .expect("clamp missing");
+        assert_eq!(decision, HookDecision::Continue);
+    }
+
+    #[test]
+    fn malformed_targets_are_failures_not_verdicts() {
+        let mut target = json!({"body": 7, "scopes": [1]});
+        let redact = HookAction::Redact {
+            pointer: "/body".to_string(),
+            needles: vec!["x".to_string()],
+            replacement: None,
+        };
+        assert!(redact.apply(&mut target).is_err());
+
+        let clamp = HookAction::Clamp {
+            pointer: "/scopes".to_string(),
+            allow: vec![],
+        };
+        assert!(clamp.apply(&mut target).is_err());
+
+        let rewrite = HookAction::Rewrite {
+            pointer: "/missing/label".to_string(),
+            value: json!(1),
+        };
+        assert_eq!(
+            rewrite.apply(&mut target),
+            Err("/missing does not exist".to_string())
+        );
+        assert_eq!(target, json!({"body": 7, "scopes": [1]}));
+    }
+}
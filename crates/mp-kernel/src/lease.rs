@@ -0,0 +1,147 @@
+use crate::{Actor, PolicyDecision};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const COMMAND_LEASE_MINT: &str = "lease.mint";
+pub const COMMAND_LEASE_REVOKE: &str = "lease.revoke";
+
+pub const EVENT_LEASE_MINTED: &str = "lease.minted";
+pub const EVENT_LEASE_USED: &str = "lease.used";
+pub const EVENT_LEASE_REVOKED: &str = "lease.revoked";
+pub const EVENT_LEASE_EXPIRED: &str = "lease.expired";
+
+/// Longest TTL a lease may be minted with; exceptions are meant to lapse on their own.
+pub const LEASE_MAX_TTL_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// Actor kinds that may mint leases; sessions can only hold them.
+pub const LEASE_MINTER_KINDS: &[&str] = &["system", "token", "user"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaseStatus {
+    Active,
+    Revoked,
+    /// Outlived its TTL; recorded by the daemon's scheduler.
+    Expired,
+}
+
+impl LeaseStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LeaseStatus::Active => "active",
+            LeaseStatus::Revoked => "revoked",
+            LeaseStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "active" => Some(LeaseStatus::Active),
+            "revoked" => Some(LeaseStatus::Revoked),
+            "expired" => Some(LeaseStatus::Expired),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LeaseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The actor a lease is issued to; only commands submitted as this actor may present it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseHolder {
+    pub kind: String,
+    pub id: String,
+}
+
+impl LeaseHolder {
+    pub fn holds(&self, actor: &Actor) -> bool {
+        self.kind == actor.kind && self.id == actor.id
+    }
+}
+
+/// What a lease lets its holder do that policy would otherwise deny.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseScope {
+    /// Command types: exact, `*`, or a `family.*` prefix, as in policy rules.
+    pub actions: Vec<String>,
+    /// Policy request attributes (e.g. `object.id`) that must take one of the listed values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub attributes: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseMintPayload {
+    pub workspace_id: String,
+    pub holder: LeaseHolder,
+    pub scope: LeaseScope,
+    pub ttl_ms: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseRevokePayload {
+    pub workspace_id: String,
+    pub lease_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseMintedPayload {
+    pub holder: LeaseHolder,
+    pub scope: LeaseScope,
+    pub ttl_ms: u64,
+    pub expires_at: String,
+    pub reason: String,
+}
+
+/// Recorded alongside the events of a command that presented the lease.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseUsedPayload {
+    pub command_type: String,
+    /// The default deny the lease overrode; absent when policy allowed the command anyway.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overrode: Option<PolicyDecision>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseRevokedPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseExpiredPayload {
+    pub expires_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeaseEntry {
+    pub workspace_id: String,
+    pub lease_id: String,
+    pub holder: LeaseHolder,
+    pub scope: LeaseScope,
+    pub reason: String,
+    /// `<kind>:<id>` of the actor that minted the lease.
+    pub minted_by: String,
+    pub expires_at: String,
+    pub status: LeaseStatus,
+    pub use_count: u64,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
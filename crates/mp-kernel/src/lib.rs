@@ -3,16 +3,69 @@ use std::fmt;
 use time::OffsetDateTime;
 use uuid::Uuid;
 
+mod artifact;
+mod blueprint;
+mod board;
+mod capsule;
+mod gate;
+mod hook;
+mod lease;
+mod message;
+mod pipeline;
+mod policy;
+mod retry;
+mod sandbox;
+mod secret;
+mod skill;
+mod task;
+mod tool;
+mod worktree;
+
+pub use artifact::*;
+pub use blueprint::*;
+pub use board::*;
+pub use capsule::*;
+pub use gate::*;
+pub use hook::*;
+pub use lease::*;
+pub use message::*;
+pub use pipeline::*;
+pub use policy::*;
+pub use retry::*;
+pub use sandbox::*;
+pub use secret::*;
+pub use skill::*;
+pub use task::*;
+pub use tool::*;
+pub use worktree::*;
+
 pub const COMMAND_DAEMON_PING: &str = "daemon.ping";
 pub const COMMAND_WORKSPACE_CREATE: &str = "workspace.create";
+pub const COMMAND_WORKSPACE_RENAME: &str = "workspace.rename";
+pub const COMMAND_WORKSPACE_ARCHIVE: &str = "workspace.archive";
+pub const COMMAND_WORKSPACE_RESTORE: &str = "workspace.restore";
 pub const COMMAND_WORKSPACE_LIST: &str = "workspace.list";
 pub const COMMAND_PROJECT_CREATE: &str = "project.create";
+pub const COMMAND_PROJECT_RENAME: &str = "project.rename";
+pub const COMMAND_PROJECT_ARCHIVE: &str = "project.archive";
+pub const COMMAND_PROJECT_RESTORE: &str = "project.restore";
 pub const COMMAND_PROJECT_LIST: &str = "project.list";
+pub const COMMAND_SESSION_SPAWN: &str = "session.spawn";
+pub const COMMAND_SESSION_FORK: &str = "session.fork";
+pub const COMMAND_SESSION_LIST: &str = "session.list";
 pub const COMMAND_EVENTS_READ_FROM: &str = "events.read_from";
 pub const COMMAND_EVENTS_SUBSCRIBE: &str = "events.subscribe";
 
 pub const EVENT_WORKSPACE_CREATED: &str = "workspace.created";
+pub const EVENT_WORKSPACE_RENAMED: &str = "workspace.renamed";
+pub const EVENT_WORKSPACE_ARCHIVED: &str = "workspace.archived";
+pub const EVENT_WORKSPACE_RESTORED: &str = "workspace.restored";
 pub const EVENT_PROJECT_CREATED: &str = "project.created";
+pub const EVENT_PROJECT_RENAMED: &str = "project.renamed";
+pub const EVENT_PROJECT_ARCHIVED: &str = "project.archived";
+pub const EVENT_PROJECT_RESTORED: &str = "project.restored";
+pub const EVENT_SESSION_SPAWNED: &str = "session.spawned";
+pub const EVENT_SESSION_FORKED: &str = "session.forked";
 pub const EVENT_COMMAND_REJECTED: &str = "command.rejected";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -26,9 +79,59 @@ pub fn command_kind(command_type: &str) -> Option<CommandKind> {
         COMMAND_DAEMON_PING
         | COMMAND_WORKSPACE_LIST
         | COMMAND_PROJECT_LIST
+        | COMMAND_SESSION_LIST
+        | COMMAND_TASK_LIST
         | COMMAND_EVENTS_READ_FROM
         | COMMAND_EVENTS_SUBSCRIBE => Some(CommandKind::ReadOnly),
-        COMMAND_WORKSPACE_CREATE | COMMAND_PROJECT_CREATE => Some(CommandKind::StateChanging),
+        COMMAND_WORKSPACE_CREATE
+        | COMMAND_WORKSPACE_RENAME
+        | COMMAND_WORKSPACE_ARCHIVE
+        | COMMAND_WORKSPACE_RESTORE
+        | COMMAND_PROJECT_CREATE
+        | COMMAND_PROJECT_RENAME
+        | COMMAND_PROJECT_ARCHIVE
+        | COMMAND_PROJECT_RESTORE
+        | COMMAND_SESSION_SPAWN
+        | COMMAND_SESSION_FORK
+        | COMMAND_TASK_CREATE
+        | COMMAND_TASK_START
+        | COMMAND_TASK_SUCCEED
+        | COMMAND_TASK_FAIL
+        | COMMAND_TASK_CANCEL
+        | COMMAND_TASK_PAUSE
+        | COMMAND_TASK_RESUME
+        | COMMAND_TASK_AWAIT_INPUT
+        | COMMAND_PIPELINE_TEMPLATE_DEFINE
+        | COMMAND_PIPELINE_BIND
+        | COMMAND_STAGE_TRANSITION
+        | COMMAND_GATE_DEFINE
+        | COMMAND_GATE_APPROVE
+        | COMMAND_GATE_REJECT
+        | COMMAND_WORKTREE_REGISTER
+        | COMMAND_WORKTREE_ATTACH
+        | COMMAND_WORKTREE_DETACH
+        | COMMAND_WORKTREE_LOCK_ACQUIRE
+        | COMMAND_WORKTREE_LOCK_RELEASE
+        | COMMAND_BOARD_GROUP_CREATE
+        | COMMAND_BOARD_COMMENT_ADD
+        | COMMAND_BOARD_NODE_MOVE
+        | COMMAND_CAPSULE_WRITE
+        | COMMAND_MESSAGE_SEND
+        | COMMAND_ARTIFACT_PUT
+        | COMMAND_BLUEPRINT_SYNC
+        | COMMAND_TOOL_REQUEST
+        | COMMAND_TOOL_COMPLETE
+        | COMMAND_SKILL_SYNC
+        | COMMAND_SKILL_APPROVE
+        | COMMAND_LEASE_MINT
+        | COMMAND_LEASE_REVOKE
+        | COMMAND_SECRET_SET
+        | COMMAND_SECRET_ROTATE
+        | COMMAND_SECRET_DELETE
+        | COMMAND_VAULT_INIT
+        | COMMAND_VAULT_UNLOCK
+        | COMMAND_VAULT_LOCK
+        | COMMAND_SECRET_EXEC => Some(CommandKind::StateChanging),
         _ => None,
     }
 }
@@ -53,6 +156,10 @@ pub enum ErrorCode {
     NotFound,
     /// Action was denied by policy or safety mode.
     PolicyDenied,
+    /// A lock needed by the command is held by someone else.
+    LockConflict,
+    /// Content exceeds a kernel-enforced budget.
+    BudgetExceeded,
     /// Unclassified error condition.
     Unknown,
     /// Internal server error (reserved for future use).
@@ -70,6 +177,8 @@ impl fmt::Display for ErrorCode {
             ErrorCode::Unauthorized => "unauthorized",
             ErrorCode::NotFound => "not_found",
             ErrorCode::PolicyDenied => "policy_denied",
+            ErrorCode::LockConflict => "lock_conflict",
+            ErrorCode::BudgetExceeded => "budget_exceeded",
             ErrorCode::Unknown => "unknown",
             ErrorCode::Internal => "internal",
         };
@@ -83,6 +192,10 @@ pub struct Actor {
     pub kind: String,
     pub id: String,
     pub label: Option<String>,
+    /// Set by the daemon when the client named this actor rather than its credentials
+    /// establishing it; a client cannot clear it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub claimed: bool,
 }
 
 impl Actor {
@@ -91,11 +204,23 @@ impl Actor {
             kind: "system".to_string(),
             id: "system".to_string(),
             label: Some("mpd".to_string()),
+            claimed: false,
+        }
+    }
+
+    /// Whoever holds the daemon token. Commands that name no actor run as it; only
+    /// the daemon's own events are recorded as `system`.
+    pub fn token_holder() -> Self {
+        Self {
+            kind: "token".to_string(),
+            id: "daemon".to_string(),
+            label: Some("daemon token holder".to_string()),
+            claimed: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct Subject {
     pub kind: String,
@@ -117,6 +242,40 @@ pub struct ProjectCreatePayload {
     pub name: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceRenamePayload {
+    pub workspace_id: String,
+    pub name: String,
+}
+
+/// Payload shared by `workspace.archive` and `workspace.restore`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorkspaceLifecyclePayload {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectRenamePayload {
+    pub workspace_id: String,
+    pub project_id: String,
+    pub name: String,
+}
+
+/// Payload shared by `project.archive` and `project.restore`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectLifecyclePayload {
+    pub workspace_id: String,
+    pub project_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct WorkspaceCreatedPayload {
@@ -131,6 +290,97 @@ pub struct ProjectCreatedPayload {
     pub name: String,
 }
 
+/// Payload shared by `workspace.renamed` and `project.renamed`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RenamedPayload {
+    pub previous_name: String,
+    pub name: String,
+}
+
+/// Payload shared by the `*.archived` and `*.restored` events.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LifecycleChangedPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// How a forked session relates to the worktree of the session it was forked from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkMode {
+    /// New worktree on a new branch.
+    Default,
+    /// New worktree on the same branch.
+    SameBranch,
+    /// New session without a worktree.
+    PlanningOnly,
+}
+
+impl fmt::Display for ForkMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            ForkMode::Default => "default",
+            ForkMode::SameBranch => "same_branch",
+            ForkMode::PlanningOnly => "planning_only",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionSpawnPayload {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Runs the session from this blueprint; its pins must match what the daemon loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blueprint: Option<BlueprintRef>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionForkPayload {
+    pub workspace_id: String,
+    pub session_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<ForkMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
+/// Lineage recorded for a new session; the session id itself is the event subject.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionSpawnedPayload {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+    pub root_session_id: String,
+    pub lineage_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blueprint: Option<SessionBlueprint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionForkedPayload {
+    pub forked_from_session_id: String,
+    pub mode: ForkMode,
+    pub parent_session_id: String,
+    pub root_session_id: String,
+    pub lineage_path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct CommandRejectedPayload {
@@ -156,6 +406,9 @@ pub struct WorkspaceListEntry {
     pub name: String,
     pub root_path: String,
     pub created_at: String,
+    /// Set while the workspace is archived; archived entries are hidden from lists by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
     pub seq_global: i64,
 }
 
@@ -166,6 +419,32 @@ pub struct ProjectListEntry {
     pub workspace_id: String,
     pub name: String,
     pub created_at: String,
+    /// Set while the project is archived; archived entries are hidden from lists by default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+    pub seq_global: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionListEntry {
+    pub session_id: String,
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_session_id: Option<String>,
+    pub root_session_id: String,
+    pub lineage_path: String,
+    /// Number of ancestors between this session and its root (roots have depth 0).
+    pub depth: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forked_from_session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fork_mode: Option<ForkMode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_at: String,
     pub seq_global: i64,
 }
 
@@ -183,12 +462,33 @@ pub fn new_uuid() -> String {
     Uuid::now_v7().to_string()
 }
 
+/// Builds the lineage path of a session from its parent's path (`/root/child/...`).
+pub fn lineage_path(parent_path: Option<&str>, session_id: &str) -> String {
+    match parent_path {
+        Some(parent) => format!("{}/{session_id}", parent.trim_end_matches('/')),
+        None => format!("/{session_id}"),
+    }
+}
+
+/// Number of ancestors encoded in a lineage path.
+pub fn lineage_depth(lineage_path: &str) -> i64 {
+    let segments = lineage_path.split('/').filter(|s| !s.is_empty()).count() as i64;
+    (segments - 1).max(0)
+}
+
 pub fn now_rfc3339() -> String {
-    OffsetDateTime::now_utc()
-        .format(&time::format_description::well_known::Rfc3339)
+    format_rfc3339(OffsetDateTime::now_utc())
+}
+
+pub fn format_rfc3339(at: OffsetDateTime) -> String {
+    at.format(&time::format_description::well_known::Rfc3339)
         .unwrap_or_else(|_| "1970-01-01T00:00:00Z".to_string())
 }
 
+pub fn parse_rfc3339(value: &str) -> Option<OffsetDateTime> {
+    OffsetDateTime::parse(value, &time::format_description::well_known::Rfc3339).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -203,9 +503,37 @@ mod tests {
             command_kind(COMMAND_WORKSPACE_CREATE),
             Some(CommandKind::StateChanging)
         );
+        assert_eq!(
+            command_kind(COMMAND_SESSION_FORK),
+            Some(CommandKind::StateChanging)
+        );
+        assert_eq!(
+            command_kind(COMMAND_SESSION_LIST),
+            Some(CommandKind::ReadOnly)
+        );
+        assert_eq!(
+            command_kind(COMMAND_PROJECT_ARCHIVE),
+            Some(CommandKind::StateChanging)
+        );
         assert_eq!(command_kind("unknown.command"), None);
     }
 
+    #[test]
+    fn lineage_path_appends_to_parent() {
+        assert_eq!(lineage_path(None, "s1"), "/s1");
+        assert_eq!(lineage_path(Some("/s1"), "s2"), "/s1/s2");
+        assert_eq!(lineage_path(Some("/s1/s2/"), "s3"), "/s1/s2/s3");
+        assert_eq!(lineage_depth("/s1"), 0);
+        assert_eq!(lineage_depth("/s1/s2/s3"), 2);
+    }
+
+    #[test]
+    fn session_fork_payload_rejects_unknown_mode() {
+        let json = r#"{"workspace_id":"w1","session_id":"s1","mode":"sideways"}"#;
+        let result: Result<SessionForkPayload, _> = serde_json::from_str(json);
+        assert!(result.is_err());
+    }
+
     #[test]
     fn error_code_display_matches_wire_format() {
         assert_eq!(ErrorCode::InvalidSchema.to_string(), "invalid_schema");
@@ -222,6 +550,8 @@ mod tests {
         assert_eq!(ErrorCode::Unauthorized.to_string(), "unauthorized");
         assert_eq!(ErrorCode::NotFound.to_string(), "not_found");
         assert_eq!(ErrorCode::PolicyDenied.to_string(), "policy_denied");
+        assert_eq!(ErrorCode::LockConflict.to_string(), "lock_conflict");
+        assert_eq!(ErrorCode::BudgetExceeded.to_string(), "budget_exceeded");
         assert_eq!(ErrorCode::Unknown.to_string(), "unknown");
         assert_eq!(ErrorCode::Internal.to_string(), "internal");
     }
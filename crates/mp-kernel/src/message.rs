@@ -0,0 +1,322 @@
+use crate::SessionListEntry;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const COMMAND_MESSAGE_SEND: &str = "message.send";
+
+pub const EVENT_MESSAGE_SENT: &str = "message.sent";
+pub const EVENT_MESSAGE_DELIVERED: &str = "message.delivered";
+
+/// Core message types; anything else must be a namespaced `x.<org>.<name>` extension.
+pub const MESSAGE_TYPES: [&str; 9] = [
+    "status_capsule",
+    "plan_capsule",
+    "decision_capsule",
+    "artifact_manifest",
+    "risk_flag",
+    "human_comment",
+    "tool_intent",
+    "tool_result",
+    "policy_notice",
+];
+
+pub fn is_valid_message_type(value: &str) -> bool {
+    if MESSAGE_TYPES.contains(&value) {
+        return true;
+    }
+    let mut parts = value.splitn(3, '.');
+    let (Some("x"), Some(org), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let valid = |segment: &str| {
+        !segment.is_empty()
+            && segment
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-')
+    };
+    valid(org) && valid(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MessageScope {
+    Parent,
+    Children,
+    Siblings,
+    Descendants,
+    Project,
+    Workspace,
+}
+
+impl MessageScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MessageScope::Parent => "PARENT",
+            MessageScope::Children => "CHILDREN",
+            MessageScope::Siblings => "SIBLINGS",
+            MessageScope::Descendants => "DESCENDANTS",
+            MessageScope::Project => "PROJECT",
+            MessageScope::Workspace => "WORKSPACE",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "PARENT" => Some(MessageScope::Parent),
+            "CHILDREN" => Some(MessageScope::Children),
+            "SIBLINGS" => Some(MessageScope::Siblings),
+            "DESCENDANTS" => Some(MessageScope::Descendants),
+            "PROJECT" => Some(MessageScope::Project),
+            "WORKSPACE" => Some(MessageScope::Workspace),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for MessageScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How a recipient session relates to the sender in the lineage tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineageRelation {
+    Parent,
+    Child,
+    Sibling,
+    /// An ancestor above the parent.
+    Ancestor,
+    /// A descendant below the children.
+    Descendant,
+    /// Same root, but not on the sender's own branch and not a sibling.
+    Cousin,
+    /// Different root.
+    Unrelated,
+}
+
+impl LineageRelation {
+    /// Default rules: cousins are denied, and cross-tree traffic needs a group channel.
+    pub fn allowed_by_default(&self) -> bool {
+        !matches!(self, LineageRelation::Cousin | LineageRelation::Unrelated)
+    }
+}
+
+/// `None` when `to` is the sender itself.
+pub fn lineage_relation(from: &SessionListEntry, to: &SessionListEntry) -> Option<LineageRelation> {
+    if from.session_id == to.session_id {
+        return None;
+    }
+    let relation = if from.parent_session_id.as_deref() == Some(to.session_id.as_str()) {
+        LineageRelation::Parent
+    } else if to.parent_session_id.as_deref() == Some(from.session_id.as_str()) {
+        LineageRelation::Child
+    } else if from.parent_session_id.is_some() && from.parent_session_id == to.parent_session_id {
+        LineageRelation::Sibling
+    } else if is_path_ancestor(&to.lineage_path, &from.lineage_path) {
+        LineageRelation::Ancestor
+    } else if is_path_ancestor(&from.lineage_path, &to.lineage_path) {
+        LineageRelation::Descendant
+    } else if from.root_session_id == to.root_session_id {
+        LineageRelation::Cousin
+    } else {
+        LineageRelation::Unrelated
+    };
+    Some(relation)
+}
+
+fn is_path_ancestor(ancestor: &str, path: &str) -> bool {
+    path.strip_prefix(ancestor)
+        .is_some_and(|rest| rest.starts_with('/'))
+}
+
+/// Sessions a scope resolves to, split by whether the default lineage rules allow delivery.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageRecipients {
+    pub delivered: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+/// Resolves `scope` for `sender` against the workspace's sessions, in the order given.
+pub fn message_recipients(
+    sender: &SessionListEntry,
+    sessions: &[SessionListEntry],
+    scope: MessageScope,
+) -> MessageRecipients {
+    let mut recipients = MessageRecipients::default();
+    for session in sessions {
+        let Some(relation) = lineage_relation(sender, session) else {
+            continue;
+        };
+        let in_scope = match scope {
+            MessageScope::Parent => relation == LineageRelation::Parent,
+            MessageScope::Children => relation == LineageRelation::Child,
+            MessageScope::Siblings => relation == LineageRelation::Sibling,
+            MessageScope::Descendants => {
+                matches!(
+                    relation,
+                    LineageRelation::Child | LineageRelation::Descendant
+                )
+            }
+            MessageScope::Project => {
+                sender.project_id.is_some() && session.project_id == sender.project_id
+            }
+            MessageScope::Workspace => true,
+        };
+        if !in_scope {
+            continue;
+        }
+        if relation.allowed_by_default() {
+            recipients.delivered.push(session.session_id.clone());
+        } else {
+            recipients.denied.push(session.session_id.clone());
+        }
+    }
+    recipients
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageSendPayload {
+    pub workspace_id: String,
+    pub from_session_id: String,
+    pub scope: MessageScope,
+    /// A [`MESSAGE_TYPES`] entry or an `x.<org>.<name>` extension.
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub body: Value,
+}
+
+/// Recorded on the sender's session; lists who received the message and who was denied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageSentPayload {
+    pub message_id: String,
+    pub scope: MessageScope,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub recipient_session_ids: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub denied_session_ids: Vec<String>,
+}
+
+/// Recorded on each recipient's session, so it lands on that session's stream.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MessageDeliveredPayload {
+    pub message_id: String,
+    pub from_session_id: String,
+    pub scope: MessageScope,
+    #[serde(rename = "type")]
+    pub message_type: String,
+    pub body: Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(
+        id: &str,
+        parent: Option<&str>,
+        path: &str,
+        project: Option<&str>,
+    ) -> SessionListEntry {
+        let root = path.split('/').nth(1).unwrap_or(id).to_string();
+        SessionListEntry {
+            session_id: id.to_string(),
+            workspace_id: "w1".to_string(),
+            project_id: project.map(str::to_string),
+            parent_session_id: parent.map(str::to_string),
+            root_session_id: root,
+            lineage_path: path.to_string(),
+            depth: path.matches('/').count() as i64 - 1,
+            forked_from_session_id: None,
+            fork_mode: None,
+            label: None,
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            seq_global: 1,
+        }
+    }
+
+    /// r -> (a -> (a1, a2), b -> b1), plus an unrelated root z.
+    fn tree() -> Vec<SessionListEntry> {
+        vec![
+            session("r", None, "/r", Some("p1")),
+            session("a", Some("r"), "/r/a", Some("p1")),
+            session("b", Some("r"), "/r/b", Some("p2")),
+            session("a1", Some("a"), "/r/a/a1", Some("p1")),
+            session("a2", Some("a"), "/r/a/a2", Some("p1")),
+            session("b1", Some("b"), "/r/b/b1", Some("p1")),
+            session("z", None, "/z", Some("p1")),
+        ]
+    }
+
+    fn resolve(sender: &str, scope: MessageScope) -> MessageRecipients {
+        let sessions = tree();
+        let sender = sessions
+            .iter()
+            .find(|session| session.session_id == sender)
+            .expect("sender");
+        message_recipients(sender, &sessions, scope)
+    }
+
+    #[test]
+    fn lineage_relation_classifies_tree() {
+        let sessions = tree();
+        let a1 = &sessions[3];
+        let relations: Vec<_> = sessions
+            .iter()
+            .map(|session| lineage_relation(a1, session))
+            .collect();
+        assert_eq!(
+            relations,
+            vec![
+                Some(LineageRelation::Ancestor),
+                Some(LineageRelation::Parent),
+                Some(LineageRelation::Cousin),
+                None,
+                Some(LineageRelation::Sibling),
+                Some(LineageRelation::Cousin),
+                Some(LineageRelation::Unrelated),
+            ]
+        );
+        assert_eq!(
+            lineage_relation(&sessions[0], &sessions[5]),
+            Some(LineageRelation::Descendant)
+        );
+    }
+
+    #[test]
+    fn message_recipients_follow_scope_and_deny_cousins() {
+        assert_eq!(resolve("a1", MessageScope::Parent).delivered, vec!["a"]);
+        assert_eq!(
+            resolve("a", MessageScope::Children).delivered,
+            vec!["a1", "a2"]
+        );
+        assert_eq!(resolve("a1", MessageScope::Siblings).delivered, vec!["a2"]);
+        assert!(resolve("r", MessageScope::Siblings).delivered.is_empty());
+        assert_eq!(
+            resolve("r", MessageScope::Descendants).delivered,
+            vec!["a", "b", "a1", "a2", "b1"]
+        );
+        let project = resolve("a1", MessageScope::Project);
+        assert_eq!(project.delivered, vec!["r", "a", "a2"]);
+        assert_eq!(project.denied, vec!["b1", "z"]);
+        let workspace = resolve("a1", MessageScope::Workspace);
+        assert_eq!(workspace.denied, vec!["b", "b1", "z"]);
+    }
+
+    #[test]
+    fn message_types_accept_core_and_namespaced_extensions() {
+        assert!(is_valid_message_type("status_capsule"));
+        assert!(is_valid_message_type("x.company.custom_event"));
+        assert!(!is_valid_message_type("x.company"));
+        assert!(!is_valid_message_type("x..name"));
+        assert!(!is_valid_message_type("gossip"));
+        for scope in [MessageScope::Parent, MessageScope::Workspace] {
+            assert_eq!(MessageScope::parse(scope.as_str()), Some(scope));
+        }
+    }
+}
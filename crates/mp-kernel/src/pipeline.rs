@@ -0,0 +1,234 @@
+use crate::TaskListEntry;
+use serde::{Deserialize, Serialize};
+
+pub const COMMAND_PIPELINE_TEMPLATE_DEFINE: &str = "pipeline.template.define";
+pub const COMMAND_PIPELINE_BIND: &str = "pipeline.bind";
+pub const COMMAND_STAGE_TRANSITION: &str = "stage.transition";
+
+pub const EVENT_PIPELINE_TEMPLATE_DEFINED: &str = "pipeline.template.defined";
+pub const EVENT_PIPELINE_BOUND: &str = "pipeline.bound";
+pub const EVENT_TASK_STAGE_CHANGED: &str = "task.stage_changed";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageDefinition {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+/// Defines a template, or a new version of the template with the same name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineTemplateDefinePayload {
+    pub workspace_id: String,
+    pub name: String,
+    pub stages: Vec<StageDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineTemplateDefinedPayload {
+    pub name: String,
+    pub version: u32,
+    pub stages: Vec<StageDefinition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineBindPayload {
+    pub workspace_id: String,
+    pub project_id: String,
+    pub template_id: String,
+    /// Defaults to the latest version of the template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template_version: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineBoundPayload {
+    pub template_id: String,
+    pub template_version: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageTransitionPayload {
+    pub workspace_id: String,
+    pub task_id: String,
+    pub to_stage: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskStageChangedPayload {
+    /// Absent for the first transition of a task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from_stage: Option<String>,
+    pub to_stage: String,
+    pub template_id: String,
+    pub template_version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineTemplateEntry {
+    pub template_id: String,
+    pub workspace_id: String,
+    pub name: String,
+    pub version: u32,
+    pub stages: Vec<StageDefinition>,
+    pub created_at: String,
+    pub seq_global: i64,
+}
+
+impl PipelineTemplateEntry {
+    pub fn has_stage(&self, stage: &str) -> bool {
+        self.stages.iter().any(|s| s.name == stage)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineBindingEntry {
+    pub project_id: String,
+    pub workspace_id: String,
+    pub template_id: String,
+    pub template_version: u32,
+    pub bound_at: String,
+    pub seq_global: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageLane {
+    pub stage: String,
+    pub tasks: Vec<TaskListEntry>,
+}
+
+/// Tasks of a project grouped by the stages of its bound pipeline, in template order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PipelineStageView {
+    pub project_id: String,
+    pub template_id: String,
+    pub template_version: u32,
+    pub lanes: Vec<StageLane>,
+    /// Tasks that have not entered any stage yet.
+    pub unstaged: Vec<TaskListEntry>,
+}
+
+impl PipelineStageView {
+    pub fn build(
+        binding: &PipelineBindingEntry,
+        template: &PipelineTemplateEntry,
+        tasks: Vec<TaskListEntry>,
+    ) -> Self {
+        let mut lanes: Vec<StageLane> = template
+            .stages
+            .iter()
+            .map(|stage| StageLane {
+                stage: stage.name.clone(),
+                tasks: Vec::new(),
+            })
+            .collect();
+        let mut unstaged = Vec::new();
+        for task in tasks {
+            let lane = task
+                .current_stage
+                .as_deref()
+                .and_then(|stage| lanes.iter_mut().find(|lane| lane.stage == stage));
+            match lane {
+                Some(lane) => lane.tasks.push(task),
+                // Stages dropped by a rebind fall back to unstaged until moved again.
+                None => unstaged.push(task),
+            }
+        }
+        Self {
+            project_id: binding.project_id.clone(),
+            template_id: binding.template_id.clone(),
+            template_version: binding.template_version,
+            lanes,
+            unstaged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TaskState;
+
+    fn task(task_id: &str, stage: Option<&str>) -> TaskListEntry {
+        TaskListEntry {
+            task_id: task_id.to_string(),
+            workspace_id: "w1".to_string(),
+            project_id: Some("p1".to_string()),
+            session_id: None,
+            title: task_id.to_string(),
+            state: TaskState::Queued,
+            deadline_at: None,
+            attempt: 0,
+            retry: None,
+            timeout_ms: None,
+            attempt_started_at: None,
+            retry_at: None,
+            current_stage: stage.map(|s| s.to_string()),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            updated_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 1,
+        }
+    }
+
+    #[test]
+    fn stage_view_groups_tasks_in_template_order() {
+        let template = PipelineTemplateEntry {
+            template_id: "tpl".to_string(),
+            workspace_id: "w1".to_string(),
+            name: "default".to_string(),
+            version: 2,
+            stages: ["plan", "build", "review"]
+                .into_iter()
+                .map(|name| StageDefinition {
+                    name: name.to_string(),
+                    description: None,
+                })
+                .collect(),
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 1,
+        };
+        let binding = PipelineBindingEntry {
+            project_id: "p1".to_string(),
+            workspace_id: "w1".to_string(),
+            template_id: "tpl".to_string(),
+            template_version: 2,
+            bound_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 2,
+        };
+        let view = PipelineStageView::build(
+            &binding,
+            &template,
+            vec![
+                task("a", Some("review")),
+                task("b", None),
+                task("c", Some("plan")),
+                task("d", Some("retired")),
+            ],
+        );
+        let lanes: Vec<_> = view
+            .lanes
+            .iter()
+            .map(|lane| (lane.stage.as_str(), lane.tasks.len()))
+            .collect();
+        assert_eq!(lanes, vec![("plan", 1), ("build", 0), ("review", 1)]);
+        let unstaged: Vec<_> = view.unstaged.iter().map(|t| t.task_id.as_str()).collect();
+        assert_eq!(unstaged, vec!["b", "d"]);
+        assert!(template.has_stage("build"));
+        assert!(!template.has_stage("deploy"));
+    }
+}
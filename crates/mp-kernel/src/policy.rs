@@ -0,0 +1,92 @@
+use crate::Actor;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const EVENT_POLICY_EVALUATED: &str = "policy.evaluated";
+
+/// Actor kinds a client may claim on a command. `system` is reserved for the daemon, and
+/// `token` is what the daemon token itself authenticates as.
+pub const CLAIMABLE_ACTOR_KINDS: &[&str] = &["user", "session"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyEffect {
+    Allow,
+    Deny,
+}
+
+impl PolicyEffect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyEffect::Allow => "allow",
+            PolicyEffect::Deny => "deny",
+        }
+    }
+}
+
+impl fmt::Display for PolicyEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Outcome of evaluating the loaded policy bundles for one command.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyDecision {
+    pub effect: PolicyEffect,
+    /// Hash of the whole loaded policy set the decision was made under.
+    pub policy_hash: String,
+    /// Bundle of the deciding rule, or of the default that applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle_id: Option<String>,
+    /// Absent when no rule matched and a default applied.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule_id: Option<String>,
+    pub rationale: String,
+}
+
+/// Recorded after an allowed command's events, tying them to the policy that admitted them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyEvaluatedPayload {
+    pub command_type: String,
+    pub decision: PolicyDecision,
+}
+
+/// Asks how the loaded policy would decide a command without submitting it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyExplainRequest {
+    pub actor: Actor,
+    pub command_type: String,
+    #[serde(default)]
+    pub payload: Value,
+}
+
+/// A rule whose action, matchers and conditions all held for the request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRuleMatch {
+    pub bundle_id: String,
+    pub rule_id: String,
+    pub effect: PolicyEffect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyExplanation {
+    pub command_type: String,
+    pub actor: Actor,
+    /// Absent when no policy is loaded, in which case the command is allowed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decision: Option<PolicyDecision>,
+    /// Every applicable rule in evaluation order, including ones the decision overrode.
+    pub matched: Vec<PolicyRuleMatch>,
+    /// Attributes the rules were evaluated against.
+    pub attributes: BTreeMap<String, String>,
+}
@@ -0,0 +1,233 @@
+use crate::TaskState;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const EVENT_TASK_RETRY_SCHEDULED: &str = "task.retry_scheduled";
+pub const EVENT_TASK_TIMED_OUT: &str = "task.timed_out";
+
+/// Why an attempt failed; `RetryPolicy::retry_on` decides which classes are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    Transient,
+    Timeout,
+    Crash,
+    Permanent,
+}
+
+impl FailureClass {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureClass::Transient => "transient",
+            FailureClass::Timeout => "timeout",
+            FailureClass::Crash => "crash",
+            FailureClass::Permanent => "permanent",
+        }
+    }
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Backoff {
+    Fixed {
+        delay_ms: u64,
+    },
+    Exponential {
+        initial_ms: u64,
+        max_ms: u64,
+        #[serde(default = "default_multiplier")]
+        multiplier: u32,
+    },
+}
+
+fn default_multiplier() -> u32 {
+    2
+}
+
+impl Backoff {
+    /// Upper bound on any delay this backoff produces, jitter included.
+    pub fn cap_ms(&self) -> u64 {
+        match self {
+            Backoff::Fixed { delay_ms } => delay_ms.saturating_mul(3),
+            Backoff::Exponential { max_ms, .. } => *max_ms,
+        }
+    }
+
+    /// Delay before the attempt following failed attempt `attempt` (1-based), before jitter.
+    pub fn base_delay_ms(&self, attempt: u32) -> u64 {
+        match self {
+            Backoff::Fixed { delay_ms } => *delay_ms,
+            Backoff::Exponential {
+                initial_ms,
+                max_ms,
+                multiplier,
+            } => {
+                let exponent = attempt.saturating_sub(1);
+                let factor = u64::from(*multiplier).saturating_pow(exponent);
+                initial_ms.saturating_mul(factor).min(*max_ms)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JitterMode {
+    #[default]
+    None,
+    /// Anywhere between zero and the base delay.
+    Full,
+    /// Between the initial delay and three times the base delay, capped by the backoff.
+    Decorrelated,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub backoff: Backoff,
+    #[serde(default)]
+    pub jitter: JitterMode,
+    #[serde(default = "default_retry_on")]
+    pub retry_on: Vec<FailureClass>,
+}
+
+fn default_retry_on() -> Vec<FailureClass> {
+    vec![
+        FailureClass::Transient,
+        FailureClass::Timeout,
+        FailureClass::Crash,
+    ]
+}
+
+impl RetryPolicy {
+    /// Whether failed attempt `attempt` (1-based) with `class` earns another attempt.
+    pub fn should_retry(&self, attempt: u32, class: FailureClass) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&class)
+    }
+
+    /// Backoff delay with jitter applied.
+    ///
+    /// Jitter is derived from `seed` (normally the task id) and the attempt so the same
+    /// inputs always produce the same delay; the result is recorded on the event anyway.
+    pub fn delay_ms(&self, attempt: u32, seed: &str) -> u64 {
+        let base = self.backoff.base_delay_ms(attempt);
+        match self.jitter {
+            JitterMode::None => base,
+            JitterMode::Full => jitter_fraction(seed, attempt, base),
+            JitterMode::Decorrelated => {
+                let low = self.backoff.base_delay_ms(1).min(base);
+                let high = base.saturating_mul(3).min(self.backoff.cap_ms());
+                let high = high.max(low);
+                low + jitter_fraction(seed, attempt, high - low)
+            }
+        }
+    }
+}
+
+/// Deterministic value in `0..=max` from an FNV-1a hash of `seed` and `attempt`.
+fn jitter_fraction(seed: &str, attempt: u32, max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.bytes().chain(attempt.to_le_bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash % (max + 1)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskRetryScheduledPayload {
+    /// The attempt that just failed.
+    pub attempt: u32,
+    pub next_attempt: u32,
+    pub failure_class: FailureClass,
+    pub delay_ms: u64,
+    pub retry_at: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskTimedOutPayload {
+    pub from: TaskState,
+    pub to: TaskState,
+    pub attempt: u32,
+    pub timeout_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(backoff: Backoff, jitter: JitterMode) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff,
+            jitter,
+            retry_on: default_retry_on(),
+        }
+    }
+
+    #[test]
+    fn exponential_backoff_doubles_and_caps() {
+        let backoff = Backoff::Exponential {
+            initial_ms: 100,
+            max_ms: 500,
+            multiplier: 2,
+        };
+        let delays: Vec<_> = (1..=5).map(|a| backoff.base_delay_ms(a)).collect();
+        assert_eq!(delays, vec![100, 200, 400, 500, 500]);
+    }
+
+    #[test]
+    fn jitter_is_bounded_and_deterministic() {
+        let full = policy(Backoff::Fixed { delay_ms: 1000 }, JitterMode::Full);
+        let decorrelated = policy(
+            Backoff::Exponential {
+                initial_ms: 100,
+                max_ms: 2000,
+                multiplier: 2,
+            },
+            JitterMode::Decorrelated,
+        );
+        for attempt in 1..20 {
+            let delay = full.delay_ms(attempt, "t1");
+            assert!(delay <= 1000);
+            assert_eq!(delay, full.delay_ms(attempt, "t1"));
+            let delay = decorrelated.delay_ms(attempt, "t1");
+            assert!((100..=2000).contains(&delay));
+        }
+        let none = policy(Backoff::Fixed { delay_ms: 1000 }, JitterMode::None);
+        assert_eq!(none.delay_ms(1, "t1"), 1000);
+    }
+
+    #[test]
+    fn retry_respects_attempts_and_classes() {
+        let policy = policy(Backoff::Fixed { delay_ms: 10 }, JitterMode::None);
+        assert!(policy.should_retry(1, FailureClass::Transient));
+        assert!(policy.should_retry(2, FailureClass::Timeout));
+        assert!(!policy.should_retry(3, FailureClass::Transient));
+        assert!(!policy.should_retry(1, FailureClass::Permanent));
+    }
+
+    #[test]
+    fn policy_defaults_apply_on_decode() {
+        let policy: RetryPolicy = serde_json::from_value(serde_json::json!({
+            "max_attempts": 2,
+            "backoff": { "kind": "exponential", "initial_ms": 50, "max_ms": 1000 }
+        }))
+        .expect("decode");
+        assert_eq!(policy.jitter, JitterMode::None);
+        assert_eq!(policy.retry_on, default_retry_on());
+        assert_eq!(policy.backoff.base_delay_ms(3), 200);
+    }
+}
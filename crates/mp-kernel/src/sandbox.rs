@@ -0,0 +1,203 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const EVENT_SANDBOX_HOSTCALL: &str = "sandbox.hostcall";
+pub const EVENT_SANDBOX_EVENT_EMITTED: &str = "sandbox.event_emitted";
+
+/// Strings longer than this are cut down before a hostcall's input or output is logged.
+pub const HOSTCALL_LOG_MAX_CHARS: usize = 256;
+
+/// A WASM module on disk; relative paths resolve against the document that names them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmModuleRef {
+    pub path: String,
+    /// Expected `blake3:<hex>` of the module bytes; loading fails on a mismatch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+}
+
+/// Per-invocation resource limits. A module that hits one traps and its caller fails closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxLimits {
+    #[serde(default = "SandboxLimits::default_fuel")]
+    pub fuel: u64,
+    #[serde(default = "SandboxLimits::default_memory_bytes")]
+    pub memory_bytes: u64,
+    #[serde(default = "SandboxLimits::default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl SandboxLimits {
+    fn default_fuel() -> u64 {
+        10_000_000
+    }
+
+    fn default_memory_bytes() -> u64 {
+        16 * 1024 * 1024
+    }
+
+    fn default_timeout_ms() -> u64 {
+        1_000
+    }
+}
+
+impl Default for SandboxLimits {
+    fn default() -> Self {
+        Self {
+            fuel: Self::default_fuel(),
+            memory_bytes: Self::default_memory_bytes(),
+            timeout_ms: Self::default_timeout_ms(),
+        }
+    }
+}
+
+/// What a module may reach through hostcalls. Everything is denied unless granted here.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxCapabilities {
+    /// Extension event types (`x.<org>.<name>`) the module may emit.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub emit_events: Vec<String>,
+    #[serde(default)]
+    pub read_capsules: bool,
+    #[serde(default)]
+    pub write_capsules: bool,
+    /// Tool ids the module may request calls to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Directories the module may read beneath.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fs_read: Vec<String>,
+    /// Directories the module may write beneath; also readable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fs_write: Vec<String>,
+    #[serde(default)]
+    pub crypto_random: bool,
+    #[serde(default)]
+    pub clock: bool,
+}
+
+/// Host functions a module imports from the `mp` namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Hostcall {
+    EmitEvent,
+    ReadCapsule,
+    WriteCapsule,
+    RequestToolCall,
+    Filesystem,
+    CryptoRandom,
+    ClockNow,
+}
+
+impl Hostcall {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Hostcall::EmitEvent => "emit_event",
+            Hostcall::ReadCapsule => "read_capsule",
+            Hostcall::WriteCapsule => "write_capsule",
+            Hostcall::RequestToolCall => "request_tool_call",
+            Hostcall::Filesystem => "filesystem",
+            Hostcall::CryptoRandom => "crypto_random",
+            Hostcall::ClockNow => "clock_now",
+        }
+    }
+}
+
+impl fmt::Display for Hostcall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One hostcall made by a module, with its input and output cut down by [`redact_for_log`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxHostcallPayload {
+    pub module_id: String,
+    pub module_hash: String,
+    pub hostcall: Hostcall,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    /// Set when the hostcall was denied or failed; the module traps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub duration_us: u64,
+}
+
+/// An extension event a module emitted through `emit_event`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SandboxEventEmittedPayload {
+    pub module_id: String,
+    pub module_hash: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub payload: Value,
+}
+
+/// What a hook module returns from `run`; `target` replaces the value the hook was given.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WasmHookOutput {
+    pub decision: WasmHookDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WasmHookDecision {
+    Continue,
+    Block,
+    RequireApproval,
+}
+
+/// Copy of `value` safe to log: strings over [`HOSTCALL_LOG_MAX_CHARS`] are truncated.
+pub fn redact_for_log(value: &Value) -> Value {
+    match value {
+        Value::String(text) if text.chars().count() > HOSTCALL_LOG_MAX_CHARS => {
+            let kept: String = text.chars().take(HOSTCALL_LOG_MAX_CHARS).collect();
+            Value::String(format!("{kept}…"))
+        }
+        Value::Array(items) => Value::Array(items.iter().map(redact_for_log).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), redact_for_log(value)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn limits_and_capabilities_default_to_closed() {
+        let limits: SandboxLimits = serde_json::from_value(json!({"fuel": 5})).expect("limits");
+        assert_eq!(limits.fuel, 5);
+        assert_eq!(limits.timeout_ms, SandboxLimits::default().timeout_ms);
+        let capabilities: SandboxCapabilities =
+            serde_json::from_value(json!({})).expect("capabilities");
+        assert!(!capabilities.clock && !capabilities.write_capsules);
+        assert!(capabilities.fs_read.is_empty());
+    }
+
+    #[test]
+    fn redaction_truncates_nested_long_strings() {
+        let long = "a".repeat(HOSTCALL_LOG_MAX_CHARS + 10);
+        let redacted = redact_for_log(&json!({"content": long, "n": [1, "short"]}));
+        let content = redacted["content"].as_str().expect("content");
+        assert_eq!(content.chars().count(), HOSTCALL_LOG_MAX_CHARS + 1);
+        assert_eq!(redacted["n"], json!([1, "short"]));
+    }
+}
@@ -0,0 +1,414 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const COMMAND_SECRET_SET: &str = "secret.set";
+pub const COMMAND_SECRET_ROTATE: &str = "secret.rotate";
+pub const COMMAND_SECRET_DELETE: &str = "secret.delete";
+pub const COMMAND_VAULT_INIT: &str = "vault.init";
+pub const COMMAND_VAULT_UNLOCK: &str = "vault.unlock";
+pub const COMMAND_VAULT_LOCK: &str = "vault.lock";
+pub const COMMAND_SECRET_EXEC: &str = "secret.exec";
+
+pub const EVENT_SECRET_CREATED: &str = "secret.created";
+pub const EVENT_SECRET_ROTATED: &str = "secret.rotated";
+pub const EVENT_SECRET_DELETED: &str = "secret.deleted";
+pub const EVENT_SECRET_INJECTED: &str = "secret.injected";
+pub const EVENT_VAULT_INITIALIZED: &str = "vault.initialized";
+pub const EVENT_VAULT_UNLOCKED: &str = "vault.unlocked";
+pub const EVENT_VAULT_LOCKED: &str = "vault.locked";
+pub const EVENT_PROCESS_LAUNCHED: &str = "process.launched";
+pub const EVENT_PROCESS_EXITED: &str = "process.exited";
+
+/// Stands in for a secret value anywhere it would otherwise be shown or recorded.
+pub const SECRET_REDACTED: &str = "****";
+
+/// Largest secret value accepted, in bytes.
+pub const SECRET_MAX_BYTES: usize = 64 * 1024;
+
+/// Idle time after which an unlocked vault locks itself, unless the unlock asked otherwise.
+pub const VAULT_DEFAULT_IDLE_TIMEOUT_MS: u64 = 15 * 60 * 1000;
+pub const VAULT_MAX_IDLE_TIMEOUT_MS: u64 = 12 * 60 * 60 * 1000;
+
+pub const VAULT_MIN_PASSWORD_CHARS: usize = 8;
+
+/// How long a process launched with secrets may run before the daemon kills it.
+pub const SECRET_EXEC_DEFAULT_TIMEOUT_MS: u64 = 60 * 1000;
+pub const SECRET_EXEC_MAX_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+
+/// Captured stdout and stderr are each cut to this many bytes.
+pub const SECRET_EXEC_OUTPUT_MAX_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretScope {
+    /// Every actor in the workspace.
+    Global,
+    User,
+    Project,
+    Session,
+}
+
+impl SecretScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretScope::Global => "global",
+            SecretScope::User => "user",
+            SecretScope::Project => "project",
+            SecretScope::Session => "session",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "global" => Some(SecretScope::Global),
+            "user" => Some(SecretScope::User),
+            "project" => Some(SecretScope::Project),
+            "session" => Some(SecretScope::Session),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SecretScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Who may see a secret by name lookup. `id` names the user, project or session and is absent
+/// for global secrets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretOwner {
+    pub scope: SecretScope,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// `value` is taken off the command before hooks, policy or validation errors can see it; every
+/// later stage sees [`SECRET_REDACTED`] instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretSetPayload {
+    pub workspace_id: String,
+    pub name: String,
+    pub owner: SecretOwner,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretRotatePayload {
+    pub workspace_id: String,
+    pub secret_id: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretDeletePayload {
+    pub workspace_id: String,
+    pub secret_id: String,
+}
+
+/// Carried by `secret.created` and `secret.rotated`: metadata only, never the value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretVersionPayload {
+    pub name: String,
+    pub owner: SecretOwner,
+    pub version: u32,
+    pub redacted: String,
+    pub length: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretDeletedPayload {
+    pub name: String,
+    pub owner: SecretOwner,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretEntry {
+    pub workspace_id: String,
+    pub secret_id: String,
+    pub name: String,
+    pub owner: SecretOwner,
+    pub version: u32,
+    pub redacted: String,
+    pub length: usize,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+/// The identities a name lookup runs on behalf of; unset parts match no scoped secret.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretContext {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+impl SecretContext {
+    fn sees(&self, owner: &SecretOwner) -> bool {
+        let id = match owner.scope {
+            SecretScope::Global => return true,
+            SecretScope::User => &self.user_id,
+            SecretScope::Project => &self.project_id,
+            SecretScope::Session => &self.session_id,
+        };
+        id.is_some() && *id == owner.id
+    }
+}
+
+/// Picks the secret `name` resolves to for `context`: session over project over user over
+/// global.
+pub fn resolve_secret<'a>(
+    secrets: &'a [SecretEntry],
+    name: &str,
+    context: &SecretContext,
+) -> Option<&'a SecretEntry> {
+    secrets
+        .iter()
+        .filter(|secret| secret.name == name && context.sees(&secret.owner))
+        .max_by_key(|secret| secret.owner.scope)
+}
+
+/// Argon2id parameters a vault's key was derived with; stored with the vault so they can be
+/// raised later without locking out existing vaults.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultKdf {
+    pub m_cost_kib: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultInitializedPayload {
+    pub kdf: VaultKdf,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultUnlockedPayload {
+    pub idle_timeout_ms: u64,
+    /// Set when the unlock re-derived the key with stronger parameters and re-wrapped every
+    /// data key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgraded_kdf: Option<VaultKdf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultLockReason {
+    Requested,
+    Idle,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultLockedPayload {
+    pub reason: VaultLockReason,
+}
+
+/// The daemon takes `password` off the command before hooks or policy see it, and only ever
+/// holds it in memory while deriving the key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultInitPayload {
+    pub workspace_id: String,
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultUnlockPayload {
+    pub workspace_id: String,
+    pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultLockPayload {
+    pub workspace_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultStatus {
+    pub workspace_id: String,
+    pub initialized: bool,
+    pub unlocked: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kdf: Option<VaultKdf>,
+    /// When the vault locks itself if left idle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locks_at: Option<String>,
+}
+
+/// How a launched process receives a secret. Environment variables are deliberately not an
+/// option: they leak into child processes, crash reports and `/proc/<pid>/environ`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretInjectVia {
+    /// Written to the process's stdin, which is then closed. At most one secret per launch.
+    #[default]
+    Stdin,
+    /// Written to an owner-only file on tmpfs that is removed once the process exits. Its path
+    /// replaces `{secret:<name>}` in the arguments.
+    File,
+}
+
+impl SecretInjectVia {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecretInjectVia::Stdin => "stdin",
+            SecretInjectVia::File => "file",
+        }
+    }
+}
+
+impl fmt::Display for SecretInjectVia {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretInjection {
+    pub name: String,
+    #[serde(default)]
+    pub via: SecretInjectVia,
+}
+
+/// Launches `program` with the named secrets, each resolved for `context` the way
+/// `GET /v1/secrets/resolve` does. Policy sees the command's actor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretExecPayload {
+    pub workspace_id: String,
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default)]
+    pub context: SecretContext,
+    pub secrets: Vec<SecretInjection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Carried by `secret.injected`, one per secret handed to a launched process.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SecretInjectedPayload {
+    pub name: String,
+    pub owner: SecretOwner,
+    pub version: u32,
+    pub via: SecretInjectVia,
+    pub program: String,
+}
+
+/// Carried by `process.launched`, recorded with the `secret.injected` events before the
+/// process starts. `args` still hold `{secret:<name>}` placeholders rather than file paths, and
+/// `env` names the variables passed on from the daemon's environment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessLaunchedPayload {
+    pub program: String,
+    pub args: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    pub env: Vec<String>,
+    pub timeout_ms: u64,
+}
+
+/// Carried by `process.exited`. Output is captured, cut to [`SECRET_EXEC_OUTPUT_MAX_BYTES`],
+/// and has every injected value replaced with [`SECRET_REDACTED`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProcessExitedPayload {
+    /// Absent when the process was killed by a signal, including on timeout, or never started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub timed_out: bool,
+    pub stdout: String,
+    pub stderr: String,
+    #[serde(default)]
+    pub truncated: bool,
+    /// Why the process could not be started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secret(name: &str, scope: SecretScope, id: Option<&str>) -> SecretEntry {
+        SecretEntry {
+            workspace_id: "w1".to_string(),
+            secret_id: format!("{scope}-{}", id.unwrap_or("all")),
+            name: name.to_string(),
+            owner: SecretOwner {
+                scope,
+                id: id.map(str::to_string),
+            },
+            version: 1,
+            redacted: SECRET_REDACTED.to_string(),
+            length: 8,
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            updated_at: "2020-01-01T00:00:00Z".to_string(),
+            seq_global: 1,
+        }
+    }
+
+    #[test]
+    fn resolution_prefers_the_narrowest_visible_scope() {
+        let secrets = vec![
+            secret("API_KEY", SecretScope::Global, None),
+            secret("API_KEY", SecretScope::User, Some("alice")),
+            secret("API_KEY", SecretScope::Project, Some("p1")),
+            secret("API_KEY", SecretScope::Session, Some("s1")),
+            secret("OTHER", SecretScope::Session, Some("s2")),
+        ];
+        let resolve = |context: SecretContext| {
+            resolve_secret(&secrets, "API_KEY", &context).map(|secret| secret.secret_id.as_str())
+        };
+        assert_eq!(resolve(SecretContext::default()), Some("global-all"));
+        let alice = SecretContext {
+            user_id: Some("alice".to_string()),
+            ..SecretContext::default()
+        };
+        assert_eq!(resolve(alice.clone()), Some("user-alice"));
+        let in_project = SecretContext {
+            project_id: Some("p1".to_string()),
+            session_id: Some("s9".to_string()),
+            ..alice.clone()
+        };
+        assert_eq!(resolve(in_project.clone()), Some("project-p1"));
+        let in_session = SecretContext {
+            session_id: Some("s1".to_string()),
+            ..in_project
+        };
+        assert_eq!(resolve(in_session), Some("session-s1"));
+        assert_eq!(
+            resolve_secret(&secrets, "OTHER", &alice).map(|secret| secret.name.as_str()),
+            None
+        );
+    }
+}
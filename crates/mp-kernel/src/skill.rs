@@ -0,0 +1,316 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+pub const COMMAND_SKILL_SYNC: &str = "skill.sync";
+pub const COMMAND_SKILL_APPROVE: &str = "skill.approve";
+
+pub const EVENT_SKILL_REGISTERED: &str = "skill.registered";
+pub const EVENT_SKILL_UPDATED: &str = "skill.updated";
+pub const EVENT_SKILL_REMOVED: &str = "skill.removed";
+pub const EVENT_SKILL_APPROVED: &str = "skill.approved";
+
+/// Workspace skills live in `<root>/agents/skills/<name>/`.
+pub const SKILL_DIR: &str = "agents/skills";
+pub const SKILL_MANIFEST: &str = "SKILL.md";
+/// Base64 ed25519 signature over the skill's content hash; not part of the hash itself.
+pub const SKILL_SIGNATURE: &str = "SKILL.sig";
+pub const SKILL_API_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillScope {
+    /// Found under the workspace's `agents/skills`.
+    Workspace,
+    /// Found under the daemon's config-dir skill directory and shared by every workspace.
+    Global,
+}
+
+impl SkillScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkillScope::Workspace => "workspace",
+            SkillScope::Global => "global",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "workspace" => Some(SkillScope::Workspace),
+            "global" => Some(SkillScope::Global),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SkillScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// How far a skill has been reviewed, which bounds the capabilities it may be granted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillTrust {
+    /// Its exact bytes are signed by a key the daemon trusts.
+    Trusted,
+    /// Installed by the operator, or approved at its current content hash.
+    Sandboxed,
+    /// Unreviewed: new or changed workspace skills, and skills with a bad signature.
+    Proposed,
+}
+
+impl SkillTrust {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkillTrust::Trusted => "trusted",
+            SkillTrust::Sandboxed => "sandboxed",
+            SkillTrust::Proposed => "proposed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "trusted" => Some(SkillTrust::Trusted),
+            "sandboxed" => Some(SkillTrust::Sandboxed),
+            "proposed" => Some(SkillTrust::Proposed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SkillTrust {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// YAML frontmatter of `SKILL.md`, following the Agent Skills format.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillFrontmatter {
+    /// Must match the skill's directory name.
+    pub name: String,
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compatibility: Option<String>,
+    /// Space-separated tool ids the skill expects to use.
+    #[serde(
+        default,
+        rename = "allowed-tools",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub allowed_tools: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Files in a skill's optional subdirectories, relative to the skill directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillComponents {
+    #[serde(default)]
+    pub wasm: Vec<String>,
+    #[serde(default)]
+    pub scripts: Vec<String>,
+    #[serde(default)]
+    pub refs: Vec<String>,
+    #[serde(default)]
+    pub tests: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkillSignatureStatus {
+    Unsigned,
+    /// Verifies against one of the daemon's trusted keys.
+    Valid,
+    /// Malformed, or not made by any trusted key over the current content hash.
+    Invalid,
+}
+
+impl SkillSignatureStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SkillSignatureStatus::Unsigned => "unsigned",
+            SkillSignatureStatus::Valid => "valid",
+            SkillSignatureStatus::Invalid => "invalid",
+        }
+    }
+}
+
+impl fmt::Display for SkillSignatureStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillSignature {
+    pub status: SkillSignatureStatus,
+    /// Trusted key that made a valid signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
+}
+
+/// Rescans workspace and global skill directories and records skills that appeared, changed
+/// or disappeared.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillSyncPayload {
+    pub workspace_id: String,
+}
+
+/// Approves a proposed skill at the content hash that was reviewed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillApprovePayload {
+    pub workspace_id: String,
+    pub skill_id: String,
+    pub content_hash: String,
+}
+
+/// Payload of both `skill.registered` (revision 1) and `skill.updated`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillVersionPayload {
+    pub skill_id: String,
+    pub revision: u32,
+    pub scope: SkillScope,
+    /// Skill directory: relative to the workspace root, or to the global skill directory.
+    pub path: String,
+    /// `blake3:<hex>` over every file in the directory except the signature.
+    pub content_hash: String,
+    pub trust: SkillTrust,
+    pub signature: SkillSignature,
+    pub frontmatter: SkillFrontmatter,
+    pub components: SkillComponents,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillRemovedPayload {
+    pub skill_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillApprovedPayload {
+    pub skill_id: String,
+    pub content_hash: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillEntry {
+    pub workspace_id: String,
+    pub skill_id: String,
+    pub revision: u32,
+    pub scope: SkillScope,
+    pub path: String,
+    pub content_hash: String,
+    pub trust: SkillTrust,
+    pub signature: SkillSignature,
+    pub frontmatter: SkillFrontmatter,
+    pub components: SkillComponents,
+    /// Content hash most recently approved; approval lapses when the content changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub approved_hash: Option<String>,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+/// A registered skill compared with its directory as it is now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkillVerifyReport {
+    pub skill_id: String,
+    pub registered_hash: String,
+    /// Absent when the directory no longer holds a loadable skill.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk_hash: Option<String>,
+    /// Set when the directory differs from what was registered.
+    pub drift: bool,
+    pub signature: SkillSignature,
+    /// Trust the skill would get if synced now.
+    pub trust: SkillTrust,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Trust follows from the signature first, then from where the skill was found and whether
+/// its current bytes were approved.
+pub fn skill_trust(
+    scope: SkillScope,
+    signature: SkillSignatureStatus,
+    content_hash: &str,
+    approved_hash: Option<&str>,
+) -> SkillTrust {
+    match signature {
+        SkillSignatureStatus::Valid => SkillTrust::Trusted,
+        _ if approved_hash == Some(content_hash) => SkillTrust::Sandboxed,
+        SkillSignatureStatus::Unsigned if scope == SkillScope::Global => SkillTrust::Sandboxed,
+        _ => SkillTrust::Proposed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trust_requires_a_signature_or_a_matching_approval() {
+        use SkillSignatureStatus::{Invalid, Unsigned, Valid};
+        let trust = |scope, signature, approved| skill_trust(scope, signature, "h2", approved);
+        assert_eq!(
+            trust(SkillScope::Workspace, Valid, None),
+            SkillTrust::Trusted
+        );
+        assert_eq!(
+            trust(SkillScope::Global, Unsigned, None),
+            SkillTrust::Sandboxed
+        );
+        assert_eq!(
+            trust(SkillScope::Global, Invalid, None),
+            SkillTrust::Proposed
+        );
+        assert_eq!(
+            trust(SkillScope::Workspace, Unsigned, None),
+            SkillTrust::Proposed
+        );
+        assert_eq!(
+            trust(SkillScope::Workspace, Unsigned, Some("h1")),
+            SkillTrust::Proposed
+        );
+        assert_eq!(
+            trust(SkillScope::Workspace, Invalid, Some("h2")),
+            SkillTrust::Sandboxed
+        );
+    }
+
+    #[test]
+    fn frontmatter_uses_agent_skills_field_names() {
+        let frontmatter: SkillFrontmatter = serde_json::from_value(serde_json::json!({
+            "name": "pdf-tools",
+            "description": "Extracts text from PDFs.",
+            "allowed-tools": "acme.pdf.read",
+            "metadata": {"owner": "docs"},
+        }))
+        .expect("frontmatter");
+        assert_eq!(frontmatter.allowed_tools.as_deref(), Some("acme.pdf.read"));
+        assert!(
+            serde_json::from_value::<SkillFrontmatter>(serde_json::json!({
+                "name": "pdf-tools",
+                "description": "Extracts text from PDFs.",
+                "tools": [],
+            }))
+            .is_err()
+        );
+    }
+}
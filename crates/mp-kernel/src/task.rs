@@ -0,0 +1,400 @@
+use crate::{FailureClass, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub const COMMAND_TASK_CREATE: &str = "task.create";
+pub const COMMAND_TASK_START: &str = "task.start";
+pub const COMMAND_TASK_SUCCEED: &str = "task.succeed";
+pub const COMMAND_TASK_FAIL: &str = "task.fail";
+pub const COMMAND_TASK_CANCEL: &str = "task.cancel";
+pub const COMMAND_TASK_PAUSE: &str = "task.pause";
+pub const COMMAND_TASK_RESUME: &str = "task.resume";
+pub const COMMAND_TASK_AWAIT_INPUT: &str = "task.await_input";
+pub const COMMAND_TASK_LIST: &str = "task.list";
+
+pub const EVENT_TASK_CREATED: &str = "task.created";
+pub const EVENT_TASK_STARTED: &str = "task.started";
+pub const EVENT_TASK_SUCCEEDED: &str = "task.succeeded";
+pub const EVENT_TASK_FAILED: &str = "task.failed";
+pub const EVENT_TASK_CANCELLED: &str = "task.cancelled";
+pub const EVENT_TASK_PAUSED: &str = "task.paused";
+pub const EVENT_TASK_RESUMED: &str = "task.resumed";
+pub const EVENT_TASK_AWAITING_INPUT: &str = "task.awaiting_input";
+pub const EVENT_TASK_BLOCKED_BY_POLICY: &str = "task.blocked_by_policy";
+pub const EVENT_TASK_BLOCKED_BY_APPROVAL: &str = "task.blocked_by_approval";
+
+/// Lifecycle state of a task as recorded by the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+    Cancelled,
+    Paused,
+    AwaitingUserInput,
+    BlockedByPolicy,
+    BlockedByApproval,
+}
+
+impl TaskState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskState::Queued => "queued",
+            TaskState::Running => "running",
+            TaskState::Succeeded => "succeeded",
+            TaskState::Failed => "failed",
+            TaskState::Cancelled => "cancelled",
+            TaskState::Paused => "paused",
+            TaskState::AwaitingUserInput => "awaiting_user_input",
+            TaskState::BlockedByPolicy => "blocked_by_policy",
+            TaskState::BlockedByApproval => "blocked_by_approval",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(TaskState::Queued),
+            "running" => Some(TaskState::Running),
+            "succeeded" => Some(TaskState::Succeeded),
+            "failed" => Some(TaskState::Failed),
+            "cancelled" => Some(TaskState::Cancelled),
+            "paused" => Some(TaskState::Paused),
+            "awaiting_user_input" => Some(TaskState::AwaitingUserInput),
+            "blocked_by_policy" => Some(TaskState::BlockedByPolicy),
+            "blocked_by_approval" => Some(TaskState::BlockedByApproval),
+            _ => None,
+        }
+    }
+
+    /// Terminal states accept no further transitions.
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            TaskState::Succeeded | TaskState::Failed | TaskState::Cancelled
+        )
+    }
+
+    /// States where the task is halted waiting on something outside the task itself.
+    pub fn is_blocked(&self) -> bool {
+        matches!(
+            self,
+            TaskState::Paused
+                | TaskState::AwaitingUserInput
+                | TaskState::BlockedByPolicy
+                | TaskState::BlockedByApproval
+        )
+    }
+}
+
+impl fmt::Display for TaskState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Lifecycle actions requested through `task.*` commands, plus the holds the daemon places on
+/// a task whose lifecycle command is refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskAction {
+    Start,
+    Succeed,
+    Fail,
+    Cancel,
+    Pause,
+    Resume,
+    AwaitInput,
+    /// Recorded by the daemon when policy denies a lifecycle command; no command requests it.
+    BlockOnPolicy,
+    /// Recorded by the daemon when an unsatisfied gate refuses a lifecycle command.
+    BlockOnApproval,
+}
+
+impl TaskAction {
+    pub fn from_command(command_type: &str) -> Option<Self> {
+        match command_type {
+            COMMAND_TASK_START => Some(TaskAction::Start),
+            COMMAND_TASK_SUCCEED => Some(TaskAction::Succeed),
+            COMMAND_TASK_FAIL => Some(TaskAction::Fail),
+            COMMAND_TASK_CANCEL => Some(TaskAction::Cancel),
+            COMMAND_TASK_PAUSE => Some(TaskAction::Pause),
+            COMMAND_TASK_RESUME => Some(TaskAction::Resume),
+            COMMAND_TASK_AWAIT_INPUT => Some(TaskAction::AwaitInput),
+            _ => None,
+        }
+    }
+
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            TaskAction::Start => EVENT_TASK_STARTED,
+            TaskAction::Succeed => EVENT_TASK_SUCCEEDED,
+            TaskAction::Fail => EVENT_TASK_FAILED,
+            TaskAction::Cancel => EVENT_TASK_CANCELLED,
+            TaskAction::Pause => EVENT_TASK_PAUSED,
+            TaskAction::Resume => EVENT_TASK_RESUMED,
+            TaskAction::AwaitInput => EVENT_TASK_AWAITING_INPUT,
+            TaskAction::BlockOnPolicy => EVENT_TASK_BLOCKED_BY_POLICY,
+            TaskAction::BlockOnApproval => EVENT_TASK_BLOCKED_BY_APPROVAL,
+        }
+    }
+
+    pub fn from_event(event_type: &str) -> Option<Self> {
+        match event_type {
+            EVENT_TASK_STARTED => Some(TaskAction::Start),
+            EVENT_TASK_SUCCEEDED => Some(TaskAction::Succeed),
+            EVENT_TASK_FAILED => Some(TaskAction::Fail),
+            EVENT_TASK_CANCELLED => Some(TaskAction::Cancel),
+            EVENT_TASK_PAUSED => Some(TaskAction::Pause),
+            EVENT_TASK_RESUMED => Some(TaskAction::Resume),
+            EVENT_TASK_AWAITING_INPUT => Some(TaskAction::AwaitInput),
+            EVENT_TASK_BLOCKED_BY_POLICY => Some(TaskAction::BlockOnPolicy),
+            EVENT_TASK_BLOCKED_BY_APPROVAL => Some(TaskAction::BlockOnApproval),
+            _ => None,
+        }
+    }
+}
+
+/// Legal task transitions; `None` means the action is not allowed from `current`.
+///
+/// Only a running task is halted: a queued one is already waiting. Every halt ends with
+/// `task.resume`, `task.fail` or `task.cancel`.
+pub fn next_task_state(current: TaskState, action: TaskAction) -> Option<TaskState> {
+    use TaskState::*;
+    match (action, current) {
+        (TaskAction::Start, Queued) => Some(Running),
+        (TaskAction::Succeed, Running) => Some(Succeeded),
+        (TaskAction::Fail, Running) => Some(Failed),
+        (TaskAction::Fail, state) if state.is_blocked() => Some(Failed),
+        (TaskAction::Cancel, state) if !state.is_terminal() => Some(Cancelled),
+        (TaskAction::Pause, Running) => Some(Paused),
+        (TaskAction::AwaitInput, Running) => Some(AwaitingUserInput),
+        (TaskAction::BlockOnPolicy, Running) => Some(BlockedByPolicy),
+        (TaskAction::BlockOnApproval, Running) => Some(BlockedByApproval),
+        (TaskAction::Resume, state) if state.is_blocked() => Some(Running),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskCreatePayload {
+    pub workspace_id: String,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    /// Per-attempt limit; a running attempt past it is timed out by the scheduler.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Payload shared by every lifecycle command (`task.start`, `task.fail`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskTransitionPayload {
+    pub workspace_id: String,
+    pub task_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Only accepted on `task.fail`; unclassified failures count as transient.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_class: Option<FailureClass>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskCreatedPayload {
+    pub title: String,
+    pub state: TaskState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Payload shared by every lifecycle event (`task.started`, `task.failed`, ...).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskTransitionedPayload {
+    pub from: TaskState,
+    pub to: TaskState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failure_class: Option<FailureClass>,
+    /// Daemon clock reading when an attempt starts; set on `task.started` only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TaskListEntry {
+    pub task_id: String,
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub title: String,
+    pub state: TaskState,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline_at: Option<String>,
+    /// Number of attempts started so far.
+    pub attempt: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attempt_started_at: Option<String>,
+    /// Earliest time a queued retry may start.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_at: Option<String>,
+    /// Pipeline stage label; unset until the first `stage.transition`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_stage: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_STATES: [TaskState; 9] = [
+        TaskState::Queued,
+        TaskState::Running,
+        TaskState::Succeeded,
+        TaskState::Failed,
+        TaskState::Cancelled,
+        TaskState::Paused,
+        TaskState::AwaitingUserInput,
+        TaskState::BlockedByPolicy,
+        TaskState::BlockedByApproval,
+    ];
+
+    #[test]
+    fn happy_path_transitions() {
+        let running = next_task_state(TaskState::Queued, TaskAction::Start);
+        assert_eq!(running, Some(TaskState::Running));
+        let paused = next_task_state(TaskState::Running, TaskAction::Pause);
+        assert_eq!(paused, Some(TaskState::Paused));
+        let resumed = next_task_state(TaskState::Paused, TaskAction::Resume);
+        assert_eq!(resumed, Some(TaskState::Running));
+        let done = next_task_state(TaskState::Running, TaskAction::Succeed);
+        assert_eq!(done, Some(TaskState::Succeeded));
+    }
+
+    const ALL_ACTIONS: [TaskAction; 9] = [
+        TaskAction::Start,
+        TaskAction::Succeed,
+        TaskAction::Fail,
+        TaskAction::Cancel,
+        TaskAction::Pause,
+        TaskAction::Resume,
+        TaskAction::AwaitInput,
+        TaskAction::BlockOnPolicy,
+        TaskAction::BlockOnApproval,
+    ];
+
+    #[test]
+    fn terminal_states_reject_every_action() {
+        for state in ALL_STATES.iter().filter(|s| s.is_terminal()) {
+            for action in ALL_ACTIONS {
+                assert_eq!(next_task_state(*state, action), None, "{state} {action:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn illegal_transitions_are_rejected() {
+        assert_eq!(
+            next_task_state(TaskState::Queued, TaskAction::Succeed),
+            None
+        );
+        assert_eq!(next_task_state(TaskState::Running, TaskAction::Start), None);
+        assert_eq!(next_task_state(TaskState::Queued, TaskAction::Resume), None);
+        assert_eq!(
+            next_task_state(TaskState::Paused, TaskAction::Succeed),
+            None
+        );
+        assert_eq!(
+            next_task_state(TaskState::BlockedByApproval, TaskAction::Resume),
+            Some(TaskState::Running)
+        );
+        assert_eq!(
+            next_task_state(TaskState::Queued, TaskAction::Cancel),
+            Some(TaskState::Cancelled)
+        );
+    }
+
+    #[test]
+    fn halted_states_are_entered_from_running_and_left_by_resume_fail_or_cancel() {
+        use TaskState::*;
+        let halts = [
+            (TaskAction::Pause, Paused),
+            (TaskAction::AwaitInput, AwaitingUserInput),
+            (TaskAction::BlockOnPolicy, BlockedByPolicy),
+            (TaskAction::BlockOnApproval, BlockedByApproval),
+        ];
+        for (halt, halted) in halts {
+            for state in ALL_STATES {
+                let expected = (state == Running).then_some(halted);
+                assert_eq!(next_task_state(state, halt), expected, "{state} {halt:?}");
+            }
+            let exits: Vec<(TaskAction, TaskState)> = ALL_ACTIONS
+                .into_iter()
+                .filter_map(|action| Some((action, next_task_state(halted, action)?)))
+                .collect();
+            assert_eq!(
+                exits,
+                vec![
+                    (TaskAction::Fail, Failed),
+                    (TaskAction::Cancel, Cancelled),
+                    (TaskAction::Resume, Running),
+                ],
+                "{halted}"
+            );
+        }
+    }
+
+    #[test]
+    fn state_wire_names_round_trip() {
+        for state in ALL_STATES {
+            let json = serde_json::to_value(state).expect("serialize");
+            assert_eq!(json, serde_json::json!(state.as_str()));
+            assert_eq!(TaskState::parse(state.as_str()), Some(state));
+        }
+    }
+
+    #[test]
+    fn action_events_round_trip() {
+        for command in [
+            COMMAND_TASK_START,
+            COMMAND_TASK_SUCCEED,
+            COMMAND_TASK_FAIL,
+            COMMAND_TASK_CANCEL,
+            COMMAND_TASK_PAUSE,
+            COMMAND_TASK_RESUME,
+            COMMAND_TASK_AWAIT_INPUT,
+        ] {
+            let action = TaskAction::from_command(command).expect("action");
+            assert_eq!(TaskAction::from_event(action.event_type()), Some(action));
+        }
+        for action in ALL_ACTIONS {
+            assert_eq!(TaskAction::from_event(action.event_type()), Some(action));
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use crate::{SandboxCapabilities, SandboxLimits, WasmModuleRef};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const COMMAND_TOOL_REQUEST: &str = "tool.request";
+pub const COMMAND_TOOL_COMPLETE: &str = "tool.complete";
+
+pub const EVENT_TOOL_REQUESTED: &str = "tool.requested";
+pub const EVENT_TOOL_RESULT: &str = "tool.result";
+
+/// The kind of change a tool can make outside its own output. Permissions are granted by
+/// taxon as well as by tool id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SideEffect {
+    /// Computes its output from its input alone.
+    Pure,
+    /// Reads or changes kernel state such as tasks and capsules.
+    Workspace,
+    Filesystem,
+    Network,
+    Process,
+    Git,
+    Container,
+}
+
+impl SideEffect {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SideEffect::Pure => "pure",
+            SideEffect::Workspace => "workspace",
+            SideEffect::Filesystem => "filesystem",
+            SideEffect::Network => "network",
+            SideEffect::Process => "process",
+            SideEffect::Git => "git",
+            SideEffect::Container => "container",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pure" => Some(SideEffect::Pure),
+            "workspace" => Some(SideEffect::Workspace),
+            "filesystem" => Some(SideEffect::Filesystem),
+            "network" => Some(SideEffect::Network),
+            "process" => Some(SideEffect::Process),
+            "git" => Some(SideEffect::Git),
+            "container" => Some(SideEffect::Container),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SideEffect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// The tools a daemon offers, loaded from a `tool_registry` document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRegistry {
+    pub api_version: u32,
+    /// Bumped whenever a tool changes; blueprints pin it as `pins.tool_registry`.
+    pub version: u32,
+    #[serde(default)]
+    pub tools: Vec<ToolDefinition>,
+}
+
+/// A tool's strict contract: inputs and outputs that do not match its schemas are refused.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolDefinition {
+    /// Namespaced id, e.g. `acme.search.web`.
+    pub id: String,
+    pub version: String,
+    pub title: String,
+    pub description: String,
+    pub side_effect: SideEffect,
+    /// JSON Schema for the input; must be an object schema that rejects unknown fields.
+    pub input_schema: Value,
+    pub output_schema: Value,
+    pub examples: Vec<ToolExample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_notes: Option<String>,
+    /// How the kernel runs the tool; without one, results arrive through `tool.complete`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<ToolRuntime>,
+}
+
+/// A sample call, checked against the tool's schemas when the registry is loaded.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolExample {
+    pub description: String,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ToolRuntime {
+    /// Runs in the sandbox; the module receives the input and returns the output.
+    Wasm {
+        module: WasmModuleRef,
+        #[serde(default)]
+        capabilities: SandboxCapabilities,
+        #[serde(default)]
+        limits: SandboxLimits,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+impl ToolCallStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ToolCallStatus::Pending => "pending",
+            ToolCallStatus::Succeeded => "succeeded",
+            ToolCallStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ToolCallStatus::Pending),
+            "succeeded" => Some(ToolCallStatus::Succeeded),
+            "failed" => Some(ToolCallStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ToolCallStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Machine-readable failure of a tool call; `message` must be safe to show and log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolError {
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRequestPayload {
+    pub workspace_id: String,
+    pub tool_id: String,
+    pub input: Value,
+    /// Session asking for the call, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+/// Reports the outcome of a call to a tool without a runtime; exactly one of `output` and
+/// `error` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCompletePayload {
+    pub workspace_id: String,
+    pub request_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ToolError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRequestedPayload {
+    pub tool_id: String,
+    pub tool_version: String,
+    pub side_effect: SideEffect,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolResultPayload {
+    pub tool_id: String,
+    pub status: ToolCallStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ToolError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolCallEntry {
+    pub workspace_id: String,
+    pub request_id: String,
+    pub tool_id: String,
+    pub tool_version: String,
+    pub side_effect: SideEffect,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub status: ToolCallStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ToolError>,
+    pub requested_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<String>,
+    pub seq_global: i64,
+}
+
+/// Response budgets for catalog endpoints, in bytes of serialized JSON. Agents discover
+/// operations a few at a time, so no single answer can crowd their context.
+pub const TOOL_SEARCH_BUDGET_BYTES: usize = 4 * 1024;
+pub const TOOL_LOAD_BUDGET_BYTES: usize = 16 * 1024;
+pub const TOOL_RUN_BUDGET_BYTES: usize = 8 * 1024;
+
+pub const TOOL_SEARCH_DEFAULT_LIMIT: usize = 8;
+pub const TOOL_SEARCH_MAX_LIMIT: usize = 25;
+
+/// Where a catalog operation comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatalogKind {
+    /// A tool from the daemon's tool registry, run through `tool.request`.
+    Tool,
+    /// A kernel command, submitted with the input as its payload.
+    Command,
+}
+
+impl CatalogKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CatalogKind::Tool => "tool",
+            CatalogKind::Command => "command",
+        }
+    }
+}
+
+impl fmt::Display for CatalogKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// One search result: enough to decide whether to `tool.load` the operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolSearchHit {
+    pub id: String,
+    pub kind: CatalogKind,
+    pub title: String,
+    /// The description, cut to its first sentence or a fixed length.
+    pub summary: String,
+    pub side_effect: SideEffect,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolSearchResponse {
+    pub hits: Vec<ToolSearchHit>,
+    /// Operations that matched, including those left out by the limit or budget.
+    pub total: usize,
+    /// Set when hits were dropped to stay within the response budget.
+    pub truncated: bool,
+}
+
+/// The full contract of one operation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolLoadResponse {
+    pub id: String,
+    pub kind: CatalogKind,
+    pub title: String,
+    pub description: String,
+    pub side_effect: SideEffect,
+    pub input_schema: Value,
+    /// Kernel commands answer with events rather than an output document.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    pub examples: Vec<ToolExample>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_notes: Option<String>,
+    /// Examples left out to stay within the response budget.
+    #[serde(default)]
+    pub omitted_examples: usize,
+}
+
+/// Runs a catalog operation by id. Tools need `workspace_id`; commands carry it in `input`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRunRequest {
+    pub id: String,
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    pub idempotency_key: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_version: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+}
+
+/// Tool ids are `<org>.<name>` with lowercase segments, like extension event types.
+pub fn is_namespaced_tool_id(id: &str) -> bool {
+    let segments: Vec<&str> = id.split('.').collect();
+    segments.len() >= 2
+        && segments.iter().all(|segment| {
+            !segment.is_empty()
+                && segment
+                    .chars()
+                    .all(|ch| ch.is_ascii_lowercase() || ch.is_ascii_digit() || ch == '_')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tool_ids_must_be_namespaced() {
+        assert!(is_namespaced_tool_id("acme.search"));
+        assert!(is_namespaced_tool_id("acme.git.diff_2"));
+        assert!(!is_namespaced_tool_id("search"));
+        assert!(!is_namespaced_tool_id("acme..search"));
+        assert!(!is_namespaced_tool_id("Acme.search"));
+    }
+
+    #[test]
+    fn definitions_require_a_side_effect() {
+        let mut tool = json!({
+            "id": "acme.echo",
+            "version": "1.0.0",
+            "title": "Echo",
+            "description": "Returns its input.",
+            "input_schema": {"type": "object", "additionalProperties": false},
+            "output_schema": {"type": "object"},
+            "examples": [],
+        });
+        assert!(serde_json::from_value::<ToolDefinition>(tool.clone()).is_err());
+        tool["side_effect"] = json!("pure");
+        let tool: ToolDefinition = serde_json::from_value(tool).expect("tool");
+        assert_eq!(tool.side_effect, SideEffect::Pure);
+        assert!(tool.runtime.is_none());
+    }
+}
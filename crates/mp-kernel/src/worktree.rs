@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+
+pub const COMMAND_WORKTREE_REGISTER: &str = "worktree.register";
+pub const COMMAND_WORKTREE_ATTACH: &str = "worktree.attach";
+pub const COMMAND_WORKTREE_DETACH: &str = "worktree.detach";
+pub const COMMAND_WORKTREE_LOCK_ACQUIRE: &str = "worktree.lock.acquire";
+pub const COMMAND_WORKTREE_LOCK_RELEASE: &str = "worktree.lock.release";
+
+pub const EVENT_WORKTREE_REGISTERED: &str = "worktree.registered";
+pub const EVENT_WORKTREE_ATTACHED: &str = "worktree.attached";
+pub const EVENT_WORKTREE_DETACHED: &str = "worktree.detached";
+pub const EVENT_WORKTREE_LOCK_ACQUIRED: &str = "worktree.lock.acquired";
+pub const EVENT_WORKTREE_LOCK_RELEASED: &str = "worktree.lock.released";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeRegisterPayload {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// Repository identity (remote URL or similar) recorded for replay.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeRegisteredPayload {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+}
+
+/// Payload shared by attach/detach and lock acquire/release commands.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeSessionPayload {
+    pub workspace_id: String,
+    pub worktree_id: String,
+    pub session_id: String,
+}
+
+/// Payload shared by `worktree.attached`, `worktree.detached` and `worktree.lock.acquired`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeSessionChangedPayload {
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeLockReleasedPayload {
+    pub session_id: String,
+    /// Set when the lock was released implicitly, e.g. `detached`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeLock {
+    pub session_id: String,
+    pub acquired_at: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorktreeEntry {
+    pub worktree_id: String,
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repo: Option<String>,
+    /// Attached sessions in attach order.
+    pub sessions: Vec<String>,
+    /// The single session allowed to write, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lock: Option<WorktreeLock>,
+    pub created_at: String,
+    pub updated_at: String,
+    pub seq_global: i64,
+}
+
+impl WorktreeEntry {
+    pub fn is_attached(&self, session_id: &str) -> bool {
+        self.sessions.iter().any(|s| s == session_id)
+    }
+
+    pub fn lock_holder(&self) -> Option<&str> {
+        self.lock.as_ref().map(|lock| lock.session_id.as_str())
+    }
+}
@@ -0,0 +1,580 @@
+//! Attribute-based policy evaluation for commands.
+//!
+//! A policy set is a list of bundles, each holding ordered allow/deny rules. A rule applies when
+//! its action pattern, subject and object matchers and every `when` condition hold. Any
+//! applicable deny wins over every allow; with no applicable rule, the strictest bundle default
+//! decides.
+
+use mp_kernel::{Actor, LeaseScope, PolicyDecision, PolicyEffect, PolicyRuleMatch};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+mod roles;
+
+pub use roles::*;
+
+pub const POLICY_API_VERSION: u32 = 1;
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum PolicyError {
+    #[error("policy bundle {0} is defined more than once")]
+    DuplicateBundle(String),
+    #[error("policy bundle {bundle} defines rule {rule} more than once")]
+    DuplicateRule { bundle: String, rule: String },
+    #[error("policy bundle {bundle}: unsupported api_version {version}")]
+    UnsupportedVersion { bundle: String, version: u32 },
+}
+
+/// One policy document, as validated against the `policy_bundle` document schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyBundle {
+    pub api_version: u32,
+    pub id: String,
+    /// Effect when no rule in the set applies.
+    pub default: PolicyEffect,
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub id: String,
+    pub effect: PolicyEffect,
+    /// Command types: exact, `*`, or a `family.*` prefix.
+    pub actions: Vec<String>,
+    /// Required `subject.<key>` attribute values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub subject: BTreeMap<String, OneOrMany>,
+    /// Required `object.<key>` attribute values.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub object: BTreeMap<String, OneOrMany>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub when: Vec<Condition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn contains(&self, value: &str) -> bool {
+        match self {
+            OneOrMany::One(expected) => expected == value,
+            OneOrMany::Many(expected) => expected.iter().any(|item| item == value),
+        }
+    }
+}
+
+/// Attribute predicate; attributes are `subject.*`, `object.*` or `payload.*` names.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Condition {
+    In {
+        attribute: String,
+        values: Vec<String>,
+    },
+    /// Also holds when the attribute is absent.
+    NotIn {
+        attribute: String,
+        values: Vec<String>,
+    },
+    Prefix {
+        attribute: String,
+        values: Vec<String>,
+    },
+    Exists {
+        attribute: String,
+    },
+    Absent {
+        attribute: String,
+    },
+    /// Both attributes are present and equal.
+    Equals {
+        attribute: String,
+        other: String,
+    },
+    /// Holds unless both attributes are present and equal.
+    NotEquals {
+        attribute: String,
+        other: String,
+    },
+}
+
+impl Condition {
+    fn holds(&self, request: &PolicyRequest) -> bool {
+        match self {
+            Condition::In { attribute, values } => request
+                .get(attribute)
+                .is_some_and(|value| values.iter().any(|item| item == value)),
+            Condition::NotIn { attribute, values } => request
+                .get(attribute)
+                .is_none_or(|value| values.iter().all(|item| item != value)),
+            Condition::Prefix { attribute, values } => request
+                .get(attribute)
+                .is_some_and(|value| values.iter().any(|item| value.starts_with(item.as_str()))),
+            Condition::Exists { attribute } => request.get(attribute).is_some(),
+            Condition::Absent { attribute } => request.get(attribute).is_none(),
+            Condition::Equals { attribute, other } => {
+                matches!((request.get(attribute), request.get(other)), (Some(a), Some(b)) if a == b)
+            }
+            Condition::NotEquals { attribute, other } => {
+                !matches!((request.get(attribute), request.get(other)), (Some(a), Some(b)) if a == b)
+            }
+        }
+    }
+}
+
+impl PolicyRule {
+    fn matches_action(&self, action: &str) -> bool {
+        self.actions
+            .iter()
+            .any(|pattern| action_matches(pattern, action))
+    }
+
+    fn applies(&self, request: &PolicyRequest) -> bool {
+        let matcher_holds = |scope: &str, matcher: &BTreeMap<String, OneOrMany>| {
+            matcher.iter().all(|(key, expected)| {
+                request
+                    .get(&format!("{scope}.{key}"))
+                    .is_some_and(|value| expected.contains(value))
+            })
+        };
+        self.matches_action(&request.action)
+            && matcher_holds("subject", &self.subject)
+            && matcher_holds("object", &self.object)
+            && self.when.iter().all(|condition| condition.holds(request))
+    }
+}
+
+/// What a command is evaluated as: the action plus flat string attributes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PolicyRequest {
+    pub action: String,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl PolicyRequest {
+    /// Subject attributes come from the actor. The object is the command family (`kind`), the
+    /// payload's `<family>_id` (`id`), and its workspace and project. Top-level scalar payload
+    /// fields are exposed as `payload.<field>`.
+    pub fn for_command(actor: &Actor, command_type: &str, payload: &Value) -> Self {
+        let mut attributes = BTreeMap::new();
+        attributes.insert("subject.kind".to_string(), actor.kind.clone());
+        attributes.insert("subject.id".to_string(), actor.id.clone());
+        attributes.insert("subject.claimed".to_string(), actor.claimed.to_string());
+        let family = command_type.split('.').next().unwrap_or(command_type);
+        attributes.insert("object.kind".to_string(), family.to_string());
+        if let Some(fields) = payload.as_object() {
+            for (key, value) in fields {
+                let value = match value {
+                    Value::String(value) => value.clone(),
+                    Value::Bool(value) => value.to_string(),
+                    Value::Number(value) => value.to_string(),
+                    _ => continue,
+                };
+                if key == &format!("{family}_id") {
+                    attributes.insert("object.id".to_string(), value.clone());
+                }
+                if key == "workspace_id" || key == "project_id" {
+                    attributes.insert(format!("object.{key}"), value.clone());
+                }
+                attributes.insert(format!("payload.{key}"), value);
+            }
+        }
+        Self {
+            action: command_type.to_string(),
+            attributes,
+        }
+    }
+
+    pub fn get(&self, attribute: &str) -> Option<&str> {
+        self.attributes.get(attribute).map(String::as_str)
+    }
+
+    /// Whether a lease scope admits this request: the action matches one of its patterns and
+    /// every scoped attribute takes one of its listed values.
+    pub fn is_covered_by(&self, scope: &LeaseScope) -> bool {
+        scope
+            .actions
+            .iter()
+            .any(|pattern| action_matches(pattern, &self.action))
+            && scope.attributes.iter().all(|(attribute, values)| {
+                self.get(attribute)
+                    .is_some_and(|value| values.iter().any(|allowed| allowed == value))
+            })
+    }
+}
+
+/// Matches a command type against `*`, a `family.*` prefix, or an exact name.
+pub fn action_matches(pattern: &str, action: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+    match pattern.strip_suffix(".*") {
+        Some(prefix) => action
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.starts_with('.')),
+        None => pattern == action,
+    }
+}
+
+/// `blake3:<hex>` of a bundle's source bytes.
+pub fn bundle_hash(bytes: &[u8]) -> String {
+    format!("blake3:{}", blake3::hash(bytes).to_hex())
+}
+
+#[derive(Debug, Clone)]
+struct LoadedBundle {
+    bundle: PolicyBundle,
+    hash: String,
+}
+
+/// The bundles a daemon enforces. An empty set makes no decisions.
+#[derive(Debug, Clone, Default)]
+pub struct PolicySet {
+    bundles: Vec<LoadedBundle>,
+    hash: Option<String>,
+}
+
+impl PolicySet {
+    /// Takes bundles in evaluation order, each with the hash of its source.
+    pub fn new(bundles: Vec<(PolicyBundle, String)>) -> Result<Self, PolicyError> {
+        let mut ids = BTreeSet::new();
+        for (bundle, _) in &bundles {
+            if bundle.api_version != POLICY_API_VERSION {
+                return Err(PolicyError::UnsupportedVersion {
+                    bundle: bundle.id.clone(),
+                    version: bundle.api_version,
+                });
+            }
+            if !ids.insert(bundle.id.as_str()) {
+                return Err(PolicyError::DuplicateBundle(bundle.id.clone()));
+            }
+            let mut rules = BTreeSet::new();
+            for rule in &bundle.rules {
+                if !rules.insert(rule.id.as_str()) {
+                    return Err(PolicyError::DuplicateRule {
+                        bundle: bundle.id.clone(),
+                        rule: rule.id.clone(),
+                    });
+                }
+            }
+        }
+        let hash = (!bundles.is_empty()).then(|| {
+            let mut sorted: Vec<_> = bundles.iter().collect();
+            sorted.sort_by(|a, b| a.0.id.cmp(&b.0.id));
+            let mut hasher = blake3::Hasher::new();
+            for (bundle, hash) in sorted {
+                hasher.update(bundle.id.as_bytes());
+                hasher.update(b"\0");
+                hasher.update(hash.as_bytes());
+                hasher.update(b"\n");
+            }
+            format!("blake3:{}", hasher.finalize().to_hex())
+        });
+        Ok(Self {
+            bundles: bundles
+                .into_iter()
+                .map(|(bundle, hash)| LoadedBundle { bundle, hash })
+                .collect(),
+            hash,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bundles.is_empty()
+    }
+
+    /// Hash over every bundle id and source hash; recorded with each decision.
+    pub fn hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
+
+    /// `(bundle id, source hash)` in evaluation order.
+    pub fn bundles(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.bundles
+            .iter()
+            .map(|loaded| (loaded.bundle.id.as_str(), loaded.hash.as_str()))
+    }
+
+    pub fn evaluate(&self, request: &PolicyRequest) -> Option<PolicyDecision> {
+        self.explain(request).0
+    }
+
+    /// The decision together with every rule that applied, in evaluation order.
+    pub fn explain(
+        &self,
+        request: &PolicyRequest,
+    ) -> (Option<PolicyDecision>, Vec<PolicyRuleMatch>) {
+        let applicable: Vec<(&PolicyBundle, &PolicyRule)> = self
+            .bundles
+            .iter()
+            .flat_map(|loaded| {
+                loaded
+                    .bundle
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.applies(request))
+                    .map(move |rule| (&loaded.bundle, rule))
+            })
+            .collect();
+        let matched = applicable
+            .iter()
+            .map(|(bundle, rule)| PolicyRuleMatch {
+                bundle_id: bundle.id.clone(),
+                rule_id: rule.id.clone(),
+                effect: rule.effect,
+                rationale: rule.rationale.clone(),
+            })
+            .collect();
+        let Some(policy_hash) = self.hash.as_deref() else {
+            return (None, matched);
+        };
+        let deciding = applicable
+            .iter()
+            .find(|(_, rule)| rule.effect == PolicyEffect::Deny)
+            .or_else(|| applicable.first());
+        if let Some((bundle, rule)) = deciding {
+            return (Some(decision(policy_hash, bundle, rule, request)), matched);
+        }
+        let strictest = self
+            .bundles
+            .iter()
+            .find(|loaded| loaded.bundle.default == PolicyEffect::Deny)
+            .unwrap_or(&self.bundles[0]);
+        let decision = PolicyDecision {
+            effect: strictest.bundle.default,
+            policy_hash: policy_hash.to_string(),
+            bundle_id: Some(strictest.bundle.id.clone()),
+            rule_id: None,
+            rationale: format!(
+                "no rule matched {}; default {} from bundle {}",
+                request.action, strictest.bundle.default, strictest.bundle.id
+            ),
+        };
+        (Some(decision), matched)
+    }
+}
+
+fn decision(
+    policy_hash: &str,
+    bundle: &PolicyBundle,
+    rule: &PolicyRule,
+    request: &PolicyRequest,
+) -> PolicyDecision {
+    PolicyDecision {
+        effect: rule.effect,
+        policy_hash: policy_hash.to_string(),
+        bundle_id: Some(bundle.id.clone()),
+        rule_id: Some(rule.id.clone()),
+        rationale: rule.rationale.clone().unwrap_or_else(|| {
+            format!(
+                "rule {}/{} {}s {}",
+                bundle.id, rule.id, rule.effect, request.action
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn bundle(value: Value) -> (PolicyBundle, String) {
+        let bytes = serde_json::to_vec(&value).expect("bytes");
+        (
+            serde_json::from_value(value).expect("bundle"),
+            bundle_hash(&bytes),
+        )
+    }
+
+    fn session(id: &str) -> Actor {
+        Actor {
+            kind: "session".to_string(),
+            id: id.to_string(),
+            label: None,
+            claimed: false,
+        }
+    }
+
+    #[test]
+    fn request_exposes_subject_object_and_payload_attributes() {
+        let request = PolicyRequest::for_command(
+            &session("s1"),
+            "worktree.create",
+            &json!({"workspace_id": "w1", "worktree_id": "wt1", "force": true, "nested": {}}),
+        );
+        assert_eq!(request.get("subject.kind"), Some("session"));
+        assert_eq!(request.get("object.kind"), Some("worktree"));
+        assert_eq!(request.get("object.id"), Some("wt1"));
+        assert_eq!(request.get("object.workspace_id"), Some("w1"));
+        assert_eq!(request.get("payload.force"), Some("true"));
+        assert_eq!(request.get("payload.nested"), None);
+        assert_eq!(request.get("object.project_id"), None);
+    }
+
+    #[test]
+    fn lease_scope_covers_matching_actions_and_attributes() {
+        let request = PolicyRequest::for_command(
+            &session("s1"),
+            "tool.request",
+            &json!({"workspace_id": "w1", "tool_id": "net.fetch"}),
+        );
+        let scope = |actions: &[&str], attributes: &[(&str, &[&str])]| LeaseScope {
+            actions: actions.iter().map(|action| action.to_string()).collect(),
+            attributes: attributes
+                .iter()
+                .map(|(key, values)| {
+                    let values = values.iter().map(|value| value.to_string()).collect();
+                    (key.to_string(), values)
+                })
+                .collect(),
+        };
+        assert!(request.is_covered_by(&scope(&["tool.*"], &[])));
+        assert!(request.is_covered_by(&scope(
+            &["tool.request"],
+            &[("payload.tool_id", &["git.push", "net.fetch"])]
+        )));
+        assert!(!request.is_covered_by(&scope(&["tool.complete", "toolbox.*"], &[])));
+        assert!(!request.is_covered_by(&scope(&["*"], &[("payload.tool_id", &["git.push"])])));
+        assert!(!request.is_covered_by(&scope(&["*"], &[("object.project_id", &["p1"])])));
+    }
+
+    #[test]
+    fn deny_wins_over_earlier_allows() {
+        let set = PolicySet::new(vec![
+            bundle(json!({
+                "api_version": 1, "id": "base", "default": "allow",
+                "rules": [{"id": "sessions-may-push", "effect": "allow",
+                           "actions": ["tool.*"], "subject": {"kind": "session"}}]
+            })),
+            bundle(json!({
+                "api_version": 1, "id": "guard", "default": "allow",
+                "rules": [{"id": "no-push", "effect": "deny", "actions": ["tool.request"],
+                           "when": [{"op": "in", "attribute": "payload.tool_id",
+                                     "values": ["git.push"]}],
+                           "rationale": "pushes need a human"}]
+            })),
+        ])
+        .expect("set");
+        let deny = set
+            .evaluate(&PolicyRequest::for_command(
+                &session("s1"),
+                "tool.request",
+                &json!({"tool_id": "git.push"}),
+            ))
+            .expect("decision");
+        assert_eq!(deny.effect, PolicyEffect::Deny);
+        assert_eq!(deny.bundle_id.as_deref(), Some("guard"));
+        assert_eq!(deny.rule_id.as_deref(), Some("no-push"));
+        assert_eq!(deny.rationale, "pushes need a human");
+        assert_eq!(deny.policy_hash, set.hash().expect("hash"));
+
+        let allow = set
+            .evaluate(&PolicyRequest::for_command(
+                &session("s1"),
+                "tool.request",
+                &json!({"tool_id": "git.status"}),
+            ))
+            .expect("decision");
+        assert_eq!(allow.effect, PolicyEffect::Allow);
+        assert_eq!(allow.rule_id.as_deref(), Some("sessions-may-push"));
+    }
+
+    #[test]
+    fn unmatched_requests_fall_back_to_the_strictest_default() {
+        let set = PolicySet::new(vec![
+            bundle(json!({"api_version": 1, "id": "open", "default": "allow", "rules": []})),
+            bundle(json!({
+                "api_version": 1, "id": "closed", "default": "deny",
+                "rules": [{"id": "users", "effect": "allow", "actions": ["*"],
+                           "subject": {"kind": ["user"]}}]
+            })),
+        ])
+        .expect("set");
+        let user = Actor {
+            kind: "user".to_string(),
+            id: "alice".to_string(),
+            label: None,
+            claimed: false,
+        };
+        let allowed = set
+            .evaluate(&PolicyRequest::for_command(
+                &user,
+                "task.create",
+                &json!({}),
+            ))
+            .expect("decision");
+        assert_eq!(allowed.effect, PolicyEffect::Allow);
+        let denied = set
+            .evaluate(&PolicyRequest::for_command(
+                &session("s1"),
+                "task.create",
+                &json!({}),
+            ))
+            .expect("decision");
+        assert_eq!(denied.effect, PolicyEffect::Deny);
+        assert_eq!(denied.bundle_id.as_deref(), Some("closed"));
+        assert_eq!(denied.rule_id, None);
+        assert!(PolicySet::default()
+            .evaluate(&PolicyRequest::default())
+            .is_none());
+    }
+
+    #[test]
+    fn conditions_compare_attributes() {
+        let rule: PolicyRule = serde_json::from_value(json!({
+            "id": "own-session", "effect": "allow", "actions": ["session.*"],
+            "when": [
+                {"op": "equals", "attribute": "subject.id", "other": "object.id"},
+                {"op": "prefix", "attribute": "payload.branch", "values": ["agent/"]},
+                {"op": "not_in", "attribute": "payload.mode", "values": ["force"]}
+            ]
+        }))
+        .expect("rule");
+        let request = |id: &str, branch: &str| {
+            PolicyRequest::for_command(
+                &session("s1"),
+                "session.fork",
+                &json!({"session_id": id, "branch": branch}),
+            )
+        };
+        assert!(rule.applies(&request("s1", "agent/x")));
+        assert!(!rule.applies(&request("s2", "agent/x")));
+        assert!(!rule.applies(&request("s1", "main")));
+        assert!(!rule.matches_action("sessions.fork"));
+        assert!(!rule.matches_action("session"));
+    }
+
+    #[test]
+    fn set_rejects_duplicates_and_hashes_independently_of_order() {
+        let a = bundle(json!({"api_version": 1, "id": "a", "default": "allow"}));
+        let b = bundle(json!({"api_version": 1, "id": "b", "default": "deny"}));
+        let forward = PolicySet::new(vec![a.clone(), b.clone()]).expect("set");
+        let backward = PolicySet::new(vec![b, a.clone()]).expect("set");
+        assert_eq!(forward.hash(), backward.hash());
+        assert_eq!(
+            PolicySet::new(vec![a.clone(), a]).unwrap_err(),
+            PolicyError::DuplicateBundle("a".to_string())
+        );
+        let dup = bundle(json!({
+            "api_version": 1, "id": "c", "default": "allow",
+            "rules": [{"id": "r", "effect": "allow", "actions": ["*"]},
+                      {"id": "r", "effect": "deny", "actions": ["*"]}]
+        }));
+        assert!(matches!(
+            PolicySet::new(vec![dup]),
+            Err(PolicyError::DuplicateRule { .. })
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+use crate::{OneOrMany, PolicyBundle, PolicyRule, POLICY_API_VERSION};
+use mp_kernel::PolicyEffect;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Commands are submitted by operators; approvers only decide gates and skill reviews.
+const OPERATOR_ACTIONS: &[&str] = &[
+    "project.*",
+    "session.*",
+    "task.*",
+    "worktree.*",
+    "message.*",
+    "board.*",
+    "capsule.*",
+    "artifact.*",
+    "tool.*",
+    "pipeline.bind",
+    "stage.transition",
+    "blueprint.sync",
+    "skill.sync",
+];
+const APPROVER_ACTIONS: &[&str] = &["gate.approve", "gate.reject", "skill.approve"];
+const ADMIN_ACTIONS: &[&str] = &["*"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PolicyRole {
+    /// Reads through query endpoints only; may submit no commands.
+    Viewer,
+    /// Runs day-to-day work: projects, sessions, tasks, worktrees, tools and boards.
+    Operator,
+    /// Decides gates and approves proposed skills.
+    Approver,
+    /// Everything, including workspace lifecycle, gate and pipeline definitions.
+    Admin,
+}
+
+impl PolicyRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyRole::Viewer => "viewer",
+            PolicyRole::Operator => "operator",
+            PolicyRole::Approver => "approver",
+            PolicyRole::Admin => "admin",
+        }
+    }
+
+    /// Command patterns the role may submit, in policy rule syntax.
+    pub fn actions(&self) -> &'static [&'static str] {
+        match self {
+            PolicyRole::Viewer => &[],
+            PolicyRole::Operator => OPERATOR_ACTIONS,
+            PolicyRole::Approver => APPROVER_ACTIONS,
+            PolicyRole::Admin => ADMIN_ACTIONS,
+        }
+    }
+}
+
+impl fmt::Display for PolicyRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A `role_bindings` document: who holds which role, and where.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleBindings {
+    pub api_version: u32,
+    pub id: String,
+    pub bindings: Vec<RoleBinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleBinding {
+    pub role: PolicyRole,
+    pub subject: RoleSubject,
+    /// Limits the binding to commands on this workspace; unset binds everywhere.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    /// Further limits a workspace binding to commands naming this project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RoleSubject {
+    pub kind: String,
+    /// Unset binds every actor of the kind.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ids: Vec<String>,
+}
+
+impl RoleBindings {
+    /// Compiles the bindings into a default-deny bundle with one allow rule per binding, so
+    /// anything not granted by a role is denied. The daemon's own `system` actor stays
+    /// unrestricted; clients can never submit as it, and the daemon token's `token` actor needs
+    /// a binding like anyone else. Bindings only match unclaimed actors: a client that names
+    /// itself `user:alice` has not shown it is alice, so it does not get alice's roles.
+    pub fn compile(&self) -> PolicyBundle {
+        let mut rules = vec![PolicyRule {
+            id: "system".to_string(),
+            effect: PolicyEffect::Allow,
+            actions: vec!["*".to_string()],
+            subject: BTreeMap::from([("kind".to_string(), OneOrMany::One("system".to_string()))]),
+            object: BTreeMap::new(),
+            when: Vec::new(),
+            rationale: Some("the daemon's system actor is not bound by roles".to_string()),
+        }];
+        for (index, binding) in self.bindings.iter().enumerate() {
+            if binding.role.actions().is_empty() {
+                continue;
+            }
+            rules.push(binding.compile(index));
+        }
+        PolicyBundle {
+            api_version: POLICY_API_VERSION,
+            id: format!("roles.{}", self.id),
+            default: PolicyEffect::Deny,
+            rules,
+        }
+    }
+}
+
+impl RoleBinding {
+    fn compile(&self, index: usize) -> PolicyRule {
+        let mut subject = BTreeMap::from([(
+            "kind".to_string(),
+            OneOrMany::One(self.subject.kind.clone()),
+        )]);
+        if !self.subject.ids.is_empty() {
+            subject.insert("id".to_string(), OneOrMany::Many(self.subject.ids.clone()));
+        }
+        subject.insert("claimed".to_string(), OneOrMany::One("false".to_string()));
+        let mut object = BTreeMap::new();
+        if let Some(workspace_id) = &self.workspace_id {
+            object.insert(
+                "workspace_id".to_string(),
+                OneOrMany::One(workspace_id.clone()),
+            );
+        }
+        if let Some(project_id) = &self.project_id {
+            object.insert("project_id".to_string(), OneOrMany::One(project_id.clone()));
+        }
+
+        let holders = if self.subject.ids.is_empty() {
+            format!("every {}", self.subject.kind)
+        } else {
+            format!("{} {}", self.subject.kind, self.subject.ids.join(", "))
+        };
+        let scope = match (&self.workspace_id, &self.project_id) {
+            (Some(workspace_id), Some(project_id)) => {
+                format!("in project {project_id} of workspace {workspace_id}")
+            }
+            (Some(workspace_id), None) => format!("in workspace {workspace_id}"),
+            _ => "everywhere".to_string(),
+        };
+        PolicyRule {
+            id: format!("{}-{index}", self.role),
+            effect: PolicyEffect::Allow,
+            actions: self
+                .role
+                .actions()
+                .iter()
+                .map(|action| action.to_string())
+                .collect(),
+            subject,
+            object,
+            when: Vec::new(),
+            rationale: Some(format!("{holders} holds role {} {scope}", self.role)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bundle_hash, PolicyRequest, PolicySet};
+    use mp_kernel::Actor;
+    use serde_json::json;
+
+    fn actor(kind: &str, id: &str) -> Actor {
+        Actor {
+            kind: kind.to_string(),
+            id: id.to_string(),
+            label: None,
+            claimed: false,
+        }
+    }
+
+    #[test]
+    fn bindings_compile_to_scoped_allows_under_default_deny() {
+        let bindings: RoleBindings = serde_json::from_value(json!({
+            "api_version": 1,
+            "id": "team",
+            "bindings": [
+                {"role": "operator", "subject": {"kind": "user", "ids": ["alice"]},
+                 "workspace_id": "w1"},
+                {"role": "approver", "subject": {"kind": "user", "ids": ["bob"]}},
+                {"role": "viewer", "subject": {"kind": "session"}},
+                {"role": "operator", "subject": {"kind": "session"},
+                 "workspace_id": "w1", "project_id": "p1"}
+            ]
+        }))
+        .expect("bindings");
+        let bundle = bindings.compile();
+        assert_eq!(bundle.id, "roles.team");
+        let ids: Vec<&str> = bundle.rules.iter().map(|rule| rule.id.as_str()).collect();
+        assert_eq!(ids, ["system", "operator-0", "approver-1", "operator-3"]);
+        let set = PolicySet::new(vec![(bundle, bundle_hash(b"team"))]).expect("set");
+        let decide = |actor: &Actor, command: &str, payload: serde_json::Value| {
+            set.evaluate(&PolicyRequest::for_command(actor, command, &payload))
+                .expect("decision")
+        };
+
+        let alice = actor("user", "alice");
+        let allowed = decide(&alice, "task.create", json!({"workspace_id": "w1"}));
+        assert_eq!(allowed.effect, PolicyEffect::Allow);
+        assert_eq!(
+            allowed.rationale,
+            "user alice holds role operator in workspace w1"
+        );
+        let elsewhere = decide(&alice, "task.create", json!({"workspace_id": "w2"}));
+        assert_eq!(elsewhere.effect, PolicyEffect::Deny);
+        assert_eq!(elsewhere.rule_id, None);
+        let approval = decide(&alice, "gate.approve", json!({"workspace_id": "w1"}));
+        assert_eq!(approval.effect, PolicyEffect::Deny);
+        let claimed = Actor {
+            claimed: true,
+            ..alice.clone()
+        };
+        let impersonated = decide(&claimed, "task.create", json!({"workspace_id": "w1"}));
+        assert_eq!(impersonated.effect, PolicyEffect::Deny);
+        assert_eq!(impersonated.rule_id, None);
+
+        let bob = actor("user", "bob");
+        let approved = decide(&bob, "gate.approve", json!({"workspace_id": "w9"}));
+        assert_eq!(approved.rule_id.as_deref(), Some("approver-1"));
+
+        let agent = actor("session", "s1");
+        let in_project = json!({"workspace_id": "w1", "project_id": "p1"});
+        assert_eq!(
+            decide(&agent, "tool.request", in_project).effect,
+            PolicyEffect::Allow
+        );
+        assert_eq!(
+            decide(&agent, "tool.request", json!({"workspace_id": "w1"})).effect,
+            PolicyEffect::Deny
+        );
+        assert_eq!(
+            decide(&Actor::system(), "workspace.create", json!({})).effect,
+            PolicyEffect::Allow
+        );
+        assert_eq!(
+            decide(&Actor::token_holder(), "workspace.create", json!({})).effect,
+            PolicyEffect::Deny
+        );
+    }
+}
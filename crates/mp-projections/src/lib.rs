@@ -1,5 +1,30 @@
 use mp_kernel::{
-    ProjectCreatedPayload, WorkspaceCreatedPayload, EVENT_PROJECT_CREATED, EVENT_WORKSPACE_CREATED,
+    board_label, board_node_id, lineage_depth, ArtifactStoredPayload, BlueprintEntry,
+    BlueprintVersionPayload, BoardCommentAddedPayload, BoardEdge, BoardEdgeKind,
+    BoardGroupCreatedPayload, BoardNode, BoardNodeMovedPayload, BoardPosition, CapsuleEntry,
+    CapsuleWrittenPayload, GateDecidedPayload, GateDecisionEntry, GateDefinedPayload, GateEntry,
+    GateStatus, LeaseEntry, LeaseMintedPayload, LeaseStatus, LifecycleChangedPayload,
+    PipelineBindingEntry, PipelineBoundPayload, PipelineTemplateDefinedPayload,
+    PipelineTemplateEntry, ProjectCreatedPayload, RenamedPayload, SecretEntry,
+    SecretVersionPayload, SessionForkedPayload, SessionListEntry, SessionSpawnedPayload,
+    SkillApprovedPayload, SkillEntry, SkillRemovedPayload, SkillVersionPayload, TaskAction,
+    TaskCreatedPayload, TaskListEntry, TaskRetryScheduledPayload, TaskStageChangedPayload,
+    TaskState, TaskTimedOutPayload, TaskTransitionedPayload, ToolCallEntry, ToolCallStatus,
+    ToolRequestedPayload, ToolResultPayload, WorkspaceCreatedPayload, WorktreeEntry, WorktreeLock,
+    WorktreeLockReleasedPayload, WorktreeRegisteredPayload, WorktreeSessionChangedPayload,
+    BOARD_NODE_KINDS, EVENT_ARTIFACT_STORED, EVENT_BLUEPRINT_REGISTERED,
+    EVENT_BLUEPRINT_VERSION_CREATED, EVENT_BOARD_COMMENT_ADDED, EVENT_BOARD_GROUP_CREATED,
+    EVENT_BOARD_NODE_MOVED, EVENT_CAPSULE_WRITTEN, EVENT_GATE_APPROVED, EVENT_GATE_DEFINED,
+    EVENT_GATE_REJECTED, EVENT_LEASE_EXPIRED, EVENT_LEASE_MINTED, EVENT_LEASE_REVOKED,
+    EVENT_LEASE_USED, EVENT_PIPELINE_BOUND, EVENT_PIPELINE_TEMPLATE_DEFINED,
+    EVENT_PROJECT_ARCHIVED, EVENT_PROJECT_CREATED, EVENT_PROJECT_RENAMED, EVENT_PROJECT_RESTORED,
+    EVENT_SECRET_CREATED, EVENT_SECRET_DELETED, EVENT_SECRET_ROTATED, EVENT_SESSION_FORKED,
+    EVENT_SESSION_SPAWNED, EVENT_SKILL_APPROVED, EVENT_SKILL_REGISTERED, EVENT_SKILL_REMOVED,
+    EVENT_SKILL_UPDATED, EVENT_TASK_CREATED, EVENT_TASK_RETRY_SCHEDULED, EVENT_TASK_STAGE_CHANGED,
+    EVENT_TASK_TIMED_OUT, EVENT_TOOL_REQUESTED, EVENT_TOOL_RESULT, EVENT_WORKSPACE_ARCHIVED,
+    EVENT_WORKSPACE_CREATED, EVENT_WORKSPACE_RENAMED, EVENT_WORKSPACE_RESTORED,
+    EVENT_WORKTREE_ATTACHED, EVENT_WORKTREE_DETACHED, EVENT_WORKTREE_LOCK_ACQUIRED,
+    EVENT_WORKTREE_LOCK_RELEASED, EVENT_WORKTREE_REGISTERED,
 };
 use mp_protocol::EventEnvelope;
 use serde_json::from_value;
@@ -29,6 +54,173 @@ pub trait ProjectionWriter {
         created_at: &str,
         seq_global: i64,
     ) -> Result<(), ProjectionError>;
+    fn rename_workspace(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Archives the workspace at `archived_at`, or restores it when `None`.
+    fn set_workspace_archived(
+        &self,
+        workspace_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn rename_project(
+        &self,
+        project_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Archives the project at `archived_at`, or restores it when `None`.
+    fn set_project_archived(
+        &self,
+        project_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn upsert_session(&self, session: &SessionListEntry) -> Result<(), ProjectionError>;
+    fn upsert_task(&self, task: &TaskListEntry) -> Result<(), ProjectionError>;
+    fn update_task_state(
+        &self,
+        task_id: &str,
+        state: TaskState,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Moves the task to running and bumps its attempt counter.
+    fn start_task_attempt(
+        &self,
+        task_id: &str,
+        started_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Re-queues a failed task that may not start again before `retry_at`.
+    fn schedule_task_retry(
+        &self,
+        task_id: &str,
+        retry_at: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn upsert_pipeline_template(
+        &self,
+        template: &PipelineTemplateEntry,
+    ) -> Result<(), ProjectionError>;
+    fn upsert_pipeline_binding(
+        &self,
+        binding: &PipelineBindingEntry,
+    ) -> Result<(), ProjectionError>;
+    fn update_task_stage(
+        &self,
+        task_id: &str,
+        stage: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn upsert_gate(&self, gate: &GateEntry) -> Result<(), ProjectionError>;
+    /// Appends an approval or rejection and moves the gate to `status`.
+    fn record_gate_decision(
+        &self,
+        gate_id: &str,
+        decision: &GateDecisionEntry,
+        status: GateStatus,
+    ) -> Result<(), ProjectionError>;
+    fn upsert_worktree(&self, worktree: &WorktreeEntry) -> Result<(), ProjectionError>;
+    fn attach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        attached_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn detach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Sets or clears (`lock == None`) the single-writer lock of a worktree.
+    fn set_worktree_lock(
+        &self,
+        worktree_id: &str,
+        lock: Option<&WorktreeLock>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Inserts a board node, or refreshes an existing one; `None` fields and layout are kept.
+    fn touch_board_node(&self, node: &BoardNode) -> Result<(), ProjectionError>;
+    fn set_board_node_layout(
+        &self,
+        node_id: &str,
+        position: &BoardPosition,
+        group_node_id: Option<&str>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn add_board_edge(&self, workspace_id: &str, edge: &BoardEdge) -> Result<(), ProjectionError>;
+    fn remove_board_edge(
+        &self,
+        kind: BoardEdgeKind,
+        from_node_id: &str,
+        to_node_id: &str,
+    ) -> Result<(), ProjectionError>;
+    /// Records one capsule version; earlier versions are kept.
+    fn insert_capsule(&self, capsule: &CapsuleEntry) -> Result<(), ProjectionError>;
+    /// Records one blueprint version; earlier versions are kept.
+    fn insert_blueprint(&self, blueprint: &BlueprintEntry) -> Result<(), ProjectionError>;
+    fn insert_tool_call(&self, call: &ToolCallEntry) -> Result<(), ProjectionError>;
+    /// Settles a pending call with its validated output or error.
+    fn complete_tool_call(
+        &self,
+        request_id: &str,
+        result: &ToolResultPayload,
+        completed_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Records a skill's current revision, keeping any earlier approval.
+    fn upsert_skill(&self, skill: &SkillEntry) -> Result<(), ProjectionError>;
+    /// Records an approval; a proposed skill whose content still matches becomes sandboxed.
+    fn approve_skill(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+        content_hash: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn remove_skill(&self, workspace_id: &str, skill_id: &str) -> Result<(), ProjectionError>;
+    fn insert_lease(&self, lease: &LeaseEntry) -> Result<(), ProjectionError>;
+    fn record_lease_use(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Ends an active lease; a lease that already ended keeps its first status.
+    fn end_lease(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+        status: LeaseStatus,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    fn insert_secret(&self, secret: &SecretEntry) -> Result<(), ProjectionError>;
+    fn rotate_secret(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+        version: u32,
+        length: usize,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError>;
+    /// Writers that also hold the secret's ciphertext drop it here, in the same transaction.
+    fn delete_secret(&self, workspace_id: &str, secret_id: &str) -> Result<(), ProjectionError>;
     fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError>;
 }
 
@@ -36,6 +228,7 @@ pub fn apply_event<W: ProjectionWriter>(
     writer: &W,
     event: &EventEnvelope,
 ) -> Result<(), ProjectionError> {
+    touch_board_node(writer, event)?;
     match event.event_type.as_str() {
         EVENT_WORKSPACE_CREATED => {
             let payload: WorkspaceCreatedPayload =
@@ -69,6 +262,593 @@ pub fn apply_event<W: ProjectionWriter>(
             )?;
             writer.set_meta(&event.workspace_id, event.seq_global)?;
         }
+        EVENT_WORKSPACE_RENAMED | EVENT_PROJECT_RENAMED => {
+            let payload: RenamedPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+            })?;
+            if event.event_type == EVENT_WORKSPACE_RENAMED {
+                writer.rename_workspace(&event.workspace_id, &payload.name, event.seq_global)?;
+            } else {
+                writer.rename_project(&event.subject.id, &payload.name, event.seq_global)?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_WORKSPACE_ARCHIVED
+        | EVENT_WORKSPACE_RESTORED
+        | EVENT_PROJECT_ARCHIVED
+        | EVENT_PROJECT_RESTORED => {
+            let _payload: LifecycleChangedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+                })?;
+            let archived_at = match event.event_type.as_str() {
+                EVENT_WORKSPACE_ARCHIVED | EVENT_PROJECT_ARCHIVED => Some(event.timestamp.as_str()),
+                _ => None,
+            };
+            if event.subject.kind == "workspace" {
+                writer.set_workspace_archived(
+                    &event.workspace_id,
+                    archived_at,
+                    event.seq_global,
+                )?;
+            } else {
+                writer.set_project_archived(&event.subject.id, archived_at, event.seq_global)?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SESSION_SPAWNED => {
+            let payload: SessionSpawnedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid session.spawned payload: {err}"))
+                })?;
+            if let Some(parent_session_id) = &payload.parent_session_id {
+                add_lineage_edge(writer, event, parent_session_id)?;
+            }
+            writer.upsert_session(&SessionListEntry {
+                session_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                project_id: event.project_id.clone(),
+                parent_session_id: payload.parent_session_id,
+                root_session_id: payload.root_session_id,
+                depth: lineage_depth(&payload.lineage_path),
+                lineage_path: payload.lineage_path,
+                forked_from_session_id: None,
+                fork_mode: None,
+                label: payload.label,
+                created_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SESSION_FORKED => {
+            let payload: SessionForkedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid session.forked payload: {err}"))
+                })?;
+            add_lineage_edge(writer, event, &payload.parent_session_id)?;
+            writer.upsert_session(&SessionListEntry {
+                session_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                project_id: event.project_id.clone(),
+                parent_session_id: Some(payload.parent_session_id),
+                root_session_id: payload.root_session_id,
+                depth: lineage_depth(&payload.lineage_path),
+                lineage_path: payload.lineage_path,
+                forked_from_session_id: Some(payload.forked_from_session_id),
+                fork_mode: Some(payload.mode),
+                label: payload.label,
+                created_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TASK_CREATED => {
+            let payload: TaskCreatedPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid task.created payload: {err}"))
+            })?;
+            writer.upsert_task(&TaskListEntry {
+                task_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                project_id: event.project_id.clone(),
+                session_id: payload.session_id,
+                title: payload.title,
+                state: payload.state,
+                deadline_at: payload.deadline_at,
+                attempt: 0,
+                retry: payload.retry,
+                timeout_ms: payload.timeout_ms,
+                attempt_started_at: None,
+                retry_at: None,
+                current_stage: None,
+                created_at: event.timestamp.clone(),
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_PIPELINE_TEMPLATE_DEFINED => {
+            let payload: PipelineTemplateDefinedPayload = from_value(event.payload.clone())
+                .map_err(|err| {
+                    ProjectionError::Apply(format!(
+                        "invalid pipeline.template.defined payload: {err}"
+                    ))
+                })?;
+            writer.upsert_pipeline_template(&PipelineTemplateEntry {
+                template_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                name: payload.name,
+                version: payload.version,
+                stages: payload.stages,
+                created_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_PIPELINE_BOUND => {
+            let payload: PipelineBoundPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid pipeline.bound payload: {err}"))
+                })?;
+            writer.upsert_pipeline_binding(&PipelineBindingEntry {
+                project_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                template_id: payload.template_id,
+                template_version: payload.template_version,
+                bound_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TASK_STAGE_CHANGED => {
+            let payload: TaskStageChangedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid task.stage_changed payload: {err}"))
+                })?;
+            writer.update_task_stage(
+                &event.subject.id,
+                &payload.to_stage,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_GATE_DEFINED => {
+            let payload: GateDefinedPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid gate.defined payload: {err}"))
+            })?;
+            writer.add_board_edge(
+                &event.workspace_id,
+                &BoardEdge {
+                    kind: BoardEdgeKind::Dependency,
+                    from_node_id: board_node_id(&event.subject.kind, &event.subject.id),
+                    to_node_id: board_node_id(
+                        &payload.scope.subject.kind,
+                        &payload.scope.subject.id,
+                    ),
+                    seq_global: event.seq_global,
+                },
+            )?;
+            writer.upsert_gate(&GateEntry {
+                gate_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                scope: payload.scope,
+                kind: payload.kind,
+                required_approvals: payload.required_approvals,
+                delay_ms: payload.delay_ms,
+                description: payload.description,
+                status: GateStatus::Open,
+                decisions: Vec::new(),
+                created_at: event.timestamp.clone(),
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_GATE_APPROVED | EVENT_GATE_REJECTED => {
+            let payload: GateDecidedPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+            })?;
+            writer.record_gate_decision(
+                &event.subject.id,
+                &GateDecisionEntry {
+                    approved: event.event_type == EVENT_GATE_APPROVED,
+                    approver: payload.approver,
+                    reason: payload.reason,
+                    evidence: payload.evidence,
+                    decided_at: event.timestamp.clone(),
+                    seq_global: event.seq_global,
+                },
+                payload.status,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_WORKTREE_REGISTERED => {
+            let payload: WorktreeRegisteredPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid worktree.registered payload: {err}"))
+                })?;
+            writer.upsert_worktree(&WorktreeEntry {
+                worktree_id: event.subject.id.clone(),
+                workspace_id: event.workspace_id.clone(),
+                project_id: event.project_id.clone(),
+                path: payload.path,
+                branch: payload.branch,
+                repo: payload.repo,
+                sessions: Vec::new(),
+                lock: None,
+                created_at: event.timestamp.clone(),
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_WORKTREE_ATTACHED | EVENT_WORKTREE_DETACHED | EVENT_WORKTREE_LOCK_ACQUIRED => {
+            let payload: WorktreeSessionChangedPayload = from_value(event.payload.clone())
+                .map_err(|err| {
+                    ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+                })?;
+            let worktree_id = &event.subject.id;
+            let session_node_id = board_node_id("session", &payload.session_id);
+            let worktree_node_id = board_node_id(&event.subject.kind, worktree_id);
+            match event.event_type.as_str() {
+                EVENT_WORKTREE_ATTACHED => writer.add_board_edge(
+                    &event.workspace_id,
+                    &BoardEdge {
+                        kind: BoardEdgeKind::Attachment,
+                        from_node_id: session_node_id,
+                        to_node_id: worktree_node_id,
+                        seq_global: event.seq_global,
+                    },
+                )?,
+                EVENT_WORKTREE_DETACHED => writer.remove_board_edge(
+                    BoardEdgeKind::Attachment,
+                    &session_node_id,
+                    &worktree_node_id,
+                )?,
+                _ => {}
+            }
+            match event.event_type.as_str() {
+                EVENT_WORKTREE_ATTACHED => writer.attach_worktree_session(
+                    worktree_id,
+                    &payload.session_id,
+                    &event.timestamp,
+                    event.seq_global,
+                )?,
+                EVENT_WORKTREE_DETACHED => writer.detach_worktree_session(
+                    worktree_id,
+                    &payload.session_id,
+                    &event.timestamp,
+                    event.seq_global,
+                )?,
+                _ => writer.set_worktree_lock(
+                    worktree_id,
+                    Some(&WorktreeLock {
+                        session_id: payload.session_id,
+                        acquired_at: event.timestamp.clone(),
+                    }),
+                    &event.timestamp,
+                    event.seq_global,
+                )?,
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_WORKTREE_LOCK_RELEASED => {
+            let _payload: WorktreeLockReleasedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid worktree.lock.released payload: {err}"))
+                })?;
+            writer.set_worktree_lock(
+                &event.subject.id,
+                None,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_BOARD_GROUP_CREATED => {
+            let payload: BoardGroupCreatedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid board.group.created payload: {err}"))
+                })?;
+            if let Some(position) = &payload.position {
+                set_own_board_layout(writer, event, position, None)?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_BOARD_COMMENT_ADDED => {
+            let payload: BoardCommentAddedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid board.comment.added payload: {err}"))
+                })?;
+            if let Some(position) = &payload.position {
+                set_own_board_layout(writer, event, position, None)?;
+            }
+            if let Some(target_node_id) = payload.target_node_id {
+                writer.add_board_edge(
+                    &event.workspace_id,
+                    &BoardEdge {
+                        kind: BoardEdgeKind::Annotation,
+                        from_node_id: board_node_id(&event.subject.kind, &event.subject.id),
+                        to_node_id: target_node_id,
+                        seq_global: event.seq_global,
+                    },
+                )?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_ARTIFACT_STORED => {
+            let payload: ArtifactStoredPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid artifact.stored payload: {err}"))
+                })?;
+            if let Some(producer) = payload.producer {
+                writer.add_board_edge(
+                    &event.workspace_id,
+                    &BoardEdge {
+                        kind: BoardEdgeKind::Provenance,
+                        from_node_id: board_node_id(&producer.kind, &producer.id),
+                        to_node_id: board_node_id(&event.subject.kind, &event.subject.id),
+                        seq_global: event.seq_global,
+                    },
+                )?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_BOARD_NODE_MOVED => {
+            let payload: BoardNodeMovedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid board.node.moved payload: {err}"))
+                })?;
+            set_own_board_layout(
+                writer,
+                event,
+                &payload.position,
+                payload.group_node_id.as_deref(),
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_CAPSULE_WRITTEN => {
+            let payload: CapsuleWrittenPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid capsule.written payload: {err}"))
+                })?;
+            writer.insert_capsule(&CapsuleEntry {
+                workspace_id: event.workspace_id.clone(),
+                subject: event.subject.clone(),
+                kind: payload.kind,
+                version: payload.version,
+                content: payload.content,
+                chars: payload.chars,
+                created_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_BLUEPRINT_REGISTERED | EVENT_BLUEPRINT_VERSION_CREATED => {
+            let payload: BlueprintVersionPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+                })?;
+            writer.insert_blueprint(&BlueprintEntry {
+                workspace_id: event.workspace_id.clone(),
+                blueprint_id: payload.blueprint_id,
+                version: payload.version,
+                name: payload.name,
+                path: payload.path,
+                content_hash: payload.content_hash,
+                blueprint: payload.blueprint,
+                created_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TOOL_REQUESTED => {
+            let payload: ToolRequestedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid tool.requested payload: {err}"))
+                })?;
+            writer.insert_tool_call(&ToolCallEntry {
+                workspace_id: event.workspace_id.clone(),
+                request_id: event.subject.id.clone(),
+                tool_id: payload.tool_id,
+                tool_version: payload.tool_version,
+                side_effect: payload.side_effect,
+                input: payload.input,
+                session_id: payload.session_id,
+                status: ToolCallStatus::Pending,
+                output: None,
+                error: None,
+                requested_at: event.timestamp.clone(),
+                completed_at: None,
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TOOL_RESULT => {
+            let payload: ToolResultPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid tool.result payload: {err}"))
+            })?;
+            writer.complete_tool_call(
+                &event.subject.id,
+                &payload,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SKILL_REGISTERED | EVENT_SKILL_UPDATED => {
+            let payload: SkillVersionPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid {} payload: {err}", event.event_type))
+                })?;
+            writer.upsert_skill(&SkillEntry {
+                workspace_id: event.workspace_id.clone(),
+                skill_id: payload.skill_id,
+                revision: payload.revision,
+                scope: payload.scope,
+                path: payload.path,
+                content_hash: payload.content_hash,
+                trust: payload.trust,
+                signature: payload.signature,
+                frontmatter: payload.frontmatter,
+                components: payload.components,
+                approved_hash: None,
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SKILL_APPROVED => {
+            let payload: SkillApprovedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid skill.approved payload: {err}"))
+                })?;
+            writer.approve_skill(
+                &event.workspace_id,
+                &payload.skill_id,
+                &payload.content_hash,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SKILL_REMOVED => {
+            let payload: SkillRemovedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid skill.removed payload: {err}"))
+                })?;
+            writer.remove_skill(&event.workspace_id, &payload.skill_id)?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_LEASE_MINTED => {
+            let payload: LeaseMintedPayload = from_value(event.payload.clone()).map_err(|err| {
+                ProjectionError::Apply(format!("invalid lease.minted payload: {err}"))
+            })?;
+            writer.insert_lease(&LeaseEntry {
+                workspace_id: event.workspace_id.clone(),
+                lease_id: event.subject.id.clone(),
+                holder: payload.holder,
+                scope: payload.scope,
+                reason: payload.reason,
+                minted_by: format!("{}:{}", event.actor.kind, event.actor.id),
+                expires_at: payload.expires_at,
+                status: LeaseStatus::Active,
+                use_count: 0,
+                created_at: event.timestamp.clone(),
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_LEASE_USED => {
+            writer.record_lease_use(
+                &event.workspace_id,
+                &event.subject.id,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_LEASE_REVOKED | EVENT_LEASE_EXPIRED => {
+            let status = if event.event_type == EVENT_LEASE_REVOKED {
+                LeaseStatus::Revoked
+            } else {
+                LeaseStatus::Expired
+            };
+            writer.end_lease(
+                &event.workspace_id,
+                &event.subject.id,
+                status,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SECRET_CREATED => {
+            let payload: SecretVersionPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid secret.created payload: {err}"))
+                })?;
+            writer.insert_secret(&SecretEntry {
+                workspace_id: event.workspace_id.clone(),
+                secret_id: event.subject.id.clone(),
+                name: payload.name,
+                owner: payload.owner,
+                version: payload.version,
+                redacted: payload.redacted,
+                length: payload.length,
+                created_at: event.timestamp.clone(),
+                updated_at: event.timestamp.clone(),
+                seq_global: event.seq_global,
+            })?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SECRET_ROTATED => {
+            let payload: SecretVersionPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid secret.rotated payload: {err}"))
+                })?;
+            writer.rotate_secret(
+                &event.workspace_id,
+                &event.subject.id,
+                payload.version,
+                payload.length,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_SECRET_DELETED => {
+            writer.delete_secret(&event.workspace_id, &event.subject.id)?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TASK_RETRY_SCHEDULED => {
+            let payload: TaskRetryScheduledPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid task.retry_scheduled payload: {err}"))
+                })?;
+            writer.schedule_task_retry(
+                &event.subject.id,
+                &payload.retry_at,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        EVENT_TASK_TIMED_OUT => {
+            let payload: TaskTimedOutPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid task.timed_out payload: {err}"))
+                })?;
+            writer.update_task_state(
+                &event.subject.id,
+                payload.to,
+                &event.timestamp,
+                event.seq_global,
+            )?;
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
+        event_type if TaskAction::from_event(event_type).is_some() => {
+            let payload: TaskTransitionedPayload =
+                from_value(event.payload.clone()).map_err(|err| {
+                    ProjectionError::Apply(format!("invalid {event_type} payload: {err}"))
+                })?;
+            // Attempts are counted from the log so a rebuild reproduces them exactly. The
+            // start time comes from the daemon clock recorded in the payload, which timeouts
+            // are measured against; older events fall back to the event timestamp.
+            if TaskAction::from_event(event_type) == Some(TaskAction::Start) {
+                let started_at = payload.started_at.as_deref().unwrap_or(&event.timestamp);
+                writer.start_task_attempt(&event.subject.id, started_at, event.seq_global)?;
+            } else {
+                writer.update_task_state(
+                    &event.subject.id,
+                    payload.to,
+                    &event.timestamp,
+                    event.seq_global,
+                )?;
+            }
+            writer.set_meta(&event.workspace_id, event.seq_global)?;
+        }
         _ => {
             // Ignore events that do not affect projections.
         }
@@ -76,6 +856,66 @@ pub fn apply_event<W: ProjectionWriter>(
     Ok(())
 }
 
+/// Subjects of the kinds in [`BOARD_NODE_KINDS`] are board nodes. Labels follow the payload's
+/// name-like fields; see [`board_label`].
+fn touch_board_node<W: ProjectionWriter>(
+    writer: &W,
+    event: &EventEnvelope,
+) -> Result<(), ProjectionError> {
+    if !BOARD_NODE_KINDS.contains(&event.subject.kind.as_str()) {
+        return Ok(());
+    }
+    writer.touch_board_node(&BoardNode {
+        node_id: board_node_id(&event.subject.kind, &event.subject.id),
+        workspace_id: event.workspace_id.clone(),
+        project_id: event.project_id.clone(),
+        kind: event.subject.kind.clone(),
+        subject_id: event.subject.id.clone(),
+        label: board_label(&event.payload),
+        body: event
+            .payload
+            .get("body")
+            .and_then(|body| body.as_str())
+            .map(str::to_string),
+        position: None,
+        group_node_id: None,
+        created_at: event.timestamp.clone(),
+        updated_at: event.timestamp.clone(),
+        seq_global: event.seq_global,
+    })
+}
+
+fn add_lineage_edge<W: ProjectionWriter>(
+    writer: &W,
+    event: &EventEnvelope,
+    parent_session_id: &str,
+) -> Result<(), ProjectionError> {
+    writer.add_board_edge(
+        &event.workspace_id,
+        &BoardEdge {
+            kind: BoardEdgeKind::Lineage,
+            from_node_id: board_node_id("session", parent_session_id),
+            to_node_id: board_node_id(&event.subject.kind, &event.subject.id),
+            seq_global: event.seq_global,
+        },
+    )
+}
+
+fn set_own_board_layout<W: ProjectionWriter>(
+    writer: &W,
+    event: &EventEnvelope,
+    position: &BoardPosition,
+    group_node_id: Option<&str>,
+) -> Result<(), ProjectionError> {
+    writer.set_board_node_layout(
+        &board_node_id(&event.subject.kind, &event.subject.id),
+        position,
+        group_node_id,
+        &event.timestamp,
+        event.seq_global,
+    )
+}
+
 pub fn rebuild_projections<W, I>(writer: &W, events: I) -> Result<(), ProjectionError>
 where
     W: ProjectionWriter,
@@ -91,7 +931,8 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use mp_kernel::{Actor, Subject};
+    use mp_kernel::SkillTrust;
+    use mp_kernel::{Actor, ForkMode, Subject};
     use std::cell::{Cell, RefCell};
 
     #[derive(Debug, Clone, PartialEq, Eq)]
@@ -100,6 +941,7 @@ mod tests {
         name: String,
         root_path: String,
         created_at: String,
+        archived_at: Option<String>,
         seq_global: i64,
     }
 
@@ -109,6 +951,7 @@ mod tests {
         workspace_id: String,
         name: String,
         created_at: String,
+        archived_at: Option<String>,
         seq_global: i64,
     }
 
@@ -123,6 +966,20 @@ mod tests {
         resets: Cell<usize>,
         workspaces: RefCell<Vec<WorkspaceRecord>>,
         projects: RefCell<Vec<ProjectRecord>>,
+        sessions: RefCell<Vec<SessionListEntry>>,
+        tasks: RefCell<Vec<TaskListEntry>>,
+        templates: RefCell<Vec<PipelineTemplateEntry>>,
+        bindings: RefCell<Vec<PipelineBindingEntry>>,
+        gates: RefCell<Vec<GateEntry>>,
+        worktrees: RefCell<Vec<WorktreeEntry>>,
+        board_nodes: RefCell<Vec<BoardNode>>,
+        board_edges: RefCell<Vec<BoardEdge>>,
+        capsules: RefCell<Vec<CapsuleEntry>>,
+        blueprints: RefCell<Vec<BlueprintEntry>>,
+        tool_calls: RefCell<Vec<ToolCallEntry>>,
+        skills: RefCell<Vec<SkillEntry>>,
+        leases: RefCell<Vec<LeaseEntry>>,
+        secrets: RefCell<Vec<SecretEntry>>,
         metas: RefCell<Vec<MetaRecord>>,
     }
 
@@ -131,6 +988,19 @@ mod tests {
             self.resets.set(self.resets.get() + 1);
             self.workspaces.borrow_mut().clear();
             self.projects.borrow_mut().clear();
+            self.sessions.borrow_mut().clear();
+            self.tasks.borrow_mut().clear();
+            self.templates.borrow_mut().clear();
+            self.bindings.borrow_mut().clear();
+            self.gates.borrow_mut().clear();
+            self.worktrees.borrow_mut().clear();
+            self.board_nodes.borrow_mut().clear();
+            self.board_edges.borrow_mut().clear();
+            self.capsules.borrow_mut().clear();
+            self.tool_calls.borrow_mut().clear();
+            self.skills.borrow_mut().clear();
+            self.leases.borrow_mut().clear();
+            self.secrets.borrow_mut().clear();
             self.metas.borrow_mut().clear();
             Ok(())
         }
@@ -148,6 +1018,7 @@ mod tests {
                 name: name.to_string(),
                 root_path: root_path.to_string(),
                 created_at: created_at.to_string(),
+                archived_at: None,
                 seq_global,
             });
             Ok(())
@@ -166,22 +1037,511 @@ mod tests {
                 workspace_id: workspace_id.to_string(),
                 name: name.to_string(),
                 created_at: created_at.to_string(),
+                archived_at: None,
                 seq_global,
             });
             Ok(())
         }
 
-        fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError> {
-            self.metas.borrow_mut().push(MetaRecord {
-                workspace_id: workspace_id.to_string(),
-                seq_global,
-            });
+        fn rename_workspace(
+            &self,
+            workspace_id: &str,
+            name: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut workspaces = self.workspaces.borrow_mut();
+            let workspace = workspaces
+                .iter_mut()
+                .find(|workspace| workspace.workspace_id == workspace_id)
+                .ok_or_else(|| {
+                    ProjectionError::Apply(format!("unknown workspace {workspace_id}"))
+                })?;
+            workspace.name = name.to_string();
+            workspace.seq_global = seq_global;
             Ok(())
         }
-    }
 
-    fn workspace_event(seq_global: i64) -> EventEnvelope {
-        EventEnvelope {
+        fn set_workspace_archived(
+            &self,
+            workspace_id: &str,
+            archived_at: Option<&str>,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut workspaces = self.workspaces.borrow_mut();
+            let workspace = workspaces
+                .iter_mut()
+                .find(|workspace| workspace.workspace_id == workspace_id)
+                .ok_or_else(|| {
+                    ProjectionError::Apply(format!("unknown workspace {workspace_id}"))
+                })?;
+            workspace.archived_at = archived_at.map(str::to_string);
+            workspace.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn rename_project(
+            &self,
+            project_id: &str,
+            name: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut projects = self.projects.borrow_mut();
+            let project = projects
+                .iter_mut()
+                .find(|project| project.project_id == project_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown project {project_id}")))?;
+            project.name = name.to_string();
+            project.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn set_project_archived(
+            &self,
+            project_id: &str,
+            archived_at: Option<&str>,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut projects = self.projects.borrow_mut();
+            let project = projects
+                .iter_mut()
+                .find(|project| project.project_id == project_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown project {project_id}")))?;
+            project.archived_at = archived_at.map(str::to_string);
+            project.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn upsert_session(&self, session: &SessionListEntry) -> Result<(), ProjectionError> {
+            self.sessions.borrow_mut().push(session.clone());
+            Ok(())
+        }
+
+        fn upsert_task(&self, task: &TaskListEntry) -> Result<(), ProjectionError> {
+            self.tasks.borrow_mut().push(task.clone());
+            Ok(())
+        }
+
+        fn update_task_state(
+            &self,
+            task_id: &str,
+            state: TaskState,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut tasks = self.tasks.borrow_mut();
+            let task = tasks
+                .iter_mut()
+                .find(|task| task.task_id == task_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown task {task_id}")))?;
+            task.state = state;
+            task.updated_at = updated_at.to_string();
+            task.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn start_task_attempt(
+            &self,
+            task_id: &str,
+            started_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut tasks = self.tasks.borrow_mut();
+            let task = tasks
+                .iter_mut()
+                .find(|task| task.task_id == task_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown task {task_id}")))?;
+            task.state = TaskState::Running;
+            task.attempt += 1;
+            task.attempt_started_at = Some(started_at.to_string());
+            task.retry_at = None;
+            task.updated_at = started_at.to_string();
+            task.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn schedule_task_retry(
+            &self,
+            task_id: &str,
+            retry_at: &str,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut tasks = self.tasks.borrow_mut();
+            let task = tasks
+                .iter_mut()
+                .find(|task| task.task_id == task_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown task {task_id}")))?;
+            task.state = TaskState::Queued;
+            task.retry_at = Some(retry_at.to_string());
+            task.updated_at = updated_at.to_string();
+            task.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn upsert_pipeline_template(
+            &self,
+            template: &PipelineTemplateEntry,
+        ) -> Result<(), ProjectionError> {
+            self.templates.borrow_mut().push(template.clone());
+            Ok(())
+        }
+
+        fn upsert_pipeline_binding(
+            &self,
+            binding: &PipelineBindingEntry,
+        ) -> Result<(), ProjectionError> {
+            let mut bindings = self.bindings.borrow_mut();
+            bindings.retain(|existing| existing.project_id != binding.project_id);
+            bindings.push(binding.clone());
+            Ok(())
+        }
+
+        fn update_task_stage(
+            &self,
+            task_id: &str,
+            stage: &str,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut tasks = self.tasks.borrow_mut();
+            let task = tasks
+                .iter_mut()
+                .find(|task| task.task_id == task_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown task {task_id}")))?;
+            task.current_stage = Some(stage.to_string());
+            task.updated_at = updated_at.to_string();
+            task.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn upsert_gate(&self, gate: &GateEntry) -> Result<(), ProjectionError> {
+            self.gates.borrow_mut().push(gate.clone());
+            Ok(())
+        }
+
+        fn record_gate_decision(
+            &self,
+            gate_id: &str,
+            decision: &GateDecisionEntry,
+            status: GateStatus,
+        ) -> Result<(), ProjectionError> {
+            let mut gates = self.gates.borrow_mut();
+            let gate = gates
+                .iter_mut()
+                .find(|gate| gate.gate_id == gate_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown gate {gate_id}")))?;
+            gate.decisions.push(decision.clone());
+            gate.status = status;
+            gate.updated_at = decision.decided_at.clone();
+            gate.seq_global = decision.seq_global;
+            Ok(())
+        }
+
+        fn upsert_worktree(&self, worktree: &WorktreeEntry) -> Result<(), ProjectionError> {
+            self.worktrees.borrow_mut().push(worktree.clone());
+            Ok(())
+        }
+
+        fn attach_worktree_session(
+            &self,
+            worktree_id: &str,
+            session_id: &str,
+            attached_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            self.with_worktree(worktree_id, attached_at, seq_global, |worktree| {
+                worktree.sessions.push(session_id.to_string());
+            })
+        }
+
+        fn detach_worktree_session(
+            &self,
+            worktree_id: &str,
+            session_id: &str,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            self.with_worktree(worktree_id, updated_at, seq_global, |worktree| {
+                worktree.sessions.retain(|session| session != session_id);
+            })
+        }
+
+        fn set_worktree_lock(
+            &self,
+            worktree_id: &str,
+            lock: Option<&WorktreeLock>,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            self.with_worktree(worktree_id, updated_at, seq_global, |worktree| {
+                worktree.lock = lock.cloned();
+            })
+        }
+
+        fn touch_board_node(&self, node: &BoardNode) -> Result<(), ProjectionError> {
+            let mut nodes = self.board_nodes.borrow_mut();
+            match nodes
+                .iter_mut()
+                .find(|existing| existing.node_id == node.node_id)
+            {
+                Some(existing) => {
+                    if node.label.is_some() {
+                        existing.label = node.label.clone();
+                    }
+                    if node.body.is_some() {
+                        existing.body = node.body.clone();
+                    }
+                    existing.updated_at = node.updated_at.clone();
+                    existing.seq_global = node.seq_global;
+                }
+                None => nodes.push(node.clone()),
+            }
+            Ok(())
+        }
+
+        fn set_board_node_layout(
+            &self,
+            node_id: &str,
+            position: &BoardPosition,
+            group_node_id: Option<&str>,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut nodes = self.board_nodes.borrow_mut();
+            let node = nodes
+                .iter_mut()
+                .find(|node| node.node_id == node_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown board node {node_id}")))?;
+            node.position = Some(*position);
+            node.group_node_id = group_node_id.map(str::to_string);
+            node.updated_at = updated_at.to_string();
+            node.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn add_board_edge(
+            &self,
+            _workspace_id: &str,
+            edge: &BoardEdge,
+        ) -> Result<(), ProjectionError> {
+            self.board_edges.borrow_mut().push(edge.clone());
+            Ok(())
+        }
+
+        fn remove_board_edge(
+            &self,
+            kind: BoardEdgeKind,
+            from_node_id: &str,
+            to_node_id: &str,
+        ) -> Result<(), ProjectionError> {
+            self.board_edges.borrow_mut().retain(|edge| {
+                !(edge.kind == kind
+                    && edge.from_node_id == from_node_id
+                    && edge.to_node_id == to_node_id)
+            });
+            Ok(())
+        }
+
+        fn insert_capsule(&self, capsule: &CapsuleEntry) -> Result<(), ProjectionError> {
+            self.capsules.borrow_mut().push(capsule.clone());
+            Ok(())
+        }
+
+        fn insert_blueprint(&self, blueprint: &BlueprintEntry) -> Result<(), ProjectionError> {
+            self.blueprints.borrow_mut().push(blueprint.clone());
+            Ok(())
+        }
+
+        fn insert_tool_call(&self, call: &ToolCallEntry) -> Result<(), ProjectionError> {
+            self.tool_calls.borrow_mut().push(call.clone());
+            Ok(())
+        }
+
+        fn complete_tool_call(
+            &self,
+            request_id: &str,
+            result: &ToolResultPayload,
+            completed_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut calls = self.tool_calls.borrow_mut();
+            let call = calls
+                .iter_mut()
+                .find(|call| call.request_id == request_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown call {request_id}")))?;
+            call.status = result.status;
+            call.output = result.output.clone();
+            call.error = result.error.clone();
+            call.completed_at = Some(completed_at.to_string());
+            call.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn upsert_skill(&self, skill: &SkillEntry) -> Result<(), ProjectionError> {
+            let mut skills = self.skills.borrow_mut();
+            match skills.iter_mut().find(|existing| {
+                existing.workspace_id == skill.workspace_id && existing.skill_id == skill.skill_id
+            }) {
+                Some(existing) => {
+                    let approved_hash = existing.approved_hash.take();
+                    *existing = SkillEntry {
+                        approved_hash,
+                        ..skill.clone()
+                    };
+                }
+                None => skills.push(skill.clone()),
+            }
+            Ok(())
+        }
+
+        fn approve_skill(
+            &self,
+            workspace_id: &str,
+            skill_id: &str,
+            content_hash: &str,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut skills = self.skills.borrow_mut();
+            let skill = skills
+                .iter_mut()
+                .find(|skill| skill.workspace_id == workspace_id && skill.skill_id == skill_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown skill {skill_id}")))?;
+            skill.approved_hash = Some(content_hash.to_string());
+            if skill.trust == SkillTrust::Proposed && skill.content_hash == content_hash {
+                skill.trust = SkillTrust::Sandboxed;
+            }
+            skill.updated_at = updated_at.to_string();
+            skill.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn remove_skill(&self, workspace_id: &str, skill_id: &str) -> Result<(), ProjectionError> {
+            self.skills.borrow_mut().retain(|skill| {
+                !(skill.workspace_id == workspace_id && skill.skill_id == skill_id)
+            });
+            Ok(())
+        }
+
+        fn insert_lease(&self, lease: &LeaseEntry) -> Result<(), ProjectionError> {
+            self.leases.borrow_mut().push(lease.clone());
+            Ok(())
+        }
+
+        fn record_lease_use(
+            &self,
+            workspace_id: &str,
+            lease_id: &str,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            self.with_lease(workspace_id, lease_id, updated_at, seq_global, |lease| {
+                lease.use_count += 1;
+            })
+        }
+
+        fn end_lease(
+            &self,
+            workspace_id: &str,
+            lease_id: &str,
+            status: LeaseStatus,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            self.with_lease(workspace_id, lease_id, updated_at, seq_global, |lease| {
+                if lease.status == LeaseStatus::Active {
+                    lease.status = status;
+                }
+            })
+        }
+
+        fn insert_secret(&self, secret: &SecretEntry) -> Result<(), ProjectionError> {
+            self.secrets.borrow_mut().push(secret.clone());
+            Ok(())
+        }
+
+        fn rotate_secret(
+            &self,
+            workspace_id: &str,
+            secret_id: &str,
+            version: u32,
+            length: usize,
+            updated_at: &str,
+            seq_global: i64,
+        ) -> Result<(), ProjectionError> {
+            let mut secrets = self.secrets.borrow_mut();
+            let secret = secrets
+                .iter_mut()
+                .find(|secret| secret.workspace_id == workspace_id && secret.secret_id == secret_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown secret {secret_id}")))?;
+            secret.version = version;
+            secret.length = length;
+            secret.updated_at = updated_at.to_string();
+            secret.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn delete_secret(
+            &self,
+            workspace_id: &str,
+            secret_id: &str,
+        ) -> Result<(), ProjectionError> {
+            self.secrets.borrow_mut().retain(|secret| {
+                !(secret.workspace_id == workspace_id && secret.secret_id == secret_id)
+            });
+            Ok(())
+        }
+
+        fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError> {
+            self.metas.borrow_mut().push(MetaRecord {
+                workspace_id: workspace_id.to_string(),
+                seq_global,
+            });
+            Ok(())
+        }
+    }
+
+    impl RecordingWriter {
+        fn with_lease(
+            &self,
+            workspace_id: &str,
+            lease_id: &str,
+            updated_at: &str,
+            seq_global: i64,
+            update: impl FnOnce(&mut LeaseEntry),
+        ) -> Result<(), ProjectionError> {
+            let mut leases = self.leases.borrow_mut();
+            let lease = leases
+                .iter_mut()
+                .find(|lease| lease.workspace_id == workspace_id && lease.lease_id == lease_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown lease {lease_id}")))?;
+            update(lease);
+            lease.updated_at = updated_at.to_string();
+            lease.seq_global = seq_global;
+            Ok(())
+        }
+
+        fn with_worktree(
+            &self,
+            worktree_id: &str,
+            updated_at: &str,
+            seq_global: i64,
+            update: impl FnOnce(&mut WorktreeEntry),
+        ) -> Result<(), ProjectionError> {
+            let mut worktrees = self.worktrees.borrow_mut();
+            let worktree = worktrees
+                .iter_mut()
+                .find(|worktree| worktree.worktree_id == worktree_id)
+                .ok_or_else(|| ProjectionError::Apply(format!("unknown worktree {worktree_id}")))?;
+            update(worktree);
+            worktree.updated_at = updated_at.to_string();
+            worktree.seq_global = seq_global;
+            Ok(())
+        }
+    }
+
+    fn workspace_event(seq_global: i64) -> EventEnvelope {
+        EventEnvelope {
             event_id: "e1".to_string(),
             event_type: EVENT_WORKSPACE_CREATED.to_string(),
             timestamp: "2020-01-01T00:00:00Z".to_string(),
@@ -226,6 +1586,30 @@ mod tests {
         }
     }
 
+    fn session_event(
+        event_type: &str,
+        session_id: &str,
+        payload: serde_json::Value,
+    ) -> EventEnvelope {
+        EventEnvelope {
+            event_id: format!("e_{session_id}"),
+            event_type: event_type.to_string(),
+            timestamp: "2020-01-01T00:00:00Z".to_string(),
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: Some("p1".to_string()),
+            subject: Subject {
+                kind: "session".to_string(),
+                id: session_id.to_string(),
+            },
+            payload,
+            schema_version: 1,
+            seq_global: 4,
+            seq_stream: 1,
+            trace_id: None,
+        }
+    }
+
     #[test]
     fn apply_event_records_workspace() {
         let writer = RecordingWriter::default();
@@ -241,6 +1625,7 @@ mod tests {
                 name: "demo".to_string(),
                 root_path: "/tmp/demo".to_string(),
                 created_at: "2020-01-01T00:00:00Z".to_string(),
+                archived_at: None,
                 seq_global: 3,
             }
         );
@@ -271,6 +1656,7 @@ mod tests {
                 workspace_id: "w1".to_string(),
                 name: "core".to_string(),
                 created_at: "2020-01-01T00:00:00Z".to_string(),
+                archived_at: None,
                 seq_global: 7,
             }
         );
@@ -286,6 +1672,734 @@ mod tests {
         );
     }
 
+    #[test]
+    fn apply_event_renames_and_archives_project() {
+        let writer = RecordingWriter::default();
+        let lifecycle_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = project_event(seq_global);
+            event.event_type = event_type.to_string();
+            event.timestamp = format!("2020-01-0{seq_global}T00:00:00Z");
+            event.payload = payload;
+            event
+        };
+        let events = vec![
+            project_event(1),
+            lifecycle_event(
+                EVENT_PROJECT_RENAMED,
+                2,
+                serde_json::json!({"previous_name": "core", "name": "kernel"}),
+            ),
+            lifecycle_event(
+                EVENT_PROJECT_ARCHIVED,
+                3,
+                serde_json::json!({"reason": "typo"}),
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+        {
+            let projects = writer.projects.borrow();
+            assert_eq!(projects[0].name, "kernel");
+            assert_eq!(
+                projects[0].archived_at.as_deref(),
+                Some("2020-01-03T00:00:00Z")
+            );
+        }
+
+        apply_event(
+            &writer,
+            &lifecycle_event(EVENT_PROJECT_RESTORED, 4, serde_json::json!({})),
+        )
+        .expect("restore");
+        let projects = writer.projects.borrow();
+        assert_eq!(projects[0].archived_at, None);
+        assert_eq!(projects[0].seq_global, 4);
+    }
+
+    #[test]
+    fn apply_event_records_session_lineage() {
+        let writer = RecordingWriter::default();
+        let spawned = session_event(
+            EVENT_SESSION_SPAWNED,
+            "s2",
+            serde_json::json!({
+                "parent_session_id": "s1",
+                "root_session_id": "s1",
+                "lineage_path": "/s1/s2"
+            }),
+        );
+        let forked = session_event(
+            EVENT_SESSION_FORKED,
+            "s3",
+            serde_json::json!({
+                "forked_from_session_id": "s2",
+                "mode": "planning_only",
+                "parent_session_id": "s2",
+                "root_session_id": "s1",
+                "lineage_path": "/s1/s2/s3"
+            }),
+        );
+        apply_event(&writer, &spawned).expect("apply spawned");
+        apply_event(&writer, &forked).expect("apply forked");
+
+        let sessions = writer.sessions.borrow();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session_id, "s2");
+        assert_eq!(sessions[0].parent_session_id.as_deref(), Some("s1"));
+        assert_eq!(sessions[0].project_id.as_deref(), Some("p1"));
+        assert_eq!(sessions[0].depth, 1);
+        assert_eq!(sessions[0].forked_from_session_id, None);
+        assert_eq!(sessions[1].session_id, "s3");
+        assert_eq!(sessions[1].forked_from_session_id.as_deref(), Some("s2"));
+        assert_eq!(sessions[1].fork_mode, Some(ForkMode::PlanningOnly));
+        assert_eq!(sessions[1].depth, 2);
+    }
+
+    fn task_event(event_type: &str, seq_global: i64, payload: serde_json::Value) -> EventEnvelope {
+        EventEnvelope {
+            event_id: format!("e_task_{seq_global}"),
+            event_type: event_type.to_string(),
+            timestamp: format!("2020-01-01T00:00:0{seq_global}Z"),
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "task".to_string(),
+                id: "t1".to_string(),
+            },
+            payload,
+            schema_version: 1,
+            seq_global,
+            seq_stream: seq_global,
+            trace_id: None,
+        }
+    }
+
+    #[test]
+    fn apply_event_tracks_task_state() {
+        let writer = RecordingWriter::default();
+        let events = vec![
+            task_event(
+                EVENT_TASK_CREATED,
+                1,
+                serde_json::json!({"title": "lint", "state": "queued"}),
+            ),
+            task_event(
+                mp_kernel::EVENT_TASK_STARTED,
+                2,
+                serde_json::json!({"from": "queued", "to": "running"}),
+            ),
+            task_event(
+                mp_kernel::EVENT_TASK_FAILED,
+                3,
+                serde_json::json!({"from": "running", "to": "failed", "reason": "exit 1"}),
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+
+        let tasks = writer.tasks.borrow();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].title, "lint");
+        assert_eq!(tasks[0].state, TaskState::Failed);
+        assert_eq!(tasks[0].created_at, "2020-01-01T00:00:01Z");
+        assert_eq!(tasks[0].updated_at, "2020-01-01T00:00:03Z");
+        assert_eq!(tasks[0].seq_global, 3);
+    }
+
+    #[test]
+    fn rebuild_reproduces_retry_attempt_counts() {
+        let events = vec![
+            task_event(
+                EVENT_TASK_CREATED,
+                1,
+                serde_json::json!({
+                    "title": "deploy",
+                    "state": "queued",
+                    "timeout_ms": 1000,
+                    "retry": { "max_attempts": 3, "backoff": { "kind": "fixed", "delay_ms": 500 } }
+                }),
+            ),
+            task_event(
+                mp_kernel::EVENT_TASK_STARTED,
+                2,
+                serde_json::json!({"from": "queued", "to": "running"}),
+            ),
+            task_event(
+                EVENT_TASK_TIMED_OUT,
+                3,
+                serde_json::json!({"from": "running", "to": "failed", "attempt": 1, "timeout_ms": 1000}),
+            ),
+            task_event(
+                EVENT_TASK_RETRY_SCHEDULED,
+                4,
+                serde_json::json!({
+                    "attempt": 1,
+                    "next_attempt": 2,
+                    "failure_class": "timeout",
+                    "delay_ms": 500,
+                    "retry_at": "2020-01-01T00:00:05Z"
+                }),
+            ),
+            task_event(
+                mp_kernel::EVENT_TASK_STARTED,
+                5,
+                serde_json::json!({
+                    "from": "queued",
+                    "to": "running",
+                    "started_at": "2019-06-01T00:00:00Z"
+                }),
+            ),
+        ];
+
+        let writer = RecordingWriter::default();
+        for event in &events[..4] {
+            apply_event(&writer, event).expect("apply");
+        }
+        {
+            let tasks = writer.tasks.borrow();
+            assert_eq!(tasks[0].state, TaskState::Queued);
+            assert_eq!(tasks[0].attempt, 1);
+            assert_eq!(tasks[0].retry_at.as_deref(), Some("2020-01-01T00:00:05Z"));
+        }
+        apply_event(&writer, &events[4]).expect("apply");
+        let live = writer.tasks.borrow().clone();
+
+        let rebuilt = RecordingWriter::default();
+        rebuild_projections(&rebuilt, events).expect("rebuild");
+        let rebuilt = rebuilt.tasks.borrow().clone();
+        assert_eq!(live, rebuilt);
+        assert_eq!(rebuilt[0].attempt, 2);
+        assert_eq!(rebuilt[0].state, TaskState::Running);
+        assert_eq!(rebuilt[0].retry_at, None);
+        assert_eq!(
+            rebuilt[0].attempt_started_at.as_deref(),
+            Some("2019-06-01T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn apply_event_tracks_pipeline_binding_and_stage() {
+        let writer = RecordingWriter::default();
+        let mut template = task_event(
+            EVENT_PIPELINE_TEMPLATE_DEFINED,
+            1,
+            serde_json::json!({
+                "name": "delivery",
+                "version": 1,
+                "stages": [{"name": "plan"}, {"name": "build"}]
+            }),
+        );
+        template.subject = Subject {
+            kind: "pipeline_template".to_string(),
+            id: "tpl".to_string(),
+        };
+        let mut bound = task_event(
+            EVENT_PIPELINE_BOUND,
+            2,
+            serde_json::json!({"template_id": "tpl", "template_version": 1}),
+        );
+        bound.subject = Subject {
+            kind: "project".to_string(),
+            id: "p1".to_string(),
+        };
+        let events = vec![
+            template,
+            bound,
+            task_event(
+                EVENT_TASK_CREATED,
+                3,
+                serde_json::json!({"title": "ship", "state": "queued"}),
+            ),
+            task_event(
+                EVENT_TASK_STAGE_CHANGED,
+                4,
+                serde_json::json!({"to_stage": "build", "template_id": "tpl", "template_version": 1}),
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+
+        assert_eq!(writer.templates.borrow()[0].stages.len(), 2);
+        assert_eq!(writer.bindings.borrow()[0].project_id, "p1");
+        let tasks = writer.tasks.borrow();
+        assert_eq!(tasks[0].current_stage.as_deref(), Some("build"));
+        assert_eq!(tasks[0].seq_global, 4);
+    }
+
+    #[test]
+    fn apply_event_records_gate_decisions() {
+        let writer = RecordingWriter::default();
+        let gate_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = task_event(event_type, seq_global, payload);
+            event.subject = Subject {
+                kind: "gate".to_string(),
+                id: "g1".to_string(),
+            };
+            event
+        };
+        let scope = serde_json::json!({
+            "command_type": "stage.transition",
+            "subject": {"kind": "task", "id": "t1"}
+        });
+        let events = vec![
+            gate_event(
+                EVENT_GATE_DEFINED,
+                1,
+                serde_json::json!({"scope": scope, "kind": "manual_approval", "required_approvals": 1}),
+            ),
+            gate_event(
+                EVENT_GATE_APPROVED,
+                2,
+                serde_json::json!({
+                    "approver": {"kind": "human", "id": "alice"},
+                    "scope": scope,
+                    "evidence": ["blake3:abc"],
+                    "status": "approved"
+                }),
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+
+        let gates = writer.gates.borrow();
+        assert_eq!(gates[0].status, GateStatus::Approved);
+        assert_eq!(gates[0].decisions[0].approver.id, "alice");
+        assert!(gates[0].decisions[0].approved);
+        assert_eq!(gates[0].seq_global, 2);
+    }
+
+    #[test]
+    fn apply_event_tracks_worktree_sessions_and_lock() {
+        let writer = RecordingWriter::default();
+        let worktree_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = task_event(event_type, seq_global, payload);
+            event.subject = Subject {
+                kind: "worktree".to_string(),
+                id: "wt1".to_string(),
+            };
+            event
+        };
+        let events = vec![
+            worktree_event(
+                EVENT_WORKTREE_REGISTERED,
+                1,
+                serde_json::json!({"path": "/repo/wt1", "branch": "feature"}),
+            ),
+            worktree_event(
+                EVENT_WORKTREE_ATTACHED,
+                2,
+                serde_json::json!({"session_id": "s1"}),
+            ),
+            worktree_event(
+                EVENT_WORKTREE_ATTACHED,
+                3,
+                serde_json::json!({"session_id": "s2"}),
+            ),
+            worktree_event(
+                EVENT_WORKTREE_LOCK_ACQUIRED,
+                4,
+                serde_json::json!({"session_id": "s1"}),
+            ),
+            worktree_event(
+                EVENT_WORKTREE_LOCK_RELEASED,
+                5,
+                serde_json::json!({"session_id": "s1", "reason": "detached"}),
+            ),
+            worktree_event(
+                EVENT_WORKTREE_DETACHED,
+                5,
+                serde_json::json!({"session_id": "s1"}),
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+
+        let worktrees = writer.worktrees.borrow();
+        assert_eq!(worktrees[0].path, "/repo/wt1");
+        assert_eq!(worktrees[0].sessions, vec!["s2".to_string()]);
+        assert_eq!(worktrees[0].lock_holder(), None);
+        assert_eq!(worktrees[0].seq_global, 5);
+    }
+
+    #[test]
+    fn apply_event_builds_board_from_node_kind_subjects() {
+        let writer = RecordingWriter::default();
+        let with_subject = |mut event: EventEnvelope, kind: &str, id: &str| {
+            event.subject = Subject {
+                kind: kind.to_string(),
+                id: id.to_string(),
+            };
+            event
+        };
+        let events = vec![
+            workspace_event(1),
+            session_event(
+                EVENT_SESSION_SPAWNED,
+                "s1",
+                serde_json::json!({"root_session_id": "s1", "lineage_path": "/s1", "label": "planner"}),
+            ),
+            session_event(
+                EVENT_SESSION_SPAWNED,
+                "s2",
+                serde_json::json!({
+                    "parent_session_id": "s1",
+                    "root_session_id": "s1",
+                    "lineage_path": "/s1/s2"
+                }),
+            ),
+            with_subject(
+                task_event(
+                    EVENT_WORKTREE_REGISTERED,
+                    4,
+                    serde_json::json!({"path": "/repo/wt1"}),
+                ),
+                "worktree",
+                "wt1",
+            ),
+            with_subject(
+                task_event(
+                    EVENT_WORKTREE_ATTACHED,
+                    5,
+                    serde_json::json!({"session_id": "s2"}),
+                ),
+                "worktree",
+                "wt1",
+            ),
+            with_subject(
+                task_event("widget.spun", 6, serde_json::json!({"name": "gizmo"})),
+                "widget",
+                "x1",
+            ),
+            with_subject(
+                task_event(mp_kernel::EVENT_COMMAND_REJECTED, 7, serde_json::json!({})),
+                "command",
+                "tr_1",
+            ),
+            with_subject(
+                task_event(
+                    EVENT_BOARD_NODE_MOVED,
+                    8,
+                    serde_json::json!({"position": {"x": 10.0, "y": 20.0}}),
+                ),
+                "session",
+                "s2",
+            ),
+            with_subject(
+                task_event(
+                    EVENT_SECRET_CREATED,
+                    9,
+                    serde_json::json!({
+                        "name": "API_TOKEN",
+                        "owner": {"scope": "global"},
+                        "version": 1,
+                        "redacted": "****",
+                        "length": 12
+                    }),
+                ),
+                "secret",
+                "sec_1",
+            ),
+        ];
+        rebuild_projections(&writer, events).expect("rebuild");
+
+        let nodes = writer.board_nodes.borrow();
+        let ids: Vec<&str> = nodes.iter().map(|node| node.node_id.as_str()).collect();
+        assert_eq!(
+            ids,
+            vec!["workspace:w1", "session:s1", "session:s2", "worktree:wt1"]
+        );
+        assert_eq!(nodes[1].label.as_deref(), Some("planner"));
+        assert_eq!(nodes[3].label.as_deref(), Some("/repo/wt1"));
+        assert_eq!(nodes[2].position, Some(BoardPosition { x: 10.0, y: 20.0 }));
+        assert_eq!(nodes[2].seq_global, 8);
+
+        let edges = writer.board_edges.borrow();
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0].kind, BoardEdgeKind::Lineage);
+        assert_eq!(edges[0].from_node_id, "session:s1");
+        assert_eq!(edges[0].to_node_id, "session:s2");
+        assert_eq!(edges[1].kind, BoardEdgeKind::Attachment);
+        assert_eq!(edges[1].to_node_id, "worktree:wt1");
+    }
+
+    #[test]
+    fn apply_event_links_artifacts_to_their_producer() {
+        let writer = RecordingWriter::default();
+        let hash = format!("blake3:{}", "ab".repeat(32));
+        let mut stored = task_event(
+            EVENT_ARTIFACT_STORED,
+            2,
+            serde_json::json!({
+                "hash": hash,
+                "size_bytes": 42,
+                "name": "build.log",
+                "producer": {"kind": "task", "id": "t1"},
+                "deduplicated": false
+            }),
+        );
+        stored.subject = Subject {
+            kind: "artifact".to_string(),
+            id: hash.clone(),
+        };
+        rebuild_projections(&writer, vec![workspace_event(1), stored]).expect("rebuild");
+
+        let nodes = writer.board_nodes.borrow();
+        let artifact = nodes
+            .iter()
+            .find(|node| node.kind == "artifact")
+            .expect("artifact node");
+        assert_eq!(artifact.label.as_deref(), Some("build.log"));
+        let edges = writer.board_edges.borrow();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].kind, BoardEdgeKind::Provenance);
+        assert_eq!(edges[0].from_node_id, "task:t1");
+        assert_eq!(edges[0].to_node_id, format!("artifact:{hash}"));
+    }
+
+    #[test]
+    fn apply_event_records_capsule_versions_on_subject() {
+        let writer = RecordingWriter::default();
+        let capsule_event = |seq_global: i64, version: u32, content: &str| {
+            let mut event = session_event(
+                EVENT_CAPSULE_WRITTEN,
+                "s1",
+                serde_json::json!({
+                    "kind": "status",
+                    "version": version,
+                    "content": content,
+                    "chars": content.chars().count()
+                }),
+            );
+            event.seq_global = seq_global;
+            event
+        };
+        rebuild_projections(
+            &writer,
+            vec![capsule_event(1, 1, "booting"), capsule_event(2, 2, "green")],
+        )
+        .expect("rebuild");
+
+        let capsules = writer.capsules.borrow();
+        assert_eq!(capsules.len(), 2);
+        assert_eq!(capsules[1].subject.id, "s1");
+        assert_eq!(capsules[1].kind, mp_kernel::CapsuleKind::Status);
+        assert_eq!(capsules[1].version, 2);
+        assert_eq!(capsules[1].chars, 5);
+    }
+
+    #[test]
+    fn apply_event_settles_tool_calls() {
+        let writer = RecordingWriter::default();
+        let tool_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = session_event(event_type, "s1", payload);
+            event.subject = Subject {
+                kind: "tool_call".to_string(),
+                id: "r1".to_string(),
+            };
+            event.seq_global = seq_global;
+            event
+        };
+        rebuild_projections(
+            &writer,
+            vec![
+                tool_event(
+                    EVENT_TOOL_REQUESTED,
+                    1,
+                    serde_json::json!({
+                        "tool_id": "acme.add",
+                        "tool_version": "1.0.0",
+                        "side_effect": "pure",
+                        "input": {"a": 1, "b": 2}
+                    }),
+                ),
+                tool_event(
+                    EVENT_TOOL_RESULT,
+                    2,
+                    serde_json::json!({
+                        "tool_id": "acme.add",
+                        "status": "succeeded",
+                        "output": {"sum": 3}
+                    }),
+                ),
+            ],
+        )
+        .expect("rebuild");
+
+        let calls = writer.tool_calls.borrow();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].request_id, "r1");
+        assert_eq!(calls[0].status, ToolCallStatus::Succeeded);
+        assert_eq!(calls[0].output, Some(serde_json::json!({"sum": 3})));
+        assert_eq!(calls[0].seq_global, 2);
+    }
+
+    #[test]
+    fn apply_event_tracks_skill_revisions_and_approvals() {
+        let writer = RecordingWriter::default();
+        let skill_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = session_event(event_type, "s1", payload);
+            event.subject = Subject {
+                kind: "skill".to_string(),
+                id: "pdf-tools".to_string(),
+            };
+            event.seq_global = seq_global;
+            event
+        };
+        let version = |revision: u32, hash: &str, trust: &str| {
+            serde_json::json!({
+                "skill_id": "pdf-tools",
+                "revision": revision,
+                "scope": "workspace",
+                "path": "agents/skills/pdf-tools",
+                "content_hash": hash,
+                "trust": trust,
+                "signature": {"status": "unsigned"},
+                "frontmatter": {"name": "pdf-tools", "description": "Reads PDFs."},
+                "components": {"scripts": ["scripts/extract.sh"]}
+            })
+        };
+        let approved = serde_json::json!({"skill_id": "pdf-tools", "content_hash": "h1"});
+        rebuild_projections(
+            &writer,
+            vec![
+                skill_event(EVENT_SKILL_REGISTERED, 1, version(1, "h1", "proposed")),
+                skill_event(EVENT_SKILL_APPROVED, 2, approved),
+            ],
+        )
+        .expect("rebuild");
+        {
+            let skills = writer.skills.borrow();
+            assert_eq!(skills[0].trust, SkillTrust::Sandboxed);
+            assert_eq!(skills[0].approved_hash.as_deref(), Some("h1"));
+        }
+
+        apply_event(
+            &writer,
+            &skill_event(EVENT_SKILL_UPDATED, 3, version(2, "h2", "proposed")),
+        )
+        .expect("update");
+        {
+            let skills = writer.skills.borrow();
+            assert_eq!(skills[0].revision, 2);
+            assert_eq!(skills[0].trust, SkillTrust::Proposed);
+            assert_eq!(skills[0].approved_hash.as_deref(), Some("h1"));
+            assert_eq!(skills[0].components.scripts, vec!["scripts/extract.sh"]);
+        }
+
+        apply_event(
+            &writer,
+            &skill_event(
+                EVENT_SKILL_REMOVED,
+                4,
+                serde_json::json!({"skill_id": "pdf-tools"}),
+            ),
+        )
+        .expect("remove");
+        assert!(writer.skills.borrow().is_empty());
+    }
+
+    #[test]
+    fn apply_event_counts_lease_uses_and_keeps_first_ending() {
+        let writer = RecordingWriter::default();
+        let lease_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = session_event(event_type, "s1", payload);
+            event.subject = Subject {
+                kind: "lease".to_string(),
+                id: "l1".to_string(),
+            };
+            event.seq_global = seq_global;
+            event
+        };
+        let expires_at = "2020-01-01T01:00:00Z";
+        rebuild_projections(
+            &writer,
+            vec![
+                lease_event(
+                    EVENT_LEASE_MINTED,
+                    1,
+                    serde_json::json!({
+                        "holder": {"kind": "session", "id": "s1"},
+                        "scope": {"actions": ["tool.request"]},
+                        "ttl_ms": 3_600_000,
+                        "expires_at": expires_at,
+                        "reason": "network exception"
+                    }),
+                ),
+                lease_event(
+                    EVENT_LEASE_USED,
+                    2,
+                    serde_json::json!({"command_type": "tool.request"}),
+                ),
+                lease_event(
+                    EVENT_LEASE_REVOKED,
+                    3,
+                    serde_json::json!({"reason": "done early"}),
+                ),
+                lease_event(
+                    EVENT_LEASE_EXPIRED,
+                    4,
+                    serde_json::json!({"expires_at": expires_at}),
+                ),
+            ],
+        )
+        .expect("rebuild");
+
+        let leases = writer.leases.borrow();
+        assert_eq!(leases.len(), 1);
+        assert_eq!(leases[0].minted_by, "system:system");
+        assert_eq!(leases[0].use_count, 1);
+        assert_eq!(leases[0].status, LeaseStatus::Revoked);
+        assert_eq!(leases[0].seq_global, 4);
+    }
+
+    #[test]
+    fn apply_event_tracks_secret_versions_without_values() {
+        let writer = RecordingWriter::default();
+        let secret_event = |event_type: &str, seq_global: i64, payload: serde_json::Value| {
+            let mut event = session_event(event_type, "s1", payload);
+            event.subject = Subject {
+                kind: "secret".to_string(),
+                id: "sec1".to_string(),
+            };
+            event.seq_global = seq_global;
+            event
+        };
+        let owner = serde_json::json!({"scope": "project", "id": "p1"});
+        let version = |version: u32, length: usize| {
+            serde_json::json!({
+                "name": "OPENAI_API_KEY",
+                "owner": owner.clone(),
+                "version": version,
+                "redacted": "****",
+                "length": length
+            })
+        };
+        rebuild_projections(
+            &writer,
+            vec![
+                secret_event(EVENT_SECRET_CREATED, 1, version(1, 12)),
+                secret_event(EVENT_SECRET_ROTATED, 2, version(2, 20)),
+            ],
+        )
+        .expect("rebuild");
+        {
+            let secrets = writer.secrets.borrow();
+            assert_eq!(secrets.len(), 1);
+            assert_eq!(secrets[0].owner.scope, mp_kernel::SecretScope::Project);
+            assert_eq!(secrets[0].version, 2);
+            assert_eq!(secrets[0].length, 20);
+            assert_eq!(secrets[0].redacted, "****");
+            assert_eq!(secrets[0].seq_global, 2);
+        }
+
+        apply_event(
+            &writer,
+            &secret_event(
+                EVENT_SECRET_DELETED,
+                3,
+                serde_json::json!({"name": "OPENAI_API_KEY", "owner": owner}),
+            ),
+        )
+        .expect("delete");
+        assert!(writer.secrets.borrow().is_empty());
+    }
+
     #[test]
     fn apply_event_rejects_invalid_payload() {
         let writer = RecordingWriter::default();
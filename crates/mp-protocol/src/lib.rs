@@ -1,5 +1,8 @@
 use anyhow::Context;
-use mp_kernel::{Actor, ErrorCode, Subject};
+use mp_kernel::{
+    is_namespaced_tool_id, Actor, ErrorCode, Subject, TaskState, ToolCallStatus, ToolDefinition,
+    ToolError, ToolRegistry,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -9,13 +12,246 @@ const COMMAND_WORKSPACE_CREATE_SCHEMA: &str =
     include_str!("../../../schemas/commands/workspace.create.v1.json");
 const COMMAND_PROJECT_CREATE_SCHEMA: &str =
     include_str!("../../../schemas/commands/project.create.v1.json");
+const COMMAND_WORKSPACE_RENAME_SCHEMA: &str =
+    include_str!("../../../schemas/commands/workspace.rename.v1.json");
+const COMMAND_WORKSPACE_LIFECYCLE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/workspace.lifecycle.v1.json");
+const COMMAND_PROJECT_RENAME_SCHEMA: &str =
+    include_str!("../../../schemas/commands/project.rename.v1.json");
+const COMMAND_PROJECT_LIFECYCLE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/project.lifecycle.v1.json");
+const COMMAND_SESSION_SPAWN_SCHEMA: &str =
+    include_str!("../../../schemas/commands/session.spawn.v1.json");
+const COMMAND_SESSION_FORK_SCHEMA: &str =
+    include_str!("../../../schemas/commands/session.fork.v1.json");
+const COMMAND_TASK_CREATE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/task.create.v1.json");
+const COMMAND_TASK_TRANSITION_SCHEMA: &str =
+    include_str!("../../../schemas/commands/task.transition.v1.json");
+const COMMAND_PIPELINE_TEMPLATE_DEFINE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/pipeline.template.define.v1.json");
+const COMMAND_PIPELINE_BIND_SCHEMA: &str =
+    include_str!("../../../schemas/commands/pipeline.bind.v1.json");
+const COMMAND_STAGE_TRANSITION_SCHEMA: &str =
+    include_str!("../../../schemas/commands/stage.transition.v1.json");
+const COMMAND_GATE_DEFINE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/gate.define.v1.json");
+const COMMAND_GATE_APPROVE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/gate.approve.v1.json");
+const COMMAND_GATE_REJECT_SCHEMA: &str =
+    include_str!("../../../schemas/commands/gate.reject.v1.json");
+const COMMAND_WORKTREE_REGISTER_SCHEMA: &str =
+    include_str!("../../../schemas/commands/worktree.register.v1.json");
+const COMMAND_WORKTREE_SESSION_SCHEMA: &str =
+    include_str!("../../../schemas/commands/worktree.session.v1.json");
+const COMMAND_BOARD_GROUP_CREATE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/board.group.create.v1.json");
+const COMMAND_BOARD_COMMENT_ADD_SCHEMA: &str =
+    include_str!("../../../schemas/commands/board.comment.add.v1.json");
+const COMMAND_BOARD_NODE_MOVE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/board.node.move.v1.json");
+const COMMAND_CAPSULE_WRITE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/capsule.write.v1.json");
+const COMMAND_MESSAGE_SEND_SCHEMA: &str =
+    include_str!("../../../schemas/commands/message.send.v1.json");
+const COMMAND_ARTIFACT_PUT_SCHEMA: &str =
+    include_str!("../../../schemas/commands/artifact.put.v1.json");
+const COMMAND_BLUEPRINT_SYNC_SCHEMA: &str =
+    include_str!("../../../schemas/commands/blueprint.sync.v1.json");
+const COMMAND_TOOL_REQUEST_SCHEMA: &str =
+    include_str!("../../../schemas/commands/tool.request.v1.json");
+const COMMAND_TOOL_COMPLETE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/tool.complete.v1.json");
+const COMMAND_SKILL_SYNC_SCHEMA: &str =
+    include_str!("../../../schemas/commands/skill.sync.v1.json");
+const COMMAND_SKILL_APPROVE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/skill.approve.v1.json");
+const COMMAND_LEASE_MINT_SCHEMA: &str =
+    include_str!("../../../schemas/commands/lease.mint.v1.json");
+const COMMAND_LEASE_REVOKE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/lease.revoke.v1.json");
+const COMMAND_SECRET_SET_SCHEMA: &str =
+    include_str!("../../../schemas/commands/secret.set.v1.json");
+const COMMAND_SECRET_ROTATE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/secret.rotate.v1.json");
+const COMMAND_SECRET_DELETE_SCHEMA: &str =
+    include_str!("../../../schemas/commands/secret.delete.v1.json");
+const COMMAND_VAULT_INIT_SCHEMA: &str =
+    include_str!("../../../schemas/commands/vault.init.v1.json");
+const COMMAND_VAULT_UNLOCK_SCHEMA: &str =
+    include_str!("../../../schemas/commands/vault.unlock.v1.json");
+const COMMAND_VAULT_LOCK_SCHEMA: &str =
+    include_str!("../../../schemas/commands/vault.lock.v1.json");
+const COMMAND_SECRET_EXEC_SCHEMA: &str =
+    include_str!("../../../schemas/commands/secret.exec.v1.json");
 
 const EVENT_WORKSPACE_CREATED_SCHEMA: &str =
     include_str!("../../../schemas/events/workspace.created.v1.json");
 const EVENT_PROJECT_CREATED_SCHEMA: &str =
     include_str!("../../../schemas/events/project.created.v1.json");
+const EVENT_ENTITY_RENAMED_SCHEMA: &str =
+    include_str!("../../../schemas/events/entity.renamed.v1.json");
+const EVENT_ENTITY_LIFECYCLE_CHANGED_SCHEMA: &str =
+    include_str!("../../../schemas/events/entity.lifecycle_changed.v1.json");
 const EVENT_COMMAND_REJECTED_SCHEMA: &str =
     include_str!("../../../schemas/events/command.rejected.v1.json");
+const EVENT_SESSION_SPAWNED_SCHEMA: &str =
+    include_str!("../../../schemas/events/session.spawned.v1.json");
+const EVENT_SESSION_FORKED_SCHEMA: &str =
+    include_str!("../../../schemas/events/session.forked.v1.json");
+const EVENT_TASK_CREATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/task.created.v1.json");
+const EVENT_TASK_TRANSITIONED_SCHEMA: &str =
+    include_str!("../../../schemas/events/task.transitioned.v1.json");
+const EVENT_TASK_RETRY_SCHEDULED_SCHEMA: &str =
+    include_str!("../../../schemas/events/task.retry_scheduled.v1.json");
+const EVENT_TASK_TIMED_OUT_SCHEMA: &str =
+    include_str!("../../../schemas/events/task.timed_out.v1.json");
+const EVENT_PIPELINE_TEMPLATE_DEFINED_SCHEMA: &str =
+    include_str!("../../../schemas/events/pipeline.template.defined.v1.json");
+const EVENT_PIPELINE_BOUND_SCHEMA: &str =
+    include_str!("../../../schemas/events/pipeline.bound.v1.json");
+const EVENT_TASK_STAGE_CHANGED_SCHEMA: &str =
+    include_str!("../../../schemas/events/task.stage_changed.v1.json");
+const EVENT_GATE_DEFINED_SCHEMA: &str =
+    include_str!("../../../schemas/events/gate.defined.v1.json");
+const EVENT_GATE_APPROVED_SCHEMA: &str =
+    include_str!("../../../schemas/events/gate.approved.v1.json");
+const EVENT_GATE_REJECTED_SCHEMA: &str =
+    include_str!("../../../schemas/events/gate.rejected.v1.json");
+const EVENT_WORKTREE_REGISTERED_SCHEMA: &str =
+    include_str!("../../../schemas/events/worktree.registered.v1.json");
+const EVENT_WORKTREE_SESSION_CHANGED_SCHEMA: &str =
+    include_str!("../../../schemas/events/worktree.session_changed.v1.json");
+const EVENT_WORKTREE_LOCK_RELEASED_SCHEMA: &str =
+    include_str!("../../../schemas/events/worktree.lock.released.v1.json");
+const EVENT_BOARD_GROUP_CREATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/board.group.created.v1.json");
+const EVENT_BOARD_COMMENT_ADDED_SCHEMA: &str =
+    include_str!("../../../schemas/events/board.comment.added.v1.json");
+const EVENT_BOARD_NODE_MOVED_SCHEMA: &str =
+    include_str!("../../../schemas/events/board.node.moved.v1.json");
+const EVENT_CAPSULE_WRITTEN_SCHEMA: &str =
+    include_str!("../../../schemas/events/capsule.written.v1.json");
+const EVENT_MESSAGE_SENT_SCHEMA: &str =
+    include_str!("../../../schemas/events/message.sent.v1.json");
+const EVENT_MESSAGE_DELIVERED_SCHEMA: &str =
+    include_str!("../../../schemas/events/message.delivered.v1.json");
+const EVENT_ARTIFACT_STORED_SCHEMA: &str =
+    include_str!("../../../schemas/events/artifact.stored.v1.json");
+const EVENT_BLUEPRINT_REGISTERED_SCHEMA: &str =
+    include_str!("../../../schemas/events/blueprint.registered.v1.json");
+const EVENT_BLUEPRINT_VERSION_CREATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/blueprint.version_created.v1.json");
+const EVENT_HOOK_FAILED_SCHEMA: &str = include_str!("../../../schemas/events/hook.failed.v1.json");
+const EVENT_HOOK_INPUT_RECORDED_SCHEMA: &str =
+    include_str!("../../../schemas/events/hook.input_recorded.v1.json");
+const EVENT_SANDBOX_HOSTCALL_SCHEMA: &str =
+    include_str!("../../../schemas/events/sandbox.hostcall.v1.json");
+const EVENT_SANDBOX_EVENT_EMITTED_SCHEMA: &str =
+    include_str!("../../../schemas/events/sandbox.event_emitted.v1.json");
+const EVENT_TOOL_REQUESTED_SCHEMA: &str =
+    include_str!("../../../schemas/events/tool.requested.v1.json");
+const EVENT_TOOL_RESULT_SCHEMA: &str = include_str!("../../../schemas/events/tool.result.v1.json");
+const EVENT_SKILL_REGISTERED_SCHEMA: &str =
+    include_str!("../../../schemas/events/skill.registered.v1.json");
+const EVENT_SKILL_UPDATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/skill.updated.v1.json");
+const EVENT_SKILL_REMOVED_SCHEMA: &str =
+    include_str!("../../../schemas/events/skill.removed.v1.json");
+const EVENT_SKILL_APPROVED_SCHEMA: &str =
+    include_str!("../../../schemas/events/skill.approved.v1.json");
+const EVENT_POLICY_EVALUATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/policy.evaluated.v1.json");
+const EVENT_LEASE_MINTED_SCHEMA: &str =
+    include_str!("../../../schemas/events/lease.minted.v1.json");
+const EVENT_LEASE_USED_SCHEMA: &str = include_str!("../../../schemas/events/lease.used.v1.json");
+const EVENT_LEASE_REVOKED_SCHEMA: &str =
+    include_str!("../../../schemas/events/lease.revoked.v1.json");
+const EVENT_LEASE_EXPIRED_SCHEMA: &str =
+    include_str!("../../../schemas/events/lease.expired.v1.json");
+const EVENT_SECRET_CREATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/secret.created.v1.json");
+const EVENT_SECRET_ROTATED_SCHEMA: &str =
+    include_str!("../../../schemas/events/secret.rotated.v1.json");
+const EVENT_SECRET_DELETED_SCHEMA: &str =
+    include_str!("../../../schemas/events/secret.deleted.v1.json");
+const EVENT_SECRET_INJECTED_SCHEMA: &str =
+    include_str!("../../../schemas/events/secret.injected.v1.json");
+const EVENT_PROCESS_LAUNCHED_SCHEMA: &str =
+    include_str!("../../../schemas/events/process.launched.v1.json");
+const EVENT_PROCESS_EXITED_SCHEMA: &str =
+    include_str!("../../../schemas/events/process.exited.v1.json");
+const EVENT_VAULT_INITIALIZED_SCHEMA: &str =
+    include_str!("../../../schemas/events/vault.initialized.v1.json");
+const EVENT_VAULT_UNLOCKED_SCHEMA: &str =
+    include_str!("../../../schemas/events/vault.unlocked.v1.json");
+const EVENT_VAULT_LOCKED_SCHEMA: &str =
+    include_str!("../../../schemas/events/vault.locked.v1.json");
+
+const DOCUMENT_AGENT_BLUEPRINT_SCHEMA: &str =
+    include_str!("../../../schemas/documents/agent_blueprint.v1.json");
+const DOCUMENT_SKILL_SCHEMA: &str = include_str!("../../../schemas/documents/skill.v1.json");
+const DOCUMENT_POLICY_BUNDLE_SCHEMA: &str =
+    include_str!("../../../schemas/documents/policy_bundle.v1.json");
+const DOCUMENT_ROLE_BINDINGS_SCHEMA: &str =
+    include_str!("../../../schemas/documents/role_bindings.v1.json");
+const DOCUMENT_PIN_MANIFEST_SCHEMA: &str =
+    include_str!("../../../schemas/documents/pin_manifest.v1.json");
+const DOCUMENT_HOOK_CHAIN_SCHEMA: &str =
+    include_str!("../../../schemas/documents/hook_chain.v1.json");
+const DOCUMENT_TOOL_REGISTRY_SCHEMA: &str =
+    include_str!("../../../schemas/documents/tool_registry.v1.json");
+
+/// Task lifecycle commands share one payload schema.
+const TASK_TRANSITION_COMMANDS: [&str; 7] = [
+    "task.start",
+    "task.succeed",
+    "task.fail",
+    "task.cancel",
+    "task.pause",
+    "task.resume",
+    "task.await_input",
+];
+
+/// Task lifecycle events share one payload schema.
+const TASK_TRANSITION_EVENTS: [&str; 9] = [
+    "task.started",
+    "task.succeeded",
+    "task.failed",
+    "task.cancelled",
+    "task.paused",
+    "task.resumed",
+    "task.awaiting_input",
+    "task.blocked_by_policy",
+    "task.blocked_by_approval",
+];
+
+/// Workspace and project renames share one event schema.
+const RENAMED_EVENTS: [&str; 2] = ["workspace.renamed", "project.renamed"];
+
+/// Archive and restore events of workspaces and projects share one event schema.
+const LIFECYCLE_CHANGED_EVENTS: [&str; 4] = [
+    "workspace.archived",
+    "workspace.restored",
+    "project.archived",
+    "project.restored",
+];
+
+/// Worktree attach/detach and lock commands share one payload schema.
+const WORKTREE_SESSION_COMMANDS: [&str; 4] = [
+    "worktree.attach",
+    "worktree.detach",
+    "worktree.lock.acquire",
+    "worktree.lock.release",
+];
+
+/// Worktree events that only name the session involved.
+const WORKTREE_SESSION_EVENTS: [&str; 3] = [
+    "worktree.attached",
+    "worktree.detached",
+    "worktree.lock.acquired",
+];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -29,6 +265,13 @@ pub struct CommandEnvelope {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub expected_version: Option<i64>,
     pub trace_id: String,
+    /// Identity the caller acts as; policy subjects and event actors come from it. The daemon
+    /// acts as `system` when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub actor: Option<Actor>,
+    /// Capability lease the actor presents to run a command policy would otherwise deny.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lease_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -67,6 +310,32 @@ pub struct CommandRejection {
     pub message: String,
 }
 
+/// A compact account of a run; the recorded events stay readable through the event log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ToolRunResponse {
+    pub accepted: bool,
+    pub trace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection: Option<CommandRejection>,
+    /// What the run acted on; a `tool_call` for registry tools.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject: Option<Subject>,
+    /// Outcome of a registry tool call; `pending` until a runner completes it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ToolCallStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ToolError>,
+    /// `seq_global` of the last recorded event.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seq: Option<i64>,
+    /// Set when the output was left out to stay within the response budget.
+    #[serde(default)]
+    pub truncated: bool,
+}
+
 /// Canonical daemon error response payload used across transports.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -98,10 +367,43 @@ pub struct StdioAuthPayload {
     pub token: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StdioWorkspacesQuery {
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct StdioProjectsQuery {
     pub workspace_id: String,
+    #[serde(default)]
+    pub include_archived: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StdioSessionsQuery {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root_session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StdioTasksQuery {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<TaskState>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StdioBoardQuery {
+    pub workspace_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,10 +414,49 @@ pub struct StdioEventsSubscribe {
     pub from: Option<i64>,
 }
 
+/// Optional narrowing of an event read or stream; unset fields match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventFilter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_id: Option<String>,
+    /// Exact event type, or a `prefix.*` pattern such as `message.*`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event_type: Option<String>,
+}
+
+impl EventFilter {
+    pub fn matches(&self, event: &EventEnvelope) -> bool {
+        if let Some(kind) = &self.subject_kind {
+            if &event.subject.kind != kind {
+                return false;
+            }
+        }
+        if let Some(id) = &self.subject_id {
+            if &event.subject.id != id {
+                return false;
+            }
+        }
+        match self.event_type.as_deref() {
+            None => true,
+            Some(pattern) => match pattern.strip_suffix('*') {
+                Some(prefix) => event.event_type.starts_with(prefix),
+                None => event.event_type == pattern,
+            },
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct SchemaRegistry {
     command_schemas: HashMap<(String, i32), Value>,
     event_schemas: HashMap<(String, i32), Value>,
+    /// Files authored in the workspace, such as blueprints, keyed by kind and `api_version`.
+    document_schemas: HashMap<(String, i32), Value>,
+    /// Tools the kernel can run, keyed by id; their schemas are checked on registration.
+    tools: HashMap<String, ToolDefinition>,
 }
 
 #[derive(Debug)]
@@ -148,6 +489,230 @@ impl SchemaRegistry {
             1,
             COMMAND_PROJECT_CREATE_SCHEMA,
         )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "workspace.rename",
+            1,
+            COMMAND_WORKSPACE_RENAME_SCHEMA,
+        )?;
+        for command_type in ["workspace.archive", "workspace.restore"] {
+            Self::insert_schema(
+                &mut command_schemas,
+                command_type,
+                1,
+                COMMAND_WORKSPACE_LIFECYCLE_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut command_schemas,
+            "project.rename",
+            1,
+            COMMAND_PROJECT_RENAME_SCHEMA,
+        )?;
+        for command_type in ["project.archive", "project.restore"] {
+            Self::insert_schema(
+                &mut command_schemas,
+                command_type,
+                1,
+                COMMAND_PROJECT_LIFECYCLE_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut command_schemas,
+            "session.spawn",
+            1,
+            COMMAND_SESSION_SPAWN_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "session.fork",
+            1,
+            COMMAND_SESSION_FORK_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "task.create",
+            1,
+            COMMAND_TASK_CREATE_SCHEMA,
+        )?;
+        for command_type in TASK_TRANSITION_COMMANDS {
+            Self::insert_schema(
+                &mut command_schemas,
+                command_type,
+                1,
+                COMMAND_TASK_TRANSITION_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut command_schemas,
+            "pipeline.template.define",
+            1,
+            COMMAND_PIPELINE_TEMPLATE_DEFINE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "pipeline.bind",
+            1,
+            COMMAND_PIPELINE_BIND_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "stage.transition",
+            1,
+            COMMAND_STAGE_TRANSITION_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "gate.define",
+            1,
+            COMMAND_GATE_DEFINE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "gate.approve",
+            1,
+            COMMAND_GATE_APPROVE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "gate.reject",
+            1,
+            COMMAND_GATE_REJECT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "worktree.register",
+            1,
+            COMMAND_WORKTREE_REGISTER_SCHEMA,
+        )?;
+        for command_type in WORKTREE_SESSION_COMMANDS {
+            Self::insert_schema(
+                &mut command_schemas,
+                command_type,
+                1,
+                COMMAND_WORKTREE_SESSION_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut command_schemas,
+            "board.group.create",
+            1,
+            COMMAND_BOARD_GROUP_CREATE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "board.comment.add",
+            1,
+            COMMAND_BOARD_COMMENT_ADD_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "board.node.move",
+            1,
+            COMMAND_BOARD_NODE_MOVE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "capsule.write",
+            1,
+            COMMAND_CAPSULE_WRITE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "message.send",
+            1,
+            COMMAND_MESSAGE_SEND_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "artifact.put",
+            1,
+            COMMAND_ARTIFACT_PUT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "blueprint.sync",
+            1,
+            COMMAND_BLUEPRINT_SYNC_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "tool.request",
+            1,
+            COMMAND_TOOL_REQUEST_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "tool.complete",
+            1,
+            COMMAND_TOOL_COMPLETE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "skill.sync",
+            1,
+            COMMAND_SKILL_SYNC_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "skill.approve",
+            1,
+            COMMAND_SKILL_APPROVE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "lease.mint",
+            1,
+            COMMAND_LEASE_MINT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "lease.revoke",
+            1,
+            COMMAND_LEASE_REVOKE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "secret.set",
+            1,
+            COMMAND_SECRET_SET_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "secret.rotate",
+            1,
+            COMMAND_SECRET_ROTATE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "secret.delete",
+            1,
+            COMMAND_SECRET_DELETE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "secret.exec",
+            1,
+            COMMAND_SECRET_EXEC_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "vault.init",
+            1,
+            COMMAND_VAULT_INIT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "vault.unlock",
+            1,
+            COMMAND_VAULT_UNLOCK_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut command_schemas,
+            "vault.lock",
+            1,
+            COMMAND_VAULT_LOCK_SCHEMA,
+        )?;
 
         Self::insert_schema(
             &mut event_schemas,
@@ -161,83 +726,534 @@ impl SchemaRegistry {
             1,
             EVENT_PROJECT_CREATED_SCHEMA,
         )?;
+        for event_type in RENAMED_EVENTS {
+            Self::insert_schema(
+                &mut event_schemas,
+                event_type,
+                1,
+                EVENT_ENTITY_RENAMED_SCHEMA,
+            )?;
+        }
+        for event_type in LIFECYCLE_CHANGED_EVENTS {
+            Self::insert_schema(
+                &mut event_schemas,
+                event_type,
+                1,
+                EVENT_ENTITY_LIFECYCLE_CHANGED_SCHEMA,
+            )?;
+        }
         Self::insert_schema(
             &mut event_schemas,
             "command.rejected",
             1,
             EVENT_COMMAND_REJECTED_SCHEMA,
         )?;
-
-        Ok(Self {
-            command_schemas,
-            event_schemas,
-        })
-    }
-
-    fn insert_schema(
-        map: &mut HashMap<(String, i32), Value>,
-        command_type: &str,
-        schema_version: i32,
-        raw_schema: &str,
-    ) -> anyhow::Result<()> {
-        let schema_json: Value = serde_json::from_str(raw_schema)
-            .with_context(|| format!("invalid JSON schema for {command_type} v{schema_version}"))?;
-        map.insert((command_type.to_string(), schema_version), schema_json);
-        Ok(())
-    }
-
-    pub fn validate_command_payload(
-        &self,
-        command_type: &str,
-        schema_version: i32,
-        payload: &Value,
-    ) -> Result<(), SchemaError> {
-        let schema = self
-            .command_schemas
-            .get(&(command_type.to_string(), schema_version))
-            .ok_or_else(|| SchemaError {
-                message: format!("schema not found for command {command_type} v{schema_version}"),
-            })?;
-        Self::validate(schema, payload)
-    }
-
-    pub fn validate_event_payload(
-        &self,
-        event_type: &str,
-        schema_version: i32,
-        payload: &Value,
-    ) -> Result<(), SchemaError> {
-        let schema = self
-            .event_schemas
-            .get(&(event_type.to_string(), schema_version))
-            .ok_or_else(|| SchemaError {
-                message: format!("schema not found for event {event_type} v{schema_version}"),
-            })?;
-        Self::validate(schema, payload)
-    }
-
-    fn validate(schema: &Value, payload: &Value) -> Result<(), SchemaError> {
-        let compiled = jsonschema::JSONSchema::compile(schema).map_err(|err| SchemaError {
-            message: format!("failed to compile schema: {err}"),
-        })?;
-        let result = compiled.validate(payload);
-        if let Err(errors) = result {
-            let mut messages = Vec::new();
-            for error in errors {
-                messages.push(error.to_string());
-            }
-            return Err(SchemaError {
-                message: messages.join("; "),
-            });
-        }
+        Self::insert_schema(
+            &mut event_schemas,
+            "session.spawned",
+            1,
+            EVENT_SESSION_SPAWNED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "session.forked",
+            1,
+            EVENT_SESSION_FORKED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "task.created",
+            1,
+            EVENT_TASK_CREATED_SCHEMA,
+        )?;
+        for event_type in TASK_TRANSITION_EVENTS {
+            Self::insert_schema(
+                &mut event_schemas,
+                event_type,
+                1,
+                EVENT_TASK_TRANSITIONED_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut event_schemas,
+            "task.retry_scheduled",
+            1,
+            EVENT_TASK_RETRY_SCHEDULED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "task.timed_out",
+            1,
+            EVENT_TASK_TIMED_OUT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "pipeline.template.defined",
+            1,
+            EVENT_PIPELINE_TEMPLATE_DEFINED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "pipeline.bound",
+            1,
+            EVENT_PIPELINE_BOUND_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "task.stage_changed",
+            1,
+            EVENT_TASK_STAGE_CHANGED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "gate.defined",
+            1,
+            EVENT_GATE_DEFINED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "gate.approved",
+            1,
+            EVENT_GATE_APPROVED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "gate.rejected",
+            1,
+            EVENT_GATE_REJECTED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "worktree.registered",
+            1,
+            EVENT_WORKTREE_REGISTERED_SCHEMA,
+        )?;
+        for event_type in WORKTREE_SESSION_EVENTS {
+            Self::insert_schema(
+                &mut event_schemas,
+                event_type,
+                1,
+                EVENT_WORKTREE_SESSION_CHANGED_SCHEMA,
+            )?;
+        }
+        Self::insert_schema(
+            &mut event_schemas,
+            "worktree.lock.released",
+            1,
+            EVENT_WORKTREE_LOCK_RELEASED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "board.group.created",
+            1,
+            EVENT_BOARD_GROUP_CREATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "board.comment.added",
+            1,
+            EVENT_BOARD_COMMENT_ADDED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "board.node.moved",
+            1,
+            EVENT_BOARD_NODE_MOVED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "capsule.written",
+            1,
+            EVENT_CAPSULE_WRITTEN_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "message.sent",
+            1,
+            EVENT_MESSAGE_SENT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "message.delivered",
+            1,
+            EVENT_MESSAGE_DELIVERED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "artifact.stored",
+            1,
+            EVENT_ARTIFACT_STORED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "blueprint.registered",
+            1,
+            EVENT_BLUEPRINT_REGISTERED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "blueprint.version_created",
+            1,
+            EVENT_BLUEPRINT_VERSION_CREATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "hook.failed",
+            1,
+            EVENT_HOOK_FAILED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "hook.input_recorded",
+            1,
+            EVENT_HOOK_INPUT_RECORDED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "sandbox.hostcall",
+            1,
+            EVENT_SANDBOX_HOSTCALL_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "sandbox.event_emitted",
+            1,
+            EVENT_SANDBOX_EVENT_EMITTED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "tool.requested",
+            1,
+            EVENT_TOOL_REQUESTED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "tool.result",
+            1,
+            EVENT_TOOL_RESULT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "skill.registered",
+            1,
+            EVENT_SKILL_REGISTERED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "skill.updated",
+            1,
+            EVENT_SKILL_UPDATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "skill.removed",
+            1,
+            EVENT_SKILL_REMOVED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "skill.approved",
+            1,
+            EVENT_SKILL_APPROVED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "policy.evaluated",
+            1,
+            EVENT_POLICY_EVALUATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "lease.minted",
+            1,
+            EVENT_LEASE_MINTED_SCHEMA,
+        )?;
+        Self::insert_schema(&mut event_schemas, "lease.used", 1, EVENT_LEASE_USED_SCHEMA)?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "lease.revoked",
+            1,
+            EVENT_LEASE_REVOKED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "lease.expired",
+            1,
+            EVENT_LEASE_EXPIRED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "secret.created",
+            1,
+            EVENT_SECRET_CREATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "secret.rotated",
+            1,
+            EVENT_SECRET_ROTATED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "secret.deleted",
+            1,
+            EVENT_SECRET_DELETED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "secret.injected",
+            1,
+            EVENT_SECRET_INJECTED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "process.launched",
+            1,
+            EVENT_PROCESS_LAUNCHED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "process.exited",
+            1,
+            EVENT_PROCESS_EXITED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "vault.initialized",
+            1,
+            EVENT_VAULT_INITIALIZED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "vault.unlocked",
+            1,
+            EVENT_VAULT_UNLOCKED_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut event_schemas,
+            "vault.locked",
+            1,
+            EVENT_VAULT_LOCKED_SCHEMA,
+        )?;
+
+        let mut document_schemas = HashMap::new();
+        Self::insert_schema(
+            &mut document_schemas,
+            "agent_blueprint",
+            1,
+            DOCUMENT_AGENT_BLUEPRINT_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut document_schemas,
+            "pin_manifest",
+            1,
+            DOCUMENT_PIN_MANIFEST_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut document_schemas,
+            "hook_chain",
+            1,
+            DOCUMENT_HOOK_CHAIN_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut document_schemas,
+            "tool_registry",
+            1,
+            DOCUMENT_TOOL_REGISTRY_SCHEMA,
+        )?;
+        Self::insert_schema(&mut document_schemas, "skill", 1, DOCUMENT_SKILL_SCHEMA)?;
+        Self::insert_schema(
+            &mut document_schemas,
+            "policy_bundle",
+            1,
+            DOCUMENT_POLICY_BUNDLE_SCHEMA,
+        )?;
+        Self::insert_schema(
+            &mut document_schemas,
+            "role_bindings",
+            1,
+            DOCUMENT_ROLE_BINDINGS_SCHEMA,
+        )?;
+
+        Ok(Self {
+            command_schemas,
+            event_schemas,
+            document_schemas,
+            tools: HashMap::new(),
+        })
+    }
+
+    fn insert_schema(
+        map: &mut HashMap<(String, i32), Value>,
+        command_type: &str,
+        schema_version: i32,
+        raw_schema: &str,
+    ) -> anyhow::Result<()> {
+        let schema_json: Value = serde_json::from_str(raw_schema)
+            .with_context(|| format!("invalid JSON schema for {command_type} v{schema_version}"))?;
+        map.insert((command_type.to_string(), schema_version), schema_json);
+        Ok(())
+    }
+
+    pub fn validate_command_payload(
+        &self,
+        command_type: &str,
+        schema_version: i32,
+        payload: &Value,
+    ) -> Result<(), SchemaError> {
+        let schema = self
+            .command_schemas
+            .get(&(command_type.to_string(), schema_version))
+            .ok_or_else(|| SchemaError {
+                message: format!("schema not found for command {command_type} v{schema_version}"),
+            })?;
+        Self::validate(schema, payload)
+    }
+
+    pub fn validate_event_payload(
+        &self,
+        event_type: &str,
+        schema_version: i32,
+        payload: &Value,
+    ) -> Result<(), SchemaError> {
+        let schema = self
+            .event_schemas
+            .get(&(event_type.to_string(), schema_version))
+            .ok_or_else(|| SchemaError {
+                message: format!("schema not found for event {event_type} v{schema_version}"),
+            })?;
+        Self::validate(schema, payload)
+    }
+
+    pub fn validate_document(
+        &self,
+        kind: &str,
+        api_version: i32,
+        document: &Value,
+    ) -> Result<(), SchemaError> {
+        let schema = self
+            .document_schemas
+            .get(&(kind.to_string(), api_version))
+            .ok_or_else(|| SchemaError {
+                message: format!("schema not found for {kind} api_version {api_version}"),
+            })?;
+        Self::validate(schema, document)
+    }
+
+    /// Command types with a registered schema, ordered by type, each with its latest version.
+    pub fn command_schemas(&self) -> Vec<(&str, i32, &Value)> {
+        let mut latest: HashMap<&str, (i32, &Value)> = HashMap::new();
+        for ((command_type, version), schema) in &self.command_schemas {
+            let entry = latest
+                .entry(command_type.as_str())
+                .or_insert((*version, schema));
+            if *version > entry.0 {
+                *entry = (*version, schema);
+            }
+        }
+        let mut schemas = latest
+            .into_iter()
+            .map(|(command_type, (version, schema))| (command_type, version, schema))
+            .collect::<Vec<_>>();
+        schemas.sort_by(|a, b| a.0.cmp(b.0));
+        schemas
+    }
+
+    /// Adds every tool in `registry`, refusing the whole registry if any tool is malformed:
+    /// ids must be namespaced and unique, schemas must compile, inputs must reject unknown
+    /// fields and every example must match the schemas.
+    pub fn register_tools(&mut self, registry: &ToolRegistry) -> Result<(), SchemaError> {
+        let mut tools = HashMap::new();
+        for tool in &registry.tools {
+            Self::check_tool(tool).map_err(|message| SchemaError {
+                message: format!("tool {}: {message}", tool.id),
+            })?;
+            if tools.insert(tool.id.clone(), tool.clone()).is_some() {
+                return Err(SchemaError {
+                    message: format!("tool {} is defined more than once", tool.id),
+                });
+            }
+        }
+        self.tools.extend(tools);
+        Ok(())
+    }
+
+    fn check_tool(tool: &ToolDefinition) -> Result<(), String> {
+        if !is_namespaced_tool_id(&tool.id) {
+            return Err("id must be namespaced, e.g. `acme.search`".to_string());
+        }
+        let input = tool.input_schema.as_object();
+        if input.and_then(|schema| schema.get("type")) != Some(&Value::from("object"))
+            || input.and_then(|schema| schema.get("additionalProperties"))
+                != Some(&Value::Bool(false))
+        {
+            return Err(
+                "input_schema must be an object schema with additionalProperties: false"
+                    .to_string(),
+            );
+        }
+        for (index, example) in tool.examples.iter().enumerate() {
+            Self::validate(&tool.input_schema, &example.input)
+                .map_err(|err| format!("example {index} input: {err}"))?;
+            if let Some(output) = &example.output {
+                Self::validate(&tool.output_schema, output)
+                    .map_err(|err| format!("example {index} output: {err}"))?;
+            }
+        }
+        jsonschema::JSONSchema::compile(&tool.input_schema)
+            .map_err(|err| format!("input_schema does not compile: {err}"))?;
+        jsonschema::JSONSchema::compile(&tool.output_schema)
+            .map_err(|err| format!("output_schema does not compile: {err}"))?;
+        Ok(())
+    }
+
+    pub fn tool(&self, tool_id: &str) -> Option<&ToolDefinition> {
+        self.tools.get(tool_id)
+    }
+
+    /// Registered tools, ordered by id.
+    pub fn tools(&self) -> Vec<&ToolDefinition> {
+        let mut tools = self.tools.values().collect::<Vec<_>>();
+        tools.sort_by(|a, b| a.id.cmp(&b.id));
+        tools
+    }
+
+    pub fn validate_tool_input(&self, tool_id: &str, input: &Value) -> Result<(), SchemaError> {
+        let tool = self.tool(tool_id).ok_or_else(|| SchemaError {
+            message: format!("tool {tool_id} is not registered"),
+        })?;
+        Self::validate(&tool.input_schema, input)
+    }
+
+    pub fn validate_tool_output(&self, tool_id: &str, output: &Value) -> Result<(), SchemaError> {
+        let tool = self.tool(tool_id).ok_or_else(|| SchemaError {
+            message: format!("tool {tool_id} is not registered"),
+        })?;
+        Self::validate(&tool.output_schema, output)
+    }
+
+    fn validate(schema: &Value, payload: &Value) -> Result<(), SchemaError> {
+        let compiled = jsonschema::JSONSchema::compile(schema).map_err(|err| SchemaError {
+            message: format!("failed to compile schema: {err}"),
+        })?;
+        let result = compiled.validate(payload);
+        if let Err(errors) = result {
+            let mut messages = Vec::new();
+            for error in errors {
+                messages.push(error.to_string());
+            }
+            return Err(SchemaError {
+                message: messages.join("; "),
+            });
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ErrorResponse, SchemaRegistry};
-    use mp_kernel::ErrorCode;
+    use super::{ErrorResponse, EventEnvelope, EventFilter, SchemaRegistry};
+    use mp_kernel::{ErrorCode, ToolRegistry};
     use serde_json::json;
 
     #[test]
@@ -270,6 +1286,91 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn blueprint_document_schema_checks_shape() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let blueprint = json!({
+            "api_version": 1,
+            "id": "reviewer",
+            "name": "Reviewer",
+            "runtime": {"kind": "external_cli", "adapter": "claude-code"},
+            "hooks": {"chain": [{"id": "core.audit", "version": "1.0.0", "trust": "trusted"}]}
+        });
+        assert!(registry
+            .validate_document("agent_blueprint", 1, &blueprint)
+            .is_ok());
+        let mut unknown_trust = blueprint.clone();
+        unknown_trust["hooks"]["chain"][0]["trust"] = json!("root");
+        assert!(registry
+            .validate_document("agent_blueprint", 1, &unknown_trust)
+            .is_err());
+        let mut future = blueprint;
+        future["api_version"] = json!(2);
+        assert!(registry
+            .validate_document("agent_blueprint", 1, &future)
+            .is_err());
+    }
+
+    #[test]
+    fn skill_frontmatter_schema_checks_names() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let skill = json!({"name": "pdf-tools", "description": "Reads PDFs."});
+        assert!(registry.validate_document("skill", 1, &skill).is_ok());
+        for name in ["PDF", "pdf--tools", "-pdf", ""] {
+            let mut bad = skill.clone();
+            bad["name"] = json!(name);
+            assert!(registry.validate_document("skill", 1, &bad).is_err());
+        }
+        let mut nested = skill;
+        nested["metadata"] = json!({"owner": {"team": "docs"}});
+        assert!(registry.validate_document("skill", 1, &nested).is_err());
+    }
+
+    #[test]
+    fn policy_bundle_schema_checks_conditions() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let bundle = json!({
+            "api_version": 1,
+            "id": "team-defaults",
+            "default": "allow",
+            "rules": [{
+                "id": "agents-no-worktrees",
+                "effect": "deny",
+                "actions": ["worktree.*"],
+                "subject": {"kind": "session"},
+                "object": {"workspace_id": ["w1", "w2"]},
+                "when": [
+                    {"op": "in", "attribute": "payload.tool_id", "values": ["git.push"]},
+                    {"op": "equals", "attribute": "subject.id", "other": "payload.session_id"},
+                    {"op": "absent", "attribute": "object.project_id"}
+                ]
+            }]
+        });
+        assert!(registry
+            .validate_document("policy_bundle", 1, &bundle)
+            .is_ok());
+        let invalid = [
+            ("/rules/0/actions/0", json!("worktree*")),
+            ("/rules/0/when/0/values", json!([])),
+            ("/rules/0/when/1/other", json!("context.user")),
+            (
+                "/rules/0/when/2",
+                json!({"op": "absent", "attribute": "x", "values": ["a"]}),
+            ),
+            ("/default", json!("audit")),
+        ];
+        for (pointer, value) in invalid {
+            let mut bad = bundle.clone();
+            *bad.pointer_mut(pointer).expect("pointer") = value;
+            assert!(
+                registry
+                    .validate_document("policy_bundle", 1, &bad)
+                    .is_err(),
+                "{pointer} should be rejected"
+            );
+        }
+    }
+
     #[test]
     fn command_schema_requires_fields() {
         let registry = SchemaRegistry::new().expect("registry");
@@ -278,6 +1379,360 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn session_schemas_reject_unknown_fields() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let command = json!({
+            "workspace_id": "w1",
+            "session_id": "s1",
+            "mode": "planning_only",
+            "extra": true
+        });
+        assert!(registry
+            .validate_command_payload("session.fork", 1, &command)
+            .is_err());
+
+        let event = json!({
+            "root_session_id": "s1",
+            "lineage_path": "/s1",
+            "extra": true
+        });
+        assert!(registry
+            .validate_event_payload("session.spawned", 1, &event)
+            .is_err());
+    }
+
+    #[test]
+    fn session_fork_schema_rejects_unknown_mode() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({
+            "workspace_id": "w1",
+            "session_id": "s1",
+            "mode": "sideways"
+        });
+        let result = registry.validate_command_payload("session.fork", 1, &payload);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn task_transition_schemas_are_registered_for_every_action() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({"workspace_id": "w1", "task_id": "t1", "reason": "ci"});
+        for command_type in super::TASK_TRANSITION_COMMANDS {
+            assert!(registry
+                .validate_command_payload(command_type, 1, &payload)
+                .is_ok());
+        }
+        let unknown = json!({"workspace_id": "w1", "task_id": "t1", "extra": 1});
+        assert!(registry
+            .validate_command_payload("task.fail", 1, &unknown)
+            .is_err());
+
+        let event = json!({"from": "running", "to": "exploded"});
+        assert!(registry
+            .validate_event_payload("task.failed", 1, &event)
+            .is_err());
+    }
+
+    #[test]
+    fn task_create_schema_validates_retry_policy() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({
+            "workspace_id": "w1",
+            "title": "build",
+            "timeout_ms": 30000,
+            "retry": {
+                "max_attempts": 3,
+                "backoff": { "kind": "exponential", "initial_ms": 100, "max_ms": 5000 },
+                "jitter": "decorrelated",
+                "retry_on": ["transient", "timeout"]
+            }
+        });
+        assert!(registry
+            .validate_command_payload("task.create", 1, &payload)
+            .is_ok());
+
+        let bad_backoff = json!({
+            "workspace_id": "w1",
+            "title": "build",
+            "retry": { "max_attempts": 3, "backoff": { "kind": "fixed", "initial_ms": 100 } }
+        });
+        assert!(registry
+            .validate_command_payload("task.create", 1, &bad_backoff)
+            .is_err());
+        let zero_attempts = json!({
+            "workspace_id": "w1",
+            "title": "build",
+            "retry": { "max_attempts": 0, "backoff": { "kind": "fixed", "delay_ms": 1 } }
+        });
+        assert!(registry
+            .validate_command_payload("task.create", 1, &zero_attempts)
+            .is_err());
+    }
+
+    #[test]
+    fn pipeline_template_requires_named_stages() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({
+            "workspace_id": "w1",
+            "name": "delivery",
+            "stages": [{ "name": "plan" }, { "name": "build", "description": "compile + test" }]
+        });
+        assert!(registry
+            .validate_command_payload("pipeline.template.define", 1, &payload)
+            .is_ok());
+        let empty = json!({ "workspace_id": "w1", "name": "delivery", "stages": [] });
+        assert!(registry
+            .validate_command_payload("pipeline.template.define", 1, &empty)
+            .is_err());
+        let unnamed = json!({ "workspace_id": "w1", "name": "delivery", "stages": [{}] });
+        assert!(registry
+            .validate_command_payload("pipeline.template.define", 1, &unnamed)
+            .is_err());
+    }
+
+    #[test]
+    fn gate_approval_requires_known_approver_kind() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({
+            "workspace_id": "w1",
+            "gate_id": "g1",
+            "approver": { "kind": "human", "id": "alice" },
+            "evidence": ["blake3:abc"]
+        });
+        assert!(registry
+            .validate_command_payload("gate.approve", 1, &payload)
+            .is_ok());
+        let anonymous = json!({ "workspace_id": "w1", "gate_id": "g1" });
+        assert!(registry
+            .validate_command_payload("gate.reject", 1, &anonymous)
+            .is_err());
+        let unknown = json!({
+            "workspace_id": "w1",
+            "gate_id": "g1",
+            "approver": { "kind": "oracle", "id": "x" }
+        });
+        assert!(registry
+            .validate_command_payload("gate.approve", 1, &unknown)
+            .is_err());
+    }
+
+    #[test]
+    fn worktree_session_commands_share_schema() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({"workspace_id": "w1", "worktree_id": "wt1", "session_id": "s1"});
+        for command_type in super::WORKTREE_SESSION_COMMANDS {
+            assert!(registry
+                .validate_command_payload(command_type, 1, &payload)
+                .is_ok());
+        }
+        let missing = json!({"workspace_id": "w1", "worktree_id": "wt1"});
+        assert!(registry
+            .validate_command_payload("worktree.lock.acquire", 1, &missing)
+            .is_err());
+    }
+
+    #[test]
+    fn project_rename_requires_non_empty_name() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({"workspace_id": "w1", "project_id": "p1", "name": "core"});
+        assert!(registry
+            .validate_command_payload("project.rename", 1, &payload)
+            .is_ok());
+        let empty = json!({"workspace_id": "w1", "project_id": "p1", "name": ""});
+        assert!(registry
+            .validate_command_payload("project.rename", 1, &empty)
+            .is_err());
+        let archive = json!({"workspace_id": "w1", "project_id": "p1", "reason": "typo"});
+        assert!(registry
+            .validate_command_payload("project.archive", 1, &archive)
+            .is_ok());
+    }
+
+    #[test]
+    fn board_node_move_requires_full_position() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = json!({
+            "workspace_id": "w1",
+            "node_id": "task:t1",
+            "position": { "x": 120.5, "y": -40 }
+        });
+        assert!(registry
+            .validate_command_payload("board.node.move", 1, &payload)
+            .is_ok());
+        let partial = json!({
+            "workspace_id": "w1",
+            "node_id": "task:t1",
+            "position": { "x": 120.5 }
+        });
+        assert!(registry
+            .validate_command_payload("board.node.move", 1, &partial)
+            .is_err());
+    }
+
+    #[test]
+    fn message_send_accepts_core_and_extension_types() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let payload = |message_type: &str| {
+            json!({
+                "workspace_id": "w1",
+                "from_session_id": "s1",
+                "scope": "PARENT",
+                "type": message_type,
+                "body": {"text": "done"}
+            })
+        };
+        for message_type in ["status_capsule", "x.company.custom_event"] {
+            assert!(registry
+                .validate_command_payload("message.send", 1, &payload(message_type))
+                .is_ok());
+        }
+        for message_type in ["gossip", "x.company"] {
+            assert!(registry
+                .validate_command_payload("message.send", 1, &payload(message_type))
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn event_filter_matches_subject_and_type_prefix() {
+        let event: EventEnvelope = serde_json::from_value(json!({
+            "event_id": "e1",
+            "event_type": "message.delivered",
+            "timestamp": "2026-01-01T00:00:00Z",
+            "actor": {"kind": "agent", "id": "a1"},
+            "workspace_id": "w1",
+            "subject": {"kind": "session", "id": "s2"},
+            "payload": {},
+            "schema_version": 1,
+            "seq_global": 7,
+            "seq_stream": 2
+        }))
+        .expect("event");
+        assert!(EventFilter::default().matches(&event));
+        let filter = EventFilter {
+            subject_kind: Some("session".to_string()),
+            subject_id: Some("s2".to_string()),
+            event_type: Some("message.*".to_string()),
+        };
+        assert!(filter.matches(&event));
+        let other = EventFilter {
+            subject_id: Some("s1".to_string()),
+            ..EventFilter::default()
+        };
+        assert!(!other.matches(&event));
+        let exact = EventFilter {
+            event_type: Some("message.sent".to_string()),
+            ..EventFilter::default()
+        };
+        assert!(!exact.matches(&event));
+    }
+
+    #[test]
+    fn hook_chain_document_checks_action_shape() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let chain = |action: serde_json::Value| {
+            json!({
+                "api_version": 1,
+                "hooks": [{
+                    "id": "org.redact",
+                    "version": "1.0.0",
+                    "phases": ["pre_send"],
+                    "action": action
+                }]
+            })
+        };
+        let redact = json!({"kind": "redact", "pointer": "/body", "needles": ["secret"]});
+        assert!(registry
+            .validate_document("hook_chain", 1, &chain(redact))
+            .is_ok());
+        let missing_pointer = json!({"kind": "clamp", "allow": []});
+        assert!(registry
+            .validate_document("hook_chain", 1, &chain(missing_pointer))
+            .is_err());
+        let unknown_kind = json!({"kind": "shell", "reason": "x"});
+        assert!(registry
+            .validate_document("hook_chain", 1, &chain(unknown_kind))
+            .is_err());
+    }
+
+    #[test]
+    fn tool_registry_refuses_loose_or_inconsistent_tools() {
+        let mut registry = SchemaRegistry::new().expect("registry");
+        let tool = json!({
+            "id": "acme.add",
+            "version": "1.0.0",
+            "title": "Add",
+            "description": "Adds two integers.",
+            "side_effect": "pure",
+            "input_schema": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["a", "b"],
+                "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}}
+            },
+            "output_schema": {
+                "type": "object",
+                "required": ["sum"],
+                "properties": {"sum": {"type": "integer"}}
+            },
+            "examples": [{"description": "small", "input": {"a": 1, "b": 2}, "output": {"sum": 3}}]
+        });
+        let document = json!({"api_version": 1, "version": 1, "tools": [tool.clone()]});
+        assert!(registry
+            .validate_document("tool_registry", 1, &document)
+            .is_ok());
+        let tools = |tool: serde_json::Value| ToolRegistry {
+            api_version: 1,
+            version: 1,
+            tools: vec![serde_json::from_value(tool).expect("tool")],
+        };
+
+        let mut loose = tool.clone();
+        loose["input_schema"]["additionalProperties"] = json!(true);
+        assert!(registry.register_tools(&tools(loose)).is_err());
+        let mut bad_example = tool.clone();
+        bad_example["examples"][0]["output"] = json!({"sum": "3"});
+        assert!(registry.register_tools(&tools(bad_example)).is_err());
+        let mut bare = tool.clone();
+        bare["id"] = json!("add");
+        assert!(registry.register_tools(&tools(bare)).is_err());
+        assert!(registry.tools().is_empty());
+
+        registry.register_tools(&tools(tool)).expect("register");
+        assert!(registry
+            .validate_tool_input("acme.add", &json!({"a": 1, "b": 2}))
+            .is_ok());
+        assert!(registry
+            .validate_tool_input("acme.add", &json!({"a": 1, "b": 2, "c": 3}))
+            .is_err());
+        assert!(registry
+            .validate_tool_output("acme.add", &json!({"total": 3}))
+            .is_err());
+        assert!(registry
+            .validate_tool_input("acme.sub", &json!({}))
+            .is_err());
+    }
+
+    #[test]
+    fn tool_complete_requires_exactly_one_outcome() {
+        let registry = SchemaRegistry::new().expect("registry");
+        let base = json!({"workspace_id": "w1", "request_id": "r1"});
+        assert!(registry
+            .validate_command_payload("tool.complete", 1, &base)
+            .is_err());
+        let mut output = base.clone();
+        output["output"] = json!({"sum": 3});
+        assert!(registry
+            .validate_command_payload("tool.complete", 1, &output)
+            .is_ok());
+        let mut both = output;
+        both["error"] = json!({"code": "timeout", "message": "took too long"});
+        assert!(registry
+            .validate_command_payload("tool.complete", 1, &both)
+            .is_err());
+    }
+
     #[test]
     fn error_response_serializes_stably() {
         let error = ErrorResponse {
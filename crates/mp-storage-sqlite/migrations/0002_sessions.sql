@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS proj_sessions (
+  session_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  project_id TEXT,
+  parent_session_id TEXT,
+  root_session_id TEXT NOT NULL,
+  lineage_path TEXT NOT NULL,
+  depth INTEGER NOT NULL,
+  forked_from_session_id TEXT,
+  fork_mode TEXT,
+  label TEXT,
+  created_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_sessions_root
+  ON proj_sessions (workspace_id, root_session_id, lineage_path);
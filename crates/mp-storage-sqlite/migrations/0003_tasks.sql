@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS proj_tasks (
+  task_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  project_id TEXT,
+  session_id TEXT,
+  title TEXT NOT NULL,
+  state TEXT NOT NULL,
+  deadline_at TEXT,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_tasks_workspace_state
+  ON proj_tasks (workspace_id, state);
@@ -0,0 +1,5 @@
+ALTER TABLE proj_tasks ADD COLUMN attempt INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE proj_tasks ADD COLUMN retry_json TEXT;
+ALTER TABLE proj_tasks ADD COLUMN timeout_ms INTEGER;
+ALTER TABLE proj_tasks ADD COLUMN attempt_started_at TEXT;
+ALTER TABLE proj_tasks ADD COLUMN retry_at TEXT;
@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS proj_pipeline_templates (
+  template_id TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  workspace_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  stages_json TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (template_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_pipeline_templates_name
+  ON proj_pipeline_templates (workspace_id, name);
+
+CREATE TABLE IF NOT EXISTS proj_pipeline_bindings (
+  project_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  template_id TEXT NOT NULL,
+  template_version INTEGER NOT NULL,
+  bound_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+ALTER TABLE proj_tasks ADD COLUMN current_stage TEXT;
@@ -0,0 +1,31 @@
+CREATE TABLE IF NOT EXISTS proj_gates (
+  gate_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  command_type TEXT NOT NULL,
+  subject_kind TEXT NOT NULL,
+  subject_id TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  required_approvals INTEGER NOT NULL,
+  delay_ms INTEGER,
+  description TEXT,
+  status TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_gates_command
+  ON proj_gates (workspace_id, command_type);
+
+CREATE TABLE IF NOT EXISTS proj_gate_decisions (
+  gate_id TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  approved INTEGER NOT NULL,
+  approver_kind TEXT NOT NULL,
+  approver_id TEXT NOT NULL,
+  approver_label TEXT,
+  reason TEXT,
+  evidence_json TEXT NOT NULL,
+  decided_at TEXT NOT NULL,
+  PRIMARY KEY (gate_id, seq_global)
+);
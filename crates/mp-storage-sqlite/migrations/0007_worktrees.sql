@@ -0,0 +1,24 @@
+CREATE TABLE IF NOT EXISTS proj_worktrees (
+  worktree_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  project_id TEXT,
+  path TEXT NOT NULL,
+  branch TEXT,
+  repo TEXT,
+  lock_session_id TEXT,
+  locked_at TEXT,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_worktrees_workspace
+  ON proj_worktrees (workspace_id, seq_global);
+
+CREATE TABLE IF NOT EXISTS proj_worktree_sessions (
+  worktree_id TEXT NOT NULL,
+  session_id TEXT NOT NULL,
+  attached_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (worktree_id, session_id)
+);
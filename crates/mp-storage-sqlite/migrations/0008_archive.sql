@@ -0,0 +1,2 @@
+ALTER TABLE proj_workspaces ADD COLUMN archived_at TEXT;
+ALTER TABLE proj_projects ADD COLUMN archived_at TEXT;
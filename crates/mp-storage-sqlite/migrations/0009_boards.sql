@@ -0,0 +1,30 @@
+CREATE TABLE IF NOT EXISTS proj_board_nodes (
+  node_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  project_id TEXT,
+  kind TEXT NOT NULL,
+  subject_id TEXT NOT NULL,
+  label TEXT,
+  body TEXT,
+  position_x REAL,
+  position_y REAL,
+  group_node_id TEXT,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_board_nodes_workspace
+  ON proj_board_nodes (workspace_id, created_at);
+
+CREATE TABLE IF NOT EXISTS proj_board_edges (
+  kind TEXT NOT NULL,
+  from_node_id TEXT NOT NULL,
+  to_node_id TEXT NOT NULL,
+  workspace_id TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (kind, from_node_id, to_node_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_board_edges_workspace
+  ON proj_board_edges (workspace_id, seq_global);
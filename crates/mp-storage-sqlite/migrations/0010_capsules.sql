@@ -0,0 +1,15 @@
+CREATE TABLE IF NOT EXISTS proj_capsules (
+  workspace_id TEXT NOT NULL,
+  subject_kind TEXT NOT NULL,
+  subject_id TEXT NOT NULL,
+  kind TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  content TEXT NOT NULL,
+  chars INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (subject_kind, subject_id, kind, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_capsules_workspace
+  ON proj_capsules (workspace_id, seq_global);
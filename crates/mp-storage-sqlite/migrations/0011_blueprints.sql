@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS proj_blueprints (
+  workspace_id TEXT NOT NULL,
+  blueprint_id TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  name TEXT NOT NULL,
+  path TEXT NOT NULL,
+  content_hash TEXT NOT NULL,
+  blueprint_json TEXT NOT NULL,
+  created_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (workspace_id, blueprint_id, version)
+);
@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS proj_tool_calls (
+  request_id TEXT PRIMARY KEY,
+  workspace_id TEXT NOT NULL,
+  tool_id TEXT NOT NULL,
+  tool_version TEXT NOT NULL,
+  side_effect TEXT NOT NULL,
+  input_json TEXT NOT NULL,
+  session_id TEXT,
+  status TEXT NOT NULL,
+  output_json TEXT,
+  error_json TEXT,
+  requested_at TEXT NOT NULL,
+  completed_at TEXT,
+  seq_global INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_tool_calls_workspace
+  ON proj_tool_calls (workspace_id, seq_global);
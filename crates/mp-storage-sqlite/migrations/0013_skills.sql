@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS proj_skills (
+  workspace_id TEXT NOT NULL,
+  skill_id TEXT NOT NULL,
+  revision INTEGER NOT NULL,
+  scope TEXT NOT NULL,
+  path TEXT NOT NULL,
+  content_hash TEXT NOT NULL,
+  trust TEXT NOT NULL,
+  signature_json TEXT NOT NULL,
+  frontmatter_json TEXT NOT NULL,
+  components_json TEXT NOT NULL,
+  approved_hash TEXT,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (workspace_id, skill_id)
+);
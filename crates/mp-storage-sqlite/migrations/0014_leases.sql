@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS proj_leases (
+  workspace_id TEXT NOT NULL,
+  lease_id TEXT NOT NULL,
+  holder_json TEXT NOT NULL,
+  scope_json TEXT NOT NULL,
+  reason TEXT NOT NULL,
+  minted_by TEXT NOT NULL,
+  expires_at TEXT NOT NULL,
+  status TEXT NOT NULL,
+  use_count INTEGER NOT NULL DEFAULT 0,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (workspace_id, lease_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_proj_leases_status_expiry
+  ON proj_leases (workspace_id, status, expires_at);
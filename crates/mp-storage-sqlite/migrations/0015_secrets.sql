@@ -0,0 +1,35 @@
+CREATE TABLE IF NOT EXISTS vaults (
+  workspace_id TEXT PRIMARY KEY,
+  kdf_json TEXT NOT NULL,
+  salt BLOB NOT NULL,
+  check_value BLOB NOT NULL,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS secret_versions (
+  workspace_id TEXT NOT NULL,
+  secret_id TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  wrapped_key BLOB NOT NULL,
+  ciphertext BLOB NOT NULL,
+  PRIMARY KEY (workspace_id, secret_id, version)
+);
+
+CREATE TABLE IF NOT EXISTS proj_secrets (
+  workspace_id TEXT NOT NULL,
+  secret_id TEXT NOT NULL,
+  name TEXT NOT NULL,
+  scope TEXT NOT NULL,
+  scope_id TEXT NOT NULL,
+  version INTEGER NOT NULL,
+  redacted TEXT NOT NULL,
+  length INTEGER NOT NULL,
+  created_at TEXT NOT NULL,
+  updated_at TEXT NOT NULL,
+  seq_global INTEGER NOT NULL,
+  PRIMARY KEY (workspace_id, secret_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_proj_secrets_owner_name
+  ON proj_secrets (workspace_id, name, scope, scope_id);
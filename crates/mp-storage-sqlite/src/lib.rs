@@ -1,16 +1,47 @@
-use mp_kernel::{now_rfc3339, Actor, ProjectListEntry, Subject, WorkspaceListEntry};
+use mp_kernel::{
+    artifact_hash_hex, now_rfc3339, Actor, Approver, ApproverKind, ArtifactEntry, BlueprintEntry,
+    BoardEdge, BoardEdgeKind, BoardNode, BoardPosition, BoardSnapshot, CapsuleEntry, CapsuleKind,
+    ForkMode, GateDecisionEntry, GateEntry, GateKind, GateScope, GateStatus, LeaseEntry,
+    LeaseStatus, PipelineBindingEntry, PipelineTemplateEntry, ProjectListEntry, SecretEntry,
+    SecretOwner, SecretScope, SessionListEntry, SideEffect, SkillEntry, SkillScope, SkillTrust,
+    Subject, TaskListEntry, TaskState, ToolCallEntry, ToolCallStatus, ToolResultPayload,
+    WorkspaceListEntry, WorktreeEntry, WorktreeLock, EVENT_SECRET_CREATED, EVENT_SECRET_ROTATED,
+};
 use mp_projections::{apply_event, rebuild_projections, ProjectionError, ProjectionWriter};
 use mp_protocol::EventEnvelope;
-use mp_storage::{AppendResult, CommandMeta, EventStore, NewEvent, ProjectionReader, StoreError};
+use mp_storage::{
+    AppendResult, ArtifactStore, CommandMeta, EventStore, NewEvent, ProjectionReader, SealSecret,
+    SecretCiphertext, SecretStore, StoreError, StoredArtifact, VaultRecord, VaultWrite,
+};
 use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::path::Path;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
-const MIGRATION_0001: &str = include_str!("../migrations/0001_init.sql");
+/// Applied in order; the 1-based index is the version recorded in `schema_migrations`.
+const MIGRATIONS: [&str; 15] = [
+    include_str!("../migrations/0001_init.sql"),
+    include_str!("../migrations/0002_sessions.sql"),
+    include_str!("../migrations/0003_tasks.sql"),
+    include_str!("../migrations/0004_task_retries.sql"),
+    include_str!("../migrations/0005_pipelines.sql"),
+    include_str!("../migrations/0006_gates.sql"),
+    include_str!("../migrations/0007_worktrees.sql"),
+    include_str!("../migrations/0008_archive.sql"),
+    include_str!("../migrations/0009_boards.sql"),
+    include_str!("../migrations/0010_capsules.sql"),
+    include_str!("../migrations/0011_blueprints.sql"),
+    include_str!("../migrations/0012_tool_calls.sql"),
+    include_str!("../migrations/0013_skills.sql"),
+    include_str!("../migrations/0014_leases.sql"),
+    include_str!("../migrations/0015_secrets.sql"),
+];
 
 pub struct SqliteStore {
     conn: Connection,
+    artifact_dir: PathBuf,
 }
 
 impl SqliteStore {
@@ -21,124 +52,71 @@ impl SqliteStore {
         }
         let conn = Connection::open(path)
             .map_err(|err| StoreError::Internal(format!("failed to open db: {err}")))?;
-        let store = Self { conn };
+        let artifact_dir = path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join("artifacts");
+        let store = Self { conn, artifact_dir };
         store.migrate()?;
         Ok(store)
     }
 
-    pub fn rebuild_projections(&self) -> Result<(), StoreError> {
-        let mut stmt = self
-            .conn
-            .prepare(
-                "SELECT workspace_id, seq_global, stream_id, seq_stream, event_id, event_type, ts, actor_json, project_id, subject_kind, subject_id, schema_version, payload_json, trace_id
-                 FROM events
-                 ORDER BY workspace_id, seq_global",
-            )
-            .map_err(map_sql_err)?;
-        let events_iter = stmt.query_map([], row_to_event).map_err(map_sql_err)?;
-        let mut events = Vec::new();
-        for event in events_iter {
-            events.push(event.map_err(map_sql_err)?);
-        }
-
-        let writer = SqliteProjectionWriterConn { conn: &self.conn };
-        rebuild_projections(&writer, events).map_err(map_proj_err)?;
-        Ok(())
-    }
-
-    fn migrate(&self) -> Result<(), StoreError> {
-        self.conn
-            .execute_batch(MIGRATION_0001)
-            .map_err(map_sql_err)?;
-        Ok(())
+    /// Stores artifact blobs under `dir` instead of next to the database.
+    pub fn with_artifact_dir(mut self, dir: PathBuf) -> Self {
+        self.artifact_dir = dir;
+        self
     }
 
-    fn current_seq_in_tx(tx: &Transaction<'_>, workspace_id: &str) -> Result<i64, StoreError> {
-        let seq: Option<i64> = tx
-            .query_row(
-                "SELECT MAX(seq_global) FROM events WHERE workspace_id = ?1",
-                params![workspace_id],
-                |row| row.get::<_, Option<i64>>(0),
-            )
-            .map_err(map_sql_err)?;
-        Ok(seq.unwrap_or(0))
+    /// Blobs are sharded by the first two hex digits: `blake3/ab/<hex>`.
+    fn blob_path(&self, hex: &str) -> PathBuf {
+        self.artifact_dir.join("blake3").join(&hex[..2]).join(hex)
     }
 
-    fn current_stream_seq_in_tx(
-        tx: &Transaction<'_>,
-        workspace_id: &str,
-        stream_id: &str,
-    ) -> Result<i64, StoreError> {
-        let seq: Option<i64> = tx
-            .query_row(
-                "SELECT MAX(seq_stream) FROM events WHERE workspace_id = ?1 AND stream_id = ?2",
-                params![workspace_id, stream_id],
-                |row| row.get::<_, Option<i64>>(0),
-            )
-            .map_err(map_sql_err)?;
-        Ok(seq.unwrap_or(0))
+    fn stage_artifact(
+        &self,
+        content: &mut dyn Read,
+        staged_path: &Path,
+    ) -> Result<String, StoreError> {
+        let mut file = File::create(staged_path).map_err(map_artifact_io_err)?;
+        let mut hasher = blake3::Hasher::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = content.read(&mut buffer).map_err(map_artifact_io_err)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            file.write_all(&buffer[..read])
+                .map_err(map_artifact_io_err)?;
+        }
+        file.sync_all().map_err(map_artifact_io_err)?;
+        Ok(hasher.finalize().to_hex().to_string())
     }
 
-    fn lookup_idempotency(
+    /// Events an earlier command with this idempotency key recorded, if there was one. Lets
+    /// callers skip planning a retried command, not just its append.
+    pub fn replay_idempotent(
         &self,
         idempotency_key: &str,
         command_type: &str,
-    ) -> Result<Option<IdempotencyRecord>, StoreError> {
-        let record = self
-            .conn
-            .query_row(
-                "SELECT workspace_id, idempotency_key, command_type, trace_id, first_seq_global, last_seq_global, status_code
-                 FROM idempotency_keys
-                 WHERE idempotency_key = ?1 AND command_type = ?2",
-                params![idempotency_key, command_type],
-                |row| {
-                    Ok(IdempotencyRecord {
-                        workspace_id: row.get(0)?,
-                        idempotency_key: row.get(1)?,
-                        command_type: row.get(2)?,
-                        trace_id: row.get(3)?,
-                        first_seq_global: row.get(4)?,
-                        last_seq_global: row.get(5)?,
-                        status_code: row.get(6)?,
-                    })
-                },
-            )
-            .optional()
-            .map_err(map_sql_err)?;
-        Ok(record)
-    }
-
-    fn load_event_range(
-        &self,
-        workspace_id: &str,
-        first: i64,
-        last: i64,
-    ) -> Result<Vec<EventEnvelope>, StoreError> {
-        let mut stmt = self
-            .conn
-            .prepare(
-                "SELECT workspace_id, seq_global, stream_id, seq_stream, event_id, event_type, ts, actor_json, project_id, subject_kind, subject_id, schema_version, payload_json, trace_id
-                 FROM events
-                 WHERE workspace_id = ?1 AND seq_global BETWEEN ?2 AND ?3
-                 ORDER BY seq_global",
-            )
-            .map_err(map_sql_err)?;
-        let events_iter = stmt
-            .query_map(params![workspace_id, first, last], row_to_event)
-            .map_err(map_sql_err)?;
-        let mut events = Vec::new();
-        for event in events_iter {
-            events.push(event.map_err(map_sql_err)?);
-        }
-        Ok(events)
+    ) -> Result<Option<Vec<EventEnvelope>>, StoreError> {
+        let Some(existing) = self.lookup_idempotency(idempotency_key, command_type)? else {
+            return Ok(None);
+        };
+        self.load_event_range(
+            &existing.workspace_id,
+            existing.first_seq_global,
+            existing.last_seq_global,
+        )
+        .map(Some)
     }
-}
 
-impl EventStore for SqliteStore {
-    fn append(
+    /// Appends `events` in one transaction, storing `write` in it too.
+    fn append_batch(
         &mut self,
         meta: &CommandMeta,
         events: Vec<NewEvent>,
+        write: Option<VaultWrite<'_>>,
     ) -> Result<AppendResult, StoreError> {
         if events.is_empty() {
             return Ok(AppendResult {
@@ -146,14 +124,19 @@ impl EventStore for SqliteStore {
                 idempotent: false,
             });
         }
+        // Sequence numbers and the idempotency record belong to one workspace.
+        let workspace_id = events[0].workspace_id.clone();
+        if events
+            .iter()
+            .any(|event| event.workspace_id != workspace_id)
+        {
+            return Err(StoreError::Invalid(
+                "a batch must not mix workspaces".to_string(),
+            ));
+        }
 
         if let Some(key) = &meta.idempotency_key {
-            if let Some(existing) = self.lookup_idempotency(key, &meta.command_type)? {
-                let events = self.load_event_range(
-                    &existing.workspace_id,
-                    existing.first_seq_global,
-                    existing.last_seq_global,
-                )?;
+            if let Some(events) = self.replay_idempotent(key, &meta.command_type)? {
                 return Ok(AppendResult {
                     events,
                     idempotent: true,
@@ -161,15 +144,22 @@ impl EventStore for SqliteStore {
             }
         }
 
-        let workspace_id = events[0].workspace_id.clone();
         let tx = self.conn.transaction().map_err(map_sql_err)?;
+        match write {
+            Some(VaultWrite::Create(vault)) => create_vault(&tx, vault)?,
+            Some(VaultWrite::Rekey(vault, rewrapped)) => rekey_vault(&tx, vault, rewrapped)?,
+            Some(VaultWrite::SealVersion(_)) | None => {}
+        }
         let mut seq_global = Self::current_seq_in_tx(&tx, &workspace_id)?;
         let mut stream_seq_cache: HashMap<String, i64> = HashMap::new();
         let mut appended = Vec::new();
         let mut first_seq = None;
         let mut last_seq = None;
 
-        for event in events {
+        for mut event in events {
+            if let Some(VaultWrite::SealVersion(seal)) = &write {
+                seal_secret_version(&tx, &mut event, seal)?;
+            }
             let stream_id = event
                 .stream_id
                 .clone()
@@ -263,6 +253,242 @@ impl EventStore for SqliteStore {
         })
     }
 
+    pub fn rebuild_projections(&self) -> Result<(), StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, seq_global, stream_id, seq_stream, event_id, event_type, ts, actor_json, project_id, subject_kind, subject_id, schema_version, payload_json, trace_id
+                 FROM events
+                 ORDER BY workspace_id, seq_global",
+            )
+            .map_err(map_sql_err)?;
+        let events_iter = stmt.query_map([], row_to_event).map_err(map_sql_err)?;
+        let mut events = Vec::new();
+        for event in events_iter {
+            events.push(event.map_err(map_sql_err)?);
+        }
+
+        let writer = SqliteProjectionWriterConn { conn: &self.conn };
+        rebuild_projections(&writer, events).map_err(map_proj_err)?;
+        Ok(())
+    }
+
+    fn migrate(&self) -> Result<(), StoreError> {
+        // 0001 creates `schema_migrations` itself and is safe to re-run, so it always applies.
+        self.conn
+            .execute_batch(MIGRATIONS[0])
+            .map_err(map_sql_err)?;
+        for (index, migration) in MIGRATIONS.iter().enumerate() {
+            let version = index as i64 + 1;
+            let applied: bool = self
+                .conn
+                .query_row(
+                    "SELECT EXISTS(SELECT 1 FROM schema_migrations WHERE version = ?1)",
+                    params![version],
+                    |row| row.get(0),
+                )
+                .map_err(map_sql_err)?;
+            if applied {
+                continue;
+            }
+            if index > 0 {
+                self.conn.execute_batch(migration).map_err(map_sql_err)?;
+            }
+            self.conn
+                .execute(
+                    "INSERT INTO schema_migrations (version, applied_at) VALUES (?1, ?2)",
+                    params![version, now_rfc3339()],
+                )
+                .map_err(map_sql_err)?;
+        }
+        Ok(())
+    }
+
+    fn current_seq_in_tx(tx: &Transaction<'_>, workspace_id: &str) -> Result<i64, StoreError> {
+        let seq: Option<i64> = tx
+            .query_row(
+                "SELECT MAX(seq_global) FROM events WHERE workspace_id = ?1",
+                params![workspace_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map_err(map_sql_err)?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    fn current_stream_seq_in_tx(
+        tx: &Transaction<'_>,
+        workspace_id: &str,
+        stream_id: &str,
+    ) -> Result<i64, StoreError> {
+        let seq: Option<i64> = tx
+            .query_row(
+                "SELECT MAX(seq_stream) FROM events WHERE workspace_id = ?1 AND stream_id = ?2",
+                params![workspace_id, stream_id],
+                |row| row.get::<_, Option<i64>>(0),
+            )
+            .map_err(map_sql_err)?;
+        Ok(seq.unwrap_or(0))
+    }
+
+    fn lookup_idempotency(
+        &self,
+        idempotency_key: &str,
+        command_type: &str,
+    ) -> Result<Option<IdempotencyRecord>, StoreError> {
+        let record = self
+            .conn
+            .query_row(
+                "SELECT workspace_id, idempotency_key, command_type, trace_id, first_seq_global, last_seq_global, status_code
+                 FROM idempotency_keys
+                 WHERE idempotency_key = ?1 AND command_type = ?2",
+                params![idempotency_key, command_type],
+                |row| {
+                    Ok(IdempotencyRecord {
+                        workspace_id: row.get(0)?,
+                        idempotency_key: row.get(1)?,
+                        command_type: row.get(2)?,
+                        trace_id: row.get(3)?,
+                        first_seq_global: row.get(4)?,
+                        last_seq_global: row.get(5)?,
+                        status_code: row.get(6)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(map_sql_err)?;
+        Ok(record)
+    }
+
+    fn load_event_range(
+        &self,
+        workspace_id: &str,
+        first: i64,
+        last: i64,
+    ) -> Result<Vec<EventEnvelope>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, seq_global, stream_id, seq_stream, event_id, event_type, ts, actor_json, project_id, subject_kind, subject_id, schema_version, payload_json, trace_id
+                 FROM events
+                 WHERE workspace_id = ?1 AND seq_global BETWEEN ?2 AND ?3
+                 ORDER BY seq_global",
+            )
+            .map_err(map_sql_err)?;
+        let events_iter = stmt
+            .query_map(params![workspace_id, first, last], row_to_event)
+            .map_err(map_sql_err)?;
+        let mut events = Vec::new();
+        for event in events_iter {
+            events.push(event.map_err(map_sql_err)?);
+        }
+        Ok(events)
+    }
+}
+
+impl ArtifactStore for SqliteStore {
+    fn put_artifact(
+        &self,
+        content: &mut dyn Read,
+        mime_type: Option<&str>,
+        metadata: &Value,
+    ) -> Result<StoredArtifact, StoreError> {
+        let staged_path = self.artifact_staging_path()?;
+        let hex = match self.stage_artifact(content, &staged_path) {
+            Ok(hex) => hex,
+            Err(err) => {
+                let _ = std::fs::remove_file(&staged_path);
+                return Err(err);
+            }
+        };
+        let hash = format!("{}{hex}", mp_kernel::ARTIFACT_HASH_PREFIX);
+        self.commit_artifact(&staged_path, &hash, mime_type, metadata)
+    }
+
+    fn artifact_staging_path(&self) -> Result<PathBuf, StoreError> {
+        let staging_dir = self.artifact_dir.join("staging");
+        std::fs::create_dir_all(&staging_dir).map_err(map_artifact_io_err)?;
+        Ok(staging_dir.join(format!("{}.part", mp_kernel::new_uuid())))
+    }
+
+    fn commit_artifact(
+        &self,
+        staged_path: &Path,
+        hash: &str,
+        mime_type: Option<&str>,
+        metadata: &Value,
+    ) -> Result<StoredArtifact, StoreError> {
+        let Some(hex) = artifact_hash_hex(hash) else {
+            let _ = std::fs::remove_file(staged_path);
+            return Err(StoreError::Invalid(format!(
+                "malformed artifact hash {hash}"
+            )));
+        };
+        let size_bytes = std::fs::metadata(staged_path)
+            .map_err(map_artifact_io_err)?
+            .len();
+        let blob_path = self.blob_path(hex);
+        let deduplicated = blob_path.exists();
+        if deduplicated {
+            let _ = std::fs::remove_file(staged_path);
+        } else {
+            if let Some(parent) = blob_path.parent() {
+                std::fs::create_dir_all(parent).map_err(map_artifact_io_err)?;
+            }
+            std::fs::rename(staged_path, &blob_path).map_err(map_artifact_io_err)?;
+        }
+
+        let metadata_json = serde_json::to_string(metadata)
+            .map_err(|err| StoreError::Invalid(format!("artifact metadata: {err}")))?;
+        self.conn
+            .execute(
+                "INSERT INTO artifacts (hash, size_bytes, mime_type, created_at, metadata_json)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(hash) DO NOTHING",
+                params![hash, size_bytes, mime_type, now_rfc3339(), metadata_json],
+            )
+            .map_err(map_sql_err)?;
+        let entry = self
+            .get_artifact(hash)?
+            .ok_or_else(|| StoreError::Internal(format!("artifact {hash} missing after put")))?;
+        Ok(StoredArtifact {
+            entry,
+            deduplicated,
+        })
+    }
+
+    fn get_artifact(&self, hash: &str) -> Result<Option<ArtifactEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT hash, size_bytes, mime_type, created_at, metadata_json
+                 FROM artifacts
+                 WHERE hash = ?1",
+                params![hash],
+                row_to_artifact,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn artifact_path(&self, hash: &str) -> Result<Option<PathBuf>, StoreError> {
+        let hex = artifact_hash_hex(hash)
+            .ok_or_else(|| StoreError::Invalid(format!("malformed artifact hash {hash}")))?;
+        if self.get_artifact(hash)?.is_none() {
+            return Ok(None);
+        }
+        let blob_path = self.blob_path(hex);
+        Ok(blob_path.exists().then_some(blob_path))
+    }
+}
+
+impl EventStore for SqliteStore {
+    fn append(
+        &mut self,
+        meta: &CommandMeta,
+        events: Vec<NewEvent>,
+    ) -> Result<AppendResult, StoreError> {
+        self.append_batch(meta, events, None)
+    }
+
     fn read_from(
         &self,
         workspace_id: &str,
@@ -304,23 +530,28 @@ impl EventStore for SqliteStore {
 }
 
 impl ProjectionReader for SqliteStore {
-    fn list_workspaces(&self) -> Result<Vec<WorkspaceListEntry>, StoreError> {
+    fn list_workspaces(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<WorkspaceListEntry>, StoreError> {
         let mut stmt = self
             .conn
             .prepare(
-                "SELECT workspace_id, name, root_path, created_at, seq_global
+                "SELECT workspace_id, name, root_path, created_at, archived_at, seq_global
                  FROM proj_workspaces
+                 WHERE ?1 OR archived_at IS NULL
                  ORDER BY name",
             )
             .map_err(map_sql_err)?;
         let rows = stmt
-            .query_map([], |row| {
+            .query_map(params![include_archived], |row| {
                 Ok(WorkspaceListEntry {
                     workspace_id: row.get(0)?,
                     name: row.get(1)?,
                     root_path: row.get(2)?,
                     created_at: row.get(3)?,
-                    seq_global: row.get(4)?,
+                    archived_at: row.get(4)?,
+                    seq_global: row.get(5)?,
                 })
             })
             .map_err(map_sql_err)?;
@@ -331,44 +562,1047 @@ impl ProjectionReader for SqliteStore {
         Ok(workspaces)
     }
 
-    fn list_projects(&self, workspace_id: &str) -> Result<Vec<ProjectListEntry>, StoreError> {
-        let mut stmt = self
+    fn list_projects(
+        &self,
+        workspace_id: &str,
+        include_archived: bool,
+    ) -> Result<Vec<ProjectListEntry>, StoreError> {
+        let mut stmt = self
             .conn
             .prepare(
-                "SELECT project_id, workspace_id, name, created_at, seq_global
+                "SELECT project_id, workspace_id, name, created_at, archived_at, seq_global
                  FROM proj_projects
-                 WHERE workspace_id = ?1
+                 WHERE workspace_id = ?1 AND (?2 OR archived_at IS NULL)
                  ORDER BY name",
             )
-            .map_err(map_sql_err)?;
-        let rows = stmt
-            .query_map(params![workspace_id], |row| {
-                Ok(ProjectListEntry {
-                    project_id: row.get(0)?,
-                    workspace_id: row.get(1)?,
-                    name: row.get(2)?,
-                    created_at: row.get(3)?,
-                    seq_global: row.get(4)?,
-                })
-            })
-            .map_err(map_sql_err)?;
-        let mut projects = Vec::new();
-        for row in rows {
-            projects.push(row.map_err(map_sql_err)?);
-        }
-        Ok(projects)
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id, include_archived], |row| {
+                Ok(ProjectListEntry {
+                    project_id: row.get(0)?,
+                    workspace_id: row.get(1)?,
+                    name: row.get(2)?,
+                    created_at: row.get(3)?,
+                    archived_at: row.get(4)?,
+                    seq_global: row.get(5)?,
+                })
+            })
+            .map_err(map_sql_err)?;
+        let mut projects = Vec::new();
+        for row in rows {
+            projects.push(row.map_err(map_sql_err)?);
+        }
+        Ok(projects)
+    }
+
+    fn list_sessions(
+        &self,
+        workspace_id: &str,
+        root_session_id: Option<&str>,
+    ) -> Result<Vec<SessionListEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT session_id, workspace_id, project_id, parent_session_id, root_session_id, lineage_path, depth, forked_from_session_id, fork_mode, label, created_at, seq_global
+                 FROM proj_sessions
+                 WHERE workspace_id = ?1 AND (?2 IS NULL OR root_session_id = ?2)
+                 ORDER BY lineage_path",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id, root_session_id], row_to_session)
+            .map_err(map_sql_err)?;
+        let mut sessions = Vec::new();
+        for row in rows {
+            sessions.push(row.map_err(map_sql_err)?);
+        }
+        Ok(sessions)
+    }
+
+    fn get_session(
+        &self,
+        workspace_id: &str,
+        session_id: &str,
+    ) -> Result<Option<SessionListEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT session_id, workspace_id, project_id, parent_session_id, root_session_id, lineage_path, depth, forked_from_session_id, fork_mode, label, created_at, seq_global
+                 FROM proj_sessions
+                 WHERE workspace_id = ?1 AND session_id = ?2",
+                params![workspace_id, session_id],
+                row_to_session,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_tasks(
+        &self,
+        workspace_id: &str,
+        project_id: Option<&str>,
+        state: Option<TaskState>,
+    ) -> Result<Vec<TaskListEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT task_id, workspace_id, project_id, session_id, title, state, deadline_at, attempt, retry_json, timeout_ms, attempt_started_at, retry_at, created_at, updated_at, seq_global, current_stage
+                 FROM proj_tasks
+                 WHERE workspace_id = ?1 AND (?2 IS NULL OR project_id = ?2) AND (?3 IS NULL OR state = ?3)
+                 ORDER BY created_at, task_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(
+                params![workspace_id, project_id, state.map(|s| s.as_str())],
+                row_to_task,
+            )
+            .map_err(map_sql_err)?;
+        let mut tasks = Vec::new();
+        for row in rows {
+            tasks.push(row.map_err(map_sql_err)?);
+        }
+        Ok(tasks)
+    }
+
+    fn get_task(
+        &self,
+        workspace_id: &str,
+        task_id: &str,
+    ) -> Result<Option<TaskListEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT task_id, workspace_id, project_id, session_id, title, state, deadline_at, attempt, retry_json, timeout_ms, attempt_started_at, retry_at, created_at, updated_at, seq_global, current_stage
+                 FROM proj_tasks
+                 WHERE workspace_id = ?1 AND task_id = ?2",
+                params![workspace_id, task_id],
+                row_to_task,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_pipeline_templates(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<PipelineTemplateEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT template_id, workspace_id, name, version, stages_json, created_at, seq_global
+                 FROM proj_pipeline_templates
+                 WHERE workspace_id = ?1
+                 ORDER BY name, version",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_pipeline_template)
+            .map_err(map_sql_err)?;
+        let mut templates = Vec::new();
+        for row in rows {
+            templates.push(row.map_err(map_sql_err)?);
+        }
+        Ok(templates)
+    }
+
+    fn get_pipeline_template(
+        &self,
+        workspace_id: &str,
+        template_id: &str,
+        version: Option<u32>,
+    ) -> Result<Option<PipelineTemplateEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT template_id, workspace_id, name, version, stages_json, created_at, seq_global
+                 FROM proj_pipeline_templates
+                 WHERE workspace_id = ?1 AND template_id = ?2 AND (?3 IS NULL OR version = ?3)
+                 ORDER BY version DESC
+                 LIMIT 1",
+                params![workspace_id, template_id, version],
+                row_to_pipeline_template,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn get_pipeline_binding(
+        &self,
+        workspace_id: &str,
+        project_id: &str,
+    ) -> Result<Option<PipelineBindingEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT project_id, workspace_id, template_id, template_version, bound_at, seq_global
+                 FROM proj_pipeline_bindings
+                 WHERE workspace_id = ?1 AND project_id = ?2",
+                params![workspace_id, project_id],
+                |row| {
+                    Ok(PipelineBindingEntry {
+                        project_id: row.get(0)?,
+                        workspace_id: row.get(1)?,
+                        template_id: row.get(2)?,
+                        template_version: row.get(3)?,
+                        bound_at: row.get(4)?,
+                        seq_global: row.get(5)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_gates(
+        &self,
+        workspace_id: &str,
+        command_type: Option<&str>,
+    ) -> Result<Vec<GateEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT gate_id, workspace_id, command_type, subject_kind, subject_id, kind, required_approvals, delay_ms, description, status, created_at, updated_at, seq_global
+                 FROM proj_gates
+                 WHERE workspace_id = ?1 AND (?2 IS NULL OR command_type = ?2)
+                 ORDER BY seq_global",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id, command_type], row_to_gate)
+            .map_err(map_sql_err)?;
+        let mut gates = Vec::new();
+        for row in rows {
+            let mut gate = row.map_err(map_sql_err)?;
+            gate.decisions = load_gate_decisions(&self.conn, &gate.gate_id)?;
+            gates.push(gate);
+        }
+        Ok(gates)
+    }
+
+    fn get_gate(&self, workspace_id: &str, gate_id: &str) -> Result<Option<GateEntry>, StoreError> {
+        let gate = self
+            .conn
+            .query_row(
+                "SELECT gate_id, workspace_id, command_type, subject_kind, subject_id, kind, required_approvals, delay_ms, description, status, created_at, updated_at, seq_global
+                 FROM proj_gates
+                 WHERE workspace_id = ?1 AND gate_id = ?2",
+                params![workspace_id, gate_id],
+                row_to_gate,
+            )
+            .optional()
+            .map_err(map_sql_err)?;
+        let Some(mut gate) = gate else {
+            return Ok(None);
+        };
+        gate.decisions = load_gate_decisions(&self.conn, &gate.gate_id)?;
+        Ok(Some(gate))
+    }
+
+    fn list_worktrees(&self, workspace_id: &str) -> Result<Vec<WorktreeEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT worktree_id, workspace_id, project_id, path, branch, repo, lock_session_id, locked_at, created_at, updated_at, seq_global
+                 FROM proj_worktrees
+                 WHERE workspace_id = ?1
+                 ORDER BY seq_global, worktree_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_worktree)
+            .map_err(map_sql_err)?;
+        let mut worktrees = Vec::new();
+        for row in rows {
+            let mut worktree = row.map_err(map_sql_err)?;
+            worktree.sessions = load_worktree_sessions(&self.conn, &worktree.worktree_id)?;
+            worktrees.push(worktree);
+        }
+        Ok(worktrees)
+    }
+
+    fn get_worktree(
+        &self,
+        workspace_id: &str,
+        worktree_id: &str,
+    ) -> Result<Option<WorktreeEntry>, StoreError> {
+        let worktree = self
+            .conn
+            .query_row(
+                "SELECT worktree_id, workspace_id, project_id, path, branch, repo, lock_session_id, locked_at, created_at, updated_at, seq_global
+                 FROM proj_worktrees
+                 WHERE workspace_id = ?1 AND worktree_id = ?2",
+                params![workspace_id, worktree_id],
+                row_to_worktree,
+            )
+            .optional()
+            .map_err(map_sql_err)?;
+        let Some(mut worktree) = worktree else {
+            return Ok(None);
+        };
+        worktree.sessions = load_worktree_sessions(&self.conn, &worktree.worktree_id)?;
+        Ok(Some(worktree))
+    }
+
+    fn get_board(&self, workspace_id: &str) -> Result<BoardSnapshot, StoreError> {
+        let seq_global = self
+            .conn
+            .query_row(
+                "SELECT last_seq_global FROM proj_meta WHERE workspace_id = ?1",
+                params![workspace_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(map_sql_err)?
+            .unwrap_or(0);
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT node_id, workspace_id, project_id, kind, subject_id, label, body, position_x, position_y, group_node_id, created_at, updated_at, seq_global
+                 FROM proj_board_nodes
+                 WHERE workspace_id = ?1
+                 ORDER BY created_at, node_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_board_node)
+            .map_err(map_sql_err)?;
+        let mut nodes = Vec::new();
+        for row in rows {
+            nodes.push(row.map_err(map_sql_err)?);
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT kind, from_node_id, to_node_id, seq_global
+                 FROM proj_board_edges
+                 WHERE workspace_id = ?1
+                 ORDER BY seq_global, kind, from_node_id, to_node_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_board_edge)
+            .map_err(map_sql_err)?;
+        let mut edges = Vec::new();
+        for row in rows {
+            edges.push(row.map_err(map_sql_err)?);
+        }
+
+        Ok(BoardSnapshot {
+            workspace_id: workspace_id.to_string(),
+            seq_global,
+            nodes,
+            edges,
+        })
+    }
+
+    fn get_board_node(
+        &self,
+        workspace_id: &str,
+        node_id: &str,
+    ) -> Result<Option<BoardNode>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT node_id, workspace_id, project_id, kind, subject_id, label, body, position_x, position_y, group_node_id, created_at, updated_at, seq_global
+                 FROM proj_board_nodes
+                 WHERE workspace_id = ?1 AND node_id = ?2",
+                params![workspace_id, node_id],
+                row_to_board_node,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_capsules(
+        &self,
+        workspace_id: &str,
+        subject: Option<&Subject>,
+        kind: Option<CapsuleKind>,
+        all_versions: bool,
+    ) -> Result<Vec<CapsuleEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT c.workspace_id, c.subject_kind, c.subject_id, c.kind, c.version, c.content, c.chars, c.created_at, c.seq_global
+                 FROM proj_capsules c
+                 WHERE c.workspace_id = ?1
+                   AND (?2 IS NULL OR (c.subject_kind = ?2 AND c.subject_id = ?3))
+                   AND (?4 IS NULL OR c.kind = ?4)
+                   AND (?5 OR c.version = (
+                     SELECT MAX(latest.version) FROM proj_capsules latest
+                     WHERE latest.subject_kind = c.subject_kind
+                       AND latest.subject_id = c.subject_id
+                       AND latest.kind = c.kind))
+                 ORDER BY c.seq_global",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(
+                params![
+                    workspace_id,
+                    subject.map(|subject| subject.kind.as_str()),
+                    subject.map(|subject| subject.id.as_str()),
+                    kind.map(|kind| kind.as_str()),
+                    all_versions
+                ],
+                row_to_capsule,
+            )
+            .map_err(map_sql_err)?;
+        let mut capsules = Vec::new();
+        for row in rows {
+            capsules.push(row.map_err(map_sql_err)?);
+        }
+        Ok(capsules)
+    }
+
+    fn list_blueprints(
+        &self,
+        workspace_id: &str,
+        all_versions: bool,
+    ) -> Result<Vec<BlueprintEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT b.workspace_id, b.blueprint_id, b.version, b.name, b.path, b.content_hash, b.blueprint_json, b.created_at, b.seq_global
+                 FROM proj_blueprints b
+                 WHERE b.workspace_id = ?1
+                   AND (?2 OR b.version = (
+                     SELECT MAX(latest.version) FROM proj_blueprints latest
+                     WHERE latest.workspace_id = b.workspace_id
+                       AND latest.blueprint_id = b.blueprint_id))
+                 ORDER BY b.blueprint_id, b.version",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id, all_versions], row_to_blueprint)
+            .map_err(map_sql_err)?;
+        let mut blueprints = Vec::new();
+        for row in rows {
+            blueprints.push(row.map_err(map_sql_err)?);
+        }
+        Ok(blueprints)
+    }
+
+    fn get_blueprint(
+        &self,
+        workspace_id: &str,
+        blueprint_id: &str,
+        version: Option<u32>,
+    ) -> Result<Option<BlueprintEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, blueprint_id, version, name, path, content_hash, blueprint_json, created_at, seq_global
+                 FROM proj_blueprints
+                 WHERE workspace_id = ?1 AND blueprint_id = ?2 AND (?3 IS NULL OR version = ?3)
+                 ORDER BY version DESC
+                 LIMIT 1",
+                params![workspace_id, blueprint_id, version],
+                row_to_blueprint,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+    fn list_tool_calls(
+        &self,
+        workspace_id: &str,
+        status: Option<ToolCallStatus>,
+        session_id: Option<&str>,
+    ) -> Result<Vec<ToolCallEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, request_id, tool_id, tool_version, side_effect, input_json, session_id, status, output_json, error_json, requested_at, completed_at, seq_global
+                 FROM proj_tool_calls
+                 WHERE workspace_id = ?1
+                   AND (?2 IS NULL OR status = ?2)
+                   AND (?3 IS NULL OR session_id = ?3)
+                 ORDER BY requested_at, rowid",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(
+                params![
+                    workspace_id,
+                    status.map(|status| status.as_str()),
+                    session_id
+                ],
+                row_to_tool_call,
+            )
+            .map_err(map_sql_err)?;
+        let mut calls = Vec::new();
+        for row in rows {
+            calls.push(row.map_err(map_sql_err)?);
+        }
+        Ok(calls)
+    }
+
+    fn get_tool_call(
+        &self,
+        workspace_id: &str,
+        request_id: &str,
+    ) -> Result<Option<ToolCallEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, request_id, tool_id, tool_version, side_effect, input_json, session_id, status, output_json, error_json, requested_at, completed_at, seq_global
+                 FROM proj_tool_calls
+                 WHERE workspace_id = ?1 AND request_id = ?2",
+                params![workspace_id, request_id],
+                row_to_tool_call,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_skills(
+        &self,
+        workspace_id: &str,
+        trust: Option<SkillTrust>,
+    ) -> Result<Vec<SkillEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, skill_id, revision, scope, path, content_hash, trust, signature_json, frontmatter_json, components_json, approved_hash, updated_at, seq_global
+                 FROM proj_skills
+                 WHERE workspace_id = ?1 AND (?2 IS NULL OR trust = ?2)
+                 ORDER BY skill_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(
+                params![workspace_id, trust.map(|trust| trust.as_str())],
+                row_to_skill,
+            )
+            .map_err(map_sql_err)?;
+        let mut skills = Vec::new();
+        for row in rows {
+            skills.push(row.map_err(map_sql_err)?);
+        }
+        Ok(skills)
+    }
+
+    fn get_skill(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+    ) -> Result<Option<SkillEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, skill_id, revision, scope, path, content_hash, trust, signature_json, frontmatter_json, components_json, approved_hash, updated_at, seq_global
+                 FROM proj_skills
+                 WHERE workspace_id = ?1 AND skill_id = ?2",
+                params![workspace_id, skill_id],
+                row_to_skill,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_leases(
+        &self,
+        workspace_id: &str,
+        status: Option<LeaseStatus>,
+    ) -> Result<Vec<LeaseEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, lease_id, holder_json, scope_json, reason, minted_by, expires_at, status, use_count, created_at, updated_at, seq_global
+                 FROM proj_leases
+                 WHERE workspace_id = ?1 AND (?2 IS NULL OR status = ?2)
+                 ORDER BY expires_at, lease_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(
+                params![workspace_id, status.map(|status| status.as_str())],
+                row_to_lease,
+            )
+            .map_err(map_sql_err)?;
+        let mut leases = Vec::new();
+        for row in rows {
+            leases.push(row.map_err(map_sql_err)?);
+        }
+        Ok(leases)
+    }
+
+    fn get_lease(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+    ) -> Result<Option<LeaseEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, lease_id, holder_json, scope_json, reason, minted_by, expires_at, status, use_count, created_at, updated_at, seq_global
+                 FROM proj_leases
+                 WHERE workspace_id = ?1 AND lease_id = ?2",
+                params![workspace_id, lease_id],
+                row_to_lease,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_secrets(&self, workspace_id: &str) -> Result<Vec<SecretEntry>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, secret_id, name, scope, scope_id, version, redacted, length, created_at, updated_at, seq_global
+                 FROM proj_secrets
+                 WHERE workspace_id = ?1
+                 ORDER BY name, CASE scope WHEN 'global' THEN 0 WHEN 'user' THEN 1 WHEN 'project' THEN 2 ELSE 3 END, scope_id",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_secret)
+            .map_err(map_sql_err)?;
+        let mut secrets = Vec::new();
+        for row in rows {
+            secrets.push(row.map_err(map_sql_err)?);
+        }
+        Ok(secrets)
+    }
+
+    fn get_secret(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+    ) -> Result<Option<SecretEntry>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, secret_id, name, scope, scope_id, version, redacted, length, created_at, updated_at, seq_global
+                 FROM proj_secrets
+                 WHERE workspace_id = ?1 AND secret_id = ?2",
+                params![workspace_id, secret_id],
+                row_to_secret,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+}
+
+impl SecretStore for SqliteStore {
+    fn get_vault(&self, workspace_id: &str) -> Result<Option<VaultRecord>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, kdf_json, salt, check_value, created_at, updated_at
+                 FROM vaults
+                 WHERE workspace_id = ?1",
+                params![workspace_id],
+                row_to_vault,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn get_secret_version(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+        version: u32,
+    ) -> Result<Option<SecretCiphertext>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT workspace_id, secret_id, version, wrapped_key, ciphertext
+                 FROM secret_versions
+                 WHERE workspace_id = ?1 AND secret_id = ?2 AND version = ?3",
+                params![workspace_id, secret_id, version],
+                row_to_secret_ciphertext,
+            )
+            .optional()
+            .map_err(map_sql_err)
+    }
+
+    fn list_secret_versions(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<SecretCiphertext>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT workspace_id, secret_id, version, wrapped_key, ciphertext
+                 FROM secret_versions
+                 WHERE workspace_id = ?1
+                 ORDER BY secret_id, version",
+            )
+            .map_err(map_sql_err)?;
+        let rows = stmt
+            .query_map(params![workspace_id], row_to_secret_ciphertext)
+            .map_err(map_sql_err)?;
+        let mut versions = Vec::new();
+        for row in rows {
+            versions.push(row.map_err(map_sql_err)?);
+        }
+        Ok(versions)
+    }
+
+    fn append_with_vault(
+        &mut self,
+        meta: &CommandMeta,
+        events: Vec<NewEvent>,
+        write: VaultWrite<'_>,
+    ) -> Result<AppendResult, StoreError> {
+        self.append_batch(meta, events, Some(write))
+    }
+}
+
+struct SqliteProjectionWriterTx<'a> {
+    tx: &'a Transaction<'a>,
+}
+
+impl ProjectionWriter for SqliteProjectionWriterTx<'_> {
+    fn reset(&self) -> Result<(), ProjectionError> {
+        Err(ProjectionError::Apply(
+            "reset not supported in transaction writer".to_string(),
+        ))
+    }
+
+    fn upsert_workspace(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        root_path: &str,
+        created_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        self.tx
+            .execute(
+                "INSERT INTO proj_workspaces (workspace_id, name, root_path, created_at, seq_global)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(workspace_id) DO UPDATE SET name = excluded.name, root_path = excluded.root_path, created_at = excluded.created_at, seq_global = excluded.seq_global",
+                params![workspace_id, name, root_path, created_at, seq_global],
+            )
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+        Ok(())
+    }
+
+    fn upsert_project(
+        &self,
+        project_id: &str,
+        workspace_id: &str,
+        name: &str,
+        created_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        self.tx
+            .execute(
+                "INSERT INTO proj_projects (project_id, workspace_id, name, created_at, seq_global)
+                 VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT(project_id) DO UPDATE SET workspace_id = excluded.workspace_id, name = excluded.name, created_at = excluded.created_at, seq_global = excluded.seq_global",
+                params![project_id, workspace_id, name, created_at, seq_global],
+            )
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+        Ok(())
+    }
+
+    fn rename_workspace(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        rename_workspace(self.tx, workspace_id, name, seq_global)
+    }
+
+    fn set_workspace_archived(
+        &self,
+        workspace_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_workspace_archived(self.tx, workspace_id, archived_at, seq_global)
+    }
+
+    fn rename_project(
+        &self,
+        project_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        rename_project(self.tx, project_id, name, seq_global)
+    }
+
+    fn set_project_archived(
+        &self,
+        project_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_project_archived(self.tx, project_id, archived_at, seq_global)
+    }
+
+    fn upsert_session(&self, session: &SessionListEntry) -> Result<(), ProjectionError> {
+        upsert_session(self.tx, session)
+    }
+
+    fn upsert_task(&self, task: &TaskListEntry) -> Result<(), ProjectionError> {
+        upsert_task(self.tx, task)
+    }
+
+    fn update_task_state(
+        &self,
+        task_id: &str,
+        state: TaskState,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        update_task_state(self.tx, task_id, state, updated_at, seq_global)
+    }
+
+    fn start_task_attempt(
+        &self,
+        task_id: &str,
+        started_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        start_task_attempt(self.tx, task_id, started_at, seq_global)
+    }
+
+    fn schedule_task_retry(
+        &self,
+        task_id: &str,
+        retry_at: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        schedule_task_retry(self.tx, task_id, retry_at, updated_at, seq_global)
+    }
+
+    fn upsert_pipeline_template(
+        &self,
+        template: &PipelineTemplateEntry,
+    ) -> Result<(), ProjectionError> {
+        upsert_pipeline_template(self.tx, template)
+    }
+
+    fn upsert_pipeline_binding(
+        &self,
+        binding: &PipelineBindingEntry,
+    ) -> Result<(), ProjectionError> {
+        upsert_pipeline_binding(self.tx, binding)
+    }
+
+    fn update_task_stage(
+        &self,
+        task_id: &str,
+        stage: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        update_task_stage(self.tx, task_id, stage, updated_at, seq_global)
+    }
+
+    fn upsert_gate(&self, gate: &GateEntry) -> Result<(), ProjectionError> {
+        upsert_gate(self.tx, gate)
+    }
+
+    fn record_gate_decision(
+        &self,
+        gate_id: &str,
+        decision: &GateDecisionEntry,
+        status: GateStatus,
+    ) -> Result<(), ProjectionError> {
+        record_gate_decision(self.tx, gate_id, decision, status)
+    }
+
+    fn upsert_worktree(&self, worktree: &WorktreeEntry) -> Result<(), ProjectionError> {
+        upsert_worktree(self.tx, worktree)
+    }
+
+    fn attach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        attached_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        attach_worktree_session(self.tx, worktree_id, session_id, attached_at, seq_global)
+    }
+
+    fn detach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        detach_worktree_session(self.tx, worktree_id, session_id, updated_at, seq_global)
+    }
+
+    fn set_worktree_lock(
+        &self,
+        worktree_id: &str,
+        lock: Option<&WorktreeLock>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_worktree_lock(self.tx, worktree_id, lock, updated_at, seq_global)
+    }
+
+    fn touch_board_node(&self, node: &BoardNode) -> Result<(), ProjectionError> {
+        touch_board_node(self.tx, node)
+    }
+
+    fn set_board_node_layout(
+        &self,
+        node_id: &str,
+        position: &BoardPosition,
+        group_node_id: Option<&str>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_board_node_layout(
+            self.tx,
+            node_id,
+            position,
+            group_node_id,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn add_board_edge(&self, workspace_id: &str, edge: &BoardEdge) -> Result<(), ProjectionError> {
+        add_board_edge(self.tx, workspace_id, edge)
+    }
+
+    fn remove_board_edge(
+        &self,
+        kind: BoardEdgeKind,
+        from_node_id: &str,
+        to_node_id: &str,
+    ) -> Result<(), ProjectionError> {
+        remove_board_edge(self.tx, kind, from_node_id, to_node_id)
+    }
+
+    fn insert_capsule(&self, capsule: &CapsuleEntry) -> Result<(), ProjectionError> {
+        insert_capsule(self.tx, capsule)
+    }
+
+    fn insert_blueprint(&self, blueprint: &BlueprintEntry) -> Result<(), ProjectionError> {
+        insert_blueprint(self.tx, blueprint)
+    }
+
+    fn insert_tool_call(&self, call: &ToolCallEntry) -> Result<(), ProjectionError> {
+        insert_tool_call(self.tx, call)
+    }
+
+    fn complete_tool_call(
+        &self,
+        request_id: &str,
+        result: &ToolResultPayload,
+        completed_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        complete_tool_call(self.tx, request_id, result, completed_at, seq_global)
+    }
+
+    fn upsert_skill(&self, skill: &SkillEntry) -> Result<(), ProjectionError> {
+        upsert_skill(self.tx, skill)
+    }
+
+    fn approve_skill(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+        content_hash: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        approve_skill(
+            self.tx,
+            workspace_id,
+            skill_id,
+            content_hash,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn remove_skill(&self, workspace_id: &str, skill_id: &str) -> Result<(), ProjectionError> {
+        remove_skill(self.tx, workspace_id, skill_id)
+    }
+
+    fn insert_lease(&self, lease: &LeaseEntry) -> Result<(), ProjectionError> {
+        insert_lease(self.tx, lease)
+    }
+
+    fn record_lease_use(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        record_lease_use(self.tx, workspace_id, lease_id, updated_at, seq_global)
+    }
+
+    fn end_lease(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+        status: LeaseStatus,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        end_lease(
+            self.tx,
+            workspace_id,
+            lease_id,
+            status,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn insert_secret(&self, secret: &SecretEntry) -> Result<(), ProjectionError> {
+        insert_secret(self.tx, secret)
+    }
+
+    fn rotate_secret(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+        version: u32,
+        length: usize,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        rotate_secret(
+            self.tx,
+            workspace_id,
+            secret_id,
+            version,
+            length,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn delete_secret(&self, workspace_id: &str, secret_id: &str) -> Result<(), ProjectionError> {
+        delete_secret(self.tx, workspace_id, secret_id)
+    }
+
+    fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError> {
+        self.tx
+            .execute(
+                "INSERT INTO proj_meta (workspace_id, last_seq_global)
+                 VALUES (?1, ?2)
+                 ON CONFLICT(workspace_id) DO UPDATE SET last_seq_global = excluded.last_seq_global",
+                params![workspace_id, seq_global],
+            )
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+        Ok(())
     }
 }
 
-struct SqliteProjectionWriterTx<'a> {
-    tx: &'a Transaction<'a>,
+struct SqliteProjectionWriterConn<'a> {
+    conn: &'a Connection,
 }
 
-impl ProjectionWriter for SqliteProjectionWriterTx<'_> {
+impl ProjectionWriter for SqliteProjectionWriterConn<'_> {
     fn reset(&self) -> Result<(), ProjectionError> {
-        Err(ProjectionError::Apply(
-            "reset not supported in transaction writer".to_string(),
-        ))
+        self.conn
+            .execute_batch(
+                "DELETE FROM proj_workspaces; DELETE FROM proj_projects; DELETE FROM proj_sessions; DELETE FROM proj_tasks; DELETE FROM proj_pipeline_templates; DELETE FROM proj_pipeline_bindings; DELETE FROM proj_gates; DELETE FROM proj_gate_decisions; DELETE FROM proj_worktrees; DELETE FROM proj_worktree_sessions; DELETE FROM proj_board_nodes; DELETE FROM proj_board_edges; DELETE FROM proj_capsules; DELETE FROM proj_blueprints; DELETE FROM proj_tool_calls; DELETE FROM proj_skills; DELETE FROM proj_leases; DELETE FROM proj_secrets; DELETE FROM proj_meta;",
+            )
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+        Ok(())
     }
 
     fn upsert_workspace(
@@ -379,7 +1613,7 @@ impl ProjectionWriter for SqliteProjectionWriterTx<'_> {
         created_at: &str,
         seq_global: i64,
     ) -> Result<(), ProjectionError> {
-        self.tx
+        self.conn
             .execute(
                 "INSERT INTO proj_workspaces (workspace_id, name, root_path, created_at, seq_global)
                  VALUES (?1, ?2, ?3, ?4, ?5)
@@ -398,7 +1632,7 @@ impl ProjectionWriter for SqliteProjectionWriterTx<'_> {
         created_at: &str,
         seq_global: i64,
     ) -> Result<(), ProjectionError> {
-        self.tx
+        self.conn
             .execute(
                 "INSERT INTO proj_projects (project_id, workspace_id, name, created_at, seq_global)
                  VALUES (?1, ?2, ?3, ?4, ?5)
@@ -409,69 +1643,291 @@ impl ProjectionWriter for SqliteProjectionWriterTx<'_> {
         Ok(())
     }
 
-    fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError> {
-        self.tx
-            .execute(
-                "INSERT INTO proj_meta (workspace_id, last_seq_global)
-                 VALUES (?1, ?2)
-                 ON CONFLICT(workspace_id) DO UPDATE SET last_seq_global = excluded.last_seq_global",
-                params![workspace_id, seq_global],
-            )
-            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
-        Ok(())
+    fn rename_workspace(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        rename_workspace(self.conn, workspace_id, name, seq_global)
     }
-}
 
-struct SqliteProjectionWriterConn<'a> {
-    conn: &'a Connection,
-}
+    fn set_workspace_archived(
+        &self,
+        workspace_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_workspace_archived(self.conn, workspace_id, archived_at, seq_global)
+    }
 
-impl ProjectionWriter for SqliteProjectionWriterConn<'_> {
-    fn reset(&self) -> Result<(), ProjectionError> {
-        self.conn
-            .execute_batch(
-                "DELETE FROM proj_workspaces; DELETE FROM proj_projects; DELETE FROM proj_meta;",
-            )
-            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
-        Ok(())
+    fn rename_project(
+        &self,
+        project_id: &str,
+        name: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        rename_project(self.conn, project_id, name, seq_global)
+    }
+
+    fn set_project_archived(
+        &self,
+        project_id: &str,
+        archived_at: Option<&str>,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_project_archived(self.conn, project_id, archived_at, seq_global)
+    }
+
+    fn upsert_session(&self, session: &SessionListEntry) -> Result<(), ProjectionError> {
+        upsert_session(self.conn, session)
+    }
+
+    fn upsert_task(&self, task: &TaskListEntry) -> Result<(), ProjectionError> {
+        upsert_task(self.conn, task)
+    }
+
+    fn update_task_state(
+        &self,
+        task_id: &str,
+        state: TaskState,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        update_task_state(self.conn, task_id, state, updated_at, seq_global)
+    }
+
+    fn start_task_attempt(
+        &self,
+        task_id: &str,
+        started_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        start_task_attempt(self.conn, task_id, started_at, seq_global)
+    }
+
+    fn schedule_task_retry(
+        &self,
+        task_id: &str,
+        retry_at: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        schedule_task_retry(self.conn, task_id, retry_at, updated_at, seq_global)
+    }
+
+    fn upsert_pipeline_template(
+        &self,
+        template: &PipelineTemplateEntry,
+    ) -> Result<(), ProjectionError> {
+        upsert_pipeline_template(self.conn, template)
+    }
+
+    fn upsert_pipeline_binding(
+        &self,
+        binding: &PipelineBindingEntry,
+    ) -> Result<(), ProjectionError> {
+        upsert_pipeline_binding(self.conn, binding)
+    }
+
+    fn update_task_stage(
+        &self,
+        task_id: &str,
+        stage: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        update_task_stage(self.conn, task_id, stage, updated_at, seq_global)
+    }
+
+    fn upsert_gate(&self, gate: &GateEntry) -> Result<(), ProjectionError> {
+        upsert_gate(self.conn, gate)
+    }
+
+    fn record_gate_decision(
+        &self,
+        gate_id: &str,
+        decision: &GateDecisionEntry,
+        status: GateStatus,
+    ) -> Result<(), ProjectionError> {
+        record_gate_decision(self.conn, gate_id, decision, status)
+    }
+
+    fn upsert_worktree(&self, worktree: &WorktreeEntry) -> Result<(), ProjectionError> {
+        upsert_worktree(self.conn, worktree)
+    }
+
+    fn attach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        attached_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        attach_worktree_session(self.conn, worktree_id, session_id, attached_at, seq_global)
+    }
+
+    fn detach_worktree_session(
+        &self,
+        worktree_id: &str,
+        session_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        detach_worktree_session(self.conn, worktree_id, session_id, updated_at, seq_global)
+    }
+
+    fn set_worktree_lock(
+        &self,
+        worktree_id: &str,
+        lock: Option<&WorktreeLock>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_worktree_lock(self.conn, worktree_id, lock, updated_at, seq_global)
+    }
+
+    fn touch_board_node(&self, node: &BoardNode) -> Result<(), ProjectionError> {
+        touch_board_node(self.conn, node)
+    }
+
+    fn set_board_node_layout(
+        &self,
+        node_id: &str,
+        position: &BoardPosition,
+        group_node_id: Option<&str>,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        set_board_node_layout(
+            self.conn,
+            node_id,
+            position,
+            group_node_id,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn add_board_edge(&self, workspace_id: &str, edge: &BoardEdge) -> Result<(), ProjectionError> {
+        add_board_edge(self.conn, workspace_id, edge)
+    }
+
+    fn remove_board_edge(
+        &self,
+        kind: BoardEdgeKind,
+        from_node_id: &str,
+        to_node_id: &str,
+    ) -> Result<(), ProjectionError> {
+        remove_board_edge(self.conn, kind, from_node_id, to_node_id)
+    }
+
+    fn insert_capsule(&self, capsule: &CapsuleEntry) -> Result<(), ProjectionError> {
+        insert_capsule(self.conn, capsule)
+    }
+
+    fn insert_blueprint(&self, blueprint: &BlueprintEntry) -> Result<(), ProjectionError> {
+        insert_blueprint(self.conn, blueprint)
+    }
+
+    fn insert_tool_call(&self, call: &ToolCallEntry) -> Result<(), ProjectionError> {
+        insert_tool_call(self.conn, call)
+    }
+
+    fn complete_tool_call(
+        &self,
+        request_id: &str,
+        result: &ToolResultPayload,
+        completed_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        complete_tool_call(self.conn, request_id, result, completed_at, seq_global)
+    }
+
+    fn upsert_skill(&self, skill: &SkillEntry) -> Result<(), ProjectionError> {
+        upsert_skill(self.conn, skill)
+    }
+
+    fn approve_skill(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+        content_hash: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        approve_skill(
+            self.conn,
+            workspace_id,
+            skill_id,
+            content_hash,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn remove_skill(&self, workspace_id: &str, skill_id: &str) -> Result<(), ProjectionError> {
+        remove_skill(self.conn, workspace_id, skill_id)
+    }
+
+    fn insert_lease(&self, lease: &LeaseEntry) -> Result<(), ProjectionError> {
+        insert_lease(self.conn, lease)
+    }
+
+    fn record_lease_use(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+        updated_at: &str,
+        seq_global: i64,
+    ) -> Result<(), ProjectionError> {
+        record_lease_use(self.conn, workspace_id, lease_id, updated_at, seq_global)
     }
 
-    fn upsert_workspace(
+    fn end_lease(
         &self,
         workspace_id: &str,
-        name: &str,
-        root_path: &str,
-        created_at: &str,
+        lease_id: &str,
+        status: LeaseStatus,
+        updated_at: &str,
         seq_global: i64,
     ) -> Result<(), ProjectionError> {
-        self.conn
-            .execute(
-                "INSERT INTO proj_workspaces (workspace_id, name, root_path, created_at, seq_global)
-                 VALUES (?1, ?2, ?3, ?4, ?5)
-                 ON CONFLICT(workspace_id) DO UPDATE SET name = excluded.name, root_path = excluded.root_path, created_at = excluded.created_at, seq_global = excluded.seq_global",
-                params![workspace_id, name, root_path, created_at, seq_global],
-            )
-            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
-        Ok(())
+        end_lease(
+            self.conn,
+            workspace_id,
+            lease_id,
+            status,
+            updated_at,
+            seq_global,
+        )
     }
 
-    fn upsert_project(
+    fn insert_secret(&self, secret: &SecretEntry) -> Result<(), ProjectionError> {
+        insert_secret(self.conn, secret)
+    }
+
+    fn rotate_secret(
         &self,
-        project_id: &str,
         workspace_id: &str,
-        name: &str,
-        created_at: &str,
+        secret_id: &str,
+        version: u32,
+        length: usize,
+        updated_at: &str,
         seq_global: i64,
     ) -> Result<(), ProjectionError> {
-        self.conn
-            .execute(
-                "INSERT INTO proj_projects (project_id, workspace_id, name, created_at, seq_global)
-                 VALUES (?1, ?2, ?3, ?4, ?5)
-                 ON CONFLICT(project_id) DO UPDATE SET workspace_id = excluded.workspace_id, name = excluded.name, created_at = excluded.created_at, seq_global = excluded.seq_global",
-                params![project_id, workspace_id, name, created_at, seq_global],
-            )
-            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
-        Ok(())
+        rotate_secret(
+            self.conn,
+            workspace_id,
+            secret_id,
+            version,
+            length,
+            updated_at,
+            seq_global,
+        )
+    }
+
+    fn delete_secret(&self, workspace_id: &str, secret_id: &str) -> Result<(), ProjectionError> {
+        delete_secret(self.conn, workspace_id, secret_id)
     }
 
     fn set_meta(&self, workspace_id: &str, seq_global: i64) -> Result<(), ProjectionError> {
@@ -487,6 +1943,1030 @@ impl ProjectionWriter for SqliteProjectionWriterConn<'_> {
     }
 }
 
+/// Shared by the transaction and connection writers; `Transaction` derefs to `Connection`.
+fn rename_workspace(
+    conn: &Connection,
+    workspace_id: &str,
+    name: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_workspaces SET name = ?2, seq_global = ?3 WHERE workspace_id = ?1",
+        params![workspace_id, name, seq_global],
+        &format!("workspace {workspace_id}"),
+    )
+}
+
+fn set_workspace_archived(
+    conn: &Connection,
+    workspace_id: &str,
+    archived_at: Option<&str>,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_workspaces SET archived_at = ?2, seq_global = ?3 WHERE workspace_id = ?1",
+        params![workspace_id, archived_at, seq_global],
+        &format!("workspace {workspace_id}"),
+    )
+}
+
+fn rename_project(
+    conn: &Connection,
+    project_id: &str,
+    name: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_projects SET name = ?2, seq_global = ?3 WHERE project_id = ?1",
+        params![project_id, name, seq_global],
+        &format!("project {project_id}"),
+    )
+}
+
+fn set_project_archived(
+    conn: &Connection,
+    project_id: &str,
+    archived_at: Option<&str>,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_projects SET archived_at = ?2, seq_global = ?3 WHERE project_id = ?1",
+        params![project_id, archived_at, seq_global],
+        &format!("project {project_id}"),
+    )
+}
+
+/// Runs an UPDATE that must touch exactly the row named by `what`.
+fn update_one(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+    what: &str,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(sql, params)
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "{what} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn upsert_session(conn: &Connection, session: &SessionListEntry) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_sessions (session_id, workspace_id, project_id, parent_session_id, root_session_id, lineage_path, depth, forked_from_session_id, fork_mode, label, created_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+         ON CONFLICT(session_id) DO UPDATE SET workspace_id = excluded.workspace_id, project_id = excluded.project_id, parent_session_id = excluded.parent_session_id, root_session_id = excluded.root_session_id, lineage_path = excluded.lineage_path, depth = excluded.depth, forked_from_session_id = excluded.forked_from_session_id, fork_mode = excluded.fork_mode, label = excluded.label, created_at = excluded.created_at, seq_global = excluded.seq_global",
+        params![
+            session.session_id,
+            session.workspace_id,
+            session.project_id,
+            session.parent_session_id,
+            session.root_session_id,
+            session.lineage_path,
+            session.depth,
+            session.forked_from_session_id,
+            session.fork_mode.map(|mode| mode.to_string()),
+            session.label,
+            session.created_at,
+            session.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn upsert_task(conn: &Connection, task: &TaskListEntry) -> Result<(), ProjectionError> {
+    let retry_json = task
+        .retry
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    conn.execute(
+        "INSERT INTO proj_tasks (task_id, workspace_id, project_id, session_id, title, state, deadline_at, attempt, retry_json, timeout_ms, attempt_started_at, retry_at, created_at, updated_at, seq_global, current_stage)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+         ON CONFLICT(task_id) DO UPDATE SET current_stage = excluded.current_stage, workspace_id = excluded.workspace_id, project_id = excluded.project_id, session_id = excluded.session_id, title = excluded.title, state = excluded.state, deadline_at = excluded.deadline_at, attempt = excluded.attempt, retry_json = excluded.retry_json, timeout_ms = excluded.timeout_ms, attempt_started_at = excluded.attempt_started_at, retry_at = excluded.retry_at, created_at = excluded.created_at, updated_at = excluded.updated_at, seq_global = excluded.seq_global",
+        params![
+            task.task_id,
+            task.workspace_id,
+            task.project_id,
+            task.session_id,
+            task.title,
+            task.state.as_str(),
+            task.deadline_at,
+            task.attempt,
+            retry_json,
+            task.timeout_ms,
+            task.attempt_started_at,
+            task.retry_at,
+            task.created_at,
+            task.updated_at,
+            task.seq_global,
+            task.current_stage,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn update_task_state(
+    conn: &Connection,
+    task_id: &str,
+    state: TaskState,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(
+            "UPDATE proj_tasks SET state = ?2, updated_at = ?3, seq_global = ?4 WHERE task_id = ?1",
+            params![task_id, state.as_str(), updated_at, seq_global],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "task {task_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn start_task_attempt(
+    conn: &Connection,
+    task_id: &str,
+    started_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(
+            "UPDATE proj_tasks SET state = ?2, attempt = attempt + 1, attempt_started_at = ?3, retry_at = NULL, updated_at = ?3, seq_global = ?4 WHERE task_id = ?1",
+            params![task_id, TaskState::Running.as_str(), started_at, seq_global],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "task {task_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn schedule_task_retry(
+    conn: &Connection,
+    task_id: &str,
+    retry_at: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(
+            "UPDATE proj_tasks SET state = ?2, retry_at = ?3, updated_at = ?4, seq_global = ?5 WHERE task_id = ?1",
+            params![task_id, TaskState::Queued.as_str(), retry_at, updated_at, seq_global],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "task {task_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn upsert_pipeline_template(
+    conn: &Connection,
+    template: &PipelineTemplateEntry,
+) -> Result<(), ProjectionError> {
+    let stages_json = serde_json::to_string(&template.stages)
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    conn.execute(
+        "INSERT INTO proj_pipeline_templates (template_id, version, workspace_id, name, stages_json, created_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(template_id, version) DO UPDATE SET workspace_id = excluded.workspace_id, name = excluded.name, stages_json = excluded.stages_json, created_at = excluded.created_at, seq_global = excluded.seq_global",
+        params![
+            template.template_id,
+            template.version,
+            template.workspace_id,
+            template.name,
+            stages_json,
+            template.created_at,
+            template.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn upsert_pipeline_binding(
+    conn: &Connection,
+    binding: &PipelineBindingEntry,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_pipeline_bindings (project_id, workspace_id, template_id, template_version, bound_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(project_id) DO UPDATE SET workspace_id = excluded.workspace_id, template_id = excluded.template_id, template_version = excluded.template_version, bound_at = excluded.bound_at, seq_global = excluded.seq_global",
+        params![
+            binding.project_id,
+            binding.workspace_id,
+            binding.template_id,
+            binding.template_version,
+            binding.bound_at,
+            binding.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn update_task_stage(
+    conn: &Connection,
+    task_id: &str,
+    stage: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(
+            "UPDATE proj_tasks SET current_stage = ?2, updated_at = ?3, seq_global = ?4 WHERE task_id = ?1",
+            params![task_id, stage, updated_at, seq_global],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "task {task_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn upsert_gate(conn: &Connection, gate: &GateEntry) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_gates (gate_id, workspace_id, command_type, subject_kind, subject_id, kind, required_approvals, delay_ms, description, status, created_at, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+         ON CONFLICT(gate_id) DO UPDATE SET workspace_id = excluded.workspace_id, command_type = excluded.command_type, subject_kind = excluded.subject_kind, subject_id = excluded.subject_id, kind = excluded.kind, required_approvals = excluded.required_approvals, delay_ms = excluded.delay_ms, description = excluded.description, status = excluded.status, created_at = excluded.created_at, updated_at = excluded.updated_at, seq_global = excluded.seq_global",
+        params![
+            gate.gate_id,
+            gate.workspace_id,
+            gate.scope.command_type,
+            gate.scope.subject.kind,
+            gate.scope.subject.id,
+            gate.kind.as_str(),
+            gate.required_approvals,
+            gate.delay_ms.map(|delay_ms| delay_ms as i64),
+            gate.description,
+            gate.status.as_str(),
+            gate.created_at,
+            gate.updated_at,
+            gate.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn record_gate_decision(
+    conn: &Connection,
+    gate_id: &str,
+    decision: &GateDecisionEntry,
+    status: GateStatus,
+) -> Result<(), ProjectionError> {
+    let evidence_json = serde_json::to_string(&decision.evidence)
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    conn.execute(
+        "INSERT INTO proj_gate_decisions (gate_id, seq_global, approved, approver_kind, approver_id, approver_label, reason, evidence_json, decided_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(gate_id, seq_global) DO NOTHING",
+        params![
+            gate_id,
+            decision.seq_global,
+            decision.approved,
+            decision.approver.kind.as_str(),
+            decision.approver.id,
+            decision.approver.label,
+            decision.reason,
+            evidence_json,
+            decision.decided_at,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    let updated = conn
+        .execute(
+            "UPDATE proj_gates SET status = ?2, updated_at = ?3, seq_global = ?4 WHERE gate_id = ?1",
+            params![
+                gate_id,
+                status.as_str(),
+                decision.decided_at,
+                decision.seq_global
+            ],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "gate {gate_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn load_gate_decisions(
+    conn: &Connection,
+    gate_id: &str,
+) -> Result<Vec<GateDecisionEntry>, StoreError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT approved, approver_kind, approver_id, approver_label, reason, evidence_json, decided_at, seq_global
+             FROM proj_gate_decisions
+             WHERE gate_id = ?1
+             ORDER BY seq_global",
+        )
+        .map_err(map_sql_err)?;
+    let rows = stmt
+        .query_map(params![gate_id], |row| {
+            let kind: String = row.get(1)?;
+            let kind = ApproverKind::parse(&kind).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    1,
+                    rusqlite::types::Type::Text,
+                    format!("unknown approver kind {kind}").into(),
+                )
+            })?;
+            let evidence_json: String = row.get(5)?;
+            let evidence = serde_json::from_str(&evidence_json).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    5,
+                    rusqlite::types::Type::Text,
+                    Box::new(err),
+                )
+            })?;
+            Ok(GateDecisionEntry {
+                approved: row.get(0)?,
+                approver: Approver {
+                    kind,
+                    id: row.get(2)?,
+                    label: row.get(3)?,
+                },
+                reason: row.get(4)?,
+                evidence,
+                decided_at: row.get(6)?,
+                seq_global: row.get(7)?,
+            })
+        })
+        .map_err(map_sql_err)?;
+    let mut decisions = Vec::new();
+    for row in rows {
+        decisions.push(row.map_err(map_sql_err)?);
+    }
+    Ok(decisions)
+}
+
+fn upsert_worktree(conn: &Connection, worktree: &WorktreeEntry) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_worktrees (worktree_id, workspace_id, project_id, path, branch, repo, lock_session_id, locked_at, created_at, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT(worktree_id) DO UPDATE SET workspace_id = excluded.workspace_id, project_id = excluded.project_id, path = excluded.path, branch = excluded.branch, repo = excluded.repo, lock_session_id = excluded.lock_session_id, locked_at = excluded.locked_at, created_at = excluded.created_at, updated_at = excluded.updated_at, seq_global = excluded.seq_global",
+        params![
+            worktree.worktree_id,
+            worktree.workspace_id,
+            worktree.project_id,
+            worktree.path,
+            worktree.branch,
+            worktree.repo,
+            worktree.lock.as_ref().map(|lock| lock.session_id.as_str()),
+            worktree.lock.as_ref().map(|lock| lock.acquired_at.as_str()),
+            worktree.created_at,
+            worktree.updated_at,
+            worktree.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn attach_worktree_session(
+    conn: &Connection,
+    worktree_id: &str,
+    session_id: &str,
+    attached_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_worktree_sessions (worktree_id, session_id, attached_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(worktree_id, session_id) DO NOTHING",
+        params![worktree_id, session_id, attached_at, seq_global],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    touch_worktree(conn, worktree_id, attached_at, seq_global)
+}
+
+fn detach_worktree_session(
+    conn: &Connection,
+    worktree_id: &str,
+    session_id: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "DELETE FROM proj_worktree_sessions WHERE worktree_id = ?1 AND session_id = ?2",
+        params![worktree_id, session_id],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    touch_worktree(conn, worktree_id, updated_at, seq_global)
+}
+
+fn set_worktree_lock(
+    conn: &Connection,
+    worktree_id: &str,
+    lock: Option<&WorktreeLock>,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    if let Some(lock) = lock {
+        // Acquiring over another session's lock means the log disagrees with itself; fail the
+        // append rather than silently handing the lock over.
+        let holder: Option<String> = conn
+            .query_row(
+                "SELECT lock_session_id FROM proj_worktrees WHERE worktree_id = ?1",
+                params![worktree_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?
+            .flatten();
+        if let Some(holder) = holder.filter(|holder| *holder != lock.session_id) {
+            return Err(ProjectionError::Apply(format!(
+                "worktree {worktree_id} is already locked by session {holder}"
+            )));
+        }
+    }
+    let updated = conn
+        .execute(
+            "UPDATE proj_worktrees SET lock_session_id = ?2, locked_at = ?3, updated_at = ?4, seq_global = ?5 WHERE worktree_id = ?1",
+            params![
+                worktree_id,
+                lock.map(|lock| lock.session_id.as_str()),
+                lock.map(|lock| lock.acquired_at.as_str()),
+                updated_at,
+                seq_global
+            ],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "worktree {worktree_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn touch_worktree(
+    conn: &Connection,
+    worktree_id: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let updated = conn
+        .execute(
+            "UPDATE proj_worktrees SET updated_at = ?2, seq_global = ?3 WHERE worktree_id = ?1",
+            params![worktree_id, updated_at, seq_global],
+        )
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    if updated == 0 {
+        return Err(ProjectionError::Apply(format!(
+            "worktree {worktree_id} missing from projection"
+        )));
+    }
+    Ok(())
+}
+
+fn load_worktree_sessions(conn: &Connection, worktree_id: &str) -> Result<Vec<String>, StoreError> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT session_id FROM proj_worktree_sessions
+             WHERE worktree_id = ?1
+             ORDER BY seq_global, session_id",
+        )
+        .map_err(map_sql_err)?;
+    let rows = stmt
+        .query_map(params![worktree_id], |row| row.get(0))
+        .map_err(map_sql_err)?;
+    let mut sessions = Vec::new();
+    for row in rows {
+        sessions.push(row.map_err(map_sql_err)?);
+    }
+    Ok(sessions)
+}
+
+fn touch_board_node(conn: &Connection, node: &BoardNode) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_board_nodes (node_id, workspace_id, project_id, kind, subject_id, label, body, position_x, position_y, group_node_id, created_at, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+         ON CONFLICT(node_id) DO UPDATE SET project_id = COALESCE(project_id, excluded.project_id), label = COALESCE(excluded.label, label), body = COALESCE(excluded.body, body), updated_at = excluded.updated_at, seq_global = excluded.seq_global",
+        params![
+            node.node_id,
+            node.workspace_id,
+            node.project_id,
+            node.kind,
+            node.subject_id,
+            node.label,
+            node.body,
+            node.position.map(|position| position.x),
+            node.position.map(|position| position.y),
+            node.group_node_id,
+            node.created_at,
+            node.updated_at,
+            node.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn set_board_node_layout(
+    conn: &Connection,
+    node_id: &str,
+    position: &BoardPosition,
+    group_node_id: Option<&str>,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_board_nodes SET position_x = ?2, position_y = ?3, group_node_id = ?4, updated_at = ?5, seq_global = ?6 WHERE node_id = ?1",
+        params![
+            node_id,
+            position.x,
+            position.y,
+            group_node_id,
+            updated_at,
+            seq_global
+        ],
+        &format!("board node {node_id}"),
+    )
+}
+
+fn add_board_edge(
+    conn: &Connection,
+    workspace_id: &str,
+    edge: &BoardEdge,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_board_edges (kind, from_node_id, to_node_id, workspace_id, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(kind, from_node_id, to_node_id) DO UPDATE SET seq_global = excluded.seq_global",
+        params![
+            edge.kind.as_str(),
+            edge.from_node_id,
+            edge.to_node_id,
+            workspace_id,
+            edge.seq_global
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn remove_board_edge(
+    conn: &Connection,
+    kind: BoardEdgeKind,
+    from_node_id: &str,
+    to_node_id: &str,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "DELETE FROM proj_board_edges WHERE kind = ?1 AND from_node_id = ?2 AND to_node_id = ?3",
+        params![kind.as_str(), from_node_id, to_node_id],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn insert_capsule(conn: &Connection, capsule: &CapsuleEntry) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_capsules (workspace_id, subject_kind, subject_id, kind, version, content, chars, created_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            capsule.workspace_id,
+            capsule.subject.kind,
+            capsule.subject.id,
+            capsule.kind.as_str(),
+            capsule.version,
+            capsule.content,
+            capsule.chars,
+            capsule.created_at,
+            capsule.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn insert_blueprint(conn: &Connection, blueprint: &BlueprintEntry) -> Result<(), ProjectionError> {
+    let blueprint_json = serde_json::to_string(&blueprint.blueprint)
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    conn.execute(
+        "INSERT INTO proj_blueprints (workspace_id, blueprint_id, version, name, path, content_hash, blueprint_json, created_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            blueprint.workspace_id,
+            blueprint.blueprint_id,
+            blueprint.version,
+            blueprint.name,
+            blueprint.path,
+            blueprint.content_hash,
+            blueprint_json,
+            blueprint.created_at,
+            blueprint.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn insert_tool_call(conn: &Connection, call: &ToolCallEntry) -> Result<(), ProjectionError> {
+    let input_json = serde_json::to_string(&call.input)
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    conn.execute(
+        "INSERT INTO proj_tool_calls (request_id, workspace_id, tool_id, tool_version, side_effect, input_json, session_id, status, requested_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            call.request_id,
+            call.workspace_id,
+            call.tool_id,
+            call.tool_version,
+            call.side_effect.as_str(),
+            input_json,
+            call.session_id,
+            call.status.as_str(),
+            call.requested_at,
+            call.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn complete_tool_call(
+    conn: &Connection,
+    request_id: &str,
+    result: &ToolResultPayload,
+    completed_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    let output_json = result
+        .output
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    let error_json = result
+        .error
+        .as_ref()
+        .map(serde_json::to_string)
+        .transpose()
+        .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    update_one(
+        conn,
+        "UPDATE proj_tool_calls
+         SET status = ?2, output_json = ?3, error_json = ?4, completed_at = ?5, seq_global = ?6
+         WHERE request_id = ?1",
+        params![
+            request_id,
+            result.status.as_str(),
+            output_json,
+            error_json,
+            completed_at,
+            seq_global
+        ],
+        "tool call",
+    )
+}
+
+fn upsert_skill(conn: &Connection, skill: &SkillEntry) -> Result<(), ProjectionError> {
+    let to_json = |value: Result<String, serde_json::Error>| {
+        value.map_err(|err| ProjectionError::Apply(err.to_string()))
+    };
+    let signature_json = to_json(serde_json::to_string(&skill.signature))?;
+    let frontmatter_json = to_json(serde_json::to_string(&skill.frontmatter))?;
+    let components_json = to_json(serde_json::to_string(&skill.components))?;
+    conn.execute(
+        "INSERT INTO proj_skills (workspace_id, skill_id, revision, scope, path, content_hash, trust, signature_json, frontmatter_json, components_json, approved_hash, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+         ON CONFLICT(workspace_id, skill_id) DO UPDATE SET revision = excluded.revision, scope = excluded.scope, path = excluded.path, content_hash = excluded.content_hash, trust = excluded.trust, signature_json = excluded.signature_json, frontmatter_json = excluded.frontmatter_json, components_json = excluded.components_json, updated_at = excluded.updated_at, seq_global = excluded.seq_global",
+        params![
+            skill.workspace_id,
+            skill.skill_id,
+            skill.revision,
+            skill.scope.as_str(),
+            skill.path,
+            skill.content_hash,
+            skill.trust.as_str(),
+            signature_json,
+            frontmatter_json,
+            components_json,
+            skill.approved_hash,
+            skill.updated_at,
+            skill.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn approve_skill(
+    conn: &Connection,
+    workspace_id: &str,
+    skill_id: &str,
+    content_hash: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_skills
+         SET approved_hash = ?3,
+             trust = CASE WHEN trust = 'proposed' AND content_hash = ?3 THEN 'sandboxed' ELSE trust END,
+             updated_at = ?4, seq_global = ?5
+         WHERE workspace_id = ?1 AND skill_id = ?2",
+        params![workspace_id, skill_id, content_hash, updated_at, seq_global],
+        "skill",
+    )
+}
+
+fn remove_skill(
+    conn: &Connection,
+    workspace_id: &str,
+    skill_id: &str,
+) -> Result<(), ProjectionError> {
+    conn.execute(
+        "DELETE FROM proj_skills WHERE workspace_id = ?1 AND skill_id = ?2",
+        params![workspace_id, skill_id],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn insert_lease(conn: &Connection, lease: &LeaseEntry) -> Result<(), ProjectionError> {
+    let to_json = |value: Result<String, serde_json::Error>| {
+        value.map_err(|err| ProjectionError::Apply(err.to_string()))
+    };
+    let holder_json = to_json(serde_json::to_string(&lease.holder))?;
+    let scope_json = to_json(serde_json::to_string(&lease.scope))?;
+    conn.execute(
+        "INSERT INTO proj_leases (workspace_id, lease_id, holder_json, scope_json, reason, minted_by, expires_at, status, use_count, created_at, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        params![
+            lease.workspace_id,
+            lease.lease_id,
+            holder_json,
+            scope_json,
+            lease.reason,
+            lease.minted_by,
+            lease.expires_at,
+            lease.status.as_str(),
+            lease.use_count as i64,
+            lease.created_at,
+            lease.updated_at,
+            lease.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn record_lease_use(
+    conn: &Connection,
+    workspace_id: &str,
+    lease_id: &str,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_leases
+         SET use_count = use_count + 1, updated_at = ?3, seq_global = ?4
+         WHERE workspace_id = ?1 AND lease_id = ?2",
+        params![workspace_id, lease_id, updated_at, seq_global],
+        "lease",
+    )
+}
+
+fn end_lease(
+    conn: &Connection,
+    workspace_id: &str,
+    lease_id: &str,
+    status: LeaseStatus,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_leases
+         SET status = CASE WHEN status = 'active' THEN ?3 ELSE status END,
+             updated_at = ?4, seq_global = ?5
+         WHERE workspace_id = ?1 AND lease_id = ?2",
+        params![
+            workspace_id,
+            lease_id,
+            status.as_str(),
+            updated_at,
+            seq_global
+        ],
+        "lease",
+    )
+}
+
+fn create_vault(tx: &Transaction<'_>, vault: &VaultRecord) -> Result<(), StoreError> {
+    let kdf_json = serde_json::to_string(&vault.kdf).map_err(map_serde_err)?;
+    let inserted = tx
+        .execute(
+            "INSERT INTO vaults (workspace_id, kdf_json, salt, check_value, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT(workspace_id) DO NOTHING",
+            params![
+                vault.workspace_id,
+                kdf_json,
+                vault.salt,
+                vault.check,
+                vault.created_at,
+                vault.updated_at,
+            ],
+        )
+        .map_err(map_sql_err)?;
+    if inserted == 0 {
+        return Err(StoreError::Conflict(format!(
+            "workspace {} already has a vault",
+            vault.workspace_id
+        )));
+    }
+    Ok(())
+}
+
+fn rekey_vault(
+    tx: &Transaction<'_>,
+    vault: &VaultRecord,
+    rewrapped: &[SecretCiphertext],
+) -> Result<(), StoreError> {
+    let kdf_json = serde_json::to_string(&vault.kdf).map_err(map_serde_err)?;
+    let updated = tx
+        .execute(
+            "UPDATE vaults
+             SET kdf_json = ?2, salt = ?3, check_value = ?4, updated_at = ?5
+             WHERE workspace_id = ?1",
+            params![
+                vault.workspace_id,
+                kdf_json,
+                vault.salt,
+                vault.check,
+                vault.updated_at
+            ],
+        )
+        .map_err(map_sql_err)?;
+    if updated == 0 {
+        return Err(StoreError::NotFound(format!(
+            "vault for workspace {}",
+            vault.workspace_id
+        )));
+    }
+    for secret in rewrapped {
+        tx.execute(
+            "UPDATE secret_versions
+             SET wrapped_key = ?4
+             WHERE workspace_id = ?1 AND secret_id = ?2 AND version = ?3",
+            params![
+                secret.workspace_id,
+                secret.secret_id,
+                secret.version,
+                secret.wrapped_key
+            ],
+        )
+        .map_err(map_sql_err)?;
+    }
+    Ok(())
+}
+
+/// Derives the version a `secret.created` or `secret.rotated` event records from the projection
+/// the same transaction reads, then stores the value sealed for it. Other events pass through.
+fn seal_secret_version(
+    tx: &Transaction<'_>,
+    event: &mut NewEvent,
+    seal: &SealSecret<'_>,
+) -> Result<(), StoreError> {
+    let creates = event.event_type == EVENT_SECRET_CREATED;
+    if !creates && event.event_type != EVENT_SECRET_ROTATED {
+        return Ok(());
+    }
+    let secret_id = event.subject.id.clone();
+    let current: Option<u32> = tx
+        .query_row(
+            "SELECT version FROM proj_secrets WHERE workspace_id = ?1 AND secret_id = ?2",
+            params![event.workspace_id, secret_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_sql_err)?;
+    let version = match (creates, current) {
+        (true, None) => 1,
+        (false, Some(current)) => current + 1,
+        (true, Some(_)) => {
+            return Err(StoreError::Conflict(format!(
+                "secret {secret_id} already exists"
+            )))
+        }
+        (false, None) => return Err(StoreError::NotFound(format!("secret {secret_id}"))),
+    };
+    let Some(payload) = event.payload.as_object_mut() else {
+        return Err(StoreError::Invalid(format!(
+            "{} payload is not an object",
+            event.event_type
+        )));
+    };
+    payload.insert("version".to_string(), Value::from(version));
+    let sealed = seal(&event.workspace_id, &secret_id, version)?;
+    tx.execute(
+        "INSERT INTO secret_versions (workspace_id, secret_id, version, wrapped_key, ciphertext)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            sealed.workspace_id,
+            sealed.secret_id,
+            sealed.version,
+            sealed.wrapped_key,
+            sealed.ciphertext,
+        ],
+    )
+    .map_err(map_sql_err)?;
+    Ok(())
+}
+
+fn insert_secret(conn: &Connection, secret: &SecretEntry) -> Result<(), ProjectionError> {
+    conn.execute(
+        "INSERT INTO proj_secrets (workspace_id, secret_id, name, scope, scope_id, version, redacted, length, created_at, updated_at, seq_global)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            secret.workspace_id,
+            secret.secret_id,
+            secret.name,
+            secret.owner.scope.as_str(),
+            secret.owner.id.as_deref().unwrap_or_default(),
+            secret.version,
+            secret.redacted,
+            secret.length as i64,
+            secret.created_at,
+            secret.updated_at,
+            secret.seq_global,
+        ],
+    )
+    .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    Ok(())
+}
+
+fn rotate_secret(
+    conn: &Connection,
+    workspace_id: &str,
+    secret_id: &str,
+    version: u32,
+    length: usize,
+    updated_at: &str,
+    seq_global: i64,
+) -> Result<(), ProjectionError> {
+    update_one(
+        conn,
+        "UPDATE proj_secrets
+         SET version = ?3, length = ?4, updated_at = ?5, seq_global = ?6
+         WHERE workspace_id = ?1 AND secret_id = ?2",
+        params![
+            workspace_id,
+            secret_id,
+            version,
+            length as i64,
+            updated_at,
+            seq_global
+        ],
+        "secret",
+    )
+}
+
+/// Drops every stored version with the metadata, so a deleted secret cannot be recovered from
+/// the database.
+fn delete_secret(
+    conn: &Connection,
+    workspace_id: &str,
+    secret_id: &str,
+) -> Result<(), ProjectionError> {
+    for sql in [
+        "DELETE FROM proj_secrets WHERE workspace_id = ?1 AND secret_id = ?2",
+        "DELETE FROM secret_versions WHERE workspace_id = ?1 AND secret_id = ?2",
+    ] {
+        conn.execute(sql, params![workspace_id, secret_id])
+            .map_err(|err| ProjectionError::Apply(err.to_string()))?;
+    }
+    Ok(())
+}
+
 #[derive(Debug)]
 #[allow(dead_code)]
 struct IdempotencyRecord {
@@ -528,6 +3008,431 @@ fn row_to_event(row: &Row<'_>) -> Result<EventEnvelope, rusqlite::Error> {
     })
 }
 
+fn row_to_session(row: &Row<'_>) -> Result<SessionListEntry, rusqlite::Error> {
+    let fork_mode: Option<String> = row.get(8)?;
+    let fork_mode = fork_mode
+        .map(|mode| {
+            serde_json::from_value::<ForkMode>(Value::String(mode)).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    8,
+                    rusqlite::types::Type::Text,
+                    Box::new(err),
+                )
+            })
+        })
+        .transpose()?;
+    Ok(SessionListEntry {
+        session_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        project_id: row.get(2)?,
+        parent_session_id: row.get(3)?,
+        root_session_id: row.get(4)?,
+        lineage_path: row.get(5)?,
+        depth: row.get(6)?,
+        forked_from_session_id: row.get(7)?,
+        fork_mode,
+        label: row.get(9)?,
+        created_at: row.get(10)?,
+        seq_global: row.get(11)?,
+    })
+}
+
+fn row_to_task(row: &Row<'_>) -> Result<TaskListEntry, rusqlite::Error> {
+    let state: String = row.get(5)?;
+    let state = TaskState::parse(&state).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            5,
+            rusqlite::types::Type::Text,
+            format!("unknown task state {state}").into(),
+        )
+    })?;
+    let retry_json: Option<String> = row.get(8)?;
+    let retry = retry_json
+        .map(|raw| serde_json::from_str(&raw))
+        .transpose()
+        .map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, Box::new(err))
+        })?;
+    Ok(TaskListEntry {
+        task_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        project_id: row.get(2)?,
+        session_id: row.get(3)?,
+        title: row.get(4)?,
+        state,
+        deadline_at: row.get(6)?,
+        attempt: row.get(7)?,
+        retry,
+        timeout_ms: row.get(9)?,
+        attempt_started_at: row.get(10)?,
+        retry_at: row.get(11)?,
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+        seq_global: row.get(14)?,
+        current_stage: row.get(15)?,
+    })
+}
+
+fn row_to_pipeline_template(row: &Row<'_>) -> Result<PipelineTemplateEntry, rusqlite::Error> {
+    let stages_json: String = row.get(4)?;
+    let stages = serde_json::from_str(&stages_json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    Ok(PipelineTemplateEntry {
+        template_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        name: row.get(2)?,
+        version: row.get(3)?,
+        stages,
+        created_at: row.get(5)?,
+        seq_global: row.get(6)?,
+    })
+}
+
+/// Decisions are left empty; callers fill them from `proj_gate_decisions`.
+fn row_to_gate(row: &Row<'_>) -> Result<GateEntry, rusqlite::Error> {
+    let kind: String = row.get(5)?;
+    let kind = GateKind::parse(&kind).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            5,
+            rusqlite::types::Type::Text,
+            format!("unknown gate kind {kind}").into(),
+        )
+    })?;
+    let status: String = row.get(9)?;
+    let status = GateStatus::parse(&status).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            9,
+            rusqlite::types::Type::Text,
+            format!("unknown gate status {status}").into(),
+        )
+    })?;
+    let delay_ms: Option<i64> = row.get(7)?;
+    Ok(GateEntry {
+        gate_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        scope: GateScope {
+            command_type: row.get(2)?,
+            subject: Subject {
+                kind: row.get(3)?,
+                id: row.get(4)?,
+            },
+        },
+        kind,
+        required_approvals: row.get(6)?,
+        delay_ms: delay_ms.map(|delay_ms| delay_ms as u64),
+        description: row.get(8)?,
+        status,
+        decisions: Vec::new(),
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        seq_global: row.get(12)?,
+    })
+}
+
+fn row_to_worktree(row: &Row<'_>) -> Result<WorktreeEntry, rusqlite::Error> {
+    let lock_session_id: Option<String> = row.get(6)?;
+    let locked_at: Option<String> = row.get(7)?;
+    Ok(WorktreeEntry {
+        worktree_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        project_id: row.get(2)?,
+        path: row.get(3)?,
+        branch: row.get(4)?,
+        repo: row.get(5)?,
+        sessions: Vec::new(),
+        lock: lock_session_id
+            .zip(locked_at)
+            .map(|(session_id, acquired_at)| WorktreeLock {
+                session_id,
+                acquired_at,
+            }),
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        seq_global: row.get(10)?,
+    })
+}
+
+fn row_to_board_node(row: &Row<'_>) -> Result<BoardNode, rusqlite::Error> {
+    let x: Option<f64> = row.get(7)?;
+    let y: Option<f64> = row.get(8)?;
+    Ok(BoardNode {
+        node_id: row.get(0)?,
+        workspace_id: row.get(1)?,
+        project_id: row.get(2)?,
+        kind: row.get(3)?,
+        subject_id: row.get(4)?,
+        label: row.get(5)?,
+        body: row.get(6)?,
+        position: x.zip(y).map(|(x, y)| BoardPosition { x, y }),
+        group_node_id: row.get(9)?,
+        created_at: row.get(10)?,
+        updated_at: row.get(11)?,
+        seq_global: row.get(12)?,
+    })
+}
+
+fn row_to_artifact(row: &Row<'_>) -> Result<ArtifactEntry, rusqlite::Error> {
+    let metadata_json: String = row.get(4)?;
+    let metadata = serde_json::from_str(&metadata_json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(4, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    Ok(ArtifactEntry {
+        hash: row.get(0)?,
+        size_bytes: row.get(1)?,
+        mime_type: row.get(2)?,
+        created_at: row.get(3)?,
+        metadata,
+    })
+}
+
+fn row_to_capsule(row: &Row<'_>) -> Result<CapsuleEntry, rusqlite::Error> {
+    let kind: String = row.get(3)?;
+    let kind = CapsuleKind::parse(&kind).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            3,
+            rusqlite::types::Type::Text,
+            format!("unknown capsule kind {kind}").into(),
+        )
+    })?;
+    Ok(CapsuleEntry {
+        workspace_id: row.get(0)?,
+        subject: Subject {
+            kind: row.get(1)?,
+            id: row.get(2)?,
+        },
+        kind,
+        version: row.get(4)?,
+        content: row.get(5)?,
+        chars: row.get(6)?,
+        created_at: row.get(7)?,
+        seq_global: row.get(8)?,
+    })
+}
+
+fn row_to_blueprint(row: &Row<'_>) -> Result<BlueprintEntry, rusqlite::Error> {
+    let blueprint_json: String = row.get(6)?;
+    let blueprint = serde_json::from_str(&blueprint_json).map_err(|err| {
+        rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(err))
+    })?;
+    Ok(BlueprintEntry {
+        workspace_id: row.get(0)?,
+        blueprint_id: row.get(1)?,
+        version: row.get(2)?,
+        name: row.get(3)?,
+        path: row.get(4)?,
+        content_hash: row.get(5)?,
+        blueprint,
+        created_at: row.get(7)?,
+        seq_global: row.get(8)?,
+    })
+}
+
+fn row_to_tool_call(row: &Row<'_>) -> Result<ToolCallEntry, rusqlite::Error> {
+    let json_column = |index: usize| -> Result<Option<Value>, rusqlite::Error> {
+        let raw: Option<String> = row.get(index)?;
+        raw.map(|raw| {
+            serde_json::from_str(&raw).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(
+                    index,
+                    rusqlite::types::Type::Text,
+                    Box::new(err),
+                )
+            })
+        })
+        .transpose()
+    };
+    let side_effect: String = row.get(4)?;
+    let side_effect = SideEffect::parse(&side_effect).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            4,
+            rusqlite::types::Type::Text,
+            format!("unknown side effect {side_effect}").into(),
+        )
+    })?;
+    let error = json_column(9)?
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(9, rusqlite::types::Type::Text, Box::new(err))
+        })?;
+    let status: String = row.get(7)?;
+    let status = ToolCallStatus::parse(&status).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            7,
+            rusqlite::types::Type::Text,
+            format!("unknown tool call status {status}").into(),
+        )
+    })?;
+    Ok(ToolCallEntry {
+        workspace_id: row.get(0)?,
+        request_id: row.get(1)?,
+        tool_id: row.get(2)?,
+        tool_version: row.get(3)?,
+        side_effect,
+        input: json_column(5)?.unwrap_or(Value::Null),
+        session_id: row.get(6)?,
+        status,
+        output: json_column(8)?,
+        error,
+        requested_at: row.get(10)?,
+        completed_at: row.get(11)?,
+        seq_global: row.get(12)?,
+    })
+}
+
+fn row_to_lease(row: &Row<'_>) -> Result<LeaseEntry, rusqlite::Error> {
+    let json_error = |index: usize| {
+        move |err: serde_json::Error| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(err),
+            )
+        }
+    };
+    let holder: String = row.get(2)?;
+    let scope: String = row.get(3)?;
+    let status: String = row.get(7)?;
+    let status = LeaseStatus::parse(&status).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            7,
+            rusqlite::types::Type::Text,
+            format!("unknown lease status {status}").into(),
+        )
+    })?;
+    let use_count: i64 = row.get(8)?;
+    Ok(LeaseEntry {
+        workspace_id: row.get(0)?,
+        lease_id: row.get(1)?,
+        holder: serde_json::from_str(&holder).map_err(json_error(2))?,
+        scope: serde_json::from_str(&scope).map_err(json_error(3))?,
+        reason: row.get(4)?,
+        minted_by: row.get(5)?,
+        expires_at: row.get(6)?,
+        status,
+        use_count: use_count as u64,
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+        seq_global: row.get(11)?,
+    })
+}
+
+fn row_to_secret(row: &Row<'_>) -> Result<SecretEntry, rusqlite::Error> {
+    let scope: String = row.get(3)?;
+    let scope = SecretScope::parse(&scope).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            3,
+            rusqlite::types::Type::Text,
+            format!("unknown secret scope {scope}").into(),
+        )
+    })?;
+    let scope_id: String = row.get(4)?;
+    let length: i64 = row.get(7)?;
+    Ok(SecretEntry {
+        workspace_id: row.get(0)?,
+        secret_id: row.get(1)?,
+        name: row.get(2)?,
+        owner: SecretOwner {
+            scope,
+            id: (!scope_id.is_empty()).then_some(scope_id),
+        },
+        version: row.get(5)?,
+        redacted: row.get(6)?,
+        length: length as usize,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+        seq_global: row.get(10)?,
+    })
+}
+
+fn row_to_vault(row: &Row<'_>) -> Result<VaultRecord, rusqlite::Error> {
+    let kdf: String = row.get(1)?;
+    Ok(VaultRecord {
+        workspace_id: row.get(0)?,
+        kdf: serde_json::from_str(&kdf).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, Box::new(err))
+        })?,
+        salt: row.get(2)?,
+        check: row.get(3)?,
+        created_at: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn row_to_secret_ciphertext(row: &Row<'_>) -> Result<SecretCiphertext, rusqlite::Error> {
+    Ok(SecretCiphertext {
+        workspace_id: row.get(0)?,
+        secret_id: row.get(1)?,
+        version: row.get(2)?,
+        wrapped_key: row.get(3)?,
+        ciphertext: row.get(4)?,
+    })
+}
+
+fn row_to_skill(row: &Row<'_>) -> Result<SkillEntry, rusqlite::Error> {
+    let json_error = |index: usize| {
+        move |err: serde_json::Error| {
+            rusqlite::Error::FromSqlConversionFailure(
+                index,
+                rusqlite::types::Type::Text,
+                Box::new(err),
+            )
+        }
+    };
+    let signature: String = row.get(7)?;
+    let frontmatter: String = row.get(8)?;
+    let components: String = row.get(9)?;
+    let scope: String = row.get(3)?;
+    let scope = SkillScope::parse(&scope).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            3,
+            rusqlite::types::Type::Text,
+            format!("unknown skill scope {scope}").into(),
+        )
+    })?;
+    let trust: String = row.get(6)?;
+    let trust = SkillTrust::parse(&trust).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            6,
+            rusqlite::types::Type::Text,
+            format!("unknown skill trust {trust}").into(),
+        )
+    })?;
+    Ok(SkillEntry {
+        workspace_id: row.get(0)?,
+        skill_id: row.get(1)?,
+        revision: row.get(2)?,
+        scope,
+        path: row.get(4)?,
+        content_hash: row.get(5)?,
+        trust,
+        signature: serde_json::from_str(&signature).map_err(json_error(7))?,
+        frontmatter: serde_json::from_str(&frontmatter).map_err(json_error(8))?,
+        components: serde_json::from_str(&components).map_err(json_error(9))?,
+        approved_hash: row.get(10)?,
+        updated_at: row.get(11)?,
+        seq_global: row.get(12)?,
+    })
+}
+
+fn row_to_board_edge(row: &Row<'_>) -> Result<BoardEdge, rusqlite::Error> {
+    let kind: String = row.get(0)?;
+    let kind = BoardEdgeKind::parse(&kind).ok_or_else(|| {
+        rusqlite::Error::FromSqlConversionFailure(
+            0,
+            rusqlite::types::Type::Text,
+            format!("unknown board edge kind {kind}").into(),
+        )
+    })?;
+    Ok(BoardEdge {
+        kind,
+        from_node_id: row.get(1)?,
+        to_node_id: row.get(2)?,
+        seq_global: row.get(3)?,
+    })
+}
+
 fn map_sql_err(err: rusqlite::Error) -> StoreError {
     StoreError::Internal(err.to_string())
 }
@@ -536,6 +3441,10 @@ fn map_serde_err(err: serde_json::Error) -> StoreError {
     StoreError::Internal(err.to_string())
 }
 
+fn map_artifact_io_err(err: std::io::Error) -> StoreError {
+    StoreError::Internal(format!("artifact io: {err}"))
+}
+
 fn map_proj_err(err: ProjectionError) -> StoreError {
     StoreError::Internal(err.to_string())
 }
@@ -544,10 +3453,20 @@ fn map_proj_err(err: ProjectionError) -> StoreError {
 mod tests {
     use super::*;
     use mp_kernel::{
-        Actor, ProjectCreatedPayload, WorkspaceCreatedPayload, EVENT_PROJECT_CREATED,
-        EVENT_WORKSPACE_CREATED,
+        Actor, ProjectCreatedPayload, SessionForkedPayload, SessionSpawnedPayload,
+        TaskCreatedPayload, TaskTransitionedPayload, WorkspaceCreatedPayload,
+        EVENT_BLUEPRINT_REGISTERED, EVENT_BLUEPRINT_VERSION_CREATED, EVENT_BOARD_NODE_MOVED,
+        EVENT_CAPSULE_WRITTEN, EVENT_GATE_APPROVED, EVENT_GATE_DEFINED, EVENT_GATE_REJECTED,
+        EVENT_LEASE_EXPIRED, EVENT_LEASE_MINTED, EVENT_LEASE_USED, EVENT_PIPELINE_BOUND,
+        EVENT_PIPELINE_TEMPLATE_DEFINED, EVENT_PROJECT_ARCHIVED, EVENT_PROJECT_CREATED,
+        EVENT_PROJECT_RENAMED, EVENT_PROJECT_RESTORED, EVENT_SECRET_CREATED, EVENT_SECRET_DELETED,
+        EVENT_SECRET_ROTATED, EVENT_SESSION_FORKED, EVENT_SESSION_SPAWNED, EVENT_SKILL_APPROVED,
+        EVENT_SKILL_REGISTERED, EVENT_SKILL_REMOVED, EVENT_SKILL_UPDATED, EVENT_TASK_CREATED,
+        EVENT_TASK_STARTED, EVENT_TOOL_REQUESTED, EVENT_TOOL_RESULT, EVENT_WORKSPACE_CREATED,
+        EVENT_WORKTREE_ATTACHED, EVENT_WORKTREE_DETACHED, EVENT_WORKTREE_LOCK_ACQUIRED,
+        EVENT_WORKTREE_REGISTERED,
     };
-    use mp_storage::{CommandMeta, NewEvent};
+    use mp_storage::{ArtifactStore, CommandMeta, NewEvent, SecretStore};
     use rusqlite::Connection;
     use tempfile::TempDir;
 
@@ -609,6 +3528,23 @@ mod tests {
         }
     }
 
+    fn session_event(event_type: &str, session_id: &str, payload: Value) -> NewEvent {
+        NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "session".to_string(),
+                id: session_id.to_string(),
+            },
+            payload,
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        }
+    }
+
     #[test]
     fn append_and_read_from_persists_events() {
         let (_dir, mut store) = temp_store();
@@ -653,6 +3589,35 @@ mod tests {
         assert_eq!(first.events[0].event_id, second.events[0].event_id);
         assert_eq!(first.events[0].seq_global, second.events[0].seq_global);
         assert_eq!(store.head_seq("w1").unwrap(), 1);
+
+        let replayed = store
+            .replay_idempotent("ik_test", "workspace.create")
+            .expect("replay")
+            .expect("recorded");
+        assert_eq!(replayed[0].event_id, first.events[0].event_id);
+        assert!(store
+            .replay_idempotent("ik_test", "project.create")
+            .expect("replay")
+            .is_none());
+    }
+
+    #[test]
+    fn append_rejects_batches_that_mix_workspaces() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("workspace.create", Some("ik_mixed"));
+        let events = vec![
+            workspace_event("global", "alpha", "/tmp/alpha"),
+            workspace_event("w1", "beta", "/tmp/beta"),
+        ];
+
+        let err = store.append(&meta, events).expect_err("mixed batch");
+        assert!(matches!(err, StoreError::Invalid(_)));
+        assert_eq!(store.head_seq("global").unwrap(), 0);
+        assert_eq!(store.head_seq("w1").unwrap(), 0);
+        assert!(store
+            .replay_idempotent("ik_mixed", "workspace.create")
+            .expect("replay")
+            .is_none());
     }
 
     #[test]
@@ -681,11 +3646,11 @@ mod tests {
         ];
         store.append(&meta, events).expect("append");
 
-        let workspaces = store.list_workspaces().expect("workspaces");
+        let workspaces = store.list_workspaces(false).expect("workspaces");
         assert_eq!(workspaces.len(), 1);
         assert_eq!(workspaces[0].name, "alpha");
 
-        let projects = store.list_projects("w1").expect("projects");
+        let projects = store.list_projects("w1", false).expect("projects");
         assert_eq!(projects.len(), 1);
         assert_eq!(projects[0].name, "core");
 
@@ -698,7 +3663,1051 @@ mod tests {
         .expect("corrupt");
 
         store.rebuild_projections().expect("rebuild");
-        let workspaces = store.list_workspaces().expect("workspaces");
+        let workspaces = store.list_workspaces(false).expect("workspaces");
         assert_eq!(workspaces[0].name, "alpha");
     }
+
+    #[test]
+    fn archived_projects_are_hidden_unless_requested() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("project.archive", None);
+        let lifecycle = |event_type: &str, payload: Value| {
+            let mut event = project_event("w1", "p1", "core");
+            event.event_type = event_type.to_string();
+            event.payload = payload;
+            event
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    project_event("w1", "p1", "core"),
+                    project_event("w1", "p2", "docs"),
+                    lifecycle(
+                        EVENT_PROJECT_RENAMED,
+                        serde_json::json!({"previous_name": "core", "name": "kernel"}),
+                    ),
+                    lifecycle(
+                        EVENT_PROJECT_ARCHIVED,
+                        serde_json::json!({"reason": "merged"}),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let visible = store.list_projects("w1", false).expect("projects");
+        assert_eq!(visible.len(), 1);
+        assert_eq!(visible[0].project_id, "p2");
+        let all = store.list_projects("w1", true).expect("projects");
+        assert_eq!(all.len(), 2);
+        let archived = all.iter().find(|project| project.project_id == "p1");
+        assert_eq!(
+            archived.map(|project| project.name.as_str()),
+            Some("kernel")
+        );
+        assert!(archived
+            .and_then(|project| project.archived_at.as_ref())
+            .is_some());
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_projects("w1", false).expect("projects").len(), 1);
+
+        store
+            .append(
+                &meta,
+                vec![lifecycle(EVENT_PROJECT_RESTORED, serde_json::json!({}))],
+            )
+            .expect("append");
+        assert_eq!(store.list_projects("w1", false).expect("projects").len(), 2);
+    }
+
+    #[test]
+    fn board_projection_tracks_nodes_edges_and_layout() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("session.spawn", None);
+        let mut moved = session_event(
+            EVENT_BOARD_NODE_MOVED,
+            "s2",
+            serde_json::json!({"position": {"x": 40.0, "y": -12.5}, "group_node_id": "group:g1"}),
+        );
+        moved.project_id = Some("p1".to_string());
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    project_event("w1", "p1", "core"),
+                    session_event(
+                        EVENT_SESSION_SPAWNED,
+                        "s1",
+                        serde_json::json!({"root_session_id": "s1", "lineage_path": "/s1"}),
+                    ),
+                    session_event(
+                        EVENT_SESSION_SPAWNED,
+                        "s2",
+                        serde_json::json!({
+                            "parent_session_id": "s1",
+                            "root_session_id": "s1",
+                            "lineage_path": "/s1/s2",
+                            "label": "reviewer"
+                        }),
+                    ),
+                    moved,
+                ],
+            )
+            .expect("append");
+
+        let board = store.get_board("w1").expect("board");
+        assert_eq!(board.seq_global, 5);
+        assert_eq!(board.nodes.len(), 4);
+        let project = board.node("project:p1").expect("project node");
+        assert_eq!(project.label.as_deref(), Some("core"));
+        let session = board.node("session:s2").expect("session node");
+        assert_eq!(session.label.as_deref(), Some("reviewer"));
+        assert_eq!(session.position, Some(BoardPosition { x: 40.0, y: -12.5 }));
+        assert_eq!(session.group_node_id.as_deref(), Some("group:g1"));
+        assert_eq!(session.project_id.as_deref(), Some("p1"));
+        assert_eq!(board.edges.len(), 1);
+        assert_eq!(board.edges[0].kind, BoardEdgeKind::Lineage);
+        assert_eq!(board.edges[0].from_node_id, "session:s1");
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.get_board("w1").expect("board"), board);
+        assert!(store.get_board("w2").expect("board").nodes.is_empty());
+    }
+
+    #[test]
+    fn artifact_store_dedups_identical_content() {
+        let (dir, store) = temp_store();
+        let store = store.with_artifact_dir(dir.path().join("data").join("artifacts"));
+        let log = b"error[E0382]: borrow of moved value".to_vec();
+        let metadata = serde_json::json!({"source": "cargo build"});
+
+        let first = store
+            .put_artifact(&mut log.as_slice(), Some("text/plain"), &metadata)
+            .expect("put");
+        assert!(!first.deduplicated);
+        assert_eq!(
+            first.entry.hash,
+            format!("blake3:{}", blake3::hash(&log).to_hex())
+        );
+        assert_eq!(first.entry.size_bytes, log.len() as u64);
+        assert_eq!(first.entry.metadata, metadata);
+
+        let second = store
+            .put_artifact(&mut log.as_slice(), None, &serde_json::json!({}))
+            .expect("put again");
+        assert!(second.deduplicated);
+        assert_eq!(second.entry, first.entry);
+
+        let path = store
+            .artifact_path(&first.entry.hash)
+            .expect("path")
+            .expect("stored blob");
+        assert!(path.starts_with(dir.path().join("data").join("artifacts")));
+        assert_eq!(std::fs::read(path).expect("read blob"), log);
+        let staging = dir.path().join("data").join("artifacts").join("staging");
+        assert_eq!(std::fs::read_dir(staging).expect("staging").count(), 0);
+
+        let missing = format!("blake3:{}", "0".repeat(64));
+        assert!(store.artifact_path(&missing).expect("path").is_none());
+        assert!(store.artifact_path("blake3:../../etc").is_err());
+    }
+
+    #[test]
+    fn capsule_projection_keeps_versions_per_subject_and_kind() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("capsule.write", None);
+        let capsule = |session_id: &str, kind: &str, version: u32, content: &str| {
+            session_event(
+                EVENT_CAPSULE_WRITTEN,
+                session_id,
+                serde_json::json!({
+                    "kind": kind,
+                    "version": version,
+                    "content": content,
+                    "chars": content.chars().count()
+                }),
+            )
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    capsule("s1", "status", 1, "starting"),
+                    capsule("s1", "plan", 1, "1. read 2. write"),
+                    capsule("s1", "status", 2, "halfway"),
+                    capsule("s2", "status", 1, "idle"),
+                ],
+            )
+            .expect("append");
+
+        let latest = store
+            .list_capsules("w1", None, None, false)
+            .expect("capsules");
+        let contents: Vec<&str> = latest.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents, vec!["1. read 2. write", "halfway", "idle"]);
+
+        let s1 = Subject {
+            kind: "session".to_string(),
+            id: "s1".to_string(),
+        };
+        let history = store
+            .list_capsules("w1", Some(&s1), Some(CapsuleKind::Status), true)
+            .expect("capsules");
+        assert_eq!(
+            history.iter().map(|c| c.version).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(history[1].chars, 7);
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(
+            store
+                .list_capsules("w1", None, None, false)
+                .expect("capsules"),
+            latest
+        );
+    }
+
+    #[test]
+    fn blueprint_projection_resolves_exact_versions() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("blueprint.sync", None);
+        let version = |event_type: &str, version: u32, name: &str| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "blueprint".to_string(),
+                id: "reviewer".to_string(),
+            },
+            payload: serde_json::json!({
+                "blueprint_id": "reviewer",
+                "version": version,
+                "name": name,
+                "path": "agents/blueprints/reviewer.yaml",
+                "content_hash": format!("blake3:{}", blake3::hash(name.as_bytes()).to_hex()),
+                "blueprint": {
+                    "api_version": 1,
+                    "id": "reviewer",
+                    "name": name,
+                    "runtime": {"kind": "internal", "adapter": "mp"}
+                }
+            }),
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    version(EVENT_BLUEPRINT_REGISTERED, 1, "Reviewer"),
+                    version(EVENT_BLUEPRINT_VERSION_CREATED, 2, "Strict reviewer"),
+                ],
+            )
+            .expect("append");
+
+        let latest = store.list_blueprints("w1", false).expect("blueprints");
+        assert_eq!(latest.len(), 1);
+        assert_eq!(latest[0].version, 2);
+        assert_eq!(store.list_blueprints("w1", true).expect("all").len(), 2);
+        let pinned = store
+            .get_blueprint("w1", "reviewer", Some(1))
+            .expect("get")
+            .expect("version 1");
+        assert_eq!(pinned.blueprint.name, "Reviewer");
+        assert_eq!(
+            store.get_blueprint("w1", "reviewer", None).expect("get"),
+            Some(latest[0].clone())
+        );
+        assert!(store
+            .get_blueprint("w1", "reviewer", Some(3))
+            .expect("get")
+            .is_none());
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_blueprints("w1", false).expect("rebuilt"), latest);
+    }
+
+    #[test]
+    fn tool_call_projection_settles_results_and_filters() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("tool.request", None);
+        let tool_event = |event_type: &str, request_id: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "tool_call".to_string(),
+                id: request_id.to_string(),
+            },
+            payload,
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        let requested = |request_id: &str, session_id: &str| {
+            tool_event(
+                EVENT_TOOL_REQUESTED,
+                request_id,
+                serde_json::json!({
+                    "tool_id": "acme.add",
+                    "tool_version": "1.0.0",
+                    "side_effect": "pure",
+                    "input": {"a": 1, "b": 2},
+                    "session_id": session_id
+                }),
+            )
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    requested("r1", "s1"),
+                    requested("r2", "s2"),
+                    tool_event(
+                        EVENT_TOOL_RESULT,
+                        "r1",
+                        serde_json::json!({
+                            "tool_id": "acme.add",
+                            "status": "failed",
+                            "error": {"code": "overflow", "message": "too large"}
+                        }),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let calls = store.list_tool_calls("w1", None, None).expect("calls");
+        assert_eq!(calls.len(), 2);
+        let failed = store.get_tool_call("w1", "r1").expect("get").expect("r1");
+        assert_eq!(failed.status, ToolCallStatus::Failed);
+        assert_eq!(
+            failed.error.as_ref().map(|e| e.code.as_str()),
+            Some("overflow")
+        );
+        assert!(failed.completed_at.is_some());
+        let pending = store
+            .list_tool_calls("w1", Some(ToolCallStatus::Pending), None)
+            .expect("pending");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].request_id, "r2");
+        assert_eq!(pending[0].input, serde_json::json!({"a": 1, "b": 2}));
+        assert_eq!(
+            store
+                .list_tool_calls("w1", None, Some("s1"))
+                .expect("by session")
+                .len(),
+            1
+        );
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(
+            store.list_tool_calls("w1", None, None).expect("rebuilt"),
+            calls
+        );
+    }
+
+    #[test]
+    fn skill_projection_keeps_approval_across_revisions() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("skill.sync", None);
+        let skill_event = |event_type: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "skill".to_string(),
+                id: "pdf-tools".to_string(),
+            },
+            payload,
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        let version = |revision: u32, hash: &str| {
+            serde_json::json!({
+                "skill_id": "pdf-tools",
+                "revision": revision,
+                "scope": "workspace",
+                "path": "agents/skills/pdf-tools",
+                "content_hash": hash,
+                "trust": "proposed",
+                "signature": {"status": "unsigned"},
+                "frontmatter": {"name": "pdf-tools", "description": "Reads PDFs."},
+                "components": {"wasm": ["wasm/pdf.wasm"]}
+            })
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    skill_event(EVENT_SKILL_REGISTERED, version(1, "h1")),
+                    skill_event(
+                        EVENT_SKILL_APPROVED,
+                        serde_json::json!({"skill_id": "pdf-tools", "content_hash": "h1"}),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let approved = store
+            .get_skill("w1", "pdf-tools")
+            .expect("get")
+            .expect("skill");
+        assert_eq!(approved.trust, SkillTrust::Sandboxed);
+        assert_eq!(approved.approved_hash.as_deref(), Some("h1"));
+        assert_eq!(approved.components.wasm, vec!["wasm/pdf.wasm"]);
+
+        store
+            .append(
+                &meta,
+                vec![skill_event(EVENT_SKILL_UPDATED, version(2, "h2"))],
+            )
+            .expect("update");
+        let changed = store.list_skills("w1", None).expect("list");
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].revision, 2);
+        assert_eq!(changed[0].trust, SkillTrust::Proposed);
+        assert_eq!(changed[0].approved_hash.as_deref(), Some("h1"));
+        assert!(store
+            .list_skills("w1", Some(SkillTrust::Sandboxed))
+            .expect("filtered")
+            .is_empty());
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_skills("w1", None).expect("rebuilt"), changed);
+
+        store
+            .append(
+                &meta,
+                vec![skill_event(
+                    EVENT_SKILL_REMOVED,
+                    serde_json::json!({"skill_id": "pdf-tools"}),
+                )],
+            )
+            .expect("remove");
+        assert!(store.get_skill("w1", "pdf-tools").expect("get").is_none());
+    }
+
+    #[test]
+    fn lease_projection_tracks_status_and_rebuilds() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("lease.mint", None);
+        let lease_event = |event_type: &str, lease_id: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "lease".to_string(),
+                id: lease_id.to_string(),
+            },
+            payload,
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        let minted = |expires_at: &str| {
+            serde_json::json!({
+                "holder": {"kind": "session", "id": "s1"},
+                "scope": {"actions": ["tool.request"], "attributes": {"payload.tool_id": ["net.fetch"]}},
+                "ttl_ms": 3_600_000,
+                "expires_at": expires_at,
+                "reason": "network exception"
+            })
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    workspace_event("w1", "alpha", "/tmp/alpha"),
+                    lease_event(EVENT_LEASE_MINTED, "l2", minted("2030-01-01T02:00:00Z")),
+                    lease_event(EVENT_LEASE_MINTED, "l1", minted("2030-01-01T01:00:00Z")),
+                    lease_event(
+                        EVENT_LEASE_USED,
+                        "l1",
+                        serde_json::json!({"command_type": "tool.request"}),
+                    ),
+                    lease_event(
+                        EVENT_LEASE_EXPIRED,
+                        "l2",
+                        serde_json::json!({"expires_at": "2030-01-01T02:00:00Z"}),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let leases = store.list_leases("w1", None).expect("list");
+        let ids: Vec<&str> = leases.iter().map(|lease| lease.lease_id.as_str()).collect();
+        assert_eq!(ids, ["l1", "l2"]);
+        assert_eq!(leases[0].use_count, 1);
+        assert_eq!(
+            leases[0].scope.attributes["payload.tool_id"],
+            vec!["net.fetch"]
+        );
+        assert_eq!(leases[1].status, LeaseStatus::Expired);
+        let active = store
+            .list_leases("w1", Some(LeaseStatus::Active))
+            .expect("active");
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].lease_id, "l1");
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_leases("w1", None).expect("rebuilt"), leases);
+        assert!(store.get_lease("w1", "missing").expect("get").is_none());
+    }
+
+    #[test]
+    fn secret_store_keeps_ciphertext_until_the_secret_is_deleted() {
+        let (_dir, mut store) = temp_store();
+        store
+            .append(
+                &command_meta("workspace.create", None),
+                vec![workspace_event("w1", "alpha", "/tmp/alpha")],
+            )
+            .expect("workspace");
+        let vault = VaultRecord {
+            workspace_id: "w1".to_string(),
+            kdf: mp_kernel::VaultKdf {
+                m_cost_kib: 64,
+                t_cost: 1,
+                p_cost: 1,
+            },
+            salt: vec![1; 16],
+            check: vec![2; 40],
+            created_at: "2020-01-01T00:00:00Z".to_string(),
+            updated_at: "2020-01-01T00:00:00Z".to_string(),
+        };
+        let vault_event = |event_type: &str| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "vault".to_string(),
+                id: "w1".to_string(),
+            },
+            payload: serde_json::json!({}),
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        store
+            .append_with_vault(
+                &command_meta("vault.init", None),
+                vec![vault_event("vault.initialized")],
+                VaultWrite::Create(&vault),
+            )
+            .expect("create vault");
+        let head = store.head_seq("w1").expect("head");
+        assert!(matches!(
+            store.append_with_vault(
+                &command_meta("vault.init", None),
+                vec![vault_event("vault.initialized")],
+                VaultWrite::Create(&vault),
+            ),
+            Err(StoreError::Conflict(_))
+        ));
+        assert_eq!(store.head_seq("w1").expect("head"), head);
+
+        let ciphertext = |secret_id: &str, version: u32| SecretCiphertext {
+            workspace_id: "w1".to_string(),
+            secret_id: secret_id.to_string(),
+            version,
+            wrapped_key: vec![version as u8; 60],
+            ciphertext: vec![9; 28],
+        };
+        let seal = |_: &str, secret_id: &str, version: u32| Ok(ciphertext(secret_id, version));
+        let secret_event = |event_type: &str, secret_id: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "secret".to_string(),
+                id: secret_id.to_string(),
+            },
+            payload,
+            trace_id: Some("tr_evt".to_string()),
+            stream_id: None,
+        };
+        let version = |name: &str, owner: Value, version: u32| {
+            serde_json::json!({
+                "name": name,
+                "owner": owner,
+                "version": version,
+                "redacted": "****",
+                "length": 12
+            })
+        };
+        let global = serde_json::json!({"scope": "global"});
+        let user = serde_json::json!({"scope": "user", "id": "alice"});
+        let mut seal_one = |event: NewEvent| {
+            store.append_with_vault(
+                &command_meta("secret.set", None),
+                vec![event],
+                VaultWrite::SealVersion(&seal),
+            )
+        };
+        seal_one(secret_event(
+            EVENT_SECRET_CREATED,
+            "sec1",
+            version("API_KEY", user.clone(), 1),
+        ))
+        .expect("create sec1");
+        seal_one(secret_event(
+            EVENT_SECRET_CREATED,
+            "sec2",
+            version("API_KEY", global.clone(), 1),
+        ))
+        .expect("create sec2");
+        // The stored version comes from the projection, whatever the event claimed.
+        let rotated = seal_one(secret_event(
+            EVENT_SECRET_ROTATED,
+            "sec1",
+            version("API_KEY", user.clone(), 7),
+        ))
+        .expect("rotate sec1");
+        assert_eq!(rotated.events[0].payload["version"], 2);
+        assert!(matches!(
+            seal_one(secret_event(
+                EVENT_SECRET_CREATED,
+                "sec1",
+                version("API_KEY", user.clone(), 1),
+            )),
+            Err(StoreError::Conflict(_))
+        ));
+        assert!(matches!(
+            seal_one(secret_event(
+                EVENT_SECRET_ROTATED,
+                "missing",
+                version("API_KEY", user, 2),
+            )),
+            Err(StoreError::NotFound(_))
+        ));
+        let stored: Vec<(String, u32)> = store
+            .list_secret_versions("w1")
+            .expect("versions")
+            .into_iter()
+            .map(|version| (version.secret_id, version.version))
+            .collect();
+        assert_eq!(
+            stored,
+            [
+                ("sec1".to_string(), 1),
+                ("sec1".to_string(), 2),
+                ("sec2".to_string(), 1)
+            ]
+        );
+
+        let rekeyed = VaultRecord {
+            salt: vec![3; 16],
+            updated_at: "2020-01-02T00:00:00Z".to_string(),
+            ..vault.clone()
+        };
+        let rewrapped = SecretCiphertext {
+            wrapped_key: vec![7; 60],
+            ..ciphertext("sec1", 2)
+        };
+        store
+            .append_with_vault(
+                &command_meta("vault.unlock", None),
+                vec![vault_event("vault.unlocked")],
+                VaultWrite::Rekey(&rekeyed, std::slice::from_ref(&rewrapped)),
+            )
+            .expect("rekey");
+        assert_eq!(store.get_vault("w1").expect("vault"), Some(rekeyed));
+        assert_eq!(
+            store.get_secret_version("w1", "sec1", 2).expect("get"),
+            Some(rewrapped)
+        );
+
+        let secrets = store.list_secrets("w1").expect("list");
+        let ids: Vec<&str> = secrets
+            .iter()
+            .map(|secret| secret.secret_id.as_str())
+            .collect();
+        assert_eq!(ids, ["sec2", "sec1"]);
+        assert_eq!(secrets[0].owner.id, None);
+        assert_eq!(secrets[1].owner.id.as_deref(), Some("alice"));
+        assert_eq!(secrets[1].version, 2);
+
+        store
+            .append(
+                &command_meta("secret.delete", None),
+                vec![secret_event(
+                    EVENT_SECRET_DELETED,
+                    "sec1",
+                    serde_json::json!({"name": "API_KEY", "owner": {"scope": "user", "id": "alice"}}),
+                )],
+            )
+            .expect("delete");
+        assert!(store.get_secret("w1", "sec1").expect("get").is_none());
+        let remaining: Vec<String> = store
+            .list_secret_versions("w1")
+            .expect("versions")
+            .into_iter()
+            .map(|version| version.secret_id)
+            .collect();
+        assert_eq!(remaining, ["sec2"]);
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_secrets("w1").expect("rebuilt"), secrets[..1]);
+    }
+
+    #[test]
+    fn session_lineage_projection_orders_tree_and_rebuilds() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("session.spawn", None);
+        let root = session_event(
+            EVENT_SESSION_SPAWNED,
+            "s1",
+            serde_json::to_value(SessionSpawnedPayload {
+                parent_session_id: None,
+                root_session_id: "s1".to_string(),
+                lineage_path: "/s1".to_string(),
+                label: Some("planner".to_string()),
+                blueprint: None,
+            })
+            .expect("payload"),
+        );
+        let other_root = session_event(
+            EVENT_SESSION_SPAWNED,
+            "s0",
+            serde_json::to_value(SessionSpawnedPayload {
+                parent_session_id: None,
+                root_session_id: "s0".to_string(),
+                lineage_path: "/s0".to_string(),
+                label: None,
+                blueprint: None,
+            })
+            .expect("payload"),
+        );
+        let fork = session_event(
+            EVENT_SESSION_FORKED,
+            "s2",
+            serde_json::to_value(SessionForkedPayload {
+                forked_from_session_id: "s1".to_string(),
+                mode: ForkMode::SameBranch,
+                parent_session_id: "s1".to_string(),
+                root_session_id: "s1".to_string(),
+                lineage_path: "/s1/s2".to_string(),
+                label: None,
+            })
+            .expect("payload"),
+        );
+        store
+            .append(&meta, vec![root, other_root, fork])
+            .expect("append");
+
+        let tree = store.list_sessions("w1", Some("s1")).expect("sessions");
+        let ids: Vec<_> = tree.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(ids, vec!["s1", "s2"]);
+        assert_eq!(tree[1].fork_mode, Some(ForkMode::SameBranch));
+        assert_eq!(tree[1].depth, 1);
+        assert_eq!(store.list_sessions("w1", None).expect("all").len(), 3);
+
+        store.rebuild_projections().expect("rebuild");
+        let fork = store
+            .get_session("w1", "s2")
+            .expect("get")
+            .expect("session exists");
+        assert_eq!(fork.parent_session_id.as_deref(), Some("s1"));
+        assert_eq!(fork.forked_from_session_id.as_deref(), Some("s1"));
+        assert!(store.get_session("w2", "s2").expect("get").is_none());
+    }
+
+    #[test]
+    fn task_projection_filters_by_state_and_rebuilds() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("task.create", None);
+        let task_event = |event_type: &str, task_id: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: Some("p1".to_string()),
+            subject: Subject {
+                kind: "task".to_string(),
+                id: task_id.to_string(),
+            },
+            payload,
+            trace_id: None,
+            stream_id: None,
+        };
+        let created = |title: &str| {
+            serde_json::to_value(TaskCreatedPayload {
+                title: title.to_string(),
+                state: TaskState::Queued,
+                session_id: None,
+                deadline_at: None,
+                retry: None,
+                timeout_ms: None,
+            })
+            .expect("payload")
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    task_event(EVENT_TASK_CREATED, "t1", created("build")),
+                    task_event(EVENT_TASK_CREATED, "t2", created("test")),
+                    task_event(
+                        EVENT_TASK_STARTED,
+                        "t1",
+                        serde_json::to_value(TaskTransitionedPayload {
+                            from: TaskState::Queued,
+                            to: TaskState::Running,
+                            reason: None,
+                            failure_class: None,
+                            started_at: None,
+                        })
+                        .expect("payload"),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let running = store
+            .list_tasks("w1", None, Some(TaskState::Running))
+            .expect("tasks");
+        assert_eq!(running.len(), 1);
+        assert_eq!(running[0].task_id, "t1");
+        assert_eq!(store.list_tasks("w1", Some("p1"), None).unwrap().len(), 2);
+        assert!(store.list_tasks("w1", Some("p2"), None).unwrap().is_empty());
+
+        store.rebuild_projections().expect("rebuild");
+        let task = store.get_task("w1", "t1").expect("get").expect("task");
+        assert_eq!(task.state, TaskState::Running);
+        assert_eq!(task.attempt, 1);
+        assert_eq!(task.seq_global, 3);
+    }
+
+    #[test]
+    fn pipeline_templates_resolve_latest_version_and_binding() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("pipeline.template.define", None);
+        let event = |event_type: &str, kind: &str, id: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: kind.to_string(),
+                id: id.to_string(),
+            },
+            payload,
+            trace_id: None,
+            stream_id: None,
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    event(
+                        EVENT_PIPELINE_TEMPLATE_DEFINED,
+                        "pipeline_template",
+                        "tpl",
+                        serde_json::json!({"name": "delivery", "version": 1, "stages": [{"name": "plan"}]}),
+                    ),
+                    event(
+                        EVENT_PIPELINE_TEMPLATE_DEFINED,
+                        "pipeline_template",
+                        "tpl",
+                        serde_json::json!({"name": "delivery", "version": 2, "stages": [{"name": "plan"}, {"name": "ship"}]}),
+                    ),
+                    event(
+                        EVENT_PIPELINE_BOUND,
+                        "project",
+                        "p1",
+                        serde_json::json!({"template_id": "tpl", "template_version": 2}),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        assert_eq!(store.list_pipeline_templates("w1").unwrap().len(), 2);
+        let latest = store
+            .get_pipeline_template("w1", "tpl", None)
+            .unwrap()
+            .expect("latest");
+        assert_eq!(latest.version, 2);
+        assert!(latest.has_stage("ship"));
+        let first = store
+            .get_pipeline_template("w1", "tpl", Some(1))
+            .unwrap()
+            .expect("v1");
+        assert!(!first.has_stage("ship"));
+
+        store.rebuild_projections().expect("rebuild");
+        let binding = store
+            .get_pipeline_binding("w1", "p1")
+            .unwrap()
+            .expect("binding");
+        assert_eq!(binding.template_version, 2);
+        assert!(store.get_pipeline_binding("w1", "p2").unwrap().is_none());
+    }
+
+    #[test]
+    fn gate_decisions_persist_in_order_and_rebuild() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("gate.define", None);
+        let event = |event_type: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "gate".to_string(),
+                id: "g1".to_string(),
+            },
+            payload,
+            trace_id: None,
+            stream_id: None,
+        };
+        let scope = serde_json::json!({
+            "command_type": "stage.transition",
+            "subject": {"kind": "task", "id": "t1"}
+        });
+        store
+            .append(
+                &meta,
+                vec![
+                    event(
+                        EVENT_GATE_DEFINED,
+                        serde_json::json!({"scope": scope, "kind": "manual_approval", "required_approvals": 2}),
+                    ),
+                    event(
+                        EVENT_GATE_APPROVED,
+                        serde_json::json!({
+                            "approver": {"kind": "human", "id": "alice", "label": "Alice"},
+                            "scope": scope,
+                            "evidence": ["blake3:abc"],
+                            "status": "open"
+                        }),
+                    ),
+                    event(
+                        EVENT_GATE_REJECTED,
+                        serde_json::json!({
+                            "approver": {"kind": "human", "id": "bob"},
+                            "scope": scope,
+                            "reason": "missing changelog",
+                            "status": "rejected"
+                        }),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        store.rebuild_projections().expect("rebuild");
+        let gates = store.list_gates("w1", Some("stage.transition")).unwrap();
+        assert_eq!(gates.len(), 1);
+        let gate = &gates[0];
+        assert_eq!(gate.status, GateStatus::Rejected);
+        assert_eq!(gate.required_approvals, 2);
+        assert_eq!(gate.decisions.len(), 2);
+        assert_eq!(gate.decisions[0].approver.label.as_deref(), Some("Alice"));
+        assert_eq!(gate.decisions[0].evidence, vec!["blake3:abc".to_string()]);
+        assert!(!gate.decisions[1].approved);
+        assert!(store
+            .list_gates("w1", Some("task.start"))
+            .unwrap()
+            .is_empty());
+        assert_eq!(store.get_gate("w1", "g1").unwrap(), Some(gate.clone()));
+    }
+
+    #[test]
+    fn worktree_sessions_and_lock_persist_and_rebuild() {
+        let (_dir, mut store) = temp_store();
+        let meta = command_meta("worktree.register", None);
+        let event = |event_type: &str, payload: Value| NewEvent {
+            event_type: event_type.to_string(),
+            schema_version: 1,
+            actor: Actor::system(),
+            workspace_id: "w1".to_string(),
+            project_id: None,
+            subject: Subject {
+                kind: "worktree".to_string(),
+                id: "wt1".to_string(),
+            },
+            payload,
+            trace_id: None,
+            stream_id: None,
+        };
+        store
+            .append(
+                &meta,
+                vec![
+                    event(
+                        EVENT_WORKTREE_REGISTERED,
+                        serde_json::json!({"path": "/repo/wt1", "branch": "feature"}),
+                    ),
+                    event(
+                        EVENT_WORKTREE_ATTACHED,
+                        serde_json::json!({"session_id": "s1"}),
+                    ),
+                    event(
+                        EVENT_WORKTREE_ATTACHED,
+                        serde_json::json!({"session_id": "s2"}),
+                    ),
+                    event(
+                        EVENT_WORKTREE_LOCK_ACQUIRED,
+                        serde_json::json!({"session_id": "s2"}),
+                    ),
+                    event(
+                        EVENT_WORKTREE_DETACHED,
+                        serde_json::json!({"session_id": "s1"}),
+                    ),
+                ],
+            )
+            .expect("append");
+
+        let worktree = store.get_worktree("w1", "wt1").unwrap().expect("worktree");
+        assert_eq!(worktree.sessions, vec!["s2".to_string()]);
+        assert_eq!(worktree.lock_holder(), Some("s2"));
+        assert_eq!(worktree.branch.as_deref(), Some("feature"));
+
+        store.rebuild_projections().expect("rebuild");
+        assert_eq!(store.list_worktrees("w1").unwrap(), vec![worktree.clone()]);
+
+        // A second holder is refused inside the append transaction and nothing is recorded.
+        let stolen = store.append(
+            &command_meta("worktree.lock.acquire", None),
+            vec![event(
+                EVENT_WORKTREE_LOCK_ACQUIRED,
+                serde_json::json!({"session_id": "s3"}),
+            )],
+        );
+        assert!(stolen.is_err());
+        assert_eq!(store.list_worktrees("w1").unwrap(), vec![worktree]);
+    }
+
+    #[test]
+    fn reopen_applies_each_migration_once() {
+        let dir = TempDir::new().expect("tempdir");
+        let db_path = dir.path().join("mpd.sqlite");
+        drop(SqliteStore::open(&db_path).expect("first open"));
+        let store = SqliteStore::open(&db_path).expect("second open");
+        let versions: i64 = store
+            .conn
+            .query_row("SELECT COUNT(*) FROM schema_migrations", [], |row| {
+                row.get(0)
+            })
+            .expect("count");
+        assert_eq!(versions, MIGRATIONS.len() as i64);
+    }
 }
@@ -1,4 +1,10 @@
-use mp_kernel::{Actor, ProjectListEntry, Subject, WorkspaceListEntry};
+use mp_kernel::{
+    Actor, ArtifactEntry, BlueprintEntry, BoardNode, BoardSnapshot, CapsuleEntry, CapsuleKind,
+    GateEntry, LeaseEntry, LeaseStatus, PipelineBindingEntry, PipelineTemplateEntry,
+    ProjectListEntry, SecretEntry, SessionListEntry, SkillEntry, SkillTrust, Subject,
+    TaskListEntry, TaskState, ToolCallEntry, ToolCallStatus, VaultKdf, WorkspaceListEntry,
+    WorktreeEntry,
+};
 use mp_protocol::EventEnvelope;
 use serde_json::Value;
 use thiserror::Error;
@@ -57,7 +63,233 @@ pub trait EventStore {
     fn head_seq(&self, workspace_id: &str) -> Result<i64, StoreError>;
 }
 
+/// Result of storing content in an [`ArtifactStore`].
+#[derive(Debug, Clone)]
+pub struct StoredArtifact {
+    pub entry: ArtifactEntry,
+    /// Identical content was already stored; no blob was written.
+    pub deduplicated: bool,
+}
+
+/// Content-addressed blob storage with an index row per hash.
+pub trait ArtifactStore {
+    /// Hashes `content` while writing it; identical content is stored once.
+    fn put_artifact(
+        &self,
+        content: &mut dyn std::io::Read,
+        mime_type: Option<&str>,
+        metadata: &Value,
+    ) -> Result<StoredArtifact, StoreError>;
+    /// A fresh path in the store's staging area, for writers that stream content themselves.
+    fn artifact_staging_path(&self) -> Result<std::path::PathBuf, StoreError>;
+    /// Moves a staged blob into place under `hash`, which the caller has already verified.
+    /// The staged file is consumed either way.
+    fn commit_artifact(
+        &self,
+        staged_path: &std::path::Path,
+        hash: &str,
+        mime_type: Option<&str>,
+        metadata: &Value,
+    ) -> Result<StoredArtifact, StoreError>;
+    fn get_artifact(&self, hash: &str) -> Result<Option<ArtifactEntry>, StoreError>;
+    /// On-disk location of a stored blob, for streaming reads.
+    fn artifact_path(&self, hash: &str) -> Result<Option<std::path::PathBuf>, StoreError>;
+}
+
+/// A workspace vault as stored: the key-derivation salt and parameters, plus a value sealed
+/// by the vault key so an unlock can verify the password. The key itself is never stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultRecord {
+    pub workspace_id: String,
+    pub kdf: VaultKdf,
+    pub salt: Vec<u8>,
+    pub check: Vec<u8>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// One version of a secret: its value sealed by a data key, and that data key wrapped by the
+/// vault key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SecretCiphertext {
+    pub workspace_id: String,
+    pub secret_id: String,
+    pub version: u32,
+    pub wrapped_key: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// Encrypted secret storage. It sits outside the event log, which only ever carries redacted
+/// metadata, and is not rebuilt from it.
+pub trait SecretStore {
+    fn get_vault(&self, workspace_id: &str) -> Result<Option<VaultRecord>, StoreError>;
+    fn get_secret_version(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+        version: u32,
+    ) -> Result<Option<SecretCiphertext>, StoreError>;
+    fn list_secret_versions(&self, workspace_id: &str)
+        -> Result<Vec<SecretCiphertext>, StoreError>;
+    /// Appends `events` like [`EventStore::append`] and stores `write` in the same transaction,
+    /// so vault state never exists without the events that record it, nor the reverse. An
+    /// idempotent replay stores nothing.
+    fn append_with_vault(
+        &mut self,
+        meta: &CommandMeta,
+        events: Vec<NewEvent>,
+        write: VaultWrite<'_>,
+    ) -> Result<AppendResult, StoreError>;
+}
+
+/// Seals a secret value for `(workspace_id, secret_id, version)`.
+pub type SealSecret<'a> = dyn Fn(&str, &str, u32) -> Result<SecretCiphertext, StoreError> + 'a;
+
+/// Vault state stored alongside the events of the command that changes it.
+pub enum VaultWrite<'a> {
+    /// A new vault; fails with [`StoreError::Conflict`] when the workspace already has one.
+    Create(&'a VaultRecord),
+    /// Replaces the vault record and the wrapped key of every listed version, so a re-key
+    /// never leaves data keys under two different vault keys.
+    Rekey(&'a VaultRecord, &'a [SecretCiphertext]),
+    /// The version recorded by the batch's `secret.created` or `secret.rotated` event. The
+    /// version is derived inside the transaction (1 for a new secret, one past the projected
+    /// version for a rotation), written into the event's `version`, and sealed for.
+    SealVersion(&'a SealSecret<'a>),
+}
+
 pub trait ProjectionReader {
-    fn list_workspaces(&self) -> Result<Vec<WorkspaceListEntry>, StoreError>;
-    fn list_projects(&self, workspace_id: &str) -> Result<Vec<ProjectListEntry>, StoreError>;
+    /// Archived workspaces are only listed when `include_archived` is set.
+    fn list_workspaces(
+        &self,
+        include_archived: bool,
+    ) -> Result<Vec<WorkspaceListEntry>, StoreError>;
+    /// Archived projects are only listed when `include_archived` is set.
+    fn list_projects(
+        &self,
+        workspace_id: &str,
+        include_archived: bool,
+    ) -> Result<Vec<ProjectListEntry>, StoreError>;
+    /// Sessions in lineage order (parents before children), optionally limited to one tree.
+    fn list_sessions(
+        &self,
+        workspace_id: &str,
+        root_session_id: Option<&str>,
+    ) -> Result<Vec<SessionListEntry>, StoreError>;
+    fn get_session(
+        &self,
+        workspace_id: &str,
+        session_id: &str,
+    ) -> Result<Option<SessionListEntry>, StoreError>;
+    fn list_tasks(
+        &self,
+        workspace_id: &str,
+        project_id: Option<&str>,
+        state: Option<TaskState>,
+    ) -> Result<Vec<TaskListEntry>, StoreError>;
+    fn get_task(
+        &self,
+        workspace_id: &str,
+        task_id: &str,
+    ) -> Result<Option<TaskListEntry>, StoreError>;
+    /// Every version of every template, ordered by name then version.
+    fn list_pipeline_templates(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Vec<PipelineTemplateEntry>, StoreError>;
+    /// A specific template version, or the latest one when `version` is `None`.
+    fn get_pipeline_template(
+        &self,
+        workspace_id: &str,
+        template_id: &str,
+        version: Option<u32>,
+    ) -> Result<Option<PipelineTemplateEntry>, StoreError>;
+    fn get_pipeline_binding(
+        &self,
+        workspace_id: &str,
+        project_id: &str,
+    ) -> Result<Option<PipelineBindingEntry>, StoreError>;
+    /// Gates with their decisions, optionally limited to those guarding `command_type`.
+    fn list_gates(
+        &self,
+        workspace_id: &str,
+        command_type: Option<&str>,
+    ) -> Result<Vec<GateEntry>, StoreError>;
+    fn get_gate(&self, workspace_id: &str, gate_id: &str) -> Result<Option<GateEntry>, StoreError>;
+    /// Registered worktrees with their attached sessions and lock holder.
+    fn list_worktrees(&self, workspace_id: &str) -> Result<Vec<WorktreeEntry>, StoreError>;
+    fn get_worktree(
+        &self,
+        workspace_id: &str,
+        worktree_id: &str,
+    ) -> Result<Option<WorktreeEntry>, StoreError>;
+    /// Every node and edge of the workspace board; empty for an unknown workspace.
+    fn get_board(&self, workspace_id: &str) -> Result<BoardSnapshot, StoreError>;
+    fn get_board_node(
+        &self,
+        workspace_id: &str,
+        node_id: &str,
+    ) -> Result<Option<BoardNode>, StoreError>;
+    /// Latest capsule per subject and kind, or every version when `all_versions` is set.
+    fn list_capsules(
+        &self,
+        workspace_id: &str,
+        subject: Option<&Subject>,
+        kind: Option<CapsuleKind>,
+        all_versions: bool,
+    ) -> Result<Vec<CapsuleEntry>, StoreError>;
+    /// Latest version per blueprint id, or every version when `all_versions` is set.
+    fn list_blueprints(
+        &self,
+        workspace_id: &str,
+        all_versions: bool,
+    ) -> Result<Vec<BlueprintEntry>, StoreError>;
+    /// `version` of `None` resolves the latest version.
+    fn get_blueprint(
+        &self,
+        workspace_id: &str,
+        blueprint_id: &str,
+        version: Option<u32>,
+    ) -> Result<Option<BlueprintEntry>, StoreError>;
+    /// Tool calls in request order, optionally narrowed to one status or session.
+    fn list_tool_calls(
+        &self,
+        workspace_id: &str,
+        status: Option<ToolCallStatus>,
+        session_id: Option<&str>,
+    ) -> Result<Vec<ToolCallEntry>, StoreError>;
+    fn get_tool_call(
+        &self,
+        workspace_id: &str,
+        request_id: &str,
+    ) -> Result<Option<ToolCallEntry>, StoreError>;
+    /// Registered skills by name, optionally narrowed to one trust tier.
+    fn list_skills(
+        &self,
+        workspace_id: &str,
+        trust: Option<SkillTrust>,
+    ) -> Result<Vec<SkillEntry>, StoreError>;
+    fn get_skill(
+        &self,
+        workspace_id: &str,
+        skill_id: &str,
+    ) -> Result<Option<SkillEntry>, StoreError>;
+    /// Leases ordered by expiry, optionally narrowed to one status.
+    fn list_leases(
+        &self,
+        workspace_id: &str,
+        status: Option<LeaseStatus>,
+    ) -> Result<Vec<LeaseEntry>, StoreError>;
+    fn get_lease(
+        &self,
+        workspace_id: &str,
+        lease_id: &str,
+    ) -> Result<Option<LeaseEntry>, StoreError>;
+    /// Secret metadata ordered by name, then scope from global to session.
+    fn list_secrets(&self, workspace_id: &str) -> Result<Vec<SecretEntry>, StoreError>;
+    fn get_secret(
+        &self,
+        workspace_id: &str,
+        secret_id: &str,
+    ) -> Result<Option<SecretEntry>, StoreError>;
 }
@@ -0,0 +1,234 @@
+//! Envelope encryption for workspace secrets.
+//!
+//! Each secret version is sealed with its own random data key (DEK) under AES-256-GCM. The DEK
+//! is in turn sealed by the vault's key-encryption key (KEK), which is derived from the vault
+//! password with Argon2id and exists only in memory while the vault is unlocked. Both seals
+//! bind the secret's location as associated data, so a ciphertext copied to another secret or
+//! version fails to open.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use mp_kernel::VaultKdf;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+
+pub const SALT_LEN: usize = 16;
+const KEY_LEN: usize = 32;
+
+/// Sealed with a fresh KEK so unlocking can tell a wrong password from a right one.
+const CHECK_PLAINTEXT: &[u8] = b"mp-vault-check-v1";
+const CHECK_AAD: &[u8] = b"vault-check";
+
+/// Parameters new vaults use (RFC 9106's second recommended option). Vaults created with
+/// weaker parameters are re-keyed with these on their next unlock.
+pub const DEFAULT_KDF: VaultKdf = VaultKdf {
+    m_cost_kib: 64 * 1024,
+    t_cost: 3,
+    p_cost: 1,
+};
+
+#[derive(Debug, Error)]
+pub enum VaultError {
+    #[error("invalid key derivation parameters: {0}")]
+    Kdf(String),
+    #[error("vault password is incorrect")]
+    WrongPassword,
+    #[error("sealed data failed to decrypt")]
+    Corrupt,
+    #[error("system randomness unavailable")]
+    Random,
+}
+
+/// Vault metadata that is safe to store: the salt, KDF parameters and the sealed check value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultHeader {
+    pub kdf: VaultKdf,
+    pub salt: Vec<u8>,
+    pub check: Vec<u8>,
+}
+
+/// A secret version as stored: its data key sealed by the KEK, and its value sealed by the
+/// data key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealedSecret {
+    pub wrapped_key: Vec<u8>,
+    pub ciphertext: Vec<u8>,
+}
+
+/// The unlocked key-encryption key.
+pub struct VaultKey {
+    key: LessSafeKey,
+}
+
+impl std::fmt::Debug for VaultKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VaultKey(..)")
+    }
+}
+
+impl VaultKey {
+    /// Derives a KEK for a new vault (or a re-key) and the header that unlocks it again.
+    pub fn create(password: &str, kdf: VaultKdf) -> Result<(Self, VaultHeader), VaultError> {
+        let mut salt = vec![0u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| VaultError::Random)?;
+        let key = derive(password, &salt, kdf)?;
+        let check = seal(&key.key, CHECK_AAD, CHECK_PLAINTEXT)?;
+        Ok((key, VaultHeader { kdf, salt, check }))
+    }
+
+    pub fn unlock(password: &str, header: &VaultHeader) -> Result<Self, VaultError> {
+        let key = derive(password, &header.salt, header.kdf)?;
+        match open(&key.key, CHECK_AAD, &header.check) {
+            Ok(plaintext) if plaintext == CHECK_PLAINTEXT => Ok(key),
+            _ => Err(VaultError::WrongPassword),
+        }
+    }
+
+    /// Seals `value` under a fresh data key. `location` names where the result will be stored.
+    pub fn seal_secret(&self, location: &str, value: &[u8]) -> Result<SealedSecret, VaultError> {
+        let mut dek = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut dek)
+            .map_err(|_| VaultError::Random)?;
+        let data_key = aead_key(&dek)?;
+        Ok(SealedSecret {
+            wrapped_key: seal(&self.key, location.as_bytes(), &dek)?,
+            ciphertext: seal(&data_key, location.as_bytes(), value)?,
+        })
+    }
+
+    pub fn open_secret(
+        &self,
+        location: &str,
+        sealed: &SealedSecret,
+    ) -> Result<Vec<u8>, VaultError> {
+        let dek = open(&self.key, location.as_bytes(), &sealed.wrapped_key)?;
+        let data_key = aead_key(&dek)?;
+        open(&data_key, location.as_bytes(), &sealed.ciphertext)
+    }
+
+    /// Re-seals a data key under `other`; the secret's ciphertext is unchanged.
+    pub fn rewrap(
+        &self,
+        other: &VaultKey,
+        location: &str,
+        wrapped_key: &[u8],
+    ) -> Result<Vec<u8>, VaultError> {
+        let dek = open(&self.key, location.as_bytes(), wrapped_key)?;
+        seal(&other.key, location.as_bytes(), &dek)
+    }
+}
+
+/// Whether `kdf` is weaker than [`DEFAULT_KDF`] in any dimension.
+pub fn needs_upgrade(kdf: &VaultKdf) -> bool {
+    kdf.m_cost_kib < DEFAULT_KDF.m_cost_kib || kdf.t_cost < DEFAULT_KDF.t_cost
+}
+
+fn derive(password: &str, salt: &[u8], kdf: VaultKdf) -> Result<VaultKey, VaultError> {
+    let params = Params::new(kdf.m_cost_kib, kdf.t_cost, kdf.p_cost, Some(KEY_LEN))
+        .map_err(|err| VaultError::Kdf(err.to_string()))?;
+    let mut kek = [0u8; KEY_LEN];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, &mut kek)
+        .map_err(|err| VaultError::Kdf(err.to_string()))?;
+    Ok(VaultKey {
+        key: aead_key(&kek)?,
+    })
+}
+
+fn aead_key(bytes: &[u8]) -> Result<LessSafeKey, VaultError> {
+    UnboundKey::new(&AES_256_GCM, bytes)
+        .map(LessSafeKey::new)
+        .map_err(|_| VaultError::Corrupt)
+}
+
+/// `nonce || ciphertext || tag`, with a random nonce per seal.
+fn seal(key: &LessSafeKey, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, VaultError> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| VaultError::Random)?;
+    let mut sealed = plaintext.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(aad),
+        &mut sealed,
+    )
+    .map_err(|_| VaultError::Corrupt)?;
+    let mut out = nonce.to_vec();
+    out.extend_from_slice(&sealed);
+    Ok(out)
+}
+
+fn open(key: &LessSafeKey, aad: &[u8], sealed: &[u8]) -> Result<Vec<u8>, VaultError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(VaultError::Corrupt);
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| VaultError::Corrupt)?;
+    let mut buffer = ciphertext.to_vec();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(aad), &mut buffer)
+        .map_err(|_| VaultError::Corrupt)?;
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps tests fast; real vaults use `DEFAULT_KDF`.
+    const TEST_KDF: VaultKdf = VaultKdf {
+        m_cost_kib: 64,
+        t_cost: 1,
+        p_cost: 1,
+    };
+
+    #[test]
+    fn secrets_open_only_with_the_right_password_and_location() {
+        let (key, header) = VaultKey::create("correct horse", TEST_KDF).expect("create");
+        assert_eq!(header.salt.len(), SALT_LEN);
+        let sealed = key.seal_secret("w1/s1/1", b"sk-live-123").expect("seal");
+        assert!(!sealed
+            .ciphertext
+            .windows(b"sk-live-123".len())
+            .any(|window| window == b"sk-live-123"));
+
+        let unlocked = VaultKey::unlock("correct horse", &header).expect("unlock");
+        assert_eq!(
+            unlocked.open_secret("w1/s1/1", &sealed).expect("open"),
+            b"sk-live-123"
+        );
+        assert!(matches!(
+            unlocked.open_secret("w1/s1/2", &sealed),
+            Err(VaultError::Corrupt)
+        ));
+        assert!(matches!(
+            VaultKey::unlock("wrong", &header),
+            Err(VaultError::WrongPassword)
+        ));
+    }
+
+    #[test]
+    fn rewrapping_moves_data_keys_to_a_new_kek() {
+        let (old, _) = VaultKey::create("pw", TEST_KDF).expect("old");
+        let sealed = old.seal_secret("w1/s1/1", b"value").expect("seal");
+        let (new, header) = VaultKey::create("pw", DEFAULT_KDF).expect("new");
+        assert!(needs_upgrade(&TEST_KDF));
+        assert!(!needs_upgrade(&header.kdf));
+
+        let rewrapped = SealedSecret {
+            wrapped_key: old
+                .rewrap(&new, "w1/s1/1", &sealed.wrapped_key)
+                .expect("rewrap"),
+            ciphertext: sealed.ciphertext.clone(),
+        };
+        assert_eq!(
+            new.open_secret("w1/s1/1", &rewrapped).expect("open"),
+            b"value"
+        );
+        assert!(old.open_secret("w1/s1/1", &rewrapped).is_err());
+    }
+}